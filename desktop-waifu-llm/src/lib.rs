@@ -0,0 +1,355 @@
+//! Chat completions for the assistant, shared by the overlay and the Tauri app
+//!
+//! OpenAI, OpenRouter (which speaks OpenAI's API), Anthropic, Gemini and a local
+//! OpenAI-compatible server (Ollama, llama.cpp's) are supported. [`chat`] sends a request,
+//! retrying connection failures, rate limits and server errors, and passes the streamed
+//! reply on a chunk at a time.
+//!
+//! - request.rs: each provider's request body
+//! - reply.rs: the server-sent event stream, and the text and tool calls it carries
+//!
+//! Where API keys come from, cancelling requests and which local servers to try are up to
+//! the app. Logging goes through `tracing`.
+
+mod reply;
+mod request;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::future::Future;
+use std::time::Duration;
+
+use reply::{ReplyBuilder, SseParser};
+
+/// Attempts per request when the provider is rate limiting or failing
+const MAX_ATTEMPTS: u32 = 3;
+/// Wait before the first retry; doubled for each one after
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+/// Longest `Retry-After` honored
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(20);
+/// Where Ollama and llama.cpp's server listen by default
+const LOCAL_SERVERS: [&str; 2] = ["http://127.0.0.1:11434/v1", "http://127.0.0.1:8080/v1"];
+/// How long a local server gets to answer the model list
+const LOCAL_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    OpenAI,
+    OpenRouter,
+    Anthropic,
+    Gemini,
+    /// Ollama, llama.cpp's server or another OpenAI-compatible server on this machine
+    Local,
+}
+
+impl Provider {
+    /// Name of the provider's API key in the keyring
+    pub fn secret_name(self) -> &'static str {
+        match self {
+            Provider::OpenAI => "openai-api-key",
+            Provider::OpenRouter => "openrouter-api-key",
+            Provider::Anthropic => "anthropic-api-key",
+            Provider::Gemini => "gemini-api-key",
+            Provider::Local => "local-api-key",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Provider::OpenAI => "OpenAI",
+            Provider::OpenRouter => "OpenRouter",
+            Provider::Anthropic => "Anthropic",
+            Provider::Gemini => "Gemini",
+            Provider::Local => "Local model server",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+    /// The result of one of the assistant's tool calls
+    Tool,
+}
+
+/// Message content as the frontend sends it (`LLMMessage` in types/index.ts)
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Content {
+    Text(String),
+    Parts(Vec<Part>),
+}
+
+impl Default for Content {
+    fn default() -> Self {
+        Content::Text(String::new())
+    }
+}
+
+impl Content {
+    /// The text of a message, without its images
+    pub fn text(&self) -> String {
+        match self {
+            Content::Text(text) => text.clone(),
+            Content::Parts(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    Part::Text { text } => Some(text.as_str()),
+                    Part::Image { .. } => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Part {
+    Text {
+        text: String,
+    },
+    Image {
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Message {
+    pub role: Role,
+    #[serde(default)]
+    pub content: Content,
+    /// Tools the assistant called (assistant messages)
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCall>,
+    /// The call a tool message answers, and the tool's name
+    pub tool_call_id: Option<String>,
+    pub name: Option<String>,
+}
+
+/// A tool offered to the model
+#[derive(Debug, Deserialize)]
+pub struct ToolSpec {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default = "empty_schema")]
+    pub parameters: Value,
+}
+
+fn empty_schema() -> Value {
+    json!({ "type": "object", "properties": {} })
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    /// The model's arguments; a string if they weren't valid JSON
+    pub arguments: Value,
+}
+
+/// A finished reply
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reply {
+    pub text: String,
+    pub tool_calls: Vec<ToolCall>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatRequest {
+    pub provider: Provider,
+    pub model: String,
+    pub messages: Vec<Message>,
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: u32,
+    #[serde(default = "default_temperature")]
+    pub temperature: f32,
+    #[serde(default)]
+    pub tools: Vec<ToolSpec>,
+}
+
+fn default_max_tokens() -> u32 {
+    500
+}
+
+fn default_temperature() -> f32 {
+    0.8
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LlmError {
+    #[error("no API key set for {0}")]
+    NoKey(&'static str),
+    #[error("{0} request failed: {1}")]
+    Request(&'static str, reqwest::Error),
+    #[error("{0} returned {1}: {2}")]
+    Api(&'static str, u16, String),
+    #[error("{0}: {1}")]
+    Stream(&'static str, String),
+    #[error("no local model server found; start Ollama or llama-server")]
+    NoLocalServer,
+    #[error("the local model server has no models; pull one first (e.g. `ollama pull llama3.2`)")]
+    NoLocalModels,
+}
+
+/// A local model server that answered, and the models it offers
+#[derive(Debug, Serialize)]
+pub struct LocalServer {
+    pub url: String,
+    pub models: Vec<String>,
+}
+
+/// Base URLs to look for a local model server at: `configured` if set, else where Ollama
+/// and llama.cpp's server listen by default
+pub fn local_servers(configured: Option<&str>) -> Vec<String> {
+    match configured {
+        Some(url) => vec![url.trim_end_matches('/').to_string()],
+        None => LOCAL_SERVERS.iter().map(|url| url.to_string()).collect(),
+    }
+}
+
+/// The first of `servers` that answers, and the models it lists
+pub async fn local_models(servers: &[String]) -> Result<LocalServer, LlmError> {
+    let client = reqwest::Client::new();
+    for url in servers {
+        let response = client
+            .get(format!("{}/models", url))
+            .timeout(LOCAL_PROBE_TIMEOUT)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        let Ok(response) = response else { continue };
+        let Ok(body) = response.json::<Value>().await else { continue };
+        let models = body["data"]
+            .as_array()
+            .map(|models| models.iter().filter_map(|m| m["id"].as_str().map(str::to_string)).collect())
+            .unwrap_or_default();
+        return Ok(LocalServer {
+            url: url.clone(),
+            models,
+        });
+    }
+    Err(LlmError::NoLocalServer)
+}
+
+/// Where a request goes
+struct Endpoint {
+    key: String,
+    /// Base URL of a local server
+    local_url: Option<String>,
+    model: String,
+}
+
+async fn endpoint(
+    request: &ChatRequest,
+    key: impl Future<Output = Option<String>>,
+    local_servers: &[String],
+) -> Result<Endpoint, LlmError> {
+    let provider = request.provider;
+    if provider != Provider::Local {
+        return Ok(Endpoint {
+            key: key.await.ok_or(LlmError::NoKey(provider.name()))?,
+            local_url: None,
+            model: request.model.clone(),
+        });
+    }
+    let server = local_models(local_servers).await?;
+    // No model picked yet (none were listed when the provider was chosen): take the first
+    let model = if request.model.is_empty() {
+        server.models.into_iter().next().ok_or(LlmError::NoLocalModels)?
+    } else {
+        request.model.clone()
+    };
+    Ok(Endpoint {
+        key: String::new(),
+        local_url: Some(server.url),
+        model,
+    })
+}
+
+/// Run a chat completion, passing the reply's text to `on_token` as it streams in. `key`
+/// looks up the provider's API key and is only awaited for providers that need one; the
+/// local provider uses the first of `local_servers` that answers.
+pub async fn chat(
+    request: &ChatRequest,
+    key: impl Future<Output = Option<String>>,
+    local_servers: &[String],
+    mut on_token: impl FnMut(&str),
+) -> Result<Reply, LlmError> {
+    let provider = request.provider;
+    let endpoint = endpoint(request, key, local_servers).await?;
+    let mut response = send_with_retry(request, &endpoint).await?;
+
+    let mut reply = ReplyBuilder::default();
+    let mut events = SseParser::default();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| LlmError::Request(provider.name(), e))?
+    {
+        for data in events.push(&chunk) {
+            if data == "[DONE]" {
+                return Ok(reply.finish());
+            }
+            let Ok(event) = serde_json::from_str::<Value>(&data) else {
+                continue;
+            };
+            if let Some(token) = reply.read_event(provider, &event)? {
+                on_token(&token);
+                reply.text.push_str(&token);
+            }
+        }
+    }
+    Ok(reply.finish())
+}
+
+/// Send the request, retrying connection failures, rate limits and server errors.
+/// Retries stop once a response starts streaming: its text has already been passed on.
+async fn send_with_retry(request: &ChatRequest, endpoint: &Endpoint) -> Result<reqwest::Response, LlmError> {
+    let provider = request.provider;
+    let client = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(15))
+        .build()
+        .map_err(|e| LlmError::Request(provider.name(), e))?;
+
+    let mut delay = RETRY_DELAY;
+    for attempt in 1..=MAX_ATTEMPTS {
+        let last = attempt == MAX_ATTEMPTS;
+        match request::build(&client, request, endpoint).send().await {
+            Ok(response) if response.status().is_success() => return Ok(response),
+            Ok(response) => {
+                let status = response.status();
+                let retryable = status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error();
+                if !retryable || last {
+                    let body = response.text().await.unwrap_or_default();
+                    return Err(LlmError::Api(provider.name(), status.as_u16(), reply::error_message(&body)));
+                }
+                let wait = response
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok()?.parse().ok())
+                    .map(|secs| Duration::from_secs(secs).min(MAX_RETRY_DELAY))
+                    .unwrap_or(delay);
+                tracing::info!("{} returned {}, retrying in {:?}", provider.name(), status, wait);
+                tokio::time::sleep(wait).await;
+            }
+            Err(e) if !last && (e.is_connect() || e.is_timeout()) => {
+                tracing::info!("{} request failed ({}), retrying in {:?}", provider.name(), e, delay);
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) => return Err(LlmError::Request(provider.name(), e)),
+        }
+        delay *= 2;
+    }
+    unreachable!("the last attempt always returns")
+}
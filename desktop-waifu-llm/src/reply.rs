@@ -0,0 +1,250 @@
+//! Reading the streamed reply: server-sent events, and the text and tool calls they carry
+
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+use crate::{LlmError, Provider, Reply, ToolCall};
+
+/// The reply as it streams in: its text, and tool calls put together from their pieces
+#[derive(Default)]
+pub(crate) struct ReplyBuilder {
+    pub(crate) text: String,
+    /// By the provider's index for the call
+    calls: BTreeMap<u64, PartialCall>,
+}
+
+#[derive(Default)]
+struct PartialCall {
+    id: String,
+    name: String,
+    /// JSON text, streamed in fragments
+    arguments: String,
+    /// Arguments that arrived whole (Gemini)
+    parsed: Option<Value>,
+}
+
+impl ReplyBuilder {
+    /// Record one streamed event, returning the text it carries, if any. Errors reported
+    /// mid-stream end the request.
+    pub(crate) fn read_event(&mut self, provider: Provider, event: &Value) -> Result<Option<String>, LlmError> {
+        if let Some(error) = event.get("error") {
+            let message = error["message"].as_str().unwrap_or("unknown error").to_string();
+            return Err(LlmError::Stream(provider.name(), message));
+        }
+        let text = match provider {
+            Provider::OpenAI | Provider::OpenRouter | Provider::Local => {
+                let delta = &event["choices"][0]["delta"];
+                for call in delta["tool_calls"].as_array().into_iter().flatten() {
+                    let partial = self.calls.entry(call["index"].as_u64().unwrap_or(0)).or_default();
+                    if let Some(id) = call["id"].as_str() {
+                        partial.id = id.to_string();
+                    }
+                    let function = &call["function"];
+                    partial.name.push_str(function["name"].as_str().unwrap_or(""));
+                    partial.arguments.push_str(function["arguments"].as_str().unwrap_or(""));
+                }
+                delta["content"].as_str().map(str::to_string)
+            }
+            Provider::Anthropic => {
+                let index = event["index"].as_u64().unwrap_or(0);
+                match event["type"].as_str() {
+                    Some("content_block_start") if event["content_block"]["type"] == "tool_use" => {
+                        let block = &event["content_block"];
+                        let partial = self.calls.entry(index).or_default();
+                        partial.id = block["id"].as_str().unwrap_or("").to_string();
+                        partial.name = block["name"].as_str().unwrap_or("").to_string();
+                        None
+                    }
+                    Some("content_block_delta") if event["delta"]["type"] == "input_json_delta" => {
+                        let json = event["delta"]["partial_json"].as_str().unwrap_or("");
+                        self.calls.entry(index).or_default().arguments.push_str(json);
+                        None
+                    }
+                    Some("content_block_delta") => event["delta"]["text"].as_str().map(str::to_string),
+                    _ => None,
+                }
+            }
+            Provider::Gemini => {
+                let mut text = String::new();
+                for part in event["candidates"][0]["content"]["parts"].as_array().into_iter().flatten() {
+                    if let Some(call) = part.get("functionCall") {
+                        // Gemini doesn't number its calls; make ids up so results can refer to them
+                        let index = self.calls.len() as u64;
+                        self.calls.insert(
+                            index,
+                            PartialCall {
+                                id: format!("call_{}", index),
+                                name: call["name"].as_str().unwrap_or("").to_string(),
+                                arguments: String::new(),
+                                parsed: Some(call["args"].clone()),
+                            },
+                        );
+                    } else if let Some(t) = part["text"].as_str() {
+                        text.push_str(t);
+                    }
+                }
+                Some(text)
+            }
+        };
+        Ok(text.filter(|t| !t.is_empty()))
+    }
+
+    pub(crate) fn finish(self) -> Reply {
+        let tool_calls = self
+            .calls
+            .into_values()
+            .filter(|call| !call.name.is_empty())
+            .map(|call| {
+                let arguments = call.parsed.unwrap_or_else(|| {
+                    if call.arguments.trim().is_empty() {
+                        json!({})
+                    } else {
+                        serde_json::from_str(&call.arguments).unwrap_or(Value::String(call.arguments))
+                    }
+                });
+                ToolCall {
+                    id: call.id,
+                    name: call.name,
+                    arguments,
+                }
+            })
+            .collect();
+        Reply {
+            text: self.text,
+            tool_calls,
+        }
+    }
+}
+
+/// The message out of an error response body, which all four wrap as `{"error": {"message"}}`
+/// (Gemini as a one-element array)
+pub(crate) fn error_message(body: &str) -> String {
+    let Ok(value) = serde_json::from_str::<Value>(body) else {
+        return body.trim().to_string();
+    };
+    let error = if value.is_array() { &value[0]["error"] } else { &value["error"] };
+    error["message"]
+        .as_str()
+        .map(str::to_string)
+        .unwrap_or_else(|| body.trim().to_string())
+}
+
+/// Splits a server-sent event stream into the data of each event
+#[derive(Default)]
+pub(crate) struct SseParser {
+    line: Vec<u8>,
+    data: String,
+}
+
+impl SseParser {
+    /// Feed the next bytes of the stream; returns the events they complete
+    pub(crate) fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        let mut events = Vec::new();
+        for &byte in bytes {
+            if byte != b'\n' {
+                self.line.push(byte);
+                continue;
+            }
+            let line = String::from_utf8_lossy(&self.line).trim_end_matches('\r').to_string();
+            self.line.clear();
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(std::mem::take(&mut self.data));
+                }
+            } else if let Some(data) = line.strip_prefix("data:") {
+                if !self.data.is_empty() {
+                    self.data.push('\n');
+                }
+                self.data.push_str(data.strip_prefix(' ').unwrap_or(data));
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sse_events_can_span_chunks() {
+        let mut parser = SseParser::default();
+        assert!(parser.push(b"data: {\"a\"").is_empty());
+        assert_eq!(parser.push(b":1}\r\n\r\ndata: [DONE]\n\n"), vec!["{\"a\":1}", "[DONE]"]);
+    }
+
+    #[test]
+    fn sse_multiline_data_is_joined_and_other_fields_skipped() {
+        let mut parser = SseParser::default();
+        let events = parser.push(b"event: message\nid: 3\ndata: one\ndata:two\n\n: comment\n\n");
+        assert_eq!(events, vec!["one\ntwo"]);
+    }
+
+    #[test]
+    fn openai_tool_call_fragments_are_put_together() {
+        let mut reply = ReplyBuilder::default();
+        let events = [
+            json!({ "choices": [{ "delta": { "content": "Let me look." } }] }),
+            json!({ "choices": [{ "delta": { "tool_calls": [{ "index": 0, "id": "call_1", "function": { "name": "read_", "arguments": "{\"pa" } }] } }] }),
+            json!({ "choices": [{ "delta": { "tool_calls": [{ "index": 0, "function": { "name": "file", "arguments": "th\":\"a\"}" } }] } }] }),
+        ];
+        let texts: Vec<_> = events.iter().map(|e| reply.read_event(Provider::OpenAI, e).unwrap()).collect();
+        assert_eq!(texts, vec![Some("Let me look.".to_string()), None, None]);
+        let reply = reply.finish();
+        assert_eq!(reply.tool_calls.len(), 1);
+        assert_eq!(reply.tool_calls[0].id, "call_1");
+        assert_eq!(reply.tool_calls[0].name, "read_file");
+        assert_eq!(reply.tool_calls[0].arguments, json!({ "path": "a" }));
+    }
+
+    #[test]
+    fn anthropic_tool_use_blocks_are_put_together() {
+        let mut reply = ReplyBuilder::default();
+        for event in [
+            json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "text_delta", "text": "Hi" } }),
+            json!({ "type": "content_block_start", "index": 1, "content_block": { "type": "tool_use", "id": "toolu_1", "name": "now" } }),
+            json!({ "type": "content_block_delta", "index": 1, "delta": { "type": "input_json_delta", "partial_json": "" } }),
+        ] {
+            reply.read_event(Provider::Anthropic, &event).unwrap();
+        }
+        let reply = reply.finish();
+        assert_eq!(reply.tool_calls[0].name, "now");
+        // No arguments streamed means an empty object, not a parse failure
+        assert_eq!(reply.tool_calls[0].arguments, json!({}));
+    }
+
+    #[test]
+    fn gemini_calls_get_made_up_ids() {
+        let mut reply = ReplyBuilder::default();
+        let event = json!({ "candidates": [{ "content": { "parts": [
+            { "text": "Sure" },
+            { "functionCall": { "name": "a", "args": { "x": 1 } } },
+            { "functionCall": { "name": "b", "args": {} } },
+        ] } }] });
+        assert_eq!(reply.read_event(Provider::Gemini, &event).unwrap(), Some("Sure".to_string()));
+        let ids: Vec<_> = reply.finish().tool_calls.into_iter().map(|call| call.id).collect();
+        assert_eq!(ids, vec!["call_0", "call_1"]);
+    }
+
+    #[test]
+    fn invalid_arguments_are_kept_as_text() {
+        let mut reply = ReplyBuilder::default();
+        let event = json!({ "choices": [{ "delta": { "tool_calls": [{ "index": 0, "id": "c", "function": { "name": "f", "arguments": "{oops" } }] } }] });
+        reply.read_event(Provider::Local, &event).unwrap();
+        assert_eq!(reply.finish().tool_calls[0].arguments, json!("{oops"));
+    }
+
+    #[test]
+    fn stream_errors_end_the_request() {
+        let event = json!({ "error": { "message": "overloaded" } });
+        let error = ReplyBuilder::default().read_event(Provider::Anthropic, &event).unwrap_err();
+        assert_eq!(error.to_string(), "Anthropic: overloaded");
+    }
+
+    #[test]
+    fn error_messages_are_unwrapped() {
+        assert_eq!(error_message(r#"{"error":{"message":"bad key"}}"#), "bad key");
+        assert_eq!(error_message(r#"[{"error":{"message":"quota"}}]"#), "quota");
+        assert_eq!(error_message(" Bad Gateway \n"), "Bad Gateway");
+    }
+}
@@ -0,0 +1,364 @@
+//! Each provider's request: its URL, headers and the conversation in its format
+
+use serde_json::{json, Value};
+
+use crate::{ChatRequest, Content, Endpoint, Message, Part, Provider, Role, ToolCall};
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+pub(crate) fn build(client: &reqwest::Client, request: &ChatRequest, endpoint: &Endpoint) -> reqwest::RequestBuilder {
+    let key = endpoint.key.as_str();
+    let model = endpoint.model.as_str();
+    match request.provider {
+        Provider::OpenAI | Provider::OpenRouter | Provider::Local => {
+            let url = match &endpoint.local_url {
+                Some(local_url) => format!("{}/chat/completions", local_url),
+                None if request.provider == Provider::OpenRouter => {
+                    "https://openrouter.ai/api/v1/chat/completions".to_string()
+                }
+                None => "https://api.openai.com/v1/chat/completions".to_string(),
+            };
+            let messages: Vec<Value> = request.messages.iter().map(openai_message).collect();
+            let mut body = json!({
+                "model": model,
+                "messages": messages,
+                "max_tokens": request.max_tokens,
+                "temperature": request.temperature,
+                "stream": true,
+            });
+            if !request.tools.is_empty() {
+                let tools: Vec<Value> = request
+                    .tools
+                    .iter()
+                    .map(|tool| json!({
+                        "type": "function",
+                        "function": { "name": tool.name, "description": tool.description, "parameters": tool.parameters },
+                    }))
+                    .collect();
+                body["tools"] = json!(tools);
+            }
+            let mut builder = client.post(url);
+            if !key.is_empty() {
+                builder = builder.bearer_auth(key);
+            }
+            builder
+                // OpenRouter's attribution headers; the others ignore them
+                .header("HTTP-Referer", "https://github.com/yv-was-taken/desktop-waifu")
+                .header("X-Title", "Desktop Waifu")
+                .json(&body)
+        }
+        Provider::Anthropic => {
+            let mut body = json!({
+                "model": model,
+                "system": system_text(&request.messages),
+                "messages": group_tool_results(&request.messages, anthropic_message, "content"),
+                "max_tokens": request.max_tokens,
+                "temperature": request.temperature,
+                "stream": true,
+            });
+            if !request.tools.is_empty() {
+                let tools: Vec<Value> = request
+                    .tools
+                    .iter()
+                    .map(|tool| json!({ "name": tool.name, "description": tool.description, "input_schema": tool.parameters }))
+                    .collect();
+                body["tools"] = json!(tools);
+            }
+            client
+                .post("https://api.anthropic.com/v1/messages")
+                .header("x-api-key", key)
+                .header("anthropic-version", ANTHROPIC_VERSION)
+                .json(&body)
+        }
+        Provider::Gemini => {
+            let mut body = json!({
+                "contents": group_tool_results(&request.messages, gemini_content, "parts"),
+                "generationConfig": {
+                    "maxOutputTokens": request.max_tokens,
+                    "temperature": request.temperature,
+                },
+            });
+            let system = system_text(&request.messages);
+            if !system.is_empty() {
+                body["systemInstruction"] = json!({ "parts": [{ "text": system }] });
+            }
+            if !request.tools.is_empty() {
+                let declarations: Vec<Value> = request
+                    .tools
+                    .iter()
+                    .map(|tool| {
+                        let mut declaration = json!({ "name": tool.name, "description": tool.description });
+                        if let Some(parameters) = gemini_schema(&tool.parameters) {
+                            declaration["parameters"] = parameters;
+                        }
+                        declaration
+                    })
+                    .collect();
+                body["tools"] = json!([{ "functionDeclarations": declarations }]);
+            }
+            client
+                .post(format!(
+                    "https://generativelanguage.googleapis.com/v1beta/models/{}:streamGenerateContent?alt=sse",
+                    model
+                ))
+                .header("x-goog-api-key", key)
+                .json(&body)
+        }
+    }
+}
+
+/// Gemini takes an OpenAPI subset of JSON Schema, rejecting keywords outside it and objects
+/// without properties; keep only what it knows. `None` when nothing is left to describe.
+fn gemini_schema(schema: &Value) -> Option<Value> {
+    const KEEP: [&str; 6] = ["type", "description", "enum", "format", "nullable", "required"];
+    let mut out = serde_json::Map::new();
+    for (key, value) in schema.as_object()? {
+        match key.as_str() {
+            "properties" => {
+                let properties: serde_json::Map<String, Value> = value
+                    .as_object()?
+                    .iter()
+                    .filter_map(|(name, property)| Some((name.clone(), gemini_schema(property)?)))
+                    .collect();
+                if !properties.is_empty() {
+                    out.insert(key.clone(), Value::Object(properties));
+                }
+            }
+            "items" => {
+                out.insert(key.clone(), gemini_schema(value)?);
+            }
+            key if KEEP.contains(&key) => {
+                out.insert(key.to_string(), value.clone());
+            }
+            _ => {}
+        }
+    }
+    if out.get("type") == Some(&json!("object")) && !out.contains_key("properties") {
+        return None;
+    }
+    Some(Value::Object(out))
+}
+
+/// The conversation without system messages, for Anthropic and Gemini, which want the
+/// results of one turn's tool calls together in a single message
+fn group_tool_results(messages: &[Message], convert: fn(&Message) -> Value, parts_key: &str) -> Vec<Value> {
+    let mut out: Vec<Value> = Vec::new();
+    let mut previous_was_tool = false;
+    for message in messages.iter().filter(|m| !matches!(m.role, Role::System)) {
+        let value = convert(message);
+        let is_tool = matches!(message.role, Role::Tool);
+        let previous = out.last_mut().filter(|_| is_tool && previous_was_tool);
+        match (previous.and_then(|last| last[parts_key].as_array_mut()), value[parts_key].as_array()) {
+            (Some(parts), Some(more)) => parts.extend(more.iter().cloned()),
+            _ => out.push(value),
+        }
+        previous_was_tool = is_tool;
+    }
+    out
+}
+
+/// The system messages' text; Anthropic and Gemini take it apart from the conversation
+fn system_text(messages: &[Message]) -> String {
+    messages
+        .iter()
+        .filter(|m| matches!(m.role, Role::System))
+        .map(|m| m.content.text())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn role_name(role: &Role) -> &'static str {
+    match role {
+        Role::System => "system",
+        Role::User => "user",
+        Role::Assistant => "assistant",
+        Role::Tool => "tool",
+    }
+}
+
+/// Tool arguments as an object; providers that take them as JSON reject anything else
+fn arguments_object(call: &ToolCall) -> Value {
+    if call.arguments.is_object() {
+        call.arguments.clone()
+    } else {
+        json!({})
+    }
+}
+
+fn openai_message(message: &Message) -> Value {
+    if matches!(message.role, Role::Tool) {
+        return json!({
+            "role": "tool",
+            "tool_call_id": message.tool_call_id,
+            "content": message.content.text(),
+        });
+    }
+    let content = match &message.content {
+        Content::Text(text) => json!(text),
+        Content::Parts(parts) => parts
+            .iter()
+            .map(|part| match part {
+                Part::Text { text } => json!({ "type": "text", "text": text }),
+                Part::Image { data, mime_type } => json!({
+                    "type": "image_url",
+                    "image_url": { "url": format!("data:{};base64,{}", mime_type, data) },
+                }),
+            })
+            .collect(),
+    };
+    let mut value = json!({ "role": role_name(&message.role), "content": content });
+    if !message.tool_calls.is_empty() {
+        let calls: Vec<Value> = message
+            .tool_calls
+            .iter()
+            .map(|call| json!({
+                "id": call.id,
+                "type": "function",
+                "function": { "name": call.name, "arguments": arguments_object(call).to_string() },
+            }))
+            .collect();
+        value["tool_calls"] = json!(calls);
+    }
+    value
+}
+
+fn anthropic_message(message: &Message) -> Value {
+    if matches!(message.role, Role::Tool) {
+        return json!({
+            "role": "user",
+            "content": [{
+                "type": "tool_result",
+                "tool_use_id": message.tool_call_id,
+                "content": message.content.text(),
+            }],
+        });
+    }
+    let mut content: Vec<Value> = match &message.content {
+        Content::Text(text) if text.is_empty() => Vec::new(),
+        Content::Text(text) => vec![json!({ "type": "text", "text": text })],
+        Content::Parts(parts) => parts
+            .iter()
+            .map(|part| match part {
+                Part::Text { text } => json!({ "type": "text", "text": text }),
+                Part::Image { data, mime_type } => json!({
+                    "type": "image",
+                    "source": { "type": "base64", "media_type": mime_type, "data": data },
+                }),
+            })
+            .collect(),
+    };
+    content.extend(message.tool_calls.iter().map(|call| json!({
+        "type": "tool_use",
+        "id": call.id,
+        "name": call.name,
+        "input": arguments_object(call),
+    })));
+    json!({ "role": role_name(&message.role), "content": content })
+}
+
+fn gemini_content(message: &Message) -> Value {
+    if matches!(message.role, Role::Tool) {
+        // The response has to be an object: results that are one stay as they are
+        let text = message.content.text();
+        let response = match serde_json::from_str::<Value>(&text) {
+            Ok(value) if value.is_object() => value,
+            _ => json!({ "result": text }),
+        };
+        return json!({
+            "role": "user",
+            "parts": [{ "functionResponse": { "name": message.name, "response": response } }],
+        });
+    }
+    let mut parts: Vec<Value> = match &message.content {
+        Content::Text(text) if text.is_empty() => Vec::new(),
+        Content::Text(text) => vec![json!({ "text": text })],
+        Content::Parts(parts) => parts
+            .iter()
+            .map(|part| match part {
+                Part::Text { text } => json!({ "text": text }),
+                Part::Image { data, mime_type } => json!({
+                    "inlineData": { "mimeType": mime_type, "data": data },
+                }),
+            })
+            .collect(),
+    };
+    parts.extend(
+        message
+            .tool_calls
+            .iter()
+            .map(|call| json!({ "functionCall": { "name": call.name, "args": arguments_object(call) } })),
+    );
+    let role = if matches!(message.role, Role::Assistant) { "model" } else { "user" };
+    json!({ "role": role, "parts": parts })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(value: Value) -> Message {
+        serde_json::from_value(value).expect("test message parses")
+    }
+
+    #[test]
+    fn tool_results_of_one_turn_are_grouped() {
+        let messages = [
+            message(json!({ "role": "system", "content": "Be nice" })),
+            message(json!({ "role": "user", "content": "Hi" })),
+            message(json!({ "role": "assistant", "content": "", "toolCalls": [
+                { "id": "a", "name": "one", "arguments": {} },
+                { "id": "b", "name": "two", "arguments": "not json" },
+            ] })),
+            message(json!({ "role": "tool", "content": "1", "toolCallId": "a", "name": "one" })),
+            message(json!({ "role": "tool", "content": "2", "toolCallId": "b", "name": "two" })),
+        ];
+        let grouped = group_tool_results(&messages, anthropic_message, "content");
+        assert_eq!(grouped.len(), 3);
+        assert_eq!(grouped[1]["content"][1]["input"], json!({}));
+        assert_eq!(grouped[2]["role"], "user");
+        assert_eq!(grouped[2]["content"].as_array().unwrap().len(), 2);
+        assert_eq!(system_text(&messages), "Be nice");
+    }
+
+    #[test]
+    fn gemini_tool_results_become_objects() {
+        let text = gemini_content(&message(json!({ "role": "tool", "content": "done", "name": "f" })));
+        assert_eq!(text["parts"][0]["functionResponse"]["response"], json!({ "result": "done" }));
+        let object = gemini_content(&message(json!({ "role": "tool", "content": "{\"ok\":true}", "name": "f" })));
+        assert_eq!(object["parts"][0]["functionResponse"]["response"], json!({ "ok": true }));
+    }
+
+    #[test]
+    fn gemini_schema_keeps_what_gemini_knows() {
+        let schema = json!({
+            "type": "object",
+            "additionalProperties": false,
+            "properties": {
+                "path": { "type": "string", "description": "File", "minLength": 1 },
+                "options": { "type": "object" },
+            },
+            "required": ["path"],
+        });
+        assert_eq!(
+            gemini_schema(&schema),
+            Some(json!({
+                "type": "object",
+                "properties": { "path": { "type": "string", "description": "File" } },
+                "required": ["path"],
+            }))
+        );
+        assert_eq!(gemini_schema(&json!({ "type": "object", "properties": {} })), None);
+    }
+
+    #[test]
+    fn images_use_each_providers_format() {
+        let with_image = message(json!({ "role": "user", "content": [
+            { "type": "text", "text": "What's this?" },
+            { "type": "image", "data": "AAAA", "mimeType": "image/png" },
+        ] }));
+        assert_eq!(openai_message(&with_image)["content"][1]["image_url"]["url"], "data:image/png;base64,AAAA");
+        assert_eq!(anthropic_message(&with_image)["content"][1]["source"]["media_type"], "image/png");
+        assert_eq!(gemini_content(&with_image)["parts"][1]["inlineData"]["data"], "AAAA");
+        assert_eq!(with_image.content.text(), "What's this?");
+    }
+}
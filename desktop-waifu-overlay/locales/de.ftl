@@ -0,0 +1,150 @@
+app-name = Desktop Waifu
+
+## Tray menu
+
+tray-show = Anzeigen
+tray-hide = Ausblenden
+tray-quit = Beenden
+tray-profile = Profil
+tray-profile-none = Keines
+tray-night-light = Nachtlicht
+tray-night-light-auto = Automatisch
+tray-night-light-on = An
+tray-night-light-off = Aus
+tray-animations = Animationen
+tray-open-settings = Einstellungen öffnen
+tray-character = Charakter
+tray-expression = Gesichtsausdruck
+tray-expression-neutral = Neutral
+tray-expression-happy = Fröhlich
+tray-expression-sad = Traurig
+tray-expression-angry = Wütend
+tray-expression-surprised = Überrascht
+tray-expression-relaxed = Entspannt
+tray-scale = Größe
+tray-scale-percent = { $percent } %
+tray-mute-notifications = Benachrichtigungen stummschalten
+tray-click-through = Klicks durchlassen
+tray-walk-around = Herumlaufen
+
+## Command line
+
+cli-about = Desktop Waifu Overlay - Animierte 3D-VRM-Figuren für deinen Desktop
+cli-toggle = Overlay ein- oder ausblenden (Befehl an laufende Instanz senden)
+cli-show = Overlay anzeigen (Befehl an laufende Instanz senden)
+cli-hide = Overlay ausblenden (Befehl an laufende Instanz senden)
+cli-ask-screenshot = Einen Bildschirmbereich aufnehmen und die Figur dazu fragen (Befehl an laufende Instanz senden)
+cli-status = Zustand der laufenden Instanz (Sichtbarkeit, Größe, Position, Modell, Laufzeit) als JSON ausgeben
+cli-ping = Prüfen, ob die laufende Instanz und ihre Seite antworten, und Version, PID und Laufzeit als JSON ausgeben
+cli-restart-webview = Die Oberfläche der laufenden Instanz neu laden, ohne ihr Fenster zu schließen
+cli-subscribe = Ereignisse der laufenden Instanz (Klicks, beendete Animationen, Sichtbarkeit) als JSON-Zeilen ausgeben, bis sie beendet wird
+cli-profile = Die laufende Instanz auf ein Profil aus profiles.toml umschalten ("default" für keines)
+cli-remind = Die laufende Instanz nach der mit --in angegebenen Zeit an TEXT erinnern lassen
+cli-remind-in = Verzögerung für --remind, z. B. "25m", "1h30m" oder "90s"
+cli-log-level = Log-Filter der laufenden Instanz ändern, z. B. "debug", "info" oder "info,desktop_waifu_overlay::ipc=trace"
+cli-unload-after = Speichersparmodus: Webinhalte entladen, nachdem das Overlay so viele Sekunden ausgeblendet war, und beim Anzeigen neu laden
+cli-strict-hotkeys = Alle IPC-Befehle ignorieren, nicht nur --toggle, solange der Hotkey deaktiviert ist
+cli-no-meeting-mode = Nicht stumm schalten, während andere Apps Mikrofon oder Kamera nutzen oder der Bildschirm geteilt wird
+cli-shortcut = Taste, die für das globale Ein-/Ausblende-Tastenkürzel über das Desktop-Portal vorgeschlagen wird (z. B. "LOGO+w" oder "CTRL+ALT+d"); "none", um keines zu registrieren
+cli-debug = Debug-Ausgaben protokollieren (RUST_LOG hat Vorrang)
+cli-log-file = Das Log zusätzlich in ~/.cache/desktop-waifu/overlay.log schreiben, rotiert bei 5 MB
+cli-supervise = Das Overlay als Kindprozess starten und nach einem Absturz mit wachsender Wartezeit neu starten
+cli-install-autostart = Das Overlay bei der Anmeldung starten, mit den hier angegebenen übrigen Optionen: über einen systemd-Benutzerdienst, einen XDG-Autostart-Eintrag oder was passt ("auto")
+cli-uninstall-autostart = Das Overlay nicht mehr bei der Anmeldung starten
+cli-check-update = Auf GitHub nach einer neueren Version suchen
+cli-self-update = Die neueste Version über diese installieren (Tarball-Installationen) und das Overlay neu starten
+cli-locale = Sprache für Tray, Dialoge und Meldungen (z. B. "de" oder "ja-JP"); Standard ist die Systemsprache
+cli-plugin = Plugins verwalten (installieren, aktivieren, Berechtigungen erteilen, ...)
+cli-plugin-list = Installierte Plugins und ihren Status auflisten
+cli-plugin-install = Ein Plugin aus einem Verzeichnis mit plugin.toml installieren
+cli-plugin-remove = Ein installiertes Plugin entfernen
+cli-plugin-enable = Ein deaktiviertes Plugin aktivieren
+cli-plugin-disable = Ein Plugin deaktivieren, ohne es zu entfernen
+cli-plugin-grant = Einem Plugin alle angeforderten Berechtigungen erteilen
+cli-plugin-revoke = Einem Plugin alle Berechtigungen entziehen
+cli-send-failed = { $command } konnte nicht gesendet werden: { $error }. Läuft desktop-waifu?
+cli-reminder-set = Erinnerung gestellt für { $time }
+error-no-dist = Das dist-Verzeichnis wurde nicht gefunden. Baue zuerst das Frontend mit: bun build
+
+## Autostart
+
+autostart-installed-systemd = { $path } installiert und aktiviert; das Overlay startet mit der nächsten grafischen Sitzung
+autostart-installed-xdg = { $path } installiert; das Overlay startet bei der nächsten Anmeldung
+autostart-removed = { $path } entfernt
+autostart-not-installed = Autostart ist nicht eingerichtet
+autostart-systemctl-failed = systemctl --user { $command } fehlgeschlagen: { $error }
+
+## Self-update
+
+update-current = Desktop Waifu { $version } ist aktuell
+update-available = Desktop Waifu { $version } ist verfügbar (installiert: { $current }). Mit --self-update installieren.
+update-not-writable = { $path } ist nicht beschreibbar. Aktualisiere Desktop Waifu über den Paketmanager, mit dem es installiert wurde.
+update-no-key = Dieser Build hat keinen Schlüssel zum Prüfen von Releases und kann sich daher nicht selbst aktualisieren
+update-no-build = Die neueste Version enthält keinen Build für { $arch }
+update-no-digest = Für { $file } ist keine signierte Prüfsumme veröffentlicht, daher wurde es nicht installiert
+update-downloading = Desktop Waifu { $version } wird heruntergeladen…
+update-digest-mismatch = { $file } stimmt nicht mit der veröffentlichten Prüfsumme überein, daher wurde es nicht installiert
+update-bad-signature = Die Signatur von { $file } ist ungültig, daher wurde nichts installiert
+update-bad-archive = Das Archiv der Version enthält kein Overlay
+update-installed = Desktop Waifu { $version } installiert
+update-restarting-service = Der Dienst desktop-waifu wird neu gestartet
+update-restarting = Das Overlay wird neu gestartet
+
+## Plugins
+
+plugin-prompt-title = Plugin „{ $name }“ erlauben?
+plugin-prompt-permissions = Angeforderte Berechtigungen: { $permissions }
+plugin-prompt-warning = Plugins führen nativen Code mit deinen Benutzerrechten aus. Erlaube nur Plugins, denen du vertraust.
+plugin-prompt-deny = Ablehnen
+plugin-prompt-allow = Erlauben
+plugin-not-installed = Plugin '{ $name }' ist nicht installiert
+plugin-none-installed = Keine Plugins in { $dir } installiert
+plugin-status-disabled = deaktiviert
+plugin-status-enabled = aktiviert
+plugin-status-awaiting = wartet auf Erlaubnis
+plugin-status-denied = abgelehnt
+plugin-list-entry = { $name } { $version } [{ $status }] Berechtigungen: { $permissions }
+plugin-library-missing = Bibliothek '{ $library }' nicht gefunden in { $dir }
+plugin-already-installed = Plugin '{ $name }' ist bereits installiert; entferne es zuerst
+plugin-installed = Plugin '{ $name }' nach { $dir } installiert
+plugin-installed-hint = Beim nächsten Start von desktop-waifu wird nach der Erlaubnis gefragt
+plugin-removed = Plugin '{ $name }' entfernt
+plugin-enabled = Plugin '{ $name }' aktiviert
+plugin-disabled = Plugin '{ $name }' deaktiviert
+plugin-granted = Alle angeforderten Berechtigungen an '{ $name }' erteilt
+plugin-revoked = Berechtigungen von '{ $name }' entzogen
+
+## Accessibility
+
+a11y-window-description = Desktop-Begleiterfigur mit KI-Chat
+a11y-chat-label = Figur und Chat
+
+## Global shortcuts
+
+shortcut-toggle = Desktop Waifu ein- oder ausblenden
+
+## Reminders
+
+reminder-title = Erinnerung
+
+## Web process crashes
+
+web-process-reloading = Die Figur reagierte nicht mehr und wurde neu geladen.
+web-process-gave-up = Die Figur stürzt wiederholt ab und wurde nicht neu geladen. Beende Desktop Waifu über das Tray-Symbol und starte es neu.
+
+## Content policy
+
+content-filter-error = Die Seite wurde nicht geladen, weil ihr Inhaltsfilter nicht eingerichtet werden konnte: { $error }
+
+## Confirmation dialogs
+
+confirm-command-title = Diesen Befehl ausführen?
+confirm-privileged-title = Diesen Befehl als Administrator ausführen?
+confirm-write-title = In { $path } schreiben?
+confirm-tool-title = Dem Assistenten { $tool } erlauben?
+confirm-read-dir-title = Dem Assistenten erlauben, Dateien in { $path } zu lesen?
+confirm-command = Befehl
+confirm-cwd = Ordner
+confirm-changes = Änderungen
+confirm-deny = Abbrechen
+confirm-allow = Erlauben
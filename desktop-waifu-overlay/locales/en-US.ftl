@@ -0,0 +1,150 @@
+app-name = Desktop Waifu
+
+## Tray menu
+
+tray-show = Show
+tray-hide = Hide
+tray-quit = Quit
+tray-profile = Profile
+tray-profile-none = None
+tray-night-light = Night light
+tray-night-light-auto = Automatic
+tray-night-light-on = On
+tray-night-light-off = Off
+tray-animations = Animations
+tray-open-settings = Open settings
+tray-character = Character
+tray-expression = Expression
+tray-expression-neutral = Neutral
+tray-expression-happy = Happy
+tray-expression-sad = Sad
+tray-expression-angry = Angry
+tray-expression-surprised = Surprised
+tray-expression-relaxed = Relaxed
+tray-scale = Size
+tray-scale-percent = { $percent }%
+tray-mute-notifications = Mute notifications
+tray-click-through = Click-through
+tray-walk-around = Walk around
+
+## Command line
+
+cli-about = Desktop Waifu overlay - Animated 3D VRM characters for your desktop
+cli-toggle = Toggle overlay visibility (send command to running instance)
+cli-show = Show overlay (send command to running instance)
+cli-hide = Hide overlay (send command to running instance)
+cli-ask-screenshot = Capture a screen region and ask the character about it (send command to running instance)
+cli-status = Print the running instance's state (visibility, size, position, model, uptime) as JSON
+cli-ping = Check that the running instance and its page respond, and print its version, PID and uptime as JSON
+cli-restart-webview = Reload the running instance's frontend without closing its window
+cli-subscribe = Print the running instance's events (clicks, finished animations, visibility) as JSON lines until it quits
+cli-profile = Switch the running instance to a profile from profiles.toml ("default" for none)
+cli-remind = Have the running instance remind you of TEXT, after the delay given with --in
+cli-remind-in = Delay for --remind, e.g. "25m", "1h30m" or "90s"
+cli-log-level = Change the running instance's log filter, e.g. "debug", "info" or "info,desktop_waifu_overlay::ipc=trace"
+cli-unload-after = Memory mode: unload the web content after the overlay has been hidden this many seconds, and reload it when shown again
+cli-strict-hotkeys = Ignore every IPC command, not just --toggle, while the hotkey setting is disabled
+cli-no-meeting-mode = Don't go quiet while other apps use the microphone or camera or the screen is shared
+cli-shortcut = Key to suggest for the global toggle shortcut through the desktop portal (e.g. "LOGO+w" or "CTRL+ALT+d"); "none" to not register one
+cli-debug = Log debug output (RUST_LOG takes precedence)
+cli-log-file = Also write the log to ~/.cache/desktop-waifu/overlay.log, rotated at 5 MB
+cli-supervise = Run the overlay as a child process and restart it, with backoff, when it crashes
+cli-install-autostart = Start the overlay on login, with the other options given here: through a systemd user service, an XDG autostart entry, or whichever fits ("auto")
+cli-uninstall-autostart = Stop starting the overlay on login
+cli-check-update = Check GitHub for a newer release
+cli-self-update = Install the latest release over this one (tarball installs) and restart the overlay
+cli-locale = Language for tray, dialogs and messages (e.g. "de" or "ja-JP"); defaults to the system locale
+cli-plugin = Manage plugins (install, enable, grant permissions, ...)
+cli-plugin-list = List installed plugins and their status
+cli-plugin-install = Install a plugin from a directory containing plugin.toml
+cli-plugin-remove = Remove an installed plugin
+cli-plugin-enable = Enable a disabled plugin
+cli-plugin-disable = Disable a plugin without removing it
+cli-plugin-grant = Grant all permissions requested by a plugin
+cli-plugin-revoke = Revoke all permissions from a plugin
+cli-send-failed = Failed to send { $command }: { $error }. Is desktop-waifu running?
+cli-reminder-set = Reminder set for { $time }
+error-no-dist = Could not find dist directory. Build the frontend first with: bun build
+
+## Autostart
+
+autostart-installed-systemd = Installed and enabled { $path }; the overlay starts with your next graphical session
+autostart-installed-xdg = Installed { $path }; the overlay starts on your next login
+autostart-removed = Removed { $path }
+autostart-not-installed = Autostart is not installed
+autostart-systemctl-failed = systemctl --user { $command } failed: { $error }
+
+## Self-update
+
+update-current = Desktop Waifu { $version } is up to date
+update-available = Desktop Waifu { $version } is available (you have { $current }). Run with --self-update to install it.
+update-not-writable = { $path } is not writable. Update Desktop Waifu through the package manager it was installed with.
+update-no-key = This build has no key to check releases with, so it can't update itself
+update-no-build = The latest release has no build for { $arch }
+update-no-digest = The release publishes no signed checksum for { $file }, so it was not installed
+update-downloading = Downloading Desktop Waifu { $version }…
+update-digest-mismatch = { $file } does not match its published checksum, so it was not installed
+update-bad-signature = The signature on { $file } doesn't check out, so nothing was installed
+update-bad-archive = The release archive does not contain the overlay
+update-installed = Installed Desktop Waifu { $version }
+update-restarting-service = Restarting the desktop-waifu service
+update-restarting = Restarting the overlay
+
+## Plugins
+
+plugin-prompt-title = Allow plugin "{ $name }"?
+plugin-prompt-permissions = Requested permissions: { $permissions }
+plugin-prompt-warning = Plugins run native code with your user's privileges. Only allow plugins you trust.
+plugin-prompt-deny = Deny
+plugin-prompt-allow = Allow
+plugin-not-installed = Plugin '{ $name }' is not installed
+plugin-none-installed = No plugins installed in { $dir }
+plugin-status-disabled = disabled
+plugin-status-enabled = enabled
+plugin-status-awaiting = awaiting permission
+plugin-status-denied = denied
+plugin-list-entry = { $name } { $version } [{ $status }] permissions: { $permissions }
+plugin-library-missing = Library '{ $library }' not found in { $dir }
+plugin-already-installed = Plugin '{ $name }' is already installed; remove it first
+plugin-installed = Installed plugin '{ $name }' to { $dir }
+plugin-installed-hint = It will ask for permission the next time desktop-waifu starts
+plugin-removed = Removed plugin '{ $name }'
+plugin-enabled = Enabled plugin '{ $name }'
+plugin-disabled = Disabled plugin '{ $name }'
+plugin-granted = Granted all requested permissions to '{ $name }'
+plugin-revoked = Revoked permissions from '{ $name }'
+
+## Accessibility
+
+a11y-window-description = Desktop companion character with an AI chat
+a11y-chat-label = Character and chat
+
+## Global shortcuts
+
+shortcut-toggle = Show or hide Desktop Waifu
+
+## Reminders
+
+reminder-title = Reminder
+
+## Web process crashes
+
+web-process-reloading = The character stopped responding and was reloaded.
+web-process-gave-up = The character keeps crashing, so it was not reloaded. Quit Desktop Waifu from the tray and start it again.
+
+## Content policy
+
+content-filter-error = The page was not loaded because its content filter could not be set up: { $error }
+
+## Confirmation dialogs
+
+confirm-command-title = Run this command?
+confirm-privileged-title = Run this command as administrator?
+confirm-write-title = Write to { $path }?
+confirm-tool-title = Let the assistant use { $tool }?
+confirm-read-dir-title = Let the assistant read files in { $path }?
+confirm-command = Command
+confirm-cwd = Folder
+confirm-changes = Changes
+confirm-deny = Cancel
+confirm-allow = Allow
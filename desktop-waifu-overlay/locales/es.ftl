@@ -0,0 +1,150 @@
+app-name = Desktop Waifu
+
+## Tray menu
+
+tray-show = Mostrar
+tray-hide = Ocultar
+tray-quit = Salir
+tray-profile = Perfil
+tray-profile-none = Ninguno
+tray-night-light = Luz nocturna
+tray-night-light-auto = Automática
+tray-night-light-on = Activada
+tray-night-light-off = Desactivada
+tray-animations = Animaciones
+tray-open-settings = Abrir ajustes
+tray-character = Personaje
+tray-expression = Expresión
+tray-expression-neutral = Neutral
+tray-expression-happy = Alegría
+tray-expression-sad = Tristeza
+tray-expression-angry = Enfado
+tray-expression-surprised = Sorpresa
+tray-expression-relaxed = Calma
+tray-scale = Tamaño
+tray-scale-percent = { $percent } %
+tray-mute-notifications = Silenciar notificaciones
+tray-click-through = Dejar pasar los clics
+tray-walk-around = Pasear
+
+## Command line
+
+cli-about = Desktop Waifu overlay - Personajes VRM 3D animados para tu escritorio
+cli-toggle = Alternar la visibilidad del overlay (envía el comando a la instancia en ejecución)
+cli-show = Mostrar el overlay (envía el comando a la instancia en ejecución)
+cli-hide = Ocultar el overlay (envía el comando a la instancia en ejecución)
+cli-ask-screenshot = Capturar una región de la pantalla y preguntar al personaje sobre ella (envía el comando a la instancia en ejecución)
+cli-status = Mostrar el estado de la instancia en ejecución (visibilidad, tamaño, posición, modelo, tiempo activo) como JSON
+cli-ping = Comprobar que la instancia en ejecución y su página responden, y mostrar su versión, PID y tiempo activo como JSON
+cli-restart-webview = Recargar la interfaz de la instancia en ejecución sin cerrar su ventana
+cli-subscribe = Mostrar los eventos de la instancia en ejecución (clics, animaciones terminadas, visibilidad) como líneas JSON hasta que se cierre
+cli-profile = Cambiar la instancia en ejecución a un perfil de profiles.toml ("default" para ninguno)
+cli-remind = Hacer que la instancia en ejecución te recuerde TEXT tras el tiempo indicado con --in
+cli-remind-in = Tiempo de espera para --remind, p. ej. "25m", "1h30m" o "90s"
+cli-log-level = Cambiar el filtro de registro de la instancia en ejecución, p. ej. "debug", "info" o "info,desktop_waifu_overlay::ipc=trace"
+cli-unload-after = Modo de memoria: descargar el contenido web tras estar oculto esta cantidad de segundos y recargarlo al mostrarse
+cli-strict-hotkeys = Ignorar todos los comandos IPC, no solo --toggle, mientras el atajo esté desactivado
+cli-no-meeting-mode = No silenciar mientras otras apps usan el micrófono o la cámara o se comparte la pantalla
+cli-shortcut = Tecla que se propone para el atajo global de mostrar/ocultar a través del portal del escritorio (p. ej. "LOGO+w" o "CTRL+ALT+d"); "none" para no registrar ninguno
+cli-debug = Registrar la salida de depuración (RUST_LOG tiene prioridad)
+cli-log-file = Escribir también el registro en ~/.cache/desktop-waifu/overlay.log, rotado a los 5 MB
+cli-supervise = Ejecutar el overlay como proceso hijo y reiniciarlo, con espera creciente, cuando falle
+cli-install-autostart = Iniciar la superposición al iniciar sesión, con las demás opciones indicadas aquí: mediante un servicio de usuario de systemd, una entrada de inicio automático XDG o lo que corresponda ("auto")
+cli-uninstall-autostart = Dejar de iniciar la superposición al iniciar sesión
+cli-check-update = Buscar una versión más reciente en GitHub
+cli-self-update = Instalar la última versión sobre esta (instalaciones desde tarball) y reiniciar el overlay
+cli-locale = Idioma de la bandeja, los diálogos y los mensajes (p. ej. "es" o "ja-JP"); por defecto, el idioma del sistema
+cli-plugin = Gestionar plugins (instalar, activar, conceder permisos, ...)
+cli-plugin-list = Listar los plugins instalados y su estado
+cli-plugin-install = Instalar un plugin desde un directorio que contenga plugin.toml
+cli-plugin-remove = Eliminar un plugin instalado
+cli-plugin-enable = Activar un plugin desactivado
+cli-plugin-disable = Desactivar un plugin sin eliminarlo
+cli-plugin-grant = Conceder todos los permisos que solicita un plugin
+cli-plugin-revoke = Revocar todos los permisos de un plugin
+cli-send-failed = No se pudo enviar { $command }: { $error }. ¿Está desktop-waifu en ejecución?
+cli-reminder-set = Recordatorio programado para las { $time }
+error-no-dist = No se encontró el directorio dist. Compila primero el frontend con: bun build
+
+## Autostart
+
+autostart-installed-systemd = { $path } instalado y habilitado; la superposición se iniciará con tu próxima sesión gráfica
+autostart-installed-xdg = { $path } instalado; la superposición se iniciará la próxima vez que inicies sesión
+autostart-removed = { $path } eliminado
+autostart-not-installed = El inicio automático no está instalado
+autostart-systemctl-failed = systemctl --user { $command } falló: { $error }
+
+## Self-update
+
+update-current = Desktop Waifu { $version } está actualizado
+update-available = Desktop Waifu { $version } está disponible (tienes { $current }). Ejecuta con --self-update para instalarlo.
+update-not-writable = No se puede escribir en { $path }. Actualiza Desktop Waifu con el gestor de paquetes con el que lo instalaste.
+update-no-key = Esta compilación no tiene una clave para comprobar las versiones, así que no puede actualizarse sola
+update-no-build = La última versión no tiene una compilación para { $arch }
+update-no-digest = La versión no publica una suma de comprobación firmada para { $file }, así que no se instaló
+update-downloading = Descargando Desktop Waifu { $version }…
+update-digest-mismatch = { $file } no coincide con su suma de comprobación publicada, así que no se instaló
+update-bad-signature = La firma de { $file } no es válida, así que no se instaló nada
+update-bad-archive = El archivo de la versión no contiene el overlay
+update-installed = Desktop Waifu { $version } instalado
+update-restarting-service = Reiniciando el servicio desktop-waifu
+update-restarting = Reiniciando el overlay
+
+## Plugins
+
+plugin-prompt-title = ¿Permitir el plugin "{ $name }"?
+plugin-prompt-permissions = Permisos solicitados: { $permissions }
+plugin-prompt-warning = Los plugins ejecutan código nativo con los privilegios de tu usuario. Permite solo plugins en los que confíes.
+plugin-prompt-deny = Denegar
+plugin-prompt-allow = Permitir
+plugin-not-installed = El plugin '{ $name }' no está instalado
+plugin-none-installed = No hay plugins instalados en { $dir }
+plugin-status-disabled = desactivado
+plugin-status-enabled = activado
+plugin-status-awaiting = pendiente de permiso
+plugin-status-denied = denegado
+plugin-list-entry = { $name } { $version } [{ $status }] permisos: { $permissions }
+plugin-library-missing = No se encontró la biblioteca '{ $library }' en { $dir }
+plugin-already-installed = El plugin '{ $name }' ya está instalado; elimínalo primero
+plugin-installed = Plugin '{ $name }' instalado en { $dir }
+plugin-installed-hint = Pedirá permiso la próxima vez que se inicie desktop-waifu
+plugin-removed = Plugin '{ $name }' eliminado
+plugin-enabled = Plugin '{ $name }' activado
+plugin-disabled = Plugin '{ $name }' desactivado
+plugin-granted = Se concedieron todos los permisos solicitados a '{ $name }'
+plugin-revoked = Se revocaron los permisos de '{ $name }'
+
+## Accessibility
+
+a11y-window-description = Personaje de compañía para el escritorio con chat de IA
+a11y-chat-label = Personaje y chat
+
+## Global shortcuts
+
+shortcut-toggle = Mostrar u ocultar Desktop Waifu
+
+## Reminders
+
+reminder-title = Recordatorio
+
+## Web process crashes
+
+web-process-reloading = El personaje dejó de responder y se volvió a cargar.
+web-process-gave-up = El personaje sigue fallando, así que no se volvió a cargar. Cierra Desktop Waifu desde la bandeja y vuelve a iniciarlo.
+
+## Content policy
+
+content-filter-error = La página no se cargó porque no se pudo configurar su filtro de contenido: { $error }
+
+## Confirmation dialogs
+
+confirm-command-title = ¿Ejecutar este comando?
+confirm-privileged-title = ¿Ejecutar este comando como administrador?
+confirm-write-title = ¿Escribir en { $path }?
+confirm-tool-title = ¿Permitir que el asistente use { $tool }?
+confirm-read-dir-title = ¿Permitir que el asistente lea archivos en { $path }?
+confirm-command = Comando
+confirm-cwd = Carpeta
+confirm-changes = Cambios
+confirm-deny = Cancelar
+confirm-allow = Permitir
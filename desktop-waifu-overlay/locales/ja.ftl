@@ -0,0 +1,150 @@
+app-name = Desktop Waifu
+
+## Tray menu
+
+tray-show = 表示
+tray-hide = 非表示
+tray-quit = 終了
+tray-profile = プロファイル
+tray-profile-none = なし
+tray-night-light = 夜間モード
+tray-night-light-auto = 自動
+tray-night-light-on = オン
+tray-night-light-off = オフ
+tray-animations = アニメーション
+tray-open-settings = 設定を開く
+tray-character = キャラクター
+tray-expression = 表情
+tray-expression-neutral = 通常
+tray-expression-happy = 喜び
+tray-expression-sad = 悲しみ
+tray-expression-angry = 怒り
+tray-expression-surprised = 驚き
+tray-expression-relaxed = リラックス
+tray-scale = サイズ
+tray-scale-percent = { $percent }%
+tray-mute-notifications = 通知をミュート
+tray-click-through = クリックを透過
+tray-walk-around = 歩き回る
+
+## Command line
+
+cli-about = Desktop Waifu オーバーレイ - デスクトップのためのアニメーション 3D VRM キャラクター
+cli-toggle = オーバーレイの表示を切り替える（実行中のインスタンスにコマンドを送信）
+cli-show = オーバーレイを表示する（実行中のインスタンスにコマンドを送信）
+cli-hide = オーバーレイを隠す（実行中のインスタンスにコマンドを送信）
+cli-ask-screenshot = 画面の一部をキャプチャしてキャラクターに質問する（実行中のインスタンスにコマンドを送信）
+cli-status = 実行中のインスタンスの状態（表示、サイズ、位置、モデル、稼働時間）をJSONで出力
+cli-ping = 実行中のインスタンスとそのページが応答するか確認し、バージョン、PID、稼働時間をJSONで出力
+cli-restart-webview = 実行中のインスタンスのウィンドウを閉じずにフロントエンドを再読み込みする
+cli-subscribe = 実行中のインスタンスのイベント（クリック、アニメーション終了、表示状態）を終了するまで JSON 行で出力する
+cli-profile = 実行中のインスタンスを profiles.toml のプロファイルに切り替える（なしにするには "default"）
+cli-remind = --in で指定した時間の後に、実行中のインスタンスから TEXT をリマインドする
+cli-remind-in = --remind までの時間（例: "25m"、"1h30m"、"90s"）
+cli-log-level = 実行中のインスタンスのログフィルターを変更する（例: "debug"、"info"、"info,desktop_waifu_overlay::ipc=trace"）
+cli-unload-after = メモリ節約モード: 指定した秒数オーバーレイが非表示のままならウェブコンテンツを解放し、表示時に再読み込みする
+cli-strict-hotkeys = ホットキー設定が無効の間は --toggle だけでなくすべての IPC コマンドを無視する
+cli-no-meeting-mode = 他のアプリがマイクやカメラを使用中、または画面共有中でも静かにしない
+cli-shortcut = デスクトップポータル経由で登録するグローバル表示切り替えショートカットの推奨キー（例: "LOGO+w" や "CTRL+ALT+d"）。"none" で登録しない
+cli-debug = デバッグ出力をログに記録する（RUST_LOG が優先）
+cli-log-file = ログを ~/.cache/desktop-waifu/overlay.log にも書き込む（5 MB でローテーション）
+cli-supervise = オーバーレイを子プロセスとして実行し、クラッシュしたら間隔を空けて再起動する
+cli-install-autostart = ログイン時にオーバーレイを起動する（ここで指定した他のオプション付き）。systemd ユーザーサービス、XDG 自動起動エントリ、または環境に合う方（"auto"）を使う
+cli-uninstall-autostart = ログイン時にオーバーレイを起動しないようにする
+cli-check-update = GitHub で新しいリリースを確認する
+cli-self-update = 最新リリースをこのインストールに上書きし（tarball インストール）、オーバーレイを再起動する
+cli-locale = トレイ・ダイアログ・メッセージの言語（例: "ja" や "de-DE"）。既定はシステムのロケール
+cli-plugin = プラグインを管理する（インストール、有効化、権限の付与など）
+cli-plugin-list = インストール済みのプラグインと状態を一覧表示する
+cli-plugin-install = plugin.toml を含むディレクトリからプラグインをインストールする
+cli-plugin-remove = インストール済みのプラグインを削除する
+cli-plugin-enable = 無効化されたプラグインを有効にする
+cli-plugin-disable = プラグインを削除せずに無効にする
+cli-plugin-grant = プラグインが要求するすべての権限を付与する
+cli-plugin-revoke = プラグインからすべての権限を取り消す
+cli-send-failed = { $command } を送信できませんでした: { $error }。desktop-waifu は起動していますか？
+cli-reminder-set = { $time } にリマインダーを設定しました
+error-no-dist = dist ディレクトリが見つかりません。先にフロントエンドをビルドしてください: bun build
+
+## Autostart
+
+autostart-installed-systemd = { $path } をインストールして有効にしました。次のグラフィカルセッションからオーバーレイが起動します
+autostart-installed-xdg = { $path } をインストールしました。次回のログインからオーバーレイが起動します
+autostart-removed = { $path } を削除しました
+autostart-not-installed = 自動起動は設定されていません
+autostart-systemctl-failed = systemctl --user { $command } に失敗しました: { $error }
+
+## Self-update
+
+update-current = Desktop Waifu { $version } は最新です
+update-available = Desktop Waifu { $version } が利用可能です（現在 { $current }）。--self-update でインストールできます。
+update-not-writable = { $path } に書き込めません。インストールに使ったパッケージマネージャーで Desktop Waifu を更新してください。
+update-no-key = このビルドにはリリースを検証する鍵がないため、自動更新できません
+update-no-build = 最新リリースには { $arch } 向けのビルドがありません
+update-no-digest = { $file } の署名付きチェックサムが公開されていないため、インストールしませんでした
+update-downloading = Desktop Waifu { $version } をダウンロードしています…
+update-digest-mismatch = { $file } が公開されたチェックサムと一致しないため、インストールしませんでした
+update-bad-signature = { $file } の署名が正しくないため、何もインストールしませんでした
+update-bad-archive = リリースのアーカイブにオーバーレイが含まれていません
+update-installed = Desktop Waifu { $version } をインストールしました
+update-restarting-service = desktop-waifu サービスを再起動しています
+update-restarting = オーバーレイを再起動しています
+
+## Plugins
+
+plugin-prompt-title = プラグイン「{ $name }」を許可しますか？
+plugin-prompt-permissions = 要求された権限: { $permissions }
+plugin-prompt-warning = プラグインはあなたのユーザー権限でネイティブコードを実行します。信頼できるプラグインだけを許可してください。
+plugin-prompt-deny = 拒否
+plugin-prompt-allow = 許可
+plugin-not-installed = プラグイン '{ $name }' はインストールされていません
+plugin-none-installed = { $dir } にプラグインはインストールされていません
+plugin-status-disabled = 無効
+plugin-status-enabled = 有効
+plugin-status-awaiting = 許可待ち
+plugin-status-denied = 拒否済み
+plugin-list-entry = { $name } { $version } [{ $status }] 権限: { $permissions }
+plugin-library-missing = ライブラリ '{ $library }' が { $dir } に見つかりません
+plugin-already-installed = プラグイン '{ $name }' はすでにインストールされています。先に削除してください
+plugin-installed = プラグイン '{ $name }' を { $dir } にインストールしました
+plugin-installed-hint = 次回 desktop-waifu の起動時に許可を求めます
+plugin-removed = プラグイン '{ $name }' を削除しました
+plugin-enabled = プラグイン '{ $name }' を有効にしました
+plugin-disabled = プラグイン '{ $name }' を無効にしました
+plugin-granted = '{ $name }' に要求されたすべての権限を付与しました
+plugin-revoked = '{ $name }' の権限を取り消しました
+
+## Accessibility
+
+a11y-window-description = AI チャット付きのデスクトップ キャラクター
+a11y-chat-label = キャラクターとチャット
+
+## Global shortcuts
+
+shortcut-toggle = Desktop Waifu を表示・非表示にする
+
+## Reminders
+
+reminder-title = リマインダー
+
+## Web process crashes
+
+web-process-reloading = キャラクターが応答しなくなったため、再読み込みしました。
+web-process-gave-up = キャラクターがクラッシュを繰り返すため、再読み込みしませんでした。トレイから Desktop Waifu を終了して、もう一度起動してください。
+
+## Content policy
+
+content-filter-error = コンテンツフィルターを設定できなかったため、ページを読み込みませんでした: { $error }
+
+## Confirmation dialogs
+
+confirm-command-title = このコマンドを実行しますか？
+confirm-privileged-title = このコマンドを管理者として実行しますか？
+confirm-write-title = { $path } に書き込みますか？
+confirm-tool-title = アシスタントに { $tool } の使用を許可しますか？
+confirm-read-dir-title = { $path } のファイルの読み取りをアシスタントに許可しますか？
+confirm-command = コマンド
+confirm-cwd = フォルダー
+confirm-changes = 変更内容
+confirm-deny = キャンセル
+confirm-allow = 許可
@@ -0,0 +1,62 @@
+//! Screen reader support
+//!
+//! Gives the overlay window and webview accessible names so they show up sensibly in the
+//! AT-SPI tree, and turns `announce` messages from the frontend (new character messages,
+//! state changes) into screen reader announcements. Announcements need GTK 4.14 and the
+//! `accessibility` feature; without it they are only logged.
+
+use gtk4::prelude::*;
+use gtk4::{accessible, ApplicationWindow};
+use serde::Deserialize;
+use webkit6::WebView;
+
+use crate::tr;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Announcement {
+    pub message: String,
+    #[serde(default)]
+    pub priority: Priority,
+}
+
+/// Label the window and webview for assistive technologies
+pub fn setup(window: &ApplicationWindow, webview: &WebView) {
+    window.update_property(&[
+        accessible::Property::Label(&tr!("app-name")),
+        accessible::Property::Description(&tr!("a11y-window-description")),
+    ]);
+    webview.update_property(&[accessible::Property::Label(&tr!("a11y-chat-label"))]);
+}
+
+/// Have the screen reader speak `announcement`
+pub fn announce(window: &ApplicationWindow, announcement: &Announcement) {
+    let message = announcement.message.trim();
+    if message.is_empty() {
+        return;
+    }
+
+    #[cfg(feature = "accessibility")]
+    {
+        let priority = match announcement.priority {
+            Priority::Low => gtk4::AccessibleAnnouncementPriority::Low,
+            Priority::Medium => gtk4::AccessibleAnnouncementPriority::Medium,
+            Priority::High => gtk4::AccessibleAnnouncementPriority::High,
+        };
+        window.announce(message, priority);
+    }
+
+    #[cfg(not(feature = "accessibility"))]
+    {
+        let _ = window;
+        crate::debug_log!("[A11Y] Announcements disabled at build time: {}", message);
+    }
+}
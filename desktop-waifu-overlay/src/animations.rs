@@ -0,0 +1,141 @@
+//! Animation clips the character plays on request
+//!
+//! Clips come from the frontend's bundled `animations/` folder and from VRMA files the user
+//! puts in `~/.local/share/desktop-waifu/animations/`, which the webview loads as
+//! `waifu-animation://<file name>`. A clip is named after its file, lowercased with spaces
+//! and dashes as underscores ("Joyful Jump.vrma" is `joyful_jump`); a user clip replaces a
+//! bundled one of the same name. FBX files have to be converted to VRMA first (see the
+//! README).
+//!
+//! `PlayAnimation` (IPC, companions, behavior scripts, the tray's Animations menu) looks the
+//! clip up here and hands the frontend its URL and how to blend it in. When the clip has
+//! finished, or something else replaced it, the frontend reports `AnimationComplete` with
+//! its name.
+
+use crate::ipc::AnimationRequest;
+use gtk4::glib;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use webkit6::prelude::*;
+use webkit6::{URISchemeRequest, WebView};
+
+pub const SCHEME: &str = "waifu-animation";
+
+/// Cross-fade in and out, in seconds, when a request doesn't say
+const DEFAULT_FADE: f32 = 0.3;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Clip {
+    pub name: String,
+    /// Where the webview loads it from
+    pub url: String,
+    /// From the user's animations folder rather than bundled
+    pub user: bool,
+}
+
+pub fn animations_dir() -> PathBuf {
+    glib::user_data_dir().join("desktop-waifu").join("animations")
+}
+
+/// The frontend's bundled clips
+fn bundled_dir() -> Option<PathBuf> {
+    crate::server::root()
+        .or_else(crate::server::find_dist_dir)
+        .map(|dist| dist.join("animations"))
+        // Running from a checkout that hasn't been built, where Vite serves public/
+        .or_else(|| {
+            ["../public/animations", "public/animations"]
+                .into_iter()
+                .map(PathBuf::from)
+                .find(|dir| dir.is_dir())
+        })
+}
+
+/// The name a clip file or a request refers to: `Joyful Jump` and `joyful-jump` are both
+/// `joyful_jump`
+pub fn clip_name(name: &str) -> String {
+    name.trim().to_lowercase().replace([' ', '-'], "_")
+}
+
+/// The `.vrma` files in `dir`, as (clip name, file name)
+fn clip_files(dir: &Path) -> Vec<(String, String)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .filter_map(|path| {
+            let extension = path.extension()?.to_str()?.to_lowercase();
+            if extension != "vrma" {
+                if extension == "fbx" {
+                    crate::debug_log!("[ANIMATIONS] Skipping {}, convert it to VRMA first", path.display());
+                }
+                return None;
+            }
+            let stem = path.file_stem()?.to_string_lossy();
+            let file_name = path.file_name()?.to_string_lossy().into_owned();
+            Some((clip_name(&stem), file_name))
+        })
+        .collect()
+}
+
+fn escape(file_name: &str) -> String {
+    glib::Uri::escape_string(file_name, None, false).to_string()
+}
+
+/// Every clip, by name
+pub fn list() -> Vec<Clip> {
+    let mut clips = BTreeMap::new();
+    if let Some(dir) = bundled_dir() {
+        for (name, file_name) in clip_files(&dir) {
+            let url = format!("/animations/{}", escape(&file_name));
+            clips.insert(name.clone(), Clip { name, url, user: false });
+        }
+    }
+    for (name, file_name) in clip_files(&animations_dir()) {
+        let url = format!("{}://{}", SCHEME, escape(&file_name));
+        clips.insert(name.clone(), Clip { name, url, user: true });
+    }
+    clips.into_values().collect()
+}
+
+pub fn find(name: &str) -> Option<Clip> {
+    let name = clip_name(name);
+    list().into_iter().find(|clip| clip.name == name)
+}
+
+/// The `playAnimation` event for `request`, or why it can't be played
+pub fn play_event(request: &AnimationRequest) -> Result<serde_json::Value, String> {
+    let clip = find(&request.name).ok_or_else(|| format!("unknown animation '{}'", request.name))?;
+    Ok(serde_json::json!({
+        "animation": clip.name,
+        "url": clip.url,
+        "fade": request.fade.filter(|fade| *fade >= 0.0).unwrap_or(DEFAULT_FADE),
+        "repeat": request.repeat.unwrap_or(1),
+        "speed": request.speed.filter(|speed| *speed > 0.0).unwrap_or(1.0),
+    }))
+}
+
+/// Register the URI scheme on the webview's context so user clips resolve
+pub fn install(webview: &WebView) {
+    let Some(context) = webview.web_context() else {
+        tracing::warn!("WebView has no context, user animations are unavailable");
+        return;
+    };
+    if let Some(security) = context.security_manager() {
+        // Allow fetch() from the http:// page origin
+        security.register_uri_scheme_as_cors_enabled(SCHEME);
+        security.register_uri_scheme_as_secure(SCHEME);
+    }
+    context.register_uri_scheme(SCHEME, respond);
+}
+
+fn respond(request: &URISchemeRequest) {
+    let path = crate::models::requested_file(request, SCHEME)
+        .filter(|name| !name.is_empty() && !name.contains(['/', '\\']) && !name.starts_with('.'))
+        .map(|name| animations_dir().join(name));
+    crate::models::serve_file(request, path);
+}
@@ -0,0 +1,216 @@
+//! REST control API on the static server (`[api]` in config.toml)
+//!
+//! For Home Assistant, Stream Deck plugins and scripts that would rather speak HTTP than
+//! the Unix socket; other machines reach it through an SSH tunnel, since the server only
+//! listens on localhost. Every request needs `Authorization: Bearer <token>`, with the
+//! token from `~/.config/desktop-waifu/api-token` (created on first use, readable only by
+//! the user). Requests go through the same IPC channel as socket clients:
+//!
+//! - `POST /api/v1/toggle` — like `--toggle`
+//! - `POST /api/v1/say` `{"text": "..."}` — the character says it
+//! - `POST /api/v1/animation` — a `PlayAnimation` request, e.g. `"wave"` or
+//!   `{"name": "wave", "repeat": 2}`
+//! - `GET /api/v1/status` — what `--status` prints
+//!
+//! Successful requests answer 200 with the result (204 without one), failures
+//! `{"error": "..."}`.
+//!
+//! `/ws` is a WebSocket for integrations that want to follow the character, such as an OBS
+//! browser source mirroring it: every `OverlayEvent` arrives as a JSON text message, and
+//! text messages sent to it are `OverlayCommand`s, answered with an `Error` event when they
+//! fail. Browsers can't set headers on a WebSocket, so it also takes the token as
+//! `/ws?token=...`. With the API disabled every path answers 404.
+
+use crate::ipc::{self, IpcMessage, Request};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Json, Request as HttpRequest};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::Router;
+use gtk4::glib;
+use serde::Deserialize;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// How long a request waits for the main loop to answer
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+/// Largest WebSocket message accepted (commands are small)
+const MAX_MESSAGE_LEN: usize = 64 * 1024;
+
+/// Where requests go and events come from; set once the overlay handles IPC
+static IPC: OnceLock<(async_channel::Sender<IpcMessage>, ipc::IpcClients)> = OnceLock::new();
+
+#[derive(Deserialize)]
+struct SayBody {
+    text: String,
+}
+
+/// Start handing API requests to the IPC handler and WebSocket clients its events
+pub fn connect(sender: async_channel::Sender<IpcMessage>, clients: ipc::IpcClients) {
+    let _ = IPC.set((sender, clients));
+}
+
+/// Routes under /api/v1
+pub fn router() -> Router {
+    Router::new()
+        .route("/toggle", post(|| send(Request::Text("toggle".into()))))
+        .route("/say", post(|Json(body): Json<SayBody>| send(Request::Say(body.text))))
+        .route(
+            "/animation",
+            post(|Json(animation): Json<ipc::AnimationRequest>| {
+                send(Request::Command(ipc::OverlayCommand::PlayAnimation(animation)))
+            }),
+        )
+        .route("/status", get(|| send(Request::GetState)))
+        .layer(middleware::from_fn(authorize))
+}
+
+/// The /ws route
+pub fn websocket_router() -> Router {
+    Router::new()
+        .route(
+            "/ws",
+            get(|ws: WebSocketUpgrade| async move { ws.max_message_size(MAX_MESSAGE_LEN).on_upgrade(serve_websocket) }),
+        )
+        .layer(middleware::from_fn(authorize))
+}
+
+pub fn token_path() -> PathBuf {
+    glib::user_config_dir().join("desktop-waifu").join("api-token")
+}
+
+/// The API token, creating the token file if there is none
+pub fn token() -> std::io::Result<String> {
+    let path = token_path();
+    match std::fs::read_to_string(&path) {
+        Ok(token) if !token.trim().is_empty() => return Ok(token.trim().to_string()),
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+
+    let token = crate::server::random_token()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&path)?
+        .write_all(format!("{}\n", token).as_bytes())?;
+    tracing::info!("Created API token in {}", path.display());
+    Ok(token)
+}
+
+/// 404 while the API is off, 401 without the token
+async fn authorize(headers: HeaderMap, request: HttpRequest, next: Next) -> Response {
+    if !crate::config::current().api.enabled {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let expected = match token() {
+        Ok(token) => token,
+        Err(e) => {
+            tracing::warn!("Could not read the API token from {}: {}", token_path().display(), e);
+            return error(StatusCode::INTERNAL_SERVER_ERROR, "API token unavailable");
+        }
+    };
+    let from_query = request
+        .uri()
+        .query()
+        .into_iter()
+        .flat_map(|query| query.split('&'))
+        .find_map(|pair| pair.strip_prefix("token="));
+    let given = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or(from_query)
+        .unwrap_or_default()
+        .to_string();
+    if !constant_time_eq(given.trim().as_bytes(), expected.as_bytes()) {
+        crate::debug_log!("[API] Rejected {} {}: bad token", request.method(), request.uri().path());
+        return error(StatusCode::UNAUTHORIZED, "missing or invalid token");
+    }
+    crate::debug_log!("[API] {} {}", request.method(), request.uri().path());
+    next.run(request).await
+}
+
+/// Pass `request` to the IPC handler and turn its answer into a response
+async fn send(request: Request) -> Response {
+    match call(request).await {
+        Ok(serde_json::Value::Null) => StatusCode::NO_CONTENT.into_response(),
+        Ok(value) => Json(value).into_response(),
+        Err((status, e)) => error(status, &e),
+    }
+}
+
+/// Pass `request` to the IPC handler and wait for its answer
+async fn call(request: Request) -> Result<serde_json::Value, (StatusCode, String)> {
+    let Some((sender, _)) = IPC.get() else {
+        return Err((StatusCode::SERVICE_UNAVAILABLE, "overlay is starting".into()));
+    };
+    let (message, reply) = IpcMessage::with_reply(request);
+    if sender.send(message).await.is_err() {
+        return Err((StatusCode::SERVICE_UNAVAILABLE, "overlay is shutting down".into()));
+    }
+    match tokio::time::timeout(REPLY_TIMEOUT, reply).await {
+        Ok(Ok(result)) => result.map_err(|e| (StatusCode::BAD_REQUEST, e)),
+        Ok(Err(_)) => Err((StatusCode::SERVICE_UNAVAILABLE, "overlay is shutting down".into())),
+        Err(_) => Err((StatusCode::GATEWAY_TIMEOUT, "overlay did not answer".into())),
+    }
+}
+
+/// Forward events to a WebSocket client and its commands to the overlay until either side
+/// closes
+async fn serve_websocket(mut socket: WebSocket) {
+    let Some((_, clients)) = IPC.get() else { return };
+    let events = clients.subscribe();
+    let shutdown = crate::runtime::shutdown_token();
+    crate::debug_log!("[API] WebSocket client connected");
+
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            event = events.recv() => {
+                let Ok(line) = event else { break };
+                let text = String::from_utf8_lossy(&line).trim_end().to_string();
+                if socket.send(Message::Text(text.into())).await.is_err() {
+                    break;
+                }
+            }
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    let failure = match serde_json::from_str::<ipc::OverlayCommand>(text.as_str()) {
+                        Ok(command) => call(Request::Command(command)).await.err().map(|(_, e)| e),
+                        Err(e) => Some(format!("invalid command: {}", e)),
+                    };
+                    if let Some(error) = failure
+                        && let Ok(json) = serde_json::to_string(&ipc::OverlayEvent::Error(error))
+                        && socket.send(Message::Text(json.into())).await.is_err()
+                    {
+                        break;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    crate::debug_log!("[API] WebSocket client disconnected");
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+/// Compare without stopping at the first difference, so timing doesn't leak the token
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
@@ -0,0 +1,146 @@
+//! System appearance from the XDG settings portal
+//!
+//! Reads `org.freedesktop.appearance` (color scheme, accent color, contrast) through
+//! `org.freedesktop.portal.Settings` and follows its `SettingChanged` signal, so the chat
+//! UI can follow dark/light mode. Without a portal the defaults (no preference) are kept.
+
+use gtk4::gio;
+use gtk4::glib;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::rc::Rc;
+use tracing::info;
+
+const PORTAL_BUS_NAME: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const SETTINGS_INTERFACE: &str = "org.freedesktop.portal.Settings";
+const APPEARANCE_NAMESPACE: &str = "org.freedesktop.appearance";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorScheme {
+    #[default]
+    NoPreference,
+    Dark,
+    Light,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Appearance {
+    pub color_scheme: ColorScheme,
+    /// CSS hex color, e.g. "#3584e4"; None when the desktop doesn't set one
+    pub accent_color: Option<String>,
+    pub high_contrast: bool,
+}
+
+impl Appearance {
+    /// Apply one portal setting; returns whether anything changed
+    fn apply(&mut self, key: &str, value: &glib::Variant) -> bool {
+        let before = self.clone();
+        match key {
+            "color-scheme" => {
+                self.color_scheme = match value.get::<u32>() {
+                    Some(1) => ColorScheme::Dark,
+                    Some(2) => ColorScheme::Light,
+                    _ => ColorScheme::NoPreference,
+                };
+            }
+            "accent-color" => {
+                // Components outside 0..=1 mean "no accent color"
+                self.accent_color = value
+                    .get::<(f64, f64, f64)>()
+                    .filter(|(r, g, b)| [r, g, b].iter().all(|c| (0.0..=1.0).contains(*c)))
+                    .map(|(r, g, b)| {
+                        let byte = |c: f64| (c * 255.0).round() as u8;
+                        format!("#{:02x}{:02x}{:02x}", byte(r), byte(g), byte(b))
+                    });
+            }
+            "contrast" => self.high_contrast = value.get::<u32>() == Some(1),
+            _ => {}
+        }
+        *self != before
+    }
+}
+
+/// Read the current appearance into `current` and keep it updated, calling `on_change`
+/// whenever it changes (including once after the initial read if it differs from the
+/// defaults)
+pub fn watch(current: Rc<RefCell<Appearance>>, on_change: Rc<dyn Fn(&Appearance)>) {
+    glib::spawn_future_local(async move {
+        let connection = match gio::bus_get_future(gio::BusType::Session).await {
+            Ok(connection) => connection,
+            Err(e) => {
+                tracing::warn!("No session bus, system appearance unavailable: {}", e);
+                return;
+            }
+        };
+
+        // Subscribe before reading so a change in between isn't missed
+        let current_for_signal = current.clone();
+        let on_change_for_signal = on_change.clone();
+        let subscription = connection.subscribe_to_signal(
+            Some(PORTAL_BUS_NAME),
+            Some(SETTINGS_INTERFACE),
+            Some("SettingChanged"),
+            Some(PORTAL_PATH),
+            Some(APPEARANCE_NAMESPACE),
+            gio::DBusSignalFlags::NONE,
+            move |signal| {
+                let params = signal.parameters;
+                if params.n_children() != 3 {
+                    return;
+                }
+                let Some(key) = params.child_value(1).str().map(str::to_string) else { return };
+                let Some(value) = params.child_value(2).as_variant() else { return };
+                let changed = current_for_signal.borrow_mut().apply(&key, &value);
+                if changed {
+                    let appearance = current_for_signal.borrow().clone();
+                    crate::debug_log!("[APPEARANCE] {} changed: {:?}", key, appearance);
+                    on_change_for_signal(&appearance);
+                }
+            },
+        );
+        // The subscription lasts for the life of the process
+        std::mem::forget(subscription);
+
+        let reply = connection
+            .call_future(
+                Some(PORTAL_BUS_NAME),
+                PORTAL_PATH,
+                SETTINGS_INTERFACE,
+                "ReadAll",
+                Some(&(vec![APPEARANCE_NAMESPACE],).into()),
+                Some(glib::VariantTy::new("(a{sa{sv}})").unwrap()),
+                gio::DBusCallFlags::NONE,
+                5000,
+            )
+            .await;
+        let reply = match reply {
+            Ok(reply) => reply,
+            Err(e) => {
+                info!("Settings portal unavailable, using default appearance: {}", e);
+                return;
+            }
+        };
+
+        let mut changed = false;
+        for namespace in reply.child_value(0).iter() {
+            if namespace.child_value(0).str() != Some(APPEARANCE_NAMESPACE) {
+                continue;
+            }
+            let settings = glib::VariantDict::new(Some(&namespace.child_value(1)));
+            for key in ["color-scheme", "accent-color", "contrast"] {
+                if let Some(value) = settings.lookup_value(key, None) {
+                    changed |= current.borrow_mut().apply(key, &value);
+                }
+            }
+        }
+
+        let appearance = current.borrow().clone();
+        info!("System appearance: {:?}", appearance);
+        if changed {
+            on_change(&appearance);
+        }
+    });
+}
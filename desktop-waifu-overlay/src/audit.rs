@@ -0,0 +1,133 @@
+//! Audit log of what the overlay did on the user's behalf
+//!
+//! Every command (plain, privileged or in a session), file write, notification and tool
+//! call is appended to `~/.local/share/desktop-waifu/audit.jsonl` as one JSON object per
+//! line, with when it happened and how it went, including requests that were refused. The
+//! file is only ever appended to; at 2 MB it moves to `audit.jsonl.1`, and five old files
+//! are kept. The frontend reads it back with `getAuditLog`, newest first.
+
+use crate::logging::RotatingFile;
+use gtk4::glib;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MAX_LOG_BYTES: u64 = 2 * 1024 * 1024;
+const KEPT_LOGS: u32 = 5;
+/// Longest subject or detail kept; commands and tool arguments can be long
+const MAX_FIELD: usize = 4096;
+
+static FILE: LazyLock<Mutex<Option<RotatingFile>>> = LazyLock::new(|| {
+    let file = RotatingFile::open(log_path(), MAX_LOG_BYTES, KEPT_LOGS)
+        .inspect_err(|e| tracing::warn!("Not writing the audit log {}: {}", log_path().display(), e))
+        .ok();
+    Mutex::new(file)
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Kind {
+    Command,
+    PrivilegedCommand,
+    SessionCommand,
+    FileWrite,
+    Notification,
+    ToolCall,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Ok,
+    Failed,
+    /// Blocked by the policy, declined or unconfirmed, or held back (muted notifications)
+    Refused,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    /// Unix time in milliseconds
+    pub time: i64,
+    pub kind: Kind,
+    /// The command, path, notification title or tool call
+    pub subject: String,
+    pub outcome: Outcome,
+    /// Exit code, error or other particulars
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+pub fn log_path() -> PathBuf {
+    glib::user_data_dir().join("desktop-waifu").join("audit.jsonl")
+}
+
+/// Append an entry; failures to write are logged, never passed on
+pub fn record(kind: Kind, subject: &str, outcome: Outcome, detail: Option<String>) {
+    let entry = Entry {
+        time: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or_default(),
+        kind,
+        subject: clip(subject),
+        outcome,
+        detail: detail.as_deref().map(clip),
+    };
+    let Ok(mut line) = serde_json::to_vec(&entry) else { return };
+    line.push(b'\n');
+    let mut file = FILE.lock().unwrap();
+    if let Some(file) = file.as_mut()
+        && let Err(e) = file.write_all(&line)
+    {
+        tracing::warn!("Failed to write the audit log: {}", e);
+    }
+}
+
+/// Record a finished command: ok when it exited with 0
+pub fn exit(kind: Kind, cmd: &str, exit_code: i32) {
+    let outcome = if exit_code == 0 { Outcome::Ok } else { Outcome::Failed };
+    record(kind, cmd, outcome, Some(format!("exit code {}", exit_code)));
+}
+
+/// Record the result of something that either worked or failed with an error
+pub fn result<T, E: std::fmt::Display>(kind: Kind, subject: &str, result: &Result<T, E>) {
+    match result {
+        Ok(_) => record(kind, subject, Outcome::Ok, None),
+        Err(e) => record(kind, subject, Outcome::Failed, Some(e.to_string())),
+    }
+}
+
+/// Up to `limit` entries, newest first, skipping the `offset` newest
+pub fn read(offset: usize, limit: usize) -> Vec<Entry> {
+    let path = log_path();
+    let files = std::iter::once(path.clone()).chain((1..=KEPT_LOGS).map(|n| RotatingFile::rotated_path(&path, n)));
+    let mut entries = Vec::new();
+    for file in files {
+        let Ok(file) = std::fs::File::open(&file) else { break };
+        let mut lines: Vec<Entry> = BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .collect();
+        lines.reverse();
+        entries.extend(lines);
+        if entries.len() >= offset + limit {
+            break;
+        }
+    }
+    entries.into_iter().skip(offset).take(limit).collect()
+}
+
+fn clip(text: &str) -> String {
+    if text.len() <= MAX_FIELD {
+        return text.to_string();
+    }
+    let mut end = MAX_FIELD;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}…", &text[..end])
+}
@@ -0,0 +1,179 @@
+//! Launch on login (`--install-autostart` / `--uninstall-autostart`)
+//!
+//! Two ways to start with the session:
+//! - a systemd user service (`~/.config/systemd/user/desktop-waifu.service`) bound to
+//!   `graphical-session.target`, which also restarts the overlay if it crashes
+//! - an XDG autostart entry (`~/.config/autostart/desktop-waifu.desktop`), for sessions
+//!   that run those (GNOME, KDE, XFCE, ...)
+//!
+//! `auto` picks systemd when a user instance is running. Uninstalling removes both.
+
+use crate::tr;
+use clap::ValueEnum;
+use gtk4::glib;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const SERVICE_NAME: &str = "desktop-waifu.service";
+const DESKTOP_FILE_NAME: &str = "desktop-waifu.desktop";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AutostartMethod {
+    /// systemd if a user instance is running, otherwise XDG autostart
+    Auto,
+    Systemd,
+    Xdg,
+}
+
+fn service_path() -> PathBuf {
+    glib::user_config_dir()
+        .join("systemd")
+        .join("user")
+        .join(SERVICE_NAME)
+}
+
+fn desktop_file_path() -> PathBuf {
+    glib::user_config_dir().join("autostart").join(DESKTOP_FILE_NAME)
+}
+
+/// A user systemd instance answers `systemctl --user`
+fn systemd_available() -> bool {
+    Command::new("systemctl")
+        .args(["--user", "is-system-running"])
+        .output()
+        // "degraded" and "starting" count too; without a user instance it prints nothing
+        // (or "offline" when systemd isn't the init system)
+        .is_ok_and(|out| {
+            let state = String::from_utf8_lossy(&out.stdout);
+            !state.trim().is_empty() && state.trim() != "offline"
+        })
+}
+
+fn systemctl(args: &[&str]) -> anyhow::Result<()> {
+    let output = Command::new("systemctl").arg("--user").args(args).output()?;
+    if !output.status.success() {
+        anyhow::bail!(tr!(
+            "autostart-systemctl-failed",
+            command = args.join(" "),
+            error = String::from_utf8_lossy(&output.stderr).trim().to_string()
+        ));
+    }
+    Ok(())
+}
+
+/// Whether the overlay is running as the systemd user service
+pub fn service_active() -> bool {
+    Command::new("systemctl")
+        .args(["--user", "is-active", "--quiet", SERVICE_NAME])
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Restart the systemd user service, e.g. on a new binary
+pub fn restart_service() -> anyhow::Result<()> {
+    systemctl(&["restart", SERVICE_NAME])
+}
+
+/// Quote an argument for an `Exec=`/`ExecStart=` line if it needs it. Both expand `%`
+/// specifiers, so that is doubled either way.
+fn exec_quote(path: &Path) -> String {
+    let path = path.display().to_string().replace('%', "%%");
+    if path.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+        format!("\"{}\"", path.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        path
+    }
+}
+
+fn write_file(path: &Path, contents: &str) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, contents)?;
+    Ok(())
+}
+
+/// Start the overlay (this executable, with `args`) on login
+pub fn install(method: AutostartMethod, args: &[String]) -> anyhow::Result<()> {
+    let exe = std::env::current_exe()?;
+    let exec = std::iter::once(exec_quote(&exe))
+        .chain(args.iter().map(|arg| exec_quote(Path::new(arg))))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let use_systemd = match method {
+        AutostartMethod::Systemd => true,
+        AutostartMethod::Xdg => false,
+        AutostartMethod::Auto => systemd_available(),
+    };
+
+    if use_systemd {
+        let path = service_path();
+        write_file(
+            &path,
+            &format!(
+                "[Unit]\n\
+                 Description=Desktop Waifu overlay\n\
+                 PartOf=graphical-session.target\n\
+                 After=graphical-session.target\n\
+                 \n\
+                 [Service]\n\
+                 ExecStart={exec}\n\
+                 Restart=on-failure\n\
+                 RestartSec=3\n\
+                 \n\
+                 [Install]\n\
+                 WantedBy=graphical-session.target\n"
+            ),
+        )?;
+        systemctl(&["daemon-reload"])?;
+        systemctl(&["enable", SERVICE_NAME])?;
+        println!("{}", tr!("autostart-installed-systemd", path = path.display().to_string()));
+    } else {
+        let path = desktop_file_path();
+        write_file(
+            &path,
+            &format!(
+                "[Desktop Entry]\n\
+                 Type=Application\n\
+                 Name=Desktop Waifu\n\
+                 Comment=Animated 3D VRM characters with AI-powered conversational chat\n\
+                 Exec={exec}\n\
+                 Icon=desktop-waifu\n\
+                 Terminal=false\n\
+                 X-GNOME-Autostart-enabled=true\n"
+            ),
+        )?;
+        println!("{}", tr!("autostart-installed-xdg", path = path.display().to_string()));
+    }
+    Ok(())
+}
+
+/// Remove the systemd service and the XDG autostart entry, whichever exist
+pub fn uninstall() -> anyhow::Result<()> {
+    let mut removed = false;
+
+    let service = service_path();
+    if service.exists() {
+        // The unit file is removed either way; a stale enable symlink is harmless
+        if let Err(e) = systemctl(&["disable", SERVICE_NAME]) {
+            eprintln!("{}", e);
+        }
+        std::fs::remove_file(&service)?;
+        let _ = systemctl(&["daemon-reload"]);
+        println!("{}", tr!("autostart-removed", path = service.display().to_string()));
+        removed = true;
+    }
+
+    let desktop_file = desktop_file_path();
+    if desktop_file.exists() {
+        std::fs::remove_file(&desktop_file)?;
+        println!("{}", tr!("autostart-removed", path = desktop_file.display().to_string()));
+        removed = true;
+    }
+
+    if !removed {
+        println!("{}", tr!("autostart-not-installed"));
+    }
+    Ok(())
+}
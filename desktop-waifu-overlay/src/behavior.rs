@@ -0,0 +1,205 @@
+//! What the character does on its own
+//!
+//! The scheduler runs on the main loop, so it keeps time while the window is hidden (the
+//! webview's JS timers are throttled then). It follows whether the user is at the computer,
+//! through the compositor's ext-idle-notify protocol, and whether they are busy with the
+//! overlay itself, and from that decides when to:
+//!
+//! - play one of `[behavior] idle_animations` every `animation_interval` seconds or so
+//! - send a `stretchReminder` after `stretch_after` minutes without a break
+//! - send an `idleRemark`, for the frontend to have the character say something unprompted
+//!
+//! All of these wait while the user is away or using the overlay, and reminders and
+//! remarks wait while meeting mode is on. Compositors without ext-idle-notify (GNOME) count
+//! the user as always present.
+
+use crate::config;
+use gtk4::glib;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use wayland_client::globals::{GlobalListContents, registry_queue_init};
+use wayland_client::protocol::{wl_registry, wl_seat};
+use wayland_client::{Connection, Dispatch, QueueHandle};
+use wayland_protocols::ext::idle_notify::v1::client::ext_idle_notification_v1::{self, ExtIdleNotificationV1};
+use wayland_protocols::ext::idle_notify::v1::client::ext_idle_notifier_v1::ExtIdleNotifierV1;
+
+const TICK: Duration = Duration::from_secs(5);
+
+thread_local! {
+    /// When the user went away, while they are
+    static AWAY_SINCE: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+/// Something the scheduler wants the character to do
+#[derive(Debug)]
+pub enum Behavior {
+    /// Play this clip (see animations.rs)
+    Animation(String),
+    /// The user has been at it for `minutes` without a break
+    StretchReminder { minutes: u64 },
+    /// Time for an unprompted remark
+    Remark,
+}
+
+/// How long the user has been away, if they are
+pub fn away_seconds() -> Option<u64> {
+    AWAY_SINCE.with(Cell::get).map(|since| since.elapsed().as_secs())
+}
+
+/// `interval` give or take a quarter, so the character doesn't run like clockwork
+fn jittered(interval: Duration) -> Duration {
+    interval.mul_f64(glib::random_double_range(0.75, 1.25))
+}
+
+struct Schedule {
+    /// Start of the current stretch of activity
+    active_since: Instant,
+    next_animation: Instant,
+    next_remark: Instant,
+}
+
+/// Start the scheduler. `engaged` says whether the user is busy with the overlay (or it's
+/// hidden), which holds off animations and remarks; `on_behavior` carries them out.
+pub fn start(engaged: impl Fn() -> bool + 'static, on_behavior: impl Fn(Behavior) + 'static) {
+    let settings = config::current().behavior.clone();
+    let now = Instant::now();
+    let schedule = Rc::new(RefCell::new(Schedule {
+        active_since: now,
+        next_animation: now + jittered(settings.animation_interval()),
+        next_remark: now + jittered(settings.remark_interval()),
+    }));
+
+    // The compositor tells us when the user has been idle for idle_after, and when they're back
+    let idle_after = settings.idle_after();
+    let presence = spawn_idle_monitor(idle_after);
+    let schedule_for_presence = schedule.clone();
+    glib::spawn_future_local(async move {
+        while let Ok(away) = presence.recv().await {
+            let now = Instant::now();
+            if away {
+                // They stopped idle_after ago
+                AWAY_SINCE.with(|a| a.set(Some(now.checked_sub(idle_after).unwrap_or(now))));
+            } else {
+                AWAY_SINCE.with(|a| a.set(None));
+                // A break restarts the stretch clock
+                schedule_for_presence.borrow_mut().active_since = now;
+            }
+            tracing::info!("User {}", if away { "went idle" } else { "is back" });
+        }
+    });
+
+    glib::timeout_add_local(TICK, move || {
+        if AWAY_SINCE.with(Cell::get).is_some() {
+            return glib::ControlFlow::Continue;
+        }
+        let settings = config::current().behavior.clone();
+        let now = Instant::now();
+        let mut schedule = schedule.borrow_mut();
+        let quiet = crate::presence::is_quiet();
+
+        if let Some(stretch_after) = settings.stretch_after()
+            && now.duration_since(schedule.active_since) >= stretch_after
+            && !quiet
+        {
+            schedule.active_since = now;
+            on_behavior(Behavior::StretchReminder { minutes: stretch_after.as_secs() / 60 });
+            return glib::ControlFlow::Continue;
+        }
+
+        if engaged() {
+            return glib::ControlFlow::Continue;
+        }
+        if now >= schedule.next_animation {
+            schedule.next_animation = now + jittered(settings.animation_interval());
+            let clips: Vec<_> = settings
+                .idle_animations
+                .iter()
+                .filter_map(|name| crate::animations::find(name))
+                .collect();
+            if settings.animation_interval > 0 && !clips.is_empty() {
+                let clip = &clips[glib::random_int_range(0, clips.len() as i32) as usize];
+                on_behavior(Behavior::Animation(clip.name.clone()));
+            }
+        }
+        if now >= schedule.next_remark {
+            schedule.next_remark = now + jittered(settings.remark_interval());
+            if settings.remark_interval > 0 && !quiet {
+                on_behavior(Behavior::Remark);
+            }
+        }
+        glib::ControlFlow::Continue
+    });
+}
+
+/// Watch for the user going idle for `threshold` and coming back (true: idle). The channel
+/// closes without a message if the compositor can't tell.
+fn spawn_idle_monitor(threshold: Duration) -> async_channel::Receiver<bool> {
+    let (tx, rx) = async_channel::unbounded();
+    std::thread::spawn(move || {
+        if let Err(e) = watch_idle(threshold, tx) {
+            tracing::info!("Idle detection unavailable ({}), the user counts as always present", e);
+        }
+    });
+    rx
+}
+
+struct IdleState {
+    tx: async_channel::Sender<bool>,
+}
+
+fn watch_idle(threshold: Duration, tx: async_channel::Sender<bool>) -> Result<(), String> {
+    let connection = Connection::connect_to_env().map_err(|e| e.to_string())?;
+    let (globals, mut queue) = registry_queue_init::<IdleState>(&connection).map_err(|e| e.to_string())?;
+    let handle = queue.handle();
+    let seat: wl_seat::WlSeat = globals.bind(&handle, 1..=1, ()).map_err(|e| e.to_string())?;
+    let notifier: ExtIdleNotifierV1 = globals
+        .bind(&handle, 1..=1, ())
+        .map_err(|_| "the compositor doesn't support ext-idle-notify".to_string())?;
+    let timeout = u32::try_from(threshold.as_millis()).unwrap_or(u32::MAX);
+    let _notification = notifier.get_idle_notification(timeout, &seat, &handle, ());
+
+    let mut state = IdleState { tx };
+    while !state.tx.is_closed() {
+        queue.blocking_dispatch(&mut state).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for IdleState {
+    fn event(_: &mut Self, _: &wl_registry::WlRegistry, _: wl_registry::Event, _: &GlobalListContents, _: &Connection, _: &QueueHandle<Self>) {}
+}
+
+impl Dispatch<wl_seat::WlSeat, ()> for IdleState {
+    fn event(_: &mut Self, _: &wl_seat::WlSeat, _: wl_seat::Event, _: &(), _: &Connection, _: &QueueHandle<Self>) {}
+}
+
+impl Dispatch<ExtIdleNotifierV1, ()> for IdleState {
+    fn event(
+        _: &mut Self,
+        _: &ExtIdleNotifierV1,
+        _: <ExtIdleNotifierV1 as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ExtIdleNotificationV1, ()> for IdleState {
+    fn event(
+        state: &mut Self,
+        _: &ExtIdleNotificationV1,
+        event: ext_idle_notification_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let idle = match event {
+            ext_idle_notification_v1::Event::Idled => true,
+            ext_idle_notification_v1::Event::Resumed => false,
+            _ => return,
+        };
+        let _ = state.tx.send_blocking(idle);
+    }
+}
@@ -0,0 +1,57 @@
+//! Calls from the overlay into the page
+//!
+//! Data never becomes part of the script's source: each call is a fixed function body,
+//! and values go in as its arguments through `call_async_javascript_function`, which
+//! WebKit converts to JS values itself. JSON payloads travel as strings the page parses,
+//! so command output or file contents, whatever bytes they hold, can't run as code.
+
+use gtk4::gio;
+use gtk4::glib;
+use gtk4::prelude::*;
+use webkit6::prelude::*;
+use webkit6::WebView;
+
+/// Run `body` as an async function with `args` as its named arguments; `on_done` runs once
+/// the page has finished with it
+pub fn call(webview: &WebView, body: &str, args: &[(&str, glib::Variant)], on_done: impl FnOnce() + 'static) {
+    let dict = glib::VariantDict::new(None);
+    for (name, value) in args {
+        dict.insert_value(name, value);
+    }
+    webview.call_async_javascript_function(
+        body,
+        Some(&dict.end()),
+        None,
+        None,
+        None::<&gio::Cancellable>,
+        move |result| {
+            if let Err(e) = result {
+                crate::debug_log!("[BRIDGE] Page call failed: {}", e);
+            }
+            on_done();
+        },
+    );
+}
+
+/// Dispatch a CustomEvent on the frontend window with a JSON detail payload
+pub fn dispatch_event(webview: &WebView, name: &str, detail: &serde_json::Value) {
+    call(
+        webview,
+        "window.dispatchEvent(new CustomEvent(name, { detail: JSON.parse(detail) }))",
+        &[("name", name.to_variant()), ("detail", detail.to_string().to_variant())],
+        || {},
+    );
+}
+
+/// Resolve a frontend callback registered in `window.__commandCallbacks` with a JSON payload
+pub fn dispatch_callback(webview: &WebView, callback_id: &str, payload: &serde_json::Value) {
+    if callback_id.is_empty() {
+        return;
+    }
+    call(
+        webview,
+        "window.__commandCallbacks?.[id]?.(JSON.parse(payload))",
+        &[("id", callback_id.to_variant()), ("payload", payload.to_string().to_variant())],
+        || {},
+    );
+}
@@ -0,0 +1,236 @@
+//! Supervised companion processes
+//!
+//! Companions are helper executables registered in `~/.config/desktop-waifu/companions.toml`.
+//! The overlay spawns each one and speaks newline-delimited JSON over its stdio:
+//! `OverlayEvent`s are written to the companion's stdin and every line it prints on stdout
+//! is parsed as an `OverlayCommand`. Stderr is forwarded to the log. Companions that exit
+//! are restarted with exponential backoff according to their restart policy.
+//!
+//! ```toml
+//! [[companion]]
+//! name = "obs-bridge"
+//! command = "/usr/local/bin/waifu-obs-bridge"
+//! args = ["--port", "4455"]
+//! restart = "on-failure"   # "always" | "on-failure" | "never"
+//! ```
+
+use crate::ipc::{OverlayCommand, OverlayEvent};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::Command;
+use tracing::info;
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+// A companion that ran at least this long is considered healthy and its backoff resets
+const STABLE_RUNTIME: Duration = Duration::from_secs(60);
+// Time a companion gets to exit after its stdin closes on shutdown
+const EXIT_GRACE: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    Always,
+    #[default]
+    OnFailure,
+    Never,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CompanionConfig {
+    pub name: String,
+    pub command: PathBuf,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub restart: RestartPolicy,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CompanionsFile {
+    #[serde(default, rename = "companion")]
+    companions: Vec<CompanionConfig>,
+}
+
+/// Running companions (cheap to clone)
+#[derive(Clone, Default)]
+pub struct CompanionManager {
+    // Lines queued for each companion's stdin writer task
+    stdins: Arc<Mutex<HashMap<String, async_channel::Sender<String>>>>,
+}
+
+fn config_path() -> PathBuf {
+    gtk4::glib::user_config_dir()
+        .join("desktop-waifu")
+        .join("companions.toml")
+}
+
+fn load_config() -> Vec<CompanionConfig> {
+    let Ok(text) = std::fs::read_to_string(config_path()) else {
+        return Vec::new();
+    };
+    match toml::from_str::<CompanionsFile>(&text) {
+        Ok(file) => file.companions,
+        Err(e) => {
+            tracing::warn!("Invalid companions.toml: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Start every configured companion under supervision on the shared runtime
+/// Returns the manager plus a receiver of (companion name, command) pairs
+pub fn spawn_companions() -> (CompanionManager, async_channel::Receiver<(String, OverlayCommand)>) {
+    let (tx, rx) = async_channel::unbounded();
+    let manager = CompanionManager::default();
+
+    for config in load_config() {
+        let manager = manager.clone();
+        let tx = tx.clone();
+        crate::runtime::spawn(async move { manager.supervise(config, tx).await });
+    }
+
+    (manager, rx)
+}
+
+impl CompanionManager {
+    /// Send an event to every running companion
+    pub fn broadcast(&self, event: &OverlayEvent) {
+        let Ok(mut line) = serde_json::to_string(event) else { return };
+        line.push('\n');
+
+        let mut stdins = self.stdins.lock().unwrap();
+        stdins.retain(|name, stdin| match stdin.try_send(line.clone()) {
+            Ok(()) => true,
+            Err(e) => {
+                crate::debug_log!("[COMPANION] Dropping stdin of '{}': {}", name, e);
+                false
+            }
+        });
+    }
+
+    /// Run a companion, restarting it per its policy until shutdown
+    async fn supervise(&self, config: CompanionConfig, tx: async_channel::Sender<(String, OverlayCommand)>) {
+        let shutdown = crate::runtime::shutdown_token();
+        let mut backoff = INITIAL_BACKOFF;
+
+        while !shutdown.is_cancelled() {
+            let started = Instant::now();
+            let success = match self.run_once(&config, &tx).await {
+                Ok(success) => success,
+                Err(e) => {
+                    tracing::warn!("Companion '{}' failed to start: {}", config.name, e);
+                    false
+                }
+            };
+
+            let restart = match config.restart {
+                RestartPolicy::Always => true,
+                RestartPolicy::OnFailure => !success,
+                RestartPolicy::Never => false,
+            };
+            if !restart || shutdown.is_cancelled() {
+                break;
+            }
+
+            if started.elapsed() >= STABLE_RUNTIME {
+                backoff = INITIAL_BACKOFF;
+            }
+            info!("Restarting companion '{}' in {:?}", config.name, backoff);
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = tokio::time::sleep(backoff) => {}
+            }
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+        info!("Companion '{}' supervisor stopped", config.name);
+    }
+
+    /// Spawn the companion once and pump its stdio until it exits or the overlay shuts down.
+    /// Returns whether it exited successfully.
+    async fn run_once(&self, config: &CompanionConfig, tx: &async_channel::Sender<(String, OverlayCommand)>) -> std::io::Result<bool> {
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        info!("Started companion '{}' (pid {:?})", config.name, child.id());
+
+        if let Some(mut stdin) = child.stdin.take() {
+            let (line_tx, line_rx) = async_channel::unbounded::<String>();
+            // Greet the companion so it knows the protocol is live
+            if let Ok(ready) = serde_json::to_string(&OverlayEvent::Ready) {
+                let _ = line_tx.try_send(format!("{}\n", ready));
+            }
+            self.stdins.lock().unwrap().insert(config.name.clone(), line_tx);
+            crate::runtime::spawn(async move {
+                while let Ok(line) = line_rx.recv().await {
+                    if stdin.write_all(line.as_bytes()).await.is_err() || stdin.flush().await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        if let Some(stderr) = child.stderr.take() {
+            let name = config.name.clone();
+            crate::runtime::spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    info!("[companion {}] {}", name, line);
+                }
+            });
+        }
+
+        if let Some(stdout) = child.stdout.take() {
+            let name = config.name.clone();
+            let tx = tx.clone();
+            crate::runtime::spawn(async move {
+                let mut lines = BufReader::new(stdout).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let line = line.trim();
+                    if line.is_empty() {
+                        continue;
+                    }
+                    match serde_json::from_str::<OverlayCommand>(line) {
+                        Ok(cmd) => {
+                            crate::debug_log!("[COMPANION] '{}' sent {:?}", name, cmd);
+                            if tx.send((name.clone(), cmd)).await.is_err() {
+                                break;
+                            }
+                        }
+                        Err(e) => tracing::warn!("Companion '{}' sent invalid command: {}", name, e),
+                    }
+                }
+            });
+        }
+
+        let shutdown = crate::runtime::shutdown_token();
+        let status = tokio::select! {
+            status = child.wait() => status?,
+            _ = shutdown.cancelled() => {
+                // Closing stdin asks the companion to exit; kill it if it lingers
+                self.stdins.lock().unwrap().remove(&config.name);
+                match tokio::time::timeout(EXIT_GRACE, child.wait()).await {
+                    Ok(status) => status?,
+                    Err(_) => {
+                        tracing::warn!("Companion '{}' ignored shutdown, killing it", config.name);
+                        child.kill().await?;
+                        child.wait().await?
+                    }
+                }
+            }
+        };
+
+        self.stdins.lock().unwrap().remove(&config.name);
+        info!("Companion '{}' exited with {}", config.name, status);
+        Ok(status.success())
+    }
+}
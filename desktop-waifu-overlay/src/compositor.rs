@@ -0,0 +1,408 @@
+//! Compositor integration (Hyprland and Sway)
+//!
+//! Wayland gives clients no view of other windows, but Hyprland and Sway answer over IPC
+//! sockets: Hyprland's request and event sockets in `$XDG_RUNTIME_DIR/hypr/<instance>/`,
+//! Sway's i3-ipc socket at `$SWAYSOCK`. The overlay subscribes to their window and
+//! workspace events and after each one asks for the focused window, the focused workspace
+//! and which outputs show a fullscreen window. The frontend gets this as `desktopChanged`
+//! and tells the assistant which app the user is in (see `[compositor]` in config.toml for
+//! what's shared). On other compositors there's nothing to ask and the integration stays off.
+//!
+//! A fullscreen window on the character's monitor (a game, a video, a presentation) also
+//! sends the overlay down to the bottom layer until it leaves fullscreen, unless
+//! `hide_on_fullscreen` is off. Walking around (walk.rs) asks where the windows on the
+//! character's monitor are, to climb onto them.
+
+use gtk4::prelude::*;
+use gtk4::ApplicationWindow;
+use gtk4_layer_shell::{Layer, LayerShell as _};
+use serde::Serialize;
+use serde_json::Value;
+use std::cell::Cell;
+use std::io;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::UnixStream;
+use tracing::info;
+
+/// i3-ipc message types
+const SWAY_GET_WORKSPACES: u32 = 1;
+const SWAY_SUBSCRIBE: u32 = 2;
+const SWAY_GET_TREE: u32 = 4;
+const SWAY_MAGIC: &[u8] = b"i3-ipc";
+
+thread_local! {
+    /// Whether the overlay is lowered for a fullscreen window on its monitor
+    static UNDER_FULLSCREEN: Cell<bool> = const { Cell::new(false) };
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Desktop {
+    /// "hyprland" or "sway"
+    pub compositor: &'static str,
+    /// Name of the focused workspace
+    pub workspace: Option<String>,
+    /// App ID or window class of the focused window
+    pub focused_app: Option<String>,
+    pub focused_title: Option<String>,
+    /// Connector names of the outputs showing a fullscreen window, e.g. "DP-1"
+    pub fullscreen_outputs: Vec<String>,
+}
+
+impl Desktop {
+    /// What the frontend may pass on to the assistant, per `[compositor]` in config.toml
+    pub fn shared(&self, config: &crate::config::CompositorConfig) -> Desktop {
+        let mut desktop = self.clone();
+        if !config.share_focused_app {
+            desktop.focused_app = None;
+        }
+        if !config.share_focused_app || !config.share_window_title {
+            desktop.focused_title = None;
+        }
+        desktop
+    }
+}
+
+/// Top edge of a window, where the character can sit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ledge {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+}
+
+/// Layer to return the overlay to after a dialog or screenshot lowered it: the configured
+/// one, or the bottom layer while it is out of the way of a fullscreen window
+pub fn resting_layer() -> Layer {
+    if UNDER_FULLSCREEN.get() {
+        Layer::Bottom
+    } else {
+        crate::config::current().window.layer.layer()
+    }
+}
+
+/// Lower the overlay while `desktop` shows a fullscreen window on the monitor the
+/// character is on, and raise it again once there is none
+pub fn follow_fullscreen(window: &ApplicationWindow, desktop: &Desktop) {
+    let covered = crate::config::current().compositor.hide_on_fullscreen
+        && crate::monitors::current_monitor(window)
+            .and_then(|monitor| monitor.connector())
+            .is_some_and(|name| desktop.fullscreen_outputs.iter().any(|output| output.as_str() == name.as_str()));
+    set_under_fullscreen(window, covered);
+}
+
+/// Raise the overlay if it was lowered for a fullscreen window, e.g. when the setting is
+/// turned off
+pub fn set_under_fullscreen(window: &ApplicationWindow, covered: bool) {
+    if UNDER_FULLSCREEN.replace(covered) == covered {
+        return;
+    }
+    crate::debug_log!("[COMPOSITOR] {} for a fullscreen window", if covered { "Lowering" } else { "Raising" });
+    window.set_layer(resting_layer());
+}
+
+#[derive(Debug, Clone)]
+enum Compositor {
+    /// Directory holding `.socket.sock` and `.socket2.sock`
+    Hyprland(PathBuf),
+    /// The i3-ipc socket
+    Sway(PathBuf),
+}
+
+fn detect() -> Option<Compositor> {
+    if let Ok(instance) = std::env::var("HYPRLAND_INSTANCE_SIGNATURE") {
+        // Hyprland 0.40 moved the sockets from /tmp/hypr to the runtime directory
+        let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from);
+        return runtime_dir
+            .map(|dir| dir.join("hypr").join(&instance))
+            .into_iter()
+            .chain([PathBuf::from("/tmp/hypr").join(&instance)])
+            .find(|dir| dir.join(".socket2.sock").exists())
+            .map(Compositor::Hyprland);
+    }
+    std::env::var_os("SWAYSOCK").map(|path| Compositor::Sway(PathBuf::from(path)))
+}
+
+/// Start following the compositor; yields the desktop state each time it changes. None on
+/// compositors without a supported IPC.
+pub fn spawn_monitor() -> Option<async_channel::Receiver<Desktop>> {
+    let compositor = detect()?;
+    info!("Compositor integration: {:?}", compositor);
+    let (tx, rx) = async_channel::unbounded();
+    let shutdown = crate::runtime::shutdown_token();
+
+    crate::runtime::spawn(async move {
+        let result = tokio::select! {
+            _ = shutdown.cancelled() => Ok(()),
+            result = watch(&compositor, &tx) => result,
+        };
+        if let Err(e) = result {
+            info!("Compositor integration stopped: {}", e);
+        }
+    });
+
+    Some(rx)
+}
+
+/// The focused window, workspace and fullscreen outputs right now (`getActiveWindow`).
+/// None on compositors without a supported IPC.
+pub async fn active_window() -> io::Result<Option<Desktop>> {
+    match detect() {
+        Some(Compositor::Hyprland(dir)) => hyprland_snapshot(&dir).await.map(Some),
+        Some(Compositor::Sway(path)) => sway_snapshot(&path).await.map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Top edges of the windows on output `connector`'s visible workspace, relative to the
+/// part of the output panels leave with `work_area`, else to the whole output (the
+/// overlay's surface either way). Empty on compositors without a supported IPC.
+pub async fn ledges(connector: &str, work_area: bool) -> io::Result<Vec<Ledge>> {
+    match detect() {
+        Some(Compositor::Hyprland(dir)) => hyprland_ledges(&dir, connector, work_area).await,
+        Some(Compositor::Sway(path)) => sway_ledges(&path, connector, work_area).await,
+        None => Ok(Vec::new()),
+    }
+}
+
+async fn watch(compositor: &Compositor, tx: &async_channel::Sender<Desktop>) -> io::Result<()> {
+    let mut last = None;
+    match compositor {
+        Compositor::Hyprland(dir) => {
+            let events = UnixStream::connect(dir.join(".socket2.sock")).await?;
+            let mut lines = BufReader::new(events).lines();
+            loop {
+                send_if_changed(hyprland_snapshot(dir).await?, &mut last, tx).await?;
+                // One "event>>data" line per event; any of them may have changed something
+                if lines.next_line().await?.is_none() {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Hyprland closed the event socket"));
+                }
+            }
+        }
+        Compositor::Sway(path) => {
+            let mut events = UnixStream::connect(path).await?;
+            sway_send(&mut events, SWAY_SUBSCRIBE, r#"["window","workspace","output"]"#).await?;
+            let reply = sway_receive(&mut events).await?;
+            if reply["success"] != true {
+                return Err(io::Error::other("Sway refused the event subscription"));
+            }
+            loop {
+                send_if_changed(sway_snapshot(path).await?, &mut last, tx).await?;
+                sway_receive(&mut events).await?;
+            }
+        }
+    }
+}
+
+async fn send_if_changed(
+    desktop: Desktop,
+    last: &mut Option<Desktop>,
+    tx: &async_channel::Sender<Desktop>,
+) -> io::Result<()> {
+    if last.as_ref() == Some(&desktop) {
+        return Ok(());
+    }
+    crate::debug_log!("[COMPOSITOR] {:?}", desktop);
+    *last = Some(desktop.clone());
+    tx.send(desktop)
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "nobody is listening"))
+}
+
+/// Send `command` (e.g. "j/monitors") to Hyprland and parse the JSON reply
+async fn hyprland_request(dir: &Path, command: &str) -> io::Result<Value> {
+    let mut stream = UnixStream::connect(dir.join(".socket.sock")).await?;
+    stream.write_all(command.as_bytes()).await?;
+    let mut reply = Vec::new();
+    stream.read_to_end(&mut reply).await?;
+    serde_json::from_slice(&reply).map_err(io::Error::other)
+}
+
+async fn hyprland_snapshot(dir: &Path) -> io::Result<Desktop> {
+    let window = hyprland_request(dir, "j/activewindow").await?;
+    let monitors = hyprland_request(dir, "j/monitors").await?;
+    let workspaces = hyprland_request(dir, "j/workspaces").await?;
+
+    let has_fullscreen = |id: &Value| {
+        id.as_i64().is_some_and(|id| id != 0)
+            && workspaces
+                .as_array()
+                .into_iter()
+                .flatten()
+                .any(|ws| &ws["id"] == id && ws["hasfullscreen"] == true)
+    };
+    let monitors = monitors.as_array().cloned().unwrap_or_default();
+    let fullscreen_outputs = monitors
+        .iter()
+        .filter(|m| has_fullscreen(&m["activeWorkspace"]["id"]) || has_fullscreen(&m["specialWorkspace"]["id"]))
+        .filter_map(|m| m["name"].as_str().map(str::to_string))
+        .collect();
+    let workspace = monitors
+        .iter()
+        .find(|m| m["focused"] == true)
+        .and_then(|m| m["activeWorkspace"]["name"].as_str())
+        .map(str::to_string);
+
+    Ok(Desktop {
+        compositor: "hyprland",
+        workspace,
+        focused_app: non_empty(&window["class"]),
+        focused_title: non_empty(&window["title"]),
+        fullscreen_outputs,
+    })
+}
+
+async fn hyprland_ledges(dir: &Path, connector: &str, work_area: bool) -> io::Result<Vec<Ledge>> {
+    let monitors = hyprland_request(dir, "j/monitors").await?;
+    let Some(monitor) = monitors.as_array().into_iter().flatten().find(|m| m["name"] == connector) else {
+        return Ok(Vec::new());
+    };
+    // Reserved space is [left, top, right, bottom]; positions are in layout pixels
+    let reserved = |side: usize| if work_area { monitor["reserved"][side].as_i64().unwrap_or(0) } else { 0 };
+    let origin_x = monitor["x"].as_i64().unwrap_or(0) + reserved(0);
+    let origin_y = monitor["y"].as_i64().unwrap_or(0) + reserved(1);
+    let workspace = &monitor["activeWorkspace"]["id"];
+
+    let clients = hyprland_request(dir, "j/clients").await?;
+    Ok(clients
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|client| &client["workspace"]["id"] == workspace && client["mapped"] == true && client["hidden"] != true)
+        .filter_map(|client| {
+            Some(Ledge {
+                x: (client["at"][0].as_i64()? - origin_x) as i32,
+                y: (client["at"][1].as_i64()? - origin_y) as i32,
+                width: client["size"][0].as_i64()? as i32,
+            })
+        })
+        .collect())
+}
+
+/// Write an i3-ipc message: magic, payload length, type, payload (native byte order)
+async fn sway_send(stream: &mut UnixStream, kind: u32, payload: &str) -> io::Result<()> {
+    let mut message = SWAY_MAGIC.to_vec();
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&kind.to_ne_bytes());
+    message.extend_from_slice(payload.as_bytes());
+    stream.write_all(&message).await
+}
+
+/// Read one i3-ipc reply or event and parse its payload
+async fn sway_receive(stream: &mut UnixStream) -> io::Result<Value> {
+    let mut header = [0u8; 14];
+    stream.read_exact(&mut header).await?;
+    if &header[..6] != SWAY_MAGIC {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not an i3-ipc message"));
+    }
+    let length = u32::from_ne_bytes(header[6..10].try_into().unwrap()) as usize;
+    let mut payload = vec![0u8; length];
+    stream.read_exact(&mut payload).await?;
+    serde_json::from_slice(&payload).map_err(io::Error::other)
+}
+
+async fn sway_request(path: &Path, kind: u32) -> io::Result<Value> {
+    let mut stream = UnixStream::connect(path).await?;
+    sway_send(&mut stream, kind, "").await?;
+    sway_receive(&mut stream).await
+}
+
+async fn sway_snapshot(path: &Path) -> io::Result<Desktop> {
+    let workspaces = sway_request(path, SWAY_GET_WORKSPACES).await?;
+    let tree = sway_request(path, SWAY_GET_TREE).await?;
+
+    let workspaces = workspaces.as_array().cloned().unwrap_or_default();
+    let visible: Vec<&str> = workspaces
+        .iter()
+        .filter(|ws| ws["visible"] == true)
+        .filter_map(|ws| ws["name"].as_str())
+        .collect();
+    let mut desktop = Desktop {
+        compositor: "sway",
+        workspace: workspaces
+            .iter()
+            .find(|ws| ws["focused"] == true)
+            .and_then(|ws| ws["name"].as_str())
+            .map(str::to_string),
+        ..Default::default()
+    };
+    visit_sway_node(&tree, None, false, &visible, &mut desktop);
+    desktop.fullscreen_outputs.dedup();
+    Ok(desktop)
+}
+
+async fn sway_ledges(path: &Path, connector: &str, work_area: bool) -> io::Result<Vec<Ledge>> {
+    let tree = sway_request(path, SWAY_GET_TREE).await?;
+    let Some(output) = tree["nodes"].as_array().into_iter().flatten().find(|node| node["name"] == connector) else {
+        return Ok(Vec::new());
+    };
+    let Some(workspace) = output["nodes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|node| node["name"] == output["current_workspace"])
+    else {
+        return Ok(Vec::new());
+    };
+    // A workspace covers what the output's panels leave
+    let origin = if work_area { &workspace["rect"] } else { &output["rect"] };
+    let origin_x = origin["x"].as_i64().unwrap_or(0);
+    let origin_y = origin["y"].as_i64().unwrap_or(0);
+
+    let mut ledges = Vec::new();
+    collect_sway_ledges(workspace, origin_x, origin_y, &mut ledges);
+    Ok(ledges)
+}
+
+/// Add the visible windows under `node` to `ledges`
+fn collect_sway_ledges(node: &Value, origin_x: i64, origin_y: i64, ledges: &mut Vec<Ledge>) {
+    if node["visible"] == true
+        && let (Some(x), Some(y), Some(width)) =
+            (node["rect"]["x"].as_i64(), node["rect"]["y"].as_i64(), node["rect"]["width"].as_i64())
+    {
+        ledges.push(Ledge { x: (x - origin_x) as i32, y: (y - origin_y) as i32, width: width as i32 });
+    }
+    for child in ["nodes", "floating_nodes"]
+        .iter()
+        .filter_map(|key| node[*key].as_array())
+        .flatten()
+    {
+        collect_sway_ledges(child, origin_x, origin_y, ledges);
+    }
+}
+
+/// Walk Sway's layout tree for the focused window and fullscreen windows on visible workspaces
+fn visit_sway_node(node: &Value, output: Option<&str>, on_visible: bool, visible: &[&str], desktop: &mut Desktop) {
+    let name = node["name"].as_str();
+    let (output, on_visible) = match node["type"].as_str() {
+        Some("output") => (name, on_visible),
+        Some("workspace") => (output, name.is_some_and(|name| visible.contains(&name))),
+        _ => (output, on_visible),
+    };
+
+    if matches!(node["type"].as_str(), Some("con" | "floating_con")) {
+        if node["focused"] == true {
+            desktop.focused_app = non_empty(&node["app_id"]).or_else(|| non_empty(&node["window_properties"]["class"]));
+            desktop.focused_title = non_empty(&node["name"]);
+        }
+        // 1: fullscreen on its output, 2: across all outputs
+        if on_visible && node["fullscreen_mode"].as_i64().is_some_and(|mode| mode > 0) {
+            if let Some(output) = output {
+                desktop.fullscreen_outputs.push(output.to_string());
+            }
+        }
+    }
+
+    for child in ["nodes", "floating_nodes"]
+        .iter()
+        .filter_map(|key| node[*key].as_array())
+        .flatten()
+    {
+        visit_sway_node(child, output, on_visible, visible, desktop);
+    }
+}
+
+fn non_empty(value: &Value) -> Option<String> {
+    value.as_str().filter(|s| !s.is_empty()).map(str::to_string)
+}
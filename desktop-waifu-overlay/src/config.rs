@@ -0,0 +1,872 @@
+//! Overlay configuration (`~/.config/desktop-waifu/config.toml`)
+//!
+//! Window sizes, layer-shell behavior and a few startup settings. Every key is optional;
+//! these are the defaults:
+//!
+//! ```toml
+//! debug_logging = false    # like --debug
+//! # Like --strict-hotkeys: while the Global Hotkey setting is off, ignore --show, --hide
+//! # and JSON clients as well as --toggle. Read on every command; the flag forces it on
+//! strict_hotkeys = false
+//! # Vite dev server to try before serving the built frontend
+//! dev_server_port = 1420
+//! # Port for the built frontend; localStorage is per origin, so keep it stable
+//! static_server_port = 1421
+//!
+//! [window]
+//! collapsed_width = 160    # character only
+//! collapsed_height = 380
+//! expanded_width = 800     # chat + character
+//! expanded_height = 1000
+//! # Character size as a multiple of the collapsed size (0.5 to 2), until one is set from
+//! # the settings, the tray or IPC
+//! scale = 1.0
+//! layer = "overlay"        # "top" stays below fullscreen windows
+//! # Keep the character and the chat off the edges panels and docks reserve (waybar, KDE's
+//! # panel); false covers the whole monitor
+//! respect_panels = true
+//! keyboard_mode = "on_demand"  # "none": only take keyboard focus while typing in the page
+//! # Where the chat opens: "follow-character" (beside it), "left-panel" or "right-panel"
+//! # (against that screen edge, full height) or "floating" (mid-screen); see layout.rs
+//! chat_dock = "follow-character"
+//!
+//! [position]
+//! # Where the character starts when there is no saved position
+//! corner = "bottom-right"
+//! margin = 20
+//! # A drag that ends this close to a screen edge slides the character onto it (at
+//! # `margin`), two edges in a corner; 0 turns snapping off
+//! snap_distance = 32
+//!
+//! [tts]
+//! engine = "auto"          # "piper", "espeak" or "cloud"; auto: Piper if a voice is installed
+//! # Piper: model name in ~/.local/share/desktop-waifu/voices or a path to an .onnx file;
+//! # espeak-ng: voice such as "en-us"; cloud: voice such as "alloy". Unset: the first found
+//! # voice = "en_US-amy-medium"
+//! rate = 1.0
+//!
+//! [tts.cloud]              # any OpenAI-compatible speech endpoint
+//! url = "https://api.openai.com/v1/audio/speech"
+//! model = "tts-1"
+//! api_key_env = "OPENAI_API_KEY"  # environment variable holding the key
+//!
+//! [stt]                    # speech input, in builds with the `stt` feature
+//! # Whisper model: name in ~/.local/share/desktop-waifu/models (ggml-<name>.bin) or a path
+//! model = "base.en"
+//! language = "auto"        # or a code such as "en"
+//! pause_ms = 800           # silence that ends an utterance
+//!
+//! [llm]
+//! # OpenAI-compatible API of a local model server for the "local" provider. Unset: use
+//! # Ollama (port 11434) or llama.cpp's server (port 8080), whichever is running
+//! # local_url = "http://127.0.0.1:11434/v1"
+//!
+//! [memory]                 # long-term memories about the user
+//! enabled = true           # add relevant memories to each chat request
+//! # Where embeddings come from: "local", "openai" or "none" (keyword matching only);
+//! # auto: the local model server if it has the model, otherwise OpenAI if a key is set
+//! embeddings = "auto"
+//! # Unset: nomic-embed-text on a local server, text-embedding-3-small on OpenAI
+//! # model = "nomic-embed-text"
+//! recall_count = 5         # memories added per request, at most
+//!
+//! [files]                  # what readFile, listDir, statPath and watchPath may reach
+//! allowed_dirs = []        # e.g. ["~/projects"]; added to when the user allows a directory
+//!
+//! [behavior]               # what the character does on its own (see behavior.rs)
+//! idle_after = 300         # seconds without input before the user counts as away
+//! animation_interval = 90  # seconds between idle animations, roughly; 0: none
+//! idle_animations = ["arm_stretching", "female_standing_pose", "dwarf_idle"]
+//! stretch_after = 50       # minutes without a break before a stretch reminder; 0: none
+//! remark_interval = 0      # minutes between unprompted remarks (LLM requests); 0: none
+//!
+//! [compositor]             # Hyprland and Sway only (see compositor.rs)
+//! share_focused_app = true     # tell the assistant which app the user is in
+//! share_window_title = false   # and the focused window's title
+//! hide_on_fullscreen = true    # drop below fullscreen windows on the character's monitor
+//!
+//! [api]                    # REST control API on the static server (see api.rs)
+//! enabled = false
+//!
+//! [sound]                  # chimes and sound effects (see sound.rs)
+//! enabled = true
+//! volume = 1.0             # scales every sound's own volume
+//!
+//! [confirm]                # who approves commands, tool calls and file writes (see confirm.rs)
+//! dialog = false           # true: a native dialog, whatever the chat says
+//!
+//! [frontend]               # frontend updates without a new package (see frontend.rs)
+//! # A release's asset-manifest.json, with the files and its signature next to it; https
+//! # only. Unset: no updates
+//! # update_url = "https://example.com/desktop-waifu/frontend/asset-manifest.json"
+//!
+//! [keys]                   # shortcuts while the overlay has the keyboard (see keys.rs)
+//! # GTK accelerator syntax; "" turns one off
+//! close_chat = "Escape"
+//! focus_input = "<Control>l"
+//! devtools = "<Control><Shift>i"
+//! click_through = "<Control><Shift>x"
+//!
+//! [expressions]            # faces the overlay sets itself (see expressions.rs)
+//! enabled = true           # false: only SetExpression changes the face
+//! thinking = "relaxed"     # waiting for the LLM; "" leaves the face alone
+//! working = "surprised"    # a command has been running for `working_after` seconds
+//! working_after = 5
+//! success = "happy"        # for `flash` seconds after a command succeeds
+//! error = "sad"            # ... or fails
+//! flash = 3
+//! hold = 10                # seconds SetExpression holds a face without a duration
+//!
+//! [walk]                   # wandering about on its own (see walk.rs); also in the tray
+//! enabled = false
+//! speed = 80               # pixels per second
+//! interval = 45            # seconds between walks, on average
+//! # On Hyprland and Sway, sometimes climb onto the top of a window instead
+//! perch_on_windows = true
+//!
+//! [render]                 # how WebKit draws the character (see render.rs)
+//! # "auto" picks from the GPU and driver; "gpu", "no-dmabuf" (NVIDIA) or "software"
+//! mode = "auto"
+//!
+//! [webkit]                 # levers on WebKit's memory use; the 3D character can take >1 GB
+//! # "always", "never" (no WebGL, so no character), or "auto": on unless render.rs
+//! # found no EGL
+//! hardware_acceleration = "auto"
+//! # How much WebKit caches: "web-browser", "document-browser" or "document-viewer" (least).
+//! # WebKitGTK 6 always gives each page its own web process, so this is the process-level
+//! # setting left.
+//! cache_model = "web-browser"
+//! # Memory a web process may use, in MB, before WebKit frees caches (at the conservative
+//! # and strict thresholds, fractions of the limit) and, past the kill threshold, ends it.
+//! # The overlay then reloads the page. No limit: WebKit's default, the machine's memory.
+//! # memory_limit = 1024
+//! conservative_threshold = 0.33
+//! strict_threshold = 0.5
+//! kill_threshold = 0       # 0 never ends the process
+//! ```
+//!
+//! Edits apply while the overlay runs, except the ports, `idle_after`, `[render]` and
+//! `[webkit]`, which are only read at startup.
+//! The overlay itself only writes `[files] allowed_dirs` (see `update`), keeping the rest of
+//! the file as it was.
+//! The frontend gets the window settings through `getOverlayConfig` and `configChanged`.
+
+use gtk4::gio;
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4_layer_shell::{KeyboardMode, Layer};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::{Arc, LazyLock, RwLock};
+use std::time::Duration;
+
+static CONFIG: LazyLock<RwLock<Arc<Config>>> = LazyLock::new(Default::default);
+
+thread_local! {
+    static MONITOR: RefCell<Option<gio::FileMonitor>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, rename_all(serialize = "camelCase"))]
+pub struct Config {
+    pub debug_logging: bool,
+    pub strict_hotkeys: bool,
+    pub dev_server_port: u16,
+    pub static_server_port: u16,
+    pub window: WindowConfig,
+    pub position: PositionConfig,
+    pub tts: TtsConfig,
+    pub stt: SttConfig,
+    pub llm: LlmConfig,
+    pub memory: MemoryConfig,
+    pub files: FilesConfig,
+    pub behavior: BehaviorConfig,
+    pub compositor: CompositorConfig,
+    pub api: ApiConfig,
+    pub sound: SoundConfig,
+    pub confirm: ConfirmConfig,
+    pub frontend: FrontendConfig,
+    pub keys: KeysConfig,
+    pub expressions: ExpressionsConfig,
+    pub walk: WalkConfig,
+    pub render: RenderConfig,
+    pub webkit: WebkitConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            debug_logging: false,
+            strict_hotkeys: false,
+            dev_server_port: 1420,
+            static_server_port: 1421,
+            window: WindowConfig::default(),
+            position: PositionConfig::default(),
+            tts: TtsConfig::default(),
+            stt: SttConfig::default(),
+            llm: LlmConfig::default(),
+            memory: MemoryConfig::default(),
+            files: FilesConfig::default(),
+            behavior: BehaviorConfig::default(),
+            compositor: CompositorConfig::default(),
+            api: ApiConfig::default(),
+            sound: SoundConfig::default(),
+            confirm: ConfirmConfig::default(),
+            frontend: FrontendConfig::default(),
+            keys: KeysConfig::default(),
+            expressions: ExpressionsConfig::default(),
+            walk: WalkConfig::default(),
+            render: RenderConfig::default(),
+            webkit: WebkitConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, rename_all(serialize = "camelCase"))]
+pub struct WindowConfig {
+    /// Character only
+    pub collapsed_width: i32,
+    pub collapsed_height: i32,
+    /// Chat + character
+    pub expanded_width: i32,
+    pub expanded_height: i32,
+    /// Character scale until one is set from the settings, the tray or IPC
+    pub scale: f32,
+    pub layer: WindowLayer,
+    /// Stay out of other surfaces' exclusive zones
+    pub respect_panels: bool,
+    pub keyboard_mode: IdleKeyboardMode,
+    pub chat_dock: ChatDock,
+}
+
+impl WindowConfig {
+    /// Size of the character's box at `scale`: the collapsed size, scaled like the frontend does
+    pub fn character_size(&self, scale: f32) -> (i32, i32) {
+        (
+            (self.collapsed_width as f32 * scale).round() as i32,
+            (self.collapsed_height as f32 * scale).round() as i32,
+        )
+    }
+
+    /// Layer-shell exclusive zone for the overlay: 0 has the compositor fit it between
+    /// panels, -1 covers them
+    pub fn exclusive_zone(&self) -> i32 {
+        if self.respect_panels { 0 } else { -1 }
+    }
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            collapsed_width: 160,
+            collapsed_height: 380,
+            expanded_width: 800,
+            expanded_height: 1000,
+            scale: 1.0,
+            layer: WindowLayer::Overlay,
+            respect_panels: true,
+            keyboard_mode: IdleKeyboardMode::OnDemand,
+            chat_dock: ChatDock::FollowCharacter,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowLayer {
+    /// Above everything, fullscreen windows included
+    Overlay,
+    /// Above normal windows
+    Top,
+}
+
+impl WindowLayer {
+    pub fn layer(self) -> Layer {
+        match self {
+            WindowLayer::Overlay => Layer::Overlay,
+            WindowLayer::Top => Layer::Top,
+        }
+    }
+}
+
+/// Keyboard interactivity while no text field in the page has focus (see focus.rs)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdleKeyboardMode {
+    /// Focus the overlay when it is clicked
+    OnDemand,
+    /// Never take keyboard focus
+    None,
+}
+
+impl IdleKeyboardMode {
+    pub fn mode(self) -> KeyboardMode {
+        match self {
+            IdleKeyboardMode::OnDemand => KeyboardMode::OnDemand,
+            IdleKeyboardMode::None => KeyboardMode::None,
+        }
+    }
+}
+
+/// Where the chat panel goes (see layout.rs)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChatDock {
+    /// Beside the character, towards the middle of the screen
+    FollowCharacter,
+    /// Against the left screen edge, full height
+    LeftPanel,
+    /// Against the right screen edge, full height
+    RightPanel,
+    /// In the middle of the screen
+    Floating,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, rename_all(serialize = "camelCase"))]
+pub struct PositionConfig {
+    pub corner: Corner,
+    /// Distance from the screen edges
+    pub margin: i32,
+    /// How close to an edge a drag has to end to snap onto it
+    pub snap_distance: i32,
+}
+
+impl Default for PositionConfig {
+    fn default() -> Self {
+        Self {
+            corner: Corner::BottomRight,
+            margin: 20,
+            snap_distance: 32,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, rename_all(serialize = "camelCase"))]
+pub struct TtsConfig {
+    pub engine: TtsEngine,
+    pub voice: Option<String>,
+    /// 1.0 is the voice's normal speed
+    pub rate: f32,
+    pub cloud: CloudTtsConfig,
+}
+
+impl Default for TtsConfig {
+    fn default() -> Self {
+        Self {
+            engine: TtsEngine::Auto,
+            voice: None,
+            rate: 1.0,
+            cloud: CloudTtsConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TtsEngine {
+    /// Piper if it and a voice are installed, otherwise espeak-ng
+    Auto,
+    Piper,
+    Espeak,
+    Cloud,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, rename_all(serialize = "camelCase"))]
+pub struct CloudTtsConfig {
+    pub url: String,
+    pub model: String,
+    /// Environment variable with the API key, so the key itself stays out of the file
+    pub api_key_env: String,
+}
+
+impl Default for CloudTtsConfig {
+    fn default() -> Self {
+        Self {
+            url: "https://api.openai.com/v1/audio/speech".into(),
+            model: "tts-1".into(),
+            api_key_env: "OPENAI_API_KEY".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, rename_all(serialize = "camelCase"))]
+pub struct SttConfig {
+    pub model: String,
+    pub language: String,
+    /// Silence that ends an utterance
+    pub pause_ms: u32,
+}
+
+impl Default for SttConfig {
+    fn default() -> Self {
+        Self {
+            model: "base.en".into(),
+            language: "auto".into(),
+            pause_ms: 800,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, rename_all(serialize = "camelCase"))]
+pub struct LlmConfig {
+    /// Base URL of a local server's OpenAI-compatible API; unset: detect Ollama or llama.cpp
+    pub local_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, rename_all(serialize = "camelCase"))]
+pub struct MemoryConfig {
+    pub enabled: bool,
+    pub embeddings: EmbeddingSource,
+    /// Embedding model; unset: the source's default
+    pub model: Option<String>,
+    pub recall_count: usize,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            embeddings: EmbeddingSource::Auto,
+            model: None,
+            recall_count: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingSource {
+    /// The local model server if it has the model, otherwise OpenAI if a key is set
+    Auto,
+    Local,
+    #[serde(rename = "openai")]
+    OpenAI,
+    /// No embeddings: memories are matched by keywords
+    None,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, rename_all(serialize = "camelCase"))]
+pub struct FilesConfig {
+    /// Directories the user has allowed file access to, `~/` for the home directory
+    pub allowed_dirs: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, rename_all(serialize = "camelCase"))]
+pub struct BehaviorConfig {
+    /// Seconds
+    pub idle_after: u64,
+    /// Seconds; 0 turns idle animations off
+    pub animation_interval: u64,
+    /// Clip names (see animations.rs)
+    pub idle_animations: Vec<String>,
+    /// Minutes; 0 turns stretch reminders off
+    pub stretch_after: u64,
+    /// Minutes; 0 turns remarks off
+    pub remark_interval: u64,
+}
+
+impl Default for BehaviorConfig {
+    fn default() -> Self {
+        Self {
+            idle_after: 300,
+            animation_interval: 90,
+            idle_animations: vec!["arm_stretching".into(), "female_standing_pose".into(), "dwarf_idle".into()],
+            stretch_after: 50,
+            remark_interval: 0,
+        }
+    }
+}
+
+impl BehaviorConfig {
+    pub fn idle_after(&self) -> Duration {
+        Duration::from_secs(self.idle_after.max(10))
+    }
+
+    pub fn animation_interval(&self) -> Duration {
+        Duration::from_secs(self.animation_interval)
+    }
+
+    pub fn stretch_after(&self) -> Option<Duration> {
+        (self.stretch_after > 0).then(|| Duration::from_secs(self.stretch_after * 60))
+    }
+
+    pub fn remark_interval(&self) -> Duration {
+        Duration::from_secs(self.remark_interval * 60)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, rename_all(serialize = "camelCase"))]
+pub struct CompositorConfig {
+    /// Pass the focused window's app ID or class to the assistant
+    pub share_focused_app: bool,
+    /// Pass its title too; titles can name documents, sites or people
+    pub share_window_title: bool,
+    /// Lower the overlay while a window is fullscreen on the character's monitor
+    pub hide_on_fullscreen: bool,
+}
+
+impl Default for CompositorConfig {
+    fn default() -> Self {
+        Self {
+            share_focused_app: true,
+            share_window_title: false,
+            hide_on_fullscreen: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, rename_all(serialize = "camelCase"))]
+pub struct ApiConfig {
+    /// Serve /api/v1 (token in ~/.config/desktop-waifu/api-token)
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, rename_all(serialize = "camelCase"))]
+pub struct SoundConfig {
+    pub enabled: bool,
+    /// Multiplies the volume the frontend asks for
+    pub volume: f64,
+}
+
+impl Default for SoundConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            volume: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, rename_all(serialize = "camelCase"))]
+pub struct ConfirmConfig {
+    /// Ask in a native dialog before anything that needs approval, ignoring the frontend's
+    /// `confirmed`
+    pub dialog: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, rename_all(serialize = "camelCase"))]
+pub struct FrontendConfig {
+    /// Release manifest to check for frontend updates; https only
+    pub update_url: Option<String>,
+}
+
+impl FrontendConfig {
+    /// Drop an update URL that isn't https: whoever can tamper with the download could
+    /// otherwise hand the page its commands and files
+    fn refuse_insecure(&mut self) {
+        let insecure = self
+            .update_url
+            .as_deref()
+            .is_some_and(|url| reqwest::Url::parse(url).map_or(true, |url| url.scheme() != "https"));
+        if insecure {
+            tracing::warn!("Ignoring [frontend] update_url, which isn't an https:// URL");
+            self.update_url = None;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, rename_all(serialize = "camelCase"))]
+pub struct KeysConfig {
+    /// Close the chat
+    pub close_chat: String,
+    /// Open the chat if needed and put the cursor in its input
+    pub focus_input: String,
+    /// Show or close the web inspector
+    pub devtools: String,
+    /// Let clicks through to the windows below, or stop
+    pub click_through: String,
+}
+
+impl Default for KeysConfig {
+    fn default() -> Self {
+        Self {
+            close_chat: "Escape".to_string(),
+            focus_input: "<Control>l".to_string(),
+            devtools: "<Control><Shift>i".to_string(),
+            click_through: "<Control><Shift>x".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, rename_all(serialize = "camelCase"))]
+pub struct ExpressionsConfig {
+    /// Follow what the assistant is doing; off, only SetExpression changes the face
+    pub enabled: bool,
+    /// While waiting for the LLM
+    pub thinking: String,
+    /// While a command has been running for `working_after` seconds
+    pub working: String,
+    pub working_after: f32,
+    /// For `flash` seconds after a command succeeds or fails
+    pub success: String,
+    pub error: String,
+    pub flash: f32,
+    /// Seconds SetExpression holds a face when it doesn't say; 0 until the next one
+    pub hold: f32,
+}
+
+impl Default for ExpressionsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            thinking: "relaxed".to_string(),
+            working: "surprised".to_string(),
+            working_after: 5.0,
+            success: "happy".to_string(),
+            error: "sad".to_string(),
+            flash: 3.0,
+            hold: 10.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, rename_all(serialize = "camelCase"))]
+pub struct WalkConfig {
+    /// Walk around until turned off from the tray
+    pub enabled: bool,
+    /// Pixels per second
+    pub speed: f64,
+    /// Average seconds between walks
+    pub interval: f64,
+    /// Sometimes walk onto the top edge of a window (Hyprland and Sway)
+    pub perch_on_windows: bool,
+}
+
+impl Default for WalkConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            speed: 80.0,
+            interval: 45.0,
+            perch_on_windows: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, rename_all(serialize = "camelCase"))]
+pub struct RenderConfig {
+    pub mode: RenderMode,
+}
+
+/// How WebKit draws the page (see render.rs)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RenderMode {
+    /// Pick one of the others from the GPU and its driver
+    #[default]
+    Auto,
+    /// WebKit's defaults
+    Gpu,
+    /// The GPU without DMA-BUF frames
+    NoDmabuf,
+    /// Mesa's software renderer
+    Software,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, rename_all(serialize = "camelCase"))]
+pub struct WebkitConfig {
+    pub hardware_acceleration: HardwareAcceleration,
+    pub cache_model: WebkitCacheModel,
+    /// MB a web process may use; None: WebKit's default
+    pub memory_limit: Option<u32>,
+    /// Fractions of `memory_limit` where WebKit starts freeing memory, then frees more
+    pub conservative_threshold: f64,
+    pub strict_threshold: f64,
+    /// Fraction of `memory_limit` where WebKit ends the web process; 0 never
+    pub kill_threshold: f64,
+}
+
+impl WebkitConfig {
+    /// Memory pressure settings for web processes, when there's a limit
+    pub fn memory_pressure_settings(&self) -> Option<webkit6::MemoryPressureSettings> {
+        let limit = self.memory_limit.filter(|limit| *limit > 0)?;
+        // WebKit wants 0 < conservative < strict < 1, and a kill threshold above strict
+        let conservative = self.conservative_threshold.clamp(0.01, 0.98);
+        let strict = self.strict_threshold.clamp(conservative + 0.01, 0.99);
+        let mut settings = webkit6::MemoryPressureSettings::new();
+        settings.set_memory_limit(limit);
+        settings.set_conservative_threshold(conservative);
+        settings.set_strict_threshold(strict);
+        if self.kill_threshold > 0.0 {
+            settings.set_kill_threshold(self.kill_threshold.max(strict + 0.01));
+        }
+        Some(settings)
+    }
+}
+
+impl Default for WebkitConfig {
+    fn default() -> Self {
+        Self {
+            hardware_acceleration: HardwareAcceleration::Auto,
+            cache_model: WebkitCacheModel::WebBrowser,
+            memory_limit: None,
+            conservative_threshold: 0.33,
+            strict_threshold: 0.5,
+            kill_threshold: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HardwareAcceleration {
+    /// On unless render.rs found no EGL
+    Auto,
+    Always,
+    /// No GPU compositing, and no WebGL
+    Never,
+}
+
+/// How much WebKit caches, from most to least
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WebkitCacheModel {
+    WebBrowser,
+    DocumentBrowser,
+    DocumentViewer,
+}
+
+impl WebkitCacheModel {
+    pub fn model(self) -> webkit6::CacheModel {
+        match self {
+            WebkitCacheModel::WebBrowser => webkit6::CacheModel::WebBrowser,
+            WebkitCacheModel::DocumentBrowser => webkit6::CacheModel::DocumentBrowser,
+            WebkitCacheModel::DocumentViewer => webkit6::CacheModel::DocumentViewer,
+        }
+    }
+}
+
+impl Config {
+    /// Default character position (left, top) on a screen of the given size, for a character
+    /// at `scale`
+    pub fn default_position(&self, screen_width: i32, screen_height: i32, scale: f32) -> (i32, i32) {
+        let margin = self.position.margin;
+        let (width, height) = self.window.character_size(scale);
+        let right = screen_width - width - margin;
+        let bottom = screen_height - height - margin;
+        match self.position.corner {
+            Corner::TopLeft => (margin, margin),
+            Corner::TopRight => (right, margin),
+            Corner::BottomLeft => (margin, bottom),
+            Corner::BottomRight => (right, bottom),
+        }
+    }
+}
+
+pub fn config_path() -> PathBuf {
+    glib::user_config_dir()
+        .join("desktop-waifu")
+        .join("config.toml")
+}
+
+/// config.toml, or the defaults if it is missing or invalid
+fn load() -> Config {
+    let path = config_path();
+    let Ok(text) = std::fs::read_to_string(&path) else {
+        return Config::default();
+    };
+    let mut config: Config = toml::from_str(&text).unwrap_or_else(|e| {
+        tracing::warn!("Ignoring invalid {}: {}", path.display(), e);
+        Config::default()
+    });
+    config.frontend.refuse_insecure();
+    config
+}
+
+/// Change config.toml with `edit`, keeping its comments and layout, and apply the result
+/// right away rather than when the file monitor notices
+pub fn update(edit: impl FnOnce(&mut toml_edit::DocumentMut)) -> std::io::Result<()> {
+    let path = config_path();
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+    let mut document: toml_edit::DocumentMut = text
+        .parse()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))?;
+    edit(&mut document);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, document.to_string())?;
+    set(load());
+    Ok(())
+}
+
+fn set(config: Config) {
+    *CONFIG.write().unwrap() = Arc::new(config);
+}
+
+/// Read config.toml; call once at startup
+pub fn init() -> Arc<Config> {
+    set(load());
+    current()
+}
+
+pub fn current() -> Arc<Config> {
+    CONFIG.read().unwrap().clone()
+}
+
+/// Re-read config.toml whenever it changes and pass the new config to `on_change`
+pub fn watch(on_change: impl Fn(&Config) + 'static) {
+    let path = config_path();
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let monitor = match gio::File::for_path(&path).monitor_file(gio::FileMonitorFlags::NONE, None::<&gio::Cancellable>) {
+        Ok(monitor) => monitor,
+        Err(e) => {
+            tracing::warn!("Failed to watch {}: {}", path.display(), e);
+            return;
+        }
+    };
+
+    let on_change = Rc::new(on_change);
+    let reload_pending = Rc::new(RefCell::new(false));
+    monitor.connect_changed(move |_, _, _, _| {
+        // Editors emit bursts of events per save; coalesce them
+        if reload_pending.replace(true) {
+            return;
+        }
+        let on_change = on_change.clone();
+        let reload_pending = reload_pending.clone();
+        glib::timeout_add_local_once(Duration::from_millis(300), move || {
+            *reload_pending.borrow_mut() = false;
+            set(load());
+            tracing::info!("Reloaded {}", config_path().display());
+            on_change(&current());
+        });
+    });
+    MONITOR.with(|m| *m.borrow_mut() = Some(monitor));
+}
@@ -0,0 +1,336 @@
+//! Native confirmation dialogs (`[confirm] dialog` in config.toml)
+//!
+//! By default the chat asks before a command the policy marks `confirm`, a privileged
+//! command, a tool call that needs approval, access to a folder or a file export, and the overlay trusts the `confirmed` flag
+//! the frontend sends along. With `dialog = true` the overlay asks itself instead: a GTK
+//! window shows the exact command and the folder it runs in, or for a file write the path
+//! and a diff of the change, and nothing runs unless the user approves it there. The
+//! frontend's `confirmed` is then ignored, so a compromised or confused page can't approve
+//! anything on the user's behalf. As with the file dialog, the overlay drops to the bottom
+//! layer while a dialog is open so it can't cover it.
+
+use crate::policy::{self, Action};
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::ApplicationWindow;
+use gtk4_layer_shell::{Layer, LayerShell as _};
+use std::cell::{Cell, RefCell};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Error for requests the user turned down in the dialog
+pub const DECLINED: &str = "Declined by the user";
+/// Longest diff shown; larger changes are cut off with a note
+const MAX_DIFF: usize = 256 * 1024;
+
+thread_local! {
+    /// Dialogs open right now; the overlay stays lowered until the last one closes
+    static OPEN: Cell<u32> = const { Cell::new(0) };
+}
+
+/// What the user is asked to approve
+#[derive(Debug, Default)]
+pub struct Prompt {
+    pub title: String,
+    /// Why it needs approval, e.g. the matching policy rule's reason
+    pub reason: Option<String>,
+    /// Command line, or a tool call
+    pub command: Option<String>,
+    pub cwd: Option<PathBuf>,
+    /// Unified diff of a file write
+    pub diff: Option<String>,
+}
+
+/// Whether approvals come from the native dialog rather than the chat
+pub fn enabled() -> bool {
+    crate::config::current().confirm.dialog
+}
+
+/// Decide whether `cmd` may run in `cwd` and hand `proceed` the answer. The policy's
+/// denials always hold; what it wants confirmed is settled by the dialog when it's enabled,
+/// otherwise by `confirmed`. `privileged` commands are confirmed even when the policy
+/// allows them.
+pub fn command(
+    window: &ApplicationWindow,
+    cmd: &str,
+    cwd: Option<PathBuf>,
+    confirmed: bool,
+    privileged: bool,
+    proceed: impl FnOnce(Result<(), String>) + 'static,
+) {
+    let policy = match policy::load() {
+        Ok(policy) => policy,
+        Err(e) => return proceed(Err(e)),
+    };
+    if !enabled() {
+        let result = if privileged && !confirmed {
+            Err("Privileged commands require confirmation".to_string())
+        } else {
+            policy.check(cmd, confirmed)
+        };
+        proceed(result);
+        return;
+    }
+
+    let verdict = policy.classify(cmd);
+    match verdict.action {
+        Action::Deny => proceed(policy.check(cmd, false)),
+        Action::Allow if !privileged => proceed(Ok(())),
+        _ => {
+            let prompt = Prompt {
+                title: if privileged {
+                    tr!("confirm-privileged-title")
+                } else {
+                    tr!("confirm-command-title")
+                },
+                reason: verdict.reason,
+                command: Some(cmd.to_string()),
+                cwd,
+                diff: None,
+            };
+            ask(window, prompt, move |approved| {
+                proceed(if approved { Ok(()) } else { Err(DECLINED.to_string()) })
+            });
+        }
+    }
+}
+
+/// The prompt for a tool call that needs approval: the command for `run_shell`, the diff
+/// for `write_file`, the arguments for anything else
+pub fn tool_prompt(name: &str, args: &serde_json::Value) -> Prompt {
+    match name {
+        "run_shell" => {
+            let options = crate::shell::ShellOptions {
+                cwd: args["cwd"].as_str().map(str::to_string),
+                ..Default::default()
+            };
+            Prompt {
+                title: tr!("confirm-command-title"),
+                command: args["command"].as_str().map(str::to_string),
+                cwd: options.cwd(),
+                ..Default::default()
+            }
+        }
+        "write_file" => {
+            let path = crate::files::expand(args["path"].as_str().unwrap_or_default());
+            let content = args["content"].as_str().unwrap_or_default();
+            let append = args["append"].as_bool().unwrap_or(false);
+            write_prompt(&path, content, append)
+        }
+        _ => Prompt {
+            title: tr!("confirm-tool-title", tool = name),
+            command: Some(format!(
+                "{} {}",
+                name,
+                serde_json::to_string_pretty(args).unwrap_or_default()
+            )),
+            ..Default::default()
+        },
+    }
+}
+
+/// The prompt for writing `content` to `path`, with a diff against what's there now
+pub fn write_prompt(path: &Path, content: &str, append: bool) -> Prompt {
+    // Missing and unreadable files diff as empty, so the whole content shows as added
+    let old = std::fs::read_to_string(path).unwrap_or_default();
+    let new = if append { format!("{}{}", old, content) } else { content.to_string() };
+    let name = path.display().to_string();
+    let mut diff = similar::TextDiff::from_lines(&old, &new)
+        .unified_diff()
+        .context_radius(3)
+        .header(&name, &name)
+        .to_string();
+    if diff.len() > MAX_DIFF {
+        let mut end = MAX_DIFF;
+        while !diff.is_char_boundary(end) {
+            end -= 1;
+        }
+        diff.truncate(end);
+        diff.push_str("\n…\n");
+    }
+    Prompt {
+        title: tr!("confirm-write-title", path = name.as_str()),
+        diff: Some(diff),
+        ..Default::default()
+    }
+}
+
+/// The prompt for letting the assistant read everything in `dir`
+pub fn read_dir_prompt(dir: &str) -> Prompt {
+    let path = crate::files::expand(dir);
+    let path = path.canonicalize().unwrap_or(path);
+    Prompt {
+        title: tr!("confirm-read-dir-title", path = path.display().to_string().as_str()),
+        ..Default::default()
+    }
+}
+
+/// Show `prompt` over the overlay and call `on_answer` once with whether the user approved.
+/// Closing the dialog or pressing Escape declines.
+pub fn ask(window: &ApplicationWindow, prompt: Prompt, on_answer: impl FnOnce(bool) + 'static) {
+    tracing::info!(
+        "Asking for confirmation: {} {}",
+        prompt.title,
+        prompt.command.as_deref().unwrap_or_default()
+    );
+
+    let dialog = gtk4::Window::builder()
+        .title(prompt.title.as_str())
+        .transient_for(window)
+        .modal(true)
+        .default_width(560)
+        .build();
+
+    let content = gtk4::Box::new(gtk4::Orientation::Vertical, 12);
+    content.set_margin_top(18);
+    content.set_margin_bottom(18);
+    content.set_margin_start(18);
+    content.set_margin_end(18);
+
+    let heading = gtk4::Label::new(Some(&prompt.title));
+    heading.add_css_class("title-3");
+    heading.set_xalign(0.0);
+    heading.set_wrap(true);
+    content.append(&heading);
+    if let Some(reason) = &prompt.reason {
+        let label = gtk4::Label::new(Some(reason));
+        label.add_css_class("dim-label");
+        label.set_xalign(0.0);
+        label.set_wrap(true);
+        content.append(&label);
+    }
+    if let Some(command) = &prompt.command {
+        content.append(&field(&tr!("confirm-command"), command));
+    }
+    if let Some(cwd) = &prompt.cwd {
+        content.append(&field(&tr!("confirm-cwd"), &cwd.display().to_string()));
+    }
+    if let Some(diff) = &prompt.diff {
+        content.append(&diff_view(diff));
+    }
+
+    let deny = gtk4::Button::with_label(&tr!("confirm-deny"));
+    let allow = gtk4::Button::with_label(&tr!("confirm-allow"));
+    allow.add_css_class("destructive-action");
+    let buttons = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
+    buttons.set_halign(gtk4::Align::End);
+    buttons.append(&deny);
+    buttons.append(&allow);
+    content.append(&buttons);
+    dialog.set_child(Some(&content));
+
+    // Whichever happens first answers; closing the window afterwards is a no-op
+    let on_answer: Rc<RefCell<Option<Box<dyn FnOnce(bool)>>>> = Rc::new(RefCell::new(Some(Box::new(on_answer))));
+    let window_for_restore = window.clone();
+    let answer = Rc::new(move |approved: bool| {
+        let Some(on_answer) = on_answer.borrow_mut().take() else { return };
+        restore(&window_for_restore);
+        tracing::info!("Confirmation {}", if approved { "granted" } else { "declined" });
+        on_answer(approved);
+    });
+
+    let answer_for_close = answer.clone();
+    dialog.connect_close_request(move |_| {
+        answer_for_close(false);
+        glib::Propagation::Proceed
+    });
+    let dialog_for_deny = dialog.clone();
+    deny.connect_clicked(move |_| dialog_for_deny.close());
+    let dialog_for_allow = dialog.clone();
+    allow.connect_clicked(move |_| {
+        answer(true);
+        dialog_for_allow.close();
+    });
+    let keys = gtk4::EventControllerKey::new();
+    let dialog_for_keys = dialog.clone();
+    keys.connect_key_pressed(move |_, key, _, _| {
+        if key == gtk4::gdk::Key::Escape {
+            dialog_for_keys.close();
+            return glib::Propagation::Stop;
+        }
+        glib::Propagation::Proceed
+    });
+    dialog.add_controller(keys);
+
+    // Temporarily lower the overlay layer so the dialog appears on top
+    lower(window);
+    dialog.present();
+    // Enter shouldn't approve by accident
+    deny.grab_focus();
+}
+
+/// A caption over a selectable monospace value
+fn field(caption: &str, value: &str) -> gtk4::Box {
+    let row = gtk4::Box::new(gtk4::Orientation::Vertical, 4);
+    let caption = gtk4::Label::new(Some(caption));
+    caption.add_css_class("heading");
+    caption.set_xalign(0.0);
+    let value = gtk4::Label::new(Some(value));
+    value.add_css_class("monospace");
+    value.set_xalign(0.0);
+    value.set_wrap(true);
+    value.set_wrap_mode(gtk4::pango::WrapMode::WordChar);
+    value.set_selectable(true);
+    row.append(&caption);
+    row.append(&value);
+    row
+}
+
+/// A scrolling, read-only view of a unified diff with added and removed lines colored
+fn diff_view(diff: &str) -> gtk4::Box {
+    let buffer = gtk4::TextBuffer::new(None);
+    let added = buffer.create_tag(Some("added"), &[("foreground", &"#26a269")]);
+    let removed = buffer.create_tag(Some("removed"), &[("foreground", &"#c01c28")]);
+    let hunk = buffer.create_tag(Some("hunk"), &[("foreground", &"#1c71d8")]);
+    for line in diff.split_inclusive('\n') {
+        let tag = if line.starts_with("+++") || line.starts_with("---") {
+            None
+        } else {
+            match line.as_bytes().first() {
+                Some(b'+') => added.as_ref(),
+                Some(b'-') => removed.as_ref(),
+                Some(b'@') => hunk.as_ref(),
+                _ => None,
+            }
+        };
+        let mut end = buffer.end_iter();
+        match tag {
+            Some(tag) => buffer.insert_with_tags(&mut end, line, &[tag]),
+            None => buffer.insert(&mut end, line),
+        }
+    }
+
+    let view = gtk4::TextView::with_buffer(&buffer);
+    view.set_editable(false);
+    view.set_cursor_visible(false);
+    view.set_monospace(true);
+    let scrolled = gtk4::ScrolledWindow::builder()
+        .child(&view)
+        .min_content_height(240)
+        .max_content_height(480)
+        .propagate_natural_height(true)
+        .vexpand(true)
+        .build();
+
+    let row = gtk4::Box::new(gtk4::Orientation::Vertical, 4);
+    let caption = gtk4::Label::new(Some(&tr!("confirm-changes")));
+    caption.add_css_class("heading");
+    caption.set_xalign(0.0);
+    row.append(&caption);
+    row.append(&scrolled);
+    row
+}
+
+fn lower(window: &ApplicationWindow) {
+    if OPEN.get() == 0 {
+        window.set_layer(Layer::Bottom);
+    }
+    OPEN.set(OPEN.get() + 1);
+}
+
+fn restore(window: &ApplicationWindow) {
+    OPEN.set(OPEN.get().saturating_sub(1));
+    if OPEN.get() == 0 {
+        window.set_layer(crate::compositor::resting_layer());
+    }
+}
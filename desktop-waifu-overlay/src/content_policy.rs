@@ -0,0 +1,127 @@
+//! What the webview may load and where it may go
+//!
+//! The page renders LLM output, so a crafted reply shouldn't be able to pull in remote
+//! scripts, leak the conversation through an image URL or navigate the overlay to another
+//! site. A WebKit content filter blocks every load except the page's own origin (the
+//! static server or the Vite dev server, including its HMR socket), the overlay's own
+//! `waifu-*` schemes and `data:`/`blob:` URLs; LLM and cloud voice requests go through the
+//! overlay, not the page. Navigations away from the page's origin are refused, and links
+//! the user clicks open in the default browser instead.
+//!
+//! The page is only loaded once the filter is in place. If it can't be compiled, an error
+//! page is shown instead of the page running unfiltered.
+
+use gtk4::gio;
+use gtk4::glib;
+use gtk4::prelude::*;
+use std::cell::Cell;
+use webkit6::prelude::*;
+use webkit6::{NavigationPolicyDecision, PolicyDecisionType, UserContentFilterStore, WebView};
+
+const FILTER_ID: &str = "desktop-waifu-origins";
+
+thread_local! {
+    /// Whether the content filter has been added to the webview
+    static FILTERED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Keep `webview`'s navigations on the origin of `page_url`
+pub fn install(webview: &WebView, page_url: &str) {
+    let origin = origin(page_url).to_string();
+
+    webview.connect_decide_policy(move |_webview, decision, decision_type| {
+        if !matches!(decision_type, PolicyDecisionType::NavigationAction | PolicyDecisionType::NewWindowAction) {
+            return false;
+        }
+        let Some(mut action) = decision
+            .downcast_ref::<NavigationPolicyDecision>()
+            .and_then(|decision| decision.navigation_action())
+        else {
+            return false;
+        };
+        let uri = action.request().and_then(|request| request.uri()).unwrap_or_default();
+        if decision_type == PolicyDecisionType::NavigationAction && allowed_navigation(&uri, &origin) {
+            return false;
+        }
+
+        decision.ignore();
+        if action.is_user_gesture() && (uri.starts_with("https://") || uri.starts_with("http://")) {
+            tracing::info!("Opening link in the browser: {}", uri);
+            if let Err(e) = gio::AppInfo::launch_default_for_uri(&uri, None::<&gio::AppLaunchContext>) {
+                tracing::warn!("Could not open {}: {}", uri, e);
+            }
+        } else {
+            tracing::warn!("Blocked navigation to {}", uri);
+        }
+        true
+    });
+}
+
+/// The page itself, or about:blank for frames
+fn allowed_navigation(uri: &str, origin: &str) -> bool {
+    uri == "about:blank" || uri.strip_prefix(origin).is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '?', '#']))
+}
+
+/// `scheme://host:port` of a URL
+fn origin(url: &str) -> &str {
+    let after_scheme = url.find("://").map_or(0, |i| i + 3);
+    let end = url[after_scheme..]
+        .find(['/', '?', '#'])
+        .map_or(url.len(), |i| after_scheme + i);
+    &url[..end]
+}
+
+/// Load `page_url` once the content filter restricting it to its origin is in place
+pub fn load(webview: &WebView, page_url: &str) {
+    if FILTERED.get() {
+        webview.load_uri(page_url);
+        return;
+    }
+    let Some(content_manager) = webview.user_content_manager() else {
+        show_error(webview, "no content manager");
+        return;
+    };
+    let (rules, store) = filter(origin(page_url));
+    let webview = webview.clone();
+    let page_url = page_url.to_string();
+    glib::spawn_future_local(async move {
+        match store.save_future(FILTER_ID, &rules).await {
+            Ok(filter) => {
+                content_manager.add_filter(&filter);
+                FILTERED.set(true);
+                crate::debug_log!("[CONTENT_POLICY] Content filter installed");
+                webview.load_uri(&page_url);
+            }
+            Err(e) => {
+                tracing::error!("Could not compile the content filter, not loading the page: {}", e);
+                show_error(&webview, &e.to_string());
+            }
+        }
+    });
+}
+
+/// A local page saying why the real one wasn't loaded
+fn show_error(webview: &WebView, error: &str) {
+    let message = crate::tr!("content-filter-error", error = error);
+    let message = message.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+    webview.load_html(&format!("<!DOCTYPE html><meta charset=\"utf-8\"><p>{}</p>", message), None);
+}
+
+/// The content filter's rules (cached by WebKit) and the store that compiles them
+fn filter(origin: &str) -> (glib::Bytes, UserContentFilterStore) {
+    let host = origin.split_once("://").map_or(origin, |(_, host)| host);
+    let allow = |pattern: String| {
+        serde_json::json!({ "trigger": { "url-filter": pattern }, "action": { "type": "ignore-previous-rules" } })
+    };
+    // Content filter regexes have no alternation, so each allowed prefix is its own rule
+    let mut rules = vec![serde_json::json!({ "trigger": { "url-filter": ".*" }, "action": { "type": "block" } })];
+    rules.push(allow(format!("^{}/", regex::escape(origin))));
+    rules.push(allow(format!("^ws://{}/", regex::escape(host))));
+    for scheme in [crate::transfer::SCHEME, crate::models::SCHEME, crate::animations::SCHEME, "data", "blob", "about"] {
+        rules.push(allow(format!("^{}:", regex::escape(scheme))));
+    }
+    let rules = glib::Bytes::from_owned(serde_json::to_vec(&rules).unwrap_or_default());
+
+    let store_dir = glib::user_cache_dir().join("desktop-waifu").join("content-filters");
+    (rules, UserContentFilterStore::new(&store_dir.to_string_lossy()))
+}
@@ -0,0 +1,138 @@
+//! Files dropped onto the character
+//!
+//! A drop target on the webview takes files dragged in from a file manager. Each one is read
+//! on a worker thread and checked: images (PNG, JPEG, GIF, WebP) up to 20 MB, as the chat's
+//! attachments allow, and UTF-8 text files up to 512 KB. The frontend gets them in one
+//! `fileDropped` event: `{ files: [{ name, mimeType, size, data }], rejected: [{ name,
+//! reason }] }`, `data` being base64. Images are attached to the next message and text
+//! files quoted into it.
+
+use gtk4::prelude::*;
+use gtk4::{gdk, gio, glib};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use webkit6::WebView;
+
+const MAX_IMAGE_SIZE: u64 = 20 * 1024 * 1024;
+const MAX_TEXT_SIZE: u64 = 512 * 1024;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DroppedFile {
+    name: String,
+    mime_type: String,
+    size: u64,
+    data: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Rejected {
+    name: String,
+    reason: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct Dropped {
+    files: Vec<DroppedFile>,
+    rejected: Vec<Rejected>,
+}
+
+/// Accept file drops on `webview`, passing the `fileDropped` detail of each to `on_drop`
+pub fn install(webview: &WebView, on_drop: impl Fn(serde_json::Value) + 'static) {
+    let target = gtk4::DropTarget::new(gdk::FileList::static_type(), gdk::DragAction::COPY);
+    // Ahead of WebKit's own handling, which would open the file in the webview
+    target.set_propagation_phase(gtk4::PropagationPhase::Capture);
+
+    let on_drop = Rc::new(on_drop);
+    target.connect_drop(move |_, value, _, _| {
+        let Ok(list) = value.get::<gdk::FileList>() else {
+            return false;
+        };
+        let paths: Vec<PathBuf> = list.files().iter().filter_map(|file| file.path()).collect();
+        if paths.is_empty() {
+            return false;
+        }
+        tracing::info!("{} file(s) dropped on the character", paths.len());
+
+        let task = gio::spawn_blocking(move || read_all(&paths));
+        let on_drop = on_drop.clone();
+        glib::spawn_future_local(async move {
+            if let Ok(dropped) = task.await {
+                on_drop(serde_json::json!(dropped));
+            }
+        });
+        true
+    });
+    webview.add_controller(target);
+}
+
+fn read_all(paths: &[PathBuf]) -> Dropped {
+    let mut dropped = Dropped::default();
+    for path in paths {
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        match read(path) {
+            Ok(file) => dropped.files.push(file),
+            Err(reason) => {
+                tracing::info!("Rejected dropped file {}: {}", path.display(), reason);
+                dropped.rejected.push(Rejected { name, reason });
+            }
+        }
+    }
+    dropped
+}
+
+fn read(path: &Path) -> Result<DroppedFile, String> {
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let metadata = std::fs::metadata(path).map_err(|e| e.to_string())?;
+    if !metadata.is_file() {
+        return Err("Not a file".to_string());
+    }
+    let size = metadata.len();
+    let image_type = image_mime_type(path);
+    let limit = if image_type.is_some() { MAX_IMAGE_SIZE } else { MAX_TEXT_SIZE };
+    if size > limit {
+        return Err(format!("Too large ({} KB, at most {} KB)", size / 1024, limit / 1024));
+    }
+
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    let mime_type = match image_type {
+        // Trust the contents over the extension
+        Some(_) => sniff_image(&bytes).ok_or("Not a PNG, JPEG, GIF or WebP image")?,
+        None if std::str::from_utf8(&bytes).is_ok() && !bytes.contains(&0) => "text/plain",
+        None => return Err("Only images and text files can be dropped".to_string()),
+    };
+    Ok(DroppedFile {
+        name,
+        mime_type: mime_type.to_string(),
+        size,
+        data: glib::base64_encode(&bytes).to_string(),
+    })
+}
+
+/// The image type the extension claims, if it's one the chat takes
+fn image_mime_type(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    match ext.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    }
+}
+
+/// The image type from the file's magic bytes
+fn sniff_image(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
@@ -0,0 +1,195 @@
+//! Facial expressions set by the overlay (`[expressions]` in config.toml)
+//!
+//! What the assistant is doing decides the face here rather than in the page, so IPC
+//! clients, companions and the command runner can change it on their own. The expression
+//! shown is the first of these that applies:
+//!
+//! 1. One set with `SetExpression` (IPC, the tray, behavior scripts), for its duration or
+//!    `[expressions] hold` seconds
+//! 2. `success` or `error` for `flash` seconds after a command finishes, or after an
+//!    `ExpressionCue`
+//! 3. `working` while a command has been running for over `working_after` seconds
+//! 4. `thinking` while the page waits for the LLM (its `trayState`) or an IPC client set
+//!    the `thinking` animation state
+//! 5. `neutral`
+//!
+//! Whenever that changes, the page gets a `setExpression` event. An empty expression in
+//! `[expressions]` skips its step.
+
+use gtk4::glib;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use webkit6::WebView;
+
+const NEUTRAL: &str = "neutral";
+
+/// A one-off outcome shown for `[expressions] flash` seconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Cue {
+    Success,
+    Error,
+}
+
+/// A running command, from `job_started`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Job(u64);
+
+#[derive(Default)]
+struct State {
+    webview: Option<WebView>,
+    /// From SetExpression, until the deadline (None: until the next one)
+    manual: Option<(String, Option<Instant>)>,
+    flash: Option<(Cue, Instant)>,
+    thinking: bool,
+    /// Running commands and when they started
+    jobs: HashMap<Job, Instant>,
+    next_job: u64,
+    /// Expression last sent to the page
+    shown: Option<String>,
+    /// Wakes `update` at the next deadline
+    timer: Option<glib::SourceId>,
+}
+
+thread_local! {
+    static STATE: RefCell<State> = RefCell::new(State::default());
+}
+
+/// Send expressions to `webview` from now on
+pub fn install(webview: &WebView) {
+    STATE.with(|state| state.borrow_mut().webview = Some(webview.clone()));
+}
+
+/// A new page starts out neutral; send it the current expression
+pub fn page_loaded() {
+    STATE.with(|state| state.borrow_mut().shown = Some(NEUTRAL.to_string()));
+    update();
+}
+
+/// Show `expression` for `duration` seconds (`[expressions] hold` when None, until the next
+/// one when 0). `neutral` with no duration hands the face back to what the assistant is doing.
+pub fn set(expression: &str, duration: Option<f32>) {
+    let hold = duration.unwrap_or(crate::config::current().expressions.hold);
+    let manual = if expression == NEUTRAL && duration.is_none() {
+        None
+    } else if hold > 0.0 {
+        Some((expression.to_string(), Some(Instant::now() + Duration::from_secs_f32(hold))))
+    } else {
+        Some((expression.to_string(), None))
+    };
+    STATE.with(|state| state.borrow_mut().manual = manual);
+    update();
+}
+
+/// The assistant started or stopped thinking
+pub fn set_thinking(thinking: bool) {
+    let changed = STATE.with(|state| std::mem::replace(&mut state.borrow_mut().thinking, thinking) != thinking);
+    if changed {
+        update();
+    }
+}
+
+/// Show a success or error face for a moment
+pub fn cue(cue: Cue) {
+    let flash = Duration::from_secs_f32(crate::config::current().expressions.flash.max(0.0));
+    STATE.with(|state| state.borrow_mut().flash = Some((cue, Instant::now() + flash)));
+    update();
+}
+
+/// A command started running; pass the result to `job_finished`
+pub fn job_started() -> Job {
+    let job = STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let job = Job(state.next_job);
+        state.next_job += 1;
+        state.jobs.insert(job, Instant::now());
+        job
+    });
+    update();
+    job
+}
+
+/// The command `job` ended, successfully or not
+pub fn job_finished(job: Job, succeeded: bool) {
+    STATE.with(|state| state.borrow_mut().jobs.remove(&job));
+    cue(if succeeded { Cue::Success } else { Cue::Error });
+}
+
+/// Send the expression that applies now if it changed, and wake up at the next deadline
+fn update() {
+    let config = crate::config::current().expressions.clone();
+    let now = Instant::now();
+    let working_after = Duration::from_secs_f32(config.working_after.max(0.0));
+    let named = |name: &str| (!name.is_empty()).then(|| name.to_string());
+
+    let (expression, wake, webview) = STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if state.manual.as_ref().is_some_and(|(_, until)| until.is_some_and(|until| until <= now)) {
+            state.manual = None;
+        }
+        if state.flash.is_some_and(|(_, until)| until <= now) {
+            state.flash = None;
+        }
+        let working = state.jobs.values().any(|started| now.duration_since(*started) >= working_after);
+
+        let cued = || {
+            if !config.enabled {
+                return None;
+            }
+            let flash = state.flash.and_then(|(cue, _)| match cue {
+                Cue::Success => named(&config.success),
+                Cue::Error => named(&config.error),
+            });
+            flash
+                .or_else(|| working.then(|| named(&config.working)).flatten())
+                .or_else(|| state.thinking.then(|| named(&config.thinking)).flatten())
+        };
+        let expression = state
+            .manual
+            .as_ref()
+            .map(|(expression, _)| expression.clone())
+            .or_else(cued)
+            .unwrap_or_else(|| NEUTRAL.to_string());
+
+        // Whichever ends or starts first: the manual expression, the flash, a command
+        // turning long-running
+        let wake = [
+            state.manual.as_ref().and_then(|(_, until)| *until),
+            state.flash.map(|(_, until)| until),
+            state
+                .jobs
+                .values()
+                .map(|started| *started + working_after)
+                .filter(|at| *at > now)
+                .min(),
+        ]
+        .into_iter()
+        .flatten()
+        .min();
+        (expression, wake, state.webview.clone())
+    });
+
+    let changed = STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if let Some(timer) = state.timer.take() {
+            timer.remove();
+        }
+        if let Some(wake) = wake {
+            state.timer = Some(glib::timeout_add_local_once(wake.saturating_duration_since(now), || {
+                STATE.with(|state| state.borrow_mut().timer = None);
+                update();
+            }));
+        }
+        if webview.is_none() || state.shown.as_deref() == Some(expression.as_str()) {
+            return false;
+        }
+        state.shown = Some(expression.clone());
+        true
+    });
+    if changed && let Some(webview) = webview {
+        crate::debug_log!("[EXPRESSION] {}", expression);
+        crate::bridge::dispatch_event(&webview, "setExpression", &serde_json::json!({ "expression": expression }));
+    }
+}
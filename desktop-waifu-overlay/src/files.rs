@@ -0,0 +1,303 @@
+//! Read access to the user's files, limited to directories they have allowed
+//!
+//! `readFile`, `listDir`, `statPath` and `watchPath` (and the assistant's `read_file` and
+//! `list_dir` tools) only reach paths inside a directory listed under `[files]
+//! allowed_dirs` in config.toml. Allowing a directory (`fileAccess` handler, `/allow` in
+//! the chat) adds it there, so the user is asked once per directory rather than per file.
+//! Paths are resolved, symlinks included, before they are checked, so a link can't lead
+//! outside an allowed directory.
+//!
+//! Writing stays with `saveFile` and the `write_file` tool, which ask every time.
+
+use gtk4::gio;
+use gtk4::prelude::*;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Largest file `read_file` returns
+pub const MAX_READ: u64 = 4 * 1024 * 1024;
+
+thread_local! {
+    /// Active watches by id; dropping a monitor stops it
+    static WATCHES: RefCell<HashMap<u64, gio::FileMonitor>> = RefCell::new(HashMap::new());
+    static NEXT_WATCH: RefCell<u64> = const { RefCell::new(1) };
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FileError {
+    #[error("{} is outside the folders you've allowed access to", .0.display())]
+    NotAllowed(PathBuf),
+    #[error("{} is not a folder", .0.display())]
+    NotADirectory(PathBuf),
+    #[error("{} is too large to read ({size} bytes)", .path.display())]
+    TooLarge { path: PathBuf, size: u64 },
+    #[error("{}: {source}", .path.display())]
+    Io { path: PathBuf, source: std::io::Error },
+    #[error("Couldn't save config.toml: {0}")]
+    Config(std::io::Error),
+    #[error("Couldn't watch {}: {message}", .path.display())]
+    Watch { path: PathBuf, message: String },
+}
+
+pub type Result<T> = std::result::Result<T, FileError>;
+
+fn io_error(path: &Path) -> impl FnOnce(std::io::Error) -> FileError + '_ {
+    move |source| FileError::Io { path: path.to_path_buf(), source }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    File,
+    Directory,
+    Symlink,
+    Other,
+}
+
+/// What `statPath` reports, and each entry of `listDir`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PathInfo {
+    pub name: String,
+    pub path: PathBuf,
+    pub kind: Kind,
+    pub size: u64,
+    /// Milliseconds since the epoch
+    pub modified: Option<i64>,
+    pub readonly: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileContent {
+    pub path: PathBuf,
+    /// The text, or base64 when `encoding` is "base64" (the file isn't UTF-8)
+    pub content: String,
+    pub encoding: &'static str,
+    pub size: u64,
+}
+
+/// `~/` and relative paths are taken from the home directory
+pub fn expand(path: &str) -> PathBuf {
+    let home = gtk4::glib::home_dir();
+    match path.strip_prefix("~/") {
+        Some(rest) => home.join(rest),
+        None if path == "~" => home,
+        None => home.join(path),
+    }
+}
+
+/// `path` with the home directory shown as `~`, the way allowed_dirs are written
+fn home_relative(path: &Path) -> String {
+    let home = gtk4::glib::home_dir();
+    match path.strip_prefix(&home) {
+        Ok(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Ok(rest) => format!("~/{}", rest.display()),
+        Err(_) => path.display().to_string(),
+    }
+}
+
+/// The allowed directories, resolved; ones that no longer exist are left out
+fn allowed() -> Vec<PathBuf> {
+    crate::config::current()
+        .files
+        .allowed_dirs
+        .iter()
+        .filter_map(|dir| expand(dir).canonicalize().ok())
+        .collect()
+}
+
+/// Resolve `path` and check it's inside an allowed directory
+pub fn resolve(path: &str) -> Result<PathBuf> {
+    let expanded = expand(path);
+    let resolved = expanded.canonicalize().map_err(io_error(&expanded))?;
+    if allowed().iter().any(|dir| resolved.starts_with(dir)) {
+        Ok(resolved)
+    } else {
+        Err(FileError::NotAllowed(expanded))
+    }
+}
+
+fn info(path: &Path, metadata: &std::fs::Metadata) -> PathInfo {
+    let kind = if metadata.is_symlink() {
+        Kind::Symlink
+    } else if metadata.is_dir() {
+        Kind::Directory
+    } else if metadata.is_file() {
+        Kind::File
+    } else {
+        Kind::Other
+    };
+    PathInfo {
+        name: path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+        path: path.to_path_buf(),
+        kind,
+        size: metadata.len(),
+        modified: metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as i64),
+        readonly: metadata.permissions().readonly(),
+    }
+}
+
+pub fn stat(path: &str) -> Result<PathInfo> {
+    let path = resolve(path)?;
+    let metadata = std::fs::metadata(&path).map_err(io_error(&path))?;
+    Ok(info(&path, &metadata))
+}
+
+/// The directory's entries, folders first, then by name. Symlinks are listed as such and
+/// not followed.
+pub fn list_dir(path: &str) -> Result<Vec<PathInfo>> {
+    let path = resolve(path)?;
+    if !path.is_dir() {
+        return Err(FileError::NotADirectory(path));
+    }
+    let mut entries: Vec<PathInfo> = std::fs::read_dir(&path)
+        .map_err(io_error(&path))?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let metadata = entry.path().symlink_metadata().ok()?;
+            Some(info(&entry.path(), &metadata))
+        })
+        .collect();
+    entries.sort_by(|a, b| {
+        let a_dir = matches!(a.kind, Kind::Directory);
+        let b_dir = matches!(b.kind, Kind::Directory);
+        b_dir.cmp(&a_dir).then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+    Ok(entries)
+}
+
+/// The file's contents: text if it's UTF-8, otherwise base64
+pub fn read_file(path: &str, max: u64) -> Result<FileContent> {
+    let path = resolve(path)?;
+    let size = std::fs::metadata(&path).map_err(io_error(&path))?.len();
+    if size > max {
+        return Err(FileError::TooLarge { path, size });
+    }
+    let bytes = std::fs::read(&path).map_err(io_error(&path))?;
+    let (content, encoding) = match String::from_utf8(bytes) {
+        Ok(text) => (text, "utf-8"),
+        Err(e) => (gtk4::glib::base64_encode(e.as_bytes()).to_string(), "base64"),
+    };
+    Ok(FileContent { path, content, encoding, size })
+}
+
+/// A change `watch` reports
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Change {
+    pub watch_id: u64,
+    pub path: PathBuf,
+    /// "changed", "created", "deleted", "renamed" or "attributes"
+    pub change: &'static str,
+}
+
+/// Watch a file, or a directory's direct entries, calling `on_change` for each change until
+/// `unwatch`. Call on the main thread.
+pub fn watch(path: &str, on_change: impl Fn(Change) + 'static) -> Result<u64> {
+    let path = resolve(path)?;
+    let monitor = gio::File::for_path(&path)
+        .monitor(gio::FileMonitorFlags::WATCH_MOVES, None::<&gio::Cancellable>)
+        .map_err(|e| FileError::Watch { path: path.clone(), message: e.to_string() })?;
+    let id = NEXT_WATCH.with(|next| next.replace_with(|n| *n + 1));
+    let watched = path.clone();
+    monitor.connect_changed(move |monitor, file, _, event| {
+        // Access may have been taken back since the watch started
+        if !allowed().iter().any(|dir| watched.starts_with(dir)) {
+            monitor.cancel();
+            WATCHES.with(|w| w.borrow_mut().remove(&id));
+            return;
+        }
+        let change = match event {
+            gio::FileMonitorEvent::ChangesDoneHint => "changed",
+            gio::FileMonitorEvent::Created | gio::FileMonitorEvent::MovedIn => "created",
+            gio::FileMonitorEvent::Deleted | gio::FileMonitorEvent::MovedOut => "deleted",
+            gio::FileMonitorEvent::Renamed => "renamed",
+            gio::FileMonitorEvent::AttributeChanged => "attributes",
+            // Changed comes in bursts while a file is written; ChangesDoneHint ends them
+            _ => return,
+        };
+        on_change(Change {
+            watch_id: id,
+            path: file.path().unwrap_or_default(),
+            change,
+        });
+    });
+    WATCHES.with(|w| w.borrow_mut().insert(id, monitor));
+    tracing::info!("Watching {} (watch {})", path.display(), id);
+    Ok(id)
+}
+
+/// Returns whether the watch existed
+pub fn unwatch(id: u64) -> bool {
+    let monitor = WATCHES.with(|w| w.borrow_mut().remove(&id));
+    if let Some(monitor) = &monitor {
+        monitor.cancel();
+    }
+    monitor.is_some()
+}
+
+/// The allowed directories as written in config.toml
+pub fn allowed_dirs() -> Vec<String> {
+    crate::config::current().files.allowed_dirs.clone()
+}
+
+/// Allow access to `dir` and everything in it, saving it in config.toml. Returns the
+/// directory as saved.
+pub fn allow(dir: &str) -> Result<String> {
+    let expanded = expand(dir);
+    let resolved = expanded.canonicalize().map_err(io_error(&expanded))?;
+    if !resolved.is_dir() {
+        return Err(FileError::NotADirectory(resolved));
+    }
+    let entry = home_relative(&resolved);
+    if allowed().contains(&resolved) {
+        return Ok(entry);
+    }
+    let saved = entry.clone();
+    crate::config::update(move |document| {
+        let dirs = allowed_dirs_array(document);
+        dirs.push(saved);
+    })
+    .map_err(FileError::Config)?;
+    tracing::info!("Allowed file access to {}", entry);
+    Ok(entry)
+}
+
+/// Take access to `dir` back. Returns whether it was allowed.
+pub fn disallow(dir: &str) -> Result<bool> {
+    let target = expand(dir);
+    let target = target.canonicalize().unwrap_or(target);
+    let same = |entry: &str| {
+        let path = expand(entry);
+        path.canonicalize().unwrap_or(path) == target
+    };
+    if !allowed_dirs().iter().any(|entry| same(entry)) {
+        return Ok(false);
+    }
+    crate::config::update(|document| {
+        allowed_dirs_array(document).retain(|value| !value.as_str().is_some_and(&same));
+    })
+    .map_err(FileError::Config)?;
+    tracing::info!("Took back file access to {}", home_relative(&target));
+    Ok(true)
+}
+
+/// `[files] allowed_dirs`, created if missing (or replaced, if it isn't an array)
+fn allowed_dirs_array(document: &mut toml_edit::DocumentMut) -> &mut toml_edit::Array {
+    if !document.get("files").is_some_and(|files| files.is_table_like()) {
+        document["files"] = toml_edit::table();
+    }
+    let dirs = &mut document["files"]["allowed_dirs"];
+    if !dirs.is_array() {
+        *dirs = toml_edit::value(toml_edit::Array::new());
+    }
+    dirs.as_array_mut().expect("allowed_dirs was just made an array")
+}
@@ -0,0 +1,215 @@
+//! Keyboard mode, following the focused element
+//!
+//! A layer surface gets the keyboard according to its keyboard mode: exclusive takes it
+//! from every other window, on-demand when the surface is clicked, none never. The overlay
+//! wants it while the user types in the page and not otherwise, so the script below reports
+//! text fields (inputs, textareas, contenteditable) gaining and losing focus as `textFocus`,
+//! and the mode is worked out from that and the window's state, in one place:
+//!
+//! - A grab (the hotkey, the tray, screenshot-and-ask or the page's `keyboardFocus`: the
+//!   user didn't click, so the compositor won't hand over the keyboard) is exclusive until
+//!   the window is active and the compositor has settled, then lets go
+//! - A text field focused in the active window keeps on-demand (exclusive where the
+//!   compositor has no on-demand mode), so clicking another window still takes the keyboard
+//! - A text field focused or clicked while the window isn't active grabs
+//! - Otherwise, and whenever the window loses the keyboard, `[window] keyboard_mode`
+//!
+//! What differs between compositors is in `Quirks`.
+
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::ApplicationWindow;
+use gtk4_layer_shell::{KeyboardMode, LayerShell as _};
+use std::cell::RefCell;
+use std::sync::OnceLock;
+use std::time::Duration;
+use webkit6::prelude::*;
+use webkit6::{UserContentInjectedFrames, UserContentManager, UserScript, UserScriptInjectionTime};
+
+/// How long a grab waits for the window to become active before it gives up
+const GRAB_TIMEOUT: Duration = Duration::from_secs(1);
+
+const FOCUS_SCRIPT: &str = r#"
+(() => {
+  const NOT_TEXT = ['button', 'checkbox', 'color', 'file', 'hidden', 'image', 'radio', 'range', 'reset', 'submit'];
+  const isText = (element) =>
+    !!element &&
+    (element.isContentEditable ||
+      element.tagName === 'TEXTAREA' ||
+      (element.tagName === 'INPUT' && !NOT_TEXT.includes(element.type)));
+  let last = null;
+
+  const report = () => {
+    const focused = isText(document.activeElement);
+    if (focused === last) return;
+    last = focused;
+    window.webkit?.messageHandlers?.textFocus?.postMessage({ focused });
+  };
+  // Moving between fields fires focusout before focusin; report where focus ends up
+  const schedule = () => setTimeout(report);
+  document.addEventListener('focusin', schedule, true);
+  document.addEventListener('focusout', schedule, true);
+  // Clicking a field that already has focus fires neither, but without the keyboard it
+  // still needs one
+  document.addEventListener('pointerdown', (event) => {
+    if (isText(event.target)) {
+      window.webkit?.messageHandlers?.textFocus?.postMessage({ focused: true });
+    }
+  }, true);
+})();
+"#;
+
+/// Compositor differences in handing out the keyboard
+#[derive(Debug)]
+struct Quirks {
+    /// On-demand mode exists (layer-shell protocol version 4 and up); without it a text
+    /// field can only get the keyboard exclusively
+    on_demand: bool,
+    /// How long after the window becomes active a grab holds on. Hyprland takes the
+    /// keyboard back from a layer surface ~14ms after it maps or resizes.
+    settle: Duration,
+    /// Whether the chat opening (the page's resizeWindow) loses the keyboard, so it grabs
+    refocus_on_resize: bool,
+}
+
+impl Quirks {
+    fn detect() -> Quirks {
+        let on_demand = gtk4_layer_shell::protocol_version() >= 4;
+        let quirks = if std::env::var_os("SWAYSOCK").is_some() {
+            Quirks { on_demand, settle: Duration::ZERO, refocus_on_resize: false }
+        } else {
+            // Hyprland, and what's unknown is treated like it
+            Quirks { on_demand, settle: Duration::from_millis(50), refocus_on_resize: true }
+        };
+        crate::debug_log!("[FOCUS] Compositor quirks: {:?}", quirks);
+        quirks
+    }
+}
+
+fn quirks() -> &'static Quirks {
+    static QUIRKS: OnceLock<Quirks> = OnceLock::new();
+    QUIRKS.get_or_init(Quirks::detect)
+}
+
+#[derive(Default)]
+struct State {
+    text_focused: bool,
+    grabbing: bool,
+    /// Ends the grab: the settle time once the window is active, or the grab timeout
+    grab_end: Option<glib::SourceId>,
+}
+
+thread_local! {
+    static STATE: RefCell<State> = RefCell::new(State::default());
+}
+
+/// Listen for `textFocus` from pages loaded through `content_manager`, and for `window`
+/// gaining and losing the keyboard
+pub fn install(window: &ApplicationWindow, content_manager: &UserContentManager) {
+    let script = UserScript::new(
+        FOCUS_SCRIPT,
+        UserContentInjectedFrames::TopFrame,
+        UserScriptInjectionTime::Start,
+        &[],
+        &[],
+    );
+    content_manager.add_script(&script);
+    content_manager.register_script_message_handler("textFocus", None);
+
+    let window_for_script = window.clone();
+    content_manager.connect_script_message_received(Some("textFocus"), move |_manager, js_value| {
+        let Some(json) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json.as_str()) else { return };
+        set_text_focused(&window_for_script, parsed["focused"].as_bool().unwrap_or(false));
+    });
+
+    window.connect_is_active_notify(|window| {
+        if window.is_active() {
+            if STATE.with(|state| state.borrow().grabbing) {
+                end_grab_after(window, quirks().settle);
+            }
+        } else {
+            end_grab();
+            apply(window);
+        }
+    });
+    apply(window);
+}
+
+/// Take the keyboard without the user clicking the overlay
+pub fn grab(window: &ApplicationWindow) {
+    STATE.with(|state| state.borrow_mut().grabbing = true);
+    apply(window);
+    end_grab_after(window, if window.is_active() { quirks().settle } else { GRAB_TIMEOUT });
+}
+
+/// The page opened or closed the chat. On compositors that take the keyboard away when the
+/// surface resizes, opening it grabs.
+pub fn resized(window: &ApplicationWindow, expanding: bool) {
+    if expanding && quirks().refocus_on_resize {
+        grab(window);
+    }
+}
+
+/// Forget the page's focus, for when its web process is gone
+pub fn reset(window: &ApplicationWindow) {
+    end_grab();
+    STATE.with(|state| state.borrow_mut().text_focused = false);
+    apply(window);
+}
+
+/// Set the keyboard mode the current state calls for, e.g. after `[window] keyboard_mode`
+/// changed
+pub fn apply(window: &ApplicationWindow) {
+    let mode = STATE.with(|state| {
+        let state = state.borrow();
+        if state.grabbing {
+            KeyboardMode::Exclusive
+        } else if state.text_focused && window.is_active() {
+            if quirks().on_demand { KeyboardMode::OnDemand } else { KeyboardMode::Exclusive }
+        } else {
+            crate::config::current().window.keyboard_mode.mode()
+        }
+    });
+    if window.keyboard_mode() != mode {
+        crate::debug_log!("[FOCUS] Keyboard mode: {:?}", mode);
+        window.set_keyboard_mode(mode);
+    }
+}
+
+fn set_text_focused(window: &ApplicationWindow, focused: bool) {
+    crate::debug_log!("[FOCUS] Text field focused: {}", focused);
+    STATE.with(|state| state.borrow_mut().text_focused = focused);
+    if focused && !window.is_active() {
+        grab(window);
+    } else {
+        apply(window);
+    }
+}
+
+/// End the grab in `delay`, replacing any earlier end
+fn end_grab_after(window: &ApplicationWindow, delay: Duration) {
+    let window = window.clone();
+    let source = glib::timeout_add_local_once(delay, move || {
+        STATE.with(|state| {
+            let mut state = state.borrow_mut();
+            state.grab_end = None;
+            state.grabbing = false;
+        });
+        apply(&window);
+    });
+    if let Some(previous) = STATE.with(|state| state.borrow_mut().grab_end.replace(source)) {
+        previous.remove();
+    }
+}
+
+fn end_grab() {
+    let source = STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        state.grabbing = false;
+        state.grab_end.take()
+    });
+    if let Some(source) = source {
+        source.remove();
+    }
+}
@@ -0,0 +1,425 @@
+//! Built frontend: asset manifest, integrity and in-place updates
+//!
+//! A build writes `dist/asset-manifest.json` (vite.config.ts) listing every file with its
+//! size and SHA-256, the frontend's version, and the overlay version it was built with. The
+//! static server serves the manifest, and a dist with one is checked against it at startup.
+//! A dist without one (an older build) gets its manifest computed from the files instead.
+//!
+//! Frontend updates come from `[frontend] update_url`, a release's manifest with each file
+//! next to it at the same relative path, over https only. The updated page gets the same
+//! bridges as the bundled one (commands, files, sessions), so the manifest must also carry
+//! an Ed25519 signature, hex in `asset-manifest.json.sig` next to it, made with the key
+//! whose public half is built in (`DESKTOP_WAIFU_UPDATE_KEY`, hex, at compile time). A
+//! build without a key takes no updates. `check` compares the release with the dist being
+//! served.
+//! `apply` builds the new dist in `~/.local/share/desktop-waifu/frontend`: it copies
+//! unchanged files, downloads the rest and verifies every hash. It then points the
+//! `current` symlink there, an atomic rename, so the served dist is always complete. The
+//! signed manifest is kept with it and checked again at startup. An update only applies to
+//! the overlay version it names, so installing a new package falls back to the frontend
+//! that ships with it; one that fails either check does too.
+
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+
+pub const MANIFEST_FILE: &str = "asset-manifest.json";
+/// Hex Ed25519 signature over the manifest's bytes, next to it
+const SIGNATURE_FILE: &str = "asset-manifest.json.sig";
+/// Public key update manifests are signed with, as hex; set when building releases
+const UPDATE_KEY: Option<&str> = option_env!("DESKTOP_WAIFU_UPDATE_KEY");
+/// Largest single file an update may download
+const MAX_FILE_SIZE: u64 = 256 * 1024 * 1024;
+const MAX_MANIFEST_SIZE: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum UpdateError {
+    #[error("No update source is set ([frontend] update_url in config.toml)")]
+    NoSource,
+    #[error("The frontend is served by the Vite dev server")]
+    DevServer,
+    #[error("{0}: {1}")]
+    Fetch(String, reqwest::Error),
+    #[error("{0}: HTTP {1}")]
+    Status(String, reqwest::StatusCode),
+    #[error("Invalid manifest: {0}")]
+    Manifest(String),
+    #[error("Frontend updates need an https:// URL")]
+    Insecure,
+    #[error("This build has no key to check frontend updates with")]
+    NoKey,
+    #[error("The manifest's signature doesn't check out")]
+    BadSignature,
+    #[error("{0} does not match the manifest")]
+    Mismatch(String),
+    #[error("{}: {source}", .path.display())]
+    Io { path: PathBuf, source: std::io::Error },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileEntry {
+    pub size: u64,
+    /// Hex SHA-256 of the contents
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    /// The frontend's version (package.json)
+    pub version: String,
+    /// Overlay version the frontend was built with; updates for other versions are refused
+    #[serde(default)]
+    pub overlay_version: String,
+    /// Paths relative to the dist folder, with `/` separators
+    pub files: BTreeMap<String, FileEntry>,
+}
+
+impl Manifest {
+    /// Short hash over every file's hash; changes whenever any file does
+    pub fn digest(&self) -> String {
+        let mut hasher = Sha256::new();
+        for (path, entry) in &self.files {
+            hasher.update(path.as_bytes());
+            hasher.update([0]);
+            hasher.update(entry.sha256.as_bytes());
+        }
+        hex(&hasher.finalize()[..8])
+    }
+}
+
+/// What `check` found
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateInfo {
+    pub current: String,
+    /// Version of the newer frontend, if there is one for this overlay
+    pub available: Option<String>,
+    /// Files to download for it, and their total size
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Where applied updates are kept, each in its own folder, with `current` pointing at one
+pub fn updates_dir() -> PathBuf {
+    gtk4::glib::user_data_dir().join("desktop-waifu").join("frontend")
+}
+
+/// The manifest `dir` ships with, if any
+pub fn read_manifest(dir: &Path) -> Option<Manifest> {
+    let text = std::fs::read_to_string(dir.join(MANIFEST_FILE)).ok()?;
+    serde_json::from_str(&text)
+        .inspect_err(|e| tracing::warn!("Ignoring {}: {}", dir.join(MANIFEST_FILE).display(), e))
+        .ok()
+}
+
+/// The manifest `dir` ships with, or one computed from its files
+pub fn manifest(dir: &Path) -> std::io::Result<Manifest> {
+    if let Some(manifest) = read_manifest(dir) {
+        return Ok(manifest);
+    }
+    let mut files = BTreeMap::new();
+    collect(dir, dir, &mut files)?;
+    Ok(Manifest {
+        version: String::new(),
+        overlay_version: String::new(),
+        files,
+    })
+}
+
+/// Files in `dir` that are missing or differ from its shipped manifest; empty without one
+pub fn verify(dir: &Path) -> Vec<String> {
+    let Some(manifest) = read_manifest(dir) else { return Vec::new() };
+    manifest
+        .files
+        .iter()
+        .filter(|(path, entry)| {
+            let Some(relative) = safe_path(path) else { return true };
+            hash_file(&dir.join(relative)).ok().as_ref() != Some(*entry)
+        })
+        .map(|(path, _)| path.clone())
+        .collect()
+}
+
+/// The applied update to serve, if there is one for this overlay and it's intact. Checked
+/// once per run; an update applied later is served through `server::set_root`.
+pub fn installed_update() -> Option<PathBuf> {
+    static INSTALLED: OnceLock<Option<PathBuf>> = OnceLock::new();
+    INSTALLED.get_or_init(find_installed_update).clone()
+}
+
+fn find_installed_update() -> Option<PathBuf> {
+    let dir = updates_dir().join("current").canonicalize().ok()?;
+    let manifest_bytes = std::fs::read(dir.join(MANIFEST_FILE)).ok()?;
+    let signature = std::fs::read_to_string(dir.join(SIGNATURE_FILE)).unwrap_or_default();
+    if let Err(e) = verify_signature(&manifest_bytes, &signature) {
+        tracing::warn!("Not using the frontend update in {}: {}", dir.display(), e);
+        return None;
+    }
+    let manifest = read_manifest(&dir)?;
+    if manifest.overlay_version != env!("CARGO_PKG_VERSION") {
+        tracing::info!(
+            "Not using frontend update {}, which is for overlay {}",
+            manifest.version,
+            manifest.overlay_version
+        );
+        return None;
+    }
+    let damaged = verify(&dir);
+    if !damaged.is_empty() {
+        tracing::warn!("Frontend update {} is damaged ({}), not using it", manifest.version, damaged.join(", "));
+        return None;
+    }
+    Some(dir)
+}
+
+/// Compare the release manifest at `url` with the dist in `dist`
+pub async fn check(url: &str, dist: PathBuf) -> Result<UpdateInfo, UpdateError> {
+    let (remote, _) = fetch_manifest(url).await?;
+    let local = local_manifest(dist).await?;
+    let changed = changed_files(&local, &remote);
+    let newer = remote.overlay_version == env!("CARGO_PKG_VERSION") && !changed.is_empty();
+    Ok(UpdateInfo {
+        current: local.version,
+        available: newer.then(|| remote.version.clone()),
+        files: if newer { changed.len() } else { 0 },
+        bytes: if newer { changed.iter().map(|(_, entry)| entry.size).sum() } else { 0 },
+    })
+}
+
+/// Download the release at `url` next to `dist`, switch `current` to it and return its
+/// folder. Nothing changes for the running overlay until it's served from there.
+pub async fn apply(url: &str, dist: PathBuf) -> Result<PathBuf, UpdateError> {
+    let (remote, signed) = fetch_manifest(url).await?;
+    if remote.overlay_version != env!("CARGO_PKG_VERSION") {
+        return Err(UpdateError::Manifest(format!(
+            "it's for overlay {}, this is {}",
+            remote.overlay_version,
+            env!("CARGO_PKG_VERSION")
+        )));
+    }
+    let local = local_manifest(dist.clone()).await?;
+    let base = reqwest::Url::parse(url).map_err(|e| UpdateError::Manifest(e.to_string()))?;
+
+    let root = updates_dir();
+    let version: String = remote
+        .version
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '+'))
+        .collect();
+    let name = format!("{}-{}", version.trim_start_matches('.'), remote.digest());
+    let target = root.join(&name);
+    let staging = root.join(format!(".{}.partial", name));
+    let io_error = |path: &Path| {
+        let path = path.to_path_buf();
+        move |source: std::io::Error| UpdateError::Io { path, source }
+    };
+    if !target.is_dir() {
+        let _ = std::fs::remove_dir_all(&staging);
+        std::fs::create_dir_all(&staging).map_err(io_error(&staging))?;
+        let client = reqwest::Client::new();
+        for (path, entry) in &remote.files {
+            let relative = safe_path(path).ok_or_else(|| UpdateError::Manifest(format!("bad path '{}'", path)))?;
+            let destination = staging.join(&relative);
+            if let Some(parent) = destination.parent() {
+                std::fs::create_dir_all(parent).map_err(io_error(parent))?;
+            }
+            // Unchanged files are copied, unless the copy turns out damaged
+            if local.files.get(path) == Some(entry)
+                && std::fs::copy(dist.join(&relative), &destination).is_ok()
+                && hash_file(&destination).ok().as_ref() == Some(entry)
+            {
+                continue;
+            }
+            let file_url = base.join(path).map_err(|e| UpdateError::Manifest(e.to_string()))?;
+            let bytes = download(&client, file_url.as_str(), entry.size).await?;
+            if FileEntry::of(&bytes) != *entry {
+                return Err(UpdateError::Mismatch(path.clone()));
+            }
+            std::fs::write(&destination, &bytes).map_err(io_error(&destination))?;
+        }
+        // The manifest as it was signed, so startup can check it again
+        let manifest_path = staging.join(MANIFEST_FILE);
+        std::fs::write(&manifest_path, &signed.manifest).map_err(io_error(&manifest_path))?;
+        let signature_path = staging.join(SIGNATURE_FILE);
+        std::fs::write(&signature_path, &signed.signature).map_err(io_error(&signature_path))?;
+        std::fs::rename(&staging, &target).map_err(io_error(&target))?;
+    }
+
+    // A new symlink renamed over the old one: `current` always names a whole dist
+    let link = root.join("current");
+    let new_link = root.join(".current.new");
+    let _ = std::fs::remove_file(&new_link);
+    std::os::unix::fs::symlink(&name, &new_link).map_err(io_error(&new_link))?;
+    std::fs::rename(&new_link, &link).map_err(io_error(&link))?;
+    tracing::info!("Installed frontend {} in {}", remote.version, target.display());
+
+    // Older updates aren't needed any more; the one being served stays until the next start
+    let serving = dist.canonicalize().ok();
+    if let Ok(entries) = std::fs::read_dir(&root) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let keep = path == target || Some(&path) == serving.as_ref() || !path.is_dir() || path.is_symlink();
+            if !keep {
+                let _ = std::fs::remove_dir_all(&path);
+            }
+        }
+    }
+    Ok(target)
+}
+
+impl FileEntry {
+    fn of(bytes: &[u8]) -> Self {
+        Self {
+            size: bytes.len() as u64,
+            sha256: hex(&Sha256::digest(bytes)),
+        }
+    }
+}
+
+/// Files in `remote` that `local` doesn't have as they are
+fn changed_files<'a>(local: &Manifest, remote: &'a Manifest) -> Vec<(&'a String, &'a FileEntry)> {
+    remote
+        .files
+        .iter()
+        .filter(|(path, entry)| local.files.get(*path) != Some(*entry))
+        .collect()
+}
+
+async fn local_manifest(dist: PathBuf) -> Result<Manifest, UpdateError> {
+    let path = dist.clone();
+    crate::runtime::spawn_blocking(move || manifest(&dist))
+        .await
+        .map_err(|e| UpdateError::Io { path: path.clone(), source: std::io::Error::other(e) })?
+        .map_err(|source| UpdateError::Io { path, source })
+}
+
+/// A manifest and its signature, as downloaded
+struct Signed {
+    manifest: Vec<u8>,
+    signature: String,
+}
+
+/// The release manifest at `url`, once its signature checks out
+async fn fetch_manifest(url: &str) -> Result<(Manifest, Signed), UpdateError> {
+    let mut signature_url = reqwest::Url::parse(url).map_err(|e| UpdateError::Manifest(e.to_string()))?;
+    if signature_url.scheme() != "https" {
+        return Err(UpdateError::Insecure);
+    }
+    signature_url.set_path(&format!("{}.sig", signature_url.path()));
+
+    let client = reqwest::Client::new();
+    let bytes = download(&client, url, MAX_MANIFEST_SIZE as u64).await?;
+    let signature = download(&client, signature_url.as_str(), 1024).await?;
+    let signature = String::from_utf8_lossy(&signature).into_owned();
+    verify_signature(&bytes, &signature)?;
+    let manifest = serde_json::from_slice(&bytes).map_err(|e| UpdateError::Manifest(e.to_string()))?;
+    Ok((manifest, Signed { manifest: bytes, signature }))
+}
+
+/// Whether this build has a key to check updates with
+pub fn has_update_key() -> bool {
+    UPDATE_KEY.is_some()
+}
+
+/// Check `signature` (hex) over `manifest` against the built-in key. Also checks the
+/// release's SHA256SUMS for `--self-update` (update.rs).
+pub fn verify_signature(manifest: &[u8], signature: &str) -> Result<(), UpdateError> {
+    let key = UPDATE_KEY.ok_or(UpdateError::NoKey)?;
+    let key: [u8; 32] = unhex(key).and_then(|key| key.try_into().ok()).ok_or(UpdateError::NoKey)?;
+    let key = VerifyingKey::from_bytes(&key).map_err(|_| UpdateError::NoKey)?;
+    let signature: [u8; 64] = unhex(signature.trim())
+        .and_then(|signature| signature.try_into().ok())
+        .ok_or(UpdateError::BadSignature)?;
+    key.verify_strict(manifest, &Signature::from_bytes(&signature))
+        .map_err(|_| UpdateError::BadSignature)
+}
+
+/// The body at `url`, refusing anything larger than `limit` bytes
+async fn download(client: &reqwest::Client, url: &str, limit: u64) -> Result<Vec<u8>, UpdateError> {
+    let fetch_error = |e| UpdateError::Fetch(url.to_string(), e);
+    let mut response = client.get(url).send().await.map_err(fetch_error)?;
+    if !response.status().is_success() {
+        return Err(UpdateError::Status(url.to_string(), response.status()));
+    }
+    let limit = limit.min(MAX_FILE_SIZE);
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(fetch_error)? {
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() as u64 > limit {
+            return Err(UpdateError::Mismatch(url.to_string()));
+        }
+    }
+    Ok(bytes)
+}
+
+/// Every regular file under `dir`, except the manifest, keyed by its path from `root`
+fn collect(root: &Path, dir: &Path, files: &mut BTreeMap<String, FileEntry>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let kind = entry.file_type()?;
+        if kind.is_dir() {
+            collect(root, &path, files)?;
+        } else if kind.is_file() {
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            let key = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            if key != MANIFEST_FILE && key != SIGNATURE_FILE {
+                files.insert(key, hash_file(&path)?);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn hash_file(path: &Path) -> std::io::Result<FileEntry> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut size = 0u64;
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+    Ok(FileEntry {
+        size,
+        sha256: hex(&hasher.finalize()),
+    })
+}
+
+/// A manifest path as a relative path, or None if it could leave the dist folder
+fn safe_path(path: &str) -> Option<PathBuf> {
+    let relative = PathBuf::from(path);
+    let safe = !path.is_empty()
+        && path != MANIFEST_FILE
+        && path != SIGNATURE_FILE
+        && relative.components().all(|c| matches!(c, Component::Normal(_)));
+    safe.then_some(relative)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if text.len() % 2 != 0 || !text.is_ascii() {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect()
+}
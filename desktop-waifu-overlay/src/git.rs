@@ -0,0 +1,456 @@
+//! Git repository watcher for developer context
+//!
+//! Users register repositories from the frontend; a background thread polls them
+//! (branch, dirty state, ahead/behind, last CI run via `gh`) and reports changes so the
+//! character can mention uncommitted work and help write commit messages.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+// How often registered repositories are re-scanned
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+// CI status hits the network via `gh`, so refresh it far less often
+const CI_REFRESH_INTERVAL: Duration = Duration::from_secs(300);
+
+// Cap on diff text handed to the frontend for commit message generation
+const MAX_DIFF_BYTES: usize = 64 * 1024;
+
+/// Latest CI run for the current branch, as reported by `gh run list`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CiStatus {
+    pub workflow: String,
+    pub status: String,
+    pub conclusion: Option<String>,
+    pub url: String,
+}
+
+/// Snapshot of a watched repository
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepoStatus {
+    pub path: PathBuf,
+    pub name: String,
+    pub branch: Option<String>,
+    pub upstream: Option<String>,
+    pub ahead: u32,
+    pub behind: u32,
+    pub changed_files: u32,
+    pub untracked_files: u32,
+    /// Unix timestamp of the oldest uncommitted modification (file mtime)
+    pub dirty_since: Option<u64>,
+    /// Unix timestamp of the HEAD commit
+    pub last_commit_at: Option<u64>,
+    pub ci: Option<CiStatus>,
+}
+
+impl RepoStatus {
+    pub fn is_dirty(&self) -> bool {
+        self.changed_files > 0 || self.untracked_files > 0
+    }
+}
+
+/// Material for drafting a commit message
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitContext {
+    pub path: PathBuf,
+    /// True if the diff is the staged diff, false if it fell back to the working tree
+    pub staged: bool,
+    pub stat: String,
+    pub diff: String,
+    pub truncated: bool,
+    pub recent_messages: Vec<String>,
+}
+
+#[derive(Default)]
+struct WatcherState {
+    repos: Vec<PathBuf>,
+    statuses: HashMap<PathBuf, RepoStatus>,
+    ci_checked: HashMap<PathBuf, SystemTime>,
+}
+
+/// Handle to the background git watcher (cheap to clone)
+#[derive(Clone)]
+pub struct GitWatcher {
+    state: Arc<Mutex<WatcherState>>,
+}
+
+/// Spawn the git watcher task
+/// Returns the watcher handle and a receiver yielding statuses whenever a repo changes
+pub fn spawn_git_watcher() -> (GitWatcher, async_channel::Receiver<RepoStatus>) {
+    let (tx, rx) = async_channel::unbounded();
+
+    let state = WatcherState {
+        repos: load_registered_repos(),
+        ..Default::default()
+    };
+    let watcher = GitWatcher {
+        state: Arc::new(Mutex::new(state)),
+    };
+
+    let watcher_for_task = watcher.clone();
+    let shutdown = crate::runtime::shutdown_token();
+    crate::runtime::spawn(async move {
+        info!("Git watcher started");
+        loop {
+            // git and gh are blocking subprocesses, keep them off the async workers
+            let watcher = watcher_for_task.clone();
+            let changed = crate::runtime::spawn_blocking(move || watcher.refresh_all())
+                .await
+                .unwrap_or_default();
+            for status in changed {
+                if tx.send(status).await.is_err() {
+                    crate::debug_log!("[GIT] Receiver dropped, stopping watcher");
+                    return;
+                }
+            }
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            }
+        }
+        info!("Git watcher stopped");
+    });
+
+    (watcher, rx)
+}
+
+impl GitWatcher {
+    /// Register a repository (any path inside the work tree) and return its initial status
+    pub fn register(&self, path: &Path) -> Result<RepoStatus, String> {
+        let root = repo_root(path)?;
+        let status = read_status(&root, true)?;
+
+        let mut state = self.state.lock().unwrap();
+        if !state.repos.contains(&root) {
+            state.repos.push(root.clone());
+            save_registered_repos(&state.repos);
+            info!("Registered git repository {:?}", root);
+        }
+        state.ci_checked.insert(root.clone(), SystemTime::now());
+        state.statuses.insert(root, status.clone());
+        Ok(status)
+    }
+
+    /// Stop watching a repository. Returns false if it wasn't registered.
+    pub fn unregister(&self, path: &Path) -> bool {
+        let root = repo_root(path).unwrap_or_else(|_| path.to_path_buf());
+        let mut state = self.state.lock().unwrap();
+        let before = state.repos.len();
+        state.repos.retain(|p| p != &root);
+        state.statuses.remove(&root);
+        state.ci_checked.remove(&root);
+        let removed = state.repos.len() != before;
+        if removed {
+            save_registered_repos(&state.repos);
+        }
+        removed
+    }
+
+    /// Last known status of every registered repository
+    pub fn statuses(&self) -> Vec<RepoStatus> {
+        let state = self.state.lock().unwrap();
+        state
+            .repos
+            .iter()
+            .filter_map(|p| state.statuses.get(p).cloned())
+            .collect()
+    }
+
+    /// Human-readable summary suitable for injecting into the assistant's context
+    pub fn context_summary(&self) -> String {
+        let now = unix_now();
+        self.statuses()
+            .iter()
+            .map(|s| describe_status(s, now))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Re-read every registered repo, returning the statuses that changed
+    fn refresh_all(&self) -> Vec<RepoStatus> {
+        let (repos, ci_due): (Vec<PathBuf>, Vec<bool>) = {
+            let state = self.state.lock().unwrap();
+            state
+                .repos
+                .iter()
+                .map(|p| {
+                    let due = state
+                        .ci_checked
+                        .get(p)
+                        .and_then(|t| t.elapsed().ok())
+                        .is_none_or(|age| age >= CI_REFRESH_INTERVAL);
+                    (p.clone(), due)
+                })
+                .unzip()
+        };
+
+        let mut changed = Vec::new();
+        for (path, ci_due) in repos.into_iter().zip(ci_due) {
+            let mut status = match read_status(&path, ci_due) {
+                Ok(s) => s,
+                Err(e) => {
+                    crate::debug_log!("[GIT] Failed to read status of {:?}: {}", path, e);
+                    continue;
+                }
+            };
+
+            let mut state = self.state.lock().unwrap();
+            // Repo may have been unregistered while we were scanning
+            if !state.repos.contains(&path) {
+                continue;
+            }
+            if ci_due {
+                state.ci_checked.insert(path.clone(), SystemTime::now());
+            } else if let Some(prev) = state.statuses.get(&path) {
+                status.ci = prev.ci.clone();
+            }
+            if state.statuses.get(&path) != Some(&status) {
+                state.statuses.insert(path, status.clone());
+                changed.push(status);
+            }
+        }
+        changed
+    }
+}
+
+/// Gather the diff and recent history needed to draft a commit message
+pub fn commit_context(path: &Path) -> Result<CommitContext, String> {
+    let root = repo_root(path)?;
+
+    let staged_stat = git(&root, &["diff", "--cached", "--stat"])?;
+    let staged = !staged_stat.trim().is_empty();
+    let (stat, mut diff) = if staged {
+        (staged_stat, git(&root, &["diff", "--cached"])?)
+    } else {
+        (git(&root, &["diff", "--stat"])?, git(&root, &["diff"])?)
+    };
+
+    let truncated = diff.len() > MAX_DIFF_BYTES;
+    if truncated {
+        let mut cut = MAX_DIFF_BYTES;
+        while !diff.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        diff.truncate(cut);
+    }
+
+    let recent_messages = git(&root, &["log", "-10", "--format=%s"])
+        .map(|out| out.lines().map(str::to_string).collect())
+        .unwrap_or_default();
+
+    Ok(CommitContext {
+        path: root,
+        staged,
+        stat,
+        diff,
+        truncated,
+        recent_messages,
+    })
+}
+
+/// Run a git subcommand in `repo`, returning stdout
+fn git(repo: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Resolve any path inside a work tree to the repository root
+fn repo_root(path: &Path) -> Result<PathBuf, String> {
+    let out = git(path, &["rev-parse", "--show-toplevel"])?;
+    Ok(PathBuf::from(out.trim()))
+}
+
+fn read_status(root: &Path, include_ci: bool) -> Result<RepoStatus, String> {
+    let porcelain = git(root, &["status", "--porcelain=v2", "--branch"])?;
+
+    let mut status = RepoStatus {
+        path: root.to_path_buf(),
+        name: root
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("repo")
+            .to_string(),
+        branch: None,
+        upstream: None,
+        ahead: 0,
+        behind: 0,
+        changed_files: 0,
+        untracked_files: 0,
+        dirty_since: None,
+        last_commit_at: None,
+        ci: None,
+    };
+
+    let mut oldest_change: Option<u64> = None;
+    for line in porcelain.lines() {
+        let changed_path = if let Some(head) = line.strip_prefix("# branch.head ") {
+            if head != "(detached)" {
+                status.branch = Some(head.to_string());
+            }
+            None
+        } else if let Some(upstream) = line.strip_prefix("# branch.upstream ") {
+            status.upstream = Some(upstream.to_string());
+            None
+        } else if let Some(ab) = line.strip_prefix("# branch.ab ") {
+            // Format: "+<ahead> -<behind>"
+            let mut parts = ab.split_whitespace();
+            status.ahead = parts.next().and_then(|a| a.trim_start_matches('+').parse().ok()).unwrap_or(0);
+            status.behind = parts.next().and_then(|b| b.trim_start_matches('-').parse().ok()).unwrap_or(0);
+            None
+        } else if let Some(path) = line.strip_prefix("? ") {
+            status.untracked_files += 1;
+            Some(path)
+        } else if line.starts_with("1 ") {
+            status.changed_files += 1;
+            line.splitn(9, ' ').nth(8)
+        } else if line.starts_with("2 ") {
+            // Renames carry "<path>\t<origPath>"
+            status.changed_files += 1;
+            line.splitn(10, ' ').nth(9).and_then(|p| p.split('\t').next())
+        } else if line.starts_with("u ") {
+            status.changed_files += 1;
+            line.splitn(11, ' ').nth(10)
+        } else {
+            None
+        };
+
+        if let Some(mtime) = changed_path.and_then(|p| file_mtime(&root.join(p))) {
+            oldest_change = Some(oldest_change.map_or(mtime, |o| o.min(mtime)));
+        }
+    }
+    status.dirty_since = oldest_change;
+
+    status.last_commit_at = git(root, &["log", "-1", "--format=%ct"])
+        .ok()
+        .and_then(|s| s.trim().parse().ok());
+
+    if include_ci && let Some(ref branch) = status.branch {
+        status.ci = read_ci_status(root, branch);
+    }
+
+    Ok(status)
+}
+
+/// Query the latest GitHub Actions run for a branch (requires an authenticated `gh`)
+fn read_ci_status(root: &Path, branch: &str) -> Option<CiStatus> {
+    let output = Command::new("gh")
+        .current_dir(root)
+        .args(["run", "list", "--limit", "1", "--branch", branch])
+        .args(["--json", "status,conclusion,workflowName,url"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let runs: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+    let run = runs.as_array()?.first()?;
+    Some(CiStatus {
+        workflow: run["workflowName"].as_str().unwrap_or("").to_string(),
+        status: run["status"].as_str().unwrap_or("").to_string(),
+        conclusion: run["conclusion"]
+            .as_str()
+            .filter(|c| !c.is_empty())
+            .map(str::to_string),
+        url: run["url"].as_str().unwrap_or("").to_string(),
+    })
+}
+
+fn file_mtime(path: &Path) -> Option<u64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    modified.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Format a duration in seconds as "3 hours", "12 minutes", etc.
+fn format_age(secs: u64) -> String {
+    let (value, unit) = match secs {
+        0..=59 => return "moments".to_string(),
+        60..=3599 => (secs / 60, "minute"),
+        3600..=86399 => (secs / 3600, "hour"),
+        _ => (secs / 86400, "day"),
+    };
+    format!("{} {}{}", value, unit, if value == 1 { "" } else { "s" })
+}
+
+fn describe_status(status: &RepoStatus, now: u64) -> String {
+    let mut parts = vec![format!(
+        "{} ({})",
+        status.name,
+        status.branch.as_deref().unwrap_or("detached HEAD")
+    )];
+
+    if status.is_dirty() {
+        let mut dirty = format!(
+            "{} changed and {} untracked files",
+            status.changed_files, status.untracked_files
+        );
+        if let Some(since) = status.dirty_since {
+            dirty.push_str(&format!(", uncommitted for {}", format_age(now.saturating_sub(since))));
+        }
+        parts.push(dirty);
+    } else {
+        parts.push("clean".to_string());
+    }
+
+    if status.ahead > 0 || status.behind > 0 {
+        parts.push(format!("{} ahead / {} behind upstream", status.ahead, status.behind));
+    }
+    if let Some(ref ci) = status.ci {
+        let outcome = ci.conclusion.as_deref().unwrap_or(&ci.status);
+        parts.push(format!("last CI run '{}': {}", ci.workflow, outcome));
+    }
+
+    parts.join(", ")
+}
+
+fn repos_file() -> PathBuf {
+    gtk4::glib::user_data_dir()
+        .join("desktop-waifu")
+        .join("git-repos.json")
+}
+
+fn load_registered_repos() -> Vec<PathBuf> {
+    std::fs::read_to_string(repos_file())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_registered_repos(repos: &[PathBuf]) {
+    let path = repos_file();
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    match serde_json::to_string_pretty(repos) {
+        Ok(json) => {
+            if let Err(e) = std::fs::write(&path, json) {
+                tracing::warn!("Failed to save registered repos: {}", e);
+            }
+        }
+        Err(e) => tracing::warn!("Failed to serialize registered repos: {}", e),
+    }
+}
@@ -0,0 +1,169 @@
+//! Localization of backend-facing strings (tray menu, dialogs, notifications, CLI)
+//!
+//! Messages are Fluent catalogs embedded from `locales/*.ftl`. The locale comes from
+//! `--locale`, then `DESKTOP_WAIFU_LOCALE`, then the usual POSIX variables
+//! (`LANGUAGE`, `LC_ALL`, `LC_MESSAGES`, `LANG`). Lookups fall back to en-US for
+//! locales or messages that aren't translated.
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+use fluent_langneg::{negotiate_languages, NegotiationStrategy};
+use std::sync::OnceLock;
+use unic_langid::LanguageIdentifier;
+
+const FALLBACK_LOCALE: &str = "en-US";
+
+const CATALOGS: &[(&str, &str)] = &[
+    ("en-US", include_str!("../locales/en-US.ftl")),
+    ("de", include_str!("../locales/de.ftl")),
+    ("es", include_str!("../locales/es.ftl")),
+    ("ja", include_str!("../locales/ja.ftl")),
+];
+
+struct Localizer {
+    locale: String,
+    /// Negotiated locales in preference order, always ending with en-US
+    bundles: Vec<FluentBundle<FluentResource>>,
+}
+
+static LOCALIZER: OnceLock<Localizer> = OnceLock::new();
+
+/// Pick the locale. Call once, before any string is translated; `requested` is the
+/// `--locale` override. Without a call the system locale is used.
+pub fn init(requested: Option<&str>) {
+    let _ = LOCALIZER.set(build(requested));
+}
+
+/// Locale strings are translated into, e.g. "de" or "en-US"
+pub fn current_locale() -> &'static str {
+    &localizer().locale
+}
+
+/// Translate `id`, substituting `args`. Unknown ids are returned as-is.
+/// Usually called through `tr!`.
+pub fn tr(id: &str, args: &[(&str, FluentValue<'_>)]) -> String {
+    let args = (!args.is_empty()).then(|| {
+        let mut fluent_args = FluentArgs::new();
+        for (name, value) in args {
+            fluent_args.set(*name, value.clone());
+        }
+        fluent_args
+    });
+
+    for bundle in &localizer().bundles {
+        let Some(pattern) = bundle.get_message(id).and_then(|m| m.value()) else {
+            continue;
+        };
+        let mut errors = Vec::new();
+        let text = bundle.format_pattern(pattern, args.as_ref(), &mut errors);
+        if !errors.is_empty() {
+            tracing::warn!("Errors formatting message '{}': {:?}", id, errors);
+        }
+        return text.into_owned();
+    }
+
+    tracing::warn!("Missing translation for '{}'", id);
+    id.to_string()
+}
+
+/// Translate a message: `tr!("tray-quit")`, `tr!("plugin-removed", name = name)`
+#[macro_export]
+macro_rules! tr {
+    ($id:expr) => {
+        $crate::i18n::tr($id, &[])
+    };
+    ($id:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::tr(
+            $id,
+            &[$((stringify!($name), ::fluent_bundle::FluentValue::from($value))),+],
+        )
+    };
+}
+
+fn localizer() -> &'static Localizer {
+    LOCALIZER.get_or_init(|| build(None))
+}
+
+fn build(requested: Option<&str>) -> Localizer {
+    let requested = requested_locales(requested);
+    let available: Vec<LanguageIdentifier> = CATALOGS
+        .iter()
+        .map(|(id, _)| id.parse().expect("built-in locale id is valid"))
+        .collect();
+    let fallback: LanguageIdentifier = FALLBACK_LOCALE.parse().expect("fallback locale id is valid");
+
+    let negotiated = negotiate_languages(
+        &requested,
+        &available,
+        Some(&fallback),
+        NegotiationStrategy::Filtering,
+    );
+
+    let bundles: Vec<_> = negotiated
+        .iter()
+        .filter_map(|lang| {
+            let (_, source) = CATALOGS.iter().find(|(id, _)| lang.to_string() == *id)?;
+            Some(bundle_for(lang, source))
+        })
+        .collect();
+
+    Localizer {
+        locale: negotiated
+            .first()
+            .map(|lang| lang.to_string())
+            .unwrap_or_else(|| FALLBACK_LOCALE.to_string()),
+        bundles,
+    }
+}
+
+fn bundle_for(lang: &LanguageIdentifier, source: &str) -> FluentBundle<FluentResource> {
+    let resource = match FluentResource::try_new(source.to_string()) {
+        Ok(resource) => resource,
+        Err((resource, errors)) => {
+            tracing::warn!("Errors parsing {} catalog: {:?}", lang, errors);
+            resource
+        }
+    };
+    let mut bundle = FluentBundle::new_concurrent(vec![lang.clone()]);
+    // Unicode isolation marks show up literally in terminals and tray menus
+    bundle.set_use_isolating(false);
+    if let Err(errors) = bundle.add_resource(resource) {
+        tracing::warn!("Errors loading {} catalog: {:?}", lang, errors);
+    }
+    bundle
+}
+
+/// Locales the user asked for, most preferred first
+fn requested_locales(requested: Option<&str>) -> Vec<LanguageIdentifier> {
+    let mut candidates: Vec<String> = Vec::new();
+    if let Some(locale) = requested {
+        candidates.push(locale.to_string());
+    }
+    if let Ok(locale) = std::env::var("DESKTOP_WAIFU_LOCALE") {
+        candidates.push(locale);
+    }
+    // LANGUAGE is a colon-separated priority list; the rest name a single locale
+    if let Ok(list) = std::env::var("LANGUAGE") {
+        candidates.extend(list.split(':').map(str::to_string));
+    }
+    for var in ["LC_ALL", "LC_MESSAGES", "LANG"] {
+        if let Ok(locale) = std::env::var(var) {
+            candidates.push(locale);
+        }
+    }
+
+    candidates
+        .iter()
+        .filter_map(|locale| parse_posix_locale(locale))
+        .collect()
+}
+
+/// "de_DE.UTF-8@euro" -> de-DE. "C" and "POSIX" mean untranslated, i.e. en-US.
+pub fn parse_posix_locale(locale: &str) -> Option<LanguageIdentifier> {
+    let tag = locale.split(['.', '@']).next()?.trim();
+    match tag {
+        "" => None,
+        "C" | "POSIX" => FALLBACK_LOCALE.parse().ok(),
+        _ => tag.replace('_', "-").parse().ok(),
+    }
+}
@@ -0,0 +1,144 @@
+//! Input region synced from the page layout
+//!
+//! Rather than computing rectangles for setInputRegion itself, the frontend can tag the
+//! elements that take clicks with `data-waifu-interactive` and send `{ mode: 'auto' }`.
+//! The script below, injected into every page, then measures the tagged elements whenever
+//! the layout may have changed (DOM and attribute mutations, element and window resizes,
+//! transitions and animations ending, and a slow poll for anything else) and reports their
+//! rectangles back as `{ mode: 'auto', rects }` when they differ from the last report. The
+//! overlay unites them into the surface's input region, so a chat panel that grows or
+//! moves stays clickable without the frontend keeping track of it. Any other mode stops
+//! the syncing.
+//!
+//! Click-through (a shortcut or the tray) empties the input region whatever the page asks
+//! for; the last requested region comes back when it's turned off.
+
+use cairo::{RectangleInt, Region};
+use gtk4::gio;
+use gtk4::prelude::*;
+use std::cell::{Cell, RefCell};
+use webkit6::prelude::*;
+use webkit6::{UserContentInjectedFrames, UserContentManager, UserScript, UserScriptInjectionTime, WebView};
+
+thread_local! {
+    /// Input region last asked for, put back when click-through ends
+    static REQUESTED: RefCell<Option<Region>> = const { RefCell::new(None) };
+    static CLICK_THROUGH: Cell<bool> = const { Cell::new(false) };
+}
+
+const SYNC_SCRIPT: &str = r#"
+(() => {
+  const SELECTOR = '[data-waifu-interactive]';
+  const POLL_MS = 500;
+  let enabled = false;
+  let frame = null;
+  let last = '';
+  let poll = null;
+
+  const measure = () => {
+    frame = null;
+    if (!enabled) return;
+    const rects = [];
+    for (const element of document.querySelectorAll(SELECTOR)) {
+      for (const rect of element.getClientRects()) {
+        if (rect.width > 0 && rect.height > 0) {
+          rects.push({ x: rect.x, y: rect.y, width: rect.width, height: rect.height });
+        }
+      }
+    }
+    const key = JSON.stringify(rects);
+    if (key === last) return;
+    last = key;
+    window.webkit?.messageHandlers?.setInputRegion?.postMessage({ mode: 'auto', rects });
+  };
+  const schedule = () => {
+    if (enabled && frame === null) frame = requestAnimationFrame(measure);
+  };
+
+  const resizeObserver = new ResizeObserver(schedule);
+  const observeTagged = () => {
+    resizeObserver.disconnect();
+    document.querySelectorAll(SELECTOR).forEach((element) => resizeObserver.observe(element));
+  };
+  const mutationObserver = new MutationObserver(() => {
+    observeTagged();
+    schedule();
+  });
+
+  window.__waifuInputSync = {
+    start() {
+      if (enabled) return;
+      enabled = true;
+      last = '';
+      mutationObserver.observe(document.documentElement, { subtree: true, childList: true, attributes: true });
+      observeTagged();
+      window.addEventListener('resize', schedule);
+      document.addEventListener('transitionend', schedule, true);
+      document.addEventListener('animationend', schedule, true);
+      poll = setInterval(schedule, POLL_MS);
+      schedule();
+    },
+    stop() {
+      enabled = false;
+      mutationObserver.disconnect();
+      resizeObserver.disconnect();
+      window.removeEventListener('resize', schedule);
+      document.removeEventListener('transitionend', schedule, true);
+      document.removeEventListener('animationend', schedule, true);
+      clearInterval(poll);
+    },
+  };
+})();
+"#;
+
+/// Inject the syncing script into every page loaded through `content_manager`
+pub fn install(content_manager: &UserContentManager) {
+    let script = UserScript::new(
+        SYNC_SCRIPT,
+        UserContentInjectedFrames::TopFrame,
+        UserScriptInjectionTime::Start,
+        &[],
+        &[],
+    );
+    content_manager.add_script(&script);
+}
+
+/// Start following the layout of `data-waifu-interactive` elements, or stop
+pub fn set_syncing(webview: &WebView, enabled: bool) {
+    let js = if enabled {
+        "window.__waifuInputSync?.start()"
+    } else {
+        "window.__waifuInputSync?.stop()"
+    };
+    webview.evaluate_javascript(js, None, None, None::<&gio::Cancellable>, |_| {});
+}
+
+/// Set the input region of `window`'s surface; while click-through is on it's only kept
+/// for later
+pub fn apply(window: &impl IsA<gtk4::Native>, region: &Region) {
+    REQUESTED.with(|requested| *requested.borrow_mut() = Some(region.clone()));
+    if !CLICK_THROUGH.get()
+        && let Some(surface) = window.surface()
+    {
+        surface.set_input_region(region);
+    }
+}
+
+pub fn click_through() -> bool {
+    CLICK_THROUGH.get()
+}
+
+/// Let every click through to the windows below, or take input where it was asked for again
+pub fn set_click_through(window: &impl IsA<gtk4::Native>, enabled: bool) {
+    CLICK_THROUGH.set(enabled);
+    let Some(surface) = window.surface() else { return };
+    let region = if enabled {
+        Region::create()
+    } else {
+        REQUESTED.with(|requested| requested.borrow().clone()).unwrap_or_else(|| {
+            // Nothing was asked for, so the whole window took input
+            Region::create_rectangle(&RectangleInt::new(0, 0, surface.width(), surface.height()))
+        })
+    };
+    surface.set_input_region(&region);
+}
@@ -1,25 +1,53 @@
 //! IPC module for communication with the main Tauri application
 //!
-//! Uses Unix sockets for bidirectional communication.
+//! Uses Unix sockets for bidirectional communication. Each line a client sends is either a
+//! plain verb from the CLI (`toggle`, `show`, `profile work`, ...) or a JSON
+//! `OverlayCommand` such as `{"type":"PlayAnimation","data":"wave"}`. Clients that send
+//! JSON get `OverlayEvent`s back as JSON lines: an `Error` for commands that don't parse,
+//! and every event the overlay emits (clicks, finished animations, ready, visibility) from
+//! then on. The `subscribe` verb asks for the events without sending a command, so scripts
+//! and status bars can simply keep the socket open and read lines.
+//! Clients that need answers use the framed protocol in `protocol` instead, which
+//! correlates responses with request ids; the CLI uses it to report failures.
 
 use serde::{Deserialize, Serialize};
-use std::io::{Read, Write};
-use std::os::unix::net::{UnixListener, UnixStream};
+use std::os::unix::net::UnixStream;
 use std::path::PathBuf;
-use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
 
-/// Commands sent from Tauri to the overlay
+pub mod protocol;
+
+pub use protocol::Request;
+use protocol::{RequestFrame, ResponseFrame};
+
+/// Longest accepted command line (commands are short verbs plus arguments)
+const MAX_COMMAND_LEN: usize = 4096;
+/// Connections that send nothing for this long are closed (unless they receive events)
+const CONNECTION_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long the CLI waits for the overlay to answer
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+/// Events and replies queued for one client; one that falls this far behind is dropped
+const OUTBOX_CAPACITY: usize = 256;
+
+/// Commands sent from Tauri, companions and JSON IPC clients to the overlay
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "data")]
 pub enum OverlayCommand {
-    /// Set the character's facial expression
-    SetExpression(String),
-    /// Play an animation
-    PlayAnimation(String),
+    /// Set the character's facial expression, for a while (see expressions.rs)
+    SetExpression(ExpressionRequest),
+    /// Show how something went: "success" or "error"
+    ExpressionCue(crate::expressions::Cue),
+    /// Play an animation clip (see animations.rs), by name or with blending options
+    PlayAnimation(AnimationRequest),
     /// Move the overlay to a new position
     SetPosition { x: i32, y: i32 },
     /// Set the overlay scale
     SetScale(f32),
+    /// Dock the chat: "follow-character", "left-panel", "right-panel" or "floating"
+    SetChatDock(crate::config::ChatDock),
     /// Show the overlay
     Show,
     /// Hide the overlay
@@ -34,6 +62,81 @@ pub enum OverlayCommand {
     SetAnimationState(AnimationState),
 }
 
+/// An expression to show: `"happy"`, or `{"expression": "happy", "duration": 2.5}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "ExpressionRequestForm")]
+pub struct ExpressionRequest {
+    pub expression: String,
+    /// Seconds before the face goes back to what the assistant is doing (default
+    /// `[expressions] hold`); 0 holds it until the next one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ExpressionRequestForm {
+    Name(String),
+    Options { expression: String, duration: Option<f32> },
+}
+
+impl From<ExpressionRequestForm> for ExpressionRequest {
+    fn from(form: ExpressionRequestForm) -> Self {
+        match form {
+            ExpressionRequestForm::Name(expression) => Self { expression, duration: None },
+            ExpressionRequestForm::Options { expression, duration } => Self { expression, duration },
+        }
+    }
+}
+
+/// A clip to play: `"wave"`, or `{"name": "wave", "fade": 0.5, "repeat": 2, "speed": 1.5}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "AnimationRequestForm")]
+pub struct AnimationRequest {
+    pub name: String,
+    /// Seconds to cross-fade from the current animation, and back when done (default 0.3)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fade: Option<f32>,
+    /// Times to play it; 0 loops it until something else plays (default 1)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat: Option<u32>,
+    /// Playback speed (default 1)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed: Option<f32>,
+}
+
+impl AnimationRequest {
+    pub fn named(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            fade: None,
+            repeat: None,
+            speed: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AnimationRequestForm {
+    Name(String),
+    Options {
+        name: String,
+        fade: Option<f32>,
+        repeat: Option<u32>,
+        speed: Option<f32>,
+    },
+}
+
+impl From<AnimationRequestForm> for AnimationRequest {
+    fn from(form: AnimationRequestForm) -> Self {
+        match form {
+            AnimationRequestForm::Name(name) => Self::named(name),
+            AnimationRequestForm::Options { name, fade, repeat, speed } => Self { name, fade, repeat, speed },
+        }
+    }
+}
+
 /// Animation state for the character
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
@@ -44,16 +147,18 @@ pub enum AnimationState {
     Listening,
 }
 
-/// Events sent from overlay to Tauri
+/// Events sent from overlay to Tauri, companions and JSON IPC clients
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "data")]
 pub enum OverlayEvent {
     /// User clicked on the pet
     Clicked,
-    /// An animation completed
+    /// A clip started by PlayAnimation finished or was replaced, by name
     AnimationComplete(String),
     /// Overlay is ready
     Ready,
+    /// The overlay was shown (true) or hidden (false)
+    VisibilityChanged(bool),
     /// An error occurred
     Error(String),
 }
@@ -64,29 +169,186 @@ pub fn socket_path() -> PathBuf {
     PathBuf::from(format!("/run/user/{}/desktop-waifu.sock", uid))
 }
 
-/// Send a command to the running instance via Unix socket
-pub fn send_command(cmd: &str) -> Result<(), std::io::Error> {
+/// Remove the socket file so the next start (and CLI clients) don't see a stale socket
+pub fn remove_socket() {
+    let _ = std::fs::remove_file(socket_path());
+}
+
+/// Why a command sent to the running instance failed
+#[derive(Debug, thiserror::Error)]
+pub enum IpcError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    /// The overlay received the request but refused or failed it
+    #[error("{0}")]
+    Rejected(String),
+    #[error("invalid reply: {0}")]
+    Protocol(String),
+}
+
+/// Send a command line to the running instance and wait for its acknowledgement
+pub fn send_command(cmd: &str) -> Result<serde_json::Value, IpcError> {
+    request(Request::Text(cmd.to_string()))
+}
+
+/// Subscribe to the running instance's events and pass each one, as a JSON line, to
+/// `on_event` until the overlay closes the connection
+pub fn subscribe(mut on_event: impl FnMut(&str)) -> Result<(), IpcError> {
+    use std::io::{BufRead, Write};
+
+    let mut stream = UnixStream::connect(socket_path())?;
+    stream.write_all(b"subscribe\n")?;
+    for line in std::io::BufReader::new(stream).lines() {
+        on_event(&line?);
+    }
+    Ok(())
+}
+
+/// Send a request to the running instance via Unix socket and wait for the result
+pub fn request(request: Request) -> Result<serde_json::Value, IpcError> {
     let socket_path = socket_path();
     crate::debug_log!("[IPC] Connecting to socket at {:?}", socket_path);
     let mut stream = UnixStream::connect(&socket_path)?;
-    crate::debug_log!("[IPC] Connected, sending command: {}", cmd);
-    stream.write_all(cmd.as_bytes())?;
-    crate::debug_log!("[IPC] Command sent successfully");
-    Ok(())
+    stream.set_read_timeout(Some(REPLY_TIMEOUT))?;
+    crate::debug_log!("[IPC] Connected, sending request: {:?}", request);
+    protocol::write_frame_blocking(&mut stream, &RequestFrame { id: 1, request })?;
+
+    // Events may arrive before the response; skip them
+    loop {
+        let body = protocol::read_frame_blocking(&mut stream)?;
+        let frame: ResponseFrame = serde_json::from_slice(&body).map_err(|e| IpcError::Protocol(e.to_string()))?;
+        if frame.id != Some(1) {
+            continue;
+        }
+        crate::debug_log!("[IPC] Reply: {:?}", frame);
+        return match frame.error {
+            Some(error) => Err(IpcError::Rejected(error)),
+            None => Ok(frame.result.unwrap_or_default()),
+        };
+    }
+}
+
+/// Bytes queued for one client's writer task
+type Outbox = async_channel::Sender<Vec<u8>>;
+
+/// A request from an IPC client, answered with `respond`
+#[derive(Debug)]
+pub struct IpcMessage {
+    pub request: Request,
+    responder: Responder,
+}
+
+#[derive(Debug)]
+enum Responder {
+    /// Plain command lines get no reply
+    None,
+    /// Line-mode JSON clients only hear about failures, as `OverlayEvent::Error`
+    Line(Outbox),
+    /// Framed clients get a response with the request's id
+    Frame(u64, Outbox),
+    /// REST API requests (api.rs) wait for the result
+    Reply(tokio::sync::oneshot::Sender<Result<serde_json::Value, String>>),
+}
+
+impl IpcMessage {
+    /// A request from inside the overlay (e.g. a global shortcut) that nobody waits on
+    pub fn local(request: Request) -> Self {
+        Self {
+            request,
+            responder: Responder::None,
+        }
+    }
+
+    /// A request whose result comes back on the returned receiver
+    pub fn with_reply(request: Request) -> (Self, tokio::sync::oneshot::Receiver<Result<serde_json::Value, String>>) {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let message = Self {
+            request,
+            responder: Responder::Reply(tx),
+        };
+        (message, rx)
+    }
+
+    /// Report the outcome to the client, if it is listening for one
+    pub fn respond(self, result: Result<serde_json::Value, String>) {
+        let bytes = match (self.responder, result) {
+            (Responder::Reply(tx), result) => {
+                let _ = tx.send(result);
+                None
+            }
+            (Responder::Frame(id, out), result) => protocol::encode(&ResponseFrame::response(id, result)).ok().map(|b| (out, b)),
+            (Responder::Line(out), Err(error)) => encode_line(&OverlayEvent::Error(error)).map(|b| (out, b)),
+            _ => None,
+        };
+        if let Some((out, bytes)) = bytes {
+            let _ = out.try_send(bytes);
+        }
+    }
+}
+
+/// Connected clients that receive `OverlayEvent`s (cheap to clone)
+#[derive(Clone, Default)]
+pub struct IpcClients {
+    // Each client's outbox, and whether it speaks frames
+    senders: Arc<Mutex<Vec<(Outbox, bool)>>>,
+}
+
+impl IpcClients {
+    /// Send an event to every subscribed client still connected. A client whose queue is
+    /// full has stopped reading; its queue is closed, which disconnects it once the queued
+    /// bytes are written, instead of letting it grow without limit.
+    pub fn broadcast(&self, event: &OverlayEvent) {
+        let line = encode_line(event);
+        let frame = protocol::encode(&ResponseFrame::event(event.clone())).ok();
+
+        let mut senders = self.senders.lock().unwrap();
+        senders.retain(|(sender, framed)| {
+            let bytes = if *framed { frame.clone() } else { line.clone() };
+            let Some(bytes) = bytes else { return false };
+            match sender.try_send(bytes) {
+                Ok(()) => true,
+                Err(async_channel::TrySendError::Full(_)) => {
+                    crate::debug_log!("[IPC] Client stopped reading events, dropping it");
+                    sender.close();
+                    false
+                }
+                Err(async_channel::TrySendError::Closed(_)) => false,
+            }
+        });
+    }
+
+    /// Receive every event as a JSON line, for clients that aren't on the socket (api.rs)
+    pub fn subscribe(&self) -> async_channel::Receiver<Vec<u8>> {
+        let (tx, rx) = async_channel::bounded(OUTBOX_CAPACITY);
+        self.add(tx, false);
+        rx
+    }
+
+    fn add(&self, sender: Outbox, framed: bool) {
+        self.senders.lock().unwrap().push((sender, framed));
+    }
 }
 
-/// Spawn a socket listener that receives commands from CLI invocations
-/// Returns a receiver that yields command strings
-pub fn spawn_socket_listener() -> mpsc::Receiver<String> {
-    let (tx, rx) = mpsc::channel();
+fn encode_line(event: &OverlayEvent) -> Option<Vec<u8>> {
+    let mut line = serde_json::to_vec(event).ok()?;
+    line.push(b'\n');
+    Some(line)
+}
+
+/// Spawn a socket listener that receives commands from CLI invocations and JSON clients and
+/// passes their messages to `tx`. Returns the JSON clients (to broadcast events to).
+pub fn spawn_socket_listener(tx: async_channel::Sender<IpcMessage>) -> IpcClients {
+    let clients = IpcClients::default();
     let socket_path = socket_path();
 
     // Remove stale socket file if it exists
     let _ = std::fs::remove_file(&socket_path);
 
-    std::thread::spawn(move || {
+    let shutdown = crate::runtime::shutdown_token();
+    let clients_for_listener = clients.clone();
+    crate::runtime::spawn(async move {
         crate::debug_log!("[IPC] Binding socket listener at {:?}", socket_path);
-        let listener = match UnixListener::bind(&socket_path) {
+        let listener = match tokio::net::UnixListener::bind(&socket_path) {
             Ok(l) => {
                 crate::debug_log!("[IPC] Socket listener bound successfully");
                 l
@@ -98,22 +360,216 @@ pub fn spawn_socket_listener() -> mpsc::Receiver<String> {
         };
 
         crate::debug_log!("[IPC] Waiting for incoming connections...");
-        for stream in listener.incoming() {
-            if let Ok(mut stream) = stream {
-                crate::debug_log!("[IPC] Received incoming connection");
-                let mut buf = [0u8; 64];
-                if let Ok(n) = stream.read(&mut buf) {
-                    let cmd = String::from_utf8_lossy(&buf[..n]).trim().to_string();
-                    crate::debug_log!("[IPC] Received command: '{}'", cmd);
-                    if tx.send(cmd.clone()).is_err() {
-                        crate::debug_log!("[IPC] Receiver dropped, exiting listener thread");
-                        break;
+        loop {
+            let stream = tokio::select! {
+                _ = shutdown.cancelled() => break,
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        crate::debug_log!("[IPC] Failed to accept connection: {}", e);
+                        continue;
                     }
-                    crate::debug_log!("[IPC] Command sent to main thread");
+                },
+            };
+            crate::debug_log!("[IPC] Received incoming connection");
+            // Each connection gets its own task so a slow client can't stall the others
+            crate::runtime::spawn(handle_connection(stream, tx.clone(), clients_for_listener.clone()));
+        }
+
+        let _ = std::fs::remove_file(&socket_path);
+    });
+
+    clients
+}
+
+/// Serve one connection: read its commands and write queued replies/events back
+async fn handle_connection(stream: tokio::net::UnixStream, tx: async_channel::Sender<IpcMessage>, clients: IpcClients) {
+    let (read_half, write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    // Frames start with the high byte of their length, which is always zero; command
+    // lines never do
+    let shutdown = crate::runtime::shutdown_token();
+    let first = tokio::select! {
+        _ = shutdown.cancelled() => return,
+        first = tokio::time::timeout(CONNECTION_IDLE_TIMEOUT, reader.fill_buf()) => first,
+    };
+    let framed = match first {
+        Ok(Ok(buf)) if !buf.is_empty() => buf[0] == 0,
+        _ => return,
+    };
+
+    let (out_tx, out_rx) = async_channel::bounded::<Vec<u8>>(OUTBOX_CAPACITY);
+    crate::runtime::spawn(write_queue(write_half, out_rx));
+    if framed {
+        clients.add(out_tx.clone(), true);
+        read_frames(reader, tx, out_tx.clone()).await;
+    } else {
+        read_commands(reader, tx, out_tx.clone(), clients).await;
+    }
+    // Stops the writer once queued bytes are flushed and drops us from broadcasts
+    out_tx.close();
+}
+
+/// Write queued bytes until the queue closes or the client goes away. At shutdown the
+/// readers end and close the queue, so replies already queued still go out.
+async fn write_queue(mut writer: OwnedWriteHalf, queue: async_channel::Receiver<Vec<u8>>) {
+    while let Ok(bytes) = queue.recv().await {
+        if writer.write_all(&bytes).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Read request frames until the connection closes; framed clients are long-lived
+async fn read_frames(
+    mut reader: BufReader<OwnedReadHalf>,
+    tx: async_channel::Sender<IpcMessage>,
+    out: Outbox,
+) {
+    let shutdown = crate::runtime::shutdown_token();
+    loop {
+        let body = tokio::select! {
+            _ = shutdown.cancelled() => break,
+            body = protocol::read_frame(&mut reader) => body,
+        };
+        let body = match body {
+            Ok(Some(body)) => body,
+            Ok(None) => break,
+            Err(e) => {
+                crate::debug_log!("[IPC] Bad frame, closing: {}", e);
+                break;
+            }
+        };
+
+        let frame = match serde_json::from_slice::<RequestFrame>(&body) {
+            Ok(frame) => frame,
+            Err(e) => {
+                // Answer with the id if we can find one, so the client isn't left waiting
+                let id = serde_json::from_slice::<serde_json::Value>(&body).ok().and_then(|v| v["id"].as_u64());
+                if let Some(id) = id
+                    && let Ok(bytes) = protocol::encode(&ResponseFrame::response(id, Err(format!("invalid request: {}", e))))
+                {
+                    let _ = out.try_send(bytes);
                 }
+                continue;
             }
+        };
+        crate::debug_log!("[IPC] Received request {}: {:?}", frame.id, frame.request);
+
+        let message = IpcMessage {
+            request: frame.request,
+            responder: Responder::Frame(frame.id, out.clone()),
+        };
+        if tx.send(message).await.is_err() {
+            crate::debug_log!("[IPC] Receiver dropped, closing connection");
+            break;
         }
-    });
+    }
+}
+
+/// Read newline-delimited commands from one connection until it closes.
+/// A final command without a trailing newline is accepted; oversized lines are skipped.
+async fn read_commands(
+    mut reader: BufReader<OwnedReadHalf>,
+    tx: async_channel::Sender<IpcMessage>,
+    out: Outbox,
+    clients: IpcClients,
+) {
+    let shutdown = crate::runtime::shutdown_token();
+    let mut line = Vec::new();
+    // Set once the client sends JSON or `subscribe`: it now receives events, so it may
+    // stay idle
+    let mut subscribed = false;
+
+    loop {
+        line.clear();
+        // Cap each read so a client can't make us buffer unbounded input
+        let mut limited = (&mut reader).take(MAX_COMMAND_LEN as u64 + 1);
+        let read = limited.read_until(b'\n', &mut line);
+        let idle_timeout = if subscribed { Duration::MAX } else { CONNECTION_IDLE_TIMEOUT };
+        let read = tokio::select! {
+            _ = shutdown.cancelled() => break,
+            read = tokio::time::timeout(idle_timeout, read) => read,
+        };
+        let n = match read {
+            Ok(Ok(n)) => n,
+            Ok(Err(e)) => {
+                crate::debug_log!("[IPC] Read error: {}", e);
+                break;
+            }
+            Err(_) => {
+                crate::debug_log!("[IPC] Connection idle, closing");
+                break;
+            }
+        };
+        if n == 0 {
+            break;
+        }
+
+        if line.len() > MAX_COMMAND_LEN && !line.ends_with(b"\n") {
+            tracing::warn!("Ignoring IPC command longer than {} bytes", MAX_COMMAND_LEN);
+            if !skip_line(&mut reader).await {
+                break;
+            }
+            continue;
+        }
+
+        let cmd = String::from_utf8_lossy(&line).trim().to_string();
+        if cmd.is_empty() {
+            continue;
+        }
+        crate::debug_log!("[IPC] Received command: '{}'", cmd);
 
-    rx
+        if cmd == "subscribe" {
+            if !subscribed {
+                subscribed = true;
+                clients.add(out.clone(), false);
+            }
+            continue;
+        }
+
+        let message = if cmd.starts_with('{') {
+            if !subscribed {
+                subscribed = true;
+                clients.add(out.clone(), false);
+            }
+            match serde_json::from_str::<OverlayCommand>(&cmd) {
+                Ok(command) => IpcMessage {
+                    request: Request::Command(command),
+                    responder: Responder::Line(out.clone()),
+                },
+                Err(e) => {
+                    if let Some(bytes) = encode_line(&OverlayEvent::Error(format!("invalid command: {}", e))) {
+                        let _ = out.try_send(bytes);
+                    }
+                    continue;
+                }
+            }
+        } else {
+            IpcMessage {
+                request: Request::Text(cmd),
+                responder: Responder::None,
+            }
+        };
+        if tx.send(message).await.is_err() {
+            crate::debug_log!("[IPC] Receiver dropped, closing connection");
+            break;
+        }
+    }
+}
+
+/// Discard input up to and including the next newline; false if the connection ended
+async fn skip_line<R: AsyncRead + Unpin>(reader: &mut BufReader<R>) -> bool {
+    let mut discard = Vec::new();
+    loop {
+        discard.clear();
+        let mut limited = (&mut *reader).take(MAX_COMMAND_LEN as u64);
+        let read = limited.read_until(b'\n', &mut discard);
+        match tokio::time::timeout(CONNECTION_IDLE_TIMEOUT, read).await {
+            Ok(Ok(0)) | Ok(Err(_)) | Err(_) => return false,
+            Ok(Ok(_)) if discard.ends_with(b"\n") => return true,
+            Ok(Ok(_)) => {}
+        }
+    }
 }
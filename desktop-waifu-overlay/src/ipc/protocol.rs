@@ -0,0 +1,138 @@
+//! Framed request/response protocol
+//!
+//! A frame is a 4-byte big-endian length followed by that many bytes of JSON. Clients send
+//! `{"id": 1, "request": {...}}` and get `{"id": 1, "result": ...}` or
+//! `{"id": 1, "error": "..."}` back, in any order relative to other requests; frames
+//! without an `id` carry `{"event": {...}}` notifications. Because frames are far smaller
+//! than 16 MiB the first byte on a framed connection is always zero, which is how the
+//! listener tells them apart from plain command lines.
+
+use super::{OverlayCommand, OverlayEvent};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Largest frame either side accepts
+pub const MAX_FRAME_LEN: usize = 1024 * 1024;
+
+/// What an IPC client asks the overlay to do
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data")]
+pub enum Request {
+    /// A command line as the CLI sends it, e.g. "toggle" or "profile work"
+    Text(String),
+    /// Drive the character
+    Command(OverlayCommand),
+    /// Current visibility and character position
+    GetState,
+    /// Models in the models folder (see models.rs)
+    ListModels,
+    /// Copy a VRM file into the models folder; answers with the model
+    ImportModel(PathBuf),
+    /// Switch the character to a model from the models folder, by id
+    SetActiveModel(String),
+    /// Animation clips PlayAnimation can play (see animations.rs)
+    ListAnimations,
+    /// Remind the user of `text` after `after_secs` seconds (see reminders.rs); answers
+    /// with the reminder
+    AddReminder { text: String, after_secs: u64 },
+    /// Pending reminders
+    ListReminders,
+    /// Have the character say `text` as a chat message, read aloud
+    Say(String),
+    /// Health check: version, PID, uptime and whether the page still answers
+    Ping,
+    /// Reload the frontend in a new web process, keeping the window
+    RestartWebview,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RequestFrame {
+    pub id: u64,
+    pub request: Request,
+}
+
+/// Outcome of a request, or an event (no `id`)
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ResponseFrame {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<OverlayEvent>,
+}
+
+impl ResponseFrame {
+    pub fn response(id: u64, result: Result<serde_json::Value, String>) -> Self {
+        match result {
+            Ok(value) => Self {
+                id: Some(id),
+                result: Some(value),
+                ..Default::default()
+            },
+            Err(error) => Self {
+                id: Some(id),
+                error: Some(error),
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn event(event: OverlayEvent) -> Self {
+        Self {
+            event: Some(event),
+            ..Default::default()
+        }
+    }
+}
+
+/// Length prefix plus JSON body
+pub fn encode<T: Serialize>(message: &T) -> serde_json::Result<Vec<u8>> {
+    let body = serde_json::to_vec(message)?;
+    let mut frame = Vec::with_capacity(4 + body.len());
+    frame.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&body);
+    Ok(frame)
+}
+
+/// Read one frame's body; None at a clean end of stream
+pub async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> std::io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let mut body = frame_buffer(u32::from_be_bytes(len) as usize)?;
+    reader.read_exact(&mut body).await?;
+    Ok(Some(body))
+}
+
+/// Blocking counterpart of `read_frame` for the CLI client
+pub fn read_frame_blocking<R: Read>(reader: &mut R) -> std::io::Result<Vec<u8>> {
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len)?;
+    let mut body = frame_buffer(u32::from_be_bytes(len) as usize)?;
+    reader.read_exact(&mut body)?;
+    Ok(body)
+}
+
+/// Blocking frame write for the CLI client
+pub fn write_frame_blocking<W: Write, T: Serialize>(writer: &mut W, message: &T) -> std::io::Result<()> {
+    writer.write_all(&encode(message)?)
+}
+
+/// Zeroed buffer for a frame body of `len` bytes, refusing oversized frames
+fn frame_buffer(len: usize) -> std::io::Result<Vec<u8>> {
+    if len > MAX_FRAME_LEN {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("frame of {} bytes exceeds the {} byte limit", len, MAX_FRAME_LEN),
+        ));
+    }
+    Ok(vec![0; len])
+}
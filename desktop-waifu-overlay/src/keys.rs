@@ -0,0 +1,72 @@
+//! Shortcuts the overlay handles itself (`[keys]` in config.toml)
+//!
+//! Keydown handlers in the page miss keys on a layer-shell surface while its keyboard mode
+//! flips between on-demand and exclusive, so these shortcuts are caught on the window
+//! before WebKit gets them. The overlay does what it can natively and tells the page with
+//! an `overlayShortcut` event naming the action. Escape also goes on to the page, which
+//! uses it to clear the input or decline a command before it closes the chat; the other
+//! shortcuts stop at the window.
+
+use crate::config::KeysConfig;
+use gtk4::gdk::{Key, ModifierType};
+use gtk4::glib;
+use gtk4::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    CloseChat,
+    FocusInput,
+    DevTools,
+    ClickThrough,
+}
+
+impl Action {
+    const ALL: [Action; 4] = [Action::CloseChat, Action::FocusInput, Action::DevTools, Action::ClickThrough];
+
+    /// Name in `overlayShortcut` events
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::CloseChat => "closeChat",
+            Action::FocusInput => "focusInput",
+            Action::DevTools => "devTools",
+            Action::ClickThrough => "clickThrough",
+        }
+    }
+
+    fn accelerator(self, keys: &KeysConfig) -> &str {
+        match self {
+            Action::CloseChat => &keys.close_chat,
+            Action::FocusInput => &keys.focus_input,
+            Action::DevTools => &keys.devtools,
+            Action::ClickThrough => &keys.click_through,
+        }
+    }
+}
+
+/// Catch the configured shortcuts on `window` and hand them to `on_action`
+pub fn install(window: &impl IsA<gtk4::Widget>, on_action: impl Fn(Action) + 'static) {
+    let keys = gtk4::EventControllerKey::new();
+    keys.set_propagation_phase(gtk4::PropagationPhase::Capture);
+    keys.connect_key_pressed(move |_, key, _, state| {
+        let Some(action) = find(key, state) else { return glib::Propagation::Proceed };
+        crate::debug_log!("[KEYS] {:?}", action);
+        on_action(action);
+        if action == Action::CloseChat {
+            glib::Propagation::Proceed
+        } else {
+            glib::Propagation::Stop
+        }
+    });
+    window.add_controller(keys);
+}
+
+/// The action bound to `key` with `state`'s modifiers held, if any. Accelerators that
+/// don't parse bind nothing.
+fn find(key: Key, state: ModifierType) -> Option<Action> {
+    let config = crate::config::current();
+    let modifiers = state & gtk4::accelerator_get_default_mod_mask();
+    Action::ALL.into_iter().find(|action| {
+        gtk4::accelerator_parse(action.accelerator(&config.keys))
+            .is_some_and(|(bound, bound_modifiers)| bound.to_lower() == key.to_lower() && bound_modifiers == modifiers)
+    })
+}
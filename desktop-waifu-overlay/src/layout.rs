@@ -0,0 +1,118 @@
+//! Chat placement
+//!
+//! Where the chat panel goes is worked out here rather than in the page, from the dock
+//! mode (`[window] chat_dock`, or the `SetChatDock` command), where the character is and
+//! how big the chat is. The frontend gets the result as a `layoutChange` event and only
+//! places the panel where it says. In CSS pixels, like the character's position:
+//!
+//! - `follow-character`: next to the character, on the side facing the middle of the
+//!   screen, lined up with its top or bottom edge; it moves along when the character does
+//! - `left-panel` / `right-panel`: against that screen edge, the screen's full height
+//! - `floating`: in the middle of the screen, wherever the character is
+//!
+//! The chat is kept on screen in every mode.
+
+use crate::config::ChatDock;
+use serde::Serialize;
+
+/// A rectangle on screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// Chat placement settings that aren't in config.toml
+#[derive(Debug, Clone, Default)]
+pub struct ChatState {
+    /// Dock mode set through IPC, over `[window] chat_dock`
+    pub dock: Option<ChatDock>,
+    /// Size of the chat panel at the frontend's chat scale, as it last reported
+    pub size: Option<(i32, i32)>,
+    /// The chat panel is open, going by the page's resizeWindow
+    pub open: bool,
+}
+
+impl ChatState {
+    pub fn dock(&self) -> ChatDock {
+        self.dock.unwrap_or(crate::config::current().window.chat_dock)
+    }
+
+    /// The reported size, or the configured one before there's a report
+    pub fn size(&self) -> (i32, i32) {
+        self.size.unwrap_or_else(|| {
+            let window = &crate::config::current().window;
+            ((window.expanded_width - window.collapsed_width).max(0), window.expanded_height)
+        })
+    }
+}
+
+/// What the frontend needs to lay out the character and the chat
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Layout {
+    pub dock: ChatDock,
+    pub character: Rect,
+    pub chat: Rect,
+    /// Side of the character the chat is on
+    pub chat_side: Side,
+    pub is_right_half: bool,
+    pub is_bottom_half: bool,
+    pub screen_width: i32,
+    pub screen_height: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// Place the chat for a character at `character` in the given quarter of the screen
+pub fn compute(
+    dock: ChatDock,
+    character: Rect,
+    (is_right_half, is_bottom_half): (bool, bool),
+    (chat_width, chat_height): (i32, i32),
+    (screen_width, screen_height): (i32, i32),
+) -> Layout {
+    let chat_width = chat_width.min(screen_width);
+    let chat = match dock {
+        ChatDock::FollowCharacter => Rect {
+            // Chat goes to the opposite side of the screen from the character
+            x: if is_right_half { character.x - chat_width } else { character.x + character.width },
+            // Aligned with the character's bottom in the bottom half, its top otherwise
+            y: if is_bottom_half { character.y + character.height - chat_height } else { character.y },
+            width: chat_width,
+            height: chat_height.min(screen_height),
+        },
+        ChatDock::LeftPanel => Rect { x: 0, y: 0, width: chat_width, height: screen_height },
+        ChatDock::RightPanel => Rect { x: screen_width - chat_width, y: 0, width: chat_width, height: screen_height },
+        ChatDock::Floating => Rect {
+            x: (screen_width - chat_width) / 2,
+            y: (screen_height - chat_height).max(0) / 2,
+            width: chat_width,
+            height: chat_height.min(screen_height),
+        },
+    };
+    let chat = Rect {
+        x: chat.x.clamp(0, (screen_width - chat.width).max(0)),
+        y: chat.y.clamp(0, (screen_height - chat.height).max(0)),
+        ..chat
+    };
+    let chat_side = if chat.x + chat.width / 2 < character.x + character.width / 2 { Side::Left } else { Side::Right };
+
+    Layout {
+        dock,
+        character,
+        chat,
+        chat_side,
+        is_right_half,
+        is_bottom_half,
+        screen_width,
+        screen_height,
+    }
+}
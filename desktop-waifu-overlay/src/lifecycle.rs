@@ -0,0 +1,59 @@
+//! On-demand webview lifecycle (aggressive memory mode)
+//!
+//! Most of the overlay's footprint lives in WebKit's web process. When memory mode is on and
+//! the window stays hidden for the configured period, that process is terminated; the next
+//! time the window is shown the page is loaded again and the frontend restores its state
+//! from the persisted stores (localStorage lives in the persistent network session).
+
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::ApplicationWindow;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+use tracing::info;
+use webkit6::prelude::*;
+use webkit6::WebView;
+
+#[derive(Default)]
+struct LifecycleState {
+    unload_timer: Option<glib::SourceId>,
+    unloaded: bool,
+}
+
+/// Unload the web content after `unload_after` hidden, and reload `url` when shown again
+pub fn install(window: &ApplicationWindow, webview: &WebView, url: String, unload_after: Duration) {
+    info!("Memory mode: web content unloads after {:?} hidden", unload_after);
+    let state = Rc::new(RefCell::new(LifecycleState::default()));
+
+    let webview = webview.clone();
+    window.connect_visible_notify(move |window| {
+        let mut s = state.borrow_mut();
+        if let Some(timer) = s.unload_timer.take() {
+            timer.remove();
+        }
+
+        if window.is_visible() {
+            if s.unloaded {
+                info!("Memory mode: reloading web content");
+                s.unloaded = false;
+                crate::content_policy::load(&webview, &url);
+            }
+            return;
+        }
+
+        if s.unloaded {
+            return;
+        }
+        let state_for_timer = state.clone();
+        let webview = webview.clone();
+        s.unload_timer = Some(glib::timeout_add_local_once(unload_after, move || {
+            let mut s = state_for_timer.borrow_mut();
+            s.unload_timer = None;
+            s.unloaded = true;
+            info!("Memory mode: hidden for {:?}, unloading web content", unload_after);
+            webview.terminate_web_process();
+        }));
+    });
+}
+
@@ -0,0 +1,149 @@
+//! Chat completions for the assistant (the `llmChat` message handler)
+//!
+//! Requests are made from here instead of from the webview: the webview's own network
+//! requests never carry an API key and provider CORS rules don't apply. Building each
+//! provider's request, streaming the reply and retrying rate limits and server errors is
+//! desktop-waifu-llm's; this module holds the keys, runs requests on the runtime and
+//! cancels them.
+//!
+//! Keys live in the desktop keyring as `<provider>-api-key` (see secrets.rs), where the
+//! settings dialog saves them. `llmSetKey` puts a key in use right away, ahead of the
+//! keyring, and is how keys are passed when there is no keyring.
+//!
+//! Before a request goes out, the memories most relevant to the latest user message are
+//! added to its system prompt (see memory.rs).
+//!
+//! Requests can offer the model tools (the `tools` registry's definitions). Tool calls come
+//! back with the reply; the frontend runs them and sends the results in `tool` messages
+//! on the next request.
+
+pub use desktop_waifu_llm::{ChatRequest, LlmError, LocalServer, Provider, Reply};
+use desktop_waifu_llm::{Content, Message, Role};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use tokio_util::sync::CancellationToken;
+
+/// Keys set by the frontend, by provider
+static KEYS: LazyLock<Mutex<HashMap<Provider, String>>> = LazyLock::new(Default::default);
+/// Requests in progress, by request id
+static ACTIVE: LazyLock<Mutex<HashMap<String, CancellationToken>>> = LazyLock::new(Default::default);
+
+pub enum ChatEvent {
+    /// The next piece of the reply
+    Token(String),
+    /// The whole reply
+    Done(Reply),
+    /// `cancel` stopped the request
+    Cancelled,
+    Failed(LlmError),
+}
+
+/// Use `key` for `provider` from now on; an empty key clears it
+pub fn set_key(provider: Provider, key: &str) {
+    let mut keys = KEYS.lock().unwrap();
+    if key.trim().is_empty() {
+        keys.remove(&provider);
+    } else {
+        keys.insert(provider, key.trim().to_string());
+    }
+}
+
+pub(crate) async fn api_key(provider: Provider) -> Option<String> {
+    let set = KEYS.lock().unwrap().get(&provider).cloned();
+    if set.is_some() {
+        return set;
+    }
+    let lookup = tokio::task::spawn_blocking(move || crate::secrets::get(provider.secret_name()));
+    match lookup.await {
+        Ok(Ok(key)) => key.filter(|key| !key.is_empty()),
+        Ok(Err(e)) => {
+            tracing::warn!("Couldn't read the {} key from the keyring: {}", provider.name(), e);
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+/// Where to look for a local model server: `local_url` under [llm] in config.toml, else
+/// Ollama's and llama.cpp's default ports
+fn local_servers() -> Vec<String> {
+    desktop_waifu_llm::local_servers(crate::config::current().llm.local_url.as_deref())
+}
+
+/// Start a chat completion. The receiver yields the reply as it streams in, then how the
+/// request ended. `id` names the request for `cancel`.
+pub fn chat(id: String, request: ChatRequest) -> async_channel::Receiver<ChatEvent> {
+    let (tx, rx) = async_channel::unbounded();
+    let cancel = crate::runtime::shutdown_token().child_token();
+    if let Some(previous) = ACTIVE.lock().unwrap().insert(id.clone(), cancel.clone()) {
+        previous.cancel();
+    }
+
+    crate::runtime::spawn(async move {
+        let mut request = request;
+        let send = async {
+            add_memories(&mut request.messages).await;
+            let on_token = |token: &str| {
+                let _ = tx.try_send(ChatEvent::Token(token.to_string()));
+            };
+            desktop_waifu_llm::chat(&request, api_key(request.provider), &local_servers(), on_token).await
+        };
+        let event = tokio::select! {
+            result = send => match result {
+                Ok(reply) => ChatEvent::Done(reply),
+                Err(e) => ChatEvent::Failed(e),
+            },
+            _ = cancel.cancelled() => ChatEvent::Cancelled,
+        };
+        let _ = tx.send(event).await;
+        ACTIVE.lock().unwrap().remove(&id);
+    });
+    rx
+}
+
+/// Stop a request started with `chat`
+pub fn cancel(id: &str) -> bool {
+    match ACTIVE.lock().unwrap().remove(id) {
+        Some(token) => {
+            token.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Find the local model server and list its models
+pub async fn local_models() -> Result<LocalServer, LlmError> {
+    desktop_waifu_llm::local_models(&local_servers()).await
+}
+
+/// Add what long-term memory recalls about the latest user message to the system prompt
+async fn add_memories(messages: &mut Vec<Message>) {
+    let Some(query) = messages
+        .iter()
+        .rev()
+        .find(|m| matches!(m.role, Role::User))
+        .map(|m| m.content.text())
+    else {
+        return;
+    };
+    let Some(context) = crate::memory::context_for(&query).await else {
+        return;
+    };
+    match messages.iter_mut().find(|m| matches!(m.role, Role::System)) {
+        Some(Message { content: Content::Text(text), .. }) => {
+            text.push_str("\n\n");
+            text.push_str(&context);
+        }
+        _ => messages.insert(
+            0,
+            Message {
+                role: Role::System,
+                content: Content::Text(context),
+                tool_calls: Vec::new(),
+                tool_call_id: None,
+                name: None,
+            },
+        ),
+    }
+}
@@ -0,0 +1,125 @@
+//! Locale, timezone and date/time conventions for the frontend
+//!
+//! Backs the `getLocaleInfo` handler so the assistant's time references and reminders use
+//! the user's timezone, clock format and week start rather than the webview's guesses.
+
+use gtk4::gio;
+use gtk4::glib;
+use gtk4::prelude::*;
+use serde::Serialize;
+use std::ffi::CStr;
+
+// CLDR territories whose week starts on Sunday / Saturday / Friday; everywhere else Monday
+const SUNDAY_START: &[&str] = &[
+    "AG", "AS", "AU", "BD", "BR", "BS", "BT", "BW", "BZ", "CA", "CN", "CO", "DM", "DO", "ET",
+    "GT", "GU", "HK", "HN", "ID", "IL", "IN", "JM", "JP", "KE", "KH", "KR", "LA", "MH", "MM",
+    "MO", "MT", "MX", "MZ", "NI", "NP", "PA", "PE", "PH", "PK", "PR", "PT", "PY", "SA", "SG",
+    "SV", "TH", "TT", "TW", "UM", "US", "VE", "VI", "WS", "YE", "ZA", "ZW",
+];
+const SATURDAY_START: &[&str] = &[
+    "AE", "AF", "BH", "DJ", "DZ", "EG", "IQ", "IR", "JO", "KW", "LY", "OM", "QA", "SD", "SY",
+];
+const FRIDAY_START: &[&str] = &["MV"];
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HourCycle {
+    /// 12-hour clock with AM/PM
+    H12,
+    /// 24-hour clock
+    H23,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocaleInfo {
+    /// Locale used for dates and times (LC_TIME), e.g. "en-GB"
+    pub locale: String,
+    /// Language the overlay's own tray, dialogs and messages use
+    pub ui_locale: String,
+    /// IANA timezone name, e.g. "Europe/Berlin"
+    pub timezone: Option<String>,
+    pub utc_offset_minutes: i64,
+    pub hour_cycle: HourCycle,
+    /// ISO 8601 weekday: 1 = Monday ... 7 = Sunday
+    pub first_day_of_week: u8,
+}
+
+/// Detect the user's conventions. Call on the main thread (after GTK has set the C locale).
+pub fn detect() -> LocaleInfo {
+    let locale = time_locale();
+    LocaleInfo {
+        ui_locale: crate::i18n::current_locale().to_string(),
+        timezone: timezone(),
+        utc_offset_minutes: glib::DateTime::now_local()
+            .map(|now| now.utc_offset().as_minutes())
+            .unwrap_or(0),
+        hour_cycle: desktop_clock_format().unwrap_or_else(locale_hour_cycle),
+        first_day_of_week: first_day_of_week(&locale),
+        locale: locale.to_string(),
+    }
+}
+
+fn time_locale() -> unic_langid::LanguageIdentifier {
+    ["LC_ALL", "LC_TIME", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find_map(|value| crate::i18n::parse_posix_locale(&value))
+        .unwrap_or_else(|| "en-US".parse().expect("valid locale id"))
+}
+
+fn timezone() -> Option<String> {
+    if let Ok(tz) = std::env::var("TZ") {
+        let tz = tz.trim_start_matches(':');
+        if !tz.is_empty() && !tz.starts_with('/') {
+            return Some(tz.to_string());
+        }
+    }
+    // Usually a symlink into /usr/share/zoneinfo
+    if let Ok(target) = std::fs::read_link("/etc/localtime")
+        && let Some((_, name)) = target.to_string_lossy().split_once("zoneinfo/")
+    {
+        return Some(name.to_string());
+    }
+    std::fs::read_to_string("/etc/timezone")
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// Explicit clock preference from GNOME's settings, if the schema is installed
+fn desktop_clock_format() -> Option<HourCycle> {
+    let schema = gio::SettingsSchemaSource::default()?.lookup("org.gnome.desktop.interface", true)?;
+    if !schema.has_key("clock-format") {
+        return None;
+    }
+    match gio::Settings::new("org.gnome.desktop.interface").string("clock-format").as_str() {
+        "12h" => Some(HourCycle::H12),
+        "24h" => Some(HourCycle::H23),
+        _ => None,
+    }
+}
+
+/// Clock format implied by the C library's LC_TIME time format
+fn locale_hour_cycle() -> HourCycle {
+    let format = unsafe { CStr::from_ptr(libc::nl_langinfo(libc::T_FMT)) }.to_string_lossy();
+    if ["%I", "%l", "%r", "%p"].iter().any(|spec| format.contains(spec)) {
+        HourCycle::H12
+    } else {
+        HourCycle::H23
+    }
+}
+
+fn first_day_of_week(locale: &unic_langid::LanguageIdentifier) -> u8 {
+    let Some(region) = locale.region else { return 1 };
+    let region = region.as_str();
+    if SUNDAY_START.contains(&region) {
+        7
+    } else if SATURDAY_START.contains(&region) {
+        6
+    } else if FRIDAY_START.contains(&region) {
+        5
+    } else {
+        1
+    }
+}
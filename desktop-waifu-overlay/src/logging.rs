@@ -0,0 +1,161 @@
+//! Logging setup
+//!
+//! Everything goes through `tracing`, filtered by an `EnvFilter`: `RUST_LOG` if it is set,
+//! otherwise `info`, or `debug` with `--debug` (or `debug_logging` in config.toml). The
+//! running instance's filter can be changed with `desktop-waifu-overlay --log-level
+//! <FILTER>`. With `--log-file` the output also goes to `~/.cache/desktop-waifu/overlay.log`,
+//! rotated at 5 MB with the last three files kept.
+
+use gtk4::glib;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Registry};
+
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+/// Rotated files kept next to the current one (overlay.log.1 is the newest)
+const KEPT_LOGS: u32 = 3;
+
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+/// RUST_LOG chose the filter, so --debug and config.toml leave it alone
+static FROM_ENV: AtomicBool = AtomicBool::new(false);
+
+pub fn log_path() -> PathBuf {
+    glib::user_cache_dir()
+        .join("desktop-waifu")
+        .join("overlay.log")
+}
+
+fn default_filter(debug: bool) -> EnvFilter {
+    EnvFilter::new(if debug { "debug" } else { "info" })
+}
+
+/// Install the global subscriber; call once, before anything logs
+pub fn init(debug: bool, log_file: bool) -> anyhow::Result<()> {
+    let filter = match EnvFilter::try_from_default_env() {
+        Ok(filter) => {
+            FROM_ENV.store(true, Ordering::Relaxed);
+            filter
+        }
+        Err(_) => default_filter(debug),
+    };
+    let (filter, handle) = reload::Layer::new(filter);
+    let _ = FILTER.set(handle);
+
+    let (file, file_error) = if log_file {
+        match RotatingFile::open(log_path(), MAX_LOG_BYTES, KEPT_LOGS) {
+            Ok(file) => (Some(file), None),
+            Err(e) => (None, Some(e)),
+        }
+    } else {
+        (None, None)
+    };
+    let file_layer = file.map(|file| fmt::layer().with_ansi(false).with_writer(Mutex::new(file)));
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer())
+        .with(file_layer)
+        .try_init()?;
+
+    if let Some(e) = file_error {
+        tracing::warn!("Not writing {}: {}", log_path().display(), e);
+    }
+    Ok(())
+}
+
+/// Replace the running filter, e.g. "debug" or "info,desktop_waifu_overlay::ipc=trace"
+pub fn set_filter(spec: &str) -> Result<(), String> {
+    let filter = EnvFilter::try_new(spec).map_err(|e| format!("Invalid log filter '{}': {}", spec, e))?;
+    FILTER
+        .get()
+        .ok_or("Logging is not initialized")?
+        .reload(filter)
+        .map_err(|e| e.to_string())?;
+    tracing::info!("Log filter set to '{}'", spec);
+    Ok(())
+}
+
+/// Switch between the `info` and `debug` defaults, unless RUST_LOG chose the filter
+pub fn set_debug(debug: bool) {
+    if FROM_ENV.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Some(handle) = FILTER.get() {
+        let _ = handle.reload(default_filter(debug));
+    }
+}
+
+/// Appends to a log file, moving it to `<name>.1` (and older ones up) once it is full
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    written: u64,
+    max_bytes: u64,
+    /// Rotated files kept next to the current one
+    kept: u32,
+}
+
+impl RotatingFile {
+    pub fn open(path: PathBuf, max_bytes: u64, kept: u32) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Commands and replies end up in logs, so only the user may read them
+        let file = OpenOptions::new().create(true).append(true).mode(0o600).open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file,
+            written,
+            max_bytes,
+            kept,
+        })
+    }
+
+    /// `<path>.<n>`, the nth newest rotated file
+    pub fn rotated_path(path: &Path, n: u32) -> PathBuf {
+        let mut name = path.to_path_buf().into_os_string();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+
+    fn rotated(&self, n: u32) -> PathBuf {
+        Self::rotated_path(&self.path, n)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        for n in (1..self.kept).rev() {
+            let _ = std::fs::rename(self.rotated(n), self.rotated(n + 1));
+        }
+        std::fs::rename(&self.path, self.rotated(1))?;
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let n = self.file.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
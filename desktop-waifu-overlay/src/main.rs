@@ -1,12 +1,73 @@
+mod accessibility;
+mod animations;
+mod api;
+mod appearance;
+mod audit;
+mod autostart;
+mod behavior;
+mod bridge;
+mod companions;
+mod compositor;
+mod config;
+mod confirm;
+mod content_policy;
+mod dnd;
+mod expressions;
+mod files;
+mod focus;
+mod frontend;
+mod git;
+mod i18n;
+mod input_region;
 mod ipc;
+mod keys;
+mod layout;
+mod lifecycle;
+mod llm;
+mod locale_info;
+mod logging;
+mod memory;
+mod models;
+mod monitors;
+mod mpris;
+mod nightlight;
+mod notifications;
+mod pkg;
+mod plugins;
+mod policy;
+mod portal;
+mod presence;
+mod privileged;
+mod profiles;
+mod pty;
+mod reminders;
+mod render;
+mod runtime;
+mod sandbox;
+mod screenshot;
+mod secrets;
+#[cfg(feature = "scripting")]
+mod scripting;
 mod server;
+mod session;
+mod shell;
+mod shortcuts;
+mod sound;
+mod state;
+mod storage;
+mod supervisor;
+mod system;
+#[cfg(feature = "stt")]
+mod stt;
+mod tools;
+mod transfer;
 mod tray;
+mod tts;
+mod update;
+mod vrm;
+mod walk;
 
-use clap::Parser;
-
-// Debug logging flag - set to true to enable debug output to terminal
-// Made pub(crate) so the debug_log! macro can access it from submodules
-pub(crate) const DEBUG_LOGGING: bool = false;
+use clap::{CommandFactory, FromArgMatches, Parser};
 
 /// Desktop Waifu overlay - Animated 3D VRM characters for your desktop
 #[derive(Parser)]
@@ -23,16 +84,119 @@ struct Cli {
     /// Hide overlay (send command to running instance)
     #[arg(long)]
     hide: bool,
+
+    /// Capture a screen region and ask the character about it (send command to running instance)
+    #[arg(long)]
+    ask_screenshot: bool,
+
+    /// Print the running instance's state (visibility, size, position, model, uptime) as JSON
+    #[arg(long)]
+    status: bool,
+
+    /// Check that the running instance and its page respond, and print its version, PID
+    /// and uptime as JSON
+    #[arg(long)]
+    ping: bool,
+
+    /// Reload the running instance's frontend without closing its window
+    #[arg(long)]
+    restart_webview: bool,
+
+    /// Print the running instance's events (clicks, finished animations, visibility) as JSON
+    /// lines until it quits
+    #[arg(long)]
+    subscribe: bool,
+
+    /// Switch the running instance to a profile from profiles.toml ("default" for none)
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Have the running instance remind you of TEXT, after the delay given with --in
+    #[arg(long, value_name = "TEXT", requires = "remind_in")]
+    remind: Option<String>,
+
+    /// Delay for --remind, e.g. "25m", "1h30m" or "90s"
+    #[arg(long = "in", value_name = "DURATION", requires = "remind")]
+    remind_in: Option<String>,
+
+    /// Change the running instance's log filter, e.g. "debug", "info" or
+    /// "info,desktop_waifu_overlay::ipc=trace"
+    #[arg(long, value_name = "FILTER")]
+    log_level: Option<String>,
+
+    /// Memory mode: unload the web content after the overlay has been hidden this many
+    /// seconds, and reload it when shown again
+    #[arg(long, value_name = "SECONDS")]
+    unload_after: Option<u64>,
+
+    /// Ignore every IPC command, not just --toggle, while the hotkey setting is disabled
+    /// (overrides `strict_hotkeys = false` in config.toml)
+    #[arg(long)]
+    strict_hotkeys: bool,
+
+    /// Don't go quiet while other apps use the microphone/camera or the screen is shared
+    #[arg(long)]
+    no_meeting_mode: bool,
+
+    /// Key to suggest for the global toggle shortcut through the desktop portal, e.g.
+    /// "LOGO+w" or "CTRL+ALT+d"; "none" to not register one
+    #[arg(long, value_name = "TRIGGER", default_value = shortcuts::DEFAULT_TRIGGER)]
+    shortcut: String,
+
+    /// Log debug output (RUST_LOG takes precedence)
+    #[arg(long)]
+    debug: bool,
+
+    /// Also write the log to ~/.cache/desktop-waifu/overlay.log, rotated at 5 MB
+    #[arg(long)]
+    log_file: bool,
+
+    /// Run the overlay as a child process and restart it, with backoff, when it crashes
+    #[arg(long)]
+    supervise: bool,
+
+    /// Start the overlay on login, with the other options given here: through a systemd
+    /// user service, an XDG autostart entry, or whichever fits ("auto")
+    #[arg(long, value_name = "METHOD", num_args = 0..=1, default_missing_value = "auto")]
+    install_autostart: Option<autostart::AutostartMethod>,
+
+    /// Stop starting the overlay on login
+    #[arg(long)]
+    uninstall_autostart: bool,
+
+    /// Check GitHub for a newer release
+    #[arg(long)]
+    check_update: bool,
+
+    /// Install the latest release over this one (tarball installs) and restart the overlay
+    #[arg(long)]
+    self_update: bool,
+
+    /// Language for tray, dialogs and messages (e.g. "de" or "ja-JP"); defaults to the
+    /// system locale
+    #[arg(long, global = true, value_name = "LOCALE")]
+    locale: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Manage plugins (install, enable, grant permissions, ...)
+    Plugin {
+        #[command(subcommand)]
+        action: plugins::PluginAction,
+    },
 }
 
-// Helper macro for conditional debug logging
+// Helper macro for debug logging, shown at the `debug` log level (--debug, RUST_LOG or
+// --log-level on the running instance)
 // Uses #[macro_export] so it can be used in submodules via crate::debug_log!
 #[macro_export]
 macro_rules! debug_log {
     ($($arg:tt)*) => {
-        if crate::DEBUG_LOGGING {
-            eprintln!($($arg)*);
-        }
+        tracing::debug!($($arg)*)
     };
 }
 
@@ -41,41 +205,138 @@ use cairo::{RectangleInt, Region};
 use gtk4::{gio, glib};
 use gtk4::prelude::*;
 use gtk4::{Application, ApplicationWindow};
-use gtk4_layer_shell::{Edge, KeyboardMode, Layer, LayerShell as _};
-use std::cell::RefCell;
+use gtk4_layer_shell::{Edge, Layer, LayerShell as _};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::Stdio;
 use std::rc::Rc;
+use std::sync::Arc;
 use std::time::Duration;
-use tracing::{info, Level};
-use tracing_subscriber::FmtSubscriber;
+use tracing::info;
 use webkit6::prelude::*;
-use webkit6::{NetworkSession, Settings as WebViewSettings, UserContentManager, WebView};
+use webkit6::{NetworkSession, Settings as WebViewSettings, UserContentManager, WebContext, WebView};
 
-use tray::{spawn_tray, update_tray_visibility, TrayMessage};
+use bridge::{dispatch_callback, dispatch_event};
+use tray::{spawn_tray, update_tray_profiles, update_tray_visibility, TrayMessage};
 
 const APP_ID: &str = "com.desktop-waifu.overlay";
-
-// Window dimension constants
-const WINDOW_WIDTH_COLLAPSED: i32 = 160;   // Character only
-const WINDOW_WIDTH_EXPANDED: i32 = 800;    // Chat + Character
-const WINDOW_HEIGHT_COLLAPSED: i32 = 380;  // Character only
-const WINDOW_HEIGHT_EXPANDED: i32 = 1000;  // Chat + Character (more room for chat)
+/// Longest a requested shutdown may take before the process exits regardless
+const SHUTDOWN_DEADLINE: Duration = Duration::from_secs(10);
+/// Web process crashes within `WEB_CRASH_WINDOW` that are still reloaded; more give up
+const MAX_WEB_RELOADS: usize = 3;
+const WEB_CRASH_WINDOW: Duration = Duration::from_secs(60);
 
 // Store character position (absolute screen coordinates)
-// With fullscreen window, character is positioned via CSS within the window
+// With fullscreen window, character is positioned via CSS within the window. Everything is
+// in GTK application pixels, which are the webview's CSS pixels at any monitor scale
+// (fractional ones included); the compositor maps them to device pixels.
 #[derive(Clone, Debug)]
 struct CharacterPosition {
     // X coordinate of character's left edge on screen
     x: i32,
     // Y coordinate of character's top edge on screen
     y: i32,
+    // Size of the monitor the position is relative to. Changing a monitor's scale changes
+    // its size in application pixels, so the position is carried over with `rescale`.
+    screen: (i32, i32),
+    // Character scale as last set from the settings, the tray or IPC; unset, the config's
+    // `[window] scale` applies
+    scale: Option<f32>,
 }
 
-impl Default for CharacterPosition {
-    fn default() -> Self {
-        // Default to bottom-right area of a 1920x1080 screen
-        Self {
-            x: 1920 - WINDOW_WIDTH_COLLAPSED - 20,
-            y: 1080 - WINDOW_HEIGHT_COLLAPSED - 20,
+/// How long the character slides when it snaps to an edge
+const SNAP_DURATION: Duration = Duration::from_millis(180);
+
+/// Range of character scales, the same as the settings slider's
+const MIN_SCALE: f32 = 0.5;
+const MAX_SCALE: f32 = 2.0;
+
+impl CharacterPosition {
+    /// The saved position, carried over to the monitor's current scale and pulled back on
+    /// screen if the monitor has since shrunk; the configured corner if nothing was saved
+    fn restore(saved: &state::OverlayState, screen_width: i32, screen_height: i32) -> Self {
+        let config = config::current();
+        let current = (screen_width, screen_height);
+        let scale = saved.scale.map(|scale| scale.clamp(MIN_SCALE, MAX_SCALE));
+        let (x, y, screen) = match (saved.x, saved.y) {
+            (Some(x), Some(y)) => (x, y, saved.screen.unwrap_or(current)),
+            _ => {
+                let effective = scale.unwrap_or(config.window.scale).clamp(MIN_SCALE, MAX_SCALE);
+                let (x, y) = config.default_position(screen_width, screen_height, effective);
+                (x, y, current)
+            }
+        };
+        let mut position = Self { x, y, screen, scale };
+        position.rescale(screen_width, screen_height);
+        position
+    }
+
+    /// Keep the character at the same relative spot on a screen that's now
+    /// `screen_width`x`screen_height`, e.g. after a scale change, and on screen
+    fn rescale(&mut self, screen_width: i32, screen_height: i32) {
+        let (old_width, old_height) = self.screen;
+        if (old_width, old_height) != (screen_width, screen_height) && old_width > 0 && old_height > 0 {
+            self.x = (self.x as f64 * screen_width as f64 / old_width as f64).round() as i32;
+            self.y = (self.y as f64 * screen_height as f64 / old_height as f64).round() as i32;
+        }
+        self.screen = (screen_width, screen_height);
+        self.clamp();
+    }
+
+    /// Pull the character back on screen; whether it moved
+    fn clamp(&mut self) -> bool {
+        let (screen_width, screen_height) = self.screen;
+        let (width, height) = self.size();
+        let before = (self.x, self.y);
+        self.x = self.x.clamp(0, (screen_width - width).max(0));
+        self.y = self.y.clamp(0, (screen_height - height).max(0));
+        (self.x, self.y) != before
+    }
+
+    /// On each axis, move the character `margin` from a screen edge if it's within
+    /// `distance` of that spot; whether it moved
+    fn snap(&mut self, distance: i32, margin: i32) -> bool {
+        let (screen_width, screen_height) = self.screen;
+        let (width, height) = self.size();
+        let snap = |value: i32, size: i32, screen: i32| {
+            let far = screen - size - margin;
+            if (value - margin).abs() <= distance {
+                margin
+            } else if (value - far).abs() <= distance {
+                far
+            } else {
+                value
+            }
+        };
+        let before = (self.x, self.y);
+        self.x = snap(self.x, width, screen_width);
+        self.y = snap(self.y, height, screen_height);
+        self.clamp();
+        (self.x, self.y) != before
+    }
+
+    /// The character scale in effect
+    fn scale(&self) -> f32 {
+        self.scale.unwrap_or(config::current().window.scale).clamp(MIN_SCALE, MAX_SCALE)
+    }
+
+    /// Size of the character's box on screen
+    fn size(&self) -> (i32, i32) {
+        config::current().window.character_size(self.scale())
+    }
+
+    fn rect(&self) -> layout::Rect {
+        let (width, height) = self.size();
+        layout::Rect { x: self.x, y: self.y, width, height }
+    }
+
+    /// Which quarter of a `screen_width`x`screen_height` screen the character's center is in
+    fn quadrant(&self, screen_width: i32, screen_height: i32) -> Quadrant {
+        let (width, height) = self.size();
+        Quadrant {
+            is_right_half: self.x + width / 2 >= screen_width / 2,
+            is_bottom_half: self.y + height / 2 >= screen_height / 2,
         }
     }
 }
@@ -93,110 +354,289 @@ struct DragState {
     start_x: i32,
     start_y: i32,
     is_dragging: bool,
+    // A layoutChange update is queued for the next frame clock tick
+    frame_pending: bool,
+}
+
+/// Tell the frontend where the character and the chat go (see layout.rs)
+fn send_layout(webview: &WebView, position: &CharacterPosition, quadrant: &Quadrant, chat: &layout::ChatState) {
+    let layout = layout::compute(
+        chat.dock(),
+        position.rect(),
+        (quadrant.is_right_half, quadrant.is_bottom_half),
+        chat.size(),
+        position.screen,
+    );
+    dispatch_event(webview, "layoutChange", &serde_json::json!(layout));
 }
 
 
-/// Get screen dimensions from the monitor containing the window
-fn get_screen_dimensions(window: &ApplicationWindow) -> Option<(i32, i32)> {
-    let display = gtk4::gdk::Display::default()?;
-    let surface = window.surface()?;
-    let monitor = display.monitor_at_surface(&surface)?;
-    let geometry = monitor.geometry();
-    Some((geometry.width(), geometry.height()))
+/// `--locale` value, read before clap so the help text itself can be translated
+fn locale_arg() -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--locale" {
+            return args.next();
+        }
+        if let Some(locale) = arg.strip_prefix("--locale=") {
+            return Some(locale.to_string());
+        }
+    }
+    None
+}
+
+/// This invocation's arguments minus `--install-autostart [METHOD]`, for the login command
+fn autostart_args() -> Vec<String> {
+    let mut args = Vec::new();
+    let mut iter = std::env::args().skip(1).peekable();
+    while let Some(arg) = iter.next() {
+        if arg == "--install-autostart" {
+            iter.next_if(|next| ["auto", "systemd", "xdg"].contains(&next.as_str()));
+        } else if !arg.starts_with("--install-autostart=") {
+            args.push(arg);
+        }
+    }
+    args
+}
+
+/// This invocation's arguments minus `--supervise`, for the supervised overlay
+fn supervised_args() -> Vec<String> {
+    std::env::args().skip(1).filter(|arg| arg != "--supervise").collect()
+}
+
+/// CLI definition with help text in the current locale
+fn localized_cli() -> clap::Command {
+    Cli::command()
+        .about(tr!("cli-about"))
+        .mut_arg("toggle", |a| a.help(tr!("cli-toggle")))
+        .mut_arg("show", |a| a.help(tr!("cli-show")))
+        .mut_arg("hide", |a| a.help(tr!("cli-hide")))
+        .mut_arg("ask_screenshot", |a| a.help(tr!("cli-ask-screenshot")))
+        .mut_arg("status", |a| a.help(tr!("cli-status")))
+        .mut_arg("ping", |a| a.help(tr!("cli-ping")))
+        .mut_arg("restart_webview", |a| a.help(tr!("cli-restart-webview")))
+        .mut_arg("subscribe", |a| a.help(tr!("cli-subscribe")))
+        .mut_arg("profile", |a| a.help(tr!("cli-profile")))
+        .mut_arg("remind", |a| a.help(tr!("cli-remind")))
+        .mut_arg("remind_in", |a| a.help(tr!("cli-remind-in")))
+        .mut_arg("log_level", |a| a.help(tr!("cli-log-level")))
+        .mut_arg("unload_after", |a| a.help(tr!("cli-unload-after")))
+        .mut_arg("strict_hotkeys", |a| a.help(tr!("cli-strict-hotkeys")))
+        .mut_arg("no_meeting_mode", |a| a.help(tr!("cli-no-meeting-mode")))
+        .mut_arg("shortcut", |a| a.help(tr!("cli-shortcut")))
+        .mut_arg("debug", |a| a.help(tr!("cli-debug")))
+        .mut_arg("log_file", |a| a.help(tr!("cli-log-file")))
+        .mut_arg("supervise", |a| a.help(tr!("cli-supervise")))
+        .mut_arg("install_autostart", |a| a.help(tr!("cli-install-autostart")))
+        .mut_arg("uninstall_autostart", |a| a.help(tr!("cli-uninstall-autostart")))
+        .mut_arg("check_update", |a| a.help(tr!("cli-check-update")))
+        .mut_arg("self_update", |a| a.help(tr!("cli-self-update")))
+        .mut_arg("locale", |a| a.help(tr!("cli-locale")))
+        .mut_subcommand("plugin", |c| {
+            c.about(tr!("cli-plugin"))
+                .mut_subcommand("list", |s| s.about(tr!("cli-plugin-list")))
+                .mut_subcommand("install", |s| s.about(tr!("cli-plugin-install")))
+                .mut_subcommand("remove", |s| s.about(tr!("cli-plugin-remove")))
+                .mut_subcommand("enable", |s| s.about(tr!("cli-plugin-enable")))
+                .mut_subcommand("disable", |s| s.about(tr!("cli-plugin-disable")))
+                .mut_subcommand("grant", |s| s.about(tr!("cli-plugin-grant")))
+                .mut_subcommand("revoke", |s| s.about(tr!("cli-plugin-revoke")))
+        })
 }
 
 fn main() -> Result<()> {
-    let cli = Cli::parse();
+    i18n::init(locale_arg().as_deref());
+    let cli = Cli::from_arg_matches(&localized_cli().get_matches()).unwrap_or_else(|e| e.exit());
+
+    if let Some(Command::Plugin { action }) = cli.command {
+        return plugins::run_cli(action);
+    }
+    if let Some(method) = cli.install_autostart {
+        return autostart::install(method, &autostart_args());
+    }
+    if cli.uninstall_autostart {
+        return autostart::uninstall();
+    }
+    if cli.check_update {
+        return update::check();
+    }
+    if cli.self_update {
+        return update::self_update();
+    }
 
     // Handle CLI commands (client mode) - send to running instance and exit
     if cli.toggle {
         eprintln!("[CLI] Sending toggle command via IPC socket...");
         match ipc::send_command("toggle") {
-            Ok(()) => {
+            Ok(_) => {
                 eprintln!("[CLI] Toggle command sent successfully");
                 return Ok(());
             }
             Err(e) => {
                 eprintln!("[CLI] Failed to send toggle: {}", e);
-                return Err(anyhow::anyhow!("Failed to send toggle: {}. Is desktop-waifu running?", e));
+                return Err(anyhow::anyhow!(tr!("cli-send-failed", command = "toggle", error = e.to_string())));
             }
         }
     }
     if cli.show {
         return ipc::send_command("show")
-            .map_err(|e| anyhow::anyhow!("Failed to send show: {}. Is desktop-waifu running?", e));
+            .map(drop)
+            .map_err(|e| anyhow::anyhow!(tr!("cli-send-failed", command = "show", error = e.to_string())));
     }
     if cli.hide {
         return ipc::send_command("hide")
-            .map_err(|e| anyhow::anyhow!("Failed to send hide: {}. Is desktop-waifu running?", e));
+            .map(drop)
+            .map_err(|e| anyhow::anyhow!(tr!("cli-send-failed", command = "hide", error = e.to_string())));
+    }
+    if cli.ask_screenshot {
+        return ipc::send_command("ask-screenshot")
+            .map(drop)
+            .map_err(|e| anyhow::anyhow!(tr!("cli-send-failed", command = "ask-screenshot", error = e.to_string())));
+    }
+    if cli.status {
+        let status = ipc::request(ipc::Request::GetState)
+            .map_err(|e| anyhow::anyhow!(tr!("cli-send-failed", command = "status", error = e.to_string())))?;
+        println!("{}", serde_json::to_string_pretty(&status)?);
+        return Ok(());
+    }
+    if cli.ping {
+        let pong = ipc::request(ipc::Request::Ping)
+            .map_err(|e| anyhow::anyhow!(tr!("cli-send-failed", command = "ping", error = e.to_string())))?;
+        println!("{}", serde_json::to_string_pretty(&pong)?);
+        if pong["webviewAlive"] != true {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if cli.restart_webview {
+        return ipc::request(ipc::Request::RestartWebview)
+            .map(drop)
+            .map_err(|e| anyhow::anyhow!(tr!("cli-send-failed", command = "restart-webview", error = e.to_string())));
+    }
+    if cli.subscribe {
+        return ipc::subscribe(|event| {
+            println!("{}", event);
+            // Status bars read line by line from a pipe
+            let _ = std::io::stdout().flush();
+        })
+        .map_err(|e| anyhow::anyhow!(tr!("cli-send-failed", command = "subscribe", error = e.to_string())));
+    }
+    if let Some(profile) = cli.profile {
+        return ipc::send_command(&format!("profile {}", profile))
+            .map(drop)
+            .map_err(|e| anyhow::anyhow!(tr!("cli-send-failed", command = "profile", error = e.to_string())));
+    }
+    if let (Some(text), Some(delay)) = (cli.remind, cli.remind_in) {
+        let after = reminders::parse_duration(&delay).map_err(|e| anyhow::anyhow!(e))?;
+        let request = ipc::Request::AddReminder { text, after_secs: after.as_secs() };
+        let reminder = ipc::request(request)
+            .map_err(|e| anyhow::anyhow!(tr!("cli-send-failed", command = "remind", error = e.to_string())))?;
+        let due = reminder["due"].as_i64().and_then(|ms| glib::DateTime::from_unix_local(ms / 1000).ok());
+        if let Some(time) = due.and_then(|due| due.format("%X").ok()) {
+            println!("{}", tr!("cli-reminder-set", time = time.to_string()));
+        }
+        return Ok(());
+    }
+    if let Some(filter) = cli.log_level {
+        return ipc::send_command(&format!("log-level {}", filter))
+            .map(drop)
+            .map_err(|e| anyhow::anyhow!(tr!("cli-send-failed", command = "log-level", error = e.to_string())));
+    }
+
+    // Supervisor mode - run the overlay below and restart it when it crashes. The log
+    // file is left to the overlay, so the two don't rotate it from under each other
+    if cli.supervise {
+        logging::init(cli.debug, false)?;
+        return supervisor::run(supervised_args());
     }
 
     // Normal startup (server mode) - continue with GUI
     // Initialize logging
-    let subscriber = FmtSubscriber::builder()
-        .with_max_level(Level::DEBUG)
-        .finish();
-    tracing::subscriber::set_global_default(subscriber)?;
+    logging::init(cli.debug, cli.log_file)?;
 
-    info!("Starting desktop-waifu-overlay");
+    info!("Starting desktop-waifu-overlay (locale {})", i18n::current_locale());
+    if let Ok(restarts) = std::env::var(supervisor::RESTARTS_ENV) {
+        tracing::warn!("Restarted by the supervisor after a crash ({} restarts so far)", restarts);
+    }
+    let overlay_config = config::init();
+    if overlay_config.debug_logging {
+        logging::set_debug(true);
+    }
+    // Before anything starts WebKit or reads the environment from another thread
+    render::probe(overlay_config.render.mode);
 
     // Determine the URL to load: try dev server first, fall back to static files
-    let webview_url = if server::is_dev_server_available() {
-        info!("Vite dev server detected on port 1420");
-        "http://localhost:1420?overlay=true".to_string()
+    let dev_port = overlay_config.dev_server_port;
+    let webview_url = if server::is_dev_server_available(dev_port) {
+        info!("Vite dev server detected on port {}", dev_port);
+        format!("http://localhost:{}?overlay=true", dev_port)
     } else {
         // Production mode: find dist directory and start static server
-        let dist_path = server::find_dist_dir().ok_or_else(|| {
-            anyhow::anyhow!(
-                "Could not find dist directory. Build the frontend first with: bun build"
-            )
-        })?;
+        let dist_path = server::find_dist_dir().ok_or_else(|| anyhow::anyhow!(tr!("error-no-dist")))?;
 
         info!("Production mode: serving static files from {:?}", dist_path);
+        // Updates were checked when they were picked; this is the dist installed with us
+        if frontend::installed_update().as_ref() != Some(&dist_path) {
+            let damaged = frontend::verify(&dist_path);
+            if !damaged.is_empty() {
+                tracing::warn!("Frontend files differ from their manifest: {}", damaged.join(", "));
+            }
+        }
 
-        // Start tokio runtime in a separate thread for the HTTP server
-        let (tx, rx) = std::sync::mpsc::channel();
-        let dist_path_clone = dist_path.clone();
-
-        std::thread::spawn(move || {
-            let rt = tokio::runtime::Runtime::new().unwrap();
-            rt.block_on(async {
-                match server::start_static_server(dist_path_clone).await {
-                    Ok(port) => {
-                        tx.send(Ok(port)).ok();
-                        // Keep the runtime alive
-                        std::future::pending::<()>().await;
-                    }
-                    Err(e) => {
-                        tx.send(Err(e)).ok();
-                    }
-                }
-            });
-        });
-
-        // Wait for server to start
-        let port = rx
-            .recv()
-            .map_err(|e| anyhow::anyhow!("Server thread died: {}", e))?
+        // Bind the HTTP server on the shared runtime and wait until it's listening
+        let server = runtime::handle()
+            .block_on(server::start_static_server(dist_path.clone(), overlay_config.static_server_port))
             .map_err(|e| anyhow::anyhow!("{}", e))?;
-        format!("http://localhost:{}?overlay=true", port)
+        server.url()
     };
 
-    info!("WebView will load from: {}", webview_url);
+    info!("WebView will load from: {}", server::redact(&webview_url));
 
     // Create GTK application
     let app = Application::builder()
         .application_id(APP_ID)
         .build();
 
+    // Quit through the normal shutdown path on SIGTERM/SIGINT/SIGHUP so state is saved
+    // and the socket is removed instead of dying mid-write. A second signal means whoever
+    // sent it is done waiting, so it skips the rest of the shutdown.
+    let signals_received = Rc::new(Cell::new(0u32));
+    for signal in [libc::SIGTERM, libc::SIGINT, libc::SIGHUP] {
+        let app = app.clone();
+        let signals_received = signals_received.clone();
+        glib::unix_signal_add_local(signal, move || {
+            signals_received.set(signals_received.get() + 1);
+            if signals_received.get() > 1 {
+                tracing::warn!("Received signal {} again, exiting without finishing shutdown", signal);
+                ipc::remove_socket();
+                std::process::exit(0);
+            }
+            info!("Received signal {}, quitting", signal);
+            // Take the overlay off screen now; the rest of the shutdown can take a few seconds
+            for window in app.windows() {
+                window.close();
+            }
+            app.quit();
+            glib::ControlFlow::Continue
+        });
+    }
+
     // Clone URL for the closure
     let url_for_activate = webview_url.clone();
+    let options = UiOptions {
+        unload_after: cli.unload_after.map(Duration::from_secs),
+        strict_hotkeys: cli.strict_hotkeys,
+        meeting_mode: !cli.no_meeting_mode,
+        shortcut: (cli.shortcut != "none").then(|| cli.shortcut.clone()),
+        debug: cli.debug,
+    };
     app.connect_activate(move |app| {
-        build_ui(app, &url_for_activate);
+        build_ui(app, &url_for_activate, &options);
     });
 
     // Run the application
     let exit_code = app.run();
+    runtime::shutdown();
 
     if exit_code != glib::ExitCode::SUCCESS {
         anyhow::bail!("Application exited with error code");
@@ -205,17 +645,36 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn build_ui(app: &Application, webview_url: &str) {
+/// Startup options that shape the UI's behavior
+#[derive(Clone, Debug, Default)]
+struct UiOptions {
+    /// Memory mode: unload web content after being hidden this long
+    unload_after: Option<Duration>,
+    /// Gate every IPC command on the hotkey setting, not just toggle, whatever config.toml says
+    strict_hotkeys: bool,
+    /// Go quiet while the user is on a call (see presence.rs)
+    meeting_mode: bool,
+    /// Trigger to suggest for the portal's global toggle shortcut (see shortcuts.rs)
+    shortcut: Option<String>,
+    /// --debug; config.toml's debug_logging can only add to it
+    debug: bool,
+}
+
+fn build_ui(app: &Application, webview_url: &str, options: &UiOptions) {
+    let overlay_config = config::current();
+
     // Create the main window (start with character-only size, expands when chat opens)
     let window = ApplicationWindow::builder()
         .application(app)
         .title("Desktop Waifu Overlay")
-        .default_width(WINDOW_WIDTH_COLLAPSED)
-        .default_height(WINDOW_HEIGHT_COLLAPSED)
+        .default_width(overlay_config.window.collapsed_width)
+        .default_height(overlay_config.window.collapsed_height)
         .build();
 
     // Set up CSS for transparency
     let css_provider = gtk4::CssProvider::new();
+    // load_from_string replaces this in GTK 4.12, newer than the minimum we support
+    #[allow(deprecated)]
     css_provider.load_from_data(
         "window, window.background { background-color: transparent; }",
     );
@@ -229,8 +688,8 @@ fn build_ui(app: &Application, webview_url: &str) {
     window.init_layer_shell();
 
     // Configure layer shell properties
-    // Use OVERLAY layer (above everything)
-    window.set_layer(Layer::Overlay);
+    // OVERLAY layer (above everything) unless config.toml asks for TOP
+    window.set_layer(overlay_config.window.layer.layer());
 
     // Anchor to ALL edges (fullscreen window)
     // This makes the window cover the entire screen
@@ -239,16 +698,37 @@ fn build_ui(app: &Application, webview_url: &str) {
     window.set_anchor(Edge::Left, true);
     window.set_anchor(Edge::Right, true);
 
+    // Put the character back where it was when the overlay last ran
+    let saved_state = state::load();
+    if saved_state.notifications_muted {
+        notifications::set_muted(true);
+    }
+    let saved_monitor = saved_state.monitor.as_deref().and_then(monitors::find);
+    if let Some(ref monitor) = saved_monitor {
+        window.set_monitor(Some(monitor));
+    }
+    let (screen_width, screen_height) = match saved_monitor {
+        Some(ref monitor) => (monitor.geometry().width(), monitor.geometry().height()),
+        None => monitors::screen_dimensions(&window),
+    };
+
     // Character position (absolute screen coordinates)
-    let position = Rc::new(RefCell::new(CharacterPosition::default()));
+    let position = Rc::new(RefCell::new(CharacterPosition::restore(&saved_state, screen_width, screen_height)));
 
     // Drag state
     let drag_state = Rc::new(RefCell::new(DragState::default()));
 
     // Quadrant state (initially bottom-right)
     let quadrant = Rc::new(RefCell::new(Quadrant {
-        is_right_half: true,
-        is_bottom_half: true,
+        is_right_half: saved_state.is_right_half.unwrap_or(true),
+        is_bottom_half: saved_state.is_bottom_half.unwrap_or(true),
+    }));
+
+    // Chat dock mode and size (see layout.rs)
+    let chat = Rc::new(RefCell::new(layout::ChatState {
+        dock: saved_state.chat_dock,
+        size: None,
+        open: false,
     }));
 
     // No margins needed - window is fullscreen
@@ -257,11 +737,12 @@ fn build_ui(app: &Application, webview_url: &str) {
     window.set_margin(Edge::Left, 0);
     window.set_margin(Edge::Right, 0);
 
-    // Don't reserve exclusive space
-    window.set_exclusive_zone(-1);
+    // Don't reserve exclusive space, and stay clear of the space panels reserve unless
+    // `[window] respect_panels` is off
+    window.set_exclusive_zone(overlay_config.window.exclusive_zone());
 
-    // Allow keyboard focus when user clicks on the overlay (for text input)
-    window.set_keyboard_mode(KeyboardMode::OnDemand);
+    // Keyboard mode follows the page's text fields (focus.rs); until it loads, the idle mode
+    window.set_keyboard_mode(overlay_config.window.keyboard_mode.mode());
 
     // Set namespace for compositor identification
     window.set_namespace(Some("desktop-waifu"));
@@ -278,24 +759,152 @@ fn build_ui(app: &Application, webview_url: &str) {
     };
 
     // Track visibility state (shared between tray, IPC, and windowControl handlers)
-    let is_visible = Rc::new(RefCell::new(true));
+    let is_visible = Rc::new(RefCell::new(saved_state.visible));
+    if let Some(ref handle) = tray_handle {
+        update_tray_visibility(handle, saved_state.visible);
+        tray::update_tray_scale(handle, position.borrow().scale());
+    }
 
     // Create WebView with message handler for drag events and window control
-    let webview = create_webview_with_handlers(&window, position, drag_state, quadrant, tray_handle.clone(), is_visible.clone());
+    // Large payloads reach the frontend as streamed waifu-transfer:// URLs
+    let transfers = transfer::TransferStore::default();
+    let webview = create_webview_with_handlers(&window, position.clone(), drag_state.clone(), quadrant.clone(), chat.clone(), tray_handle.clone(), is_visible.clone(), transfers.clone());
+
+    // Wandering about on its own when `[walk]` or the tray turns it on (see walk.rs)
+    let walker = walk::Walker::new(
+        &window,
+        &webview,
+        position.clone(),
+        quadrant.clone(),
+        chat.clone(),
+        drag_state,
+        is_visible.clone(),
+    );
+    if let Some(ref handle) = tray_handle {
+        tray::update_tray_walking(handle, walker.enabled());
+    }
+
+    // Only the page's own origin and schemes load; links open in the browser
+    content_policy::install(&webview, webview_url);
+
+    // Faces for what the assistant is doing (see expressions.rs)
+    expressions::install(&webview);
 
     // Add WebView to window
     window.set_child(Some(&webview));
 
+    if let Some(unload_after) = options.unload_after {
+        lifecycle::install(&window, &webview, webview_url.to_string(), unload_after);
+    }
+
+    accessibility::setup(&window, &webview);
+
     // Set up keyboard focus handler (needs access to webview)
     let content_manager = webview.user_content_manager().unwrap();
     content_manager.register_script_message_handler("keyboardFocus", None);
 
+    let window_for_focus = window.clone();
     let webview_for_focus = webview.clone();
     content_manager.connect_script_message_received(Some("keyboardFocus"), move |_manager, _js_value| {
         debug_log!("[FOCUS] Keyboard focus requested, grabbing focus");
+        focus::grab(&window_for_focus);
         webview_for_focus.grab_focus();
     });
 
+    // The frontend reports which character it shows, for --status, and the ones it offers,
+    // for the tray
+    let character = Rc::new(RefCell::new(None::<String>));
+    content_manager.register_script_message_handler("characterChanged", None);
+    let character_for_handler = character.clone();
+    let tray_handle_for_character = tray_handle.clone();
+    content_manager.connect_script_message_received(Some("characterChanged"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let active = parsed["character"].as_str().map(str::to_string);
+        *character_for_handler.borrow_mut() = active.clone();
+        if let (Some(handle), Some(list)) = (&tray_handle_for_character, parsed["characters"].as_array()) {
+            let characters = list
+                .iter()
+                .filter_map(|c| {
+                    Some(tray::CharacterChoice {
+                        id: c["id"].as_str()?.to_string(),
+                        name: c["name"].as_str()?.to_string(),
+                    })
+                })
+                .collect();
+            tray::update_tray_characters(handle, characters, active);
+        }
+    });
+
+    // The tray icon shows when the character is thinking and when a reply is waiting unread
+    content_manager.register_script_message_handler("trayState", None);
+    let tray_handle_for_state = tray_handle.clone();
+    content_manager.connect_script_message_received(Some("trayState"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        if let Some(thinking) = parsed["thinking"].as_bool() {
+            expressions::set_thinking(thinking);
+        }
+        let Some(ref handle) = tray_handle_for_state else { return };
+        if let Some(thinking) = parsed["thinking"].as_bool() {
+            tray::update_tray_thinking(handle, thinking);
+        }
+        if let Some(unread) = parsed["unread"].as_bool() {
+            tray::update_tray_unread(handle, unread);
+        }
+    });
+
+    // Character scale, set from the tray and IPC or reported by the frontend when the
+    // settings change it; saved with the overlay state and handed to the frontend whenever
+    // the page loads. What a page reports before it's been told the scale is only its own
+    // stored setting, which would override `[window] scale` and the tray's, so it's ignored.
+    let scale_sent = Rc::new(Cell::new(false));
+    content_manager.register_script_message_handler("scaleChanged", None);
+    let position_for_scale = position.clone();
+    let tray_handle_for_scale = tray_handle.clone();
+    let scale_sent_for_handler = scale_sent.clone();
+    content_manager.connect_script_message_received(Some("scaleChanged"), move |_manager, js_value| {
+        if !scale_sent_for_handler.get() {
+            return;
+        }
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        if let Some(value) = parsed["scale"].as_f64() {
+            let scale = (value as f32).clamp(MIN_SCALE, MAX_SCALE);
+            let mut position = position_for_scale.borrow_mut();
+            if (position.scale() - scale).abs() < 0.01 {
+                return;
+            }
+            position.scale = Some(scale);
+            state::changed();
+            if let Some(ref handle) = tray_handle_for_scale {
+                tray::update_tray_scale(handle, scale);
+            }
+        }
+    });
+    let position_for_load = position.clone();
+    webview.connect_load_changed(move |webview, load_event| match load_event {
+        webkit6::LoadEvent::Started => scale_sent.set(false),
+        webkit6::LoadEvent::Finished => {
+            let scale = position_for_load.borrow().scale();
+            dispatch_event(webview, "setScale", &serde_json::json!({ "scale": scale }));
+            scale_sent.set(true);
+            expressions::page_loaded();
+        }
+        _ => {}
+    });
+
+    // Screen reader announcements for new character messages and state changes
+    content_manager.register_script_message_handler("announce", None);
+    let window_for_announce = window.clone();
+    content_manager.connect_script_message_received(Some("announce"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        match serde_json::from_str::<accessibility::Announcement>(json_str.as_str()) {
+            Ok(announcement) => accessibility::announce(&window_for_announce, &announcement),
+            Err(e) => debug_log!("[A11Y] Invalid announcement from frontend: {}", e),
+        }
+    });
+
     // Track hotkey enabled state (controlled by frontend settings)
     let hotkey_enabled = Rc::new(RefCell::new(false));
 
@@ -311,27 +920,94 @@ fn build_ui(app: &Application, webview_url: &str) {
         }
     });
 
+    // Night light: the tray override goes to the monitor, state changes go to the frontend
+    let (night_light_mode_tx, night_light_mode_rx) = async_channel::unbounded();
+    let night_light = Rc::new(RefCell::new(nightlight::NightLight::default()));
+    let night_light_receiver = nightlight::spawn_monitor(night_light_mode_rx);
+    let night_light_for_monitor = night_light.clone();
+    let webview_for_night_light = webview.clone();
+    glib::spawn_future_local(async move {
+        while let Ok(state) = night_light_receiver.recv().await {
+            dispatch_event(&webview_for_night_light, "nightLightChanged", &serde_json::json!(state));
+            *night_light_for_monitor.borrow_mut() = state;
+        }
+    });
+
+    content_manager.register_script_message_handler("getNightLight", None);
+    let webview_for_night_light_request = webview.clone();
+    content_manager.connect_script_message_received(Some("getNightLight"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let Some(callback_id) = parsed["callbackId"].as_str() else { return };
+        let current = serde_json::json!(*night_light.borrow());
+        dispatch_callback(&webview_for_night_light_request, callback_id, &current);
+    });
+
+    // Media players: track changes go to the frontend, which can also pause or skip
+    let now_playing = Rc::new(RefCell::new(None::<mpris::NowPlaying>));
+    let now_playing_receiver = mpris::spawn_monitor();
+    let now_playing_for_monitor = now_playing.clone();
+    let webview_for_now_playing = webview.clone();
+    glib::spawn_future_local(async move {
+        while let Ok(playing) = now_playing_receiver.recv().await {
+            dispatch_event(&webview_for_now_playing, "nowPlayingChanged", &serde_json::json!(playing));
+            *now_playing_for_monitor.borrow_mut() = playing;
+        }
+    });
+
+    content_manager.register_script_message_handler("getNowPlaying", None);
+    let webview_for_now_playing_request = webview.clone();
+    content_manager.connect_script_message_received(Some("getNowPlaying"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let Some(callback_id) = parsed["callbackId"].as_str() else { return };
+        let current = serde_json::json!(*now_playing.borrow());
+        dispatch_callback(&webview_for_now_playing_request, callback_id, &current);
+    });
+
+    for (name, action) in [
+        ("mediaPlayPause", mpris::Action::PlayPause),
+        ("mediaNext", mpris::Action::Next),
+        ("mediaPrevious", mpris::Action::Previous),
+    ] {
+        content_manager.register_script_message_handler(name, None);
+        let webview_for_media = webview.clone();
+        content_manager.connect_script_message_received(Some(name), move |_manager, js_value| {
+            let Some(json_str) = js_value.to_json(0) else { return };
+            let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+            let callback_id = parsed["callbackId"].as_str().unwrap_or("").to_string();
+            spawn_with_callback(&webview_for_media, callback_id, move || match mpris::control(action) {
+                Ok(()) => serde_json::json!({ "success": true }),
+                Err(e) => serde_json::json!({ "success": false, "error": e.to_string() }),
+            });
+        });
+    }
+
+    // Profile switches from the tray, IPC and the schedule all go through one channel and
+    // are applied once the overlay UI exists (see apply_profile)
+    let profiles = Rc::new(RefCell::new(profiles::ProfileManager::load()));
+    let (profile_tx, profile_rx) = async_channel::unbounded::<Option<String>>();
+    if let Some(ref handle) = tray_handle {
+        update_tray_profiles(handle, profiles.borrow().names(), None);
+    }
+
     // Set up tray message handler on GTK main loop
     if let Some(receiver) = tray_receiver {
+        let profile_tx_for_tray = profile_tx.clone();
         let window_for_tray = window.clone();
         let webview_for_tray = webview.clone();
         let tray_handle_for_update = tray_handle.clone();
         let is_visible_for_tray = is_visible.clone();
+        let position_for_tray = position.clone();
+        let walker_for_tray = walker.clone();
 
-        // Poll for tray messages every 100ms
-        glib::timeout_add_local(Duration::from_millis(100), move || {
-            while let Ok(msg) = receiver.try_recv() {
+        glib::spawn_future_local(async move {
+            while let Ok(msg) = receiver.recv().await {
                 match msg {
                     TrayMessage::Show => {
                         window_for_tray.present();
                         *is_visible_for_tray.borrow_mut() = true;
-                        webview_for_tray.evaluate_javascript(
-                            "window.dispatchEvent(new CustomEvent('trayShow'))",
-                            None,
-                            None,
-                            None::<&gio::Cancellable>,
-                            |_| {},
-                        );
+                        dispatch_event(&webview_for_tray, "trayShow", &serde_json::Value::Null);
                         if let Some(ref handle) = tray_handle_for_update {
                             update_tray_visibility(handle, true);
                         }
@@ -343,141 +1019,1366 @@ fn build_ui(app: &Application, webview_url: &str) {
                             update_tray_visibility(handle, false);
                         }
                     }
+                    TrayMessage::SetProfile(profile) => {
+                        let _ = profile_tx_for_tray.send(profile).await;
+                    }
+                    TrayMessage::SetNightLight(mode) => {
+                        let _ = night_light_mode_tx.send(mode).await;
+                    }
+                    TrayMessage::PlayAnimation(name) => match animations::play_event(&ipc::AnimationRequest::named(name)) {
+                        Ok(detail) => dispatch_event(&webview_for_tray, "playAnimation", &detail),
+                        Err(e) => tracing::warn!("Can't play animation: {}", e),
+                    },
+                    TrayMessage::SetExpression(expression) => expressions::set(&expression, None),
+                    TrayMessage::SetScale(scale) => set_character_scale(
+                        &window_for_tray,
+                        &webview_for_tray,
+                        &position_for_tray,
+                        tray_handle_for_update.as_ref(),
+                        scale,
+                    ),
+                    TrayMessage::SetCharacter(id) => {
+                        dispatch_event(&webview_for_tray, "setCharacter", &serde_json::json!({ "id": id }));
+                    }
+                    TrayMessage::SetNotificationsMuted(muted) => {
+                        notifications::set_muted(muted);
+                        state::changed();
+                    }
+                    TrayMessage::SetClickThrough(enabled) => input_region::set_click_through(&window_for_tray, enabled),
+                    TrayMessage::SetWalking(enabled) => walker_for_tray.set_enabled(enabled),
+                    TrayMessage::OpenSettings => {
+                        if !*is_visible_for_tray.borrow() {
+                            window_for_tray.present();
+                            *is_visible_for_tray.borrow_mut() = true;
+                            dispatch_event(&webview_for_tray, "trayShow", &serde_json::json!({}));
+                            if let Some(ref handle) = tray_handle_for_update {
+                                update_tray_visibility(handle, true);
+                            }
+                        }
+                        // The settings have text fields, so take the keyboard like the hotkey does
+                        focus::grab(&window_for_tray);
+                        webview_for_tray.grab_focus();
+                        dispatch_event(&webview_for_tray, "openSettings", &serde_json::json!({}));
+                    }
                     TrayMessage::Quit => {
                         window_for_tray.close();
-                        return glib::ControlFlow::Break;
+                        break;
+                    }
+                }
+            }
+        });
+    }
+
+    // Shortcuts the overlay catches itself, as the page can miss them (see keys.rs)
+    let window_for_keys = window.clone();
+    let webview_for_keys = webview.clone();
+    let tray_handle_for_keys = tray_handle.clone();
+    let inspector_open = Rc::new(Cell::new(false));
+    if let Some(inspector) = webview.inspector() {
+        let inspector_open = inspector_open.clone();
+        inspector.connect_closed(move |_| inspector_open.set(false));
+    }
+    keys::install(&window, move |action| {
+        match action {
+            // The page closes the chat, unless Escape was for its input or a command
+            keys::Action::CloseChat => {}
+            keys::Action::FocusInput => webview_for_keys.grab_focus(),
+            keys::Action::DevTools => {
+                if let Some(inspector) = webview_for_keys.inspector() {
+                    if inspector_open.replace(!inspector_open.get()) {
+                        inspector.close();
+                    } else {
+                        inspector.show();
+                    }
+                }
+            }
+            keys::Action::ClickThrough => {
+                let enabled = !input_region::click_through();
+                input_region::set_click_through(&window_for_keys, enabled);
+                if let Some(ref handle) = tray_handle_for_keys {
+                    tray::update_tray_click_through(handle, enabled);
+                }
+            }
+        }
+        dispatch_event(&webview_for_keys, "overlayShortcut", &serde_json::json!({ "action": action.name() }));
+    });
+
+    // Spawn git watcher for registered repositories (developer context)
+    let (git_watcher, git_receiver) = git::spawn_git_watcher();
+    content_manager.register_script_message_handler("gitWatcher", None);
+
+    let webview_for_git = webview.clone();
+    let git_watcher_for_handler = git_watcher.clone();
+    content_manager.connect_script_message_received(Some("gitWatcher"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+
+        let action = parsed["action"].as_str().unwrap_or("").to_string();
+        let path = std::path::PathBuf::from(parsed["path"].as_str().unwrap_or(""));
+        let callback_id = parsed["callbackId"].as_str().unwrap_or("").to_string();
+        debug_log!("[GIT] Action '{}' for {:?}", action, path);
+
+        let result = match action.as_str() {
+            "list" => serde_json::json!(git_watcher_for_handler.statuses()),
+            "context" => serde_json::json!({ "summary": git_watcher_for_handler.context_summary() }),
+            "unregister" => serde_json::json!({ "removed": git_watcher_for_handler.unregister(&path) }),
+            "register" | "commitContext" => {
+                // Both run git (and possibly gh) commands, so keep them off the main thread
+                let watcher = git_watcher_for_handler.clone();
+                spawn_with_callback(&webview_for_git, callback_id, move || {
+                    let result = if action == "register" {
+                        watcher.register(&path).map(|s| serde_json::json!(s))
+                    } else {
+                        git::commit_context(&path).map(|c| serde_json::json!(c))
+                    };
+                    result.unwrap_or_else(|e| serde_json::json!({ "error": e }))
+                });
+                return;
+            }
+            _ => return,
+        };
+        dispatch_callback(&webview_for_git, &callback_id, &result);
+    });
+
+    // Forward repo status changes to the frontend
+    let webview_for_git_events = webview.clone();
+    glib::spawn_future_local(async move {
+        while let Ok(status) = git_receiver.recv().await {
+            debug_log!("[GIT] Status changed for {:?}", status.path);
+            dispatch_event(&webview_for_git_events, "gitStatusChange", &serde_json::json!(status));
+        }
+    });
+
+    // Load plugins and expose the shared tool registry to the frontend
+    let tool_registry = tools::ToolRegistry::default();
+    tools::builtin::register_builtin_tools(&tool_registry);
+    let plugin_event_receiver = plugins::event_receiver();
+    let (plugin_host, pending_plugins) = plugins::PluginHost::load_all(&tool_registry);
+    tools::manifest::load_manifest_tools(&tool_registry);
+    for plugin in plugin_host.plugins() {
+        attach_plugin_handlers(&content_manager, &webview, plugin.clone());
+    }
+    let plugin_host = Rc::new(RefCell::new(plugin_host));
+
+    content_manager.register_script_message_handler("tools", None);
+    let window_for_tools = window.clone();
+    let webview_for_tools = webview.clone();
+    let tool_registry_for_handler = tool_registry.clone();
+    let profiles_for_tools = profiles.clone();
+    content_manager.connect_script_message_received(Some("tools"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let callback_id = parsed["callbackId"].as_str().unwrap_or("").to_string();
+
+        match parsed["action"].as_str().unwrap_or("") {
+            "list" => {
+                let profiles = profiles_for_tools.borrow();
+                let definitions: Vec<_> = tool_registry_for_handler
+                    .definitions()
+                    .into_iter()
+                    .filter(|d| profiles.allows_tool(&d.name))
+                    .collect();
+                let definitions = serde_json::json!(definitions);
+                dispatch_callback(&webview_for_tools, &callback_id, &definitions);
+            }
+            "call" => {
+                let name = parsed["name"].as_str().unwrap_or("").to_string();
+                let args = parsed["args"].clone();
+                if !profiles_for_tools.borrow().allows_tool(&name) {
+                    let error = serde_json::json!({ "error": format!("Tool '{}' is not allowed in the current profile", name) });
+                    dispatch_callback(&webview_for_tools, &callback_id, &error);
+                    return;
+                }
+                let Some(tool) = tool_registry_for_handler.get(&name) else {
+                    let error = serde_json::json!({ "error": format!("Unknown tool '{}'", name) });
+                    dispatch_callback(&webview_for_tools, &callback_id, &error);
+                    return;
+                };
+                let requires_confirmation = tool.definition().requires_confirmation;
+                let subject = format!("{} {}", name, args);
+                if requires_confirmation && !confirm::enabled() && !parsed["confirmed"].as_bool().unwrap_or(false) {
+                    audit::record(audit::Kind::ToolCall, &subject, audit::Outcome::Refused, Some("not confirmed".into()));
+                    let error = serde_json::json!({
+                        "error": format!("Tool '{}' requires user confirmation", name),
+                        "confirmationRequired": true,
+                    });
+                    dispatch_callback(&webview_for_tools, &callback_id, &error);
+                    return;
+                }
+                // With native confirmation the user approves the call here, not in the chat
+                let prompt = (requires_confirmation && confirm::enabled()).then(|| confirm::tool_prompt(&name, &args));
+                let webview = webview_for_tools.clone();
+                let callback_id_for_decline = callback_id.clone();
+                let subject_for_decline = subject.clone();
+                let call = move || {
+                    debug_log!("[TOOLS] Calling tool '{}'", name);
+                    spawn_with_callback(&webview, callback_id, move || {
+                        let result = tools::call(tool.as_ref(), args);
+                        audit::result(audit::Kind::ToolCall, &subject, &result);
+                        match result {
+                            Ok(result) => serde_json::json!({ "result": result }),
+                            Err(e) => serde_json::json!({ "error": e }),
+                        }
+                    });
+                };
+                match prompt {
+                    None => call(),
+                    Some(prompt) => {
+                        let webview = webview_for_tools.clone();
+                        confirm::ask(&window_for_tools, prompt, move |approved| {
+                            if approved {
+                                call();
+                            } else {
+                                audit::record(audit::Kind::ToolCall, &subject_for_decline, audit::Outcome::Refused, Some(confirm::DECLINED.into()));
+                                let error = serde_json::json!({ "error": confirm::DECLINED });
+                                dispatch_callback(&webview, &callback_id_for_decline, &error);
+                            }
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    });
+
+    // Forward events raised by plugins to the frontend
+    if let Some(receiver) = plugin_event_receiver {
+        let webview_for_plugin_events = webview.clone();
+        glib::spawn_future_local(async move {
+            while let Ok(event) = receiver.recv().await {
+                debug_log!("[PLUGIN] Event '{}' from '{}'", event.name, event.plugin);
+                let detail = serde_json::json!({
+                    "plugin": event.plugin,
+                    "name": event.name,
+                    "detail": event.detail,
+                });
+                dispatch_event(&webview_for_plugin_events, "pluginEvent", &detail);
+            }
+        });
+    }
+
+    // Ask the user to approve plugins whose permissions haven't been granted yet
+    if !pending_plugins.is_empty() {
+        let window_for_prompt = window.clone();
+        let content_manager_for_prompt = content_manager.clone();
+        let webview_for_prompt = webview.clone();
+        let plugin_host_for_prompt = plugin_host.clone();
+        let tool_registry_for_prompt = tool_registry.clone();
+        glib::idle_add_local_once(move || {
+            prompt_plugin_grants(
+                window_for_prompt.clone(),
+                pending_plugins,
+                Rc::new(move |manifest: plugins::PluginManifest| {
+                    if let Err(e) = plugins::grant(&manifest) {
+                        tracing::warn!("Failed to save plugin grant: {}", e);
+                    }
+                    let loaded = plugin_host_for_prompt
+                        .borrow_mut()
+                        .load(manifest, &tool_registry_for_prompt);
+                    match loaded {
+                        Ok(plugin) => attach_plugin_handlers(&content_manager_for_prompt, &webview_for_prompt, plugin),
+                        Err(e) => tracing::warn!("Failed to load plugin: {}", e),
+                    }
+                }),
+            );
+        });
+    }
+
+    // Load behavior scripts and feed them hooks from the frontend and the battery monitor
+    #[cfg(feature = "scripting")]
+    {
+        let webview_for_scripts = webview.clone();
+        let script_host = scripting::ScriptHost::start(Rc::new(move |action| {
+            let (event, detail) = match action {
+                scripting::ScriptAction::SetExpression(name) => {
+                    expressions::set(&name, None);
+                    return;
+                }
+                scripting::ScriptAction::PlayAnimation(name) => match animations::play_event(&ipc::AnimationRequest::named(name)) {
+                    Ok(detail) => ("playAnimation", detail),
+                    Err(e) => {
+                        tracing::warn!("Script can't play animation: {}", e);
+                        return;
                     }
+                },
+                scripting::ScriptAction::Say(_) if presence::is_quiet() => {
+                    debug_log!("[SCRIPT] Meeting mode, dropping say()");
+                    return;
                 }
+                scripting::ScriptAction::Say(text) => ("characterSay", serde_json::json!({ "text": text })),
+            };
+            dispatch_event(&webview_for_scripts, event, &detail);
+        }));
+
+        // Frontend reports chat messages and its own idle timer
+        content_manager.register_script_message_handler("scriptHook", None);
+        let script_host_for_hook = script_host.clone();
+        content_manager.connect_script_message_received(Some("scriptHook"), move |_manager, js_value| {
+            let Some(json_str) = js_value.to_json(0) else { return };
+            let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+            let hook = parsed["hook"].as_str().unwrap_or("");
+            if !matches!(hook, "on_idle" | "on_message") {
+                debug_log!("[SCRIPT] Ignoring unknown hook from frontend: {}", hook);
+                return;
+            }
+            let args = parsed["args"]
+                .as_array()
+                .map(|a| a.iter().map(json_to_dynamic).collect())
+                .unwrap_or_default();
+            script_host_for_hook.borrow().fire(hook, args);
+        });
+
+        // Fire on_battery_low once each time the battery drops below the threshold, and
+        // on_idle(seconds) once a minute while the user is away (see behavior.rs)
+        const BATTERY_LOW_PERCENT: i64 = 20;
+        let battery_warned = Rc::new(RefCell::new(false));
+        glib::timeout_add_seconds_local(60, move || {
+            if let Some(seconds) = behavior::away_seconds() {
+                script_host.borrow().fire("on_idle", vec![rhai::Dynamic::from_int(seconds as i64)]);
+            }
+            match scripting::discharging_battery_percent() {
+                Some(percent) if percent <= BATTERY_LOW_PERCENT => {
+                    if !battery_warned.replace(true) {
+                        script_host.borrow().fire("on_battery_low", vec![rhai::Dynamic::from_int(percent)]);
+                    }
+                }
+                _ => *battery_warned.borrow_mut() = false,
             }
             glib::ControlFlow::Continue
         });
     }
 
-    // Spawn IPC socket listener for CLI commands (--toggle, --show, --hide)
-    let ipc_receiver = ipc::spawn_socket_listener();
+    // Speech input: transcripts stream to the frontend as `transcript` events
+    #[cfg(feature = "stt")]
+    {
+        content_manager.register_script_message_handler("startListening", None);
+        content_manager.register_script_message_handler("stopListening", None);
+        let webview_for_listening = webview.clone();
+        content_manager.connect_script_message_received(Some("startListening"), move |_manager, _js_value| {
+            let events = stt::start(config::current().stt.clone());
+            let webview = webview_for_listening.clone();
+            dispatch_event(&webview, "listeningChanged", &serde_json::json!({ "listening": true }));
+            glib::spawn_future_local(async move {
+                while let Ok(event) = events.recv().await {
+                    match event {
+                        stt::SttEvent::Partial(text) => {
+                            dispatch_event(&webview, "transcript", &serde_json::json!({ "text": text, "final": false }));
+                        }
+                        stt::SttEvent::Final(text) => {
+                            dispatch_event(&webview, "transcript", &serde_json::json!({ "text": text, "final": true }));
+                        }
+                        stt::SttEvent::Stopped(error) => {
+                            let detail = serde_json::json!({
+                                "listening": false,
+                                "error": error.map(|e| e.to_string()),
+                            });
+                            dispatch_event(&webview, "listeningChanged", &detail);
+                        }
+                    }
+                }
+            });
+        });
+        content_manager.connect_script_message_received(Some("stopListening"), move |_manager, _js_value| {
+            stt::stop();
+        });
+    }
+
+    // Start companion processes and apply the OverlayCommands they send
+    let overlay_ui = OverlayUi {
+        window: window.clone(),
+        webview: webview.clone(),
+        is_visible: is_visible.clone(),
+        position: position.clone(),
+        tray_handle: tray_handle.clone(),
+        transfers,
+        quadrant,
+        chat,
+        character,
+        started: std::time::Instant::now(),
+    };
+    let overlay_ui_for_profiles = overlay_ui.clone();
+    let profiles_for_switch = profiles.clone();
+    glib::spawn_future_local(async move {
+        while let Ok(profile) = profile_rx.recv().await {
+            apply_profile(&overlay_ui_for_profiles, &profiles_for_switch, profile.as_deref());
+        }
+    });
+
+    // Follow profile schedules; a manual switch holds until the scheduled profile changes
+    let profiles_for_schedule = profiles.clone();
+    let profile_tx_for_schedule = profile_tx.clone();
+    let check_schedule = move || {
+        let Ok(now) = glib::DateTime::now_local() else { return };
+        if let Some(scheduled) = profiles_for_schedule.borrow_mut().schedule_change(&now) {
+            let _ = profile_tx_for_schedule.try_send(scheduled);
+        }
+    };
+    check_schedule();
+    glib::timeout_add_seconds_local(60, move || {
+        check_schedule();
+        glib::ControlFlow::Continue
+    });
+
+    // The frontend forgets the active profile when the page (re)loads
+    let profiles_for_reload = profiles.clone();
+    webview.connect_load_changed(move |webview, load_event| {
+        if load_event != webkit6::LoadEvent::Finished {
+            return;
+        }
+        if let Some(profile) = profiles_for_reload.borrow().active() {
+            dispatch_event(webview, "profileChanged", &profile.to_json());
+        }
+    });
+
+    let (companions, companion_receiver) = companions::spawn_companions();
+    let overlay_ui_for_companions = overlay_ui.clone();
+    glib::spawn_future_local(async move {
+        while let Ok((name, cmd)) = companion_receiver.recv().await {
+            debug_log!("[COMPANION] Applying command from '{}'", name);
+            apply_overlay_command(&overlay_ui_for_companions, cmd);
+        }
+    });
+
+    // Spawn IPC socket listener for CLI commands (--toggle, --show, --hide) and JSON clients
+    let (ipc_sender, ipc_receiver) = async_channel::unbounded();
+    let ipc_clients = ipc::spawn_socket_listener(ipc_sender.clone());
+    // The REST API (on the static server) goes through the same handler
+    api::connect(ipc_sender.clone(), ipc_clients.clone());
+    if config::current().api.enabled
+        && let Err(e) = api::token()
+    {
+        tracing::warn!("Could not create the API token in {}: {}", api::token_path().display(), e);
+    }
+
+    // Global shortcuts from the desktop portal run the same commands as the CLI (and are
+    // gated on the hotkey setting the same way)
+    let shortcut_status = Rc::new(RefCell::new(shortcuts::ShortcutStatus::default()));
+    if let Some(trigger) = options.shortcut.clone() {
+        let shortcut_events = shortcuts::spawn(trigger);
+        let shortcut_status = shortcut_status.clone();
+        glib::spawn_future_local(async move {
+            while let Ok(event) = shortcut_events.recv().await {
+                match event {
+                    shortcuts::ShortcutEvent::Bound(status) => *shortcut_status.borrow_mut() = status,
+                    shortcuts::ShortcutEvent::Activated(cmd) => {
+                        let _ = ipc_sender.send(ipc::IpcMessage::local(ipc::Request::Text(cmd))).await;
+                    }
+                }
+            }
+        });
+    }
+
+    content_manager.register_script_message_handler("getGlobalShortcut", None);
+    let webview_for_shortcut_request = webview.clone();
+    content_manager.connect_script_message_received(Some("getGlobalShortcut"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let Some(callback_id) = parsed["callbackId"].as_str() else { return };
+        let current = serde_json::json!(*shortcut_status.borrow());
+        dispatch_callback(&webview_for_shortcut_request, callback_id, &current);
+    });
+
+    // config.toml: the frontend reads it on load and hears about edits, which also reapply
+    // the layer and keyboard mode
+    content_manager.register_script_message_handler("getOverlayConfig", None);
+    let webview_for_config_request = webview.clone();
+    content_manager.connect_script_message_received(Some("getOverlayConfig"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let Some(callback_id) = parsed["callbackId"].as_str() else { return };
+        let current = serde_json::to_value(&*config::current()).unwrap_or_default();
+        dispatch_callback(&webview_for_config_request, callback_id, &current);
+    });
+
+    // Which renderer the overlay picked at startup and why (see render.rs)
+    content_manager.register_script_message_handler("getRenderInfo", None);
+    let webview_for_render_info = webview.clone();
+    content_manager.connect_script_message_received(Some("getRenderInfo"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let Some(callback_id) = parsed["callbackId"].as_str() else { return };
+        dispatch_callback(&webview_for_render_info, callback_id, &serde_json::json!(render::info()));
+    });
+
+    let window_for_config = window.clone();
+    let webview_for_config = webview.clone();
+    let position_for_config = position.clone();
+    let quadrant_for_config = quadrant.clone();
+    let chat_for_config = chat.clone();
+    let tray_handle_for_config = tray_handle.clone();
+    let walker_for_config = walker.clone();
+    let debug_option = options.debug;
+    let debug_logging = Cell::new(config::current().debug_logging);
+    config::watch(move |config| {
+        if debug_logging.replace(config.debug_logging) != config.debug_logging {
+            logging::set_debug(debug_option || config.debug_logging);
+        }
+        // Dialogs lower the layer and restore it themselves when they end
+        if !config.compositor.hide_on_fullscreen {
+            compositor::set_under_fullscreen(&window_for_config, false);
+        }
+        if window_for_config.layer() != Layer::Bottom {
+            window_for_config.set_layer(config.window.layer.layer());
+        }
+        window_for_config.set_exclusive_zone(config.window.exclusive_zone());
+        focus::apply(&window_for_config);
+        walker_for_config.config_changed();
+        if let Some(ref handle) = tray_handle_for_config {
+            tray::update_tray_walking(handle, walker_for_config.enabled());
+        }
+        // `[window] scale` only applies until a scale has been chosen
+        if position_for_config.borrow().scale.is_none() {
+            let scale = config.window.scale.clamp(MIN_SCALE, MAX_SCALE);
+            dispatch_event(&webview_for_config, "setScale", &serde_json::json!({ "scale": scale }));
+            if let Some(ref handle) = tray_handle_for_config {
+                tray::update_tray_scale(handle, scale);
+            }
+        }
+        // The dock mode or window sizes may have changed
+        send_layout(
+            &webview_for_config,
+            &position_for_config.borrow(),
+            &quadrant_for_config.borrow(),
+            &chat_for_config.borrow(),
+        );
+        dispatch_event(&webview_for_config, "configChanged", &serde_json::to_value(config).unwrap_or_default());
+    });
+
+    // Frontend reports interactions (clicks, finished animations) as OverlayEvents
+    content_manager.register_script_message_handler("overlayEvent", None);
+    let companions_for_events = companions.clone();
+    let ipc_clients_for_events = ipc_clients.clone();
+    content_manager.connect_script_message_received(Some("overlayEvent"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        match serde_json::from_str::<ipc::OverlayEvent>(json_str.as_str()) {
+            Ok(event) => {
+                companions_for_events.broadcast(&event);
+                ipc_clients_for_events.broadcast(&event);
+            }
+            Err(e) => debug_log!("[EVENT] Invalid overlay event from frontend: {}", e),
+        }
+    });
+
+    // Every show/hide path (tray, IPC, shortcuts, the frontend's hide animation) ends up
+    // mapping or unmapping the window
+    let companions_for_visibility = companions.clone();
+    let ipc_clients_for_visibility = ipc_clients.clone();
+    window.connect_visible_notify(move |window| {
+        let event = ipc::OverlayEvent::VisibilityChanged(window.is_visible());
+        companions_for_visibility.broadcast(&event);
+        ipc_clients_for_visibility.broadcast(&event);
+        state::changed();
+    });
+
+    let companions_for_ready = companions.clone();
+    let ipc_clients_for_ready = ipc_clients.clone();
+    webview.connect_load_changed(move |_webview, load_event| {
+        if load_event == webkit6::LoadEvent::Finished {
+            companions_for_ready.broadcast(&ipc::OverlayEvent::Ready);
+            ipc_clients_for_ready.broadcast(&ipc::OverlayEvent::Ready);
+        }
+    });
+
+    // Save position, quadrant, scale, monitor and visibility as they change
+    let overlay_ui_for_state = overlay_ui.clone();
+    let save_state = state::watch(saved_state, move |saved| overlay_state(&overlay_ui_for_state, saved));
+
+    // One shutdown path for tray Quit, window close and SIGTERM/SIGINT: let the frontend
+    // persist its stores, save our own state, then stop the tray and background services
+    let webview_for_shutdown = webview.clone();
+    let tray_handle_for_shutdown = tray_handle.clone();
+    app.connect_shutdown(move |_| {
+        info!("Shutting down");
+        // Signals aren't handled while the main loop is stopped, so if anything below hangs,
+        // exit anyway rather than leave a frozen process holding the socket
+        std::thread::spawn(|| {
+            std::thread::sleep(SHUTDOWN_DEADLINE);
+            tracing::warn!("Shutdown did not finish within {:?}, exiting", SHUTDOWN_DEADLINE);
+            ipc::remove_socket();
+            std::process::exit(0);
+        });
+        notify_frontend_shutdown(&webview_for_shutdown);
+        save_state();
+
+        if let Some(ref handle) = tray_handle_for_shutdown {
+            handle.shutdown();
+        }
+        runtime::shutdown();
+        ipc::remove_socket();
+    });
+
+    // Meeting mode: tell the frontend when to hold back sound and proactive messages
+    if options.meeting_mode {
+        let presence_receiver = presence::spawn_monitor();
+        let webview_for_presence = webview.clone();
+        glib::spawn_future_local(async move {
+            while let Ok(presence) = presence_receiver.recv().await {
+                if presence.quiet {
+                    stop_speaking(&webview_for_presence);
+                }
+                dispatch_event(&webview_for_presence, "presenceChanged", &serde_json::json!(presence));
+            }
+        });
+    }
+
+    // Focused app, workspace and fullscreen windows, on Hyprland and Sway. The overlay
+    // steps down below fullscreen windows on its monitor.
+    if let Some(desktop_receiver) = compositor::spawn_monitor() {
+        let window_for_desktop = window.clone();
+        let webview_for_desktop = webview.clone();
+        glib::spawn_future_local(async move {
+            while let Ok(desktop) = desktop_receiver.recv().await {
+                compositor::follow_fullscreen(&window_for_desktop, &desktop);
+                let shared = desktop.shared(&config::current().compositor);
+                dispatch_event(&webview_for_desktop, "desktopChanged", &serde_json::json!(shared));
+            }
+        });
+    }
+
+    // Idle animations, stretch reminders and remarks; held off while the user is busy with
+    // the overlay or it's hidden
+    let window_for_behavior = window.clone();
+    let webview_for_behavior = webview.clone();
+    behavior::start(
+        move || window_for_behavior.is_active() || !window_for_behavior.is_visible(),
+        move |action| match action {
+            behavior::Behavior::Animation(name) => match animations::play_event(&ipc::AnimationRequest::named(name)) {
+                Ok(detail) => dispatch_event(&webview_for_behavior, "playAnimation", &detail),
+                Err(e) => tracing::warn!("Can't play idle animation: {}", e),
+            },
+            behavior::Behavior::StretchReminder { minutes } => {
+                dispatch_event(&webview_for_behavior, "stretchReminder", &serde_json::json!({ "minutes": minutes }));
+            }
+            behavior::Behavior::Remark => dispatch_event(&webview_for_behavior, "idleRemark", &serde_json::json!({})),
+        },
+    );
 
-    // Poll for IPC messages every 50ms
+    // Reminders set in earlier sessions fire as soon as the page can announce them
+    let webview_for_reminders = webview.clone();
+    reminders::start(move |reminder, late| {
+        info!("Reminder {} is due: {}", reminder.id, reminder.text);
+        notifications::show(&tr!("reminder-title"), &reminder.text);
+        if let Err(e) = sound::play("reminder", 1.0) {
+            tracing::warn!("Reminder chime failed: {}", e);
+        }
+        dispatch_event(
+            &webview_for_reminders,
+            "reminder",
+            &serde_json::json!({ "id": reminder.id, "text": reminder.text, "late": late }),
+        );
+    });
+
+    // Handle IPC messages on the main loop as they arrive
     let window_for_ipc = window.clone();
     let webview_for_ipc = webview.clone();
     let is_visible_for_ipc = is_visible.clone();
     let tray_handle_for_ipc = tray_handle.clone();
     let hotkey_enabled_for_ipc = hotkey_enabled.clone();
-
-    glib::timeout_add_local(Duration::from_millis(50), move || {
-        while let Ok(cmd) = ipc_receiver.try_recv() {
-            debug_log!("[IPC] Received command from socket: '{}'", cmd);
-
-            // Check if hotkey is enabled before processing commands
-            let hotkey_state = *hotkey_enabled_for_ipc.borrow();
-            debug_log!("[IPC] Hotkey enabled state: {}", hotkey_state);
-            if !hotkey_state {
-                debug_log!("[IPC] Hotkey disabled, ignoring command: {}", cmd);
-                continue;
-            }
-
-            match cmd.as_str() {
-                "toggle" => {
-                    let visible = *is_visible_for_ipc.borrow();
-                    debug_log!("[IPC] Toggle command - current visibility: {}", visible);
-                    if visible {
-                        debug_log!("[IPC] Dispatching hotkeyHide event to frontend");
-                        // Dispatch hotkeyHide to frontend - triggers animation, then frontend tells us to hide
-                        webview_for_ipc.evaluate_javascript(
-                            "window.dispatchEvent(new CustomEvent('hotkeyHide'))",
-                            None,
-                            None,
-                            None::<&gio::Cancellable>,
-                            |_| {},
-                        );
-                        // Note: is_visible will be set to false when frontend sends windowControl hide
-                    } else {
-                        debug_log!("[IPC] Showing window and dispatching hotkeyShow event");
-                        window_for_ipc.present();
-                        *is_visible_for_ipc.borrow_mut() = true;
-                        // Set Exclusive to grab keyboard from compositor (user didn't click, so
-                        // Wayland won't grant focus otherwise). The is_active_notify handler
-                        // will switch back to OnDemand when user clicks elsewhere.
-                        window_for_ipc.set_keyboard_mode(KeyboardMode::Exclusive);
-                        webview_for_ipc.grab_focus();
-
-                        // Dispatch hotkeyShow after short delay to let Exclusive mode take effect
-                        let webview_for_hotkey = webview_for_ipc.clone();
-                        glib::timeout_add_local_once(Duration::from_millis(50), move || {
-                            webview_for_hotkey.evaluate_javascript(
-                                "window.dispatchEvent(new CustomEvent('hotkeyShow'))",
-                                None,
-                                None,
-                                None::<&gio::Cancellable>,
-                                |_| {},
-                            );
+    let plugin_host_for_ipc = plugin_host.clone();
+    let profile_tx_for_ipc = profile_tx.clone();
+    let overlay_ui_for_ipc = overlay_ui.clone();
+    let strict_hotkeys_flag = options.strict_hotkeys;
+
+    glib::spawn_future_local(async move {
+        while let Ok(message) = ipc_receiver.recv().await {
+            // config.toml's strict_hotkeys takes effect without a restart; the flag forces it on
+            let strict_hotkeys = strict_hotkeys_flag || config::current().strict_hotkeys;
+            let result = match &message.request {
+                ipc::Request::Text(cmd) => 'handled: {
+                    debug_log!("[IPC] Received command from socket: '{}'", cmd);
+
+                    // Commands registered by plugins aren't hotkeys, so route them before gating
+                    if let Some(plugin) = plugin_host_for_ipc.borrow().ipc_route(cmd) {
+                        let cmd = cmd.trim();
+                        let (verb, args) = cmd.split_once(char::is_whitespace).unwrap_or((cmd, ""));
+                        let (verb, args) = (verb.to_string(), args.trim().to_string());
+                        runtime::spawn_blocking(move || {
+                            let payload = serde_json::json!({ "args": args });
+                            if let Err(e) = plugin.call("ipc", &verb, &payload) {
+                                tracing::warn!("Plugin IPC command '{}' failed: {}", verb, e);
+                            }
                         });
+                        break 'handled Ok(serde_json::Value::Null);
+                    }
+
+                    // Log verbosity isn't a hotkey either
+                    if let Some(filter) = cmd.strip_prefix("log-level ") {
+                        break 'handled logging::set_filter(filter.trim()).map(|_| serde_json::Value::Null);
+                    }
+
+                    // The hotkey setting only gates toggle (what the keybinding sends); explicit
+                    // show/hide always work unless strict mode gates everything
+                    let hotkey_state = *hotkey_enabled_for_ipc.borrow();
+                    debug_log!("[IPC] Hotkey enabled state: {}", hotkey_state);
+                    let gated = strict_hotkeys || cmd == "toggle";
+                    if gated && !hotkey_state {
+                        debug_log!("[IPC] Hotkey disabled, ignoring command: {}", cmd);
+                        break 'handled Err("hotkey disabled in settings".to_string());
+                    }
+
+                    if let Some(name) = cmd.strip_prefix("profile ") {
+                        let name = name.trim();
+                        let profile = (name != "default").then(|| name.to_string());
+                        let _ = profile_tx_for_ipc.send(profile).await;
+                        break 'handled Ok(serde_json::Value::Null);
+                    }
 
-                        if let Some(ref h) = tray_handle_for_ipc {
-                            update_tray_visibility(h, true);
+                    match cmd.as_str() {
+                        "toggle" => {
+                            let visible = *is_visible_for_ipc.borrow();
+                            debug_log!("[IPC] Toggle command - current visibility: {}", visible);
+                            if visible {
+                                debug_log!("[IPC] Dispatching hotkeyHide event to frontend");
+                                // Dispatch hotkeyHide to frontend - triggers animation, then frontend tells us to hide
+                                dispatch_event(&webview_for_ipc, "hotkeyHide", &serde_json::Value::Null);
+                                // Note: is_visible will be set to false when frontend sends windowControl hide
+                            } else {
+                                debug_log!("[IPC] Showing window and dispatching hotkeyShow event");
+                                window_for_ipc.present();
+                                *is_visible_for_ipc.borrow_mut() = true;
+                                // Grab the keyboard (user didn't click, so Wayland won't grant
+                                // focus otherwise); focus.rs lets go once the window has it
+                                focus::grab(&window_for_ipc);
+                                webview_for_ipc.grab_focus();
+
+                                // Dispatch hotkeyShow after short delay to let Exclusive mode take effect
+                                let webview_for_hotkey = webview_for_ipc.clone();
+                                glib::timeout_add_local_once(Duration::from_millis(50), move || {
+                                    dispatch_event(&webview_for_hotkey, "hotkeyShow", &serde_json::Value::Null);
+                                });
+
+                                if let Some(ref h) = tray_handle_for_ipc {
+                                    update_tray_visibility(h, true);
+                                }
+                            }
+                        }
+                        "show" => {
+                            if !*is_visible_for_ipc.borrow() {
+                                window_for_ipc.present();
+                                *is_visible_for_ipc.borrow_mut() = true;
+                                dispatch_event(&webview_for_ipc, "hotkeyShow", &serde_json::Value::Null);
+                                if let Some(ref h) = tray_handle_for_ipc {
+                                    update_tray_visibility(h, true);
+                                }
+                            }
+                        }
+                        "ask-screenshot" => ask_about_screenshot(overlay_ui_for_ipc.clone()),
+                        "hide" => {
+                            if *is_visible_for_ipc.borrow() {
+                                // Dispatch hotkeyHide to frontend - triggers animation
+                                dispatch_event(&webview_for_ipc, "hotkeyHide", &serde_json::Value::Null);
+                            }
                         }
+                        _ => break 'handled Err(format!("unknown command '{}'", cmd)),
                     }
+                    Ok(serde_json::Value::Null)
                 }
-                "show" => {
-                    if !*is_visible_for_ipc.borrow() {
-                        window_for_ipc.present();
-                        *is_visible_for_ipc.borrow_mut() = true;
-                        webview_for_ipc.evaluate_javascript(
-                            "window.dispatchEvent(new CustomEvent('hotkeyShow'))",
-                            None,
-                            None,
-                            None::<&gio::Cancellable>,
-                            |_| {},
-                        );
-                        if let Some(ref h) = tray_handle_for_ipc {
-                            update_tray_visibility(h, true);
+                // JSON clients drive the character programmatically, like companions;
+                // only strict mode ties them to the hotkey setting
+                ipc::Request::Command(command) => {
+                    if strict_hotkeys && !*hotkey_enabled_for_ipc.borrow() {
+                        debug_log!("[IPC] Hotkey disabled, ignoring command: {:?}", command);
+                        Err("hotkey disabled in settings".to_string())
+                    } else {
+                        match command {
+                            // Tell the client, rather than only logging it
+                            ipc::OverlayCommand::PlayAnimation(request) if animations::find(&request.name).is_none() => {
+                                Err(format!("unknown animation '{}'", request.name))
+                            }
+                            _ => {
+                                apply_overlay_command(&overlay_ui_for_ipc, command.clone());
+                                Ok(serde_json::Value::Null)
+                            }
+                        }
+                    }
+                }
+                ipc::Request::GetState => Ok(overlay_status(&overlay_ui_for_ipc)),
+                ipc::Request::ListAnimations => Ok(serde_json::json!(animations::list())),
+                ipc::Request::ListModels => match gio::spawn_blocking(models::list).await {
+                    Ok(result) => result.map(|m| serde_json::json!(m)).map_err(|e| e.to_string()),
+                    Err(_) => Err("model listing failed".to_string()),
+                },
+                ipc::Request::ImportModel(path) => {
+                    let path = path.clone();
+                    match gio::spawn_blocking(move || models::import(&path)).await {
+                        Ok(Ok(model)) => {
+                            dispatch_event(&webview_for_ipc, "modelImported", &serde_json::json!(model));
+                            Ok(serde_json::json!(model))
                         }
+                        Ok(Err(e)) => Err(e.to_string()),
+                        Err(_) => Err("model import failed".to_string()),
                     }
                 }
-                "hide" => {
-                    if *is_visible_for_ipc.borrow() {
-                        // Dispatch hotkeyHide to frontend - triggers animation
-                        webview_for_ipc.evaluate_javascript(
-                            "window.dispatchEvent(new CustomEvent('hotkeyHide'))",
-                            None,
-                            None,
-                            None::<&gio::Cancellable>,
-                            |_| {},
-                        );
+                ipc::Request::SetActiveModel(id) => {
+                    let id = id.clone();
+                    match gio::spawn_blocking(move || models::get(&id)).await {
+                        Ok(Ok(model)) => {
+                            dispatch_event(&webview_for_ipc, "setActiveModel", &serde_json::json!(model));
+                            Ok(serde_json::json!(model))
+                        }
+                        Ok(Err(e)) => Err(e.to_string()),
+                        Err(_) => Err("model lookup failed".to_string()),
                     }
                 }
-                _ => {}
-            }
+                ipc::Request::AddReminder { text, after_secs } => {
+                    reminders::add(text, Duration::from_secs(*after_secs)).map(|r| serde_json::json!(r))
+                }
+                ipc::Request::ListReminders => Ok(serde_json::json!(reminders::list())),
+                ipc::Request::Say(_) if presence::is_quiet() => Err("meeting mode is on".to_string()),
+                ipc::Request::Say(text) => {
+                    dispatch_event(&webview_for_ipc, "characterSay", &serde_json::json!({ "text": text }));
+                    Ok(serde_json::Value::Null)
+                }
+                ipc::Request::Ping => Ok(ping(&overlay_ui_for_ipc).await),
+                ipc::Request::RestartWebview => {
+                    restart_webview(&overlay_ui_for_ipc);
+                    Ok(serde_json::Value::Null)
+                }
+            };
+            message.respond(result);
         }
-        glib::ControlFlow::Continue
     });
 
-    // Load the webview URL (dev server or static file server)
-    webview.load_uri(webview_url);
-    info!("Loading WebView from: {}", webview_url);
+    // Load the webview URL (dev server or static file server) behind the content filter
+    content_policy::load(&webview, webview_url);
+    info!("Loading WebView from: {}", server::redact(webview_url));
 
-    // When window loses focus (user clicks away), switch to OnDemand mode
-    // so other apps can receive keyboard input.
-    // Also notify frontend of focus state changes for notification logic.
+    // Notify frontend of focus state changes for notification logic (focus.rs handles the
+    // keyboard mode)
     let webview_for_focus_notify = webview.clone();
     window.connect_is_active_notify(move |w| {
         let is_active = w.is_active();
-        if !is_active {
-            w.set_keyboard_mode(KeyboardMode::OnDemand);
-        }
         // Update global variable AND dispatch event for frontend
         // Using global variable ensures the value is always readable even if event is missed
-        let js = format!(
-            "window.__desktopWaifuWindowFocused = {}; window.dispatchEvent(new CustomEvent('windowFocusChange', {{ detail: {{ isFocused: {} }} }}))",
-            is_active, is_active
+        bridge::call(
+            &webview_for_focus_notify,
+            "window.__desktopWaifuWindowFocused = focused; window.dispatchEvent(new CustomEvent('windowFocusChange', { detail: { isFocused: focused } }))",
+            &[("focused", is_active.to_variant())],
+            || {},
         );
-        webview_for_focus_notify.evaluate_javascript(&js, None, None, None::<&gio::Cancellable>, |_| {});
         debug_log!("[FOCUS] Window active state changed: is_active={}", is_active);
     });
 
-    // Show the window
-    window.present();
+    // Show the window, unless it was hidden when the overlay last quit (the tray or
+    // --show brings it back)
+    if *is_visible.borrow() {
+        window.present();
+        info!("Overlay window created and presented");
+    } else {
+        info!("Overlay window created, starting hidden");
+    }
+}
+
+/// Handles to the UI state shared by external command sources (companions, IPC)
+#[derive(Clone)]
+struct OverlayUi {
+    window: ApplicationWindow,
+    webview: WebView,
+    is_visible: Rc<RefCell<bool>>,
+    position: Rc<RefCell<CharacterPosition>>,
+    tray_handle: Option<ksni::Handle<tray::DesktopWaifuTray>>,
+    transfers: transfer::TransferStore,
+    quadrant: Rc<RefCell<Quadrant>>,
+    chat: Rc<RefCell<layout::ChatState>>,
+    /// Character the frontend shows, as it last reported
+    character: Rc<RefCell<Option<String>>>,
+    started: std::time::Instant,
+}
+
+/// Overlay state to persist. The monitor is only known while the window is mapped, so a
+/// hidden overlay keeps the `saved` one.
+fn overlay_state(ui: &OverlayUi, saved: &state::OverlayState) -> state::OverlayState {
+    let position = ui.position.borrow();
+    let quadrant = ui.quadrant.borrow();
+    let monitor = ui
+        .window
+        .is_mapped()
+        .then(|| monitors::current_monitor(&ui.window))
+        .flatten()
+        .and_then(|monitor| monitor.connector())
+        .map(|name| name.to_string());
+    state::OverlayState {
+        x: Some(position.x),
+        y: Some(position.y),
+        screen: Some(position.screen),
+        is_right_half: Some(quadrant.is_right_half),
+        is_bottom_half: Some(quadrant.is_bottom_half),
+        scale: position.scale,
+        chat_dock: ui.chat.borrow().dock,
+        monitor: monitor.or_else(|| saved.monitor.clone()),
+        visible: *ui.is_visible.borrow(),
+        notifications_muted: notifications::is_muted(),
+    }
+}
+
+/// Overlay state for IPC state queries and --status
+fn overlay_status(ui: &OverlayUi) -> serde_json::Value {
+    let position = ui.position.borrow();
+    let quadrant = ui.quadrant.borrow();
+    serde_json::json!({
+        "visible": *ui.is_visible.borrow(),
+        "window": { "width": ui.window.width(), "height": ui.window.height() },
+        "position": { "x": position.x, "y": position.y },
+        "scale": position.scale(),
+        "chatDock": ui.chat.borrow().dock(),
+        "quadrant": format!(
+            "{}-{}",
+            if quadrant.is_bottom_half { "bottom" } else { "top" },
+            if quadrant.is_right_half { "right" } else { "left" },
+        ),
+        "model": *ui.character.borrow(),
+        "uptimeSecs": ui.started.elapsed().as_secs(),
+        // How long the user has been away from the computer, if they are
+        "awaySecs": behavior::away_seconds(),
+        "render": render::info(),
+        // How it was started, so --self-update can start the new binary the same way
+        "args": std::env::args().skip(1).collect::<Vec<_>>(),
+        "cwd": std::env::current_dir().ok().and_then(|dir| dir.to_str().map(str::to_string)),
+    })
+}
+
+/// Health check for scripts and the supervisor. Any answer shows the main loop is running;
+/// `webviewAlive` says whether the page answered a script call in time as well.
+async fn ping(ui: &OverlayUi) -> serde_json::Value {
+    const PAGE_TIMEOUT: Duration = Duration::from_secs(2);
+
+    let answer = glib::future_with_timeout(PAGE_TIMEOUT, ui.webview.evaluate_javascript_future("true", None, None)).await;
+    serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "pid": std::process::id(),
+        "uptimeSecs": ui.started.elapsed().as_secs(),
+        "webviewAlive": matches!(answer, Ok(Ok(_))),
+        "webviewResponsive": ui.webview.is_web_process_responsive(),
+    })
+}
+
+/// Start the frontend over in a new web process; the window and its layer surface stay. A
+/// wedged page can't reload itself, so its process is ended first. Until the new page sets
+/// the input region the overlay is click-through.
+fn restart_webview(ui: &OverlayUi) {
+    info!("Restarting the web view");
+    input_region::apply(&ui.window, &Region::create());
+    ui.webview.terminate_web_process();
+    ui.webview.reload();
+}
+
+/// Switch to profile `name` (None = no profile): tell the frontend which settings to apply,
+/// update the tray and show or hide the overlay if the profile says so
+fn apply_profile(ui: &OverlayUi, profiles: &Rc<RefCell<profiles::ProfileManager>>, name: Option<&str>) {
+    let mut manager = profiles.borrow_mut();
+    if manager.active().map(|p| p.name.as_str()) == name {
+        return;
+    }
+    if !manager.activate(name) {
+        tracing::warn!("Unknown profile '{}'", name.unwrap_or_default());
+        return;
+    }
+    info!("Switched to profile {:?}", name);
+
+    let (detail, visible) = match manager.active() {
+        Some(profile) => (profile.to_json(), profile.visible),
+        None => (serde_json::json!({ "name": null }), None),
+    };
+    let names = manager.names();
+    drop(manager);
+
+    dispatch_event(&ui.webview, "profileChanged", &detail);
+    if let Some(ref handle) = ui.tray_handle {
+        update_tray_profiles(handle, names, name.map(str::to_string));
+    }
+    match visible {
+        Some(true) => apply_overlay_command(ui, ipc::OverlayCommand::Show),
+        Some(false) => apply_overlay_command(ui, ipc::OverlayCommand::Hide),
+        None => {}
+    }
+}
+
+/// Screenshot-and-ask: let the user capture a region, OCR it if possible, then show the
+/// overlay with the chat open, the image attached and the input focused
+fn ask_about_screenshot(ui: OverlayUi) {
+    glib::spawn_future_local(async move {
+        // Keep the overlay out of the way of the compositor's selection UI
+        ui.window.set_layer(Layer::Bottom);
+        let capture = screenshot::capture(true).await;
+        ui.window.set_layer(compositor::resting_layer());
+
+        let path = match capture {
+            Ok(path) => path,
+            Err(screenshot::ScreenshotError::Cancelled) => {
+                debug_log!("[SCREENSHOT] Capture cancelled");
+                return;
+            }
+            Err(e) => {
+                tracing::warn!("Screenshot failed: {}", e);
+                return;
+            }
+        };
+        info!("Screenshot captured: {:?}", path);
+
+        let ocr_text = screenshot::ocr(&path).await;
+        let mime_type = image_mime_type(&path);
+        let url = ui.transfers.register_file(path, mime_type);
+
+        apply_overlay_command(&ui, ipc::OverlayCommand::Show);
+        // The user didn't click the overlay, so take keyboard focus explicitly
+        focus::grab(&ui.window);
+        ui.webview.grab_focus();
+        dispatch_event(
+            &ui.webview,
+            "screenshotAsk",
+            &serde_json::json!({ "url": url, "mimeType": mime_type, "ocrText": ocr_text }),
+        );
+    });
+}
+
+/// Resize the character, from the tray or IPC. The frontend lays it out again at the new
+/// size by reloading, so until the new page reports its layout only the character's new
+/// box takes clicks.
+fn set_character_scale(
+    window: &ApplicationWindow,
+    webview: &WebView,
+    position: &Rc<RefCell<CharacterPosition>>,
+    tray_handle: Option<&ksni::Handle<tray::DesktopWaifuTray>>,
+    scale: f32,
+) {
+    let scale = scale.clamp(MIN_SCALE, MAX_SCALE);
+    let (x, y, width, height) = {
+        let mut pos = position.borrow_mut();
+        if (pos.scale() - scale).abs() < 0.01 {
+            return;
+        }
+        pos.scale = Some(scale);
+        state::changed();
+        // A bigger character may now reach past the screen's edge
+        pos.clamp();
+        let (width, height) = pos.size();
+        (pos.x, pos.y, width, height)
+    };
+    debug_log!("[SCALE] Character scale {} ({}x{} at {},{})", scale, width, height, x, y);
+    input_region::apply(window, &Region::create_rectangle(&RectangleInt::new(x, y, width, height)));
+    if let Some(handle) = tray_handle {
+        tray::update_tray_scale(handle, scale);
+    }
+    dispatch_event(webview, "setScale", &serde_json::json!({ "scale": scale }));
+}
+
+/// Apply an OverlayCommand from an external source on the GTK main thread
+fn apply_overlay_command(ui: &OverlayUi, cmd: ipc::OverlayCommand) {
+    use ipc::OverlayCommand;
+
+    match cmd {
+        OverlayCommand::Show => {
+            ui.window.present();
+            *ui.is_visible.borrow_mut() = true;
+            dispatch_event(&ui.webview, "trayShow", &serde_json::Value::Null);
+            if let Some(ref h) = ui.tray_handle {
+                update_tray_visibility(h, true);
+            }
+        }
+        OverlayCommand::Hide => {
+            // Frontend plays the hide animation, then sends windowControl hide
+            if *ui.is_visible.borrow() {
+                dispatch_event(&ui.webview, "hotkeyHide", &serde_json::Value::Null);
+            }
+        }
+        OverlayCommand::SetPosition { x, y } => {
+            {
+                let mut pos = ui.position.borrow_mut();
+                pos.x = x;
+                pos.y = y;
+                pos.clamp();
+            }
+            send_layout(&ui.webview, &ui.position.borrow(), &ui.quadrant.borrow(), &ui.chat.borrow());
+            state::changed();
+        }
+        OverlayCommand::SetExpression(request) => expressions::set(&request.expression, request.duration),
+        OverlayCommand::ExpressionCue(cue) => expressions::cue(cue),
+        OverlayCommand::PlayAnimation(request) => match animations::play_event(&request) {
+            Ok(detail) => dispatch_event(&ui.webview, "playAnimation", &detail),
+            Err(e) => tracing::warn!("Can't play animation: {}", e),
+        },
+        OverlayCommand::SetChatDock(dock) => {
+            ui.chat.borrow_mut().dock = Some(dock);
+            send_layout(&ui.webview, &ui.position.borrow(), &ui.quadrant.borrow(), &ui.chat.borrow());
+            state::changed();
+        }
+        OverlayCommand::SetScale(scale) => {
+            set_character_scale(&ui.window, &ui.webview, &ui.position, ui.tray_handle.as_ref(), scale);
+        }
+        OverlayCommand::LoadModel(path) => {
+            // Import it first, unless it's already in the models folder
+            let webview = ui.webview.clone();
+            glib::spawn_future_local(async move {
+                match gio::spawn_blocking(move || models::import(&path)).await {
+                    Ok(Ok(model)) => {
+                        dispatch_event(&webview, "modelImported", &serde_json::json!(model));
+                        dispatch_event(&webview, "setActiveModel", &serde_json::json!(model));
+                    }
+                    Ok(Err(e)) => tracing::warn!("Couldn't load model: {}", e),
+                    Err(_) => tracing::warn!("Model import task failed"),
+                }
+            });
+        }
+        OverlayCommand::SetTalking(talking) => {
+            dispatch_event(&ui.webview, "setTalking", &serde_json::json!({ "talking": talking }));
+        }
+        OverlayCommand::SetAnimationState(state) => {
+            expressions::set_thinking(state == ipc::AnimationState::Thinking);
+            dispatch_event(&ui.webview, "setAnimationState", &serde_json::json!({ "state": state }));
+        }
+        OverlayCommand::Shutdown => {
+            info!("Shutdown requested via OverlayCommand");
+            ui.window.close();
+        }
+    }
+}
+
+/// Tell the frontend we're quitting and give it a moment to persist its stores
+fn notify_frontend_shutdown(webview: &WebView) {
+    const FRONTEND_SAVE_TIMEOUT: Duration = Duration::from_millis(500);
+
+    let done = Rc::new(RefCell::new(false));
+    let done_for_callback = done.clone();
+    webview.evaluate_javascript(
+        "window.dispatchEvent(new CustomEvent('appShutdown'))",
+        None,
+        None,
+        None::<&gio::Cancellable>,
+        move |_| *done_for_callback.borrow_mut() = true,
+    );
+
+    // The main loop has already stopped, so iterate the context by hand until the
+    // frontend has handled the event (or we give up)
+    let context = glib::MainContext::default();
+    let deadline = std::time::Instant::now() + FRONTEND_SAVE_TIMEOUT;
+    while !*done.borrow() && std::time::Instant::now() < deadline {
+        context.iteration(false);
+        std::thread::sleep(Duration::from_millis(5));
+    }
+}
+
+/// Run `command` on a PTY: stream its output as `commandOutput` events, accept `commandInput`
+/// for it while it runs, and answer `callback_id` with the whole transcript when it exits
+fn run_in_pty(
+    webview: &WebView,
+    sessions: &Rc<RefCell<HashMap<String, pty::PtyInput>>>,
+    transfers: &transfer::TransferStore,
+    command: std::process::Command,
+    cmd: String,
+    callback_id: String,
+) {
+    let (input, events) = match pty::spawn(command) {
+        Ok(spawned) => spawned,
+        Err(e) => {
+            audit::record(audit::Kind::Command, &cmd, audit::Outcome::Failed, Some(e.to_string()));
+            let result = serde_json::json!({ "stdout": "", "stderr": e.to_string(), "exit_code": -1 });
+            dispatch_callback(webview, &callback_id, &result);
+            return;
+        }
+    };
+    sessions.borrow_mut().insert(callback_id.clone(), input);
+
+    let webview = webview.clone();
+    let sessions = sessions.clone();
+    let transfers = transfers.clone();
+    glib::spawn_future_local(async move {
+        let mut transcript = Vec::new();
+        let mut pending = Vec::new();
+        let mut exit_code = -1;
+        while let Ok(event) = events.recv().await {
+            match event {
+                pty::PtyEvent::Output(bytes) => {
+                    transcript.extend_from_slice(&bytes);
+                    // Hold back a multi-byte character split across reads
+                    pending.extend_from_slice(&bytes);
+                    let complete = match std::str::from_utf8(&pending) {
+                        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                        _ => pending.len(),
+                    };
+                    let chunk: Vec<u8> = pending.drain(..complete).collect();
+                    let detail = serde_json::json!({ "id": callback_id, "data": String::from_utf8_lossy(&chunk) });
+                    dispatch_event(&webview, "commandOutput", &detail);
+                }
+                pty::PtyEvent::Exit(code) => {
+                    exit_code = code;
+                    break;
+                }
+            }
+        }
+        sessions.borrow_mut().remove(&callback_id);
+        info!("PTY command completed with exit code: {}", exit_code);
+        audit::exit(audit::Kind::Command, &cmd, exit_code);
+
+        // A terminal merges stdout and stderr, so everything is reported as stdout
+        let mut result = serde_json::json!({ "stderr": "", "exit_code": exit_code });
+        if transcript.len() > INLINE_OUTPUT_LIMIT {
+            result["stdoutUrl"] = serde_json::json!(transfers.register_bytes(transcript, "text/plain; charset=utf-8"));
+        } else {
+            result["stdout"] = serde_json::json!(String::from_utf8_lossy(&transcript));
+        }
+        dispatch_callback(&webview, &callback_id, &result);
+    });
+}
+
+/// A `{x, y, width, height}` area from setInputRegion. The frontend measures in CSS
+/// pixels, which are surface coordinates; fractional ones are rounded outward so the edges
+/// still take clicks.
+fn input_rect(value: &serde_json::Value) -> Option<RectangleInt> {
+    let left = value["x"].as_f64()?;
+    let top = value["y"].as_f64()?;
+    let right = left + value["width"].as_f64()?;
+    let bottom = top + value["height"].as_f64()?;
+    let x = left.floor() as i32;
+    let y = top.floor() as i32;
+    Some(RectangleInt::new(x, y, right.ceil() as i32 - x, bottom.ceil() as i32 - y))
+}
+
+/// Run blocking work on a worker thread and resolve the frontend callback with its result
+fn spawn_with_callback<F>(webview: &WebView, callback_id: String, work: F)
+where
+    F: FnOnce() -> serde_json::Value + Send + 'static,
+{
+    let task = gio::spawn_blocking(work);
+    let webview = webview.clone();
+    glib::spawn_future_local(async move {
+        if let Ok(value) = task.await {
+            dispatch_callback(&webview, &callback_id, &value);
+        }
+    });
+}
+
+/// Command output longer than this is streamed through a transfer URL
+const INLINE_OUTPUT_LIMIT: usize = 64 * 1024;
+
+/// A command's result for the frontend, with large output behind transfer URLs
+fn command_result(
+    transfers: &transfer::TransferStore,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    exit_code: i32,
+) -> serde_json::Value {
+    let mut result = serde_json::json!({ "exit_code": exit_code });
+    for (key, bytes) in [("stdout", stdout), ("stderr", stderr)] {
+        if bytes.len() > INLINE_OUTPUT_LIMIT {
+            let url = transfers.register_bytes(bytes, "text/plain; charset=utf-8");
+            result[format!("{}Url", key)] = serde_json::json!(url);
+        } else {
+            result[key] = serde_json::json!(String::from_utf8_lossy(&bytes));
+        }
+    }
+    result
+}
+
+/// Guess an image MIME type from the file extension
+fn image_mime_type(path: &std::path::Path) -> &'static str {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| match ext.to_lowercase().as_str() {
+            "png" => "image/png",
+            "jpg" | "jpeg" => "image/jpeg",
+            "gif" => "image/gif",
+            "webp" => "image/webp",
+            _ => "image/png",
+        })
+        .unwrap_or("image/png")
+}
+
+/// Register a plugin's script message handlers ("<plugin>.<handler>") with WebKit
+fn attach_plugin_handlers(
+    content_manager: &UserContentManager,
+    webview: &WebView,
+    plugin: std::sync::Arc<plugins::LoadedPlugin>,
+) {
+    for (handler, full_name) in plugin.manifest.handlers.iter().zip(plugin.manifest.handler_names()) {
+        content_manager.register_script_message_handler(&full_name, None);
+
+        let webview_for_handler = webview.clone();
+        let plugin_for_handler = plugin.clone();
+        let handler = handler.clone();
+        content_manager.connect_script_message_received(Some(&full_name), move |_manager, js_value| {
+            let Some(json_str) = js_value.to_json(0) else { return };
+            let payload = serde_json::from_str::<serde_json::Value>(json_str.as_str())
+                .unwrap_or(serde_json::Value::Null);
+            let callback_id = payload["callbackId"].as_str().unwrap_or("").to_string();
+
+            let plugin = plugin_for_handler.clone();
+            let handler = handler.clone();
+            spawn_with_callback(&webview_for_handler, callback_id, move || {
+                match plugin.call("message", &handler, &payload) {
+                    Ok(result) => serde_json::json!({ "result": result }),
+                    Err(e) => serde_json::json!({ "error": e }),
+                }
+            });
+        });
+        debug_log!("[PLUGIN] Registered script message handler '{}'", full_name);
+    }
+}
+
+/// Ask the user, one plugin at a time, to grant the permissions each plugin requests
+fn prompt_plugin_grants(
+    window: ApplicationWindow,
+    mut pending: Vec<plugins::PluginManifest>,
+    on_grant: Rc<dyn Fn(plugins::PluginManifest)>,
+) {
+    if pending.is_empty() {
+        return;
+    }
+    let manifest = pending.remove(0);
+
+    let mut permissions: Vec<String> = manifest
+        .required_permissions()
+        .iter()
+        .map(|p| p.to_string())
+        .collect();
+    permissions.sort();
+
+    let dialog = gtk4::AlertDialog::builder()
+        .message(tr!("plugin-prompt-title", name = manifest.name.as_str()))
+        .detail(format!(
+            "{} v{}\n{}\n\n{}\n\n{}",
+            manifest.name,
+            manifest.version,
+            manifest.description,
+            tr!("plugin-prompt-permissions", permissions = permissions.join(", ")),
+            tr!("plugin-prompt-warning")
+        ))
+        .buttons([tr!("plugin-prompt-deny"), tr!("plugin-prompt-allow")])
+        .cancel_button(0)
+        .default_button(0)
+        .modal(true)
+        .build();
 
-    info!("Overlay window created and presented");
+    // Temporarily lower the overlay layer so the dialog appears on top
+    window.set_layer(Layer::Bottom);
+    let window_for_response = window.clone();
+    dialog.choose(Some(&window), None::<&gio::Cancellable>, move |response| {
+        window_for_response.set_layer(compositor::resting_layer());
+        if let Ok(1) = response {
+            info!("User granted permissions to plugin '{}'", manifest.name);
+            on_grant(manifest);
+        } else {
+            info!("User denied plugin '{}'", manifest.name);
+            if let Err(e) = plugins::deny(&manifest) {
+                tracing::warn!("Failed to save plugin denial: {}", e);
+            }
+        }
+        prompt_plugin_grants(window_for_response, pending, on_grant);
+    });
+}
+
+/// Convert a JSON value from the frontend into a script value
+#[cfg(feature = "scripting")]
+fn json_to_dynamic(value: &serde_json::Value) -> rhai::Dynamic {
+    match value {
+        serde_json::Value::Bool(b) => rhai::Dynamic::from_bool(*b),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => rhai::Dynamic::from_int(i),
+            None => rhai::Dynamic::from_float(n.as_f64().unwrap_or(0.0)),
+        },
+        serde_json::Value::String(s) => s.clone().into(),
+        serde_json::Value::Null => rhai::Dynamic::UNIT,
+        other => other.to_string().into(),
+    }
 }
 
 fn create_webview_with_handlers(
@@ -485,8 +2386,10 @@ fn create_webview_with_handlers(
     position: Rc<RefCell<CharacterPosition>>,
     drag_state: Rc<RefCell<DragState>>,
     quadrant: Rc<RefCell<Quadrant>>,
+    chat: Rc<RefCell<layout::ChatState>>,
     tray_handle: Option<ksni::Handle<tray::DesktopWaifuTray>>,
     is_visible: Rc<RefCell<bool>>,
+    transfers: transfer::TransferStore,
 ) -> WebView {
     // Set up persistent storage for localStorage/cookies
     // This ensures API keys and settings are preserved across sessions
@@ -497,10 +2400,13 @@ fn create_webview_with_handlers(
     let _ = std::fs::create_dir_all(&data_dir);
     let _ = std::fs::create_dir_all(&cache_dir);
 
-    // Check if version changed and clear WebKit cache if so
-    // This ensures users get the latest frontend after package updates
+    // Check if the overlay or the frontend it serves changed and clear WebKit cache if so
+    // This ensures users get the latest frontend after package and frontend updates
     let version_file = data_dir.join("version");
-    let current_version = env!("CARGO_PKG_VERSION");
+    let current_version = match server::root().and_then(|root| frontend::read_manifest(&root)) {
+        Some(manifest) => format!("{}+{}", env!("CARGO_PKG_VERSION"), manifest.digest()),
+        None => env!("CARGO_PKG_VERSION").to_string(),
+    };
     let stored_version = std::fs::read_to_string(&version_file).unwrap_or_default();
 
     if stored_version.trim() != current_version {
@@ -522,6 +2428,24 @@ fn create_webview_with_handlers(
     let data_dir_str = data_dir.to_str().unwrap_or("/tmp/desktop-waifu");
     let cache_dir_str = cache_dir.to_str().unwrap_or("/tmp/desktop-waifu-cache");
 
+    // [webkit] memory limits, for the network process (before any session exists) and the
+    // web process through its context
+    let webkit_config = config::current().webkit.clone();
+    let memory_pressure = webkit_config.memory_pressure_settings();
+    if let Some(mut memory_pressure) = memory_pressure.clone() {
+        NetworkSession::set_memory_pressure_settings(&mut memory_pressure);
+    }
+    let web_context = match &memory_pressure {
+        Some(memory_pressure) => WebContext::builder().memory_pressure_settings(memory_pressure).build(),
+        None => WebContext::new(),
+    };
+    web_context.set_cache_model(webkit_config.cache_model.model());
+    info!(
+        "WebKit: {:?} cache, memory limit {}",
+        webkit_config.cache_model,
+        webkit_config.memory_limit.map_or("none".to_string(), |limit| format!("{} MB", limit))
+    );
+
     let network_session = NetworkSession::new(Some(data_dir_str), Some(cache_dir_str));
 
     // Create WebView settings
@@ -532,13 +2456,16 @@ fn create_webview_with_handlers(
 
     // Enable WebGL for Three.js
     settings.set_enable_webgl(true);
+    // `[webkit] hardware_acceleration`; with auto, off only when render.rs found no EGL
+    settings.set_hardware_acceleration_policy(render::acceleration_policy());
 
     // Enable JavaScript
     settings.set_enable_javascript(true);
 
-    // Allow file access from file URLs (for loading local assets)
-    settings.set_allow_file_access_from_file_urls(true);
-    settings.set_allow_universal_access_from_file_urls(true);
+    // The page is served over http (and the waifu-* schemes), never from file:// URLs, so
+    // file pages get no special access
+    settings.set_allow_file_access_from_file_urls(false);
+    settings.set_allow_universal_access_from_file_urls(false);
 
     // Enable smooth scrolling
     settings.set_enable_smooth_scrolling(true);
@@ -555,33 +2482,115 @@ fn create_webview_with_handlers(
     // Register the "resizeWindow" message handler for dynamic width adjustment
     content_manager.register_script_message_handler("resizeWindow", None);
 
+    // The chat panel's size at the chat scale, for placing it (see layout.rs)
+    content_manager.register_script_message_handler("chatSize", None);
+
     // Register the "executeCommand" message handler for shell command execution
     content_manager.register_script_message_handler("executeCommand", None);
+    // Commands run as root through pkexec
+    content_manager.register_script_message_handler("executePrivilegedCommand", None);
+    // Keystrokes for commands running on a PTY
+    content_manager.register_script_message_handler("commandInput", None);
+    // Input for piped commands started with `stdin`
+    content_manager.register_script_message_handler("commandStdin", None);
+    // Persistent shell sessions
+    content_manager.register_script_message_handler("sessionCreate", None);
+    content_manager.register_script_message_handler("sessionExec", None);
+    content_manager.register_script_message_handler("sessionDestroy", None);
 
     // Register the "getSystemInfo" message handler
     content_manager.register_script_message_handler("getSystemInfo", None);
 
+    // Register the "getSystemMetrics" message handler for CPU, memory, disk and battery readings
+    content_manager.register_script_message_handler("getSystemMetrics", None);
+
     // Register the "debug" message handler for JS debug logging
     content_manager.register_script_message_handler("debug", None);
 
     // Register the "getQuadrant" message handler for initial quadrant state
     content_manager.register_script_message_handler("getQuadrant", None);
 
-    // Register the "setInputRegion" message handler for click-through control
-    content_manager.register_script_message_handler("setInputRegion", None);
+    // Register the "getMonitors" message handler for monitor geometry
+    content_manager.register_script_message_handler("getMonitors", None);
+
+    // Register the "getActiveWindow" message handler for the focused app (Hyprland, Sway)
+    content_manager.register_script_message_handler("getActiveWindow", None);
+
+    // Register the "getLocaleInfo" message handler for timezone and date/time conventions
+    content_manager.register_script_message_handler("getLocaleInfo", None);
+
+    // Register the "getAppearance" message handler for system dark/light mode and accent color
+    content_manager.register_script_message_handler("getAppearance", None);
+
+    // Register the "setInputRegion" message handler for click-through control, and the
+    // script that syncs it from the layout in its "auto" mode
+    content_manager.register_script_message_handler("setInputRegion", None);
+    input_region::install(&content_manager);
+
+    // Keyboard mode from the page's focused text field
+    focus::install(window, &content_manager);
+
+    // Register the "showNotification" message handler for desktop notifications
+    content_manager.register_script_message_handler("showNotification", None);
+
+    // Register the "playSound" message handler; WebKit audio is unreliable on a layer surface
+    content_manager.register_script_message_handler("playSound", None);
+
+    // Register the "openFileDialog" message handler for native file picker
+    content_manager.register_script_message_handler("openFileDialog", None);
+
+    // Register the "setHotkeyEnabled" message handler for hotkey enable/disable
+    content_manager.register_script_message_handler("setHotkeyEnabled", None);
+
+    // Register the "saveFile" message handler for file export
+    content_manager.register_script_message_handler("saveFile", None);
+
+    // Register the "getAuditLog" message handler for the record of what was done
+    content_manager.register_script_message_handler("getAuditLog", None);
+
+    // Register the frontend update handlers
+    content_manager.register_script_message_handler("checkFrontendUpdate", None);
+    content_manager.register_script_message_handler("applyFrontendUpdate", None);
+
+    // Register the file reading handlers, limited to folders the user has allowed
+    for name in ["readFile", "listDir", "statPath", "watchPath", "unwatchPath", "fileAccess"] {
+        content_manager.register_script_message_handler(name, None);
+    }
+
+    // Register the clipboard handlers; WebKit's clipboard API needs a user gesture and
+    // focus, which a layer-shell surface often doesn't have
+    content_manager.register_script_message_handler("readClipboard", None);
+    content_manager.register_script_message_handler("writeClipboard", None);
+
+    // Register the "captureScreen" message handler for "look at my screen" prompts
+    content_manager.register_script_message_handler("captureScreen", None);
+
+    // Register the text-to-speech handlers
+    content_manager.register_script_message_handler("speak", None);
+    content_manager.register_script_message_handler("stopSpeaking", None);
+
+    // Register the LLM handlers; chat requests and API keys stay on this side
+    content_manager.register_script_message_handler("llmChat", None);
+    content_manager.register_script_message_handler("llmCancel", None);
+    content_manager.register_script_message_handler("llmSetKey", None);
+    content_manager.register_script_message_handler("llmListModels", None);
 
-    // Register the "showNotification" message handler for desktop notifications
-    content_manager.register_script_message_handler("showNotification", None);
+    // Register the keyring handlers, which keep API keys out of localStorage
+    content_manager.register_script_message_handler("storeSecret", None);
+    content_manager.register_script_message_handler("getSecret", None);
+    content_manager.register_script_message_handler("deleteSecret", None);
 
-    // Register the "openFileDialog" message handler for native file picker
-    content_manager.register_script_message_handler("openFileDialog", None);
+    // Register the conversation history handler (SQLite in the data dir)
+    content_manager.register_script_message_handler("history", None);
 
-    // Register the "setHotkeyEnabled" message handler for hotkey enable/disable
-    content_manager.register_script_message_handler("setHotkeyEnabled", None);
+    // Register the long-term memory handler
+    content_manager.register_script_message_handler("memory", None);
 
-    // Register the "saveFile" message handler for file export
-    content_manager.register_script_message_handler("saveFile", None);
+    // Register the character model handler (VRMs in the data dir)
+    content_manager.register_script_message_handler("models", None);
 
+    // Register the reminders handler (see reminders.rs)
+    content_manager.register_script_message_handler("reminders", None);
 
     // Clone window for windowControl handler
     let window_for_control = window.clone();
@@ -624,6 +2633,8 @@ fn create_webview_with_handlers(
 
     // Clone window for resizeWindow handler
     let window_for_resize = window.clone();
+    let position_for_resize = position.clone();
+    let chat_for_resize = chat.clone();
 
     // Connect to the script-message-received signal for window resize
     content_manager.connect_script_message_received(Some("resizeWindow"), move |_manager, js_value| {
@@ -633,26 +2644,20 @@ fn create_webview_with_handlers(
 
                 match action {
                     "resize" => {
-                        let width = parsed["width"].as_i64().unwrap_or(WINDOW_WIDTH_EXPANDED as i64) as i32;
-                        let height = parsed["height"].as_i64().unwrap_or(WINDOW_HEIGHT_EXPANDED as i64) as i32;
+                        let window_config = config::current().window.clone();
+                        // CSS pixels, which may be fractional with a scaled chat
+                        let width = parsed["width"].as_f64().map_or(window_config.expanded_width, |w| w.ceil() as i32);
+                        let height = parsed["height"].as_f64().map_or(window_config.expanded_height, |h| h.ceil() as i32);
                         window_for_resize.set_default_width(width);
                         window_for_resize.set_default_height(height);
 
-                        // Compositor revokes keyboard focus ~14ms after resize.
-                        // Use Exclusive mode briefly when chat opens to grab focus,
-                        // then switch back to OnDemand so user can type in other apps.
-                        // Use > comparison instead of == to handle scaled chat widths
-                        let is_expanding = width > WINDOW_WIDTH_COLLAPSED;
+                        // Some compositors revoke keyboard focus right after a resize, so
+                        // opening the chat grabs it there (focus.rs). Use > comparison
+                        // instead of == to handle scaled chat widths
+                        let is_expanding = width > position_for_resize.borrow().size().0;
                         debug_log!("[RESIZE] width={}, height={}, is_expanding={}", width, height, is_expanding);
-                        let window_clone = window_for_resize.clone();
-                        glib::timeout_add_local_once(Duration::from_millis(50), move || {
-                            debug_log!("[RESIZE] Setting keyboard mode: {}", if is_expanding { "Exclusive" } else { "OnDemand" });
-                            if is_expanding {
-                                window_clone.set_keyboard_mode(KeyboardMode::Exclusive);
-                            } else {
-                                window_clone.set_keyboard_mode(KeyboardMode::OnDemand);
-                            }
-                        });
+                        focus::resized(&window_for_resize, is_expanding);
+                        chat_for_resize.borrow_mut().open = is_expanding;
                     }
                     _ => {}
                 }
@@ -665,17 +2670,60 @@ fn create_webview_with_handlers(
         .settings(&settings)
         .user_content_manager(&content_manager)
         .network_session(&network_session)
+        .web_context(&web_context)
         .build();
 
+    transfers.install(&webview);
+    models::install(&webview);
+    animations::install(&webview);
+
+    // Files dropped on the character become attachments (or quotes) in the chat
+    let webview_for_drop = webview.clone();
+    dnd::install(&webview, move |detail| dispatch_event(&webview_for_drop, "fileDropped", &detail));
+
     // Make WebView background transparent (RGBA with 0 alpha)
     webview.set_background_color(&gtk4::gdk::RGBA::new(0.0, 0.0, 0.0, 0.0));
 
+    // A dead web process leaves a transparent surface that still takes clicks and keys, so
+    // make it click-through and reload. Position and quadrant live here, and the new page
+    // gets them back through getQuadrant. Crashing again and again gives up instead of looping.
+    let window_for_crash = window.clone();
+    let tray_handle_for_crash = tray_handle.clone();
+    let web_crashes: Rc<RefCell<Vec<std::time::Instant>>> = Rc::new(RefCell::new(Vec::new()));
+    webview.connect_web_process_terminated(move |webview, reason| {
+        if reason == webkit6::WebProcessTerminationReason::TerminatedByApi {
+            return;
+        }
+        tracing::warn!("Web process terminated: {:?}", reason);
+        if reason == webkit6::WebProcessTerminationReason::ExceededMemoryLimit {
+            tracing::warn!("The page passed [webkit] memory_limit's kill threshold");
+        }
+        input_region::apply(&window_for_crash, &Region::create());
+        focus::reset(&window_for_crash);
+        if let Some(ref handle) = tray_handle_for_crash {
+            tray::update_tray_thinking(handle, false);
+        }
+
+        let now = std::time::Instant::now();
+        let mut crashes = web_crashes.borrow_mut();
+        crashes.retain(|crash| now.duration_since(*crash) < WEB_CRASH_WINDOW);
+        crashes.push(now);
+        if crashes.len() > MAX_WEB_RELOADS {
+            tracing::error!("Web process crashed {} times in {:?}, not reloading", crashes.len(), WEB_CRASH_WINDOW);
+            notifications::show(&tr!("app-name"), &tr!("web-process-gave-up"));
+            return;
+        }
+        notifications::show(&tr!("app-name"), &tr!("web-process-reloading"));
+        webview.reload();
+    });
+
     // Set up moveWindow handler (needs webview for quadrant events)
     let window_for_move = window.clone();
     let webview_for_move = webview.clone();
     let position_for_move = position.clone();
     let drag_state_for_move = drag_state.clone();
     let quadrant_for_move = quadrant.clone();
+    let chat_for_move = chat.clone();
     content_manager.connect_script_message_received(Some("moveWindow"), move |_manager, js_value| {
         // Convert JS value to JSON string
         if let Some(json_str) = js_value.to_json(0) {
@@ -694,7 +2742,7 @@ fn create_webview_with_handlers(
                     }
                     "drag" => {
                         // Fullscreen window approach: no margins, position via CSS
-                        let drag = drag_state_for_move.borrow();
+                        let mut drag = drag_state_for_move.borrow_mut();
                         if !drag.is_dragging {
                             return;
                         }
@@ -707,19 +2755,30 @@ fn create_webview_with_handlers(
                         let new_x = drag.start_x + offset_x;
                         let new_y = drag.start_y + offset_y;
 
-                        // Update stored position
+                        // Update stored position, keeping the character on screen
                         {
                             let mut pos = position_for_move.borrow_mut();
                             pos.x = new_x;
                             pos.y = new_y;
+                            pos.clamp();
                         }
 
-                        // Send position to frontend for CSS update
-                        let js = format!(
-                            "window.dispatchEvent(new CustomEvent('characterMove', {{ detail: {{ x: {}, y: {} }} }}))",
-                            new_x, new_y
-                        );
-                        webview_for_move.evaluate_javascript(&js, None, None, None::<&gio::Cancellable>, |_| {});
+                        // Send the latest position to the frontend at most once per frame;
+                        // motion arriving before the next tick just updates the stored position
+                        if drag.frame_pending {
+                            return;
+                        }
+                        drag.frame_pending = true;
+                        let webview = webview_for_move.clone();
+                        let position = position_for_move.clone();
+                        let quadrant = quadrant_for_move.clone();
+                        let chat = chat_for_move.clone();
+                        let drag_state = drag_state_for_move.clone();
+                        window_for_move.add_tick_callback(move |_, _| {
+                            drag_state.borrow_mut().frame_pending = false;
+                            send_layout(&webview, &position.borrow(), &quadrant.borrow(), &chat.borrow());
+                            glib::ControlFlow::Break
+                        });
                     }
                     "endDrag" => {
                         {
@@ -728,23 +2787,42 @@ fn create_webview_with_handlers(
                         }
 
                         // Calculate quadrant for chat positioning
-                        if let Some((screen_width, screen_height)) = get_screen_dimensions(&window_for_move) {
+                        {
+                            let (screen_width, screen_height) = monitors::screen_dimensions(&window_for_move);
+                            // Panels may have come or gone mid-drag; the character has to end up
+                            // where it can be seen and grabbed again
+                            let clamped = position_for_move.borrow_mut().clamp();
+                            // Ending near an edge slides it onto the edge
+                            let position_config = config::current().position.clone();
+                            let snapped = position_config.snap_distance > 0
+                                && position_for_move
+                                    .borrow_mut()
+                                    .snap(position_config.snap_distance, position_config.margin);
                             let pos = position_for_move.borrow();
+                            if snapped {
+                                debug_log!("[ENDDRAG] Snapped to {},{}", pos.x, pos.y);
+                                dispatch_event(
+                                    &webview_for_move,
+                                    "characterSnap",
+                                    &serde_json::json!({
+                                        "x": pos.x,
+                                        "y": pos.y,
+                                        "durationMs": SNAP_DURATION.as_millis() as u64,
+                                    }),
+                                );
+                            }
 
-                            // Character center position
-                            let char_center_x = pos.x + WINDOW_WIDTH_COLLAPSED / 2;
-                            let char_center_y = pos.y + WINDOW_HEIGHT_COLLAPSED / 2;
-
-                            let new_is_right = char_center_x >= screen_width / 2;
-                            let new_is_bottom = char_center_y >= screen_height / 2;
+                            // Quadrant of the character's center
+                            let Quadrant { is_right_half: new_is_right, is_bottom_half: new_is_bottom } =
+                                pos.quadrant(screen_width, screen_height);
 
                             let prev = quadrant_for_move.borrow();
                             let quadrant_changed = new_is_right != prev.is_right_half
                                 || new_is_bottom != prev.is_bottom_half;
 
-                            if quadrant_changed {
-                                debug_log!("[ENDDRAG] Quadrant changed: ({},{}) -> ({},{})",
-                                    prev.is_right_half, prev.is_bottom_half, new_is_right, new_is_bottom);
+                            if quadrant_changed || clamped || snapped {
+                                debug_log!("[ENDDRAG] Quadrant changed: ({},{}) -> ({},{}), clamped: {}",
+                                    prev.is_right_half, prev.is_bottom_half, new_is_right, new_is_bottom, clamped);
                                 drop(prev);
 
                                 let new_quadrant = Quadrant {
@@ -753,14 +2831,12 @@ fn create_webview_with_handlers(
                                 };
                                 *quadrant_for_move.borrow_mut() = new_quadrant.clone();
 
-                                // Send quadrant to frontend for chat positioning
-                                let js = format!(
-                                    "window.dispatchEvent(new CustomEvent('quadrantChange', {{ detail: {{ isRightHalf: {}, isBottomHalf: {} }} }}))",
-                                    new_is_right, new_is_bottom
-                                );
-                                webview_for_move.evaluate_javascript(&js, None, None, None::<&gio::Cancellable>, |_| {});
+                                // The chat moves to the other side when following the character,
+                                // and the character back on screen or onto the edge
+                                send_layout(&webview_for_move, &pos, &new_quadrant, &chat_for_move.borrow());
                             }
                         }
+                        state::changed();
                         debug_log!("[ENDDRAG] Drag finished");
                     }
                     _ => {}
@@ -770,7 +2846,13 @@ fn create_webview_with_handlers(
     });
 
     // Set up executeCommand handler (needs webview reference for callback)
+    let window_for_exec = window.clone();
     let webview_for_exec = webview.clone();
+    let transfers_for_exec = transfers.clone();
+    let pty_sessions: Rc<RefCell<HashMap<String, pty::PtyInput>>> = Rc::default();
+    let pty_sessions_for_exec = pty_sessions.clone();
+    let stdin_sessions: Rc<RefCell<HashMap<String, Arc<std::process::ChildStdin>>>> = Rc::default();
+    let stdin_sessions_for_exec = stdin_sessions.clone();
     content_manager.connect_script_message_received(Some("executeCommand"), move |_manager, js_value| {
         if let Some(json_str) = js_value.to_json(0) {
             if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) {
@@ -781,57 +2863,235 @@ fn create_webview_with_handlers(
                     return;
                 }
 
-                info!("Executing command: {}", cmd);
-
-                // Use channel to communicate result back to main thread
-                let (tx, rx) = std::sync::mpsc::channel::<String>();
-
-                // Spawn thread for command execution
-                std::thread::spawn(move || {
-                    let output = std::process::Command::new("sh")
-                        .arg("-c")
-                        .arg(&cmd)
-                        .output();
-
-                    let (stdout, stderr, exit_code) = match output {
-                        Ok(out) => (
-                            String::from_utf8_lossy(&out.stdout).to_string(),
-                            String::from_utf8_lossy(&out.stderr).to_string(),
-                            out.status.code().unwrap_or(-1),
-                        ),
-                        Err(e) => (String::new(), e.to_string(), -1),
-                    };
-
-                    info!("Command completed with exit code: {}", exit_code);
+                // `shell`, `cwd` and `env` override the login shell, home and environment
+                let options = shell::from_message(&parsed);
 
-                    // Escape strings for JavaScript
-                    let stdout_escaped = stdout.replace('\\', "\\\\").replace('`', "\\`").replace("${", "\\${");
-                    let stderr_escaped = stderr.replace('\\', "\\\\").replace('`', "\\`").replace("${", "\\${");
+                // `confirmed` is set once the user has approved this command in the chat
+                let confirmed = parsed["confirmed"].as_bool() == Some(true);
+                let webview = webview_for_exec.clone();
+                let pty_sessions = pty_sessions_for_exec.clone();
+                let stdin_sessions = stdin_sessions_for_exec.clone();
+                let transfers = transfers_for_exec.clone();
+                let cmd_for_confirm = cmd.clone();
+                confirm::command(&window_for_exec, &cmd_for_confirm, options.cwd(), confirmed, false, move |allowed| {
+                    if let Err(e) = allowed {
+                        info!("{}: {}", e, cmd);
+                        audit::record(audit::Kind::Command, &cmd, audit::Outcome::Refused, Some(e.clone()));
+                        dispatch_callback(&webview, &callback_id, &serde_json::json!({ "error": e }));
+                        return;
+                    }
+                    let mut command = match policy::load().and_then(|policy| sandbox::apply(&policy, &cmd, options.command(&cmd))) {
+                        Ok(command) => command,
+                        Err(e) => {
+                            audit::record(audit::Kind::Command, &cmd, audit::Outcome::Failed, Some(e.clone()));
+                            dispatch_callback(&webview, &callback_id, &serde_json::json!({ "error": e }));
+                            return;
+                        }
+                    };
 
-                    let js = format!(
-                        r#"window.__commandCallbacks && window.__commandCallbacks['{}'] && window.__commandCallbacks['{}']( {{ stdout: `{}`, stderr: `{}`, exit_code: {} }} )"#,
-                        callback_id, callback_id, stdout_escaped, stderr_escaped, exit_code
-                    );
+                    // Interactive programs get a terminal; the callback id names the session
+                    if parsed["pty"].as_bool() == Some(true) {
+                        info!("Executing command on a PTY: {}", cmd);
+                        run_in_pty(&webview, &pty_sessions, &transfers, command, cmd, callback_id);
+                        return;
+                    }
 
-                    let _ = tx.send(js);
-                });
+                    info!("Executing command: {}", cmd);
+
+                    // With `stdin`, the pipe stays open for commandStdin under the callback id
+                    let with_stdin = parsed["stdin"].as_bool() == Some(true);
+                    let mut child = command
+                        .stdin(if with_stdin { Stdio::piped() } else { Stdio::null() })
+                        .stdout(Stdio::piped())
+                        .stderr(Stdio::piped())
+                        .spawn();
+                    if let Ok(child) = &mut child
+                        && let Some(stdin) = child.stdin.take()
+                    {
+                        stdin_sessions
+                            .borrow_mut()
+                            .insert(callback_id.clone(), Arc::new(stdin));
+                    }
 
-                // Poll for result on main thread
-                let webview = webview_for_exec.clone();
-                glib::timeout_add_local(Duration::from_millis(10), move || {
-                    match rx.try_recv() {
-                        Ok(js) => {
-                            webview.evaluate_javascript(&js, None, None, None::<&gio::Cancellable>, |_| {});
-                            glib::ControlFlow::Break
+                    // Wait for the command on a worker thread; the result comes back as a future
+                    let job = expressions::job_started();
+                    let task = gio::spawn_blocking(move || {
+                        let output = child.and_then(|child| child.wait_with_output());
+
+                        let (stdout, stderr, exit_code) = match output {
+                            Ok(out) => (out.stdout, out.stderr, out.status.code().unwrap_or(-1)),
+                            Err(e) => (Vec::new(), e.to_string().into_bytes(), -1),
+                        };
+
+                        info!("Command completed with exit code: {}", exit_code);
+                        audit::exit(audit::Kind::Command, &cmd, exit_code);
+                        command_result(&transfers, stdout, stderr, exit_code)
+                    });
+
+                    // Deliver the result on the main thread once the worker finishes
+                    glib::spawn_future_local(async move {
+                        let result = task.await;
+                        stdin_sessions.borrow_mut().remove(&callback_id);
+                        expressions::job_finished(job, result.as_ref().is_ok_and(|result| result["exit_code"] == 0));
+                        if let Ok(result) = result {
+                            dispatch_callback(&webview, &callback_id, &result);
                         }
-                        Err(std::sync::mpsc::TryRecvError::Empty) => glib::ControlFlow::Continue,
-                        Err(std::sync::mpsc::TryRecvError::Disconnected) => glib::ControlFlow::Break,
-                    }
+                    });
                 });
             }
         }
     });
 
+    // Set up executePrivilegedCommand handler - polkit asks for the password itself; the
+    // user must have confirmed the command first, whatever the policy says
+    let window_for_privileged = window.clone();
+    let webview_for_privileged = webview.clone();
+    let transfers_for_privileged = transfers.clone();
+    content_manager.connect_script_message_received(Some("executePrivilegedCommand"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let cmd = parsed["cmd"].as_str().unwrap_or("").to_string();
+        let callback_id = parsed["callbackId"].as_str().unwrap_or("").to_string();
+        if cmd.is_empty() {
+            return;
+        }
+        let confirmed = parsed["confirmed"].as_bool() == Some(true);
+        let webview = webview_for_privileged.clone();
+        let transfers = transfers_for_privileged.clone();
+        let cmd_for_confirm = cmd.clone();
+        confirm::command(&window_for_privileged, &cmd_for_confirm, None, confirmed, true, move |allowed| {
+            if let Err(e) = allowed {
+                info!("{}: {}", e, cmd);
+                audit::record(audit::Kind::PrivilegedCommand, &cmd, audit::Outcome::Refused, Some(e.clone()));
+                dispatch_callback(&webview, &callback_id, &serde_json::json!({ "error": e }));
+                return;
+            }
+            spawn_with_callback(&webview, callback_id, move || {
+                let output = privileged::run_shell(&cmd);
+                let outcome = match output.outcome {
+                    privileged::Outcome::Completed if output.exit_code == 0 => audit::Outcome::Ok,
+                    privileged::Outcome::Cancelled | privileged::Outcome::NotAuthorized => audit::Outcome::Refused,
+                    _ => audit::Outcome::Failed,
+                };
+                let detail = format!("{:?}, exit code {}", output.outcome, output.exit_code);
+                audit::record(audit::Kind::PrivilegedCommand, &cmd, outcome, Some(detail));
+                let mut result = command_result(&transfers, output.stdout, output.stderr, output.exit_code);
+                result["command"] = serde_json::json!(output.command);
+                result["outcome"] = serde_json::json!(output.outcome);
+                result
+            });
+        });
+    });
+
+    // Set up commandInput handler - text typed into a PTY session
+    content_manager.connect_script_message_received(Some("commandInput"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let (Some(id), Some(data)) = (parsed["id"].as_str(), parsed["data"].as_str()) else { return };
+        let Some(input) = pty_sessions.borrow().get(id).cloned() else {
+            debug_log!("[EXEC] Input for unknown PTY session {}", id);
+            return;
+        };
+        let data = data.as_bytes().to_vec();
+        runtime::spawn_blocking(move || {
+            if let Err(e) = input.write(&data) {
+                tracing::warn!("Failed to write to command {}: {}", input.pid(), e);
+            }
+        });
+    });
+
+    // Set up commandStdin handler - input for a piped command started with `stdin`
+    content_manager.connect_script_message_received(Some("commandStdin"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let Some(id) = parsed["id"].as_str() else { return };
+        let data = parsed["data"].as_str().unwrap_or("").as_bytes().to_vec();
+        // Dropping the last handle closes the pipe, so the command sees end of input
+        let stdin = if parsed["eof"].as_bool() == Some(true) {
+            stdin_sessions.borrow_mut().remove(id)
+        } else {
+            stdin_sessions.borrow().get(id).cloned()
+        };
+        let Some(stdin) = stdin else {
+            debug_log!("[EXEC] Input for unknown command {}", id);
+            return;
+        };
+        runtime::spawn_blocking(move || {
+            if let Err(e) = (&*stdin).write_all(&data) {
+                tracing::warn!("Failed to write to command stdin: {}", e);
+            }
+        });
+    });
+
+    // Set up shell session handlers - commands sharing one long-lived shell
+    let webview_for_session = webview.clone();
+    content_manager.connect_script_message_received(Some("sessionCreate"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let callback_id = parsed["callbackId"].as_str().unwrap_or("").to_string();
+        let Some(id) = parsed["id"].as_str() else { return };
+        let result = match session::create(id.to_string()) {
+            Ok(()) => serde_json::json!({}),
+            Err(e) => serde_json::json!({ "error": e }),
+        };
+        dispatch_callback(&webview_for_session, &callback_id, &result);
+    });
+    let window_for_session = window.clone();
+    let webview_for_session = webview.clone();
+    let transfers_for_session = transfers.clone();
+    content_manager.connect_script_message_received(Some("sessionExec"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let callback_id = parsed["callbackId"].as_str().unwrap_or("").to_string();
+        let (Some(id), Some(cmd)) = (parsed["id"].as_str(), parsed["cmd"].as_str()) else { return };
+        let (id, cmd) = (id.to_string(), cmd.to_string());
+
+        // The session's shell keeps its own working directory, so there's none to show
+        let confirmed = parsed["confirmed"].as_bool() == Some(true);
+        let webview = webview_for_session.clone();
+        let transfers = transfers_for_session.clone();
+        let cmd_for_confirm = cmd.clone();
+        confirm::command(&window_for_session, &cmd_for_confirm, None, confirmed, false, move |allowed| {
+            if let Err(e) = allowed {
+                info!("{}: {}", e, cmd);
+                audit::record(audit::Kind::SessionCommand, &cmd, audit::Outcome::Refused, Some(e.clone()));
+                dispatch_callback(&webview, &callback_id, &serde_json::json!({ "error": e }));
+                return;
+            }
+
+            info!("Executing command in session {}: {}", id, cmd);
+            let cmd_for_audit = cmd.clone();
+            let task = runtime::spawn(async move { session::exec(&id, &cmd).await });
+            glib::spawn_future_local(async move {
+                let result = match task.await {
+                    Ok(Ok(output)) => {
+                        audit::exit(audit::Kind::SessionCommand, &cmd_for_audit, output.exit_code);
+                        command_result(&transfers, output.stdout, output.stderr, output.exit_code)
+                    }
+                    Ok(Err(e)) => {
+                        audit::record(audit::Kind::SessionCommand, &cmd_for_audit, audit::Outcome::Failed, Some(e.clone()));
+                        serde_json::json!({ "error": e })
+                    }
+                    Err(e) => serde_json::json!({ "error": e.to_string() }),
+                };
+                dispatch_callback(&webview, &callback_id, &result);
+            });
+        });
+    });
+    let webview_for_session = webview.clone();
+    content_manager.connect_script_message_received(Some("sessionDestroy"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let callback_id = parsed["callbackId"].as_str().unwrap_or("").to_string();
+        let Some(id) = parsed["id"].as_str() else { return };
+        let result = if session::destroy(id) {
+            serde_json::json!({})
+        } else {
+            serde_json::json!({ "error": format!("No session with id '{}'", id) })
+        };
+        dispatch_callback(&webview_for_session, &callback_id, &result);
+    });
+
     // Set up getSystemInfo handler
     let webview_for_sysinfo = webview.clone();
     content_manager.connect_script_message_received(Some("getSystemInfo"), move |_manager, js_value| {
@@ -839,145 +3099,231 @@ fn create_webview_with_handlers(
             if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) {
                 let callback_id = parsed["callbackId"].as_str().unwrap_or("").to_string();
 
-                let (tx, rx) = std::sync::mpsc::channel::<String>();
-
-                std::thread::spawn(move || {
-                    let os = std::env::consts::OS.to_string();
-                    let arch = std::env::consts::ARCH.to_string();
-                    let shell = std::env::var("SHELL").ok();
-
-                    // Get distro from /etc/os-release
-                    let distro = if os == "linux" {
-                        std::process::Command::new("sh")
-                            .arg("-c")
-                            .arg("cat /etc/os-release 2>/dev/null | grep -E '^NAME=' | head -1 | cut -d= -f2 | tr -d '\"'")
-                            .output()
-                            .ok()
-                            .and_then(|out| {
-                                let s = String::from_utf8_lossy(&out.stdout).trim().to_string();
-                                if s.is_empty() { None } else { Some(s) }
-                            })
-                    } else {
-                        None
-                    };
-
-                    // Detect package manager
-                    let package_manager = if os == "linux" {
-                        let managers = ["apt", "dnf", "yum", "pacman", "zypper", "apk"];
-                        let mut found = None;
-                        for mgr in managers {
-                            if let Ok(out) = std::process::Command::new("which").arg(mgr).output() {
-                                if out.status.success() {
-                                    found = Some(mgr.to_string());
-                                    break;
-                                }
-                            }
-                        }
-                        found
-                    } else {
-                        None
-                    };
-
-                    // Build JSON response
-                    let distro_json = distro.map(|d| format!("\"{}\"", d)).unwrap_or("null".to_string());
-                    let shell_json = shell.map(|s| format!("\"{}\"", s)).unwrap_or("null".to_string());
-                    let pkg_json = package_manager.map(|p| format!("\"{}\"", p)).unwrap_or("null".to_string());
-
-                    let js = format!(
-                        r#"window.__commandCallbacks && window.__commandCallbacks['{}'] && window.__commandCallbacks['{}']( {{ os: "{}", arch: "{}", distro: {}, shell: {}, package_manager: {} }} )"#,
-                        callback_id, callback_id, os, arch, distro_json, shell_json, pkg_json
-                    );
-
-                    let _ = tx.send(js);
-                });
+                let task = gio::spawn_blocking(system::info);
 
-                // Poll for result on main thread
+                // Deliver the result on the main thread once the worker finishes
                 let webview = webview_for_sysinfo.clone();
-                glib::timeout_add_local(Duration::from_millis(10), move || {
-                    match rx.try_recv() {
-                        Ok(js) => {
-                            webview.evaluate_javascript(&js, None, None, None::<&gio::Cancellable>, |_| {});
-                            glib::ControlFlow::Break
-                        }
-                        Err(std::sync::mpsc::TryRecvError::Empty) => glib::ControlFlow::Continue,
-                        Err(std::sync::mpsc::TryRecvError::Disconnected) => glib::ControlFlow::Break,
+                glib::spawn_future_local(async move {
+                    if let Ok(info) = task.await {
+                        dispatch_callback(&webview, &callback_id, &info);
                     }
                 });
             }
         }
     });
 
-    // Set up debug handler for JS debug logging (only prints when DEBUG_LOGGING is true)
+    // Set up getSystemMetrics handler - the first call waits for a second CPU sample
+    let webview_for_metrics = webview.clone();
+    content_manager.connect_script_message_received(Some("getSystemMetrics"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let callback_id = parsed["callbackId"].as_str().unwrap_or("").to_string();
+        spawn_with_callback(&webview_for_metrics, callback_id, || serde_json::json!(system::metrics()));
+    });
+
+    // Set up debug handler for JS debug logging (shown at the debug log level)
     content_manager.connect_script_message_received(Some("debug"), move |_manager, js_value| {
-        if DEBUG_LOGGING {
-            if let Some(json_str) = js_value.to_json(0) {
-                if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) {
-                    let msg = parsed["message"].as_str().unwrap_or("");
-                    println!("[JS] {}", msg);
-                }
+        if let Some(json_str) = js_value.to_json(0) {
+            if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) {
+                let msg = parsed["message"].as_str().unwrap_or("");
+                debug_log!("[JS] {}", msg);
             }
         }
     });
 
-    // Set up getQuadrant handler - sends initial position and quadrant to frontend
+    // Set up getQuadrant handler - sends the character's position, the quadrant and where
+    // the chat goes to the frontend
     let window_for_quadrant = window.clone();
     let webview_for_quadrant = webview.clone();
     let position_for_quadrant = position.clone();
     let quadrant_for_get = quadrant.clone();
+    let chat_for_get = chat.clone();
     content_manager.connect_script_message_received(Some("getQuadrant"), move |_manager, _js_value| {
-        if let Some((screen_width, screen_height)) = get_screen_dimensions(&window_for_quadrant) {
-            let pos = position_for_quadrant.borrow();
-
-            // Calculate quadrant from absolute position
-            let char_center_x = pos.x + WINDOW_WIDTH_COLLAPSED / 2;
-            let char_center_y = pos.y + WINDOW_HEIGHT_COLLAPSED / 2;
-            let is_right = char_center_x >= screen_width / 2;
-            let is_bottom = char_center_y >= screen_height / 2;
-
-            let current_quadrant = Quadrant {
-                is_right_half: is_right,
-                is_bottom_half: is_bottom,
-            };
-            *quadrant_for_get.borrow_mut() = current_quadrant.clone();
+        let (screen_width, screen_height) = monitors::screen_dimensions(&window_for_quadrant);
+        let pos = position_for_quadrant.borrow();
+
+        // Calculate quadrant from absolute position
+        let current_quadrant = pos.quadrant(screen_width, screen_height);
+        send_layout(&webview_for_quadrant, &pos, &current_quadrant, &chat_for_get.borrow());
+        *quadrant_for_get.borrow_mut() = current_quadrant;
+        state::changed();
+    });
 
-            // Send initial state to frontend: position + quadrant + screen dimensions
-            let js = format!(
-                r#"window.dispatchEvent(new CustomEvent('initialState', {{ detail: {{ x: {}, y: {}, isRightHalf: {}, isBottomHalf: {}, screenWidth: {}, screenHeight: {} }} }}))"#,
-                pos.x, pos.y, is_right, is_bottom, screen_width, screen_height
-            );
-            webview_for_quadrant.evaluate_javascript(&js, None, None, None::<&gio::Cancellable>, |_| {});
+    // The frontend reports the chat's size whenever the chat scale changes it
+    let webview_for_chat_size = webview.clone();
+    let position_for_chat_size = position.clone();
+    let quadrant_for_chat_size = quadrant.clone();
+    let chat_for_size = chat.clone();
+    content_manager.connect_script_message_received(Some("chatSize"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let (Some(width), Some(height)) = (parsed["width"].as_f64(), parsed["height"].as_f64()) else { return };
+        let size = (width.ceil() as i32, height.ceil() as i32);
+        if chat_for_size.borrow().size == Some(size) {
+            return;
+        }
+        chat_for_size.borrow_mut().size = Some(size);
+        send_layout(
+            &webview_for_chat_size,
+            &position_for_chat_size.borrow(),
+            &quadrant_for_chat_size.borrow(),
+            &chat_for_size.borrow(),
+        );
+    });
+
+    // Set up getMonitors handler - lists monitors and which one hosts the character
+    let window_for_monitors = window.clone();
+    let webview_for_monitors = webview.clone();
+    content_manager.connect_script_message_received(Some("getMonitors"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let callback_id = parsed["callbackId"].as_str().unwrap_or("");
+        let monitors = serde_json::json!(monitors::list(&window_for_monitors));
+        dispatch_callback(&webview_for_monitors, callback_id, &monitors);
+    });
+
+    // Tell the frontend when monitors are plugged in/out or change geometry or scale. A
+    // new scale resizes the monitor in application pixels, so the character is moved to
+    // the same relative spot first; the frontend then asks for it with getQuadrant.
+    let window_for_monitor_events = window.clone();
+    let webview_for_monitor_events = webview.clone();
+    let position_for_monitor_events = position.clone();
+    monitors::watch(Rc::new(move || {
+        let (screen_width, screen_height) = monitors::screen_dimensions(&window_for_monitor_events);
+        {
+            let mut pos = position_for_monitor_events.borrow_mut();
+            if pos.screen != (screen_width, screen_height) {
+                debug_log!(
+                    "[MONITORS] Screen is now {}x{} (was {}x{}), moving the character",
+                    screen_width, screen_height, pos.screen.0, pos.screen.1
+                );
+                pos.rescale(screen_width, screen_height);
+            }
+        }
+        state::changed();
+        let monitors = monitors::list(&window_for_monitor_events);
+        debug_log!("[MONITORS] Monitor configuration changed: {} monitors", monitors.len());
+        dispatch_event(&webview_for_monitor_events, "monitorsChanged", &serde_json::json!({ "monitors": monitors }));
+    }));
+
+    // Panels coming and going resize the surface with `[window] respect_panels`; keep the
+    // character within it
+    let webview_for_work_area = webview.clone();
+    let position_for_work_area = position.clone();
+    let quadrant_for_work_area = quadrant.clone();
+    let chat_for_work_area = chat.clone();
+    monitors::watch_work_area(window, move |width, height| {
+        let mut pos = position_for_work_area.borrow_mut();
+        if pos.screen == (width, height) {
+            return;
         }
+        debug_log!("[MONITORS] Work area is now {}x{} (was {}x{})", width, height, pos.screen.0, pos.screen.1);
+        pos.rescale(width, height);
+        let quadrant = pos.quadrant(width, height);
+        send_layout(&webview_for_work_area, &pos, &quadrant, &chat_for_work_area.borrow());
+        *quadrant_for_work_area.borrow_mut() = quadrant;
+        state::changed();
+    });
+
+    // Set up getActiveWindow handler - asks the compositor for the focused window, passing
+    // on only what `[compositor]` allows; null where there's no compositor to ask
+    let webview_for_active_window = webview.clone();
+    content_manager.connect_script_message_received(Some("getActiveWindow"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let callback_id = parsed["callbackId"].as_str().unwrap_or("").to_string();
+        let task = runtime::spawn(compositor::active_window());
+        let webview = webview_for_active_window.clone();
+        glib::spawn_future_local(async move {
+            let desktop = match task.await {
+                Ok(Ok(desktop)) => desktop,
+                Ok(Err(e)) => {
+                    debug_log!("[COMPOSITOR] Can't query the active window: {}", e);
+                    None
+                }
+                Err(_) => None,
+            };
+            let shared = desktop.map(|desktop| desktop.shared(&config::current().compositor));
+            dispatch_callback(&webview, &callback_id, &serde_json::json!(shared));
+        });
+    });
+
+    // Set up getLocaleInfo handler - locale, timezone, clock format and week start
+    let webview_for_locale = webview.clone();
+    content_manager.connect_script_message_received(Some("getLocaleInfo"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let callback_id = parsed["callbackId"].as_str().unwrap_or("");
+        dispatch_callback(&webview_for_locale, callback_id, &serde_json::json!(locale_info::detect()));
     });
 
+    // Set up getAppearance handler - current color scheme, accent color and contrast
+    let appearance = Rc::new(RefCell::new(appearance::Appearance::default()));
+    let appearance_for_handler = appearance.clone();
+    let webview_for_appearance = webview.clone();
+    content_manager.connect_script_message_received(Some("getAppearance"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let callback_id = parsed["callbackId"].as_str().unwrap_or("");
+        let current = serde_json::json!(*appearance_for_handler.borrow());
+        dispatch_callback(&webview_for_appearance, callback_id, &current);
+    });
+
+    // Tell the frontend when the desktop switches dark/light mode, accent color or contrast
+    let webview_for_appearance_events = webview.clone();
+    appearance::watch(appearance, Rc::new(move |appearance| {
+        dispatch_event(&webview_for_appearance_events, "appearanceChanged", &serde_json::json!(appearance));
+    }));
+
     // Set up setInputRegion handler for click-through control
     let window_for_input = window.clone();
+    let webview_for_input = webview.clone();
+    let input_syncing = Rc::new(Cell::new(false));
     content_manager.connect_script_message_received(Some("setInputRegion"), move |_manager, js_value| {
         if let Some(json_str) = js_value.to_json(0) {
             if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) {
                 let mode = parsed["mode"].as_str().unwrap_or("full");
 
-                if let Some(surface) = window_for_input.surface() {
-                    match mode {
-                        "character" => {
-                            // Set input region to only the character area
-                            let x = parsed["x"].as_i64().unwrap_or(0) as i32;
-                            let y = parsed["y"].as_i64().unwrap_or(0) as i32;
-                            let width = parsed["width"].as_i64().unwrap_or(160) as i32;
-                            let height = parsed["height"].as_i64().unwrap_or(380) as i32;
-
-                            let region = Region::create_rectangle(&RectangleInt::new(x, y, width, height));
-                            surface.set_input_region(&region);
-                            debug_log!("[INPUT_REGION] Set to character area: x={}, y={}, w={}, h={}", x, y, width, height);
-                        }
-                        "full" | _ => {
-                            // Clear input region - accept input on entire window
-                            // Create a region covering the full window
-                            let width = window_for_input.width();
-                            let height = window_for_input.height();
-                            let region = Region::create_rectangle(&RectangleInt::new(0, 0, width, height));
-                            surface.set_input_region(&region);
-                            debug_log!("[INPUT_REGION] Set to full window: w={}, h={}", width, height);
-                        }
+                // "auto" without rects comes from the frontend and starts syncing from the
+                // layout; with rects it's the sync script reporting (see input_region.rs)
+                let syncing = mode == "auto";
+                let report = syncing && parsed.get("rects").is_some();
+                if report && !input_syncing.get() {
+                    // Measured before syncing stopped
+                    return;
+                }
+                if syncing && !report {
+                    // Also after a reload, which leaves a fresh script that isn't syncing
+                    input_syncing.set(true);
+                    input_region::set_syncing(&webview_for_input, true);
+                    debug_log!("[INPUT_REGION] Syncing from the layout");
+                    return;
+                }
+                if !syncing && input_syncing.replace(false) {
+                    input_region::set_syncing(&webview_for_input, false);
+                    debug_log!("[INPUT_REGION] Stopped syncing from the layout");
+                }
+
+                match mode {
+                    "character" | "auto" => {
+                        // Set input region to the interactive areas: `rects`, or the
+                        // single rectangle older frontends send
+                        let rects: Vec<RectangleInt> = match parsed["rects"].as_array() {
+                            Some(rects) => rects.iter().filter_map(input_rect).collect(),
+                            None => input_rect(&parsed).into_iter().collect(),
+                        };
+                        let region = Region::create_rectangles(&rects);
+                        input_region::apply(&window_for_input, &region);
+                        debug_log!("[INPUT_REGION] Set to {} area(s): {:?}", rects.len(), rects);
+                    }
+                    "full" | _ => {
+                        // Clear input region - accept input on entire window
+                        // Create a region covering the full window
+                        let width = window_for_input.width();
+                        let height = window_for_input.height();
+                        let region = Region::create_rectangle(&RectangleInt::new(0, 0, width, height));
+                        input_region::apply(&window_for_input, &region);
+                        debug_log!("[INPUT_REGION] Set to full window: w={}, h={}", width, height);
                     }
                 }
             }
@@ -988,19 +3334,22 @@ fn create_webview_with_handlers(
     content_manager.connect_script_message_received(Some("showNotification"), move |_manager, js_value| {
         if let Some(json_str) = js_value.to_json(0) {
             if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) {
-                let title = parsed["title"].as_str().unwrap_or("Desktop Waifu");
+                let app_name = tr!("app-name");
+                let title = parsed["title"].as_str().unwrap_or(&app_name);
                 let body = parsed["body"].as_str().unwrap_or("");
+                notifications::show(title, body);
+            }
+        }
+    });
 
-                debug_log!("[NOTIFICATION] Showing notification: title={}, body={}", title, body);
-
-                // Show desktop notification via D-Bus (Linux) or native APIs (macOS/Windows)
-                if let Err(e) = notify_rust::Notification::new()
-                    .summary(title)
-                    .body(body)
-                    .appname("Desktop Waifu")
-                    .show()
-                {
-                    tracing::warn!("Failed to show notification: {}", e);
+    // Set up playSound handler for chimes and sound effects
+    content_manager.connect_script_message_received(Some("playSound"), move |_manager, js_value| {
+        if let Some(json_str) = js_value.to_json(0) {
+            if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) {
+                let name = parsed["name"].as_str().unwrap_or("");
+                let volume = parsed["volume"].as_f64().unwrap_or(1.0);
+                if let Err(e) = sound::play(name, volume) {
+                    tracing::warn!("playSound failed: {}", e);
                 }
             }
         }
@@ -1009,6 +3358,7 @@ fn create_webview_with_handlers(
     // Set up openFileDialog handler for native file picker
     let window_for_file = window.clone();
     let webview_for_file = webview.clone();
+    let transfers_for_file = transfers.clone();
     content_manager.connect_script_message_received(Some("openFileDialog"), move |_manager, js_value| {
         if let Some(json_str) = js_value.to_json(0) {
             if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) {
@@ -1046,76 +3396,50 @@ fn create_webview_with_handlers(
                 let callback_id_clone = callback_id.clone();
                 let window_for_dialog = window_for_file.clone();
                 let window_for_restore = window_for_file.clone();
+                let transfers = transfers_for_file.clone();
 
                 dialog.open_multiple(
                     Some(&window_for_dialog),
                     None::<&gio::Cancellable>,
                     move |result| {
                         // Restore overlay layer
-                        window_for_restore.set_layer(Layer::Overlay);
-                        debug_log!("[FILE_DIALOG] Restored layer to Overlay");
+                        window_for_restore.set_layer(compositor::resting_layer());
+                        debug_log!("[FILE_DIALOG] Restored layer");
 
                         match result {
                             Ok(files) => {
-                                let mut file_data: Vec<serde_json::Value> = Vec::new();
-
-                                for i in 0..files.n_items() {
-                                    if let Some(obj) = files.item(i) {
-                                        if let Ok(file) = obj.downcast::<gio::File>() {
-                                            if let Some(path) = file.path() {
-                                                // Read file contents
-                                                if let Ok(contents) = std::fs::read(&path) {
-                                                    // Determine MIME type from extension
-                                                    let mime_type = path.extension()
-                                                        .and_then(|ext| ext.to_str())
-                                                        .map(|ext| match ext.to_lowercase().as_str() {
-                                                            "png" => "image/png",
-                                                            "jpg" | "jpeg" => "image/jpeg",
-                                                            "gif" => "image/gif",
-                                                            "webp" => "image/webp",
-                                                            _ => "image/png",
-                                                        })
-                                                        .unwrap_or("image/png");
-
-                                                    // Base64 encode
-                                                    use base64::Engine;
-                                                    let base64_data = base64::engine::general_purpose::STANDARD.encode(&contents);
-
-                                                    // Get filename
-                                                    let filename = path.file_name()
-                                                        .and_then(|n| n.to_str())
-                                                        .unwrap_or("image")
-                                                        .to_string();
-
-                                                    file_data.push(serde_json::json!({
-                                                        "data": base64_data,
-                                                        "mimeType": mime_type,
-                                                        "filename": filename
-                                                    }));
-
-                                                    debug_log!("[FILE_DIALOG] Read file: {}, size={}, mime={}", filename, contents.len(), mime_type);
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-
-                                // Send result to JavaScript
-                                let result_json = serde_json::to_string(&file_data).unwrap_or("[]".to_string());
-                                let js = format!(
-                                    r#"window.__commandCallbacks && window.__commandCallbacks['{}'] && window.__commandCallbacks['{}']({})"#,
-                                    callback_id_clone, callback_id_clone, result_json
-                                );
-                                webview.evaluate_javascript(&js, None, None, None::<&gio::Cancellable>, |_| {});
+                                let paths: Vec<std::path::PathBuf> = (0..files.n_items())
+                                    .filter_map(|i| files.item(i))
+                                    .filter_map(|obj| obj.downcast::<gio::File>().ok())
+                                    .filter_map(|file| file.path())
+                                    .collect();
+
+                                // The frontend fetches each file through a transfer URL, so
+                                // nothing is read or encoded here on the main thread
+                                let file_data: Vec<serde_json::Value> = paths
+                                    .into_iter()
+                                    .filter_map(|path| {
+                                        let size = std::fs::metadata(&path).ok()?.len();
+                                        let filename = path.file_name()
+                                            .and_then(|n| n.to_str())
+                                            .unwrap_or("image")
+                                            .to_string();
+                                        let mime_type = image_mime_type(&path);
+                                        debug_log!("[FILE_DIALOG] Selected file: {}, size={}, mime={}", filename, size, mime_type);
+                                        Some(serde_json::json!({
+                                            "url": transfers.register_file(path, mime_type),
+                                            "mimeType": mime_type,
+                                            "filename": filename,
+                                            "size": size,
+                                        }))
+                                    })
+                                    .collect();
+                                dispatch_callback(&webview, &callback_id_clone, &serde_json::json!(file_data));
                             }
                             Err(e) => {
                                 // Dialog was cancelled or error occurred
                                 debug_log!("[FILE_DIALOG] Dialog cancelled or error: {}", e);
-                                let js = format!(
-                                    r#"window.__commandCallbacks && window.__commandCallbacks['{}'] && window.__commandCallbacks['{}'](null)"#,
-                                    callback_id_clone, callback_id_clone
-                                );
-                                webview.evaluate_javascript(&js, None, None, None::<&gio::Cancellable>, |_| {});
+                                dispatch_callback(&webview, &callback_id_clone, &serde_json::Value::Null);
                             }
                         }
                     },
@@ -1124,68 +3448,598 @@ fn create_webview_with_handlers(
         }
     });
 
-    // Set up saveFile handler for exporting conversations
+    // Set up saveFile handler for exporting conversations. With the confirm dialog on, the
+    // user sees the path and a diff before anything is written.
     let webview_for_save = webview.clone();
+    let window_for_save = window.clone();
     content_manager.connect_script_message_received(Some("saveFile"), move |_manager, js_value| {
-        if let Some(json_str) = js_value.to_json(0) {
-            if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) {
-                let path = parsed["path"].as_str().unwrap_or("").to_string();
-                let content = parsed["content"].as_str().unwrap_or("").to_string();
-                let callback_id = parsed["callbackId"].as_str().unwrap_or("").to_string();
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let path = parsed["path"].as_str().unwrap_or("").to_string();
+        let content = parsed["content"].as_str().unwrap_or("").to_string();
+        let callback_id = parsed["callbackId"].as_str().unwrap_or("").to_string();
+
+        if path.is_empty() {
+            return;
+        }
+
+        // Expand ~ to home directory
+        let expanded_path = if path.starts_with("~/") {
+            if let Ok(home) = std::env::var("HOME") {
+                path.replacen("~", &home, 1)
+            } else {
+                path.clone()
+            }
+        } else {
+            path.clone()
+        };
+
+        let prompt = confirm::enabled()
+            .then(|| confirm::write_prompt(std::path::Path::new(&expanded_path), &content, false));
+        let save = move || {
+            // Create parent directories if needed
+            if let Some(parent) = std::path::Path::new(&expanded_path).parent() {
+                let _ = std::fs::create_dir_all(parent);
+            }
+
+            // Write file
+            let written = std::fs::write(&expanded_path, &content);
+            audit::result(audit::Kind::FileWrite, &expanded_path, &written);
+            match written {
+                Ok(_) => serde_json::json!({ "success": true, "error": "" }),
+                Err(e) => serde_json::json!({ "success": false, "error": e.to_string() }),
+            }
+        };
+        let Some(prompt) = prompt else {
+            spawn_with_callback(&webview_for_save, callback_id, save);
+            return;
+        };
+        let webview = webview_for_save.clone();
+        confirm::ask(&window_for_save, prompt, move |approved| {
+            if approved {
+                spawn_with_callback(&webview, callback_id, save);
+            } else {
+                dispatch_callback(
+                    &webview,
+                    &callback_id,
+                    &serde_json::json!({ "success": false, "error": confirm::DECLINED }),
+                );
+            }
+        });
+    });
+
+    // Set up getAuditLog handler - { offset, limit } entries of the audit log, newest first
+    let webview_for_audit = webview.clone();
+    content_manager.connect_script_message_received(Some("getAuditLog"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let callback_id = parsed["callbackId"].as_str().unwrap_or("").to_string();
+        let offset = parsed["offset"].as_u64().unwrap_or(0) as usize;
+        let limit = parsed["limit"].as_u64().unwrap_or(100) as usize;
+        spawn_with_callback(&webview_for_audit, callback_id, move || {
+            serde_json::json!(audit::read(offset, limit))
+        });
+    });
 
-                if path.is_empty() {
+    // Frontend updates from [frontend] update_url: checkFrontendUpdate answers with
+    // { current, available, files, bytes }, applyFrontendUpdate installs the update, answers
+    // { version } and reloads the page from the new files; both answer { error } on failure
+    for name in ["checkFrontendUpdate", "applyFrontendUpdate"] {
+        let webview_for_update = webview.clone();
+        content_manager.connect_script_message_received(Some(name), move |_manager, js_value| {
+            let Some(json_str) = js_value.to_json(0) else { return };
+            let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+            let callback_id = parsed["callbackId"].as_str().unwrap_or("").to_string();
+            let source = match (config::current().frontend.update_url.clone(), server::root()) {
+                (None, _) => Err(frontend::UpdateError::NoSource),
+                (_, None) => Err(frontend::UpdateError::DevServer),
+                (Some(url), Some(dist)) => Ok((url, dist)),
+            };
+            let (url, dist) = match source {
+                Ok(source) => source,
+                Err(e) => {
+                    dispatch_callback(&webview_for_update, &callback_id, &serde_json::json!({ "error": e.to_string() }));
                     return;
                 }
+            };
+            let task = runtime::spawn(async move {
+                if name == "checkFrontendUpdate" {
+                    frontend::check(&url, dist).await.map(|info| (serde_json::json!(info), None))
+                } else {
+                    frontend::apply(&url, dist).await.map(|target| {
+                        let version = frontend::read_manifest(&target).map(|m| m.version).unwrap_or_default();
+                        (serde_json::json!({ "version": version }), Some(target))
+                    })
+                }
+            });
+            let webview = webview_for_update.clone();
+            glib::spawn_future_local(async move {
+                let result = match task.await {
+                    Ok(Ok((value, target))) => {
+                        if let Some(target) = target {
+                            match server::set_root(&target) {
+                                Ok(()) => {
+                                    // Let the page hear back before it's replaced
+                                    let webview = webview.clone();
+                                    glib::timeout_add_local_once(Duration::from_millis(300), move || {
+                                        webview.reload_bypass_cache();
+                                    });
+                                }
+                                Err(e) => tracing::warn!("Failed to serve the updated frontend: {}", e),
+                            }
+                        }
+                        value
+                    }
+                    Ok(Err(e)) => {
+                        tracing::warn!("{} failed: {}", name, e);
+                        serde_json::json!({ "error": e.to_string() })
+                    }
+                    Err(e) => serde_json::json!({ "error": e.to_string() }),
+                };
+                dispatch_callback(&webview, &callback_id, &result);
+            });
+        });
+    }
 
-                let (tx, rx) = std::sync::mpsc::channel::<String>();
+    // File reading, each answered with its result or { error }: readFile { path } with
+    // { path, content, encoding, size }, listDir { path } with entries, statPath { path }
+    // with one entry
+    for name in ["readFile", "listDir", "statPath"] {
+        let webview_for_files = webview.clone();
+        content_manager.connect_script_message_received(Some(name), move |_manager, js_value| {
+            let Some(json_str) = js_value.to_json(0) else { return };
+            let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+            let callback_id = parsed["callbackId"].as_str().unwrap_or("").to_string();
+            let path = parsed["path"].as_str().unwrap_or("").to_string();
+            spawn_with_callback(&webview_for_files, callback_id, move || {
+                let result = match name {
+                    "readFile" => files::read_file(&path, files::MAX_READ).map(|f| serde_json::json!(f)),
+                    "listDir" => files::list_dir(&path).map(|entries| serde_json::json!(entries)),
+                    _ => files::stat(&path).map(|info| serde_json::json!(info)),
+                };
+                result.unwrap_or_else(|e| serde_json::json!({ "error": e.to_string() }))
+            });
+        });
+    }
 
-                std::thread::spawn(move || {
-                    // Expand ~ to home directory
-                    let expanded_path = if path.starts_with("~/") {
-                        if let Ok(home) = std::env::var("HOME") {
-                            path.replacen("~", &home, 1)
-                        } else {
-                            path.clone()
-                        }
-                    } else {
-                        path.clone()
-                    };
+    // watchPath { path } answers { watchId } and then sends a "pathChanged" event
+    // ({ watchId, path, change }) per change; unwatchPath { watchId } answers { found }
+    let webview_for_watch = webview.clone();
+    content_manager.connect_script_message_received(Some("watchPath"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let callback_id = parsed["callbackId"].as_str().unwrap_or("").to_string();
+        let webview = webview_for_watch.clone();
+        let result = files::watch(parsed["path"].as_str().unwrap_or(""), move |change| {
+            dispatch_event(&webview, "pathChanged", &serde_json::json!(change));
+        });
+        let result = match result {
+            Ok(id) => serde_json::json!({ "watchId": id }),
+            Err(e) => serde_json::json!({ "error": e.to_string() }),
+        };
+        dispatch_callback(&webview_for_watch, &callback_id, &result);
+    });
+
+    let webview_for_unwatch = webview.clone();
+    content_manager.connect_script_message_received(Some("unwatchPath"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let callback_id = parsed["callbackId"].as_str().unwrap_or("").to_string();
+        let found = files::unwatch(parsed["watchId"].as_u64().unwrap_or(0));
+        dispatch_callback(&webview_for_unwatch, &callback_id, &serde_json::json!({ "found": found }));
+    });
+
+    // Folder access: { action: list | allow | disallow, dir, callbackId }. list answers
+    // { dirs }, allow { dir } (as saved), disallow { found }. The frontend asks the user
+    // before allowing; with the confirmation dialog on, the overlay asks instead.
+    let webview_for_file_access = webview.clone();
+    let window_for_file_access = window.clone();
+    content_manager.connect_script_message_received(Some("fileAccess"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let callback_id = parsed["callbackId"].as_str().unwrap_or("").to_string();
+        let action = parsed["action"].as_str().unwrap_or("").to_string();
+        let dir = parsed["dir"].as_str().unwrap_or("").to_string();
+        let answer = move || {
+            let result = match action.as_str() {
+                "list" => Ok(serde_json::json!({ "dirs": files::allowed_dirs() })),
+                "allow" => files::allow(&dir).map(|dir| serde_json::json!({ "dir": dir })),
+                "disallow" => files::disallow(&dir).map(|found| serde_json::json!({ "found": found })),
+                _ => return serde_json::json!({ "error": format!("Unknown fileAccess action '{}'", action) }),
+            };
+            result.unwrap_or_else(|e| serde_json::json!({ "error": e.to_string() }))
+        };
+        if parsed["action"] != "allow" || !confirm::enabled() {
+            spawn_with_callback(&webview_for_file_access, callback_id, answer);
+            return;
+        }
+        let prompt = confirm::read_dir_prompt(parsed["dir"].as_str().unwrap_or(""));
+        let webview = webview_for_file_access.clone();
+        confirm::ask(&window_for_file_access, prompt, move |approved| {
+            if approved {
+                spawn_with_callback(&webview, callback_id, answer);
+            } else {
+                dispatch_callback(&webview, &callback_id, &serde_json::json!({ "error": confirm::DECLINED }));
+            }
+        });
+    });
+
+    // Clipboard text through GDK, answered as {text} (null when the clipboard holds no text)
+    let webview_for_clipboard_read = webview.clone();
+    content_manager.connect_script_message_received(Some("readClipboard"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let Some(callback_id) = parsed["callbackId"].as_str().map(str::to_string) else { return };
+        let webview = webview_for_clipboard_read.clone();
+        let clipboard = webview.clipboard();
+        glib::spawn_future_local(async move {
+            let result = match clipboard.read_text_future().await {
+                Ok(text) => serde_json::json!({ "text": text.map(|t| t.to_string()) }),
+                Err(e) => serde_json::json!({ "error": e.to_string() }),
+            };
+            dispatch_callback(&webview, &callback_id, &result);
+        });
+    });
+
+    let webview_for_clipboard_write = webview.clone();
+    content_manager.connect_script_message_received(Some("writeClipboard"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let Some(callback_id) = parsed["callbackId"].as_str() else { return };
+        let result = match parsed["text"].as_str() {
+            Some(text) => {
+                webview_for_clipboard_write.clipboard().set_text(text);
+                debug_log!("[CLIPBOARD] Copied {} bytes", text.len());
+                serde_json::json!({ "success": true })
+            }
+            None => serde_json::json!({ "error": "No text to copy" }),
+        };
+        dispatch_callback(&webview_for_clipboard_write, callback_id, &result);
+    });
+
+    // Screenshot for the LLM, answered as {mimeType, data} (base64), {cancelled} or {error}.
+    // The overlay turns transparent while the whole screen is captured so it isn't in the
+    // picture; unmapping it would report a visibility change to subscribers.
+    let window_for_capture = window.clone();
+    let webview_for_capture = webview.clone();
+    content_manager.connect_script_message_received(Some("captureScreen"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let Some(callback_id) = parsed["callbackId"].as_str().map(str::to_string) else { return };
+        let region = parsed["region"].as_bool().unwrap_or(false);
+        let window = window_for_capture.clone();
+        let webview = webview_for_capture.clone();
+        glib::spawn_future_local(async move {
+            if region {
+                // Keep the overlay out of the way of the compositor's selection UI
+                window.set_layer(Layer::Bottom);
+            } else {
+                window.set_opacity(0.0);
+                // Let the compositor draw a frame without the overlay first
+                glib::timeout_future(Duration::from_millis(150)).await;
+            }
+            let capture = screenshot::capture(region).await;
+            if region {
+                window.set_layer(compositor::resting_layer());
+            } else {
+                window.set_opacity(1.0);
+            }
 
-                    // Create parent directories if needed
-                    if let Some(parent) = std::path::Path::new(&expanded_path).parent() {
-                        let _ = std::fs::create_dir_all(parent);
+            let result = match capture {
+                Ok(path) => {
+                    let mime_type = image_mime_type(&path);
+                    // Encode off the main thread; a 4K PNG is several megabytes
+                    let encoded = gio::spawn_blocking(move || {
+                        std::fs::read(&path).map(|bytes| glib::base64_encode(&bytes).to_string())
+                    })
+                    .await;
+                    match encoded {
+                        Ok(Ok(data)) => serde_json::json!({ "mimeType": mime_type, "data": data }),
+                        Ok(Err(e)) => serde_json::json!({ "error": e.to_string() }),
+                        Err(_) => serde_json::json!({ "error": "Encoding the screenshot failed" }),
                     }
+                }
+                Err(screenshot::ScreenshotError::Cancelled) => serde_json::json!({ "cancelled": true }),
+                Err(e) => {
+                    tracing::warn!("Screen capture failed: {}", e);
+                    serde_json::json!({ "error": e.to_string() })
+                }
+            };
+            dispatch_callback(&webview, &callback_id, &result);
+        });
+    });
 
-                    // Write file
-                    let result = std::fs::write(&expanded_path, &content);
-                    let (success, error) = match result {
-                        Ok(_) => (true, String::new()),
-                        Err(e) => (false, e.to_string()),
-                    };
+    // Speak through the TTS engine, streaming lip-sync tracks to the character as each
+    // sentence starts. The callback (if any) fires once speech ends: {done, interrupted} or
+    // {error}.
+    let webview_for_speak = webview.clone();
+    content_manager.connect_script_message_received(Some("speak"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let text = parsed["text"].as_str().unwrap_or("").to_string();
+        let voice = parsed["voice"].as_str().filter(|v| !v.is_empty()).map(str::to_string);
+        let callback_id = parsed["callbackId"].as_str().map(str::to_string);
+        let webview = webview_for_speak.clone();
+
+        if presence::is_quiet() {
+            if let Some(callback_id) = callback_id {
+                dispatch_callback(&webview, &callback_id, &serde_json::json!({ "error": "Meeting mode is on" }));
+            }
+            return;
+        }
 
-                    let error_escaped = error.replace('\\', "\\\\").replace('`', "\\`");
-                    let js = format!(
-                        r#"window.__commandCallbacks && window.__commandCallbacks['{}'] && window.__commandCallbacks['{}']( {{ success: {}, error: `{}` }} )"#,
-                        callback_id, callback_id, success, error_escaped
-                    );
-                    let _ = tx.send(js);
-                });
+        let events = tts::speak(text, voice);
+        glib::spawn_future_local(async move {
+            dispatch_event(&webview, "setTalking", &serde_json::json!({ "talking": true }));
+            let mut result = serde_json::json!({ "done": true, "interrupted": false });
+            while let Ok(event) = events.recv().await {
+                match event {
+                    tts::SpeechEvent::Visemes(track) => {
+                        dispatch_event(&webview, "speechVisemes", &serde_json::json!(track));
+                    }
+                    tts::SpeechEvent::Finished { interrupted } => {
+                        result = serde_json::json!({ "done": true, "interrupted": interrupted });
+                    }
+                    tts::SpeechEvent::Failed(e) => {
+                        tracing::warn!("Speech failed: {}", e);
+                        result = serde_json::json!({ "error": e.to_string() });
+                    }
+                }
+            }
+            // An interrupted speech was either stopped (which ends the talking itself) or
+            // replaced by one that is still talking
+            if result["interrupted"] != true {
+                dispatch_event(&webview, "setTalking", &serde_json::json!({ "talking": false }));
+            }
+            if let Some(callback_id) = callback_id {
+                dispatch_callback(&webview, &callback_id, &result);
+            }
+        });
+    });
 
-                // Poll for result on main thread
-                let webview = webview_for_save.clone();
-                glib::timeout_add_local(Duration::from_millis(10), move || {
-                    match rx.try_recv() {
-                        Ok(js) => {
-                            webview.evaluate_javascript(&js, None, None, None::<&gio::Cancellable>, |_| {});
-                            glib::ControlFlow::Break
-                        }
-                        Err(std::sync::mpsc::TryRecvError::Empty) => glib::ControlFlow::Continue,
-                        Err(std::sync::mpsc::TryRecvError::Disconnected) => glib::ControlFlow::Break,
+    let webview_for_stop_speaking = webview.clone();
+    content_manager.connect_script_message_received(Some("stopSpeaking"), move |_manager, _js_value| {
+        stop_speaking(&webview_for_stop_speaking);
+    });
+
+    // Streams the reply as "llmToken" events ({ requestId, text }); the callback gets
+    // { text }, { cancelled: true } or { error } once the request ends
+    let webview_for_llm = webview.clone();
+    content_manager.connect_script_message_received(Some("llmChat"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let request_id = parsed["requestId"].as_str().unwrap_or("").to_string();
+        let callback_id = parsed["callbackId"].as_str().unwrap_or("").to_string();
+        let webview = webview_for_llm.clone();
+
+        let request = match serde_json::from_value::<llm::ChatRequest>(parsed) {
+            Ok(request) => request,
+            Err(e) => {
+                dispatch_callback(&webview, &callback_id, &serde_json::json!({ "error": format!("Invalid chat request: {}", e) }));
+                return;
+            }
+        };
+        debug_log!("[LLM] {} request {} ({} messages)", request.provider.name(), request_id, request.messages.len());
+
+        let events = llm::chat(request_id.clone(), request);
+        glib::spawn_future_local(async move {
+            let mut result = serde_json::json!({ "cancelled": true });
+            while let Ok(event) = events.recv().await {
+                match event {
+                    llm::ChatEvent::Token(text) => {
+                        dispatch_event(&webview, "llmToken", &serde_json::json!({ "requestId": request_id, "text": text }));
                     }
-                });
+                    llm::ChatEvent::Done(reply) => result = serde_json::json!(reply),
+                    llm::ChatEvent::Cancelled => result = serde_json::json!({ "cancelled": true }),
+                    llm::ChatEvent::Failed(e) => {
+                        tracing::warn!("Chat request failed: {}", e);
+                        result = serde_json::json!({ "error": e.to_string() });
+                    }
+                }
             }
+            dispatch_callback(&webview, &callback_id, &result);
+        });
+    });
+
+    content_manager.connect_script_message_received(Some("llmCancel"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        llm::cancel(parsed["requestId"].as_str().unwrap_or(""));
+    });
+
+    content_manager.connect_script_message_received(Some("llmSetKey"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        match serde_json::from_value::<llm::Provider>(parsed["provider"].clone()) {
+            Ok(provider) => llm::set_key(provider, parsed["key"].as_str().unwrap_or("")),
+            Err(e) => tracing::warn!("llmSetKey: unknown provider: {}", e),
         }
     });
 
+    // Keyring access: each replies {} (getSecret: { value }, null if unset) or { error }
+    for handler in ["storeSecret", "getSecret", "deleteSecret"] {
+        let webview_for_secret = webview.clone();
+        content_manager.connect_script_message_received(Some(handler), move |_manager, js_value| {
+            let Some(json_str) = js_value.to_json(0) else { return };
+            let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+            let callback_id = parsed["callbackId"].as_str().unwrap_or("").to_string();
+            let name = parsed["name"].as_str().unwrap_or("").to_string();
+            let value = parsed["value"].as_str().unwrap_or("").to_string();
+            if name.is_empty() {
+                dispatch_callback(&webview_for_secret, &callback_id, &serde_json::json!({ "error": "Missing secret name" }));
+                return;
+            }
+            let task = runtime::spawn_blocking(move || match handler {
+                "storeSecret" => secrets::store(&name, &value).map(|()| serde_json::json!({})),
+                "getSecret" => secrets::get(&name).map(|value| serde_json::json!({ "value": value })),
+                _ => secrets::delete(&name).map(|()| serde_json::json!({})),
+            });
+            let webview = webview_for_secret.clone();
+            glib::spawn_future_local(async move {
+                let result = match task.await {
+                    Ok(Ok(result)) => result,
+                    Ok(Err(e)) => {
+                        tracing::warn!("{} failed: {}", handler, e);
+                        serde_json::json!({ "error": e.to_string() })
+                    }
+                    Err(e) => serde_json::json!({ "error": e.to_string() }),
+                };
+                dispatch_callback(&webview, &callback_id, &result);
+            });
+        });
+    }
+
+    // Conversation history: { action, ..., callbackId }, answered with the action's result or { error }
+    let webview_for_history = webview.clone();
+    content_manager.connect_script_message_received(Some("history"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let callback_id = parsed["callbackId"].as_str().unwrap_or("").to_string();
+        spawn_with_callback(&webview_for_history, callback_id, move || {
+            let action = parsed["action"].as_str().unwrap_or("");
+            let id = parsed["id"].as_i64().unwrap_or(0);
+            let str_arg = |key: &str| parsed[key].as_str().unwrap_or("").to_string();
+            let result = match action {
+                "listConversations" => storage::list_conversations().map(|c| serde_json::json!(c)),
+                "createConversation" => storage::create_conversation(&str_arg("title")).map(|c| serde_json::json!(c)),
+                "renameConversation" => storage::rename_conversation(id, &str_arg("title"))
+                    .map(|found| serde_json::json!({ "found": found })),
+                "deleteConversation" => storage::delete_conversation(id).map(|found| serde_json::json!({ "found": found })),
+                "getMessages" => storage::messages(id).map(|m| serde_json::json!(m)),
+                "saveMessage" => match serde_json::from_value::<storage::Message>(parsed["message"].clone()) {
+                    Ok(message) => storage::save_message(id, &message).map(|()| serde_json::json!({})),
+                    Err(e) => return serde_json::json!({ "error": format!("Invalid message: {}", e) }),
+                },
+                "updateMessage" => storage::update_message(&str_arg("messageId"), &str_arg("content"))
+                    .map(|found| serde_json::json!({ "found": found })),
+                "deleteMessages" => {
+                    let ids: Vec<String> = serde_json::from_value(parsed["messageIds"].clone()).unwrap_or_default();
+                    storage::delete_messages(&ids).map(|deleted| serde_json::json!({ "deleted": deleted }))
+                }
+                "addAttachment" => match serde_json::from_value::<storage::Attachment>(parsed["attachment"].clone()) {
+                    Ok(attachment) => storage::add_attachment(&str_arg("messageId"), &attachment).map(|()| serde_json::json!({})),
+                    Err(e) => return serde_json::json!({ "error": format!("Invalid attachment: {}", e) }),
+                },
+                "deleteAttachment" => storage::delete_attachment(&str_arg("attachmentId"))
+                    .map(|found| serde_json::json!({ "found": found })),
+                "search" => {
+                    let limit = parsed["limit"].as_u64().map(|l| l.min(u32::MAX as u64) as u32);
+                    storage::search(&str_arg("query"), limit).map(|hits| serde_json::json!(hits))
+                }
+                _ => return serde_json::json!({ "error": format!("Unknown history action '{}'", action) }),
+            };
+            result.unwrap_or_else(|e| {
+                tracing::warn!("history {} failed: {}", action, e);
+                serde_json::json!({ "error": e.to_string() })
+            })
+        });
+    });
+
+    // Long-term memory: { action: remember | recall | forget | list, ..., callbackId }
+    // remember replies with the memory, recall and list with memories, forget with { found }
+    let webview_for_memory = webview.clone();
+    content_manager.connect_script_message_received(Some("memory"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let callback_id = parsed["callbackId"].as_str().unwrap_or("").to_string();
+        let action = parsed["action"].as_str().unwrap_or("").to_string();
+        let text = parsed["text"].as_str().unwrap_or("").to_string();
+        let id = parsed["id"].as_i64().unwrap_or(0);
+        let k = parsed["k"].as_u64().unwrap_or(5) as usize;
+        let task = runtime::spawn(async move {
+            match action.as_str() {
+                "remember" => memory::remember(&text).await.map(|m| serde_json::json!(m)),
+                "recall" => memory::recall(&text, k).await.map(|m| serde_json::json!(m)),
+                "forget" => memory::forget(id).await.map(|found| serde_json::json!({ "found": found })),
+                "list" => memory::list().await.map(|m| serde_json::json!(m)),
+                _ => Ok(serde_json::json!({ "error": format!("Unknown memory action '{}'", action) })),
+            }
+        });
+        let webview = webview_for_memory.clone();
+        glib::spawn_future_local(async move {
+            let result = match task.await {
+                Ok(Ok(result)) => result,
+                Ok(Err(e)) => serde_json::json!({ "error": e.to_string() }),
+                Err(e) => serde_json::json!({ "error": e.to_string() }),
+            };
+            dispatch_callback(&webview, &callback_id, &result);
+        });
+    });
+
+    // Character models: { action: list | import | setActive, path, id, callbackId }. list
+    // answers with the models, import and setActive with the model; both also send the
+    // events IPC imports and switches do ("modelImported", "setActiveModel")
+    let webview_for_models = webview.clone();
+    content_manager.connect_script_message_received(Some("models"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let callback_id = parsed["callbackId"].as_str().unwrap_or("").to_string();
+        let action = parsed["action"].as_str().unwrap_or("").to_string();
+        let path = std::path::PathBuf::from(parsed["path"].as_str().unwrap_or(""));
+        let id = parsed["id"].as_str().unwrap_or("").to_string();
+        let task = gio::spawn_blocking(move || match action.as_str() {
+            "list" => models::list().map(|m| (serde_json::json!(m), None)),
+            "import" => models::import(&path).map(|m| (serde_json::json!(m), Some("modelImported"))),
+            "setActive" => models::get(&id).map(|m| (serde_json::json!(m), Some("setActiveModel"))),
+            _ => Ok((serde_json::json!({ "error": format!("Unknown models action '{}'", action) }), None)),
+        });
+        let webview = webview_for_models.clone();
+        glib::spawn_future_local(async move {
+            let result = match task.await {
+                Ok(Ok((value, event))) => {
+                    if let Some(event) = event {
+                        dispatch_event(&webview, event, &value);
+                    }
+                    value
+                }
+                Ok(Err(e)) => serde_json::json!({ "error": e.to_string() }),
+                Err(_) => serde_json::json!({ "error": "Model task failed" }),
+            };
+            dispatch_callback(&webview, &callback_id, &result);
+        });
+    });
+
+    let webview_for_reminders = webview.clone();
+    content_manager.connect_script_message_received(Some("reminders"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let callback_id = parsed["callbackId"].as_str().unwrap_or("").to_string();
+        let action = parsed["action"].as_str().unwrap_or("");
+        let result = match action {
+            "list" => Ok(serde_json::json!(reminders::list())),
+            "add" => reminders::parse_duration(parsed["in"].as_str().unwrap_or(""))
+                .and_then(|delay| reminders::add(parsed["text"].as_str().unwrap_or(""), delay))
+                .map(|r| serde_json::json!(r)),
+            "cancel" => Ok(serde_json::json!({ "found": reminders::cancel(parsed["id"].as_u64().unwrap_or(0)) })),
+            _ => Err(format!("Unknown reminders action '{}'", action)),
+        };
+        let result = result.unwrap_or_else(|e| serde_json::json!({ "error": e }));
+        dispatch_callback(&webview_for_reminders, &callback_id, &result);
+    });
+
+    // Models on the local server (Ollama, llama.cpp): { url, models } or { error }
+    let webview_for_llm_models = webview.clone();
+    content_manager.connect_script_message_received(Some("llmListModels"), move |_manager, js_value| {
+        let Some(json_str) = js_value.to_json(0) else { return };
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str.as_str()) else { return };
+        let callback_id = parsed["callbackId"].as_str().unwrap_or("").to_string();
+        let task = runtime::spawn(llm::local_models());
+        let webview = webview_for_llm_models.clone();
+        glib::spawn_future_local(async move {
+            let result = match task.await {
+                Ok(Ok(server)) => serde_json::json!({ "url": server.url, "models": server.models }),
+                Ok(Err(e)) => serde_json::json!({ "error": e.to_string() }),
+                Err(e) => serde_json::json!({ "error": e.to_string() }),
+            };
+            dispatch_callback(&webview, &callback_id, &result);
+        });
+    });
+
     webview
 }
+
+/// Cut off any speech and close the character's mouth
+fn stop_speaking(webview: &WebView) {
+    tts::stop();
+    dispatch_event(webview, "setTalking", &serde_json::json!({ "talking": false }));
+}
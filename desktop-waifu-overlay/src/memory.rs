@@ -0,0 +1,247 @@
+//! Long-term memory: facts about the user that outlast a conversation
+//!
+//! Memories are short statements ("Their name is Sam", "Working on a Rust game engine")
+//! kept in the history database with an embedding of their text. Embeddings come from the
+//! local model server (Ollama's `nomic-embed-text` by default) or OpenAI, as set under
+//! `[memory]` in config.toml. `recall` ranks memories by cosine similarity to the query;
+//! memories without a comparable embedding (none could be made, or the model changed) are
+//! ranked by the share of the query's words they contain instead.
+//!
+//! The frontend reaches `remember`, `recall`, `forget` and `list` through the `memory`
+//! message handler, and `context_for` adds relevant memories to each chat request (llm.rs).
+
+use crate::config::EmbeddingSource;
+use crate::llm::{self, Provider};
+use crate::storage::{self, StorageError, StoredMemory};
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::time::Duration;
+
+const LOCAL_MODEL: &str = "nomic-embed-text";
+const OPENAI_MODEL: &str = "text-embedding-3-small";
+const OPENAI_URL: &str = "https://api.openai.com/v1/embeddings";
+/// Embedding is on the path of every chat request, so give up quickly
+const EMBED_TIMEOUT: Duration = Duration::from_secs(10);
+/// Memories less relevant than this aren't added to chat requests
+const MIN_RELEVANCE: f32 = 0.3;
+/// A new memory this similar to an existing one is taken as the same fact
+const DUPLICATE_SIMILARITY: f32 = 0.95;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MemoryError {
+    #[error("Nothing to remember")]
+    Empty,
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+    #[error("Memory task failed: {0}")]
+    Task(#[from] tokio::task::JoinError),
+}
+
+/// A memory as the frontend sees it
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Memory {
+    pub id: i64,
+    pub text: String,
+    pub created_at: i64,
+    /// How well it matches the query, from 0 to 1 (`recall` only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relevance: Option<f32>,
+}
+
+impl From<&StoredMemory> for Memory {
+    fn from(memory: &StoredMemory) -> Self {
+        Self {
+            id: memory.id,
+            text: memory.text.clone(),
+            created_at: memory.created_at,
+            relevance: None,
+        }
+    }
+}
+
+struct Embedding {
+    model: String,
+    vector: Vec<f32>,
+}
+
+/// Run a database call on a blocking thread
+async fn db<T: Send + 'static>(
+    f: impl FnOnce() -> storage::Result<T> + Send + 'static,
+) -> Result<T, MemoryError> {
+    Ok(tokio::task::spawn_blocking(f).await??)
+}
+
+/// Save a fact about the user. Remembering something already known returns the existing
+/// memory instead of a copy.
+pub async fn remember(text: &str) -> Result<Memory, MemoryError> {
+    let text = text.trim().to_string();
+    if text.is_empty() {
+        return Err(MemoryError::Empty);
+    }
+    let embedding = embed(&text).await;
+    let existing = db(storage::memories).await?;
+    let duplicate = existing.iter().find(|memory| {
+        memory.text.eq_ignore_ascii_case(&text)
+            || embedding
+                .as_ref()
+                .and_then(|e| similarity(e, memory))
+                .is_some_and(|s| s >= DUPLICATE_SIMILARITY)
+    });
+    if let Some(duplicate) = duplicate {
+        return Ok(duplicate.into());
+    }
+
+    let saved = db(move || {
+        let embedding = embedding.as_ref().map(|e| (e.model.as_str(), e.vector.as_slice()));
+        storage::insert_memory(&text, embedding)
+    })
+    .await?;
+    tracing::info!("Remembered: {}", saved.text);
+    Ok((&saved).into())
+}
+
+/// The `k` memories most relevant to `query`, best first
+pub async fn recall(query: &str, k: usize) -> Result<Vec<Memory>, MemoryError> {
+    let memories = db(storage::memories).await?;
+    if memories.is_empty() || k == 0 {
+        return Ok(Vec::new());
+    }
+    let embedding = embed(query).await;
+    let words = keywords(query);
+    let mut ranked: Vec<Memory> = memories
+        .iter()
+        .map(|memory| {
+            let relevance = embedding
+                .as_ref()
+                .and_then(|e| similarity(e, memory))
+                .unwrap_or_else(|| keyword_overlap(&words, &memory.text));
+            Memory { relevance: Some(relevance), ..memory.into() }
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.relevance.partial_cmp(&a.relevance).unwrap_or(std::cmp::Ordering::Equal));
+    ranked.truncate(k);
+    Ok(ranked)
+}
+
+/// Returns whether the memory existed
+pub async fn forget(id: i64) -> Result<bool, MemoryError> {
+    db(move || storage::delete_memory(id)).await
+}
+
+/// Every memory, oldest first
+pub async fn list() -> Result<Vec<Memory>, MemoryError> {
+    let memories = db(storage::memories).await?;
+    Ok(memories.iter().map(Memory::from).collect())
+}
+
+/// Memories relevant to `query`, worded for the system prompt; `None` if memory is off or
+/// nothing relevant is known
+pub async fn context_for(query: &str) -> Option<String> {
+    let config = crate::config::current().memory.clone();
+    if !config.enabled || query.trim().is_empty() {
+        return None;
+    }
+    let memories = match recall(query, config.recall_count).await {
+        Ok(memories) => memories,
+        Err(e) => {
+            tracing::warn!("Couldn't recall memories: {}", e);
+            return None;
+        }
+    };
+    let facts: Vec<String> = memories
+        .into_iter()
+        .filter(|m| m.relevance.unwrap_or(0.0) >= MIN_RELEVANCE)
+        .map(|m| format!("- {}", m.text))
+        .collect();
+    if facts.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "What you remember about the user from earlier conversations (bring it up only when it's relevant):\n{}",
+        facts.join("\n")
+    ))
+}
+
+/// Cosine similarity, if the memory was embedded with the same model
+fn similarity(embedding: &Embedding, memory: &StoredMemory) -> Option<f32> {
+    let (model, vector) = memory.embedding.as_ref()?;
+    if *model != embedding.model || vector.len() != embedding.vector.len() {
+        return None;
+    }
+    let dot: f32 = vector.iter().zip(&embedding.vector).map(|(a, b)| a * b).sum();
+    let norms = vector.iter().map(|x| x * x).sum::<f32>().sqrt()
+        * embedding.vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    (norms > 0.0).then(|| dot / norms)
+}
+
+/// Lowercased words of three or more letters; shorter ones are mostly "a", "is", "to"
+fn keywords(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 3)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// The share of `words` that appear in `text`
+fn keyword_overlap(words: &[String], text: &str) -> f32 {
+    if words.is_empty() {
+        return 0.0;
+    }
+    let text = keywords(text);
+    let found = words.iter().filter(|word| text.contains(word)).count();
+    found as f32 / words.len() as f32
+}
+
+/// Embed `text` with the configured source; `None` if there is none or it failed, in which
+/// case memories fall back to keyword matching
+async fn embed(text: &str) -> Option<Embedding> {
+    let config = crate::config::current().memory.clone();
+    let (url, key, model) = match config.embeddings {
+        EmbeddingSource::None => return None,
+        EmbeddingSource::Local => {
+            let server = llm::local_models().await.ok()?;
+            (format!("{}/embeddings", server.url), None, config.model.unwrap_or_else(|| LOCAL_MODEL.into()))
+        }
+        EmbeddingSource::OpenAI => {
+            let key = llm::api_key(Provider::OpenAI).await?;
+            (OPENAI_URL.to_string(), Some(key), config.model.unwrap_or_else(|| OPENAI_MODEL.into()))
+        }
+        EmbeddingSource::Auto => {
+            let local_model = config.model.clone().unwrap_or_else(|| LOCAL_MODEL.into());
+            let local = llm::local_models().await.ok().filter(|server| {
+                // Ollama lists models with their tag ("nomic-embed-text:latest")
+                server.models.iter().any(|m| *m == local_model || m.strip_suffix(":latest") == Some(&local_model))
+            });
+            match local {
+                Some(server) => (format!("{}/embeddings", server.url), None, local_model),
+                None => {
+                    let key = llm::api_key(Provider::OpenAI).await?;
+                    (OPENAI_URL.to_string(), Some(key), config.model.unwrap_or_else(|| OPENAI_MODEL.into()))
+                }
+            }
+        }
+    };
+
+    let mut request = reqwest::Client::new()
+        .post(&url)
+        .timeout(EMBED_TIMEOUT)
+        .json(&json!({ "model": model, "input": text }));
+    if let Some(key) = key {
+        request = request.bearer_auth(key);
+    }
+    let response = match request.send().await.and_then(|r| r.error_for_status()) {
+        Ok(response) => response,
+        Err(e) => {
+            tracing::warn!("Embedding with {} failed: {}", model, e);
+            return None;
+        }
+    };
+    let body: Value = response.json().await.ok()?;
+    let vector: Vec<f32> = body["data"][0]["embedding"]
+        .as_array()?
+        .iter()
+        .filter_map(|x| x.as_f64().map(|x| x as f32))
+        .collect();
+    (!vector.is_empty()).then_some(Embedding { model, vector })
+}
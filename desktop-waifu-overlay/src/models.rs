@@ -0,0 +1,254 @@
+//! Character models the user added (`~/.local/share/desktop-waifu/models/*.vrm`)
+//!
+//! The built-in characters ship with the frontend; these are found at runtime. `import`
+//! checks that a file is a VRM the frontend can show (vrm.rs) before copying it in, and
+//! `list` reads each model's metadata the same way. The webview loads model files from
+//! `waifu-model://<file name>` and their thumbnails from `waifu-model://thumbnail/<file
+//! name>`, so switching to one (`setActiveModel` event) needs no restart.
+//!
+//! Reached through the `models` message handler, the `ListModels` / `ImportModel` /
+//! `SetActiveModel` IPC requests and the `LoadModel` command.
+
+use gtk4::{gio, glib};
+use crate::vrm::VrmError;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use webkit6::prelude::*;
+use webkit6::{URISchemeRequest, URISchemeResponse, WebView};
+
+pub const SCHEME: &str = "waifu-model";
+
+/// Largest model `import` accepts; VRMs with 4K textures run to a few hundred MB
+const MAX_MODEL_SIZE: u64 = 512 * 1024 * 1024;
+/// Thumbnail URLs are `waifu-model://thumbnail/<file name>`; file names have no slashes
+const THUMBNAIL_PREFIX: &str = "thumbnail/";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ModelError {
+    #[error("{}: {source}", .path.display())]
+    Io { path: PathBuf, source: std::io::Error },
+    #[error("{} is not a VRM model: {reason}", .path.display())]
+    Invalid { path: PathBuf, reason: String },
+    #[error("{} is too large ({size} bytes)", .path.display())]
+    TooLarge { path: PathBuf, size: u64 },
+    #[error("No model '{0}' in the models folder")]
+    NotFound(String),
+}
+
+pub type Result<T> = std::result::Result<T, ModelError>;
+
+/// A model in the models folder, as the frontend sees it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelInfo {
+    /// The file name without `.vrm`
+    pub id: String,
+    /// From the model's metadata, or the id
+    pub name: String,
+    /// 0 or 1; VRM 0.x models face away from the camera, 1.0 models towards it
+    pub vrm_version: u8,
+    pub authors: Vec<String>,
+    pub license: Option<String>,
+    pub license_url: Option<String>,
+    /// Expressions `setExpression` can show on this model
+    pub expressions: Vec<String>,
+    pub size: u64,
+    pub url: String,
+    pub thumbnail_url: Option<String>,
+}
+
+pub fn models_dir() -> PathBuf {
+    glib::user_data_dir().join("desktop-waifu").join("models")
+}
+
+/// Every valid model, by name. Files that aren't VRMs are skipped.
+pub fn list() -> Result<Vec<ModelInfo>> {
+    let dir = models_dir();
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(source) => return Err(ModelError::Io { path: dir, source }),
+    };
+    let mut models: Vec<ModelInfo> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("vrm")))
+        .filter_map(|path| match inspect(&path) {
+            Ok(model) => Some(model),
+            Err(e) => {
+                tracing::warn!("Skipping model: {}", e);
+                None
+            }
+        })
+        .collect();
+    models.sort_by_key(|model| model.name.to_lowercase());
+    Ok(models)
+}
+
+/// The model with this id
+pub fn get(id: &str) -> Result<ModelInfo> {
+    let path = model_path(id).ok_or_else(|| ModelError::NotFound(id.to_string()))?;
+    if !path.is_file() {
+        return Err(ModelError::NotFound(id.to_string()));
+    }
+    inspect(&path)
+}
+
+/// Check that `source` is a VRM and copy it into the models folder, under a new name if its
+/// own is taken. A file already in the folder is returned as it is.
+pub fn import(source: &Path) -> Result<ModelInfo> {
+    let io_error = |source_error| ModelError::Io { path: source.to_path_buf(), source: source_error };
+    let size = std::fs::metadata(source).map_err(io_error)?.len();
+    if size > MAX_MODEL_SIZE {
+        return Err(ModelError::TooLarge { path: source.to_path_buf(), size });
+    }
+    let model = inspect(source)?;
+
+    let dir = models_dir();
+    if source.parent().is_some_and(|parent| parent.canonicalize().ok() == dir.canonicalize().ok()) {
+        return Ok(model);
+    }
+    std::fs::create_dir_all(&dir).map_err(|source| ModelError::Io { path: dir.clone(), source })?;
+
+    let stem: String = source
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    let stem = if stem.is_empty() { "model".to_string() } else { stem };
+    let target = (1..)
+        .map(|n| match n {
+            1 => dir.join(format!("{}.vrm", stem)),
+            n => dir.join(format!("{}-{}.vrm", stem, n)),
+        })
+        .find(|path| !path.exists())
+        .expect("some numbered name is free");
+
+    // Copy under a name `list` ignores, so a half-copied model never shows up
+    let partial = target.with_extension("vrm.part");
+    let copied = std::fs::copy(source, &partial).and_then(|_| std::fs::rename(&partial, &target));
+    if let Err(e) = copied {
+        let _ = std::fs::remove_file(&partial);
+        return Err(ModelError::Io { path: target, source: e });
+    }
+    tracing::info!("Imported model {} as {}", source.display(), target.display());
+    inspect(&target)
+}
+
+/// Where the model with this id lives; None for ids that could name something outside the
+/// folder
+fn model_path(id: &str) -> Option<PathBuf> {
+    let valid = !id.is_empty() && !id.contains(['/', '\\']) && !id.starts_with('.');
+    valid.then(|| models_dir().join(format!("{}.vrm", id)))
+}
+
+fn model_error(path: &Path, error: VrmError) -> ModelError {
+    match error {
+        VrmError::Io(source) => ModelError::Io { path: path.to_path_buf(), source },
+        VrmError::Invalid(reason) => ModelError::Invalid { path: path.to_path_buf(), reason: reason.to_string() },
+    }
+}
+
+/// Check the model and read its metadata
+fn inspect(path: &Path) -> Result<ModelInfo> {
+    let vrm = crate::vrm::read(path).map_err(|e| model_error(path, e))?;
+    let size = std::fs::metadata(path).map_err(|source| ModelError::Io { path: path.to_path_buf(), source })?.len();
+
+    let file_name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let file_name = glib::Uri::escape_string(&file_name, None, false);
+    let id = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    Ok(ModelInfo {
+        name: vrm.meta.name.clone().unwrap_or_else(|| id.clone()),
+        id,
+        vrm_version: vrm.version,
+        authors: vrm.meta.authors.clone(),
+        license: vrm.meta.license.clone(),
+        license_url: vrm.meta.license_url.clone(),
+        size,
+        url: format!("{}://{}", SCHEME, file_name),
+        thumbnail_url: vrm.has_thumbnail().then(|| format!("{}://{}{}", SCHEME, THUMBNAIL_PREFIX, file_name)),
+        expressions: vrm.expressions,
+    })
+}
+
+/// Register the URI scheme on the webview's context so model URLs resolve
+pub fn install(webview: &WebView) {
+    let Some(context) = webview.web_context() else {
+        tracing::warn!("WebView has no context, imported models are unavailable");
+        return;
+    };
+    if let Some(security) = context.security_manager() {
+        // Allow fetch() from the http:// page origin
+        security.register_uri_scheme_as_cors_enabled(SCHEME);
+        security.register_uri_scheme_as_secure(SCHEME);
+    }
+    context.register_uri_scheme(SCHEME, respond);
+}
+
+fn respond(request: &URISchemeRequest) {
+    let Some(name) = requested_file(request, SCHEME) else {
+        return serve_file(request, None);
+    };
+    let thumbnail = name.strip_prefix(THUMBNAIL_PREFIX);
+    let path = thumbnail.unwrap_or(&name).strip_suffix(".vrm").and_then(model_path);
+    if thumbnail.is_some() {
+        serve_thumbnail(request, path);
+    } else {
+        serve_file(request, path);
+    }
+}
+
+/// Answer `request` with the thumbnail of the model at `path`
+fn serve_thumbnail(request: &URISchemeRequest, path: Option<PathBuf>) {
+    let thumbnail = match path.map(|path| crate::vrm::thumbnail(&path)) {
+        Some(Ok(Some((image, mime_type)))) => Ok((image, mime_type)),
+        Some(Err(e)) => Err(glib::Error::new(gio::IOErrorEnum::InvalidData, &e.to_string())),
+        _ => Err(glib::Error::new(gio::IOErrorEnum::NotFound, "No thumbnail")),
+    };
+    let stream = thumbnail.map(|(image, mime_type)| {
+        let len = image.len() as i64;
+        (gio::MemoryInputStream::from_bytes(&glib::Bytes::from_owned(image)).upcast(), len, mime_type)
+    });
+    finish(request, stream);
+}
+
+/// The file name in a `<scheme>://<file name>` request. URLs like these parse with the name
+/// as the host, so it's read from the URI.
+pub fn requested_file(request: &URISchemeRequest, scheme: &str) -> Option<String> {
+    let uri = request.uri()?;
+    let name = uri.strip_prefix(&format!("{}://", scheme))?.trim_end_matches('/');
+    glib::Uri::unescape_string(name, None::<&str>).map(|n| n.to_string())
+}
+
+/// Answer `request` with a binary glTF file (models and VRMA clips alike), or an error for
+/// `None`
+pub fn serve_file(request: &URISchemeRequest, path: Option<PathBuf>) {
+    let stream = match path {
+        Some(path) => gio::File::for_path(&path).read(None::<&gio::Cancellable>).map(|stream| {
+            let len = std::fs::metadata(&path).map(|m| m.len() as i64).unwrap_or(-1);
+            (stream.upcast(), len, "model/gltf-binary".to_string())
+        }),
+        None => Err(glib::Error::new(gio::IOErrorEnum::NotFound, "No such file")),
+    };
+    finish(request, stream);
+}
+
+/// Answer `request` with `stream` of `len` bytes and its content type, or the error
+fn finish(request: &URISchemeRequest, stream: std::result::Result<(gio::InputStream, i64, String), glib::Error>) {
+    match stream {
+        Ok((stream, len, content_type)) => {
+            let response = URISchemeResponse::new(&stream, len);
+            response.set_content_type(&content_type);
+            response.set_status(200, None);
+            let headers = webkit6::soup::MessageHeaders::new(webkit6::soup::MessageHeadersType::Response);
+            headers.append("Access-Control-Allow-Origin", "*");
+            response.set_http_headers(headers);
+            request.finish_with_response(&response);
+        }
+        Err(mut e) => {
+            crate::debug_log!("[MODELS] Can't serve {}: {}", request.uri().unwrap_or_default(), e);
+            request.finish_error(&mut e);
+        }
+    }
+}
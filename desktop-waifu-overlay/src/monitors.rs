@@ -0,0 +1,155 @@
+//! Monitor geometry for the frontend
+//!
+//! Lists connected monitors with their geometry and scale, reports which one hosts the
+//! character, and watches for hotplug and geometry/scale changes.
+//!
+//! Wayland doesn't tell clients about work areas. With `[window] respect_panels` the
+//! overlay's surface has exclusive zone 0, so the compositor fits it between other
+//! surfaces' exclusive zones (bars, docks) and the surface's size is the work area.
+
+use gtk4::gdk;
+use gtk4::prelude::*;
+use gtk4::ApplicationWindow;
+use serde::Serialize;
+use std::rc::Rc;
+
+// Used when no monitor can be resolved at all (e.g. before the first one is announced)
+const FALLBACK_SIZE: (i32, i32) = (1920, 1080);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorInfo {
+    /// Connector name, e.g. "DP-1"
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// Geometry in application pixels
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    /// GDK's integer scale; fractional scales (1.5x) are rounded up
+    pub scale_factor: i32,
+    /// Refresh rate in millihertz
+    pub refresh_rate: i32,
+    /// The overlay (and therefore the character) is on this monitor
+    pub hosts_character: bool,
+}
+
+fn all_monitors() -> Vec<gdk::Monitor> {
+    let Some(display) = gdk::Display::default() else {
+        return Vec::new();
+    };
+    let monitors = display.monitors();
+    (0..monitors.n_items())
+        .filter_map(|i| monitors.item(i))
+        .filter_map(|obj| obj.downcast::<gdk::Monitor>().ok())
+        .collect()
+}
+
+/// Monitor the overlay window is on, falling back to the first connected monitor
+pub fn current_monitor(window: &ApplicationWindow) -> Option<gdk::Monitor> {
+    let display = gdk::Display::default()?;
+    window
+        .surface()
+        .and_then(|surface| display.monitor_at_surface(&surface))
+        .or_else(|| all_monitors().into_iter().next())
+}
+
+/// Connected monitor with the given connector name
+pub fn find(connector: &str) -> Option<gdk::Monitor> {
+    all_monitors()
+        .into_iter()
+        .find(|monitor| monitor.connector().as_deref() == Some(connector))
+}
+
+/// Size of the area the character can use, in application pixels (CSS pixels in the
+/// webview, whatever the monitor's scale): the overlay's surface, which is the monitor
+/// less any panels, or the monitor's size before the surface is laid out. Never fails: if
+/// no monitor is known yet a 1920x1080 default is used so position and quadrant logic keep
+/// working.
+pub fn screen_dimensions(window: &ApplicationWindow) -> (i32, i32) {
+    if let Some(surface) = window.surface()
+        && surface.width() > 0
+        && surface.height() > 0
+    {
+        return (surface.width(), surface.height());
+    }
+    match current_monitor(window) {
+        Some(monitor) => {
+            let geometry = monitor.geometry();
+            (geometry.width(), geometry.height())
+        }
+        None => {
+            tracing::warn!("No monitor found, assuming {}x{}", FALLBACK_SIZE.0, FALLBACK_SIZE.1);
+            FALLBACK_SIZE
+        }
+    }
+}
+
+/// Describe every connected monitor
+pub fn list(window: &ApplicationWindow) -> Vec<MonitorInfo> {
+    let host = current_monitor(window);
+    all_monitors()
+        .into_iter()
+        .map(|monitor| {
+            let geometry = monitor.geometry();
+            MonitorInfo {
+                name: monitor.connector().map(|s| s.to_string()),
+                description: monitor.description().map(|s| s.to_string()),
+                x: geometry.x(),
+                y: geometry.y(),
+                width: geometry.width(),
+                height: geometry.height(),
+                scale_factor: monitor.scale_factor(),
+                refresh_rate: monitor.refresh_rate(),
+                hosts_character: host.as_ref() == Some(&monitor),
+            }
+        })
+        .collect()
+}
+
+/// Call `on_change` when monitors are added or removed, or any monitor's geometry or
+/// scale changes
+pub fn watch(on_change: Rc<dyn Fn()>) {
+    let Some(display) = gdk::Display::default() else { return };
+    let monitors = display.monitors();
+
+    for monitor in all_monitors() {
+        watch_monitor(&monitor, on_change.clone());
+    }
+
+    monitors.connect_items_changed(move |model, position, _removed, added| {
+        for i in position..position + added {
+            if let Some(monitor) = model.item(i).and_then(|obj| obj.downcast::<gdk::Monitor>().ok()) {
+                watch_monitor(&monitor, on_change.clone());
+            }
+        }
+        on_change();
+    });
+}
+
+/// Call `on_change` with the overlay surface's new size when it changes, e.g. when a panel
+/// appears or goes away
+pub fn watch_work_area(window: &ApplicationWindow, on_change: impl Fn(i32, i32) + 'static) {
+    let on_change = Rc::new(on_change);
+    let connect = move |surface: &gdk::Surface| {
+        let on_change = on_change.clone();
+        surface.connect_layout(move |_, width, height| on_change(width, height));
+    };
+    match window.surface() {
+        Some(surface) => connect(&surface),
+        None => {
+            window.connect_realize(move |window| {
+                if let Some(surface) = window.surface() {
+                    connect(&surface);
+                }
+            });
+        }
+    }
+}
+
+fn watch_monitor(monitor: &gdk::Monitor, on_change: Rc<dyn Fn()>) {
+    let on_geometry = on_change.clone();
+    monitor.connect_geometry_notify(move |_| on_geometry());
+    monitor.connect_scale_factor_notify(move |_| on_change());
+}
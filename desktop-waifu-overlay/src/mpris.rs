@@ -0,0 +1,251 @@
+//! Media players over MPRIS
+//!
+//! Finds players on the session bus (`org.mpris.MediaPlayer2.*`: Spotify, mpv with
+//! mpv-mpris, browsers, ...) so the character can see what's playing and pause or skip it.
+//! With several players, one that's playing wins, then a paused one. `spawn_monitor`
+//! follows PropertiesChanged and players coming and going, and yields the track each time
+//! it changes; `getNowPlaying`, the media control handlers and the media tools call
+//! `now_playing` and `control`, which block.
+
+use gtk4::gio;
+use gtk4::glib;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const BUS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+const PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+const CALL_TIMEOUT_MS: i32 = 2000;
+/// Players send several PropertiesChanged per track change; wait for them to settle
+const SETTLE: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Playing,
+    Paused,
+    Stopped,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NowPlaying {
+    /// Bus name without the MPRIS prefix, e.g. "spotify" or "mpv"
+    pub player: String,
+    pub status: Status,
+    pub title: Option<String>,
+    pub artists: Vec<String>,
+    pub album: Option<String>,
+    pub art_url: Option<String>,
+    pub length_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    PlayPause,
+    Next,
+    Previous,
+}
+
+impl Action {
+    fn method(self) -> &'static str {
+        match self {
+            Action::PlayPause => "PlayPause",
+            Action::Next => "Next",
+            Action::Previous => "Previous",
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MprisError {
+    #[error("no session bus: {0}")]
+    NoBus(glib::Error),
+    #[error("no media player is running")]
+    NoPlayer,
+    #[error("{player}: {error}")]
+    Failed { player: String, error: glib::Error },
+}
+
+/// What the preferred player is playing; None without a player
+pub fn now_playing() -> Result<Option<NowPlaying>, MprisError> {
+    let connection = connection()?;
+    Ok(preferred(&connection).map(|(_, playing)| playing))
+}
+
+/// Send `action` to the preferred player
+pub fn control(action: Action) -> Result<(), MprisError> {
+    let connection = connection()?;
+    let (bus_name, playing) = preferred(&connection).ok_or(MprisError::NoPlayer)?;
+    tracing::info!("Media control: {} on {}", action.method(), playing.player);
+    connection
+        .call_sync(
+            Some(&bus_name),
+            PATH,
+            PLAYER_INTERFACE,
+            action.method(),
+            None,
+            None,
+            gio::DBusCallFlags::NO_AUTO_START,
+            CALL_TIMEOUT_MS,
+            None::<&gio::Cancellable>,
+        )
+        .map_err(|error| MprisError::Failed { player: playing.player, error })?;
+    Ok(())
+}
+
+/// Start following players on the main loop; yields what's playing each time it changes
+pub fn spawn_monitor() -> async_channel::Receiver<Option<NowPlaying>> {
+    let (tx, rx) = async_channel::unbounded();
+
+    glib::spawn_future_local(async move {
+        let connection = match gio::bus_get_future(gio::BusType::Session).await {
+            Ok(connection) => connection,
+            Err(e) => {
+                tracing::info!("No session bus, media players unavailable: {}", e);
+                return;
+            }
+        };
+
+        // Subscribe before reading so a change in between isn't missed
+        let (changed_tx, changed_rx) = async_channel::unbounded::<()>();
+        let changed_tx_for_names = changed_tx.clone();
+        let properties = connection.subscribe_to_signal(
+            None,
+            Some("org.freedesktop.DBus.Properties"),
+            Some("PropertiesChanged"),
+            Some(PATH),
+            Some(PLAYER_INTERFACE),
+            gio::DBusSignalFlags::NONE,
+            move |_signal| {
+                let _ = changed_tx.try_send(());
+            },
+        );
+        let names = connection.subscribe_to_signal(
+            Some("org.freedesktop.DBus"),
+            Some("org.freedesktop.DBus"),
+            Some("NameOwnerChanged"),
+            Some("/org/freedesktop/DBus"),
+            None,
+            gio::DBusSignalFlags::NONE,
+            move |signal| {
+                let name = signal.parameters.child_value(0);
+                if name.str().is_some_and(|name| name.starts_with(BUS_PREFIX)) {
+                    let _ = changed_tx_for_names.try_send(());
+                }
+            },
+        );
+        // The subscriptions last for the life of the process
+        std::mem::forget(properties);
+        std::mem::forget(names);
+
+        let mut last = None;
+        loop {
+            let connection_for_read = connection.clone();
+            let Ok(playing) = gio::spawn_blocking(move || preferred(&connection_for_read).map(|(_, p)| p)).await
+            else {
+                break;
+            };
+            if last.as_ref() != Some(&playing) {
+                crate::debug_log!("[MPRIS] Now playing: {:?}", playing);
+                last = Some(playing.clone());
+                if tx.send(playing).await.is_err() {
+                    break;
+                }
+            }
+
+            if changed_rx.recv().await.is_err() {
+                break;
+            }
+            while let Ok(Ok(())) = glib::future_with_timeout(SETTLE, changed_rx.recv()).await {}
+        }
+    });
+
+    rx
+}
+
+fn connection() -> Result<gio::DBusConnection, MprisError> {
+    gio::bus_get_sync(gio::BusType::Session, None::<&gio::Cancellable>).map_err(MprisError::NoBus)
+}
+
+/// (bus name, state) of the player to show and control: playing before paused before
+/// stopped, then in bus order
+fn preferred(connection: &gio::DBusConnection) -> Option<(String, NowPlaying)> {
+    players(connection)
+        .into_iter()
+        .filter_map(|bus_name| {
+            let playing = read(connection, &bus_name)?;
+            Some((bus_name, playing))
+        })
+        .min_by_key(|(_, playing)| playing.status)
+}
+
+/// MPRIS bus names currently on the bus
+fn players(connection: &gio::DBusConnection) -> Vec<String> {
+    let reply = connection.call_sync(
+        Some("org.freedesktop.DBus"),
+        "/org/freedesktop/DBus",
+        "org.freedesktop.DBus",
+        "ListNames",
+        None,
+        Some(glib::VariantTy::new("(as)").unwrap()),
+        gio::DBusCallFlags::NONE,
+        CALL_TIMEOUT_MS,
+        None::<&gio::Cancellable>,
+    );
+    let Ok(reply) = reply else { return Vec::new() };
+    reply
+        .child_value(0)
+        .get::<Vec<String>>()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|name| name.starts_with(BUS_PREFIX))
+        .collect()
+}
+
+/// A player's status and track metadata
+fn read(connection: &gio::DBusConnection, bus_name: &str) -> Option<NowPlaying> {
+    let reply = connection
+        .call_sync(
+            Some(bus_name),
+            PATH,
+            "org.freedesktop.DBus.Properties",
+            "GetAll",
+            Some(&(PLAYER_INTERFACE,).into()),
+            Some(glib::VariantTy::new("(a{sv})").unwrap()),
+            gio::DBusCallFlags::NO_AUTO_START,
+            CALL_TIMEOUT_MS,
+            None::<&gio::Cancellable>,
+        )
+        .ok()?;
+    let props = glib::VariantDict::new(Some(&reply.child_value(0)));
+    let status = match props.lookup::<String>("PlaybackStatus").ok().flatten().as_deref() {
+        Some("Playing") => Status::Playing,
+        Some("Paused") => Status::Paused,
+        _ => Status::Stopped,
+    };
+    let metadata = glib::VariantDict::new(props.lookup_value("Metadata", Some(glib::VariantTy::VARDICT)).as_ref());
+    let text = |key: &str| metadata.lookup::<String>(key).ok().flatten().filter(|s| !s.is_empty());
+    // mpris:length is in microseconds; players disagree on signed or unsigned
+    let length_ms = metadata.lookup_value("mpris:length", None).and_then(|length| {
+        length
+            .get::<i64>()
+            .and_then(|us| u64::try_from(us).ok())
+            .or_else(|| length.get::<u64>())
+            .map(|us| us / 1000)
+    });
+
+    let name = &bus_name[BUS_PREFIX.len()..];
+    // Browsers and some players register one name per process: "firefox.instance_1_42"
+    let player = name.split_once(".instance").map_or(name, |(player, _)| player);
+    Some(NowPlaying {
+        player: player.to_string(),
+        status,
+        title: text("xesam:title"),
+        artists: metadata.lookup::<Vec<String>>("xesam:artist").ok().flatten().unwrap_or_default(),
+        album: text("xesam:album"),
+        art_url: text("mpris:artUrl"),
+        length_ms,
+    })
+}
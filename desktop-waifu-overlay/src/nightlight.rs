@@ -0,0 +1,295 @@
+//! Night light awareness
+//!
+//! Reads the desktop's night light state — GNOME's `org.gnome.SettingsDaemon.Color` or
+//! KWin's night light — and maps its color temperature to a dim amount (0 = normal,
+//! 1 = dark) so the character's lighting and the chat UI can soften in the evening.
+//! Desktops without night light fall back to fixed evening hours. Both the mapping and the
+//! fallback are set in `~/.config/desktop-waifu/nightlight.toml`:
+//!
+//! ```toml
+//! # Color temperature (K) -> dim amount, interpolated between points
+//! curve = [[6500, 0.0], [4500, 0.2], [3000, 0.4]]
+//! # Without a desktop night light, these hours count as night at `temperature`
+//! start = "20:00"
+//! end = "07:00"
+//! temperature = 3500
+//! ```
+//!
+//! The tray can force night light on or off instead of following the desktop.
+
+use gtk4::gio;
+use gtk4::glib;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::info;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// Daylight color temperature, i.e. night light off
+const NEUTRAL_TEMPERATURE: u32 = 6500;
+
+const GNOME_BUS_NAME: &str = "org.gnome.SettingsDaemon.Color";
+const GNOME_PATH: &str = "/org/gnome/SettingsDaemon/Color";
+const KWIN_BUS_NAME: &str = "org.kde.KWin";
+/// (path, interface) for Plasma 6 and Plasma 5
+const KWIN_OBJECTS: [(&str, &str); 2] = [
+    ("/org/kde/KWin/NightLight", "org.kde.KWin.NightLight"),
+    ("/ColorCorrect", "org.kde.kwin.ColorCorrect"),
+];
+
+/// Tray override
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Mode {
+    /// Follow the desktop (or the evening hours without one)
+    #[default]
+    Auto,
+    On,
+    Off,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    Gnome,
+    Kde,
+    /// Evening hours from nightlight.toml
+    Schedule,
+    /// Forced from the tray
+    Manual,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NightLight {
+    pub active: bool,
+    /// Color temperature in Kelvin
+    pub temperature: u32,
+    /// How much to dim, 0.0..=1.0, from the configured curve
+    pub dim: f64,
+    pub source: Source,
+}
+
+impl Default for NightLight {
+    fn default() -> Self {
+        Self {
+            active: false,
+            temperature: NEUTRAL_TEMPERATURE,
+            dim: 0.0,
+            source: Source::Schedule,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct NightLightConfig {
+    /// (temperature, dim) points
+    pub curve: Vec<(u32, f64)>,
+    pub start: String,
+    pub end: String,
+    pub temperature: u32,
+}
+
+impl Default for NightLightConfig {
+    fn default() -> Self {
+        Self {
+            curve: vec![(6500, 0.0), (4500, 0.2), (3000, 0.4)],
+            start: "20:00".into(),
+            end: "07:00".into(),
+            temperature: 3500,
+        }
+    }
+}
+
+impl NightLightConfig {
+    /// Dim amount for `temperature`, linear between curve points and flat past the ends
+    fn dim_for(&self, temperature: u32) -> f64 {
+        let mut points = self.curve.clone();
+        points.sort_by_key(|&(t, _)| t);
+        let Some((&(low_t, low_dim), &(high_t, high_dim))) = points.first().zip(points.last()) else {
+            return 0.0;
+        };
+
+        let dim = if temperature <= low_t {
+            low_dim
+        } else if temperature >= high_t {
+            high_dim
+        } else {
+            points
+                .windows(2)
+                .find(|pair| (pair[0].0..=pair[1].0).contains(&temperature))
+                .map(|pair| {
+                    let ((t0, d0), (t1, d1)) = (pair[0], pair[1]);
+                    if t1 == t0 {
+                        d1
+                    } else {
+                        d0 + (d1 - d0) * f64::from(temperature - t0) / f64::from(t1 - t0)
+                    }
+                })
+                .unwrap_or(0.0)
+        };
+        dim.clamp(0.0, 1.0)
+    }
+
+    /// Whether the local time falls in the configured evening hours
+    fn is_evening(&self, now: &glib::DateTime) -> bool {
+        let (Some(start), Some(end)) = (
+            crate::profiles::parse_time(&self.start),
+            crate::profiles::parse_time(&self.end),
+        ) else {
+            return false;
+        };
+        let minute = (now.hour() * 60 + now.minute()) as u32;
+        if start <= end {
+            (start..end).contains(&minute)
+        } else {
+            minute >= start || minute < end
+        }
+    }
+}
+
+pub fn config_path() -> PathBuf {
+    glib::user_config_dir()
+        .join("desktop-waifu")
+        .join("nightlight.toml")
+}
+
+/// nightlight.toml, or the defaults if it is missing or invalid
+pub fn load_config() -> NightLightConfig {
+    let path = config_path();
+    let Ok(text) = std::fs::read_to_string(&path) else {
+        return NightLightConfig::default();
+    };
+    toml::from_str(&text).unwrap_or_else(|e| {
+        tracing::warn!("Ignoring invalid {}: {}", path.display(), e);
+        NightLightConfig::default()
+    })
+}
+
+/// Start following night light on the main loop; yields the state each time it changes.
+/// Send on `mode_rx` to override it from the tray.
+pub fn spawn_monitor(mode_rx: async_channel::Receiver<Mode>) -> async_channel::Receiver<NightLight> {
+    let (tx, rx) = async_channel::unbounded();
+    let config = load_config();
+
+    glib::spawn_future_local(async move {
+        let connection = match gio::bus_get_future(gio::BusType::Session).await {
+            Ok(connection) => Some(connection),
+            Err(e) => {
+                info!("No session bus, night light follows the evening hours: {}", e);
+                None
+            }
+        };
+
+        let mut mode = Mode::Auto;
+        let mut last = None;
+        loop {
+            let state = evaluate(connection.as_ref(), &config, mode).await;
+            if last.as_ref() != Some(&state) {
+                crate::debug_log!("[NIGHTLIGHT] {:?}", state);
+                last = Some(state.clone());
+                if tx.send(state).await.is_err() {
+                    break;
+                }
+            }
+
+            // Re-check on the next poll, or right away when the tray changes the mode
+            // (without a tray the channel is closed and only the poll remains)
+            if mode_rx.is_closed() {
+                glib::timeout_future(POLL_INTERVAL).await;
+            } else if let Ok(Ok(new_mode)) = glib::future_with_timeout(POLL_INTERVAL, mode_rx.recv()).await {
+                info!("Night light mode: {:?}", new_mode);
+                mode = new_mode;
+            }
+        }
+    });
+
+    rx
+}
+
+async fn evaluate(connection: Option<&gio::DBusConnection>, config: &NightLightConfig, mode: Mode) -> NightLight {
+    let desktop = match connection {
+        Some(connection) => desktop_state(connection).await,
+        None => None,
+    };
+    let (mut active, mut temperature, mut source) = match desktop {
+        Some(state) => state,
+        None => {
+            let evening = glib::DateTime::now_local().is_ok_and(|now| config.is_evening(&now));
+            let temperature = if evening { config.temperature } else { NEUTRAL_TEMPERATURE };
+            (evening, temperature, Source::Schedule)
+        }
+    };
+
+    match mode {
+        Mode::Auto => {}
+        Mode::On => {
+            if !active {
+                temperature = config.temperature;
+            }
+            active = true;
+            source = Source::Manual;
+        }
+        Mode::Off => {
+            active = false;
+            temperature = NEUTRAL_TEMPERATURE;
+            source = Source::Manual;
+        }
+    }
+
+    NightLight {
+        active,
+        temperature,
+        dim: if active { config.dim_for(temperature) } else { 0.0 },
+        source,
+    }
+}
+
+/// (active, temperature, source) from GNOME or KWin; None when neither provides night light
+async fn desktop_state(connection: &gio::DBusConnection) -> Option<(bool, u32, Source)> {
+    if let Some(props) = get_all(connection, GNOME_BUS_NAME, GNOME_PATH, GNOME_BUS_NAME).await {
+        let active = props.lookup::<bool>("NightLightActive").ok().flatten().unwrap_or(false);
+        let temperature = props.lookup::<u32>("Temperature").ok().flatten().unwrap_or(NEUTRAL_TEMPERATURE);
+        return Some((active, temperature, Source::Gnome));
+    }
+
+    for (path, interface) in KWIN_OBJECTS {
+        let Some(props) = get_all(connection, KWIN_BUS_NAME, path, interface).await else { continue };
+        if props.lookup::<bool>("available").ok().flatten() == Some(false) {
+            return None;
+        }
+        let active = props.lookup::<bool>("running").ok().flatten().unwrap_or(false);
+        let temperature = props
+            .lookup::<u32>("currentTemperature")
+            .ok()
+            .flatten()
+            .unwrap_or(NEUTRAL_TEMPERATURE);
+        return Some((active, temperature, Source::Kde));
+    }
+
+    None
+}
+
+async fn get_all(
+    connection: &gio::DBusConnection,
+    bus_name: &str,
+    path: &str,
+    interface: &str,
+) -> Option<glib::VariantDict> {
+    let reply = connection
+        .call_future(
+            Some(bus_name),
+            path,
+            "org.freedesktop.DBus.Properties",
+            "GetAll",
+            Some(&(interface,).into()),
+            Some(glib::VariantTy::new("(a{sv})").unwrap()),
+            gio::DBusCallFlags::NO_AUTO_START,
+            2000,
+        )
+        .await
+        .ok()?;
+    let props = glib::VariantDict::new(Some(&reply.child_value(0)));
+    // An empty dict means the object exists without this interface
+    (reply.child_value(0).n_children() > 0).then_some(props)
+}
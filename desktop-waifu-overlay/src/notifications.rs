@@ -0,0 +1,45 @@
+//! Desktop notifications
+//!
+//! Everything the overlay pops up (replies the frontend asks for, reminders) goes through
+//! `show`, which holds notifications back while meeting mode is on or the user muted them
+//! from the tray. Muting is saved with the overlay state.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static MUTED: AtomicBool = AtomicBool::new(false);
+
+pub fn is_muted() -> bool {
+    MUTED.load(Ordering::Relaxed)
+}
+
+pub fn set_muted(muted: bool) {
+    MUTED.store(muted, Ordering::Relaxed);
+    tracing::info!("Notifications {}", if muted { "muted" } else { "unmuted" });
+}
+
+/// Show a notification through D-Bus (Linux) or native APIs (macOS/Windows), unless muted
+/// or in a meeting
+pub fn show(title: &str, body: &str) {
+    use crate::audit::{self, Kind, Outcome};
+
+    if is_muted() {
+        crate::debug_log!("[NOTIFICATION] Muted, suppressing: {}", title);
+        audit::record(Kind::Notification, title, Outcome::Refused, Some("muted".into()));
+        return;
+    }
+    if crate::presence::is_quiet() {
+        crate::debug_log!("[NOTIFICATION] Meeting mode, suppressing: {}", title);
+        audit::record(Kind::Notification, title, Outcome::Refused, Some("meeting mode".into()));
+        return;
+    }
+    crate::debug_log!("[NOTIFICATION] Showing notification: title={}, body={}", title, body);
+    let shown = notify_rust::Notification::new()
+        .summary(title)
+        .body(body)
+        .appname(&crate::tr!("app-name"))
+        .show();
+    if let Err(e) = &shown {
+        tracing::warn!("Failed to show notification: {}", e);
+    }
+    audit::result(Kind::Notification, title, &shown);
+}
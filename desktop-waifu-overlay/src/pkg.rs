@@ -0,0 +1,392 @@
+//! Package management through the system's package manager
+//!
+//! `search`, `info`, `list_upgrades` and `install` wrap apt, dnf (and yum), pacman and
+//! zypper, parse what they print and hand back typed results, so the assistant doesn't
+//! have to read raw shell output (the `package_*` tools). Queries run as the user;
+//! installing goes through pkexec (privileged.rs), which has polkit ask for authorization
+//! in its own dialog, so no `sudo` ends up in a command line and no password in the chat.
+
+use crate::privileged::Outcome;
+use crate::tools::manifest::run_with_timeout;
+use serde::Serialize;
+use std::process::Command;
+use std::time::Duration;
+
+const QUERY_TIMEOUT: Duration = Duration::from_secs(120);
+/// Most search results handed back
+const MAX_RESULTS: usize = 50;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PkgError {
+    #[error("no supported package manager found (apt, dnf, yum, pacman or zypper)")]
+    Unsupported,
+    #[error("'{0}' is not a valid package name")]
+    InvalidName(String),
+    #[error("package '{0}' not found")]
+    NotFound(String),
+    #[error("authorization was cancelled")]
+    Cancelled,
+    #[error("not authorized to install packages; is a polkit agent running?")]
+    NotAuthorized,
+    #[error("{command} timed out")]
+    TimedOut { command: String },
+    #[error("{command} failed: {message}")]
+    Failed { command: String, message: String },
+}
+
+pub type Result<T> = std::result::Result<T, PkgError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Manager {
+    Apt,
+    Dnf,
+    Yum,
+    Pacman,
+    Zypper,
+}
+
+impl Manager {
+    /// The first supported package manager on PATH, like `getSystemInfo` reports it
+    pub fn detect() -> Option<Manager> {
+        match crate::system::info()["package_manager"].as_str()? {
+            "apt" => Some(Manager::Apt),
+            "dnf" => Some(Manager::Dnf),
+            "yum" => Some(Manager::Yum),
+            "pacman" => Some(Manager::Pacman),
+            "zypper" => Some(Manager::Zypper),
+            _ => None,
+        }
+    }
+
+    fn program(self) -> &'static str {
+        match self {
+            Manager::Apt => "apt-get",
+            Manager::Dnf => "dnf",
+            Manager::Yum => "yum",
+            Manager::Pacman => "pacman",
+            Manager::Zypper => "zypper",
+        }
+    }
+}
+
+/// A search result
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Package {
+    pub name: String,
+    /// Not every manager's search shows versions (apt's doesn't)
+    pub version: Option<String>,
+    pub description: String,
+    /// Only pacman and zypper searches tell
+    pub installed: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageInfo {
+    pub name: String,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub url: Option<String>,
+    pub installed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Upgrade {
+    pub name: String,
+    /// Not shown by dnf and yum
+    pub current_version: Option<String>,
+    pub available_version: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallResult {
+    pub manager: Manager,
+    pub packages: Vec<String>,
+    /// The end of the package manager's output
+    pub output: String,
+}
+
+fn manager() -> Result<Manager> {
+    Manager::detect().ok_or(PkgError::Unsupported)
+}
+
+/// Package names go on command lines after `--`, but still mustn't look like options or
+/// carry anything a package manager wouldn't accept
+fn check_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '+' | ':' | '@'));
+    if valid { Ok(()) } else { Err(PkgError::InvalidName(name.to_string())) }
+}
+
+/// Run `program args`, returning stdout; `ok_codes` are the exit codes that aren't failures
+fn query(program: &str, args: &[&str], ok_codes: &[i32]) -> Result<String> {
+    let command = format!("{} {}", program, args.join(" "));
+    let mut cmd = Command::new(program);
+    cmd.args(args).env("LC_ALL", "C");
+    let (stdout, stderr, exit_code, timed_out) =
+        run_with_timeout(cmd, None, QUERY_TIMEOUT).map_err(|message| PkgError::Failed { command: command.clone(), message })?;
+    if timed_out {
+        return Err(PkgError::TimedOut { command });
+    }
+    if exit_code != 0 && !ok_codes.contains(&exit_code) {
+        let message = String::from_utf8_lossy(&stderr).trim().to_string();
+        return Err(PkgError::Failed { command, message });
+    }
+    Ok(String::from_utf8_lossy(&stdout).into_owned())
+}
+
+/// `Key : value` lines, as dnf, pacman and zypper print package details
+fn field<'a>(text: &'a str, key: &str) -> Option<&'a str> {
+    text.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        (name.trim() == key).then(|| value.trim()).filter(|value| !value.is_empty())
+    })
+}
+
+/// Split the cells of a zypper table row
+fn table_row(line: &str) -> Vec<&str> {
+    line.split('|').map(str::trim).collect()
+}
+
+pub fn search(query_text: &str) -> Result<Vec<Package>> {
+    let manager = manager()?;
+    if query_text.trim().is_empty() || query_text.starts_with('-') {
+        return Err(PkgError::InvalidName(query_text.to_string()));
+    }
+    let mut packages: Vec<Package> = match manager {
+        Manager::Apt => query("apt-cache", &["search", "--", query_text], &[])?
+            .lines()
+            .filter_map(|line| line.split_once(" - "))
+            .map(|(name, description)| Package {
+                name: name.trim().to_string(),
+                version: None,
+                description: description.trim().to_string(),
+                installed: None,
+            })
+            .collect(),
+        Manager::Dnf | Manager::Yum => query(manager.program(), &["search", "-q", "--", query_text], &[1])?
+            .lines()
+            .filter(|line| !line.starts_with('=') && !line.starts_with("Matched"))
+            .filter_map(|line| line.split_once(" : "))
+            .map(|(name, description)| Package {
+                // "name.arch"
+                name: name.trim().rsplit_once('.').map_or(name.trim(), |(name, _)| name).to_string(),
+                version: None,
+                description: description.trim().to_string(),
+                installed: None,
+            })
+            .collect(),
+        Manager::Pacman => {
+            // "repo/name version [installed]" followed by an indented description
+            let output = query("pacman", &["-Ss", "--", query_text], &[1])?;
+            let mut packages = Vec::new();
+            let mut lines = output.lines().peekable();
+            while let Some(line) = lines.next() {
+                if line.starts_with(char::is_whitespace) {
+                    continue;
+                }
+                let mut words = line.split_whitespace();
+                let Some(name) = words.next().map(|repo_name| repo_name.rsplit('/').next().unwrap_or(repo_name)) else {
+                    continue;
+                };
+                let version = words.next().map(str::to_string);
+                let description = lines.next_if(|next| next.starts_with(char::is_whitespace)).unwrap_or("");
+                packages.push(Package {
+                    name: name.to_string(),
+                    version,
+                    description: description.trim().to_string(),
+                    installed: Some(line.contains("[installed")),
+                });
+            }
+            packages
+        }
+        Manager::Zypper => query("zypper", &["--non-interactive", "--quiet", "search", "--", query_text], &[104])?
+            .lines()
+            .map(table_row)
+            // S | Name | Summary | Type
+            .filter(|cells| cells.len() >= 4 && cells[1] != "Name" && cells[3] == "package")
+            .map(|cells| Package {
+                name: cells[1].to_string(),
+                version: None,
+                description: cells[2].to_string(),
+                installed: Some(cells[0].starts_with('i')),
+            })
+            .collect(),
+    };
+    packages.truncate(MAX_RESULTS);
+    Ok(packages)
+}
+
+pub fn info(name: &str) -> Result<PackageInfo> {
+    let manager = manager()?;
+    check_name(name)?;
+    let not_found = |_| PkgError::NotFound(name.to_string());
+    let info = match manager {
+        Manager::Apt => {
+            let output = query("apt-cache", &["show", "--no-all-versions", "--", name], &[]).map_err(not_found)?;
+            if output.trim().is_empty() {
+                return Err(PkgError::NotFound(name.to_string()));
+            }
+            let status = query("dpkg-query", &["-W", "-f=${Status}", "--", name], &[1]).unwrap_or_default();
+            PackageInfo {
+                name: name.to_string(),
+                version: field(&output, "Version").map(str::to_string),
+                description: field(&output, "Description").or_else(|| field(&output, "Description-en")).map(str::to_string),
+                url: field(&output, "Homepage").map(str::to_string),
+                installed: status.ends_with("installed") && !status.contains("not-installed"),
+            }
+        }
+        Manager::Dnf | Manager::Yum => {
+            let output = query(manager.program(), &["info", "-q", "--", name], &[]).map_err(not_found)?;
+            PackageInfo {
+                name: name.to_string(),
+                version: field(&output, "Version").map(str::to_string),
+                description: field(&output, "Summary").map(str::to_string),
+                url: field(&output, "URL").map(str::to_string),
+                installed: query("rpm", &["-q", "--", name], &[]).is_ok(),
+            }
+        }
+        Manager::Pacman => {
+            let installed = query("pacman", &["-Q", "--", name], &[]).is_ok();
+            // Installed packages may come from the AUR, which -Si doesn't know
+            let flag = if installed { "-Qi" } else { "-Si" };
+            let output = query("pacman", &[flag, "--", name], &[]).map_err(not_found)?;
+            PackageInfo {
+                name: name.to_string(),
+                version: field(&output, "Version").map(str::to_string),
+                description: field(&output, "Description").map(str::to_string),
+                url: field(&output, "URL").map(str::to_string),
+                installed,
+            }
+        }
+        Manager::Zypper => {
+            let output = query("zypper", &["--non-interactive", "--quiet", "info", "--", name], &[])?;
+            if field(&output, "Name").is_none() {
+                return Err(PkgError::NotFound(name.to_string()));
+            }
+            PackageInfo {
+                name: name.to_string(),
+                version: field(&output, "Version").map(str::to_string),
+                description: field(&output, "Summary").map(str::to_string),
+                url: None,
+                installed: field(&output, "Installed").is_some_and(|value| value.starts_with("Yes")),
+            }
+        }
+    };
+    Ok(info)
+}
+
+/// Upgrades available from the package manager's last metadata refresh
+pub fn list_upgrades() -> Result<Vec<Upgrade>> {
+    let manager = manager()?;
+    let upgrades = match manager {
+        // "name/suite 1.2-3 amd64 [upgradable from: 1.2-2]"
+        Manager::Apt => query("apt", &["list", "--upgradable"], &[])?
+            .lines()
+            .filter_map(|line| {
+                let (name, rest) = line.split_once('/')?;
+                let available = rest.split_whitespace().nth(1)?;
+                let current = line.split_once("upgradable from: ")?.1.trim_end_matches(']');
+                Some(Upgrade {
+                    name: name.to_string(),
+                    current_version: Some(current.to_string()),
+                    available_version: available.to_string(),
+                })
+            })
+            .collect(),
+        // "name.arch 1.2-3.fc40 updates"; exits with 100 when there are upgrades
+        Manager::Dnf | Manager::Yum => query(manager.program(), &["check-update", "-q"], &[100])?
+            .lines()
+            .filter_map(|line| {
+                let mut words = line.split_whitespace();
+                let (name, version, _repo) = (words.next()?, words.next()?, words.next()?);
+                Some(Upgrade {
+                    name: name.rsplit_once('.').map_or(name, |(name, _)| name).to_string(),
+                    current_version: None,
+                    available_version: version.to_string(),
+                })
+            })
+            .collect(),
+        // "name 1.2-2 -> 1.2-3"; exits with 1 when there are none
+        Manager::Pacman => query("pacman", &["-Qu"], &[1])?
+            .lines()
+            .filter_map(|line| {
+                let words: Vec<&str> = line.split_whitespace().collect();
+                match words[..] {
+                    [name, current, "->", available, ..] => Some(Upgrade {
+                        name: name.to_string(),
+                        current_version: Some(current.to_string()),
+                        available_version: available.to_string(),
+                    }),
+                    _ => None,
+                }
+            })
+            .collect(),
+        // S | Repository | Name | Current Version | Available Version | Arch
+        Manager::Zypper => query("zypper", &["--non-interactive", "--quiet", "list-updates"], &[])?
+            .lines()
+            .map(table_row)
+            .filter(|cells| cells.len() >= 5 && cells[2] != "Name")
+            .map(|cells| Upgrade {
+                name: cells[2].to_string(),
+                current_version: Some(cells[3].to_string()),
+                available_version: cells[4].to_string(),
+            })
+            .collect(),
+    };
+    Ok(upgrades)
+}
+
+/// Install `packages` as root through pkexec; polkit asks the user to authorize it
+pub fn install(packages: &[String]) -> Result<InstallResult> {
+    let manager = manager()?;
+    if packages.is_empty() {
+        return Err(PkgError::InvalidName(String::new()));
+    }
+    for name in packages {
+        check_name(name)?;
+    }
+
+    let mut args: Vec<&str> = match manager {
+        // pkexec clears the environment, so apt's frontend is set inside it
+        Manager::Apt => vec!["DEBIAN_FRONTEND=noninteractive", "apt-get", "install", "-y"],
+        Manager::Dnf | Manager::Yum => vec![manager.program(), "install", "-y"],
+        Manager::Pacman => vec!["pacman", "-S", "--needed", "--noconfirm"],
+        Manager::Zypper => vec!["zypper", "--non-interactive", "install"],
+    };
+    args.push("--");
+    args.extend(packages.iter().map(String::as_str));
+
+    let command = format!("{} install {}", manager.program(), packages.join(" "));
+    let result = crate::privileged::run(crate::privileged::command("/usr/bin/env", &args), &command);
+    let stdout = match result.outcome {
+        Outcome::Completed if result.exit_code == 0 => result.stdout,
+        Outcome::Completed => {
+            let message = String::from_utf8_lossy(&result.stderr).trim().to_string();
+            return Err(PkgError::Failed { command, message });
+        }
+        Outcome::Cancelled => return Err(PkgError::Cancelled),
+        Outcome::NotAuthorized => return Err(PkgError::NotAuthorized),
+        Outcome::TimedOut => return Err(PkgError::TimedOut { command }),
+        Outcome::Unavailable => {
+            let message = String::from_utf8_lossy(&result.stderr).into_owned();
+            return Err(PkgError::Failed { command: "pkexec".to_string(), message });
+        }
+    };
+
+    let output = String::from_utf8_lossy(&stdout);
+    let lines: Vec<&str> = output.lines().collect();
+    let output = lines[lines.len().saturating_sub(20)..].join("\n");
+    Ok(InstallResult {
+        manager,
+        packages: packages.to_vec(),
+        output,
+    })
+}
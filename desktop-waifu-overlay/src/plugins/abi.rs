@@ -0,0 +1,279 @@
+//! C ABI between the overlay and native plugin libraries
+//!
+//! A plugin is a shared library exporting:
+//!
+//! ```c
+//! uint32_t waifu_plugin_abi_version(void);
+//! int32_t  waifu_plugin_init(const WaifuHostApi *host);          // 0 on success
+//! char    *waifu_plugin_call(const char *kind, const char *name, const char *payload_json);
+//! void     waifu_plugin_free(char *result);
+//! void     waifu_plugin_shutdown(void);                          // optional
+//! ```
+//!
+//! `kind` is one of "message" (script message handler), "tool" or "ipc". The payload and
+//! the returned string are UTF-8 JSON; the result must be `{"ok": <value>}` or
+//! `{"error": "<message>"}` and is released with `waifu_plugin_free`.
+//!
+//! The host API handed to `waifu_plugin_init` is capability-scoped: every call checks the
+//! permissions the user granted to that plugin and returns -1 when it isn't allowed.
+
+use super::Permission;
+use std::collections::HashSet;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::path::Path;
+use std::sync::OnceLock;
+
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// Function table passed to plugins on init
+#[repr(C)]
+pub struct HostApi {
+    pub abi_version: u32,
+    /// Opaque per-plugin context, must be passed back on every call
+    pub ctx: *const c_void,
+    /// Log a message (level: 0 = debug, 1 = info, 2 = warn, 3 = error)
+    pub log: extern "C" fn(ctx: *const c_void, level: u32, message: *const c_char),
+    /// Show a desktop notification (requires "notify")
+    pub notify: extern "C" fn(ctx: *const c_void, title: *const c_char, body: *const c_char) -> i32,
+    /// Dispatch an event to the frontend (requires "events")
+    pub emit_event: extern "C" fn(ctx: *const c_void, name: *const c_char, detail_json: *const c_char) -> i32,
+}
+
+/// Event raised by a plugin, forwarded to the webview on the main thread
+#[derive(Debug, Clone)]
+pub struct PluginEvent {
+    pub plugin: String,
+    pub name: String,
+    pub detail: serde_json::Value,
+}
+
+static EVENT_SENDER: OnceLock<async_channel::Sender<PluginEvent>> = OnceLock::new();
+
+/// Install the channel that receives plugin events. Returns None if already installed.
+pub fn event_receiver() -> Option<async_channel::Receiver<PluginEvent>> {
+    let (tx, rx) = async_channel::unbounded();
+    EVENT_SENDER.set(tx).ok()?;
+    Some(rx)
+}
+
+struct HostContext {
+    plugin: String,
+    permissions: HashSet<Permission>,
+}
+
+type AbiVersionFn = unsafe extern "C" fn() -> u32;
+type InitFn = unsafe extern "C" fn(*const HostApi) -> i32;
+type CallFn = unsafe extern "C" fn(*const c_char, *const c_char, *const c_char) -> *mut c_char;
+type FreeFn = unsafe extern "C" fn(*mut c_char);
+type ShutdownFn = unsafe extern "C" fn();
+
+/// A loaded plugin library
+pub struct PluginLibrary {
+    handle: *mut c_void,
+    call: CallFn,
+    free: FreeFn,
+    shutdown: Option<ShutdownFn>,
+    // Kept alive for as long as the plugin may call back into the host
+    _host: Box<HostApi>,
+    _ctx: Box<HostContext>,
+}
+
+// SAFETY: the library handle and function pointers are immutable after loading, and the
+// ABI contract requires plugin entry points to be callable from any thread.
+unsafe impl Send for PluginLibrary {}
+unsafe impl Sync for PluginLibrary {}
+
+impl PluginLibrary {
+    /// dlopen a plugin, verify its ABI version and run its init function
+    pub fn load(path: &Path, plugin: &str, permissions: HashSet<Permission>) -> Result<Self, String> {
+        let c_path = CString::new(path.to_string_lossy().as_bytes())
+            .map_err(|_| "Library path contains a NUL byte".to_string())?;
+
+        // SAFETY: loading a library runs its constructors; the user installed and granted it
+        let handle = unsafe { libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+        if handle.is_null() {
+            return Err(format!("dlopen failed: {}", last_dl_error()));
+        }
+
+        // From here on, close the handle if anything fails
+        let loaded = (|| {
+            // SAFETY: symbol types are fixed by the plugin ABI documented above
+            unsafe {
+                let abi_version: AbiVersionFn = std::mem::transmute(symbol(handle, "waifu_plugin_abi_version")?);
+                let init: InitFn = std::mem::transmute(symbol(handle, "waifu_plugin_init")?);
+                let call: CallFn = std::mem::transmute(symbol(handle, "waifu_plugin_call")?);
+                let free: FreeFn = std::mem::transmute(symbol(handle, "waifu_plugin_free")?);
+                let shutdown = symbol(handle, "waifu_plugin_shutdown")
+                    .ok()
+                    .map(|s| std::mem::transmute::<*mut c_void, ShutdownFn>(s));
+
+                let version = abi_version();
+                if version != PLUGIN_ABI_VERSION {
+                    return Err(format!(
+                        "Plugin ABI version {} is not supported (expected {})",
+                        version, PLUGIN_ABI_VERSION
+                    ));
+                }
+
+                let ctx = Box::new(HostContext {
+                    plugin: plugin.to_string(),
+                    permissions,
+                });
+                let host = Box::new(HostApi {
+                    abi_version: PLUGIN_ABI_VERSION,
+                    ctx: &*ctx as *const HostContext as *const c_void,
+                    log: host_log,
+                    notify: host_notify,
+                    emit_event: host_emit_event,
+                });
+
+                let rc = init(&*host);
+                if rc != 0 {
+                    return Err(format!("Plugin init returned {}", rc));
+                }
+
+                Ok(PluginLibrary {
+                    handle,
+                    call,
+                    free,
+                    shutdown,
+                    _host: host,
+                    _ctx: ctx,
+                })
+            }
+        })();
+
+        if loaded.is_err() {
+            // SAFETY: handle came from a successful dlopen and nothing references it
+            unsafe { libc::dlclose(handle) };
+        }
+        loaded
+    }
+
+    /// Invoke the plugin's dispatch entry point
+    pub fn call(&self, kind: &str, name: &str, payload: &serde_json::Value) -> Result<serde_json::Value, String> {
+        let kind = CString::new(kind).map_err(|e| e.to_string())?;
+        let name = CString::new(name).map_err(|e| e.to_string())?;
+        let payload = CString::new(payload.to_string()).map_err(|e| e.to_string())?;
+
+        // SAFETY: arguments are valid NUL-terminated strings that outlive the call
+        let result_ptr = unsafe { (self.call)(kind.as_ptr(), name.as_ptr(), payload.as_ptr()) };
+        if result_ptr.is_null() {
+            return Err("Plugin returned no result".to_string());
+        }
+
+        // SAFETY: the plugin returns a NUL-terminated string it owns until we free it
+        let result = unsafe { CStr::from_ptr(result_ptr) }.to_string_lossy().to_string();
+        unsafe { (self.free)(result_ptr) };
+
+        let parsed: serde_json::Value = serde_json::from_str(&result)
+            .map_err(|e| format!("Plugin returned invalid JSON: {}", e))?;
+        if let Some(error) = parsed.get("error") {
+            return Err(error.as_str().unwrap_or("Unknown plugin error").to_string());
+        }
+        Ok(parsed.get("ok").cloned().unwrap_or(serde_json::Value::Null))
+    }
+}
+
+impl Drop for PluginLibrary {
+    fn drop(&mut self) {
+        // SAFETY: no calls into the library can be in flight once the last Arc is dropped
+        unsafe {
+            if let Some(shutdown) = self.shutdown {
+                shutdown();
+            }
+            libc::dlclose(self.handle);
+        }
+    }
+}
+
+unsafe fn symbol(handle: *mut c_void, name: &str) -> Result<*mut c_void, String> {
+    let c_name = CString::new(name).unwrap();
+    let sym = unsafe { libc::dlsym(handle, c_name.as_ptr()) };
+    if sym.is_null() {
+        return Err(format!("Missing symbol '{}'", name));
+    }
+    Ok(sym)
+}
+
+fn last_dl_error() -> String {
+    // SAFETY: dlerror returns a thread-local string or null
+    let err = unsafe { libc::dlerror() };
+    if err.is_null() {
+        return "unknown error".to_string();
+    }
+    unsafe { CStr::from_ptr(err) }.to_string_lossy().to_string()
+}
+
+/// Borrow the host context and a C string argument; None if either is invalid
+fn context<'a>(ctx: *const c_void) -> Option<&'a HostContext> {
+    // SAFETY: ctx is the pointer we handed out in `load`, kept alive by PluginLibrary
+    unsafe { (ctx as *const HostContext).as_ref() }
+}
+
+fn c_str(ptr: *const c_char) -> Option<String> {
+    if ptr.is_null() {
+        return None;
+    }
+    // SAFETY: plugins must pass NUL-terminated strings
+    Some(unsafe { CStr::from_ptr(ptr) }.to_string_lossy().to_string())
+}
+
+extern "C" fn host_log(ctx: *const c_void, level: u32, message: *const c_char) {
+    let (Some(ctx), Some(message)) = (context(ctx), c_str(message)) else { return };
+    match level {
+        0 => crate::debug_log!("[PLUGIN:{}] {}", ctx.plugin, message),
+        1 => tracing::info!("[plugin {}] {}", ctx.plugin, message),
+        2 => tracing::warn!("[plugin {}] {}", ctx.plugin, message),
+        _ => tracing::error!("[plugin {}] {}", ctx.plugin, message),
+    }
+}
+
+extern "C" fn host_notify(ctx: *const c_void, title: *const c_char, body: *const c_char) -> i32 {
+    let Some(ctx) = context(ctx) else { return -1 };
+    if !ctx.permissions.contains(&Permission::Notify) {
+        tracing::warn!("Plugin '{}' tried to notify without permission", ctx.plugin);
+        return -1;
+    }
+    // Meeting mode: report success, the notification just isn't shown
+    if crate::presence::is_quiet() {
+        return 0;
+    }
+    let title = c_str(title).unwrap_or_else(|| ctx.plugin.clone());
+    let body = c_str(body).unwrap_or_default();
+    match notify_rust::Notification::new()
+        .summary(&title)
+        .body(&body)
+        .appname(&crate::tr!("app-name"))
+        .show()
+    {
+        Ok(_) => 0,
+        Err(e) => {
+            tracing::warn!("Failed to show plugin notification: {}", e);
+            -1
+        }
+    }
+}
+
+extern "C" fn host_emit_event(ctx: *const c_void, name: *const c_char, detail_json: *const c_char) -> i32 {
+    let Some(ctx) = context(ctx) else { return -1 };
+    if !ctx.permissions.contains(&Permission::Events) {
+        tracing::warn!("Plugin '{}' tried to emit an event without permission", ctx.plugin);
+        return -1;
+    }
+    let Some(name) = c_str(name) else { return -1 };
+    let detail = c_str(detail_json)
+        .and_then(|d| serde_json::from_str(&d).ok())
+        .unwrap_or(serde_json::Value::Null);
+
+    let Some(sender) = EVENT_SENDER.get() else { return -1 };
+    let event = PluginEvent {
+        plugin: ctx.plugin.clone(),
+        name,
+        detail,
+    };
+    match sender.send_blocking(event) {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
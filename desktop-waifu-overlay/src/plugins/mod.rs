@@ -0,0 +1,510 @@
+//! Plugin system for third-party script message handlers, tools and IPC commands
+//!
+//! Plugins live in `~/.local/share/desktop-waifu/plugins/<name>/` with a `plugin.toml`
+//! manifest next to a native library implementing the ABI in [`abi`]. A plugin is only
+//! loaded once the user has granted every permission its manifest requests. Grants and
+//! denials are tied to the library's SHA-256, so a library replaced behind a granted name
+//! is asked about again, and one the user turned down isn't asked about on every start.
+
+mod abi;
+
+pub use abi::event_receiver;
+
+use crate::tools::{Tool, ToolDefinition, ToolRegistry};
+use crate::tr;
+use abi::PluginLibrary;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::info;
+
+const MANIFEST_FILE: &str = "plugin.toml";
+const STATE_FILE: &str = "state.json";
+
+/// IPC commands the overlay handles itself. Plugin commands are routed before these (so they
+/// work with the hotkey disabled), so a plugin claiming one would take it over.
+const RESERVED_IPC_COMMANDS: &[&str] = &[
+    "toggle",
+    "show",
+    "hide",
+    "status",
+    "profile",
+    "log-level",
+    "ask-screenshot",
+    "subscribe",
+];
+
+/// Host capabilities a plugin can ask for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    /// Show desktop notifications
+    Notify,
+    /// Dispatch events to the frontend
+    Events,
+    /// Register script message handlers callable from the frontend
+    Handlers,
+    /// Register assistant tools
+    Tools,
+    /// Register IPC socket commands
+    Ipc,
+}
+
+impl std::fmt::Display for Permission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Permission::Notify => "notify",
+            Permission::Events => "events",
+            Permission::Handlers => "handlers",
+            Permission::Tools => "tools",
+            Permission::Ipc => "ipc",
+        };
+        f.write_str(s)
+    }
+}
+
+/// Tool declared in a plugin manifest
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ManifestTool {
+    pub name: String,
+    pub description: String,
+    #[serde(default = "empty_schema")]
+    pub parameters: serde_json::Value,
+}
+
+fn empty_schema() -> serde_json::Value {
+    serde_json::json!({ "type": "object", "properties": {} })
+}
+
+/// Contents of `plugin.toml`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PluginManifest {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    /// Shared library file, relative to the plugin directory
+    pub library: String,
+    #[serde(default)]
+    pub permissions: Vec<Permission>,
+    /// Script message handlers, exposed to the frontend as "<plugin>.<handler>"
+    #[serde(default)]
+    pub handlers: Vec<String>,
+    #[serde(default)]
+    pub tools: Vec<ManifestTool>,
+    /// First word of IPC socket commands routed to this plugin
+    #[serde(default)]
+    pub ipc_commands: Vec<String>,
+    #[serde(skip)]
+    pub dir: PathBuf,
+}
+
+impl PluginManifest {
+    /// Permissions implied by the manifest in addition to those listed explicitly
+    pub fn required_permissions(&self) -> HashSet<Permission> {
+        let mut perms: HashSet<Permission> = self.permissions.iter().copied().collect();
+        if !self.handlers.is_empty() {
+            perms.insert(Permission::Handlers);
+        }
+        if !self.tools.is_empty() {
+            perms.insert(Permission::Tools);
+        }
+        if !self.ipc_commands.is_empty() {
+            perms.insert(Permission::Ipc);
+        }
+        perms
+    }
+
+    /// Script message handler names as registered with WebKit
+    pub fn handler_names(&self) -> Vec<String> {
+        self.handlers
+            .iter()
+            .map(|h| format!("{}.{}", self.name, h))
+            .collect()
+    }
+
+    /// Hex SHA-256 of the plugin's library, None if it can't be read
+    fn library_digest(&self) -> Option<String> {
+        let bytes = std::fs::read(self.dir.join(&self.library)).ok()?;
+        Some(format!("{:x}", Sha256::digest(&bytes)))
+    }
+}
+
+/// Persisted grants, denials and enable/disable state
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct PluginState {
+    #[serde(default)]
+    granted: BTreeMap<String, Vec<Permission>>,
+    /// SHA-256 of the library each grant was given to
+    #[serde(default)]
+    libraries: BTreeMap<String, String>,
+    /// SHA-256 of the library the user turned down, by plugin name
+    #[serde(default)]
+    denied: BTreeMap<String, String>,
+    #[serde(default)]
+    disabled: Vec<String>,
+}
+
+impl PluginState {
+    fn load() -> Self {
+        std::fs::read_to_string(plugins_dir().join(STATE_FILE))
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let dir = plugins_dir();
+        std::fs::create_dir_all(&dir)?;
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(dir.join(STATE_FILE), json)
+    }
+
+    /// Whether every permission was granted, to the library as it is now
+    fn is_granted(&self, manifest: &PluginManifest, digest: Option<&str>) -> bool {
+        let granted: HashSet<Permission> = self
+            .granted
+            .get(&manifest.name)
+            .map(|p| p.iter().copied().collect())
+            .unwrap_or_default();
+        let same_library = digest.is_some() && self.libraries.get(&manifest.name).map(String::as_str) == digest;
+        same_library && manifest.required_permissions().is_subset(&granted)
+    }
+
+    /// Whether the user turned down the library as it is now
+    fn is_denied(&self, manifest: &PluginManifest, digest: Option<&str>) -> bool {
+        digest.is_some() && self.denied.get(&manifest.name).map(String::as_str) == digest
+    }
+
+    fn forget(&mut self, name: &str) {
+        self.granted.remove(name);
+        self.libraries.remove(name);
+        self.denied.remove(name);
+    }
+}
+
+pub fn plugins_dir() -> PathBuf {
+    gtk4::glib::user_data_dir()
+        .join("desktop-waifu")
+        .join("plugins")
+}
+
+fn read_manifest(dir: &Path) -> Result<PluginManifest, String> {
+    let text = std::fs::read_to_string(dir.join(MANIFEST_FILE))
+        .map_err(|e| format!("Failed to read {}: {}", MANIFEST_FILE, e))?;
+    let mut manifest: PluginManifest =
+        toml::from_str(&text).map_err(|e| format!("Invalid {}: {}", MANIFEST_FILE, e))?;
+
+    let valid_name = !manifest.name.is_empty()
+        && manifest
+            .name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid_name {
+        return Err(format!("Invalid plugin name '{}'", manifest.name));
+    }
+    manifest.dir = dir.to_path_buf();
+    Ok(manifest)
+}
+
+/// Read every plugin manifest in the plugins directory
+pub fn discover() -> Vec<PluginManifest> {
+    let Ok(entries) = std::fs::read_dir(plugins_dir()) else {
+        return Vec::new();
+    };
+
+    let mut manifests: Vec<PluginManifest> = entries
+        .flatten()
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| match read_manifest(&e.path()) {
+            Ok(m) => Some(m),
+            Err(err) => {
+                tracing::warn!("Skipping plugin at {:?}: {}", e.path(), err);
+                None
+            }
+        })
+        .collect();
+    manifests.sort_by(|a, b| a.name.cmp(&b.name));
+    manifests
+}
+
+/// A plugin that has been loaded into the process
+pub struct LoadedPlugin {
+    pub manifest: PluginManifest,
+    library: PluginLibrary,
+}
+
+impl LoadedPlugin {
+    /// Call into the plugin. Blocking, so run it off the GTK main thread.
+    pub fn call(&self, kind: &str, name: &str, payload: &serde_json::Value) -> Result<serde_json::Value, String> {
+        self.library.call(kind, name, payload)
+    }
+}
+
+/// Tool backed by a plugin
+struct PluginTool {
+    definition: ToolDefinition,
+    plugin: Arc<LoadedPlugin>,
+}
+
+impl Tool for PluginTool {
+    fn definition(&self) -> &ToolDefinition {
+        &self.definition
+    }
+
+    fn call(&self, args: serde_json::Value) -> Result<serde_json::Value, String> {
+        self.plugin.call("tool", &self.definition.name, &args)
+    }
+}
+
+/// All loaded plugins and the surfaces they registered
+#[derive(Default)]
+pub struct PluginHost {
+    plugins: HashMap<String, Arc<LoadedPlugin>>,
+    ipc_routes: HashMap<String, Arc<LoadedPlugin>>,
+}
+
+impl PluginHost {
+    /// Load every enabled plugin whose permissions are granted.
+    /// Returns the host plus manifests that still need a permission grant from the user.
+    pub fn load_all(tools: &ToolRegistry) -> (PluginHost, Vec<PluginManifest>) {
+        let state = PluginState::load();
+        let mut host = PluginHost::default();
+        let mut pending = Vec::new();
+
+        for manifest in discover() {
+            if state.disabled.contains(&manifest.name) {
+                info!("Plugin '{}' is disabled, skipping", manifest.name);
+                continue;
+            }
+            let digest = manifest.library_digest();
+            if state.is_denied(&manifest, digest.as_deref()) {
+                info!("Plugin '{}' was denied, skipping", manifest.name);
+                continue;
+            }
+            if !state.is_granted(&manifest, digest.as_deref()) {
+                pending.push(manifest);
+                continue;
+            }
+            if let Err(e) = host.load(manifest.clone(), tools) {
+                tracing::warn!("Failed to load plugin '{}': {}", manifest.name, e);
+            }
+        }
+
+        (host, pending)
+    }
+
+    /// Load a single plugin and register its tools and IPC commands
+    pub fn load(&mut self, manifest: PluginManifest, tools: &ToolRegistry) -> Result<Arc<LoadedPlugin>, String> {
+        if self.plugins.contains_key(&manifest.name) {
+            return Err(format!("Plugin '{}' is already loaded", manifest.name));
+        }
+        if let Some(cmd) = manifest.ipc_commands.iter().find(|c| RESERVED_IPC_COMMANDS.contains(&c.as_str())) {
+            return Err(format!("IPC command '{}' is reserved by the overlay", cmd));
+        }
+        if let Some(cmd) = manifest.ipc_commands.iter().find(|c| self.ipc_routes.contains_key(*c)) {
+            return Err(format!("IPC command '{}' is already taken", cmd));
+        }
+
+        let library_path = manifest.dir.join(&manifest.library);
+        let library = PluginLibrary::load(&library_path, &manifest.name, manifest.required_permissions())?;
+        let plugin = Arc::new(LoadedPlugin { manifest, library });
+        let source = format!("plugin:{}", plugin.manifest.name);
+
+        for tool in &plugin.manifest.tools {
+            let definition = ToolDefinition {
+                name: tool.name.clone(),
+                description: tool.description.clone(),
+                parameters: tool.parameters.clone(),
+                source: source.clone(),
+                requires_confirmation: false,
+            };
+            let registered = tools.register(Arc::new(PluginTool {
+                definition,
+                plugin: plugin.clone(),
+            }));
+            if let Err(e) = registered {
+                tools.unregister_source(&source);
+                return Err(e);
+            }
+        }
+
+        for cmd in &plugin.manifest.ipc_commands {
+            self.ipc_routes.insert(cmd.clone(), plugin.clone());
+        }
+        self.plugins.insert(plugin.manifest.name.clone(), plugin.clone());
+
+        info!(
+            "Loaded plugin '{}' v{} ({} handlers, {} tools, {} IPC commands)",
+            plugin.manifest.name,
+            plugin.manifest.version,
+            plugin.manifest.handlers.len(),
+            plugin.manifest.tools.len(),
+            plugin.manifest.ipc_commands.len()
+        );
+        Ok(plugin)
+    }
+
+    pub fn plugins(&self) -> impl Iterator<Item = &Arc<LoadedPlugin>> {
+        self.plugins.values()
+    }
+
+    /// Find the plugin that handles an IPC command (matched on its first word)
+    pub fn ipc_route(&self, cmd: &str) -> Option<Arc<LoadedPlugin>> {
+        let verb = cmd.split_whitespace().next()?;
+        self.ipc_routes.get(verb).cloned()
+    }
+}
+
+/// Persist the user's grant of all permissions a plugin requests, to its library as it is now
+pub fn grant(manifest: &PluginManifest) -> std::io::Result<()> {
+    let digest = manifest.library_digest().ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::NotFound, format!("Can't read {}", manifest.library))
+    })?;
+    let mut state = PluginState::load();
+    let mut perms: Vec<Permission> = manifest.required_permissions().into_iter().collect();
+    perms.sort();
+    state.forget(&manifest.name);
+    state.granted.insert(manifest.name.clone(), perms);
+    state.libraries.insert(manifest.name.clone(), digest);
+    state.save()
+}
+
+/// Persist that the user turned a plugin down, so it isn't asked about again until its
+/// library changes
+pub fn deny(manifest: &PluginManifest) -> std::io::Result<()> {
+    let mut state = PluginState::load();
+    state.forget(&manifest.name);
+    if let Some(digest) = manifest.library_digest() {
+        state.denied.insert(manifest.name.clone(), digest);
+    }
+    state.save()
+}
+
+/// Actions for the `plugin` CLI subcommand
+#[derive(clap::Subcommand)]
+pub enum PluginAction {
+    /// List installed plugins and their status
+    List,
+    /// Install a plugin from a directory containing plugin.toml
+    Install { path: PathBuf },
+    /// Remove an installed plugin
+    Remove { name: String },
+    /// Enable a disabled plugin
+    Enable { name: String },
+    /// Disable a plugin without removing it
+    Disable { name: String },
+    /// Grant all permissions requested by a plugin, also after it was denied
+    Grant { name: String },
+    /// Revoke all permissions from a plugin
+    Revoke { name: String },
+}
+
+/// Run a `plugin` CLI subcommand. Changes take effect on the next overlay start.
+pub fn run_cli(action: PluginAction) -> anyhow::Result<()> {
+    let mut state = PluginState::load();
+    let find = |name: &str| {
+        discover()
+            .into_iter()
+            .find(|m| m.name == name)
+            .ok_or_else(|| anyhow::anyhow!(tr!("plugin-not-installed", name = name)))
+    };
+
+    match action {
+        PluginAction::List => {
+            let manifests = discover();
+            if manifests.is_empty() {
+                println!("{}", tr!("plugin-none-installed", dir = plugins_dir().display().to_string()));
+            }
+            for m in manifests {
+                let digest = m.library_digest();
+                let status = if state.disabled.contains(&m.name) {
+                    tr!("plugin-status-disabled")
+                } else if state.is_denied(&m, digest.as_deref()) {
+                    tr!("plugin-status-denied")
+                } else if state.is_granted(&m, digest.as_deref()) {
+                    tr!("plugin-status-enabled")
+                } else {
+                    tr!("plugin-status-awaiting")
+                };
+                let mut perms: Vec<String> = m.required_permissions().iter().map(|p| p.to_string()).collect();
+                perms.sort();
+                println!(
+                    "{}",
+                    tr!(
+                        "plugin-list-entry",
+                        name = m.name.as_str(),
+                        version = m.version.as_str(),
+                        status = status,
+                        permissions = perms.join(", "),
+                    )
+                );
+                if !m.description.is_empty() {
+                    println!("    {}", m.description);
+                }
+            }
+        }
+        PluginAction::Install { path } => {
+            let manifest = read_manifest(&path).map_err(|e| anyhow::anyhow!(e))?;
+            if !path.join(&manifest.library).is_file() {
+                anyhow::bail!(tr!("plugin-library-missing", library = manifest.library.as_str(), dir = path.display().to_string()));
+            }
+            let dest = plugins_dir().join(&manifest.name);
+            if dest.exists() {
+                anyhow::bail!(tr!("plugin-already-installed", name = manifest.name.as_str()));
+            }
+            copy_dir(&path, &dest)?;
+            println!("{}", tr!("plugin-installed", name = manifest.name.as_str(), dir = dest.display().to_string()));
+            println!("{}", tr!("plugin-installed-hint"));
+        }
+        PluginAction::Remove { name } => {
+            let manifest = find(&name)?;
+            std::fs::remove_dir_all(&manifest.dir)?;
+            state.forget(&name);
+            state.disabled.retain(|n| n != &name);
+            state.save()?;
+            println!("{}", tr!("plugin-removed", name = name.as_str()));
+        }
+        PluginAction::Enable { name } => {
+            find(&name)?;
+            state.disabled.retain(|n| n != &name);
+            state.save()?;
+            println!("{}", tr!("plugin-enabled", name = name.as_str()));
+        }
+        PluginAction::Disable { name } => {
+            find(&name)?;
+            if !state.disabled.contains(&name) {
+                state.disabled.push(name.clone());
+            }
+            state.save()?;
+            println!("{}", tr!("plugin-disabled", name = name.as_str()));
+        }
+        PluginAction::Grant { name } => {
+            grant(&find(&name)?)?;
+            println!("{}", tr!("plugin-granted", name = name.as_str()));
+        }
+        PluginAction::Revoke { name } => {
+            state.granted.remove(&name);
+            state.libraries.remove(&name);
+            state.save()?;
+            println!("{}", tr!("plugin-revoked", name = name.as_str()));
+        }
+    }
+    Ok(())
+}
+
+fn copy_dir(src: &Path, dest: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dest)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let target = dest.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
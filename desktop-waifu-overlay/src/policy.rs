@@ -0,0 +1,16 @@
+//! Command policy (`~/.local/share/desktop-waifu/policy.toml`); the rules and built-in
+//! denials are desktop-waifu-shell's
+
+pub use desktop_waifu_shell::policy::{Action, Policy};
+use std::path::PathBuf;
+
+pub fn policy_path() -> PathBuf {
+    gtk4::glib::user_data_dir()
+        .join("desktop-waifu")
+        .join("policy.toml")
+}
+
+/// policy.toml, read afresh so edits apply without a restart; an error when it's invalid
+pub fn load() -> Result<Policy, String> {
+    desktop_waifu_shell::policy::load(&policy_path(), crate::sandbox::scratch_dir())
+}
@@ -0,0 +1,95 @@
+//! Requests to the XDG desktop portal
+//!
+//! Portal methods return a request object right away; the outcome arrives later as a
+//! `Response` signal on it. The object's path is derived from our bus name and the
+//! `handle_token` we pass, so `request` subscribes to it before making the call.
+
+use gtk4::gio;
+use gtk4::glib;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+pub const PORTAL_BUS_NAME: &str = "org.freedesktop.portal.Desktop";
+pub const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const REQUEST_INTERFACE: &str = "org.freedesktop.portal.Request";
+
+static NEXT_TOKEN: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Debug, thiserror::Error)]
+pub enum PortalError {
+    #[error("cancelled")]
+    Cancelled,
+    #[error("{0}")]
+    Failed(String),
+}
+
+impl From<glib::Error> for PortalError {
+    fn from(e: glib::Error) -> Self {
+        PortalError::Failed(e.to_string())
+    }
+}
+
+/// A fresh token for `handle_token` (and `session_handle_token`) options
+pub fn token() -> String {
+    format!(
+        "desktop_waifu_{}_{}",
+        std::process::id(),
+        NEXT_TOKEN.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Call `method` on a portal `interface` and wait for its response. `parameters` builds the
+/// call's arguments around the given handle token, which must go in the options as
+/// `handle_token`. Returns the response's results.
+pub async fn request(
+    connection: &gio::DBusConnection,
+    interface: &str,
+    method: &str,
+    parameters: impl FnOnce(&str) -> glib::Variant,
+) -> Result<glib::VariantDict, PortalError> {
+    let token = token();
+    let sender = connection
+        .unique_name()
+        .ok_or_else(|| PortalError::Failed("no unique bus name".into()))?;
+    let request_path = format!(
+        "{}/request/{}/{}",
+        PORTAL_PATH,
+        sender.trim_start_matches(':').replace('.', "_"),
+        token
+    );
+
+    let (tx, rx) = async_channel::bounded(1);
+    let _subscription = connection.subscribe_to_signal(
+        Some(PORTAL_BUS_NAME),
+        Some(REQUEST_INTERFACE),
+        Some("Response"),
+        Some(&request_path),
+        None,
+        gio::DBusSignalFlags::NONE,
+        move |signal| {
+            let _ = tx.try_send(signal.parameters.clone());
+        },
+    );
+
+    connection
+        .call_future(
+            Some(PORTAL_BUS_NAME),
+            PORTAL_PATH,
+            interface,
+            method,
+            Some(&parameters(&token)),
+            Some(glib::VariantTy::new("(o)").unwrap()),
+            gio::DBusCallFlags::NONE,
+            -1,
+        )
+        .await?;
+
+    let response = rx
+        .recv()
+        .await
+        .map_err(|_| PortalError::Failed("no response".into()))?;
+    match response.child_value(0).get::<u32>() {
+        Some(0) => Ok(glib::VariantDict::new(Some(&response.child_value(1)))),
+        Some(1) => Err(PortalError::Cancelled),
+        code => Err(PortalError::Failed(format!("response code {:?}", code))),
+    }
+}
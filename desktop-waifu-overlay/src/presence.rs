@@ -0,0 +1,123 @@
+//! Meeting mode: go quiet while the user is on a call
+//!
+//! Polls PipeWire (through `pw-dump`) for other applications recording from a microphone
+//! or camera, or a screen being shared. While any of those is active the overlay is quiet:
+//! notifications and proactive character messages are suppressed, and the frontend is told
+//! so it can hold back anything audible. Everything resumes once the call ends.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tracing::info;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Presence {
+    pub microphone: bool,
+    pub camera: bool,
+    pub screen_share: bool,
+    /// Any of the above: the overlay should stay silent
+    pub quiet: bool,
+}
+
+/// True while another app is using the microphone/camera or the screen is being shared
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Start watching PipeWire; yields the presence state each time it changes
+pub fn spawn_monitor() -> async_channel::Receiver<Presence> {
+    let (tx, rx) = async_channel::unbounded();
+    let shutdown = crate::runtime::shutdown_token();
+
+    crate::runtime::spawn(async move {
+        let mut last = Presence::default();
+        loop {
+            match snapshot().await {
+                Ok(presence) if presence != last => {
+                    info!("Presence changed: {:?}", presence);
+                    QUIET.store(presence.quiet, Ordering::Relaxed);
+                    last = presence.clone();
+                    if tx.send(presence).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    info!("pw-dump not found, meeting mode disabled");
+                    break;
+                }
+                Err(e) => crate::debug_log!("[PRESENCE] PipeWire query failed: {}", e),
+            }
+
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            }
+        }
+    });
+
+    rx
+}
+
+async fn snapshot() -> std::io::Result<Presence> {
+    let output = tokio::process::Command::new("pw-dump")
+        .arg("--no-colors")
+        .kill_on_drop(true)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(std::io::Error::other(format!("pw-dump exited with {}", output.status)));
+    }
+    let objects: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout)?;
+    Ok(detect(&objects))
+}
+
+/// Parent of a process, from /proc/<pid>/stat ("pid (comm) state ppid ...")
+fn parent_pid(pid: &str) -> Option<String> {
+    if pid.is_empty() {
+        return None;
+    }
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // comm may contain spaces and parentheses; the fields after it can't
+    let (_, rest) = stat.rsplit_once(')')?;
+    rest.split_whitespace().nth(1).map(str::to_string)
+}
+
+fn detect(objects: &[serde_json::Value]) -> Presence {
+    let own_pid = std::process::id().to_string();
+    let mut presence = Presence::default();
+
+    for object in objects {
+        if object["type"] != "PipeWire:Interface:Node" || object["info"]["state"] != "running" {
+            continue;
+        }
+        let props = &object["info"]["props"];
+        // Our own capture (speech input, recorded by a child process) must not silence us
+        let pid = match &props["application.process.id"] {
+            serde_json::Value::Number(n) => n.to_string(),
+            serde_json::Value::String(s) => s.clone(),
+            _ => String::new(),
+        };
+        if pid == own_pid || parent_pid(&pid).is_some_and(|parent| parent == own_pid) {
+            continue;
+        }
+
+        match props["media.class"].as_str().unwrap_or("") {
+            // Peak meters and desktop-audio recorders capture sink monitors, not a microphone
+            "Stream/Input/Audio" if props["stream.monitor"] != true => presence.microphone = true,
+            "Stream/Input/Video" => presence.camera = true,
+            // Cameras are devices; screencast sources are created by the portal backend
+            "Video/Source" if props.get("device.api").is_some() => presence.camera = true,
+            "Video/Source" => presence.screen_share = true,
+            _ => {}
+        }
+    }
+
+    presence.quiet = presence.microphone || presence.camera || presence.screen_share;
+    presence
+}
@@ -0,0 +1,93 @@
+//! Running commands as root through pkexec
+//!
+//! polkit's authentication agent asks for the password in its own dialog, showing the
+//! command, so nothing privileged goes through a `sudo` prompt and no password goes
+//! through the chat. Packages install `desktop-waifu-privileged` and a polkit policy for it
+//! (packaging/polkit), which gives the dialog our own message and requires the password
+//! every time; without them pkexec's generic "run a program as another user" action is
+//! used.
+//!
+//! Used by `executePrivilegedCommand` and package installs (pkg.rs). Privileged commands
+//! always need the user's confirmation in the chat, whatever the command policy says.
+
+use crate::tools::manifest::run_with_timeout;
+use serde::Serialize;
+use std::ffi::OsStr;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+/// Installed by the packages; runs its arguments as a command (see packaging/polkit)
+const HELPER: &str = "/usr/lib/desktop-waifu/desktop-waifu-privileged";
+const TIMEOUT: Duration = Duration::from_secs(30 * 60);
+/// pkexec's exit code when the user dismissed the authorization dialog
+const PKEXEC_DISMISSED: i32 = 126;
+/// pkexec's exit code when authorization failed or there's no polkit agent to ask
+const PKEXEC_NOT_AUTHORIZED: i32 = 127;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// The command ran as root; see the exit code
+    Completed,
+    /// The user dismissed the authorization dialog
+    Cancelled,
+    /// Wrong password, or no polkit agent to ask
+    NotAuthorized,
+    TimedOut,
+    /// pkexec isn't installed
+    Unavailable,
+}
+
+/// What `executePrivilegedCommand` hands back
+#[derive(Debug)]
+pub struct PrivilegedOutput {
+    pub command: String,
+    pub outcome: Outcome,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub exit_code: i32,
+}
+
+/// `program args` as root, through the packaged helper when it's installed
+pub fn command<I, S>(program: &str, args: I) -> Command
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let mut cmd = Command::new("pkexec");
+    if Path::new(HELPER).exists() {
+        cmd.arg(HELPER);
+    }
+    cmd.arg(program).args(args);
+    cmd
+}
+
+/// Run `cmd` with `sh -c` as root and wait for it; blocks, so run it off the main thread
+pub fn run_shell(cmd: &str) -> PrivilegedOutput {
+    tracing::info!("Running privileged command: {}", cmd);
+    run(command("/bin/sh", ["-c", cmd]), cmd)
+}
+
+/// Wait for a command from `command`; `description` names it in logs and the result
+pub fn run(cmd: Command, description: &str) -> PrivilegedOutput {
+    let (stdout, stderr, exit_code, timed_out, spawned) = match run_with_timeout(cmd, None, TIMEOUT) {
+        Ok((stdout, stderr, exit_code, timed_out)) => (stdout, stderr, exit_code, timed_out, true),
+        Err(e) => (Vec::new(), e.into_bytes(), -1, false, false),
+    };
+    let outcome = match exit_code {
+        _ if !spawned => Outcome::Unavailable,
+        _ if timed_out => Outcome::TimedOut,
+        PKEXEC_DISMISSED => Outcome::Cancelled,
+        PKEXEC_NOT_AUTHORIZED => Outcome::NotAuthorized,
+        _ => Outcome::Completed,
+    };
+    tracing::info!("Privileged command finished ({:?}, exit code {}): {}", outcome, exit_code, description);
+    PrivilegedOutput {
+        command: description.to_string(),
+        outcome,
+        stdout,
+        stderr,
+        exit_code,
+    }
+}
@@ -0,0 +1,204 @@
+//! Operating profiles (`~/.config/desktop-waifu/profiles.toml`)
+//!
+//! A profile overrides a few settings at once — persona, model, notification rule, the
+//! tools the assistant may call and whether the overlay is shown — so one install can
+//! behave differently during work hours and in the evening. Profiles are switched from
+//! the tray, with `--profile NAME`, or automatically by their `schedule`:
+//!
+//! ```toml
+//! [profiles.work]
+//! personality = "assistant"
+//! llm_model = "gpt-4o"
+//! notifications = "off"
+//! allowed_tools = ["git_status"]
+//! schedule = [{ days = ["mon", "tue", "wed", "thu", "fri"], start = "09:00", end = "17:30" }]
+//!
+//! [profiles.evening]
+//! personality = "friend"
+//! visible = false
+//! schedule = [{ start = "22:00", end = "07:00" }]
+//! ```
+//!
+//! A manual switch holds until the schedule next changes which profile applies.
+
+use gtk4::glib;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tracing::info;
+
+const WEEKDAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    #[serde(skip)]
+    pub name: String,
+    /// Personality id, e.g. "assistant"
+    pub personality: Option<String>,
+    pub llm_provider: Option<String>,
+    pub llm_model: Option<String>,
+    /// Notification rule: "off", "unfocused" or "chat_closed"
+    pub notifications: Option<String>,
+    /// Tools the assistant may call; all tools when unset
+    pub allowed_tools: Option<Vec<String>>,
+    /// Show (true) or hide (false) the overlay when the profile activates
+    pub visible: Option<bool>,
+    pub schedule: Vec<ScheduleRule>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScheduleRule {
+    /// Weekdays ("mon".."sun"); every day when empty
+    #[serde(default)]
+    pub days: Vec<String>,
+    /// "HH:MM"; a range ending before it starts runs past midnight
+    pub start: String,
+    pub end: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ProfilesFile {
+    #[serde(default)]
+    profiles: BTreeMap<String, Profile>,
+}
+
+impl Profile {
+    /// Fields the frontend applies to its settings
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "name": self.name,
+            "personality": self.personality,
+            "llmProvider": self.llm_provider,
+            "llmModel": self.llm_model,
+            "notifications": self.notifications,
+            "allowedTools": self.allowed_tools,
+        })
+    }
+
+    fn scheduled_at(&self, weekday: usize, minute_of_day: u32) -> bool {
+        self.schedule.iter().any(|rule| rule.matches(weekday, minute_of_day))
+    }
+}
+
+impl ScheduleRule {
+    /// `weekday` is 0 = Monday .. 6 = Sunday
+    fn matches(&self, weekday: usize, minute_of_day: u32) -> bool {
+        let (Some(start), Some(end)) = (parse_time(&self.start), parse_time(&self.end)) else {
+            return false;
+        };
+        let on_day = |day: usize| {
+            self.days.is_empty() || self.days.iter().any(|d| d.eq_ignore_ascii_case(WEEKDAYS[day]))
+        };
+        if start <= end {
+            on_day(weekday) && (start..end).contains(&minute_of_day)
+        } else {
+            // Overnight: the part after midnight belongs to the previous day's rule
+            (on_day(weekday) && minute_of_day >= start)
+                || (on_day((weekday + 6) % 7) && minute_of_day < end)
+        }
+    }
+}
+
+/// "HH:MM" as minutes since midnight
+pub(crate) fn parse_time(time: &str) -> Option<u32> {
+    let (hours, minutes) = time.trim().split_once(':')?;
+    let (hours, minutes): (u32, u32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+pub fn profiles_path() -> PathBuf {
+    glib::user_config_dir()
+        .join("desktop-waifu")
+        .join("profiles.toml")
+}
+
+/// Profiles from profiles.toml, sorted by name; empty if the file is missing or invalid
+pub fn load() -> Vec<Profile> {
+    let path = profiles_path();
+    let Ok(text) = std::fs::read_to_string(&path) else {
+        return Vec::new();
+    };
+    match toml::from_str::<ProfilesFile>(&text) {
+        Ok(file) => file
+            .profiles
+            .into_iter()
+            .map(|(name, mut profile)| {
+                profile.name = name;
+                profile
+            })
+            .collect(),
+        Err(e) => {
+            tracing::warn!("Ignoring invalid {}: {}", path.display(), e);
+            Vec::new()
+        }
+    }
+}
+
+/// Loaded profiles and which one is active (None = plain settings)
+#[derive(Debug, Default)]
+pub struct ProfileManager {
+    profiles: Vec<Profile>,
+    active: Option<String>,
+    /// Profile the schedule selected at the last check, to notice schedule changes
+    last_scheduled: Option<Option<String>>,
+}
+
+impl ProfileManager {
+    pub fn load() -> Self {
+        let profiles = load();
+        if !profiles.is_empty() {
+            info!("Loaded {} profiles", profiles.len());
+        }
+        Self {
+            profiles,
+            ..Default::default()
+        }
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.profiles.iter().map(|p| p.name.clone()).collect()
+    }
+
+    pub fn active(&self) -> Option<&Profile> {
+        let name = self.active.as_ref()?;
+        self.profiles.iter().find(|p| &p.name == name)
+    }
+
+    /// Switch profiles; `None` returns to plain settings. Returns false for unknown names.
+    pub fn activate(&mut self, name: Option<&str>) -> bool {
+        if let Some(name) = name
+            && !self.profiles.iter().any(|p| p.name == name)
+        {
+            return false;
+        }
+        self.active = name.map(str::to_string);
+        true
+    }
+
+    /// The profile the schedule selects now, if that differs from the last check.
+    /// `Some(None)` means the schedule no longer selects any profile.
+    pub fn schedule_change(&mut self, now: &glib::DateTime) -> Option<Option<String>> {
+        let weekday = (now.day_of_week() as usize).saturating_sub(1) % 7;
+        let minute_of_day = (now.hour() * 60 + now.minute()) as u32;
+        let scheduled = self
+            .profiles
+            .iter()
+            .find(|p| p.scheduled_at(weekday, minute_of_day))
+            .map(|p| p.name.clone());
+
+        if self.last_scheduled.as_ref() == Some(&scheduled) {
+            return None;
+        }
+        self.last_scheduled = Some(scheduled.clone());
+        Some(scheduled)
+    }
+
+    /// Whether the active profile lets the assistant call `tool`
+    pub fn allows_tool(&self, tool: &str) -> bool {
+        match self.active().and_then(|p| p.allowed_tools.as_ref()) {
+            Some(allowed) => allowed.iter().any(|t| t == tool),
+            None => true,
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! Commands attached to a pseudo-terminal (desktop-waifu-shell's), read on the blocking pool
+//!
+//! `spawn` streams everything the command prints and hands back a `PtyInput` for typing
+//! into it: answers to prompts, passwords, Ctrl-C.
+
+use desktop_waifu_shell::pty;
+use std::fs::File;
+use std::io::Write;
+use std::process::Command;
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub enum PtyEvent {
+    Output(Vec<u8>),
+    /// The command exited; signals are reported as 128 + signal, like a shell does
+    Exit(i32),
+}
+
+/// Writes to a running command's terminal (cheap to clone)
+#[derive(Clone)]
+pub struct PtyInput {
+    master: Arc<File>,
+    pid: u32,
+}
+
+impl PtyInput {
+    pub fn write(&self, data: &[u8]) -> std::io::Result<()> {
+        (&*self.master).write_all(data)
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+}
+
+/// Start `command` (a `<shell> -c ...`) on a new PTY. Output and the exit code arrive on the
+/// returned receiver, read on the blocking pool until the command and everything holding its
+/// terminal are done.
+pub fn spawn(command: Command) -> std::io::Result<(PtyInput, async_channel::Receiver<PtyEvent>)> {
+    let pty = pty::spawn(command)?;
+    let input = PtyInput {
+        master: pty.input(),
+        pid: pty.pid(),
+    };
+
+    let (tx, rx) = async_channel::unbounded();
+    crate::runtime::spawn_blocking(move || {
+        pty.read(|bytes| tx.send_blocking(PtyEvent::Output(bytes.to_vec())).is_ok());
+        let _ = tx.send_blocking(PtyEvent::Exit(pty.wait().unwrap_or(-1)));
+    });
+
+    Ok((input, rx))
+}
@@ -0,0 +1,239 @@
+//! Reminders and timers (`~/.local/share/desktop-waifu/reminders.json`)
+//!
+//! Set from the CLI (`--remind "stand up" --in 25m`, sent as `AddReminder`), the frontend's
+//! `/remind` and `/pomodoro` commands (the `reminders` message handler) and IPC clients.
+//! Reminders are kept on disk, so they survive a restart; any that came due while the
+//! overlay wasn't running fire as soon as it starts, marked late. A one-shot timer on the
+//! main loop wakes for the soonest reminder and is re-armed whenever the list changes. Due
+//! times are wall-clock times, and the main loop's clock stops during suspend, so the timer
+//! never waits longer than `MAX_WAIT` before looking at the wall clock again.
+
+use gtk4::glib;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Longest the timer waits before checking the wall clock, so reminders that came due
+/// during suspend fire soon after resume
+const MAX_WAIT: Duration = Duration::from_secs(60);
+/// A reminder this far past due when the overlay starts is announced as late
+const LATE_AFTER_MS: i64 = 60_000;
+/// Longest accepted delay (a year), so typos like `--in 25000h` are caught
+const MAX_DELAY: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reminder {
+    pub id: u64,
+    pub text: String,
+    /// Milliseconds since the epoch, like the frontend's `Date.now()`
+    pub due: i64,
+    pub created_at: i64,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Saved {
+    next_id: u64,
+    reminders: Vec<Reminder>,
+}
+
+/// What `start` set up to fire reminders
+struct Firing {
+    on_due: Rc<dyn Fn(Reminder, bool)>,
+    /// When the overlay started; reminders due well before then are late
+    started: i64,
+    /// Wakes `fire_due` for the soonest reminder
+    timer: Option<glib::SourceId>,
+}
+
+thread_local! {
+    static REMINDERS: RefCell<Saved> = RefCell::new(Saved::default());
+    static FIRING: RefCell<Option<Firing>> = const { RefCell::new(None) };
+}
+
+fn reminders_path() -> PathBuf {
+    glib::user_data_dir().join("desktop-waifu").join("reminders.json")
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// A delay like `25m`, `1h30m`, `90s` or `2h`; a bare number is minutes
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let text = text.trim().to_lowercase();
+    let invalid = || format!("'{}' is not a duration (try 25m, 1h30m or 90s)", text);
+    if let Ok(minutes) = text.parse::<u64>() {
+        return check_delay(Duration::from_secs(minutes.saturating_mul(60))).ok_or_else(invalid);
+    }
+
+    let mut total: u64 = 0;
+    let mut number = String::new();
+    for c in text.chars().filter(|c| !c.is_whitespace()) {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(invalid()),
+        };
+        let value: u64 = number.parse().map_err(|_| invalid())?;
+        total = total.saturating_add(value.saturating_mul(unit));
+        number.clear();
+    }
+    if !number.is_empty() || total == 0 {
+        return Err(invalid());
+    }
+    check_delay(Duration::from_secs(total)).ok_or_else(invalid)
+}
+
+fn check_delay(delay: Duration) -> Option<Duration> {
+    (!delay.is_zero() && delay <= MAX_DELAY).then_some(delay)
+}
+
+fn save(saved: &Saved) {
+    let path = reminders_path();
+    let write = || -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(saved).map_err(std::io::Error::other)?;
+        // Write atomically so a crash mid-write can't lose every reminder
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, &path)
+    };
+    if let Err(e) = write() {
+        tracing::warn!("Could not save {}: {}", path.display(), e);
+    }
+}
+
+/// Pending reminders, soonest first
+pub fn list() -> Vec<Reminder> {
+    REMINDERS.with(|r| r.borrow().reminders.clone())
+}
+
+/// Remind the user of `text` after `delay`
+pub fn add(text: &str, delay: Duration) -> Result<Reminder, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("a reminder needs some text".to_string());
+    }
+    let delay = check_delay(delay).ok_or_else(|| "the delay must be between a second and a year".to_string())?;
+    let now = now_ms();
+    let reminder = REMINDERS.with(|r| {
+        let mut saved = r.borrow_mut();
+        saved.next_id += 1;
+        let reminder = Reminder {
+            id: saved.next_id,
+            text: text.to_string(),
+            due: now + delay.as_millis() as i64,
+            created_at: now,
+        };
+        saved.reminders.push(reminder.clone());
+        saved.reminders.sort_by_key(|r| r.due);
+        save(&saved);
+        reminder
+    });
+    tracing::info!("Reminder {} set for {}s from now", reminder.id, delay.as_secs());
+    arm();
+    Ok(reminder)
+}
+
+/// Drop a pending reminder; false if there is none with this id
+pub fn cancel(id: u64) -> bool {
+    let removed = REMINDERS.with(|r| {
+        let mut saved = r.borrow_mut();
+        let before = saved.reminders.len();
+        saved.reminders.retain(|r| r.id != id);
+        let removed = saved.reminders.len() != before;
+        if removed {
+            save(&saved);
+        }
+        removed
+    });
+    if removed {
+        arm();
+    }
+    removed
+}
+
+/// Load the saved reminders and start firing them; `on_due` gets each one as it comes due,
+/// with whether it came due while the overlay wasn't running
+pub fn start(on_due: impl Fn(Reminder, bool) + 'static) {
+    let path = reminders_path();
+    let loaded = match std::fs::read_to_string(&path) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring invalid {}: {}", path.display(), e);
+            Saved::default()
+        }),
+        Err(_) => Saved::default(),
+    };
+    if !loaded.reminders.is_empty() {
+        tracing::info!("{} reminder(s) pending", loaded.reminders.len());
+    }
+    REMINDERS.with(|r| *r.borrow_mut() = loaded);
+    FIRING.with(|firing| {
+        *firing.borrow_mut() = Some(Firing {
+            on_due: Rc::new(on_due),
+            started: now_ms(),
+            timer: None,
+        })
+    });
+    // Overdue reminders fire on the first pass of the main loop
+    arm();
+}
+
+/// Set the timer for the soonest reminder, replacing any set before
+fn arm() {
+    let soonest = REMINDERS.with(|r| r.borrow().reminders.iter().map(|r| r.due).min());
+    FIRING.with(|firing| {
+        let mut firing = firing.borrow_mut();
+        let Some(firing) = firing.as_mut() else { return };
+        if let Some(timer) = firing.timer.take() {
+            timer.remove();
+        }
+        let Some(due) = soonest else { return };
+        let wait = Duration::from_millis(due.saturating_sub(now_ms()).max(0) as u64).min(MAX_WAIT);
+        firing.timer = Some(glib::timeout_add_local_once(wait, || {
+            FIRING.with(|firing| {
+                if let Some(firing) = firing.borrow_mut().as_mut() {
+                    firing.timer = None;
+                }
+            });
+            fire_due();
+        }));
+    });
+}
+
+/// Fire every reminder that has come due, then wait for the next
+fn fire_due() {
+    let now = now_ms();
+    let due: Vec<Reminder> = REMINDERS.with(|r| {
+        let mut saved = r.borrow_mut();
+        let (due, pending): (Vec<_>, Vec<_>) =
+            std::mem::take(&mut saved.reminders).into_iter().partition(|r| r.due <= now);
+        saved.reminders = pending;
+        if !due.is_empty() {
+            save(&saved);
+        }
+        due
+    });
+    let handler = FIRING.with(|firing| firing.borrow().as_ref().map(|f| (f.on_due.clone(), f.started)));
+    if let Some((on_due, started)) = handler {
+        for reminder in due {
+            let late = reminder.due + LATE_AFTER_MS < started;
+            on_due(reminder, late);
+        }
+    }
+    arm();
+}
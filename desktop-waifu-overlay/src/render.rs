@@ -0,0 +1,163 @@
+//! How WebKit draws the page, and what to fall back on where the GPU path doesn't work
+//!
+//! WebKitGTK draws WebGL on the GPU through EGL and, since 2.42, hands its frames to GTK
+//! as DMA-BUFs. Where that breaks the character just isn't drawn, with nothing in the log:
+//! NVIDIA's driver mishandles the DMA-BUF renderer, and without EGL or a render node there
+//! is no GPU to draw with at all. `probe` runs at startup, before WebKit reads its
+//! environment, and picks a renderer for `[render] mode` (auto by default):
+//!
+//! - `gpu`: WebKit's defaults
+//! - `no-dmabuf`: `WEBKIT_DISABLE_DMABUF_RENDERER`, for NVIDIA's driver
+//! - `software`: no DMA-BUFs and Mesa's llvmpipe for WebGL (`LIBGL_ALWAYS_SOFTWARE`), for
+//!   machines without a usable GPU; slow, but the character shows. Without EGL at all,
+//!   hardware acceleration is turned off too (unless `[webkit] hardware_acceleration`
+//!   says otherwise) and only the chat works.
+//!
+//! Variables already set in the environment are left as they are. The outcome is logged and
+//! reported by `getRenderInfo` and `--status`.
+
+use crate::config::{HardwareAcceleration, RenderMode};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::ffi::CString;
+use std::path::Path;
+use std::sync::OnceLock;
+use webkit6::HardwareAccelerationPolicy;
+
+/// Variables that decide how WebKit renders, reported as they are after `probe`
+const VARIABLES: &[&str] = &[
+    "WEBKIT_DISABLE_DMABUF_RENDERER",
+    "WEBKIT_DISABLE_COMPOSITING_MODE",
+    "LIBGL_ALWAYS_SOFTWARE",
+    "GSK_RENDERER",
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderInfo {
+    /// `[render] mode`
+    pub mode: RenderMode,
+    /// What the overlay went with; never auto
+    pub renderer: RenderMode,
+    /// Why auto fell back, if it did
+    pub reason: Option<String>,
+    /// libEGL loads
+    pub egl: bool,
+    /// e.g. /dev/dri/renderD128
+    pub render_node: Option<String>,
+    /// Kernel driver behind the render node, e.g. "amdgpu", "i915" or "nvidia"
+    pub driver: Option<String>,
+    pub hardware_acceleration: bool,
+    pub environment: BTreeMap<String, String>,
+}
+
+static INFO: OnceLock<RenderInfo> = OnceLock::new();
+
+/// Pick a renderer for `mode` and set the environment up for it. Call once, before GTK and
+/// WebKit start.
+pub fn probe(mode: RenderMode) -> &'static RenderInfo {
+    INFO.get_or_init(|| {
+        let egl = egl_available();
+        let render_node = render_node();
+        let driver = render_node.as_deref().and_then(node_driver);
+
+        let (renderer, reason) = match mode {
+            RenderMode::Auto if !egl => (RenderMode::Software, Some("libEGL can't be loaded".to_string())),
+            RenderMode::Auto if render_node.is_none() => {
+                (RenderMode::Software, Some("no GPU render node in /dev/dri".to_string()))
+            }
+            RenderMode::Auto if driver.as_deref() == Some("nvidia") => {
+                (RenderMode::NoDmabuf, Some("NVIDIA's driver breaks WebKit's DMA-BUF renderer".to_string()))
+            }
+            RenderMode::Auto => (RenderMode::Gpu, None),
+            mode => (mode, None),
+        };
+        match renderer {
+            RenderMode::NoDmabuf => set_default("WEBKIT_DISABLE_DMABUF_RENDERER", "1"),
+            RenderMode::Software => {
+                set_default("WEBKIT_DISABLE_DMABUF_RENDERER", "1");
+                set_default("LIBGL_ALWAYS_SOFTWARE", "1");
+            }
+            RenderMode::Auto | RenderMode::Gpu => {}
+        }
+
+        let info = RenderInfo {
+            mode,
+            renderer,
+            reason,
+            egl,
+            render_node,
+            driver,
+            hardware_acceleration: policy(egl) == HardwareAccelerationPolicy::Always,
+            environment: VARIABLES
+                .iter()
+                .filter_map(|name| std::env::var(name).ok().map(|value| (name.to_string(), value)))
+                .collect(),
+        };
+        match &info.reason {
+            Some(reason) => tracing::warn!("Rendering with {:?}: {}", info.renderer, reason),
+            None => tracing::info!("Rendering with {:?}", info.renderer),
+        }
+        crate::debug_log!("[RENDER] {:?}", info);
+        info
+    })
+}
+
+/// What `probe` found, once it has run
+pub fn info() -> Option<&'static RenderInfo> {
+    INFO.get()
+}
+
+/// For the web view's settings: `[webkit] hardware_acceleration`, which with auto is off
+/// only when there's no EGL to draw with
+pub fn acceleration_policy() -> HardwareAccelerationPolicy {
+    policy(info().is_none_or(|info| info.egl))
+}
+
+fn policy(egl: bool) -> HardwareAccelerationPolicy {
+    match crate::config::current().webkit.hardware_acceleration {
+        HardwareAcceleration::Always => HardwareAccelerationPolicy::Always,
+        HardwareAcceleration::Never => HardwareAccelerationPolicy::Never,
+        HardwareAcceleration::Auto if egl => HardwareAccelerationPolicy::Always,
+        HardwareAcceleration::Auto => HardwareAccelerationPolicy::Never,
+    }
+}
+
+fn set_default(name: &str, value: &str) {
+    if std::env::var_os(name).is_none() {
+        // SAFETY: probe runs at startup, before GTK, WebKit or anything else that reads the
+        // environment from another thread
+        unsafe { std::env::set_var(name, value) };
+    }
+}
+
+fn egl_available() -> bool {
+    let name = CString::new("libEGL.so.1").expect("no NUL in the library name");
+    // SAFETY: dlopen with a valid C string; the handle is closed right away
+    unsafe {
+        let handle = libc::dlopen(name.as_ptr(), libc::RTLD_LAZY | libc::RTLD_LOCAL);
+        if handle.is_null() {
+            return false;
+        }
+        libc::dlclose(handle);
+    }
+    true
+}
+
+/// The first render node, the one Mesa and WebKit pick by default
+fn render_node() -> Option<String> {
+    let mut nodes: Vec<String> = std::fs::read_dir("/dev/dri")
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| name.starts_with("renderD"))
+        .collect();
+    nodes.sort();
+    nodes.first().map(|name| format!("/dev/dri/{}", name))
+}
+
+fn node_driver(node: &str) -> Option<String> {
+    let name = Path::new(node).file_name()?;
+    let driver = std::fs::read_link(Path::new("/sys/class/drm").join(name).join("device/driver")).ok()?;
+    Some(driver.file_name()?.to_string_lossy().into_owned())
+}
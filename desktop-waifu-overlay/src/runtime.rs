@@ -0,0 +1,91 @@
+//! Shared Tokio runtime for background services
+//!
+//! The static server, git watcher, IPC listener and companion supervisors all run as tasks
+//! on this one multi-threaded runtime instead of each owning a thread or runtime. Services
+//! watch `shutdown_token()` so `shutdown()` can stop them together before the process exits.
+
+use std::future::Future;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tokio::runtime::{Handle, Runtime};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use tracing::info;
+
+// How long services get to wind down before remaining tasks are dropped
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(3);
+
+struct Shared {
+    handle: Handle,
+    shutdown: CancellationToken,
+    tracker: TaskTracker,
+}
+
+static SHARED: OnceLock<Shared> = OnceLock::new();
+// Owned separately so shutdown() can take it and tear it down
+static RUNTIME: Mutex<Option<Runtime>> = Mutex::new(None);
+
+fn shared() -> &'static Shared {
+    SHARED.get_or_init(|| {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .thread_name("waifu-services")
+            .enable_all()
+            .build()
+            .expect("Failed to create Tokio runtime");
+        let handle = runtime.handle().clone();
+        *RUNTIME.lock().unwrap() = Some(runtime);
+        Shared {
+            handle,
+            shutdown: CancellationToken::new(),
+            tracker: TaskTracker::new(),
+        }
+    })
+}
+
+/// Handle to the shared runtime (started on first use)
+pub fn handle() -> &'static Handle {
+    &shared().handle
+}
+
+/// Spawn a service task; shutdown() waits for it to finish
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let shared = shared();
+    shared.tracker.spawn_on(future, &shared.handle)
+}
+
+/// Run blocking work on the runtime's blocking pool
+pub fn spawn_blocking<F, R>(work: F) -> JoinHandle<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    shared().handle.spawn_blocking(work)
+}
+
+/// Cancelled when the overlay is shutting down
+pub fn shutdown_token() -> CancellationToken {
+    shared().shutdown.clone()
+}
+
+/// Signal every service to stop, give them a moment to finish, then stop the runtime
+pub fn shutdown() {
+    let Some(shared) = SHARED.get() else { return };
+    let Some(runtime) = RUNTIME.lock().unwrap().take() else { return };
+
+    info!("Stopping background services");
+    shared.shutdown.cancel();
+    shared.tracker.close();
+    if runtime
+        .block_on(tokio::time::timeout(SHUTDOWN_GRACE, shared.tracker.wait()))
+        .is_err()
+    {
+        tracing::warn!("Background services did not stop within {:?}", SHUTDOWN_GRACE);
+    }
+    runtime.shutdown_timeout(Duration::from_millis(500));
+}
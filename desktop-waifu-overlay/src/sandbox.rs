@@ -0,0 +1,9 @@
+//! Sandboxed commands (`[sandbox]` in policy.toml); desktop-waifu-shell does the wrapping
+
+pub use desktop_waifu_shell::sandbox::apply;
+use std::path::PathBuf;
+
+/// Where sandboxed commands may always write
+pub fn scratch_dir() -> PathBuf {
+    gtk4::glib::user_data_dir().join("desktop-waifu").join("scratch")
+}
@@ -0,0 +1,88 @@
+//! Screenshots through the XDG desktop portal
+//!
+//! `capture` asks `org.freedesktop.portal.Screenshot` for a capture of the whole screen, or
+//! an interactive one where the user picks the region (or window) in the compositor's own
+//! UI, and returns the saved file. `ocr` extracts text with `tesseract` when it is installed.
+
+use crate::portal::{self, PortalError};
+use gtk4::gio;
+use gtk4::glib;
+use gtk4::prelude::*;
+use std::path::{Path, PathBuf};
+
+const SCREENSHOT_INTERFACE: &str = "org.freedesktop.portal.Screenshot";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScreenshotError {
+    #[error("screenshot cancelled")]
+    Cancelled,
+    #[error("screenshot portal failed: {0}")]
+    Portal(String),
+}
+
+impl From<glib::Error> for ScreenshotError {
+    fn from(e: glib::Error) -> Self {
+        ScreenshotError::Portal(e.to_string())
+    }
+}
+
+impl From<PortalError> for ScreenshotError {
+    fn from(e: PortalError) -> Self {
+        match e {
+            PortalError::Cancelled => ScreenshotError::Cancelled,
+            PortalError::Failed(message) => ScreenshotError::Portal(message),
+        }
+    }
+}
+
+/// Capture the screen, or with `interactive` let the user pick a region; returns the
+/// screenshot file
+pub async fn capture(interactive: bool) -> Result<PathBuf, ScreenshotError> {
+    let connection = gio::bus_get_future(gio::BusType::Session).await?;
+    let results = portal::request(&connection, SCREENSHOT_INTERFACE, "Screenshot", |token| {
+        let options = glib::VariantDict::new(None);
+        options.insert_value("handle_token", &token.to_variant());
+        options.insert_value("interactive", &interactive.to_variant());
+        options.insert_value("modal", &true.to_variant());
+        glib::Variant::tuple_from_iter(["".to_variant(), options.end()])
+    })
+    .await?;
+
+    let uri = results
+        .lookup::<String>("uri")
+        .ok()
+        .flatten()
+        .ok_or_else(|| ScreenshotError::Portal("no uri in response".into()))?;
+    gio::File::for_uri(&uri)
+        .path()
+        .ok_or_else(|| ScreenshotError::Portal(format!("not a local file: {}", uri)))
+}
+
+/// Text in the image, if `tesseract` is installed and finds any
+pub async fn ocr(path: &Path) -> Option<String> {
+    let path = path.to_path_buf();
+    let output = gio::spawn_blocking(move || {
+        std::process::Command::new("tesseract")
+            .arg(&path)
+            .arg("-")
+            .stderr(std::process::Stdio::null())
+            .output()
+    })
+    .await
+    .ok()?;
+
+    match output {
+        Ok(output) if output.status.success() => {
+            let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            (!text.is_empty()).then_some(text)
+        }
+        Ok(output) => {
+            crate::debug_log!("[SCREENSHOT] tesseract exited with {}", output.status);
+            None
+        }
+        Err(e) => {
+            crate::debug_log!("[SCREENSHOT] OCR unavailable: {}", e);
+            None
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! Embedded Rhai scripting for custom behaviors
+//!
+//! Scripts in `~/.config/desktop-waifu/scripts/*.rhai` define hook functions that the
+//! overlay calls when things happen:
+//!
+//! ```rhai
+//! fn on_idle(seconds) { if seconds > 600 { play_animation("Situps"); } }
+//! fn on_battery_low(percent) { say(`Battery at ${percent}%, plug me in!`); }
+//! fn on_message(role, text) { if text.contains("thanks") { set_expression("happy"); } }
+//! fn on_command_done(cmd, exit_code, output) { log(output); }
+//! ```
+//!
+//! The API exposed to scripts is deliberately small: `set_expression`, `play_animation`,
+//! `say`, `notify`, `log`, and `run_command`, which only runs commands listed verbatim in
+//! `scripts/approved-commands`, and then under the command policy and sandbox like any
+//! other command. The directory is watched and scripts reload on change.
+
+use crate::audit;
+use crate::tools::builtin::SHELL_TIMEOUT;
+use crate::tools::manifest::run_with_timeout;
+use gtk4::gio;
+use gtk4::glib;
+use gtk4::prelude::*;
+use rhai::{Dynamic, Engine, Scope, AST};
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+use tracing::info;
+
+// Guard against runaway scripts blocking the GTK main thread
+const MAX_OPERATIONS: u64 = 100_000;
+
+const APPROVED_COMMANDS_FILE: &str = "approved-commands";
+
+/// Actions requested by scripts that need the webview
+#[derive(Debug, Clone)]
+pub enum ScriptAction {
+    SetExpression(String),
+    PlayAnimation(String),
+    Say(String),
+}
+
+/// Run an approved command the way the `run_shell` tool does: the policy's denials still
+/// hold, it runs in the sandbox and the audit log records it. Being listed in
+/// `approved-commands` counts as the user's confirmation.
+fn run_approved(cmd: &str) -> Result<(Vec<u8>, i32), String> {
+    let policy = match crate::policy::load().and_then(|policy| policy.check(cmd, true).map(|()| policy)) {
+        Ok(policy) => policy,
+        Err(e) => {
+            tracing::warn!("Script command refused: {}: {}", e, cmd);
+            audit::record(audit::Kind::Command, cmd, audit::Outcome::Refused, Some(e.clone()));
+            return Err(e);
+        }
+    };
+    let mut command = std::process::Command::new("sh");
+    command.arg("-c").arg(cmd);
+    let command = crate::sandbox::apply(&policy, cmd, command).inspect_err(|e| {
+        audit::record(audit::Kind::Command, cmd, audit::Outcome::Failed, Some(e.clone()));
+    })?;
+    let result = run_with_timeout(command, None, SHELL_TIMEOUT);
+    match &result {
+        Ok((_, _, exit_code, _)) => audit::exit(audit::Kind::Command, cmd, *exit_code),
+        Err(e) => audit::record(audit::Kind::Command, cmd, audit::Outcome::Failed, Some(e.clone())),
+    }
+    result.map(|(stdout, _, exit_code, _)| (stdout, exit_code))
+}
+
+/// Loaded scripts and the engine that runs them (lives on the GTK main thread)
+pub struct ScriptHost {
+    engine: Engine,
+    scripts: Vec<(PathBuf, AST)>,
+    dir: PathBuf,
+    approved: Rc<RefCell<Vec<String>>>,
+    _monitor: Option<gio::FileMonitor>,
+}
+
+pub fn scripts_dir() -> PathBuf {
+    glib::user_config_dir().join("desktop-waifu").join("scripts")
+}
+
+impl ScriptHost {
+    /// Create the engine, load all scripts and start watching the scripts directory.
+    /// `on_action` receives requests that must be forwarded to the frontend.
+    pub fn start(on_action: Rc<dyn Fn(ScriptAction)>) -> Rc<RefCell<ScriptHost>> {
+        let dir = scripts_dir();
+        let approved = Rc::new(RefCell::new(Vec::new()));
+        let (done_tx, done_rx) = async_channel::unbounded::<(String, i32, String)>();
+
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.set_max_call_levels(32);
+
+        let action = on_action.clone();
+        engine.register_fn("set_expression", move |name: &str| action(ScriptAction::SetExpression(name.to_string())));
+        let action = on_action.clone();
+        engine.register_fn("play_animation", move |name: &str| action(ScriptAction::PlayAnimation(name.to_string())));
+        let action = on_action;
+        engine.register_fn("say", move |text: &str| action(ScriptAction::Say(text.to_string())));
+
+        engine.register_fn("notify", |title: &str, body: &str| {
+            if crate::presence::is_quiet() {
+                return;
+            }
+            if let Err(e) = notify_rust::Notification::new()
+                .summary(title)
+                .body(body)
+                .appname(&crate::tr!("app-name"))
+                .show()
+            {
+                tracing::warn!("Script notification failed: {}", e);
+            }
+        });
+        engine.register_fn("log", |message: &str| info!("[script] {}", message));
+
+        // run_command(cmd) -> bool: starts an approved command in the background; the
+        // result arrives later through on_command_done(cmd, exit_code, output)
+        let approved_for_run = approved.clone();
+        engine.register_fn("run_command", move |cmd: &str| -> bool {
+            if !approved_for_run.borrow().iter().any(|c| c == cmd) {
+                tracing::warn!("Script tried to run unapproved command: {}", cmd);
+                return false;
+            }
+            let cmd = cmd.to_string();
+            let tx = done_tx.clone();
+            crate::runtime::spawn_blocking(move || {
+                let (code, text) = match run_approved(&cmd) {
+                    Ok((stdout, code)) => (code, String::from_utf8_lossy(&stdout).to_string()),
+                    Err(e) => (-1, e),
+                };
+                let _ = tx.send_blocking((cmd, code, text));
+            });
+            true
+        });
+
+        let host = Rc::new(RefCell::new(ScriptHost {
+            engine,
+            scripts: Vec::new(),
+            dir: dir.clone(),
+            approved,
+            _monitor: None,
+        }));
+        host.borrow_mut().reload();
+
+        // Hot-reload when anything in the scripts directory changes
+        let _ = std::fs::create_dir_all(&dir);
+        match gio::File::for_path(&dir).monitor_directory(gio::FileMonitorFlags::NONE, None::<&gio::Cancellable>) {
+            Ok(monitor) => {
+                let host_weak = Rc::downgrade(&host);
+                let reload_pending = Rc::new(RefCell::new(false));
+                monitor.connect_changed(move |_, _, _, _| {
+                    // Editors emit bursts of events per save; coalesce them
+                    if reload_pending.replace(true) {
+                        return;
+                    }
+                    let host_weak = host_weak.clone();
+                    let reload_pending = reload_pending.clone();
+                    glib::timeout_add_local_once(Duration::from_millis(300), move || {
+                        *reload_pending.borrow_mut() = false;
+                        if let Some(host) = host_weak.upgrade() {
+                            host.borrow_mut().reload();
+                        }
+                    });
+                });
+                host.borrow_mut()._monitor = Some(monitor);
+            }
+            Err(e) => tracing::warn!("Failed to watch scripts directory: {}", e),
+        }
+
+        // Deliver background command results to on_command_done
+        let host_weak = Rc::downgrade(&host);
+        glib::spawn_future_local(async move {
+            while let Ok((cmd, code, output)) = done_rx.recv().await {
+                let Some(host) = host_weak.upgrade() else { break };
+                host.borrow().fire(
+                    "on_command_done",
+                    vec![cmd.into(), Dynamic::from_int(code as i64), output.into()],
+                );
+            }
+        });
+
+        host
+    }
+
+    /// Recompile every script and re-read the approved command list
+    pub fn reload(&mut self) {
+        *self.approved.borrow_mut() = std::fs::read_to_string(self.dir.join(APPROVED_COMMANDS_FILE))
+            .unwrap_or_default()
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(str::to_string)
+            .collect();
+
+        let mut files: Vec<PathBuf> = std::fs::read_dir(&self.dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|e| e.path())
+                    .filter(|p| p.extension().is_some_and(|ext| ext == "rhai"))
+                    .collect()
+            })
+            .unwrap_or_default();
+        files.sort();
+
+        self.scripts = files
+            .into_iter()
+            .filter_map(|path| match self.engine.compile_file(path.clone()) {
+                Ok(ast) => Some((path, ast)),
+                Err(e) => {
+                    tracing::warn!("Failed to compile script {:?}: {}", path, e);
+                    None
+                }
+            })
+            .collect();
+        info!("Loaded {} behavior scripts from {:?}", self.scripts.len(), self.dir);
+    }
+
+    /// Call `hook` in every script that defines it with a matching number of parameters
+    pub fn fire(&self, hook: &str, args: Vec<Dynamic>) {
+        for (path, ast) in &self.scripts {
+            let defined = ast
+                .iter_functions()
+                .any(|f| f.name == hook && f.params.len() == args.len());
+            if !defined {
+                continue;
+            }
+            crate::debug_log!("[SCRIPT] Calling {} in {:?}", hook, path);
+            if let Err(e) = self.engine.call_fn::<Dynamic>(&mut Scope::new(), ast, hook, args.clone()) {
+                tracing::warn!("Script {:?} failed in {}: {}", file_name(path), hook, e);
+            }
+        }
+    }
+}
+
+fn file_name(path: &Path) -> &str {
+    path.file_name().and_then(|n| n.to_str()).unwrap_or("?")
+}
+
+/// Battery charge of the first system battery that is discharging, if any
+pub fn discharging_battery_percent() -> Option<i64> {
+    crate::system::battery()
+        .filter(|battery| battery.discharging)
+        .map(|battery| battery.percent)
+}
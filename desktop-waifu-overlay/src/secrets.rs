@@ -0,0 +1,35 @@
+//! Secrets (API keys) in the desktop keyring
+//!
+//! Stored through the Secret Service API, so GNOME Keyring, KWallet or KeePassXC keep them
+//! encrypted and unlocked with the session, under the service name `desktop-waifu`. The
+//! frontend reaches these through `storeSecret`, `getSecret` and `deleteSecret`.
+//!
+//! The calls block on D-Bus (and may wait for the user to unlock the keyring): run them on
+//! `runtime::spawn_blocking`, whose threads have the Tokio context the D-Bus client needs.
+
+const SERVICE: &str = "desktop-waifu";
+
+fn entry(name: &str) -> keyring::Result<keyring::Entry> {
+    keyring::Entry::new(SERVICE, name)
+}
+
+pub fn store(name: &str, value: &str) -> keyring::Result<()> {
+    entry(name)?.set_password(value)
+}
+
+/// The secret called `name`, or `None` if there is none
+pub fn get(name: &str) -> keyring::Result<Option<String>> {
+    match entry(name)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Remove the secret called `name`; removing one that doesn't exist is not an error
+pub fn delete(name: &str) -> keyring::Result<()> {
+    match entry(name)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
@@ -1,9 +1,79 @@
-use axum::Router;
+//! Static file server for the built frontend
+//!
+//! Serves dist/ on a fixed localhost port so the page keeps one origin (and its
+//! localStorage) across restarts, plus the REST API and WebSocket (api.rs). Any local
+//! process can reach a localhost port, and web pages can through DNS rebinding, so:
+//!
+//! - requests must name a loopback host (`localhost`, `127.0.0.1`, `[::1]`) in `Host`;
+//!   a rebound domain name gets 421
+//! - the frontend needs this session's random token: the overlay loads the page with
+//!   `?session=<token>`, which sets a cookie for the rest; `X-Desktop-Waifu-Session` works
+//!   too. The API has its own token instead
+//! - paths are decoded and resolved inside dist/ before ServeDir sees them, so `..` and
+//!   symlinks can't reach other files
+//!
+//! `/asset-manifest.json` lists every file with its hash (frontend.rs). The dist being
+//! served can change while the overlay runs, when a frontend update is applied (`set_root`).
+
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use percent_encoding::percent_decode_str;
+use std::io::Read;
 use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, RwLock};
 use tower_http::services::ServeDir;
 use tracing::info;
 
+const SESSION_COOKIE: &str = "desktop_waifu_session";
+const SESSION_HEADER: &str = "x-desktop-waifu-session";
+
+/// The dist folder being served (canonical); None until the server starts, and with the
+/// Vite dev server
+static ROOT: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Where the static server listens, and the token the page has to load with
+pub struct StaticServer {
+    pub port: u16,
+    pub session: String,
+}
+
+impl StaticServer {
+    /// URL for the overlay's webview
+    pub fn url(&self) -> String {
+        format!("http://localhost:{}?overlay=true&session={}", self.port, self.session)
+    }
+}
+
+/// `url` without the session token, for logs
+pub fn redact(url: &str) -> &str {
+    url.split_once("&session=").map_or(url, |(base, _)| base)
+}
+
+/// 32 random bytes from the kernel, hex-encoded
+pub fn random_token() -> std::io::Result<String> {
+    let mut bytes = [0u8; 32];
+    std::fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// The dist folder being served, if the built frontend is
+pub fn root() -> Option<PathBuf> {
+    ROOT.read().unwrap().clone()
+}
+
+/// Serve `dist` from now on; requests already being answered finish from the old folder
+pub fn set_root(dist: &Path) -> std::io::Result<()> {
+    let root = dist.canonicalize()?;
+    info!("Serving the frontend from {:?}", root);
+    *ROOT.write().unwrap() = Some(root);
+    Ok(())
+}
+
 /// Find the dist directory containing built frontend assets
 pub fn find_dist_dir() -> Option<PathBuf> {
     let mut search_paths: Vec<PathBuf> = vec![
@@ -24,6 +94,11 @@ pub fn find_dist_dir() -> Option<PathBuf> {
         search_paths.insert(2, exe_dist);
     }
 
+    // A frontend update for this overlay, ahead of the one installed with it
+    if let Some(update) = crate::frontend::installed_update() {
+        search_paths.insert(2, update);
+    }
+
     for path in search_paths {
         if path.exists() && path.is_dir() && path.join("index.html").exists() {
             return Some(path);
@@ -34,18 +109,26 @@ pub fn find_dist_dir() -> Option<PathBuf> {
 }
 
 /// Start a static file server on a fixed port for localStorage persistence
-/// Returns the port number the server is listening on
-pub async fn start_static_server(dist_path: PathBuf) -> Result<u16, String> {
-    let serve_dir = ServeDir::new(&dist_path);
-    let app = Router::new().fallback_service(serve_dir);
+pub async fn start_static_server(dist_path: PathBuf, preferred_port: u16) -> Result<StaticServer, String> {
+    set_root(&dist_path).map_err(|e| format!("Failed to resolve {}: {}", dist_path.display(), e))?;
+    let session = random_token().map_err(|e| format!("Failed to create a session token: {}", e))?;
 
-    // Try fixed port 1421 first for localStorage persistence, fallback to random if unavailable
-    let preferred_port = 1421;
+    let frontend = Router::new()
+        .route("/asset-manifest.json", get(asset_manifest))
+        .fallback(serve_frontend)
+        .layer(middleware::from_fn_with_state(Arc::new(session.clone()), guard_frontend));
+    let app = Router::new()
+        .nest("/api/v1", crate::api::router())
+        .merge(crate::api::websocket_router())
+        .merge(frontend)
+        .layer(middleware::from_fn(check_host));
+
+    // Try the fixed port first for localStorage persistence, fallback to random if unavailable
     let addr = SocketAddr::from(([127, 0, 0, 1], preferred_port));
     let listener = match tokio::net::TcpListener::bind(addr).await {
         Ok(l) => l,
         Err(_) => {
-            // Fallback to random port if 1421 is in use
+            // Fallback to random port if the fixed one is in use
             let fallback_addr = SocketAddr::from(([127, 0, 0, 1], 0));
             tokio::net::TcpListener::bind(fallback_addr)
                 .await
@@ -60,22 +143,242 @@ pub async fn start_static_server(dist_path: PathBuf) -> Result<u16, String> {
 
     info!("Static file server starting on port {} serving {:?}", port, dist_path);
 
-    // Spawn the server in the background
-    tokio::spawn(async move {
-        axum::serve(listener, app).await.ok();
+    // Serve in the background until the overlay shuts down
+    let shutdown = crate::runtime::shutdown_token();
+    crate::runtime::spawn(async move {
+        axum::serve(listener, app)
+            .with_graceful_shutdown(shutdown.cancelled_owned())
+            .await
+            .ok();
     });
 
-    Ok(port)
+    Ok(StaticServer { port, session })
+}
+
+/// Refuse requests whose Host isn't loopback (DNS rebinding)
+async fn check_host(headers: HeaderMap, request: Request, next: Next) -> Response {
+    let host = headers.get(header::HOST).and_then(|value| value.to_str().ok()).unwrap_or_default();
+    if !is_loopback_host(host) {
+        tracing::warn!("Refused a request for host '{}' to {}", host, request.uri().path());
+        return StatusCode::MISDIRECTED_REQUEST.into_response();
+    }
+    next.run(request).await
+}
+
+/// Whether a Host header names this machine, with or without a port
+fn is_loopback_host(host: &str) -> bool {
+    // Strip the port, minding IPv6 brackets
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if !port.contains(']') => name,
+        _ => host,
+    };
+    matches!(name, "localhost" | "127.0.0.1" | "[::1]")
+}
+
+/// Files from the dist folder being served right now
+async fn serve_frontend(request: Request) -> Response {
+    let Some(root) = root() else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    match ServeDir::new(root).try_call(request).await {
+        Ok(response) => response.into_response(),
+        Err(e) => {
+            tracing::warn!("Failed to serve a frontend file: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// The served dist's manifest: the one it shipped with, or computed from its files
+async fn asset_manifest() -> Response {
+    let Some(root) = root() else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    match crate::runtime::spawn_blocking(move || crate::frontend::manifest(&root)).await {
+        Ok(Ok(manifest)) => Json(manifest).into_response(),
+        Ok(Err(e)) => {
+            tracing::warn!("Failed to build the asset manifest: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+/// Require the session token and keep paths inside dist/
+async fn guard_frontend(
+    State(session): State<Arc<String>>,
+    headers: HeaderMap,
+    request: Request,
+    next: Next,
+) -> Response {
+    let session = session.as_str();
+
+    let from_query = request
+        .uri()
+        .query()
+        .into_iter()
+        .flat_map(|query| query.split('&'))
+        .find_map(|pair| pair.strip_prefix("session="));
+    let from_cookie = headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .find_map(|cookie| cookie.trim().strip_prefix(SESSION_COOKIE)?.strip_prefix('='));
+    let from_header = headers.get(SESSION_HEADER).and_then(|value| value.to_str().ok());
+    let authorized = [from_query, from_cookie, from_header]
+        .into_iter()
+        .flatten()
+        .any(|token| crate::api::constant_time_eq(token.as_bytes(), session.as_bytes()));
+    if !authorized {
+        crate::debug_log!("[SERVER] Refused {} without the session token", request.uri().path());
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    if !root().is_some_and(|root| inside(&root, request.uri().path())) {
+        tracing::warn!("Refused a path outside the frontend: {}", request.uri().path());
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let set_cookie = from_query.is_some();
+    let mut response = next.run(request).await;
+    if set_cookie
+        && let Ok(cookie) = format!("{}={}; Path=/; HttpOnly; SameSite=Strict", SESSION_COOKIE, session).parse::<HeaderValue>()
+    {
+        response.headers_mut().append(header::SET_COOKIE, cookie);
+    }
+    response
+}
+
+/// Whether the URL path names something inside `root` once decoded and resolved; paths
+/// that don't exist pass, ServeDir answers them with 404
+fn inside(root: &Path, url_path: &str) -> bool {
+    let Ok(decoded) = percent_decode_str(url_path).decode_utf8() else {
+        return false;
+    };
+    let relative = Path::new(decoded.trim_start_matches('/'));
+    if relative.components().any(|c| !matches!(c, Component::Normal(_) | Component::CurDir)) {
+        return false;
+    }
+    match root.join(relative).canonicalize() {
+        Ok(resolved) => resolved.starts_with(root),
+        Err(_) => true,
+    }
 }
 
-/// Check if the Vite dev server is running on localhost:1420
-pub fn is_dev_server_available() -> bool {
+/// Check if the Vite dev server is running on localhost:`port`
+pub fn is_dev_server_available(port: u16) -> bool {
     use std::net::TcpStream;
     use std::time::Duration;
 
     TcpStream::connect_timeout(
-        &"127.0.0.1:1420".parse().unwrap(),
+        &SocketAddr::from(([127, 0, 0, 1], port)),
         Duration::from_millis(100),
     )
     .is_ok()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A dist/ with index.html and, next to it, a file that mustn't be served
+    fn dist(name: &str) -> PathBuf {
+        let base = std::env::temp_dir().join(format!("server-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        std::fs::create_dir_all(base.join("dist/assets")).unwrap();
+        std::fs::write(base.join("dist/index.html"), "<html></html>").unwrap();
+        std::fs::write(base.join("secret.txt"), "secret").unwrap();
+        base.join("dist").canonicalize().unwrap()
+    }
+
+    #[test]
+    fn inside_allows_files_in_dist() {
+        let root = dist("files");
+        assert!(inside(&root, "/"));
+        assert!(inside(&root, "/index.html"));
+        assert!(inside(&root, "/./index.html"));
+        let _ = std::fs::remove_dir_all(root.parent().unwrap());
+    }
+
+    #[test]
+    fn inside_refuses_encoded_parent_dirs() {
+        let root = dist("dots");
+        for path in [
+            "/../secret.txt",
+            "/assets/../index.html",
+            "/%2e%2e/secret.txt",
+            "/%2E%2E/secret.txt",
+            "/assets/%2e%2e/%2e%2e/secret.txt",
+            "/.%2e/secret.txt",
+        ] {
+            assert!(!inside(&root, path), "{} got through", path);
+        }
+        let _ = std::fs::remove_dir_all(root.parent().unwrap());
+    }
+
+    #[test]
+    fn inside_refuses_encoded_slashes() {
+        let root = dist("slashes");
+        assert!(!inside(&root, "/..%2fsecret.txt"));
+        assert!(!inside(&root, "/assets%2F..%2F..%2Fsecret.txt"));
+        // An encoded leading slash is still relative to dist/
+        assert!(inside(&root, "/%2Findex.html"));
+        let _ = std::fs::remove_dir_all(root.parent().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn inside_refuses_symlinks_out_of_dist() {
+        let root = dist("symlink");
+        let outside = root.parent().unwrap();
+        std::os::unix::fs::symlink(outside.join("secret.txt"), root.join("leak.txt")).unwrap();
+        std::os::unix::fs::symlink(outside, root.join("up")).unwrap();
+        std::os::unix::fs::symlink(root.join("index.html"), root.join("assets/home.html")).unwrap();
+        assert!(!inside(&root, "/leak.txt"));
+        assert!(!inside(&root, "/up/secret.txt"));
+        assert!(inside(&root, "/assets/home.html"));
+        let _ = std::fs::remove_dir_all(outside);
+    }
+
+    #[test]
+    fn inside_lets_missing_files_through() {
+        let root = dist("missing");
+        assert!(inside(&root, "/nope.js"));
+        assert!(inside(&root, "/assets/deeper/nope.js"));
+        // Still not when they'd be outside dist/
+        assert!(!inside(&root, "/%2e%2e/nope.js"));
+        let _ = std::fs::remove_dir_all(root.parent().unwrap());
+    }
+
+    #[test]
+    fn inside_refuses_invalid_utf8() {
+        let root = dist("utf8");
+        assert!(!inside(&root, "/%ff.html"));
+        let _ = std::fs::remove_dir_all(root.parent().unwrap());
+    }
+
+    #[test]
+    fn loopback_hosts_pass() {
+        for host in ["localhost", "localhost:5173", "127.0.0.1", "127.0.0.1:5173", "[::1]", "[::1]:5173"] {
+            assert!(is_loopback_host(host), "{} was refused", host);
+        }
+    }
+
+    #[test]
+    fn other_hosts_are_refused() {
+        for host in [
+            "",
+            "evil.com",
+            "evil.com:5173",
+            "localhost.evil.com",
+            "localhost.evil.com:5173",
+            "127.0.0.1.evil.com:5173",
+            "[::2]:5173",
+            "::1",
+            "0.0.0.0:5173",
+        ] {
+            assert!(!is_loopback_host(host), "{} got through", host);
+        }
+    }
+}
@@ -0,0 +1,10 @@
+//! Persistent shell sessions (desktop-waifu-shell's), started in the policy's sandbox
+
+pub use desktop_waifu_shell::session::{destroy, exec};
+
+/// Start a shell for session `id`, in the default sandbox when sandboxing is on
+pub fn create(id: String) -> Result<(), String> {
+    // Spawned on the shared runtime so the pipes belong to its reactor
+    let _guard = crate::runtime::handle().enter();
+    desktop_waifu_shell::session::create(id, crate::policy::load()?.default_sandbox())
+}
@@ -0,0 +1,11 @@
+//! Which shell runs the frontend's commands, and where (desktop-waifu-shell's `ShellOptions`)
+//!
+//! Commands run as `<shell> -c <cmd>`. Unless the frontend overrides them, the shell is the
+//! user's login shell (`$SHELL`, else `sh`) and the working directory is their home.
+
+pub use desktop_waifu_shell::options::ShellOptions;
+
+/// Options from an executeCommand message; missing or malformed fields mean defaults
+pub fn from_message(message: &serde_json::Value) -> ShellOptions {
+    serde_json::from_value(message.clone()).unwrap_or_default()
+}
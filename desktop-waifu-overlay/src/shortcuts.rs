@@ -0,0 +1,125 @@
+//! Global shortcuts through the XDG desktop portal
+//!
+//! Binds a toggle shortcut with `org.freedesktop.portal.GlobalShortcuts`, so users of
+//! GNOME, KDE or Hyprland don't have to add a keybinding to their compositor config. The
+//! preferred key is only a suggestion: the desktop may ask the user to confirm or change
+//! it, and remembers their choice. Where the portal is missing, a compositor keybinding
+//! running `desktop-waifu-overlay --toggle` (through the IPC socket) still works.
+
+use crate::portal::{self, PortalError};
+use crate::tr;
+use gtk4::gio;
+use gtk4::glib;
+use gtk4::prelude::*;
+use serde::Serialize;
+use tracing::info;
+
+const SHORTCUTS_INTERFACE: &str = "org.freedesktop.portal.GlobalShortcuts";
+/// Shortcut ids double as the IPC command they run
+const TOGGLE: &str = "toggle";
+
+/// Suggested key for the toggle shortcut, in the XDG shortcuts syntax
+pub const DEFAULT_TRIGGER: &str = "LOGO+w";
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShortcutStatus {
+    /// The portal bound the toggle shortcut
+    pub bound: bool,
+    /// How the desktop describes the key, e.g. "Super+W"
+    pub trigger: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum ShortcutEvent {
+    Bound(ShortcutStatus),
+    /// A shortcut was pressed; carries the IPC command to run
+    Activated(String),
+}
+
+/// Bind the toggle shortcut on the main loop, suggesting `preferred_trigger`. Nothing is
+/// sent if the portal is unavailable.
+pub fn spawn(preferred_trigger: String) -> async_channel::Receiver<ShortcutEvent> {
+    let (tx, rx) = async_channel::unbounded();
+    glib::spawn_future_local(async move {
+        if let Err(e) = run(&preferred_trigger, &tx).await {
+            info!("Global shortcuts portal unavailable, bind `desktop-waifu-overlay --toggle` in the compositor instead: {}", e);
+        }
+    });
+    rx
+}
+
+async fn run(preferred_trigger: &str, tx: &async_channel::Sender<ShortcutEvent>) -> Result<(), PortalError> {
+    let connection = gio::bus_get_future(gio::BusType::Session).await?;
+
+    let session_token = portal::token();
+    let results = portal::request(&connection, SHORTCUTS_INTERFACE, "CreateSession", |token| {
+        let options = glib::VariantDict::new(None);
+        options.insert_value("handle_token", &token.to_variant());
+        options.insert_value("session_handle_token", &session_token.to_variant());
+        glib::Variant::tuple_from_iter([options.end()])
+    })
+    .await?;
+    // Some portals send the handle as a string, others as an object path
+    let session = results
+        .lookup_value("session_handle", None)
+        .and_then(|v| v.str().map(str::to_string))
+        .ok_or_else(|| PortalError::Failed("no session handle".into()))?;
+    let session_path = glib::variant::ObjectPath::try_from(session.clone())
+        .map_err(|_| PortalError::Failed(format!("bad session handle {}", session)))?;
+
+    // Subscribe before binding so an immediate press isn't missed
+    let (activated_tx, activated_rx) = async_channel::unbounded();
+    let session_for_signal = session.clone();
+    let _subscription = connection.subscribe_to_signal(
+        Some(portal::PORTAL_BUS_NAME),
+        Some(SHORTCUTS_INTERFACE),
+        Some("Activated"),
+        Some(portal::PORTAL_PATH),
+        None,
+        gio::DBusSignalFlags::NONE,
+        move |signal| {
+            let parameters = signal.parameters;
+            if parameters.child_value(0).str() == Some(session_for_signal.as_str())
+                && let Some(id) = parameters.child_value(1).str()
+            {
+                let _ = activated_tx.try_send(id.to_string());
+            }
+        },
+    );
+
+    let shortcut = glib::VariantDict::new(None);
+    shortcut.insert_value("description", &tr!("shortcut-toggle").to_variant());
+    shortcut.insert_value("preferred_trigger", &preferred_trigger.to_variant());
+    let shortcuts = glib::Variant::array_from_iter_with_type(
+        glib::VariantTy::new("(sa{sv})").unwrap(),
+        [glib::Variant::tuple_from_iter([TOGGLE.to_variant(), shortcut.end()])],
+    );
+    let results = portal::request(&connection, SHORTCUTS_INTERFACE, "BindShortcuts", |token| {
+        let options = glib::VariantDict::new(None);
+        options.insert_value("handle_token", &token.to_variant());
+        glib::Variant::tuple_from_iter([session_path.to_variant(), shortcuts, "".to_variant(), options.end()])
+    })
+    .await?;
+
+    let trigger = results.lookup_value("shortcuts", None).and_then(|bound| {
+        bound.iter().find_map(|entry| {
+            (entry.child_value(0).str() == Some(TOGGLE))
+                .then(|| glib::VariantDict::new(Some(&entry.child_value(1))))
+                .and_then(|props| props.lookup::<String>("trigger_description").ok().flatten())
+        })
+    });
+    info!("Bound global shortcut through the portal: {}", trigger.as_deref().unwrap_or(preferred_trigger));
+    let status = ShortcutStatus { bound: true, trigger };
+    if tx.send(ShortcutEvent::Bound(status)).await.is_err() {
+        return Ok(());
+    }
+
+    while let Ok(id) = activated_rx.recv().await {
+        crate::debug_log!("[SHORTCUTS] Activated {}", id);
+        if tx.send(ShortcutEvent::Activated(id)).await.is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,109 @@
+//! Sound effects and notification chimes (the `playSound` handler)
+//!
+//! WebKit's audio on a hidden or unfocused layer surface is muted or cut off, so the
+//! frontend asks for sounds here and GStreamer (already loaded for WebKit) plays them.
+//! A sound is one of the bundled chimes, a file in ~/.local/share/desktop-waifu/sounds
+//! named without its extension, or a path. Chimes are held back like notifications: while
+//! they're muted from the tray, in meeting mode, or with `[sound] enabled = false`.
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gtk4::glib;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Bundled chimes; the frontend serves the same files for the Tauri build
+const CHIMES: &[(&str, &[u8])] = &[
+    ("message", include_bytes!("../../public/sounds/message.wav")),
+    ("notification", include_bytes!("../../public/sounds/notification.wav")),
+    ("error", include_bytes!("../../public/sounds/error.wav")),
+    ("reminder", include_bytes!("../../public/sounds/reminder.wav")),
+];
+const EXTENSIONS: [&str; 6] = ["wav", "ogg", "oga", "flac", "mp3", "opus"];
+
+static INIT: OnceLock<Result<(), String>> = OnceLock::new();
+static CHIME_DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+#[derive(Debug, thiserror::Error)]
+pub enum SoundError {
+    #[error("no sound named '{0}'")]
+    NotFound(String),
+    #[error("GStreamer: {0}")]
+    Gstreamer(String),
+}
+
+/// Start playing `name_or_path` at `volume` (0–1, scaled by `[sound] volume`) and return;
+/// does nothing while sounds are muted
+pub fn play(name_or_path: &str, volume: f64) -> Result<(), SoundError> {
+    let config = crate::config::current();
+    if !config.sound.enabled || crate::notifications::is_muted() || crate::presence::is_quiet() {
+        crate::debug_log!("[SOUND] Muted, not playing {}", name_or_path);
+        return Ok(());
+    }
+    INIT.get_or_init(|| gst::init().map_err(|e| e.to_string()))
+        .clone()
+        .map_err(SoundError::Gstreamer)?;
+
+    let path = resolve(name_or_path).ok_or_else(|| SoundError::NotFound(name_or_path.to_string()))?;
+    let uri = glib::filename_to_uri(&path, None).map_err(|e| SoundError::Gstreamer(e.to_string()))?;
+    let volume = (volume * config.sound.volume).clamp(0.0, 1.0);
+    crate::debug_log!("[SOUND] Playing {} at volume {:.2}", path.display(), volume);
+
+    let playbin = gst::ElementFactory::make("playbin")
+        .property("uri", uri.as_str())
+        .property("volume", volume)
+        .build()
+        .map_err(|e| SoundError::Gstreamer(e.to_string()))?;
+    // Video sinks would open a window for a file with a video stream
+    playbin.set_property("video-sink", gst::ElementFactory::make("fakesink").build().ok());
+    let bus = playbin.bus().ok_or_else(|| SoundError::Gstreamer("playbin has no bus".into()))?;
+    playbin
+        .set_state(gst::State::Playing)
+        .map_err(|e| SoundError::Gstreamer(e.to_string()))?;
+
+    // Hold the pipeline until it's done, then free it
+    crate::runtime::spawn_blocking(move || {
+        let message = bus.timed_pop_filtered(gst::ClockTime::NONE, &[gst::MessageType::Eos, gst::MessageType::Error]);
+        if let Some(gst::MessageView::Error(e)) = message.as_ref().map(|m| m.view()) {
+            tracing::warn!("Could not play {}: {}", path.display(), e.error());
+        }
+        let _ = playbin.set_state(gst::State::Null);
+    });
+    Ok(())
+}
+
+/// A bundled chime, a sound in the user's sounds dir, or an existing file
+fn resolve(name_or_path: &str) -> Option<PathBuf> {
+    if CHIMES.iter().any(|(name, _)| *name == name_or_path) {
+        let dir = CHIME_DIR.get_or_init(install_chimes).as_ref()?;
+        return Some(dir.join(format!("{}.wav", name_or_path)));
+    }
+    let user_dir = glib::user_data_dir().join("desktop-waifu").join("sounds");
+    let user_sound = EXTENSIONS
+        .iter()
+        .map(|ext| user_dir.join(format!("{}.{}", name_or_path, ext)))
+        .find(|path| path.is_file());
+    if user_sound.is_some() {
+        return user_sound;
+    }
+    let path = crate::files::expand(name_or_path);
+    path.is_file().then_some(path)
+}
+
+/// Write the bundled chimes under the runtime dir so playbin can open them, returning the
+/// directory
+fn install_chimes() -> Option<PathBuf> {
+    let dir = glib::user_runtime_dir().join("desktop-waifu").join("sounds");
+    let written = std::fs::create_dir_all(&dir).and_then(|_| {
+        CHIMES
+            .iter()
+            .try_for_each(|(name, wav)| std::fs::write(dir.join(format!("{}.wav", name)), wav))
+    });
+    match written {
+        Ok(()) => Some(dir),
+        Err(e) => {
+            tracing::warn!("Could not write chimes to {}: {}", dir.display(), e);
+            None
+        }
+    }
+}
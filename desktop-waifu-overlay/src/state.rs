@@ -0,0 +1,146 @@
+//! Persisted overlay state (`~/.local/share/desktop-waifu/state.json`)
+//!
+//! Holds what the overlay knows that the frontend's own stores don't: where the character
+//! sits on screen, on which monitor, at what scale, where the chat docks, whether the
+//! overlay was visible and whether notifications are muted.
+//! Restored in `build_ui`. Whatever changes it calls `changed`, and it's saved once things
+//! have been quiet for `SAVE_DELAY`, so a drag or a walk writes the file once.
+
+use gtk4::glib;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// How long after the last change the state is saved
+const SAVE_DELAY: Duration = Duration::from_secs(2);
+
+/// Set up by `watch`
+struct Watch {
+    save_if_changed: Rc<dyn Fn()>,
+    /// Pending save, pushed back by every change
+    timer: Option<glib::SourceId>,
+}
+
+thread_local! {
+    static WATCH: RefCell<Option<Watch>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct OverlayState {
+    /// Character position (absolute screen coordinates)
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+    /// Size of the monitor, in application pixels, the position was saved against; the
+    /// position is carried over if the monitor's scale has changed since
+    pub screen: Option<(i32, i32)>,
+    pub is_right_half: Option<bool>,
+    pub is_bottom_half: Option<bool>,
+    /// Character scale, as last set through IPC, the tray or the settings
+    pub scale: Option<f32>,
+    /// Chat dock mode, as last set through IPC
+    pub chat_dock: Option<crate::config::ChatDock>,
+    /// Connector name of the monitor hosting the character, e.g. "DP-1"
+    pub monitor: Option<String>,
+    pub visible: bool,
+    /// Notifications muted from the tray
+    pub notifications_muted: bool,
+}
+
+impl Default for OverlayState {
+    fn default() -> Self {
+        Self {
+            x: None,
+            y: None,
+            screen: None,
+            is_right_half: None,
+            is_bottom_half: None,
+            scale: None,
+            chat_dock: None,
+            monitor: None,
+            visible: true,
+            notifications_muted: false,
+        }
+    }
+}
+
+pub fn state_path() -> PathBuf {
+    gtk4::glib::user_data_dir()
+        .join("desktop-waifu")
+        .join("state.json")
+}
+
+/// The saved state, or defaults if there is none or it can't be read
+pub fn load() -> OverlayState {
+    let path = state_path();
+    match std::fs::read_to_string(&path) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring invalid {}: {}", path.display(), e);
+            OverlayState::default()
+        }),
+        Err(_) => OverlayState::default(),
+    }
+}
+
+/// Write the state atomically so a crash mid-write can't leave a truncated file
+pub fn save(state: &OverlayState) -> std::io::Result<()> {
+    let path = state_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let json = serde_json::to_string_pretty(state).map_err(std::io::Error::other)?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json)?;
+    std::fs::rename(&tmp, &path)
+}
+
+/// Save `snapshot(saved)` after `changed`, when it differs from the state last saved. The
+/// returned function saves any change right away, for shutdown.
+pub fn watch(
+    initial: OverlayState,
+    snapshot: impl Fn(&OverlayState) -> OverlayState + 'static,
+) -> Rc<dyn Fn()> {
+    let saved = RefCell::new(initial);
+    let save_if_changed: Rc<dyn Fn()> = Rc::new(move || {
+        let current = snapshot(&saved.borrow());
+        if current == *saved.borrow() {
+            return;
+        }
+        match save(&current) {
+            Ok(()) => *saved.borrow_mut() = current,
+            Err(e) => tracing::warn!("Failed to save overlay state: {}", e),
+        }
+    });
+    WATCH.with(|watch| {
+        *watch.borrow_mut() = Some(Watch {
+            save_if_changed: save_if_changed.clone(),
+            timer: None,
+        })
+    });
+    save_if_changed
+}
+
+/// Something in the state may have changed: save it `SAVE_DELAY` from now, unless it
+/// changes again before then
+pub fn changed() {
+    WATCH.with(|watch| {
+        let mut watch = watch.borrow_mut();
+        let Some(watch) = watch.as_mut() else { return };
+        if let Some(timer) = watch.timer.take() {
+            timer.remove();
+        }
+        watch.timer = Some(glib::timeout_add_local_once(SAVE_DELAY, || {
+            let save_if_changed = WATCH.with(|watch| {
+                let mut watch = watch.borrow_mut();
+                let watch = watch.as_mut()?;
+                watch.timer = None;
+                Some(watch.save_if_changed.clone())
+            });
+            if let Some(save_if_changed) = save_if_changed {
+                save_if_changed();
+            }
+        }));
+    });
+}
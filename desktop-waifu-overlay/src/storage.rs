@@ -0,0 +1,466 @@
+//! Conversation history in SQLite
+//!
+//! Chats live in `history.db` under the user data dir (`~/.local/share/desktop-waifu`),
+//! outside WebKit's storage, so clearing the web cache on a version change doesn't lose them.
+//! Conversations hold messages, messages hold image attachments (stored as blobs), and an
+//! FTS5 index over message text backs `search`. The frontend reaches everything through the
+//! `history` message handler. The same database keeps long-term memories (see memory.rs).
+//!
+//! All calls block on the database: run them off the main thread.
+
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Bumped whenever `migrate` gains a step
+const SCHEMA_VERSION: i32 = 2;
+
+/// Search results returned when the caller doesn't ask for a limit
+const DEFAULT_SEARCH_LIMIT: u32 = 50;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    #[error("Could not create {}: {source}", path.display())]
+    CreateDir { path: PathBuf, source: std::io::Error },
+    #[error("History database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("Attachment {0} is not valid base64")]
+    InvalidAttachment(String),
+    #[error("No conversation with id {0}")]
+    NoConversation(i64),
+}
+
+pub type Result<T> = std::result::Result<T, StorageError>;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Conversation {
+    pub id: i64,
+    pub title: String,
+    /// Milliseconds since the epoch, like the frontend's `Date.now()`
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub message_count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Message {
+    /// The frontend's message id, so it can update and delete messages it already has
+    pub id: String,
+    pub role: String,
+    pub content: String,
+    pub timestamp: i64,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub id: String,
+    pub mime_type: String,
+    /// Base64 without a `data:` prefix, as the frontend holds images
+    pub data: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub conversation_id: i64,
+    pub conversation_title: String,
+    pub message_id: String,
+    pub role: String,
+    /// The matching part of the message, with matches wrapped in `<mark>`
+    pub snippet: String,
+    pub timestamp: i64,
+}
+
+/// A long-term memory with the embedding it was saved with, if one could be made
+#[derive(Debug)]
+pub struct StoredMemory {
+    pub id: i64,
+    pub text: String,
+    pub created_at: i64,
+    /// Embedding model and vector
+    pub embedding: Option<(String, Vec<f32>)>,
+}
+
+/// Opened on first use, so the database isn't touched until the frontend asks for history
+static DB: LazyLock<Mutex<Option<Connection>>> = LazyLock::new(|| Mutex::new(None));
+
+pub fn db_path() -> PathBuf {
+    gtk4::glib::user_data_dir()
+        .join("desktop-waifu")
+        .join("history.db")
+}
+
+fn open() -> Result<Connection> {
+    let path = db_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|source| StorageError::CreateDir { path: parent.to_path_buf(), source })?;
+    }
+    let conn = Connection::open(&path)?;
+    conn.pragma_update(None, "journal_mode", "WAL")?;
+    conn.pragma_update(None, "foreign_keys", true)?;
+    migrate(&conn)?;
+    tracing::info!("Opened conversation history at {}", path.display());
+    Ok(conn)
+}
+
+fn migrate(conn: &Connection) -> Result<()> {
+    let version: i32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if version < 1 {
+        conn.execute_batch(
+            "BEGIN;
+             CREATE TABLE conversations (
+                 id INTEGER PRIMARY KEY,
+                 title TEXT NOT NULL,
+                 created_at INTEGER NOT NULL,
+                 updated_at INTEGER NOT NULL
+             );
+             CREATE TABLE messages (
+                 seq INTEGER PRIMARY KEY,
+                 id TEXT NOT NULL UNIQUE,
+                 conversation_id INTEGER NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+                 role TEXT NOT NULL,
+                 content TEXT NOT NULL,
+                 created_at INTEGER NOT NULL
+             );
+             CREATE INDEX messages_by_conversation ON messages(conversation_id, created_at);
+             CREATE TABLE attachments (
+                 id TEXT PRIMARY KEY,
+                 message_id TEXT NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+                 mime_type TEXT NOT NULL,
+                 data BLOB NOT NULL
+             );
+             CREATE INDEX attachments_by_message ON attachments(message_id);
+             CREATE VIRTUAL TABLE messages_fts USING fts5(
+                 content, content='messages', content_rowid='seq'
+             );
+             CREATE TRIGGER messages_ai AFTER INSERT ON messages BEGIN
+                 INSERT INTO messages_fts(rowid, content) VALUES (new.seq, new.content);
+             END;
+             CREATE TRIGGER messages_ad AFTER DELETE ON messages BEGIN
+                 INSERT INTO messages_fts(messages_fts, rowid, content) VALUES ('delete', old.seq, old.content);
+             END;
+             CREATE TRIGGER messages_au AFTER UPDATE OF content ON messages BEGIN
+                 INSERT INTO messages_fts(messages_fts, rowid, content) VALUES ('delete', old.seq, old.content);
+                 INSERT INTO messages_fts(rowid, content) VALUES (new.seq, new.content);
+             END;
+             COMMIT;",
+        )?;
+    }
+    if version < 2 {
+        conn.execute_batch(
+            "CREATE TABLE memories (
+                 id INTEGER PRIMARY KEY,
+                 text TEXT NOT NULL,
+                 embedding BLOB,
+                 embedding_model TEXT,
+                 created_at INTEGER NOT NULL
+             );",
+        )?;
+    }
+    conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+    Ok(())
+}
+
+/// Run `f` with the database, opening it first if needed
+fn with_db<T>(f: impl FnOnce(&mut Connection) -> Result<T>) -> Result<T> {
+    let mut db = DB.lock().unwrap_or_else(|e| e.into_inner());
+    if db.is_none() {
+        *db = Some(open()?);
+    }
+    f(db.as_mut().expect("opened above"))
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// All conversations, most recently active first
+pub fn list_conversations() -> Result<Vec<Conversation>> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT c.id, c.title, c.created_at, c.updated_at,
+                    (SELECT COUNT(*) FROM messages m WHERE m.conversation_id = c.id)
+             FROM conversations c ORDER BY c.updated_at DESC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(Conversation {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                created_at: row.get(2)?,
+                updated_at: row.get(3)?,
+                message_count: row.get(4)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    })
+}
+
+pub fn create_conversation(title: &str) -> Result<Conversation> {
+    with_db(|conn| {
+        let now = now_ms();
+        conn.execute(
+            "INSERT INTO conversations (title, created_at, updated_at) VALUES (?1, ?2, ?2)",
+            params![title, now],
+        )?;
+        Ok(Conversation {
+            id: conn.last_insert_rowid(),
+            title: title.to_string(),
+            created_at: now,
+            updated_at: now,
+            message_count: 0,
+        })
+    })
+}
+
+/// Returns whether the conversation existed
+pub fn rename_conversation(id: i64, title: &str) -> Result<bool> {
+    with_db(|conn| {
+        let changed = conn.execute("UPDATE conversations SET title = ?1 WHERE id = ?2", params![title, id])?;
+        Ok(changed > 0)
+    })
+}
+
+/// Delete a conversation with its messages and attachments; returns whether it existed
+pub fn delete_conversation(id: i64) -> Result<bool> {
+    with_db(|conn| {
+        let changed = conn.execute("DELETE FROM conversations WHERE id = ?1", [id])?;
+        Ok(changed > 0)
+    })
+}
+
+/// A conversation's messages in order, with their attachments
+pub fn messages(conversation_id: i64) -> Result<Vec<Message>> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, role, content, created_at FROM messages
+             WHERE conversation_id = ?1 ORDER BY created_at, seq",
+        )?;
+        let mut messages = stmt
+            .query_map([conversation_id], |row| {
+                Ok(Message {
+                    id: row.get(0)?,
+                    role: row.get(1)?,
+                    content: row.get(2)?,
+                    timestamp: row.get(3)?,
+                    attachments: Vec::new(),
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut stmt = conn.prepare("SELECT id, mime_type, data FROM attachments WHERE message_id = ?1 ORDER BY rowid")?;
+        for message in &mut messages {
+            let rows = stmt.query_map([&message.id], |row| {
+                let data: Vec<u8> = row.get(2)?;
+                Ok(Attachment {
+                    id: row.get(0)?,
+                    mime_type: row.get(1)?,
+                    data: gtk4::glib::base64_encode(&data).to_string(),
+                })
+            })?;
+            message.attachments = rows.collect::<rusqlite::Result<_>>()?;
+        }
+        Ok(messages)
+    })
+}
+
+/// Save a message (and its attachments) to a conversation, replacing the content of one
+/// already saved under the same id
+pub fn save_message(conversation_id: i64, message: &Message) -> Result<()> {
+    // Decode before taking the lock so a bad attachment fails without touching the database
+    let attachments = message
+        .attachments
+        .iter()
+        .map(|a| decode_attachment(a).map(|data| (a, data)))
+        .collect::<Result<Vec<_>>>()?;
+
+    with_db(|conn| {
+        let tx = conn.transaction()?;
+        let exists = tx
+            .query_row("SELECT 1 FROM conversations WHERE id = ?1", [conversation_id], |_| Ok(()))
+            .optional()?;
+        if exists.is_none() {
+            return Err(StorageError::NoConversation(conversation_id));
+        }
+        tx.execute(
+            "INSERT INTO messages (id, conversation_id, role, content, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(id) DO UPDATE SET content = excluded.content",
+            params![message.id, conversation_id, message.role, message.content, message.timestamp],
+        )?;
+        for (attachment, data) in &attachments {
+            insert_attachment(&tx, &message.id, attachment, data)?;
+        }
+        tx.execute(
+            "UPDATE conversations SET updated_at = ?1 WHERE id = ?2",
+            params![now_ms(), conversation_id],
+        )?;
+        tx.commit()?;
+        Ok(())
+    })
+}
+
+/// Returns whether the message existed
+pub fn update_message(id: &str, content: &str) -> Result<bool> {
+    with_db(|conn| {
+        let changed = conn.execute("UPDATE messages SET content = ?1 WHERE id = ?2", params![content, id])?;
+        Ok(changed > 0)
+    })
+}
+
+/// Delete messages by id along with their attachments; returns how many existed
+pub fn delete_messages(ids: &[String]) -> Result<usize> {
+    with_db(|conn| {
+        let tx = conn.transaction()?;
+        let mut deleted = 0;
+        for id in ids {
+            deleted += tx.execute("DELETE FROM messages WHERE id = ?1", [id])?;
+        }
+        tx.commit()?;
+        Ok(deleted)
+    })
+}
+
+/// Attach an image to a saved message
+pub fn add_attachment(message_id: &str, attachment: &Attachment) -> Result<()> {
+    let data = decode_attachment(attachment)?;
+    with_db(|conn| insert_attachment(conn, message_id, attachment, &data))
+}
+
+/// Returns whether the attachment existed
+pub fn delete_attachment(id: &str) -> Result<bool> {
+    with_db(|conn| {
+        let changed = conn.execute("DELETE FROM attachments WHERE id = ?1", [id])?;
+        Ok(changed > 0)
+    })
+}
+
+fn decode_attachment(attachment: &Attachment) -> Result<Vec<u8>> {
+    // glib's decoder skips characters it doesn't know, so check first rather than store garbage
+    let data = &attachment.data;
+    let valid = data.len().is_multiple_of(4)
+        && data.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'/' | b'='));
+    if !valid {
+        return Err(StorageError::InvalidAttachment(attachment.id.clone()));
+    }
+    Ok(gtk4::glib::base64_decode(data))
+}
+
+fn insert_attachment(conn: &Connection, message_id: &str, attachment: &Attachment, data: &[u8]) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO attachments (id, message_id, mime_type, data) VALUES (?1, ?2, ?3, ?4)",
+        params![attachment.id, message_id, attachment.mime_type, data],
+    )?;
+    Ok(())
+}
+
+/// Full-text search over every saved message, best matches first
+///
+/// Each word of `query` must appear (the last as a prefix, so results show up while the
+/// user is still typing); FTS5 query syntax in the input is treated as plain text.
+pub fn search(query: &str, limit: Option<u32>) -> Result<Vec<SearchHit>> {
+    let Some(fts_query) = fts_query(query) else {
+        return Ok(Vec::new());
+    };
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT m.conversation_id, c.title, m.id, m.role,
+                    snippet(messages_fts, 0, '<mark>', '</mark>', '…', 16), m.created_at
+             FROM messages_fts
+             JOIN messages m ON m.seq = messages_fts.rowid
+             JOIN conversations c ON c.id = m.conversation_id
+             WHERE messages_fts MATCH ?1
+             ORDER BY rank LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![fts_query, limit.unwrap_or(DEFAULT_SEARCH_LIMIT)], |row| {
+            Ok(SearchHit {
+                conversation_id: row.get(0)?,
+                conversation_title: row.get(1)?,
+                message_id: row.get(2)?,
+                role: row.get(3)?,
+                snippet: row.get(4)?,
+                timestamp: row.get(5)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    })
+}
+
+pub fn insert_memory(text: &str, embedding: Option<(&str, &[f32])>) -> Result<StoredMemory> {
+    with_db(|conn| {
+        let now = now_ms();
+        let (model, blob) = match embedding {
+            Some((model, vector)) => (Some(model), Some(vector.iter().flat_map(|x| x.to_le_bytes()).collect::<Vec<u8>>())),
+            None => (None, None),
+        };
+        conn.execute(
+            "INSERT INTO memories (text, embedding, embedding_model, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![text, blob, model, now],
+        )?;
+        Ok(StoredMemory {
+            id: conn.last_insert_rowid(),
+            text: text.to_string(),
+            created_at: now,
+            embedding: embedding.map(|(model, vector)| (model.to_string(), vector.to_vec())),
+        })
+    })
+}
+
+/// Every memory, oldest first
+pub fn memories() -> Result<Vec<StoredMemory>> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare("SELECT id, text, created_at, embedding, embedding_model FROM memories ORDER BY id")?;
+        let rows = stmt.query_map([], |row| {
+            let blob: Option<Vec<u8>> = row.get(3)?;
+            let model: Option<String> = row.get(4)?;
+            let embedding = model.zip(blob).map(|(model, blob)| {
+                let vector = blob
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                    .collect();
+                (model, vector)
+            });
+            Ok(StoredMemory {
+                id: row.get(0)?,
+                text: row.get(1)?,
+                created_at: row.get(2)?,
+                embedding,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    })
+}
+
+/// Returns whether the memory existed
+pub fn delete_memory(id: i64) -> Result<bool> {
+    with_db(|conn| {
+        let changed = conn.execute("DELETE FROM memories WHERE id = ?1", [id])?;
+        Ok(changed > 0)
+    })
+}
+
+/// Quote each word so user input can't be parsed as FTS5 operators
+fn fts_query(query: &str) -> Option<String> {
+    let words: Vec<String> = query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect();
+    let last = words.len().checked_sub(1)?;
+    Some(
+        words
+            .iter()
+            .enumerate()
+            .map(|(i, word)| if i == last { format!("{}*", word) } else { word.clone() })
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}
@@ -0,0 +1,239 @@
+//! Speech input with a local Whisper model (the `stt` cargo feature)
+//!
+//! `start` records the default microphone with `pw-record`, `parecord` or `arecord` and
+//! transcribes on a worker thread with whisper.cpp. While the user speaks, the utterance so
+//! far is transcribed again about once a second for a partial transcript; after a pause
+//! (or `stop`) it is transcribed one last time for the final one. Builds with `stt-vulkan`
+//! or `stt-cuda` run the model on the GPU.
+
+use crate::config::SttConfig;
+use gtk4::glib;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+/// Whisper's input format
+const SAMPLE_RATE: usize = 16000;
+/// Audio read per step (100 ms)
+const CHUNK_SAMPLES: usize = SAMPLE_RATE / 10;
+/// New audio between partial transcripts
+const PARTIAL_EVERY_SAMPLES: usize = SAMPLE_RATE;
+/// RMS level (of full scale) above which a chunk counts as speech
+const SPEECH_LEVEL: f32 = 0.015;
+/// Whisper hallucinates on very short input; pad utterances to at least this
+const MIN_SAMPLES: usize = SAMPLE_RATE + SAMPLE_RATE / 10;
+/// Whisper's window is 30 s; longer speech without a pause is cut into utterances here
+const MAX_SAMPLES: usize = 28 * SAMPLE_RATE;
+
+/// Set to stop the recording in progress
+static CURRENT: Mutex<Option<Arc<AtomicBool>>> = Mutex::new(None);
+/// The loaded model, kept between recordings
+static MODEL: Mutex<Option<(PathBuf, Arc<WhisperContext>)>> = Mutex::new(None);
+
+#[derive(Debug, thiserror::Error)]
+pub enum SttError {
+    #[error("no Whisper model at {0}")]
+    NoModel(String),
+    #[error("no audio recorder found; install pipewire, pulseaudio-utils or alsa-utils")]
+    NoRecorder,
+    #[error("recording failed: {0}")]
+    Recording(#[from] std::io::Error),
+    #[error("whisper: {0}")]
+    Whisper(#[from] whisper_rs::WhisperError),
+}
+
+pub enum SttEvent {
+    /// The utterance so far; later partials and the final transcript replace it
+    Partial(String),
+    /// A finished utterance
+    Final(String),
+    /// Recording ended, because of `stop` or an error
+    Stopped(Option<SttError>),
+}
+
+/// Start listening, stopping any recording in progress
+pub fn start(config: SttConfig) -> async_channel::Receiver<SttEvent> {
+    let (tx, rx) = async_channel::unbounded();
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    if let Some(previous) = CURRENT.lock().unwrap().replace(stop_flag.clone()) {
+        previous.store(true, Ordering::Relaxed);
+    }
+
+    std::thread::Builder::new()
+        .name("waifu-stt".into())
+        .spawn(move || {
+            let error = listen(&config, &stop_flag, &tx).err();
+            if let Some(ref e) = error {
+                tracing::warn!("Speech input stopped: {}", e);
+            }
+            let _ = tx.send_blocking(SttEvent::Stopped(error));
+        })
+        .expect("Failed to spawn speech input thread");
+    rx
+}
+
+/// Stop listening; the current utterance is still transcribed
+pub fn stop() {
+    if let Some(current) = CURRENT.lock().unwrap().take() {
+        current.store(true, Ordering::Relaxed);
+    }
+}
+
+fn model_path(model: &str) -> PathBuf {
+    let path = PathBuf::from(model);
+    if path.is_file() {
+        return path;
+    }
+    glib::user_data_dir()
+        .join("desktop-waifu")
+        .join("models")
+        .join(format!("ggml-{}.bin", model))
+}
+
+fn load_model(model: &str) -> Result<Arc<WhisperContext>, SttError> {
+    let path = model_path(model);
+    let mut loaded = MODEL.lock().unwrap();
+    if let Some((loaded_path, context)) = loaded.as_ref()
+        && *loaded_path == path
+    {
+        return Ok(context.clone());
+    }
+    if !path.is_file() {
+        return Err(SttError::NoModel(path.display().to_string()));
+    }
+
+    whisper_rs::install_logging_hooks();
+    let mut params = WhisperContextParameters::default();
+    params.use_gpu(cfg!(any(feature = "stt-vulkan", feature = "stt-cuda")));
+    let context = Arc::new(WhisperContext::new_with_params(&path.to_string_lossy(), params)?);
+    tracing::info!("Loaded Whisper model {}", path.display());
+    *loaded = Some((path, context.clone()));
+    Ok(context)
+}
+
+/// Record 16 kHz mono 16-bit PCM from the default microphone to stdout
+fn spawn_recorder() -> Result<Child, SttError> {
+    let rate = SAMPLE_RATE.to_string();
+    let recorders: [(&str, Vec<String>); 3] = [
+        (
+            "pw-record",
+            vec!["--rate".into(), rate.clone(), "--channels".into(), "1".into(), "--format".into(), "s16".into(), "-".into()],
+        ),
+        (
+            "parecord",
+            vec!["--raw".into(), format!("--rate={}", rate), "--channels=1".into(), "--format=s16le".into()],
+        ),
+        (
+            "arecord",
+            vec!["-q".into(), "-t".into(), "raw".into(), "-r".into(), rate, "-c".into(), "1".into(), "-f".into(), "S16_LE".into()],
+        ),
+    ];
+    for (program, args) in recorders {
+        match Command::new(program)
+            .args(&args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => return Ok(child),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(SttError::NoRecorder)
+}
+
+fn transcribe(context: &WhisperContext, language: &str, samples: &[f32]) -> Result<String, SttError> {
+    let mut padded;
+    let samples = if samples.len() < MIN_SAMPLES {
+        padded = samples.to_vec();
+        padded.resize(MIN_SAMPLES, 0.0);
+        &padded[..]
+    } else {
+        samples
+    };
+
+    let mut state = context.create_state()?;
+    let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+    params.set_language(Some(language));
+    params.set_no_context(true);
+    params.set_suppress_blank(true);
+    params.set_print_special(false);
+    params.set_print_progress(false);
+    params.set_print_realtime(false);
+    params.set_print_timestamps(false);
+    state.full(params, samples)?;
+
+    let mut text = String::new();
+    for segment in 0..state.full_n_segments()? {
+        text.push_str(&state.full_get_segment_text(segment)?);
+    }
+    Ok(text.trim().to_string())
+}
+
+fn listen(config: &SttConfig, stop_flag: &AtomicBool, tx: &async_channel::Sender<SttEvent>) -> Result<(), SttError> {
+    let context = load_model(&config.model)?;
+    let mut recorder = spawn_recorder()?;
+    let mut stdout = recorder.stdout.take().ok_or(SttError::NoRecorder)?;
+    let pause_samples = config.pause_ms as usize * SAMPLE_RATE / 1000;
+
+    let mut utterance: Vec<f32> = Vec::new();
+    let mut silent_samples = 0;
+    let mut since_partial = 0;
+    let mut bytes = vec![0u8; CHUNK_SAMPLES * 2];
+
+    let result = loop {
+        if stop_flag.load(Ordering::Relaxed) {
+            break Ok(());
+        }
+        if let Err(e) = stdout.read_exact(&mut bytes) {
+            break Err(e.into());
+        }
+        let chunk: Vec<f32> = bytes
+            .chunks_exact(2)
+            .map(|pair| f32::from(i16::from_le_bytes([pair[0], pair[1]])) / 32768.0)
+            .collect();
+        let level = (chunk.iter().map(|s| s * s).sum::<f32>() / chunk.len() as f32).sqrt();
+
+        if level >= SPEECH_LEVEL {
+            silent_samples = 0;
+        } else {
+            silent_samples += chunk.len();
+            // Nothing said yet: don't collect the silence
+            if utterance.is_empty() {
+                continue;
+            }
+        }
+        utterance.extend_from_slice(&chunk);
+        since_partial += chunk.len();
+
+        if silent_samples >= pause_samples || utterance.len() >= MAX_SAMPLES {
+            let text = transcribe(&context, &config.language, &utterance)?;
+            if !text.is_empty() {
+                let _ = tx.send_blocking(SttEvent::Final(text));
+            }
+            utterance.clear();
+            since_partial = 0;
+        } else if since_partial >= PARTIAL_EVERY_SAMPLES {
+            let text = transcribe(&context, &config.language, &utterance)?;
+            if !text.is_empty() {
+                let _ = tx.send_blocking(SttEvent::Partial(text));
+            }
+            since_partial = 0;
+        }
+    };
+
+    let _ = recorder.kill();
+    let _ = recorder.wait();
+    if !utterance.is_empty() {
+        let text = transcribe(&context, &config.language, &utterance)?;
+        if !text.is_empty() {
+            let _ = tx.send_blocking(SttEvent::Final(text));
+        }
+    }
+    result
+}
@@ -0,0 +1,128 @@
+//! Supervisor mode (`--supervise`)
+//!
+//! The overlay goes down with WebKit's web or GPU process more often than it should, and
+//! outside a systemd service nothing brings it back. With `--supervise` this process only
+//! watches: it runs the overlay as a child with the same arguments and, when the child dies
+//! from a signal or exits with an error, starts it again after a backoff that doubles from
+//! one second up to a minute (and resets once a run has lasted a minute). A clean exit,
+//! such as quitting from the tray or a SIGTERM, ends the supervisor too. After more than
+//! five crashes in ten minutes it gives up rather than crash-looping. An overlay that is
+//! still running but stops answering `ping` over IPC three times in a row is wedged, so
+//! it's killed and counts as a crash.
+//!
+//! The overlay saves its position, visibility, monitor and scale to state.json every few
+//! seconds, so a restarted overlay comes back where the last one was.
+
+use std::collections::VecDeque;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Child, Command, ExitStatus};
+use std::time::{Duration, Instant};
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// A run lasting this long counts as recovered, resetting the backoff
+const STABLE_AFTER: Duration = Duration::from_secs(60);
+const MAX_CRASHES: usize = 5;
+const CRASH_WINDOW: Duration = Duration::from_secs(10 * 60);
+/// Time the overlay gets to start up before it's pinged
+const PING_GRACE: Duration = Duration::from_secs(30);
+const PING_INTERVAL: Duration = Duration::from_secs(15);
+const MAX_MISSED_PINGS: u32 = 3;
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Set for restarted children to how many restarts there have been
+pub const RESTARTS_ENV: &str = "DESKTOP_WAIFU_RESTARTS";
+
+/// Run the overlay with `args` until it exits cleanly or crashes too often
+pub fn run(args: Vec<String>) -> anyhow::Result<()> {
+    let exe = std::env::current_exe()?;
+    let mut crashes = VecDeque::new();
+    let mut backoff = INITIAL_BACKOFF;
+    let mut restarts = 0u32;
+
+    loop {
+        tracing::info!("Supervisor: starting {} {}", exe.display(), args.join(" "));
+        let started = Instant::now();
+        let mut command = Command::new(&exe);
+        command.args(&args);
+        if restarts > 0 {
+            command.env(RESTARTS_ENV, restarts.to_string());
+        }
+        // If the supervisor is killed, the overlay quits through its SIGTERM handler
+        // instead of living on unsupervised
+        unsafe {
+            command.pre_exec(|| {
+                if libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGTERM) == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let status = watch(command.spawn()?)?;
+        if status.success() {
+            tracing::info!("Supervisor: overlay exited normally");
+            return Ok(());
+        }
+
+        let ran = started.elapsed();
+        tracing::warn!("Supervisor: overlay {} after {:?}", describe(status), ran);
+        let now = Instant::now();
+        crashes.push_back(now);
+        while crashes.front().is_some_and(|crash| now.duration_since(*crash) > CRASH_WINDOW) {
+            crashes.pop_front();
+        }
+        if crashes.len() > MAX_CRASHES {
+            anyhow::bail!(
+                "The overlay crashed {} times in {} minutes; not restarting it again",
+                crashes.len(),
+                CRASH_WINDOW.as_secs() / 60
+            );
+        }
+        if ran >= STABLE_AFTER {
+            backoff = INITIAL_BACKOFF;
+        }
+
+        tracing::info!("Supervisor: restarting in {:?}", backoff);
+        std::thread::sleep(backoff);
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+        restarts += 1;
+    }
+}
+
+/// Wait for `child` to exit, killing it if it stops answering pings
+fn watch(mut child: Child) -> std::io::Result<ExitStatus> {
+    let mut next_ping = Instant::now() + PING_GRACE;
+    let mut missed = 0;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if Instant::now() >= next_ping {
+            next_ping = Instant::now() + PING_INTERVAL;
+            match crate::ipc::request(crate::ipc::Request::Ping) {
+                // Only a ping that went unanswered counts. Without a socket to reach (say
+                // another instance took it), there's nothing to judge the overlay by.
+                Err(crate::ipc::IpcError::Io(e))
+                    if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) =>
+                {
+                    missed += 1;
+                    tracing::warn!("Supervisor: overlay didn't answer a ping ({} in a row)", missed);
+                }
+                _ => missed = 0,
+            }
+            if missed >= MAX_MISSED_PINGS {
+                tracing::warn!("Supervisor: overlay stopped responding, killing it");
+                child.kill()?;
+                return child.wait();
+            }
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn describe(status: ExitStatus) -> String {
+    match (status.code(), status.signal()) {
+        (Some(code), _) => format!("exited with code {}", code),
+        (None, Some(signal)) => format!("was killed by signal {}", signal),
+        _ => "stopped".to_string(),
+    }
+}
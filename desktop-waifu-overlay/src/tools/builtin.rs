@@ -0,0 +1,534 @@
+//! Built-in tools: shell commands, reading and writing files, fetching web pages,
+//! describing the system and its load, managing packages and controlling media players
+//!
+//! Running commands, writing files, fetching URLs and installing packages require the
+//! user's confirmation per call; shell commands also go through the command policy
+//! (policy.rs), so its denials hold for the assistant as they do for `executeCommand`.
+//! A fetch could otherwise carry what `read_file` found to any server in its URL, and it
+//! never reaches loopback or private addresses, so local services stay out of reach.
+//! Reading files and listing folders is limited to the folders the user has allowed
+//! instead (files.rs).
+
+use super::manifest::run_with_timeout;
+use super::{Tool, ToolDefinition, ToolRegistry};
+use serde_json::{Value, json};
+use std::net::{IpAddr, ToSocketAddrs};
+use std::process::Command;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub(crate) const SHELL_TIMEOUT: Duration = Duration::from_secs(120);
+const FETCH_TIMEOUT_SECS: u64 = 20;
+/// Redirects `web_fetch` follows, each checked like the first URL
+const MAX_REDIRECTS: usize = 5;
+/// Longest command output, file or page handed back to the LLM
+const MAX_OUTPUT: usize = 64 * 1024;
+/// Largest file `read_file` will open
+const MAX_READ: u64 = 1024 * 1024;
+
+/// A tool implemented by a plain function
+struct Builtin {
+    definition: ToolDefinition,
+    run: fn(&Value) -> Result<Value, String>,
+}
+
+impl Tool for Builtin {
+    fn definition(&self) -> &ToolDefinition {
+        &self.definition
+    }
+
+    fn call(&self, args: Value) -> Result<Value, String> {
+        (self.run)(&args)
+    }
+}
+
+fn builtin(
+    name: &str,
+    description: &str,
+    parameters: Value,
+    requires_confirmation: bool,
+    run: fn(&Value) -> Result<Value, String>,
+) -> Arc<dyn Tool> {
+    Arc::new(Builtin {
+        definition: ToolDefinition {
+            name: name.to_string(),
+            description: description.to_string(),
+            parameters,
+            source: "builtin".to_string(),
+            requires_confirmation,
+        },
+        run,
+    })
+}
+
+pub fn register_builtin_tools(registry: &ToolRegistry) {
+    let tools = [
+        builtin(
+            "run_shell",
+            "Run a shell command on the user's computer and return its output",
+            json!({
+                "type": "object",
+                "properties": {
+                    "command": { "type": "string", "minLength": 1, "description": "Command line for the user's shell" },
+                    "cwd": { "type": "string", "description": "Working directory; defaults to the home directory" },
+                },
+                "required": ["command"],
+                "additionalProperties": false,
+            }),
+            true,
+            run_shell,
+        ),
+        builtin(
+            "read_file",
+            "Read a text file in a folder the user has allowed access to",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "minLength": 1, "description": "Absolute path, or relative to the home directory" },
+                },
+                "required": ["path"],
+                "additionalProperties": false,
+            }),
+            false,
+            read_file,
+        ),
+        builtin(
+            "list_dir",
+            "List a folder the user has allowed access to",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "minLength": 1, "description": "Absolute path, or relative to the home directory" },
+                },
+                "required": ["path"],
+                "additionalProperties": false,
+            }),
+            false,
+            list_dir,
+        ),
+        builtin(
+            "write_file",
+            "Write a text file, replacing it or appending to it",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "minLength": 1, "description": "Absolute path, or relative to the home directory" },
+                    "content": { "type": "string" },
+                    "append": { "type": "boolean", "description": "Add to the end instead of replacing the file" },
+                },
+                "required": ["path", "content"],
+                "additionalProperties": false,
+            }),
+            true,
+            write_file,
+        ),
+        builtin(
+            "web_fetch",
+            "Fetch a web page or API response; HTML comes back as plain text",
+            json!({
+                "type": "object",
+                "properties": {
+                    "url": { "type": "string", "description": "http:// or https:// URL" },
+                },
+                "required": ["url"],
+                "additionalProperties": false,
+            }),
+            true,
+            web_fetch,
+        ),
+        builtin(
+            "system_info",
+            "Describe the user's system: OS, distribution, shell, package manager, memory and uptime",
+            json!({ "type": "object", "properties": {}, "additionalProperties": false }),
+            false,
+            system_info,
+        ),
+        builtin(
+            "package_search",
+            "Search the system package manager's repositories for packages",
+            json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "minLength": 1, "description": "Words in the package name or description" },
+                },
+                "required": ["query"],
+                "additionalProperties": false,
+            }),
+            false,
+            package_search,
+        ),
+        builtin(
+            "package_info",
+            "Show a package's version, description, homepage and whether it is installed",
+            json!({
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string", "minLength": 1 },
+                },
+                "required": ["name"],
+                "additionalProperties": false,
+            }),
+            false,
+            package_info,
+        ),
+        builtin(
+            "package_upgrades",
+            "List installed packages that have upgrades available",
+            json!({ "type": "object", "properties": {}, "additionalProperties": false }),
+            false,
+            package_upgrades,
+        ),
+        builtin(
+            "package_install",
+            "Install packages with the system package manager; the system asks the user for authorization itself, never ask for a password",
+            json!({
+                "type": "object",
+                "properties": {
+                    "packages": { "type": "array", "items": { "type": "string", "minLength": 1 }, "minItems": 1 },
+                },
+                "required": ["packages"],
+                "additionalProperties": false,
+            }),
+            true,
+            package_install,
+        ),
+        builtin(
+            "system_metrics",
+            "Measure how the user's computer is doing: CPU load, memory, free disk space, network traffic, battery and temperature",
+            json!({ "type": "object", "properties": {}, "additionalProperties": false }),
+            false,
+            system_metrics,
+        ),
+        builtin(
+            "media_now_playing",
+            "See what the user is listening to or watching in their media player (Spotify, mpv, a browser, ...)",
+            json!({ "type": "object", "properties": {}, "additionalProperties": false }),
+            false,
+            media_now_playing,
+        ),
+        builtin(
+            "media_control",
+            "Pause or resume the user's media player, or skip to the next or previous track",
+            json!({
+                "type": "object",
+                "properties": {
+                    "action": { "type": "string", "enum": ["play_pause", "next", "previous"] },
+                },
+                "required": ["action"],
+                "additionalProperties": false,
+            }),
+            false,
+            media_control,
+        ),
+    ];
+    for tool in tools {
+        if let Err(e) = registry.register(tool) {
+            tracing::warn!("{}", e);
+        }
+    }
+}
+
+/// Cut `text` to at most `MAX_OUTPUT` bytes on a character boundary
+fn truncate(mut text: String) -> (String, bool) {
+    if text.len() <= MAX_OUTPUT {
+        return (text, false);
+    }
+    let mut end = MAX_OUTPUT;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    (text, true)
+}
+
+fn run_shell(args: &Value) -> Result<Value, String> {
+    let cmd = args["command"].as_str().unwrap_or_default();
+    // The registry only calls this tool once the user has confirmed it
+    let policy = crate::policy::load()?;
+    policy.check(cmd, true)?;
+    let options = crate::shell::ShellOptions {
+        cwd: args["cwd"].as_str().map(str::to_string),
+        ..Default::default()
+    };
+    let command = crate::sandbox::apply(&policy, cmd, options.command(cmd))?;
+    tracing::info!("Running shell tool: {}", cmd);
+    let (stdout, stderr, exit_code, timed_out) = run_with_timeout(command, None, SHELL_TIMEOUT)?;
+    let (stdout, stdout_truncated) = truncate(String::from_utf8_lossy(&stdout).into_owned());
+    let (stderr, stderr_truncated) = truncate(String::from_utf8_lossy(&stderr).into_owned());
+    Ok(json!({
+        "stdout": stdout,
+        "stderr": stderr,
+        "exitCode": exit_code,
+        "timedOut": timed_out,
+        "truncated": stdout_truncated || stderr_truncated,
+    }))
+}
+
+/// What to tell the model when a path is out of bounds, so it can pass it on
+fn file_error(e: crate::files::FileError) -> String {
+    match e {
+        crate::files::FileError::NotAllowed(_) => {
+            format!("{}. The user can allow a folder with /allow <folder>.", e)
+        }
+        e => e.to_string(),
+    }
+}
+
+fn read_file(args: &Value) -> Result<Value, String> {
+    let file = crate::files::read_file(args["path"].as_str().unwrap_or_default(), MAX_READ).map_err(file_error)?;
+    if file.encoding != "utf-8" {
+        return Err(format!("{} is not a text file", file.path.display()));
+    }
+    let (content, truncated) = truncate(file.content);
+    Ok(json!({ "path": file.path, "content": content, "truncated": truncated }))
+}
+
+fn list_dir(args: &Value) -> Result<Value, String> {
+    let entries = crate::files::list_dir(args["path"].as_str().unwrap_or_default()).map_err(file_error)?;
+    let entries: Vec<Value> = entries
+        .iter()
+        .map(|entry| json!({ "name": entry.name, "kind": entry.kind, "size": entry.size }))
+        .collect();
+    Ok(json!({ "entries": entries }))
+}
+
+fn write_file(args: &Value) -> Result<Value, String> {
+    use std::io::Write;
+
+    let path = crate::files::expand(args["path"].as_str().unwrap_or_default());
+    let content = args["content"].as_str().unwrap_or_default();
+    let append = args["append"].as_bool().unwrap_or(false);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("{}: {}", parent.display(), e))?;
+    }
+    let written = std::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(&path)
+        .and_then(|mut file| file.write_all(content.as_bytes()));
+    crate::audit::result(crate::audit::Kind::FileWrite, &path.to_string_lossy(), &written);
+    written.map_err(|e| format!("{}: {}", path.display(), e))?;
+    tracing::info!("Wrote {} bytes to {} (write_file tool)", content.len(), path.display());
+    Ok(json!({ "path": path, "bytesWritten": content.len() }))
+}
+
+/// GET with curl, like manifest HTTP tools. Redirects are followed here rather than by
+/// curl, so each hop's address is checked too.
+fn web_fetch(args: &Value) -> Result<Value, String> {
+    let mut url = args["url"].as_str().unwrap_or_default().to_string();
+    let mut redirects = 0;
+    let (body, status, content_type) = loop {
+        let resolve = public_address(&url)?;
+        let mut cmd = Command::new("curl");
+        cmd.args(["-sS", "--compressed", "--proto", "=http,https"])
+            .args(["--max-time", &FETCH_TIMEOUT_SECS.to_string()]);
+        // Connect to the address that was checked, not whatever a second lookup says
+        if let Some(resolve) = resolve {
+            cmd.args(["--resolve", &resolve]);
+        }
+        // Status, redirect target and content type on a line of their own after the body
+        cmd.args(["-w", "\n%{http_code}\t%{redirect_url}\t%{content_type}"])
+            .arg("--")
+            .arg(&url);
+        let timeout = Duration::from_secs(FETCH_TIMEOUT_SECS + 5);
+        let (stdout, stderr, exit_code, timed_out) = run_with_timeout(cmd, None, timeout)?;
+        if timed_out || exit_code != 0 {
+            return Err(format!("Fetching {} failed: {}", url, String::from_utf8_lossy(&stderr).trim()));
+        }
+
+        let mut output = String::from_utf8_lossy(&stdout).into_owned();
+        let trailer = output.rfind('\n').map_or(0, |i| i + 1);
+        let mut fields = output[trailer..].splitn(3, '\t');
+        let status: u16 = fields.next().unwrap_or_default().trim().parse().unwrap_or(0);
+        let location = fields.next().unwrap_or_default().trim().to_string();
+        let content_type = fields.next().unwrap_or_default().trim().to_string();
+        output.truncate(trailer.saturating_sub(1));
+        if !(300..400).contains(&status) || location.is_empty() {
+            break (output, status, content_type);
+        }
+        redirects += 1;
+        if redirects > MAX_REDIRECTS {
+            return Err(format!("Fetching {} failed: too many redirects", args["url"].as_str().unwrap_or_default()));
+        }
+        url = location;
+    };
+
+    let body = if content_type.contains("html") { html_to_text(&body) } else { body };
+    let (body, truncated) = truncate(body);
+    Ok(json!({
+        "status": status,
+        "contentType": content_type,
+        "body": body,
+        "truncated": truncated,
+    }))
+}
+
+/// curl's `--resolve` entry pinning `url`'s host to an address that isn't loopback, private
+/// or link-local (None when the host is an address already); an error for other URLs and
+/// hosts
+fn public_address(url: &str) -> Result<Option<String>, String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid URL '{}': {}", url, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("Refusing non-HTTP URL '{}'", url));
+    }
+    let host = parsed.host_str().ok_or_else(|| format!("No host in '{}'", url))?;
+    let port = parsed.port_or_known_default().unwrap_or(80);
+    let name = host.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(ip) = name.parse::<IpAddr>() {
+        return if is_public(ip) {
+            Ok(None)
+        } else {
+            Err(format!("Refusing to fetch {}: it points at this computer or the local network", host))
+        };
+    }
+    let addresses: Vec<IpAddr> = (name, port)
+        .to_socket_addrs()
+        .map_err(|e| format!("Could not resolve {}: {}", host, e))?
+        .map(|address| address.ip())
+        .collect();
+    // Every address must be public, or a host could list a private one next to a public one
+    if addresses.is_empty() || !addresses.iter().all(|ip| is_public(*ip)) {
+        return Err(format!("Refusing to fetch {}: it points at this computer or the local network", host));
+    }
+    let address = match addresses[0] {
+        IpAddr::V4(ip) => ip.to_string(),
+        IpAddr::V6(ip) => format!("[{}]", ip),
+    };
+    Ok(Some(format!("{}:{}:{}", host, port, address)))
+}
+
+/// Whether `ip` is on the internet rather than this machine or a local network
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                // Carrier-grade NAT, 100.64.0.0/10
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    // Unique local fc00::/7 and link-local fe80::/10
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+
+/// Readable text out of an HTML page: scripts, styles and tags dropped, whitespace collapsed
+fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len() / 2);
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        text.push(' ');
+        let tag = &rest[start..];
+        let lower: String = tag.chars().take(8).collect::<String>().to_ascii_lowercase();
+        // Skip the contents of script and style elements along with the tags
+        let skip_to = ["script", "style"]
+            .into_iter()
+            .find(|name| lower[1..].starts_with(name))
+            .and_then(|name| {
+                let close = format!("</{}", name);
+                tag.to_ascii_lowercase().find(&close).map(|i| i + close.len())
+            });
+        let after = match skip_to {
+            Some(i) => &tag[i..],
+            None => tag,
+        };
+        rest = match after.find('>') {
+            Some(end) => &after[end + 1..],
+            None => "",
+        };
+    }
+    text.push_str(rest);
+
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn system_info(_args: &Value) -> Result<Value, String> {
+    let mut info = crate::system::info();
+    let read = |path: &str| std::fs::read_to_string(path).ok().map(|s| s.trim().to_string());
+    info["hostname"] = json!(read("/proc/sys/kernel/hostname"));
+    info["kernel"] = json!(read("/proc/sys/kernel/osrelease"));
+    info["desktop"] = json!(std::env::var("XDG_CURRENT_DESKTOP").ok());
+    info["cpus"] = json!(std::thread::available_parallelism().map(|n| n.get()).ok());
+    info["uptimeSecs"] = json!(
+        read("/proc/uptime").and_then(|s| s.split_whitespace().next()?.parse::<f64>().ok().map(|secs| secs as u64))
+    );
+    if let Some(meminfo) = read("/proc/meminfo") {
+        let field = |name: &str| {
+            meminfo
+                .lines()
+                .find_map(|line| line.strip_prefix(name))
+                .and_then(|rest| rest.trim().trim_end_matches("kB").trim().parse::<u64>().ok())
+        };
+        info["memoryTotalMb"] = json!(field("MemTotal:").map(|kb| kb / 1024));
+        info["memoryAvailableMb"] = json!(field("MemAvailable:").map(|kb| kb / 1024));
+    }
+    Ok(info)
+}
+
+fn package_search(args: &Value) -> Result<Value, String> {
+    let packages = crate::pkg::search(args["query"].as_str().unwrap_or_default()).map_err(|e| e.to_string())?;
+    Ok(json!({ "packages": packages }))
+}
+
+fn package_info(args: &Value) -> Result<Value, String> {
+    let info = crate::pkg::info(args["name"].as_str().unwrap_or_default()).map_err(|e| e.to_string())?;
+    Ok(json!(info))
+}
+
+fn package_upgrades(_args: &Value) -> Result<Value, String> {
+    let upgrades = crate::pkg::list_upgrades().map_err(|e| e.to_string())?;
+    Ok(json!({ "upgrades": upgrades }))
+}
+
+fn package_install(args: &Value) -> Result<Value, String> {
+    let packages: Vec<String> = args["packages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|name| name.as_str().map(str::to_string))
+        .collect();
+    let result = crate::pkg::install(&packages).map_err(|e| e.to_string())?;
+    Ok(json!(result))
+}
+
+fn system_metrics(_args: &Value) -> Result<Value, String> {
+    Ok(json!(crate::system::metrics()))
+}
+
+fn media_now_playing(_args: &Value) -> Result<Value, String> {
+    let playing = crate::mpris::now_playing().map_err(|e| e.to_string())?;
+    Ok(json!({ "nowPlaying": playing }))
+}
+
+fn media_control(args: &Value) -> Result<Value, String> {
+    let action: crate::mpris::Action = serde_json::from_value(args["action"].clone()).map_err(|e| e.to_string())?;
+    crate::mpris::control(action).map_err(|e| e.to_string())?;
+    let playing = crate::mpris::now_playing().map_err(|e| e.to_string())?;
+    Ok(json!({ "nowPlaying": playing }))
+}
@@ -0,0 +1,350 @@
+//! Declarative tools loaded from TOML manifests in `~/.config/desktop-waifu/tools.d/`
+//!
+//! Each manifest holds one or more `[[tool]]` tables that run a local command or make an
+//! HTTP request, with `{param}` placeholders filled from the tool call's arguments:
+//!
+//! ```toml
+//! [[tool]]
+//! name = "disk_usage"
+//! description = "Show how much space a directory uses"
+//! confirm = "never"
+//! parameters = { type = "object", properties = { path = { type = "string" } }, required = ["path"] }
+//! command = { program = "du", args = ["-sh", "{path}"], timeout_secs = 30 }
+//!
+//! [[tool]]
+//! name = "weather"
+//! description = "Current weather for a city"
+//! parameters = { type = "object", properties = { city = { type = "string" } }, required = ["city"] }
+//! http = { url = "https://wttr.in/{city}?format=j1" }
+//! ```
+//!
+//! Command arguments are passed directly as argv (never through a shell). A value can't
+//! start an argument with `-`, so it can't pass itself off as an option; put the
+//! placeholder after the option instead (`"--file={path}"`). Values substituted into URLs
+//! are percent-encoded, header values can't contain line breaks, and request bodies go to
+//! curl on stdin.
+
+use super::{Tool, ToolDefinition, ToolRegistry};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+// Characters left as-is when substituting into URLs (RFC 3986 unreserved)
+const URL_VALUE: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'_').remove(b'.').remove(b'~');
+
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+/// Whether the user must confirm a call before it runs
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfirmPolicy {
+    /// Run without asking
+    Never,
+    /// Ask before every call
+    #[default]
+    Always,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct CommandSpec {
+    program: String,
+    #[serde(default)]
+    args: Vec<String>,
+    cwd: Option<PathBuf>,
+    #[serde(default = "default_timeout")]
+    timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct HttpSpec {
+    #[serde(default = "default_method")]
+    method: String,
+    url: String,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    body: Option<String>,
+    #[serde(default = "default_timeout")]
+    timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ToolSpec {
+    name: String,
+    description: String,
+    #[serde(default = "empty_schema")]
+    parameters: serde_json::Value,
+    #[serde(default)]
+    confirm: ConfirmPolicy,
+    command: Option<CommandSpec>,
+    http: Option<HttpSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestFile {
+    #[serde(default, rename = "tool")]
+    tools: Vec<ToolSpec>,
+}
+
+fn default_timeout() -> u64 {
+    DEFAULT_TIMEOUT_SECS
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+fn empty_schema() -> serde_json::Value {
+    serde_json::json!({ "type": "object", "properties": {} })
+}
+
+enum Action {
+    Command(CommandSpec),
+    Http(HttpSpec),
+}
+
+/// Tool defined by a manifest entry
+struct ManifestTool {
+    definition: ToolDefinition,
+    action: Action,
+}
+
+impl Tool for ManifestTool {
+    fn definition(&self) -> &ToolDefinition {
+        &self.definition
+    }
+
+    fn call(&self, args: serde_json::Value) -> Result<serde_json::Value, String> {
+        match &self.action {
+            Action::Command(spec) => run_command(spec, &args),
+            Action::Http(spec) => run_http(spec, &args),
+        }
+    }
+}
+
+pub fn tools_dir() -> PathBuf {
+    gtk4::glib::user_config_dir()
+        .join("desktop-waifu")
+        .join("tools.d")
+}
+
+/// Load every `*.toml` manifest in the tools.d directory into the registry
+pub fn load_manifest_tools(registry: &ToolRegistry) {
+    let Ok(entries) = std::fs::read_dir(tools_dir()) else {
+        return;
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    files.sort();
+
+    for file in files {
+        match load_manifest(&file, registry) {
+            Ok(count) => info!("Loaded {} tools from {:?}", count, file),
+            Err(e) => tracing::warn!("Failed to load tool manifest {:?}: {}", file, e),
+        }
+    }
+}
+
+fn load_manifest(path: &Path, registry: &ToolRegistry) -> Result<usize, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let manifest: ManifestFile = toml::from_str(&text).map_err(|e| e.to_string())?;
+    let source = format!(
+        "manifest:{}",
+        path.file_name().and_then(|n| n.to_str()).unwrap_or("?")
+    );
+
+    let mut count = 0;
+    for spec in manifest.tools {
+        let action = match (spec.command, spec.http) {
+            (Some(cmd), None) => Action::Command(cmd),
+            (None, Some(http)) => Action::Http(http),
+            _ => {
+                tracing::warn!("Tool '{}' must define exactly one of `command` or `http`", spec.name);
+                continue;
+            }
+        };
+        let tool = ManifestTool {
+            definition: ToolDefinition {
+                name: spec.name,
+                description: spec.description,
+                parameters: spec.parameters,
+                source: source.clone(),
+                requires_confirmation: spec.confirm == ConfirmPolicy::Always,
+            },
+            action,
+        };
+        match registry.register(Arc::new(tool)) {
+            Ok(()) => count += 1,
+            Err(e) => tracing::warn!("{}", e),
+        }
+    }
+    Ok(count)
+}
+
+/// Replace `{name}` placeholders with argument values; missing arguments become empty
+fn render(template: &str, args: &serde_json::Value, encode: fn(&str) -> String) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let end = after.find('}');
+        let key = end.map(|e| &after[..e]);
+        match key {
+            Some(key) if !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') => {
+                let value = match &args[key] {
+                    serde_json::Value::Null => String::new(),
+                    serde_json::Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                out.push_str(&encode(&value));
+                rest = &after[key.len() + 1..];
+            }
+            _ => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn raw(value: &str) -> String {
+    value.to_string()
+}
+
+fn url_encode(value: &str) -> String {
+    utf8_percent_encode(value, URL_VALUE).to_string()
+}
+
+/// Spawn a command, feed it optional stdin, and collect output, killing it on timeout
+pub(crate) fn run_with_timeout(mut cmd: Command, stdin: Option<String>, timeout: Duration) -> Result<(Vec<u8>, Vec<u8>, i32, bool), String> {
+    let mut child = cmd
+        .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to spawn: {}", e))?;
+
+    if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        std::thread::spawn(move || {
+            let _ = pipe.write_all(input.as_bytes());
+        });
+    }
+
+    // Drain pipes on helper threads so a chatty child can't block on a full pipe
+    let mut stdout_pipe = child.stdout.take();
+    let mut stderr_pipe = child.stderr.take();
+    let stdout_reader = std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(ref mut p) = stdout_pipe {
+            let _ = p.read_to_end(&mut buf);
+        }
+        buf
+    });
+    let stderr_reader = std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(ref mut p) = stderr_pipe {
+            let _ = p.read_to_end(&mut buf);
+        }
+        buf
+    });
+
+    let deadline = Instant::now() + timeout;
+    let (exit_code, timed_out) = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break (status.code().unwrap_or(-1), false),
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                break (-1, true);
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(20)),
+            Err(e) => return Err(e.to_string()),
+        }
+    };
+
+    let stdout = stdout_reader.join().unwrap_or_default();
+    let stderr = stderr_reader.join().unwrap_or_default();
+    Ok((stdout, stderr, exit_code, timed_out))
+}
+
+fn run_command(spec: &CommandSpec, args: &serde_json::Value) -> Result<serde_json::Value, String> {
+    let mut cmd = Command::new(&spec.program);
+    for template in &spec.args {
+        let arg = render(template, args, raw);
+        // `{path}` filled with `-rf` or `--output=...` would be read as an option
+        if arg.starts_with('-') && !template.starts_with('-') {
+            return Err(format!("Refusing argument '{}': a value can't start with '-'", arg));
+        }
+        cmd.arg(arg);
+    }
+    if let Some(ref cwd) = spec.cwd {
+        cmd.current_dir(cwd);
+    }
+
+    crate::debug_log!("[TOOLS] Running manifest command {:?}", cmd);
+    let (stdout, stderr, exit_code, timed_out) =
+        run_with_timeout(cmd, None, Duration::from_secs(spec.timeout_secs))?;
+
+    Ok(serde_json::json!({
+        "stdout": String::from_utf8_lossy(&stdout),
+        "stderr": String::from_utf8_lossy(&stderr),
+        "exitCode": exit_code,
+        "timedOut": timed_out,
+    }))
+}
+
+/// Perform the request with curl, which handles TLS and proxies for us
+fn run_http(spec: &HttpSpec, args: &serde_json::Value) -> Result<serde_json::Value, String> {
+    let url = render(&spec.url, args, url_encode);
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err(format!("Refusing non-HTTP URL '{}'", url));
+    }
+
+    let mut cmd = Command::new("curl");
+    cmd.args(["-sS", "-L", "-X", &spec.method.to_uppercase()])
+        .args(["--max-time", &spec.timeout_secs.to_string()])
+        // Append the status code on its own line after the body
+        .args(["-w", "\n%{http_code}"]);
+    for (name, value) in &spec.headers {
+        let value = render(value, args, raw);
+        // A line break would start another header, or the body
+        if value.contains(['\r', '\n']) {
+            return Err(format!("Refusing header '{}': its value contains a line break", name));
+        }
+        cmd.arg("-H").arg(format!("{}: {}", name, value));
+    }
+    let body = spec.body.as_ref().map(|b| render(b, args, raw));
+    if body.is_some() {
+        cmd.args(["--data-binary", "@-"]);
+    }
+    cmd.arg("--").arg(&url);
+
+    let timeout = Duration::from_secs(spec.timeout_secs + 5);
+    let (stdout, stderr, exit_code, timed_out) = run_with_timeout(cmd, body, timeout)?;
+    if timed_out || exit_code != 0 {
+        return Err(format!(
+            "HTTP request failed: {}",
+            String::from_utf8_lossy(&stderr).trim()
+        ));
+    }
+
+    let output = String::from_utf8_lossy(&stdout);
+    let (body, status) = output.rsplit_once('\n').unwrap_or(("", &output));
+    let status: u16 = status.trim().parse().unwrap_or(0);
+    let body = serde_json::from_str::<serde_json::Value>(body)
+        .unwrap_or_else(|_| serde_json::Value::String(body.to_string()));
+
+    Ok(serde_json::json!({ "status": status, "body": body }))
+}
@@ -0,0 +1,88 @@
+//! Assistant tool registry
+//!
+//! Tools are named operations with a JSON schema for their arguments that the frontend
+//! advertises to the LLM and invokes through the `tools` script message handler. Arguments
+//! are checked against the schema here (`call`) before a tool sees them. Besides the
+//! built-in tools, plugins and tools.d manifests add their own.
+
+pub mod builtin;
+pub mod manifest;
+pub mod schema;
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// Description of a tool as exposed to the frontend / LLM
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: String,
+    /// JSON schema describing the tool's arguments
+    pub parameters: serde_json::Value,
+    /// Where the tool comes from (e.g. "builtin", "plugin:weather", "manifest:net.toml")
+    pub source: String,
+    /// Calls must carry `confirmed: true`, i.e. the user approved this specific call
+    pub requires_confirmation: bool,
+}
+
+/// A callable assistant tool
+pub trait Tool: Send + Sync {
+    fn definition(&self) -> &ToolDefinition;
+
+    /// Execute the tool. Called from a worker thread, never the GTK main thread.
+    fn call(&self, args: serde_json::Value) -> Result<serde_json::Value, String>;
+}
+
+/// Shared registry of available tools (cheap to clone)
+#[derive(Clone, Default)]
+pub struct ToolRegistry {
+    tools: Arc<Mutex<BTreeMap<String, Arc<dyn Tool>>>>,
+}
+
+impl ToolRegistry {
+    /// Add a tool. Fails if another tool already uses the same name.
+    pub fn register(&self, tool: Arc<dyn Tool>) -> Result<(), String> {
+        let name = tool.definition().name.clone();
+        let mut tools = self.tools.lock().unwrap();
+        if let Some(existing) = tools.get(&name) {
+            return Err(format!(
+                "Tool '{}' is already provided by {}",
+                name,
+                existing.definition().source
+            ));
+        }
+        tools.insert(name, tool);
+        Ok(())
+    }
+
+    /// Remove every tool registered by `source`
+    pub fn unregister_source(&self, source: &str) {
+        self.tools
+            .lock()
+            .unwrap()
+            .retain(|_, tool| tool.definition().source != source);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Tool>> {
+        self.tools.lock().unwrap().get(name).cloned()
+    }
+
+    /// Definitions of all registered tools, sorted by name
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        self.tools
+            .lock()
+            .unwrap()
+            .values()
+            .map(|t| t.definition().clone())
+            .collect()
+    }
+}
+
+/// Validate `args` against the tool's schema, then run it. Missing arguments count as `{}`.
+pub fn call(tool: &dyn Tool, args: serde_json::Value) -> Result<serde_json::Value, String> {
+    let args = if args.is_null() { serde_json::json!({}) } else { args };
+    schema::validate(&tool.definition().parameters, &args)?;
+    tool.call(args)
+}
@@ -0,0 +1,127 @@
+//! Streamed binary transfers to the webview
+//!
+//! Instead of splicing large payloads (images, long command output) into JavaScript source,
+//! handlers register them here and pass the frontend a `waifu-transfer://<id>` URL. The
+//! frontend `fetch()`es it and WebKit streams the response straight from disk or memory, so
+//! neither side has to hold a multi-megabyte JS string. Each transfer can be fetched once.
+
+use gtk4::{gio, glib};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use webkit6::prelude::*;
+use webkit6::{URISchemeRequest, URISchemeResponse, WebView};
+
+pub const SCHEME: &str = "waifu-transfer";
+
+// Transfers the frontend never fetched (e.g. it timed out) are dropped after this long
+const TRANSFER_TTL: Duration = Duration::from_secs(300);
+
+enum Source {
+    File(PathBuf),
+    Bytes(glib::Bytes),
+}
+
+struct Transfer {
+    source: Source,
+    mime_type: String,
+    created: Instant,
+}
+
+/// Pending transfers, shared between handlers and worker threads (cheap to clone)
+#[derive(Clone, Default)]
+pub struct TransferStore {
+    transfers: Arc<Mutex<HashMap<String, Transfer>>>,
+}
+
+impl TransferStore {
+    /// Register the URI scheme on the webview's context so transfer URLs resolve
+    pub fn install(&self, webview: &WebView) {
+        let Some(context) = webview.web_context() else {
+            tracing::warn!("WebView has no context, streamed transfers are unavailable");
+            return;
+        };
+        if let Some(security) = context.security_manager() {
+            // Allow fetch() from the http:// page origin
+            security.register_uri_scheme_as_cors_enabled(SCHEME);
+            security.register_uri_scheme_as_secure(SCHEME);
+        }
+
+        let store = self.clone();
+        context.register_uri_scheme(SCHEME, move |request| store.respond(request));
+    }
+
+    /// Stream a file from disk; returns the URL the frontend should fetch
+    pub fn register_file(&self, path: PathBuf, mime_type: &str) -> String {
+        self.insert(Source::File(path), mime_type)
+    }
+
+    /// Serve an in-memory buffer; returns the URL the frontend should fetch
+    pub fn register_bytes(&self, bytes: Vec<u8>, mime_type: &str) -> String {
+        self.insert(Source::Bytes(glib::Bytes::from_owned(bytes)), mime_type)
+    }
+
+    fn insert(&self, source: Source, mime_type: &str) -> String {
+        let id = glib::uuid_string_random().to_string();
+        let mut transfers = self.transfers.lock().unwrap();
+        transfers.retain(|_, t| t.created.elapsed() < TRANSFER_TTL);
+        transfers.insert(
+            id.clone(),
+            Transfer {
+                source,
+                mime_type: mime_type.to_string(),
+                created: Instant::now(),
+            },
+        );
+        format!("{}://{}", SCHEME, id)
+    }
+
+    fn respond(&self, request: &URISchemeRequest) {
+        // waifu-transfer://<id> parses with the id as the host, so read it from the URI
+        let uri = request.uri().map(|u| u.to_string()).unwrap_or_default();
+        let id = uri
+            .strip_prefix(&format!("{}://", SCHEME))
+            .unwrap_or("")
+            .trim_end_matches('/');
+        let transfer = self.transfers.lock().unwrap().remove(id);
+
+        let Some(transfer) = transfer else {
+            crate::debug_log!("[TRANSFER] Unknown or already fetched transfer: {}", uri);
+            let mut error = glib::Error::new(gio::IOErrorEnum::NotFound, "Unknown transfer");
+            request.finish_error(&mut error);
+            return;
+        };
+
+        let stream: Result<(gio::InputStream, i64), glib::Error> = match transfer.source {
+            Source::File(path) => {
+                let file = gio::File::for_path(&path);
+                file.read(None::<&gio::Cancellable>).map(|stream| {
+                    let len = std::fs::metadata(&path).map(|m| m.len() as i64).unwrap_or(-1);
+                    (stream.upcast(), len)
+                })
+            }
+            Source::Bytes(bytes) => {
+                let len = bytes.len() as i64;
+                Ok((gio::MemoryInputStream::from_bytes(&bytes).upcast(), len))
+            }
+        };
+
+        match stream {
+            Ok((stream, len)) => {
+                crate::debug_log!("[TRANSFER] Streaming {} ({} bytes)", id, len);
+                let response = URISchemeResponse::new(&stream, len);
+                response.set_content_type(&transfer.mime_type);
+                response.set_status(200, None);
+                let headers = webkit6::soup::MessageHeaders::new(webkit6::soup::MessageHeadersType::Response);
+                headers.append("Access-Control-Allow-Origin", "*");
+                response.set_http_headers(headers);
+                request.finish_with_response(&response);
+            }
+            Err(mut e) => {
+                tracing::warn!("Failed to open transfer {}: {}", id, e);
+                request.finish_error(&mut e);
+            }
+        }
+    }
+}
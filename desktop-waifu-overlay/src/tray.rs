@@ -1,26 +1,118 @@
-use ksni::{self, menu::StandardItem, Tray, TrayService};
-use std::sync::mpsc;
+use gtk4::glib;
+use ksni::{self, menu::{CheckmarkItem, RadioGroup, RadioItem, StandardItem, SubMenu}, Tray, TrayService};
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::time::Duration;
 use tracing::info;
 
+/// The tray icons, bundled so they don't depend on how the overlay was installed. They're
+/// written to an icon theme directory at startup and named through `icon_theme_path`.
+const ICONS: [(&str, &str); 7] = [
+    ("desktop-waifu-tray", include_str!("../icons/desktop-waifu-tray.svg")),
+    ("desktop-waifu-tray-sleeping", include_str!("../icons/desktop-waifu-tray-sleeping.svg")),
+    ("desktop-waifu-tray-unread", include_str!("../icons/desktop-waifu-tray-unread.svg")),
+    ("desktop-waifu-tray-thinking-0", include_str!("../icons/desktop-waifu-tray-thinking-0.svg")),
+    ("desktop-waifu-tray-thinking-1", include_str!("../icons/desktop-waifu-tray-thinking-1.svg")),
+    ("desktop-waifu-tray-thinking-2", include_str!("../icons/desktop-waifu-tray-thinking-2.svg")),
+    ("desktop-waifu-tray-thinking-3", include_str!("../icons/desktop-waifu-tray-thinking-3.svg")),
+];
+/// Frames of the thinking spinner, and how long each shows
+const SPINNER_FRAMES: u8 = 4;
+const SPINNER_FRAME_TIME: Duration = Duration::from_millis(250);
+/// Stock icon for when the bundled ones couldn't be written
+const FALLBACK_ICON: &str = "user-available";
+
+thread_local! {
+    /// Advances the spinner while the character is thinking
+    static SPINNER: RefCell<Option<glib::SourceId>> = const { RefCell::new(None) };
+}
+
 /// Messages sent from tray to main application
 #[derive(Debug, Clone)]
 pub enum TrayMessage {
     Show,
     Hide,
+    /// Switch profile (None = no profile)
+    SetProfile(Option<String>),
+    /// Force night light on/off or follow the desktop again
+    SetNightLight(crate::nightlight::Mode),
+    /// Play an animation clip, by name
+    PlayAnimation(String),
+    /// Set the character's facial expression
+    SetExpression(String),
+    /// Resize the character
+    SetScale(f32),
+    /// Switch to a character, by the frontend's id ("emily", "user:mymodel")
+    SetCharacter(String),
+    SetNotificationsMuted(bool),
+    /// Let clicks through to the windows below, or stop
+    SetClickThrough(bool),
+    /// Walk around on its own, or stop
+    SetWalking(bool),
+    /// Show the overlay with the settings open
+    OpenSettings,
     Quit,
 }
 
+/// VRM preset expressions offered in the Expression submenu, with their label keys
+const EXPRESSIONS: [(&str, &str); 6] = [
+    ("neutral", "tray-expression-neutral"),
+    ("happy", "tray-expression-happy"),
+    ("sad", "tray-expression-sad"),
+    ("angry", "tray-expression-angry"),
+    ("surprised", "tray-expression-surprised"),
+    ("relaxed", "tray-expression-relaxed"),
+];
+
+/// Character scales offered in the Size submenu
+const SCALES: [f32; 6] = [0.5, 0.75, 1.0, 1.25, 1.5, 2.0];
+
+/// A character the frontend can show, as it reported
+#[derive(Debug, Clone)]
+pub struct CharacterChoice {
+    pub id: String,
+    pub name: String,
+}
+
 /// System tray implementation using SNI protocol
 pub struct DesktopWaifuTray {
-    sender: mpsc::Sender<TrayMessage>,
+    sender: async_channel::Sender<TrayMessage>,
     visible: bool,
+    profiles: Vec<String>,
+    active_profile: Option<String>,
+    night_light: crate::nightlight::Mode,
+    characters: Vec<CharacterChoice>,
+    active_character: Option<String>,
+    /// Character scale in effect
+    scale: f32,
+    notifications_muted: bool,
+    click_through: bool,
+    walking: bool,
+    /// Where the bundled icons were written; None to use the stock icon
+    icon_theme: Option<PathBuf>,
+    /// Waiting for the LLM, with the spinner at this frame
+    thinking: Option<u8>,
+    /// An assistant message arrived that the user hasn't seen
+    unread: bool,
 }
 
 impl DesktopWaifuTray {
-    pub fn new(sender: mpsc::Sender<TrayMessage>) -> Self {
+    pub fn new(sender: async_channel::Sender<TrayMessage>) -> Self {
         Self {
             sender,
             visible: true,
+            profiles: Vec::new(),
+            active_profile: None,
+            night_light: crate::nightlight::Mode::Auto,
+            characters: Vec::new(),
+            active_character: None,
+            scale: 1.0,
+            notifications_muted: crate::notifications::is_muted(),
+            click_through: false,
+            walking: false,
+            icon_theme: install_icons(),
+            thinking: None,
+            unread: false,
         }
     }
 }
@@ -31,12 +123,27 @@ impl Tray for DesktopWaifuTray {
     }
 
     fn title(&self) -> String {
-        "Desktop Waifu".into()
+        crate::tr!("app-name")
     }
 
+    fn icon_theme_path(&self) -> String {
+        self.icon_theme
+            .as_ref()
+            .map(|path| path.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    // Thinking shows over an unread message, which shows over the overlay being hidden
     fn icon_name(&self) -> String {
-        // Use a generic icon - can be replaced with custom icon later
-        "user-available".into()
+        if self.icon_theme.is_none() {
+            return FALLBACK_ICON.into();
+        }
+        match (self.thinking, self.unread, self.visible) {
+            (Some(frame), _, _) => format!("desktop-waifu-tray-thinking-{}", frame),
+            (None, true, _) => "desktop-waifu-tray-unread".into(),
+            (None, false, false) => "desktop-waifu-tray-sleeping".into(),
+            (None, false, true) => "desktop-waifu-tray".into(),
+        }
     }
 
     // Left-click on tray icon toggles visibility
@@ -46,47 +153,338 @@ impl Tray for DesktopWaifuTray {
         } else {
             TrayMessage::Show
         };
-        let _ = self.sender.send(msg);
+        let _ = self.sender.send_blocking(msg);
         self.visible = !self.visible;
     }
 
     fn menu(&self) -> Vec<ksni::MenuItem<Self>> {
         use ksni::MenuItem::*;
 
-        let show_hide_label = if self.visible { "Hide" } else { "Show" };
+        let show_hide_label = if self.visible { crate::tr!("tray-hide") } else { crate::tr!("tray-show") };
         let show_hide_msg = if self.visible {
             TrayMessage::Hide
         } else {
             TrayMessage::Show
         };
 
-        vec![
+        let mut items = vec![
             StandardItem {
-                label: show_hide_label.into(),
+                label: show_hide_label,
                 activate: Box::new(move |tray: &mut Self| {
-                    let _ = tray.sender.send(show_hide_msg.clone());
+                    let _ = tray.sender.send_blocking(show_hide_msg.clone());
                     tray.visible = !tray.visible;
                 }),
                 ..Default::default()
             }
             .into(),
-            Separator,
+        ];
+
+        items.push(
             StandardItem {
-                label: "Quit".into(),
+                label: crate::tr!("tray-open-settings"),
+                activate: Box::new(|tray: &mut Self| {
+                    let _ = tray.sender.send_blocking(TrayMessage::OpenSettings);
+                    tray.visible = true;
+                }),
+                ..Default::default()
+            }
+            .into(),
+        );
+
+        items.push(Separator);
+        if let Some(menu) = self.character_menu() {
+            items.push(menu);
+        }
+        if let Some(menu) = self.animation_menu() {
+            items.push(menu);
+        }
+        items.push(self.expression_menu());
+        items.push(self.scale_menu());
+
+        items.push(Separator);
+        items.push(self.night_light_menu());
+        items.push(
+            CheckmarkItem {
+                label: crate::tr!("tray-mute-notifications"),
+                checked: self.notifications_muted,
                 activate: Box::new(|tray: &mut Self| {
-                    let _ = tray.sender.send(TrayMessage::Quit);
+                    tray.notifications_muted = !tray.notifications_muted;
+                    let _ = tray.sender.send_blocking(TrayMessage::SetNotificationsMuted(tray.notifications_muted));
                 }),
                 ..Default::default()
             }
             .into(),
+        );
+
+        items.push(
+            CheckmarkItem {
+                label: crate::tr!("tray-click-through"),
+                checked: self.click_through,
+                activate: Box::new(|tray: &mut Self| {
+                    tray.click_through = !tray.click_through;
+                    let _ = tray.sender.send_blocking(TrayMessage::SetClickThrough(tray.click_through));
+                }),
+                ..Default::default()
+            }
+            .into(),
+        );
+
+        items.push(
+            CheckmarkItem {
+                label: crate::tr!("tray-walk-around"),
+                checked: self.walking,
+                activate: Box::new(|tray: &mut Self| {
+                    tray.walking = !tray.walking;
+                    let _ = tray.sender.send_blocking(TrayMessage::SetWalking(tray.walking));
+                }),
+                ..Default::default()
+            }
+            .into(),
+        );
+
+        if !self.profiles.is_empty() {
+            items.push(Separator);
+            items.push(self.profile_menu());
+        }
+
+        items.push(Separator);
+        items.push(
+            StandardItem {
+                label: crate::tr!("tray-quit"),
+                activate: Box::new(|tray: &mut Self| {
+                    let _ = tray.sender.send_blocking(TrayMessage::Quit);
+                }),
+                ..Default::default()
+            }
+            .into(),
+        );
+        items
+    }
+}
+
+impl DesktopWaifuTray {
+    /// "Profile" submenu: no profile, then each configured profile
+    fn profile_menu(&self) -> ksni::MenuItem<Self> {
+        let selected = self
+            .active_profile
+            .as_ref()
+            .and_then(|active| self.profiles.iter().position(|p| p == active))
+            .map_or(0, |i| i + 1);
+
+        // Underscores mark access keys in menu labels
+        let mut options = vec![RadioItem {
+            label: crate::tr!("tray-profile-none"),
+            ..Default::default()
+        }];
+        options.extend(self.profiles.iter().map(|name| RadioItem {
+            label: name.replace('_', "__"),
+            ..Default::default()
+        }));
+
+        SubMenu {
+            label: crate::tr!("tray-profile"),
+            submenu: vec![RadioGroup {
+                selected,
+                select: Box::new(|tray: &mut Self, index| {
+                    let profile = index.checked_sub(1).and_then(|i| tray.profiles.get(i).cloned());
+                    tray.active_profile = profile.clone();
+                    let _ = tray.sender.send_blocking(TrayMessage::SetProfile(profile));
+                }),
+                options,
+            }
+            .into()],
+            ..Default::default()
+        }
+        .into()
+    }
+
+    /// "Animations" submenu, one item per clip; None if there are none. The clips are read
+    /// each time the menu is built, so ones added since show up.
+    fn animation_menu(&self) -> Option<ksni::MenuItem<Self>> {
+        let clips = crate::animations::list();
+        if clips.is_empty() {
+            return None;
+        }
+        let submenu = clips
+            .into_iter()
+            .map(|clip| {
+                let mut label = clip.name.replace('_', " ");
+                if let Some(first) = label.get_mut(..1) {
+                    first.make_ascii_uppercase();
+                }
+                let name = clip.name;
+                StandardItem {
+                    label,
+                    activate: Box::new(move |tray: &mut Self| {
+                        let _ = tray.sender.send_blocking(TrayMessage::PlayAnimation(name.clone()));
+                    }),
+                    ..Default::default()
+                }
+                .into()
+            })
+            .collect();
+        Some(
+            SubMenu {
+                label: crate::tr!("tray-animations"),
+                submenu,
+                ..Default::default()
+            }
+            .into(),
+        )
+    }
+
+    /// "Character" submenu with the built-in characters and imported models; None until the
+    /// frontend has reported them
+    fn character_menu(&self) -> Option<ksni::MenuItem<Self>> {
+        if self.characters.is_empty() {
+            return None;
+        }
+        let options = self
+            .characters
+            .iter()
+            .map(|character| RadioItem {
+                label: character.name.replace('_', "__"),
+                ..Default::default()
+            })
+            .collect();
+        // No radio item is checked while the active character isn't among them
+        let selected = self
+            .active_character
+            .as_ref()
+            .and_then(|active| self.characters.iter().position(|c| &c.id == active))
+            .unwrap_or(usize::MAX);
+
+        Some(
+            SubMenu {
+                label: crate::tr!("tray-character"),
+                submenu: vec![RadioGroup {
+                    selected,
+                    select: Box::new(|tray: &mut Self, index| {
+                        if let Some(character) = tray.characters.get(index) {
+                            tray.active_character = Some(character.id.clone());
+                            let _ = tray.sender.send_blocking(TrayMessage::SetCharacter(character.id.clone()));
+                        }
+                    }),
+                    options,
+                }
+                .into()],
+                ..Default::default()
+            }
+            .into(),
+        )
+    }
+
+    /// "Expression" submenu with the VRM preset expressions
+    fn expression_menu(&self) -> ksni::MenuItem<Self> {
+        let submenu = EXPRESSIONS
+            .iter()
+            .map(|&(expression, label)| {
+                StandardItem {
+                    label: crate::tr!(label),
+                    activate: Box::new(move |tray: &mut Self| {
+                        let _ = tray.sender.send_blocking(TrayMessage::SetExpression(expression.to_string()));
+                    }),
+                    ..Default::default()
+                }
+                .into()
+            })
+            .collect();
+        SubMenu {
+            label: crate::tr!("tray-expression"),
+            submenu,
+            ..Default::default()
+        }
+        .into()
+    }
+
+    /// "Size" submenu with a few character scales; none is checked for a scale set elsewhere
+    fn scale_menu(&self) -> ksni::MenuItem<Self> {
+        let options = SCALES
+            .iter()
+            .map(|scale| RadioItem {
+                label: crate::tr!("tray-scale-percent", percent = (scale * 100.0).round() as i64),
+                ..Default::default()
+            })
+            .collect();
+
+        SubMenu {
+            label: crate::tr!("tray-scale"),
+            submenu: vec![RadioGroup {
+                selected: SCALES
+                    .iter()
+                    .position(|scale| (scale - self.scale).abs() < 0.01)
+                    .unwrap_or(usize::MAX),
+                select: Box::new(|tray: &mut Self, index| {
+                    let Some(&scale) = SCALES.get(index) else { return };
+                    tray.scale = scale;
+                    let _ = tray.sender.send_blocking(TrayMessage::SetScale(scale));
+                }),
+                options,
+            }
+            .into()],
+            ..Default::default()
+        }
+        .into()
+    }
+
+    /// "Night light" submenu: follow the desktop, or force it on or off
+    fn night_light_menu(&self) -> ksni::MenuItem<Self> {
+        use crate::nightlight::Mode;
+
+        const MODES: [Mode; 3] = [Mode::Auto, Mode::On, Mode::Off];
+        let options = [
+            crate::tr!("tray-night-light-auto"),
+            crate::tr!("tray-night-light-on"),
+            crate::tr!("tray-night-light-off"),
         ]
+        .into_iter()
+        .map(|label| RadioItem {
+            label,
+            ..Default::default()
+        })
+        .collect();
+
+        SubMenu {
+            label: crate::tr!("tray-night-light"),
+            submenu: vec![RadioGroup {
+                selected: MODES.iter().position(|&m| m == self.night_light).unwrap_or(0),
+                select: Box::new(|tray: &mut Self, index| {
+                    let mode = MODES.get(index).copied().unwrap_or_default();
+                    tray.night_light = mode;
+                    let _ = tray.sender.send_blocking(TrayMessage::SetNightLight(mode));
+                }),
+                options,
+            }
+            .into()],
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+/// Write the bundled icons into an icon theme under the runtime dir, returning the theme
+/// directory
+fn install_icons() -> Option<PathBuf> {
+    let theme = glib::user_runtime_dir().join("desktop-waifu").join("icons");
+    let apps = theme.join("hicolor").join("scalable").join("apps");
+    let written = std::fs::create_dir_all(&apps).and_then(|_| {
+        ICONS
+            .iter()
+            .try_for_each(|(name, svg)| std::fs::write(apps.join(format!("{}.svg", name)), svg))
+    });
+    match written {
+        Ok(()) => Some(theme),
+        Err(e) => {
+            tracing::warn!("Could not write tray icons to {}: {}", apps.display(), e);
+            None
+        }
     }
 }
 
 /// Spawn the system tray in a separate thread
 /// Returns a receiver for tray messages and a handle to update tray state
-pub fn spawn_tray() -> anyhow::Result<(mpsc::Receiver<TrayMessage>, ksni::Handle<DesktopWaifuTray>)> {
-    let (sender, receiver) = mpsc::channel();
+pub fn spawn_tray() -> anyhow::Result<(async_channel::Receiver<TrayMessage>, ksni::Handle<DesktopWaifuTray>)> {
+    let (sender, receiver) = async_channel::unbounded();
 
     let tray = DesktopWaifuTray::new(sender);
     let service = TrayService::new(tray);
@@ -110,3 +508,72 @@ pub fn update_tray_visibility(handle: &ksni::Handle<DesktopWaifuTray>, visible:
         tray.visible = visible;
     });
 }
+
+/// Update the profile submenu (available profiles and the active one)
+pub fn update_tray_profiles(handle: &ksni::Handle<DesktopWaifuTray>, profiles: Vec<String>, active: Option<String>) {
+    handle.update(move |tray| {
+        tray.profiles = profiles;
+        tray.active_profile = active;
+    });
+}
+
+/// Update the character submenu (the characters the frontend offers and the one it shows)
+pub fn update_tray_characters(handle: &ksni::Handle<DesktopWaifuTray>, characters: Vec<CharacterChoice>, active: Option<String>) {
+    handle.update(move |tray| {
+        tray.characters = characters;
+        tray.active_character = active;
+    });
+}
+
+/// Update the character scale the Size submenu checks
+pub fn update_tray_scale(handle: &ksni::Handle<DesktopWaifuTray>, scale: f32) {
+    handle.update(move |tray| {
+        tray.scale = scale;
+    });
+}
+
+/// Update the Click-through checkmark (call when it's toggled from a shortcut)
+pub fn update_tray_click_through(handle: &ksni::Handle<DesktopWaifuTray>, enabled: bool) {
+    handle.update(move |tray| {
+        tray.click_through = enabled;
+    });
+}
+
+pub fn update_tray_walking(handle: &ksni::Handle<DesktopWaifuTray>, walking: bool) {
+    handle.update(move |tray| {
+        tray.walking = walking;
+    });
+}
+
+/// Show or stop the thinking spinner
+pub fn update_tray_thinking(handle: &ksni::Handle<DesktopWaifuTray>, thinking: bool) {
+    SPINNER.with(|spinner| {
+        let mut spinner = spinner.borrow_mut();
+        match (thinking, spinner.is_some()) {
+            (true, false) => {
+                handle.update(|tray| tray.thinking = Some(0));
+                let handle = handle.clone();
+                *spinner = Some(glib::timeout_add_local(SPINNER_FRAME_TIME, move || {
+                    handle.update(|tray| {
+                        tray.thinking = tray.thinking.map(|frame| (frame + 1) % SPINNER_FRAMES);
+                    });
+                    glib::ControlFlow::Continue
+                }));
+            }
+            (false, true) => {
+                if let Some(source) = spinner.take() {
+                    source.remove();
+                }
+                handle.update(|tray| tray.thinking = None);
+            }
+            _ => {}
+        }
+    });
+}
+
+/// Mark that there is an assistant message the user hasn't seen, or that they've seen it
+pub fn update_tray_unread(handle: &ksni::Handle<DesktopWaifuTray>, unread: bool) {
+    handle.update(move |tray| {
+        tray.unread = unread;
+    });
+}
@@ -0,0 +1,464 @@
+//! Text-to-speech (the `speak` message handler)
+//!
+//! Speech is synthesized by Piper or espeak-ng, or by an OpenAI-compatible
+//! `/v1/audio/speech` endpoint, as `[tts]` in config.toml says, and played with `pw-play`,
+//! `paplay` or `aplay`, whichever is installed. Text is spoken a sentence at a time: the next
+//! sentence is synthesized while the current one plays, so a long reply starts right away.
+//!
+//! Lip-sync: just before each sentence plays, the webview gets its mouth shapes as a track
+//! of VRM visemes (aa, ih, ou, ee, oh) at fixed intervals. How far the mouth opens follows
+//! the audio's loudness; its shape follows the sentence's vowels as espeak-ng phonemizes
+//! them (when it is installed), spread over the voiced stretches.
+
+use crate::config::{self, TtsConfig, TtsEngine};
+use gtk4::glib;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Mutex;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio_util::sync::CancellationToken;
+
+/// Length of one lip-sync frame
+const FRAME_SECS: f32 = 0.04;
+/// Frames quieter than this, relative to the sentence's loudest, have the mouth closed
+const SILENCE: f32 = 0.12;
+/// OpenAI's `pcm` response format
+const CLOUD_SAMPLE_RATE: u32 = 24000;
+
+/// Cancels the speech in progress
+static CURRENT: Mutex<Option<CancellationToken>> = Mutex::new(None);
+
+#[derive(Debug, thiserror::Error)]
+pub enum TtsError {
+    #[error("no text-to-speech engine found; install piper (with a voice) or espeak-ng")]
+    NoEngine,
+    #[error("no Piper voice '{0}' in {1}")]
+    NoVoice(String, String),
+    #[error("{0} is not installed")]
+    NotInstalled(&'static str),
+    #[error("{0} failed: {1}")]
+    Failed(&'static str, String),
+    #[error("no audio player found; install pipewire, pulseaudio-utils or alsa-utils")]
+    NoPlayer,
+    #[error("cloud voice: {0}")]
+    Cloud(String),
+}
+
+pub enum SpeechEvent {
+    /// Mouth shapes for the sentence that is about to play
+    Visemes(VisemeTrack),
+    /// Everything was spoken, or `stop` (or a newer `speak`) cut it short
+    Finished { interrupted: bool },
+    Failed(TtsError),
+}
+
+#[derive(Debug, Serialize)]
+pub struct VisemeTrack {
+    /// Seconds per frame
+    pub interval: f32,
+    /// Viseme and weight (0 to 1) for each frame
+    pub frames: Vec<(&'static str, f32)>,
+}
+
+/// Mono 16-bit PCM
+struct Audio {
+    sample_rate: u32,
+    samples: Vec<i16>,
+}
+
+#[derive(Clone)]
+enum Engine {
+    Piper { model: PathBuf, espeak_voice: Option<String> },
+    Espeak { voice: String },
+    Cloud { voice: String },
+}
+
+/// Speak `text` with `voice` (or the configured one), cutting off any speech in progress.
+/// The receiver yields the lip-sync tracks as sentences start, then how it ended.
+pub fn speak(text: String, voice: Option<String>) -> async_channel::Receiver<SpeechEvent> {
+    let (tx, rx) = async_channel::unbounded();
+    let cancel = crate::runtime::shutdown_token().child_token();
+    if let Some(previous) = CURRENT.lock().unwrap().replace(cancel.clone()) {
+        previous.cancel();
+    }
+
+    crate::runtime::spawn(async move {
+        let config = config::current().tts.clone();
+        // Dropping the speech future kills the engine and player (kill_on_drop)
+        let event = tokio::select! {
+            result = speak_sentences(&config, &text, voice, &tx) => match result {
+                Ok(()) => SpeechEvent::Finished { interrupted: false },
+                Err(e) => SpeechEvent::Failed(e),
+            },
+            _ = cancel.cancelled() => SpeechEvent::Finished { interrupted: true },
+        };
+        let _ = tx.send(event).await;
+    });
+    rx
+}
+
+/// Stop speaking
+pub fn stop() {
+    if let Some(current) = CURRENT.lock().unwrap().take() {
+        current.cancel();
+    }
+}
+
+async fn speak_sentences(
+    config: &TtsConfig,
+    text: &str,
+    voice: Option<String>,
+    tx: &async_channel::Sender<SpeechEvent>,
+) -> Result<(), TtsError> {
+    let engine = resolve_engine(config, voice.or_else(|| config.voice.clone()))?;
+    let mut sentences = split_sentences(text).into_iter();
+    let Some(first) = sentences.next() else {
+        return Ok(());
+    };
+
+    let (mut audio, mut track) = synthesize(config, &engine, &first).await?;
+    loop {
+        let _ = tx.send(SpeechEvent::Visemes(track)).await;
+        let next = sentences.next();
+        let (_, next) = tokio::try_join!(play(&audio), async {
+            match next {
+                Some(sentence) => synthesize(config, &engine, &sentence).await.map(Some),
+                None => Ok(None),
+            }
+        })?;
+        match next {
+            Some((next_audio, next_track)) => (audio, track) = (next_audio, next_track),
+            None => return Ok(()),
+        }
+    }
+}
+
+fn voices_dir() -> PathBuf {
+    glib::user_data_dir().join("desktop-waifu").join("voices")
+}
+
+fn installed(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+/// A Piper model: a path to an .onnx file, or a name in the voices directory. Without a
+/// name, the first model there.
+fn piper_model(voice: Option<&str>) -> Option<PathBuf> {
+    match voice {
+        Some(voice) if Path::new(voice).is_file() => Some(PathBuf::from(voice)),
+        Some(voice) => Some(voices_dir().join(format!("{}.onnx", voice))).filter(|path| path.is_file()),
+        None => {
+            let mut models: Vec<PathBuf> = std::fs::read_dir(voices_dir())
+                .ok()?
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "onnx"))
+                .collect();
+            models.sort();
+            models.into_iter().next()
+        }
+    }
+}
+
+fn resolve_engine(config: &TtsConfig, voice: Option<String>) -> Result<Engine, TtsError> {
+    let piper = |voice: Option<&str>| -> Result<Engine, TtsError> {
+        let model = piper_model(voice).ok_or_else(|| {
+            TtsError::NoVoice(voice.unwrap_or_default().to_string(), voices_dir().display().to_string())
+        })?;
+        // The model's config names the espeak-ng voice it phonemizes with
+        let espeak_voice = std::fs::read_to_string(model.with_extension("onnx.json"))
+            .ok()
+            .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+            .and_then(|json| json["espeak"]["voice"].as_str().map(str::to_string));
+        Ok(Engine::Piper { model, espeak_voice })
+    };
+    let espeak = |voice: Option<String>| Engine::Espeak {
+        voice: voice.unwrap_or_else(|| "en-us".into()),
+    };
+
+    match config.engine {
+        TtsEngine::Piper => piper(voice.as_deref()),
+        TtsEngine::Espeak => Ok(espeak(voice)),
+        TtsEngine::Cloud => Ok(Engine::Cloud {
+            voice: voice.unwrap_or_else(|| "alloy".into()),
+        }),
+        TtsEngine::Auto => {
+            if installed("piper")
+                && let Ok(engine) = piper(voice.as_deref())
+            {
+                Ok(engine)
+            } else if installed("espeak-ng") {
+                Ok(espeak(voice))
+            } else {
+                Err(TtsError::NoEngine)
+            }
+        }
+    }
+}
+
+/// Sentences to synthesize one by one
+fn split_sentences(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut current = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            current.push(' ');
+        } else {
+            current.push(c);
+        }
+        let ends = match c {
+            '.' | '!' | '?' | ';' => chars.peek().is_none_or(|next| next.is_whitespace()),
+            '。' | '！' | '？' | '\n' => true,
+            _ => false,
+        };
+        if ends && !current.trim().is_empty() {
+            sentences.push(current.trim().to_string());
+            current.clear();
+        }
+    }
+    if !current.trim().is_empty() {
+        sentences.push(current.trim().to_string());
+    }
+    sentences
+}
+
+/// Run `program` with `input` on stdin and return its stdout
+async fn run(program: &'static str, args: &[&str], input: &str) -> Result<Vec<u8>, TtsError> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => TtsError::NotInstalled(program),
+            _ => TtsError::Failed(program, e.to_string()),
+        })?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(input.as_bytes())
+            .await
+            .map_err(|e| TtsError::Failed(program, e.to_string()))?;
+    }
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|e| TtsError::Failed(program, e.to_string()))?;
+    if !output.status.success() {
+        return Err(TtsError::Failed(
+            program,
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(output.stdout)
+}
+
+async fn synthesize(config: &TtsConfig, engine: &Engine, sentence: &str) -> Result<(Audio, VisemeTrack), TtsError> {
+    let rate = if config.rate > 0.0 { config.rate } else { 1.0 };
+    let (audio, espeak_voice) = match engine {
+        Engine::Piper { model, espeak_voice } => {
+            let sample_rate = std::fs::read_to_string(model.with_extension("onnx.json"))
+                .ok()
+                .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+                .and_then(|json| json["audio"]["sample_rate"].as_u64())
+                .unwrap_or(22050) as u32;
+            let length_scale = format!("{:.2}", 1.0 / rate);
+            let model = model.to_string_lossy();
+            let pcm = run("piper", &["--model", &model, "--output-raw", "--length_scale", &length_scale], sentence).await?;
+            (Audio { sample_rate, samples: pcm_samples(&pcm) }, espeak_voice.clone())
+        }
+        Engine::Espeak { voice } => {
+            let words_per_minute = format!("{}", (175.0 * rate).round() as u32);
+            let wav = run("espeak-ng", &["--stdout", "--stdin", "-v", voice, "-s", &words_per_minute], sentence).await?;
+            let audio = parse_wav(&wav).ok_or_else(|| TtsError::Failed("espeak-ng", "unreadable WAV output".into()))?;
+            (audio, Some(voice.clone()))
+        }
+        Engine::Cloud { voice } => (cloud_speech(config, voice, sentence, rate).await?, None),
+    };
+
+    let vowels = match &espeak_voice {
+        Some(voice) if installed("espeak-ng") => run("espeak-ng", &["-q", "--ipa", "--stdin", "-v", voice], sentence)
+            .await
+            .map(|ipa| ipa_visemes(&String::from_utf8_lossy(&ipa)))
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    let track = viseme_track(&audio, &vowels);
+    Ok((audio, track))
+}
+
+async fn cloud_speech(config: &TtsConfig, voice: &str, sentence: &str, rate: f32) -> Result<Audio, TtsError> {
+    let cloud = &config.cloud;
+    let api_key = std::env::var(&cloud.api_key_env).unwrap_or_default();
+    let response = reqwest::Client::new()
+        .post(&cloud.url)
+        .bearer_auth(api_key)
+        .json(&serde_json::json!({
+            "model": cloud.model,
+            "voice": voice,
+            "input": sentence,
+            "response_format": "pcm",
+            "speed": rate,
+        }))
+        .send()
+        .await
+        .map_err(|e| TtsError::Cloud(e.to_string()))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(TtsError::Cloud(format!("{}: {}", status, body.trim())));
+    }
+    let pcm = response.bytes().await.map_err(|e| TtsError::Cloud(e.to_string()))?;
+    Ok(Audio {
+        sample_rate: CLOUD_SAMPLE_RATE,
+        samples: pcm_samples(&pcm),
+    })
+}
+
+fn pcm_samples(bytes: &[u8]) -> Vec<i16> {
+    bytes
+        .chunks_exact(2)
+        .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+        .collect()
+}
+
+/// Samples of a mono 16-bit WAV. espeak-ng can't seek back into stdout to fill in the data
+/// size, so the data chunk runs to the end regardless of its header.
+fn parse_wav(bytes: &[u8]) -> Option<Audio> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return None;
+    }
+    let mut sample_rate = None;
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let size = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().ok()?) as usize;
+        let body = pos + 8;
+        match id {
+            b"fmt " if body + 16 <= bytes.len() => {
+                let channels = u16::from_le_bytes([bytes[body + 2], bytes[body + 3]]);
+                let bits = u16::from_le_bytes([bytes[body + 14], bytes[body + 15]]);
+                if channels != 1 || bits != 16 {
+                    return None;
+                }
+                sample_rate = Some(u32::from_le_bytes(bytes[body + 4..body + 8].try_into().ok()?));
+            }
+            b"data" => {
+                return Some(Audio {
+                    sample_rate: sample_rate?,
+                    samples: pcm_samples(&bytes[body..]),
+                });
+            }
+            _ => {}
+        }
+        pos = body + size + (size & 1);
+    }
+    None
+}
+
+/// VRM viseme for each vowel in an IPA transcription, in order
+fn ipa_visemes(ipa: &str) -> Vec<&'static str> {
+    ipa.chars()
+        .filter_map(|c| match c {
+            'a' | 'ɑ' | 'æ' | 'ʌ' | 'ɐ' | 'ə' | 'ɜ' | 'ɚ' => Some("aa"),
+            'i' | 'ɪ' | 'y' | 'ʏ' => Some("ih"),
+            'u' | 'ʊ' | 'ɯ' | 'w' => Some("ou"),
+            'e' | 'ɛ' | 'ø' | 'œ' => Some("ee"),
+            'o' | 'ɔ' | 'ɒ' => Some("oh"),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Mouth openness from the loudness of each frame, shaped by `vowels` spread evenly over
+/// the voiced frames ("aa" throughout without them)
+fn viseme_track(audio: &Audio, vowels: &[&'static str]) -> VisemeTrack {
+    let window = ((audio.sample_rate as f32 * FRAME_SECS) as usize).max(1);
+    let levels: Vec<f32> = audio
+        .samples
+        .chunks(window)
+        .map(|chunk| {
+            let energy: f64 = chunk.iter().map(|&s| f64::from(s).powi(2)).sum();
+            (energy / chunk.len() as f64).sqrt() as f32
+        })
+        .collect();
+    let peak = levels.iter().copied().fold(0.0, f32::max);
+    let openness: Vec<f32> = levels
+        .iter()
+        .map(|&level| {
+            let relative = if peak > 0.0 { level / peak } else { 0.0 };
+            // Square root so quieter syllables still visibly move the mouth
+            if relative < SILENCE { 0.0 } else { relative.sqrt() }
+        })
+        .collect();
+
+    let voiced = openness.iter().filter(|&&weight| weight > 0.0).count();
+    let mut seen = 0;
+    let frames = openness
+        .into_iter()
+        .map(|weight| {
+            if weight == 0.0 {
+                return ("aa", 0.0);
+            }
+            let shape = if vowels.is_empty() { "aa" } else { vowels[seen * vowels.len() / voiced] };
+            seen += 1;
+            (shape, weight)
+        })
+        .collect();
+    VisemeTrack {
+        interval: FRAME_SECS,
+        frames,
+    }
+}
+
+/// Play the audio, returning once it has finished
+async fn play(audio: &Audio) -> Result<(), TtsError> {
+    let rate = audio.sample_rate.to_string();
+    let players: [(&'static str, Vec<String>); 3] = [
+        (
+            "pw-play",
+            vec!["--rate".into(), rate.clone(), "--channels".into(), "1".into(), "--format".into(), "s16".into(), "-".into()],
+        ),
+        (
+            "paplay",
+            vec!["--raw".into(), format!("--rate={}", rate), "--channels=1".into(), "--format=s16le".into()],
+        ),
+        (
+            "aplay",
+            vec!["-q".into(), "-t".into(), "raw".into(), "-r".into(), rate, "-c".into(), "1".into(), "-f".into(), "S16_LE".into()],
+        ),
+    ];
+
+    for (program, args) in players {
+        let mut child = match Command::new(program)
+            .args(&args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(TtsError::Failed(program, e.to_string())),
+        };
+        let bytes: Vec<u8> = audio.samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(&bytes)
+                .await
+                .map_err(|e| TtsError::Failed(program, e.to_string()))?;
+        }
+        let status = child.wait().await.map_err(|e| TtsError::Failed(program, e.to_string()))?;
+        if !status.success() {
+            return Err(TtsError::Failed(program, format!("exited with {}", status)));
+        }
+        return Ok(());
+    }
+    Err(TtsError::NoPlayer)
+}
@@ -0,0 +1,237 @@
+//! Self-update from GitHub releases (`--check-update` / `--self-update`)
+//!
+//! For installs from the release tarball, which have no package manager to update them.
+//! `--self-update` downloads the tarball for this architecture from the latest release and
+//! checks its SHA-256 against the release's SHA256SUMS. That file must carry an Ed25519
+//! signature, hex in SHA256SUMS.sig, made with the same key as frontend updates
+//! (`DESKTOP_WAIFU_UPDATE_KEY`, frontend.rs), so a tampered release or a compromised
+//! account can't slip in a binary. Builds without a key don't self-update, and without a
+//! signed digest nothing is installed. The update is unpacked next to the
+//! running binary, then the binary, the launcher and `dist/` are each swapped in with a
+//! rename. If an overlay was running, it's restarted: through systemd when it runs as the
+//! service, otherwise this process asks it to quit and execs into the new binary with the
+//! arguments and working directory the old one reported over IPC. Installs
+//! in folders the user can't write to (distro packages) are left to their package manager.
+
+use crate::tr;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/yv-was-taken/desktop-waifu/releases/latest";
+const CHECKSUMS_ASSET: &str = "SHA256SUMS";
+/// Hex Ed25519 signature over SHA256SUMS
+const CHECKSUMS_SIGNATURE_ASSET: &str = "SHA256SUMS.sig";
+/// Folder the release tarball keeps everything in
+const TARBALL_ROOT: &str = "desktop-waifu";
+const OVERLAY_BINARY: &str = "desktop-waifu-overlay";
+const LAUNCHER_BINARY: &str = "desktop-waifu";
+/// How long a running overlay gets to quit before the new one starts anyway
+const QUIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+impl Release {
+    fn version(&self) -> &str {
+        self.tag_name.trim_start_matches('v')
+    }
+
+    fn asset(&self, name: &str) -> Option<&Asset> {
+        self.assets.iter().find(|asset| asset.name == name)
+    }
+}
+
+/// Print whether a newer release is out
+pub fn check() -> anyhow::Result<()> {
+    let release = latest_release()?;
+    if is_newer(release.version(), env!("CARGO_PKG_VERSION")) {
+        println!(
+            "{}",
+            tr!("update-available", version = release.version(), current = env!("CARGO_PKG_VERSION"))
+        );
+    } else {
+        println!("{}", tr!("update-current", version = env!("CARGO_PKG_VERSION")));
+    }
+    Ok(())
+}
+
+/// Install the latest release over this one and restart a running overlay with it
+pub fn self_update() -> anyhow::Result<()> {
+    let exe = std::env::current_exe()?.canonicalize()?;
+    let install_dir = exe.parent().map(Path::to_path_buf).unwrap_or_default();
+    if !writable(&install_dir) {
+        anyhow::bail!(tr!("update-not-writable", path = install_dir.display().to_string()));
+    }
+    if !crate::frontend::has_update_key() {
+        anyhow::bail!(tr!("update-no-key"));
+    }
+
+    let release = latest_release()?;
+    let version = release.version().to_string();
+    if !is_newer(&version, env!("CARGO_PKG_VERSION")) {
+        println!("{}", tr!("update-current", version = env!("CARGO_PKG_VERSION")));
+        return Ok(());
+    }
+    let tarball_name = format!("desktop-waifu-linux-{}.tar.gz", std::env::consts::ARCH);
+    let tarball = release
+        .asset(&tarball_name)
+        .ok_or_else(|| anyhow::anyhow!(tr!("update-no-build", arch = std::env::consts::ARCH)))?;
+    let expected = expected_digest(&release, tarball)?
+        .ok_or_else(|| anyhow::anyhow!(tr!("update-no-digest", file = tarball_name.as_str())))?;
+
+    println!("{}", tr!("update-downloading", version = version.as_str()));
+    let bytes = crate::runtime::handle().block_on(fetch(&tarball.browser_download_url))?;
+    let actual = hex(&Sha256::digest(&bytes));
+    if !actual.eq_ignore_ascii_case(&expected) {
+        anyhow::bail!(tr!("update-digest-mismatch", file = tarball_name.as_str()));
+    }
+
+    // Unpacked on the same filesystem as the install, so each swap is a rename
+    let staging = install_dir.join(format!(".update-{}", version));
+    let _ = std::fs::remove_dir_all(&staging);
+    std::fs::create_dir_all(&staging)?;
+    let installed = unpack(&bytes, &staging).and_then(|unpacked| install(&unpacked, &exe, &install_dir));
+    let _ = std::fs::remove_dir_all(&staging);
+    installed?;
+    println!("{}", tr!("update-installed", version = version.as_str()));
+
+    restart(&exe)
+}
+
+fn latest_release() -> anyhow::Result<Release> {
+    let bytes = crate::runtime::handle().block_on(fetch(LATEST_RELEASE_URL))?;
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+async fn fetch(url: &str) -> anyhow::Result<Vec<u8>> {
+    let response = reqwest::Client::new()
+        .get(url)
+        // GitHub's API refuses requests without one
+        .header(reqwest::header::USER_AGENT, concat!("desktop-waifu-overlay/", env!("CARGO_PKG_VERSION")))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(response.bytes().await?.to_vec())
+}
+
+/// The tarball's hex SHA-256 from the release's SHA256SUMS, once its signature checks out
+fn expected_digest(release: &Release, tarball: &Asset) -> anyhow::Result<Option<String>> {
+    let (Some(sums), Some(signature)) = (release.asset(CHECKSUMS_ASSET), release.asset(CHECKSUMS_SIGNATURE_ASSET))
+    else {
+        return Ok(None);
+    };
+    let (sums, signature) = crate::runtime::handle().block_on(async {
+        tokio::try_join!(fetch(&sums.browser_download_url), fetch(&signature.browser_download_url))
+    })?;
+    crate::frontend::verify_signature(&sums, &String::from_utf8_lossy(&signature))
+        .map_err(|_| anyhow::anyhow!(tr!("update-bad-signature", file = CHECKSUMS_ASSET)))?;
+    Ok(String::from_utf8_lossy(&sums).lines().find_map(|line| {
+        let (digest, name) = line.split_once(char::is_whitespace)?;
+        (name.trim().trim_start_matches('*') == tarball.name).then(|| digest.to_string())
+    }))
+}
+
+/// Unpack the tarball into `staging` and return its `desktop-waifu` folder
+fn unpack(tarball: &[u8], staging: &Path) -> anyhow::Result<PathBuf> {
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(tarball));
+    // unpack_in refuses entries that would land outside `staging`
+    for entry in archive.entries()? {
+        entry?.unpack_in(staging)?;
+    }
+    let root = staging.join(TARBALL_ROOT);
+    if !root.join(OVERLAY_BINARY).is_file() {
+        anyhow::bail!(tr!("update-bad-archive"));
+    }
+    Ok(root)
+}
+
+/// Swap the unpacked binaries and `dist/` into `install_dir`, the launcher and `dist/`
+/// only where this install has them
+fn install(unpacked: &Path, exe: &Path, install_dir: &Path) -> anyhow::Result<()> {
+    let overlay = unpacked.join(OVERLAY_BINARY);
+    std::fs::set_permissions(&overlay, std::fs::Permissions::from_mode(0o755))?;
+    std::fs::rename(&overlay, exe)?;
+
+    let launcher = install_dir.join(LAUNCHER_BINARY);
+    if launcher.is_file() && unpacked.join(LAUNCHER_BINARY).is_file() {
+        std::fs::set_permissions(unpacked.join(LAUNCHER_BINARY), std::fs::Permissions::from_mode(0o755))?;
+        std::fs::rename(unpacked.join(LAUNCHER_BINARY), &launcher)?;
+    }
+
+    let dist = install_dir.join("dist");
+    if dist.is_dir() && unpacked.join("dist").is_dir() {
+        let old = install_dir.join(".dist.old");
+        let _ = std::fs::remove_dir_all(&old);
+        std::fs::rename(&dist, &old)?;
+        std::fs::rename(unpacked.join("dist"), &dist)?;
+        let _ = std::fs::remove_dir_all(&old);
+    }
+    Ok(())
+}
+
+/// Restart a running overlay on the new binary; nothing to do if none is running
+fn restart(exe: &Path) -> anyhow::Result<()> {
+    let Ok(state) = crate::ipc::request(crate::ipc::Request::GetState) else {
+        return Ok(());
+    };
+    if crate::autostart::service_active() {
+        println!("{}", tr!("update-restarting-service"));
+        return crate::autostart::restart_service();
+    }
+
+    println!("{}", tr!("update-restarting"));
+    crate::ipc::request(crate::ipc::Request::Command(crate::ipc::OverlayCommand::Shutdown))?;
+    let deadline = std::time::Instant::now() + QUIT_TIMEOUT;
+    while crate::ipc::socket_path().exists() && std::time::Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    // Start it the way the running overlay was started (older ones don't say)
+    let mut command = std::process::Command::new(exe);
+    if let Some(args) = state["args"].as_array() {
+        command.args(args.iter().filter_map(|arg| arg.as_str()));
+    }
+    if let Some(cwd) = state["cwd"].as_str() {
+        command.current_dir(cwd);
+    }
+    let error = std::os::unix::process::CommandExt::exec(&mut command);
+    Err(error.into())
+}
+
+/// Whether version `candidate` is later than `current`, comparing dotted numbers
+fn is_newer(candidate: &str, current: &str) -> bool {
+    let parse = |version: &str| -> Vec<u64> {
+        version
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    };
+    parse(candidate) > parse(current)
+}
+
+/// Whether files can be created in `dir`
+fn writable(dir: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(path) = std::ffi::CString::new(dir.as_os_str().as_bytes()) else { return false };
+    unsafe { libc::access(path.as_ptr(), libc::W_OK) == 0 }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,288 @@
+//! Reading VRM files: checking them and pulling out their metadata
+//!
+//! A VRM is a binary glTF (GLB): a 12-byte header, a JSON chunk describing the model and a
+//! BIN chunk with the meshes and textures the JSON points into. three-vrm shows nothing at
+//! all for a model it can't make sense of, so `read` checks what it needs before a model is
+//! imported or listed: the header, both chunks, every buffer view lying inside the BIN
+//! chunk, at least one mesh and a humanoid with hips. The metadata comes from the VRM 0.x
+//! (`VRM`) or 1.0 (`VRMC_vrm`) extension: name, authors, license, thumbnail and the
+//! expressions the model has, named the way three-vrm names them (`happy` rather than
+//! 0.x's `joy`).
+
+use serde::Serialize;
+use serde_json::Value;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Largest glTF JSON chunk read while checking a model
+const MAX_JSON_CHUNK: u32 = 16 * 1024 * 1024;
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_HEADER: u64 = 12;
+const CHUNK_HEADER: u64 = 8;
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+
+/// VRM 0.x expression presets and what three-vrm calls them
+const PRESETS_0X: &[(&str, &str)] = &[
+    ("neutral", "neutral"),
+    ("joy", "happy"),
+    ("angry", "angry"),
+    ("sorrow", "sad"),
+    ("fun", "relaxed"),
+    ("a", "aa"),
+    ("i", "ih"),
+    ("u", "ou"),
+    ("e", "ee"),
+    ("o", "oh"),
+    ("blink", "blink"),
+    ("blink_l", "blinkLeft"),
+    ("blink_r", "blinkRight"),
+    ("lookup", "lookUp"),
+    ("lookdown", "lookDown"),
+    ("lookleft", "lookLeft"),
+    ("lookright", "lookRight"),
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum VrmError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("{0}")]
+    Invalid(&'static str),
+}
+
+pub type Result<T> = std::result::Result<T, VrmError>;
+
+/// What a VRM file says about itself
+#[derive(Debug, Clone)]
+pub struct Vrm {
+    /// 0 or 1
+    pub version: u8,
+    pub meta: Meta,
+    /// Expression names, presets first
+    pub expressions: Vec<String>,
+    thumbnail: Option<Thumbnail>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Meta {
+    pub name: Option<String>,
+    pub authors: Vec<String>,
+    /// The license's name, e.g. "CC BY" or "VRM Public License 1.0"
+    pub license: Option<String>,
+    pub license_url: Option<String>,
+}
+
+/// Where the thumbnail image is in the file
+#[derive(Debug, Clone)]
+struct Thumbnail {
+    offset: u64,
+    length: u64,
+    mime_type: String,
+}
+
+impl Vrm {
+    pub fn has_thumbnail(&self) -> bool {
+        self.thumbnail.is_some()
+    }
+}
+
+/// Check the VRM at `path` and read its metadata
+pub fn read(path: &Path) -> Result<Vrm> {
+    let mut file = std::fs::File::open(path)?;
+    let size = file.metadata()?.len();
+    read_from(&mut file, size)
+}
+
+/// The thumbnail of the VRM at `path` and its MIME type, if it has one
+pub fn thumbnail(path: &Path) -> Result<Option<(Vec<u8>, String)>> {
+    let mut file = std::fs::File::open(path)?;
+    let size = file.metadata()?.len();
+    let Some(thumbnail) = read_from(&mut file, size)?.thumbnail else {
+        return Ok(None);
+    };
+    file.seek(SeekFrom::Start(thumbnail.offset))?;
+    let mut image = vec![0u8; thumbnail.length as usize];
+    file.read_exact(&mut image)?;
+    Ok(Some((image, thumbnail.mime_type)))
+}
+
+fn read_from(file: &mut (impl Read + Seek), size: u64) -> Result<Vrm> {
+    let invalid = VrmError::Invalid;
+
+    let mut header = [0u8; 20];
+    file.read_exact(&mut header).map_err(|_| invalid("too short to be a glTF file"))?;
+    let word = |i: usize| u32::from_le_bytes([header[i], header[i + 1], header[i + 2], header[i + 3]]);
+    if &header[..4] != GLB_MAGIC {
+        return Err(invalid("not a binary glTF file"));
+    }
+    if word(4) != 2 {
+        return Err(invalid("only glTF 2.0 is supported"));
+    }
+    if u64::from(word(8)) != size {
+        return Err(invalid("the file is truncated or has trailing data"));
+    }
+    let json_len = word(12);
+    if word(16) != CHUNK_JSON || json_len > MAX_JSON_CHUNK {
+        return Err(invalid("the first chunk isn't glTF JSON"));
+    }
+    let mut json = vec![0u8; json_len as usize];
+    file.read_exact(&mut json).map_err(|_| invalid("the JSON chunk is truncated"))?;
+    let gltf: Value = serde_json::from_slice(&json).map_err(|_| invalid("the JSON chunk doesn't parse"))?;
+
+    // The BIN chunk, if any, follows right after
+    let bin_start = GLB_HEADER + CHUNK_HEADER + u64::from(json_len);
+    let bin = if size >= bin_start + CHUNK_HEADER {
+        let mut chunk = [0u8; 8];
+        file.read_exact(&mut chunk)?;
+        let length = u64::from(u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]));
+        let kind = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+        if kind != CHUNK_BIN {
+            return Err(invalid("the second chunk isn't glTF binary data"));
+        }
+        if bin_start + CHUNK_HEADER + length > size {
+            return Err(invalid("the binary chunk is truncated"));
+        }
+        Some((bin_start + CHUNK_HEADER, length))
+    } else {
+        None
+    };
+    check_buffers(&gltf, bin.map(|(_, length)| length))?;
+
+    if array(&gltf["meshes"]).is_empty() {
+        return Err(invalid("it has no meshes"));
+    }
+
+    let extensions = &gltf["extensions"];
+    let nodes = array(&gltf["nodes"]).len();
+    let vrm = if let Some(vrm) = extensions.get("VRMC_vrm") {
+        let hips = index(&vrm["humanoid"]["humanBones"]["hips"]["node"]);
+        if !hips.is_some_and(|hips| hips < nodes) {
+            return Err(invalid("its humanoid has no hips"));
+        }
+        let meta = &vrm["meta"];
+        let license_url = string(&meta["licenseUrl"]);
+        Vrm {
+            version: 1,
+            meta: Meta {
+                name: string(&meta["name"]),
+                authors: array(&meta["authors"]).iter().filter_map(string).collect(),
+                license: license_url.as_deref().map(|url| {
+                    if url.starts_with("https://vrm.dev/licenses/1.0") {
+                        "VRM Public License 1.0".to_string()
+                    } else {
+                        url.to_string()
+                    }
+                }),
+                license_url: license_url.or_else(|| string(&meta["otherLicenseUrl"])),
+            },
+            expressions: ["preset", "custom"]
+                .into_iter()
+                .filter_map(|kind| vrm["expressions"][kind].as_object())
+                .flat_map(|expressions| expressions.keys().cloned())
+                .collect(),
+            thumbnail: index(&meta["thumbnailImage"]).and_then(|image| locate_image(&gltf, image, bin)),
+        }
+    } else if let Some(vrm) = extensions.get("VRM") {
+        let hips = array(&vrm["humanoid"]["humanBones"])
+            .iter()
+            .find(|bone| bone["bone"] == "hips")
+            .and_then(|bone| index(&bone["node"]));
+        if !hips.is_some_and(|hips| hips < nodes) {
+            return Err(invalid("its humanoid has no hips"));
+        }
+        let meta = &vrm["meta"];
+        let license = string(&meta["licenseName"]).filter(|license| license != "Other");
+        let mut expressions: Vec<String> = Vec::new();
+        for group in array(&vrm["blendShapeMaster"]["blendShapeGroups"]) {
+            let preset = group["presetName"].as_str().unwrap_or("unknown").to_lowercase();
+            let name = match PRESETS_0X.iter().find(|(name, _)| *name == preset) {
+                Some((_, name)) => Some(name.to_string()),
+                None => string(&group["name"]),
+            };
+            if let Some(name) = name
+                && !expressions.contains(&name)
+            {
+                expressions.push(name);
+            }
+        }
+        Vrm {
+            version: 0,
+            meta: Meta {
+                name: string(&meta["title"]),
+                authors: string(&meta["author"]).into_iter().collect(),
+                license: license.map(|license| license.replace('_', " ")),
+                license_url: string(&meta["otherLicenseUrl"]),
+            },
+            expressions,
+            // 0.x points at a texture rather than an image
+            thumbnail: index(&meta["texture"])
+                .and_then(|texture| index(&gltf["textures"][texture]["source"]))
+                .and_then(|image| locate_image(&gltf, image, bin)),
+        }
+    } else {
+        return Err(invalid("no VRM extension"));
+    };
+    Ok(vrm)
+}
+
+/// Check that the model's data is all in the file: the GLB buffer fits in the BIN chunk
+/// (`bin`, its length) and every buffer view fits in its buffer
+fn check_buffers(gltf: &Value, bin: Option<u64>) -> Result<()> {
+    let invalid = VrmError::Invalid;
+    let buffers = array(&gltf["buffers"]);
+    let mut lengths = Vec::with_capacity(buffers.len());
+    for (i, buffer) in buffers.iter().enumerate() {
+        let length = buffer["byteLength"].as_u64().ok_or(invalid("a buffer has no length"))?;
+        match buffer["uri"].as_str() {
+            None if i == 0 => {
+                if !bin.is_some_and(|bin| length <= bin) {
+                    return Err(invalid("the binary chunk is missing or too short"));
+                }
+            }
+            Some(uri) if uri.starts_with("data:") => {}
+            _ => return Err(invalid("it refers to files outside it")),
+        }
+        lengths.push(length);
+    }
+    for view in array(&gltf["bufferViews"]) {
+        let length = index(&view["buffer"]).and_then(|buffer| lengths.get(buffer));
+        let offset = view["byteOffset"].as_u64().unwrap_or(0);
+        let end = view["byteLength"].as_u64().and_then(|view_length| offset.checked_add(view_length));
+        match (length, end) {
+            (Some(length), Some(end)) if end <= *length => {}
+            _ => return Err(invalid("a buffer view reaches past its buffer")),
+        }
+    }
+    Ok(())
+}
+
+/// Where image `image` is, when it's stored in the BIN chunk (`bin`: its offset and length)
+fn locate_image(gltf: &Value, image: usize, bin: Option<(u64, u64)>) -> Option<Thumbnail> {
+    let image = &gltf["images"][image];
+    let view = &gltf["bufferViews"][index(&image["bufferView"])?];
+    let (bin_offset, _) = bin?;
+    // Only the GLB buffer is in the file; check_buffers has seen the view fits
+    if index(&view["buffer"])? != 0 || gltf["buffers"][0].get("uri").is_some() {
+        return None;
+    }
+    Some(Thumbnail {
+        offset: bin_offset + view["byteOffset"].as_u64().unwrap_or(0),
+        length: view["byteLength"].as_u64()?,
+        mime_type: string(&image["mimeType"]).unwrap_or_else(|| "image/png".to_string()),
+    })
+}
+
+fn array(value: &Value) -> &[Value] {
+    value.as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+fn index(value: &Value) -> Option<usize> {
+    value.as_u64().and_then(|i| usize::try_from(i).ok())
+}
+
+/// A non-blank string, trimmed
+fn string(value: &Value) -> Option<String> {
+    value.as_str().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string)
+}
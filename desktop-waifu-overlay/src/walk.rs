@@ -0,0 +1,278 @@
+//! Walking around (`[walk]` in config.toml, or the tray)
+//!
+//! While walking is on and the character is left alone (shown, not dragged, chat closed),
+//! it sets off every `[walk] interval` seconds or so for a new spot along the bottom of
+//! the screen. On Hyprland and Sway it sometimes climbs onto the top edge of a window on
+//! its monitor instead, from the compositor's window list (compositor.rs). Each walk runs
+//! on the frame clock, easing in and out at `[walk] speed`: the page gets a `layoutChange`
+//! every frame and a `characterWalk` event when the character starts and stops, with the
+//! way it's facing, so it can play its running clip.
+
+use crate::layout::ChatState;
+use crate::{CharacterPosition, DragState, Quadrant};
+use gtk4::prelude::*;
+use gtk4::{glib, ApplicationWindow};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use webkit6::WebView;
+
+/// Out of this many walks, one goes onto a window when there's one to go to
+const PERCH_ODDS: i32 = 3;
+/// Shorter walks aren't worth getting up for
+const MIN_DISTANCE: f64 = 80.0;
+
+pub struct Walker {
+    window: ApplicationWindow,
+    webview: WebView,
+    position: Rc<RefCell<CharacterPosition>>,
+    quadrant: Rc<RefCell<Quadrant>>,
+    chat: Rc<RefCell<ChatState>>,
+    drag_state: Rc<RefCell<DragState>>,
+    is_visible: Rc<RefCell<bool>>,
+    /// Turned on or off from the tray, over `[walk] enabled`
+    enabled: Cell<Option<bool>>,
+    /// Timer for the next walk
+    next: RefCell<Option<glib::SourceId>>,
+    /// The walk under way
+    walk: RefCell<Option<Walk>>,
+}
+
+struct Walk {
+    from: (f64, f64),
+    to: (f64, f64),
+    /// Seconds
+    duration: f64,
+    /// Frame time of the first frame, in microseconds
+    start: Option<i64>,
+    tick: gtk4::TickCallbackId,
+}
+
+impl Walker {
+    pub fn new(
+        window: &ApplicationWindow,
+        webview: &WebView,
+        position: Rc<RefCell<CharacterPosition>>,
+        quadrant: Rc<RefCell<Quadrant>>,
+        chat: Rc<RefCell<ChatState>>,
+        drag_state: Rc<RefCell<DragState>>,
+        is_visible: Rc<RefCell<bool>>,
+    ) -> Rc<Walker> {
+        let walker = Rc::new(Walker {
+            window: window.clone(),
+            webview: webview.clone(),
+            position,
+            quadrant,
+            chat,
+            drag_state,
+            is_visible,
+            enabled: Cell::new(None),
+            next: RefCell::new(None),
+            walk: RefCell::new(None),
+        });
+        walker.schedule();
+        walker
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled.get().unwrap_or(crate::config::current().walk.enabled)
+    }
+
+    /// Turn walking on or off, over `[walk] enabled`
+    pub fn set_enabled(self: &Rc<Self>, enabled: bool) {
+        self.enabled.set(Some(enabled));
+        self.config_changed();
+    }
+
+    /// Start or stop walking after `[walk]` changed
+    pub fn config_changed(self: &Rc<Self>) {
+        if self.enabled() {
+            if self.next.borrow().is_none() && self.walk.borrow().is_none() {
+                self.schedule();
+            }
+        } else {
+            self.cancel();
+        }
+    }
+
+    /// Stop where it is and don't walk again until turned back on
+    fn cancel(&self) {
+        if let Some(next) = self.next.take() {
+            next.remove();
+        }
+        self.stop();
+    }
+
+    /// Set off again in about `[walk] interval` seconds
+    fn schedule(self: &Rc<Self>) {
+        if let Some(next) = self.next.take() {
+            next.remove();
+        }
+        if !self.enabled() {
+            return;
+        }
+        let interval = crate::config::current().walk.interval.max(1.0);
+        let delay = glib::random_double_range(interval * 0.5, interval * 1.5);
+        let walker = Rc::downgrade(self);
+        let source = glib::timeout_add_local_once(std::time::Duration::from_secs_f64(delay), move || {
+            let Some(walker) = walker.upgrade() else { return };
+            walker.next.take();
+            walker.set_off();
+        });
+        *self.next.borrow_mut() = Some(source);
+    }
+
+    /// Whether the character is free to walk
+    fn idle(&self) -> bool {
+        *self.is_visible.borrow() && !self.drag_state.borrow().is_dragging && !self.chat.borrow().open
+    }
+
+    fn set_off(self: &Rc<Self>) {
+        if !self.idle() {
+            self.schedule();
+            return;
+        }
+        let config = crate::config::current();
+        let perch = config.walk.perch_on_windows && glib::random_int_range(0, PERCH_ODDS) == 0;
+        let connector = crate::monitors::current_monitor(&self.window)
+            .and_then(|monitor| monitor.connector())
+            .map(|name| name.to_string());
+        let (Some(connector), true) = (connector, perch) else {
+            self.walk_to(self.ground_spot());
+            return;
+        };
+
+        let work_area = config.window.respect_panels;
+        let walker = Rc::downgrade(self);
+        glib::spawn_future_local(async move {
+            let task = crate::runtime::spawn(async move { crate::compositor::ledges(&connector, work_area).await });
+            let ledges = match task.await {
+                Ok(Ok(ledges)) => ledges,
+                Ok(Err(e)) => {
+                    crate::debug_log!("[WALK] Can't list windows: {}", e);
+                    Vec::new()
+                }
+                Err(_) => Vec::new(),
+            };
+            let Some(walker) = walker.upgrade() else { return };
+            if !walker.idle() {
+                walker.schedule();
+                return;
+            }
+            let target = walker.ledge_spot(&ledges).unwrap_or_else(|| walker.ground_spot());
+            walker.walk_to(target);
+        });
+    }
+
+    /// A random spot along the bottom of the screen
+    fn ground_spot(&self) -> (i32, i32) {
+        let margin = crate::config::current().position.margin;
+        let position = self.position.borrow();
+        let (screen_width, screen_height) = position.screen;
+        let (width, height) = position.size();
+        let x = glib::random_int_range(margin, (screen_width - width - margin).max(margin) + 1);
+        (x, screen_height - height - margin)
+    }
+
+    /// A random spot on top of one of `ledges` the character fits on, if any
+    fn ledge_spot(&self, ledges: &[crate::compositor::Ledge]) -> Option<(i32, i32)> {
+        let position = self.position.borrow();
+        let (screen_width, screen_height) = position.screen;
+        let (width, height) = position.size();
+        let fits: Vec<_> = ledges
+            .iter()
+            .filter(|ledge| ledge.width >= width && ledge.y >= height && ledge.y < screen_height)
+            .filter(|ledge| ledge.x >= 0 && ledge.x + ledge.width <= screen_width)
+            .collect();
+        if fits.is_empty() {
+            return None;
+        }
+        let ledge = fits[glib::random_int_range(0, fits.len() as i32) as usize];
+        let x = glib::random_int_range(ledge.x, ledge.x + ledge.width - width + 1);
+        Some((x, ledge.y - height))
+    }
+
+    fn walk_to(self: &Rc<Self>, (x, y): (i32, i32)) {
+        let (from_x, from_y) = {
+            let position = self.position.borrow();
+            (position.x as f64, position.y as f64)
+        };
+        let distance = (x as f64 - from_x).hypot(y as f64 - from_y);
+        if distance < MIN_DISTANCE {
+            self.schedule();
+            return;
+        }
+        let duration = distance / crate::config::current().walk.speed.max(1.0);
+        crate::debug_log!("[WALK] To {},{} over {:.1}s", x, y, duration);
+        self.stop();
+        self.send_walking(Some(if x as f64 > from_x { "right" } else { "left" }));
+
+        let walker = Rc::downgrade(self);
+        let tick = self.window.add_tick_callback(move |_, clock| {
+            let Some(walker) = walker.upgrade() else { return glib::ControlFlow::Break };
+            walker.step(clock.frame_time())
+        });
+        *self.walk.borrow_mut() = Some(Walk {
+            from: (from_x, from_y),
+            to: (x as f64, y as f64),
+            duration,
+            start: None,
+            tick,
+        });
+    }
+
+    /// Move the character for the frame at `now`
+    fn step(self: &Rc<Self>, now: i64) -> glib::ControlFlow {
+        if !self.idle() {
+            // Dragging or chatting; the tick callback ends with the walk
+            self.walk.take();
+            self.send_walking(None);
+            self.schedule();
+            return glib::ControlFlow::Break;
+        }
+        let progress = {
+            let mut walk = self.walk.borrow_mut();
+            let Some(walk) = walk.as_mut() else { return glib::ControlFlow::Break };
+            let start = *walk.start.get_or_insert(now);
+            let t = ((now - start) as f64 / 1_000_000.0 / walk.duration).clamp(0.0, 1.0);
+            // Ease in and out
+            let eased = t * t * (3.0 - 2.0 * t);
+            let mut position = self.position.borrow_mut();
+            position.x = (walk.from.0 + (walk.to.0 - walk.from.0) * eased).round() as i32;
+            position.y = (walk.from.1 + (walk.to.1 - walk.from.1) * eased).round() as i32;
+            position.clamp();
+            t
+        };
+
+        if progress < 1.0 {
+            crate::send_layout(&self.webview, &self.position.borrow(), &self.quadrant.borrow(), &self.chat.borrow());
+            return glib::ControlFlow::Continue;
+        }
+        self.walk.take();
+        let position = self.position.borrow();
+        let quadrant = position.quadrant(position.screen.0, position.screen.1);
+        crate::send_layout(&self.webview, &position, &quadrant, &self.chat.borrow());
+        *self.quadrant.borrow_mut() = quadrant;
+        drop(position);
+        crate::state::changed();
+        self.send_walking(None);
+        self.schedule();
+        glib::ControlFlow::Break
+    }
+
+    /// End the walk under way, if any, where the character is now
+    fn stop(&self) {
+        if let Some(walk) = self.walk.take() {
+            walk.tick.remove();
+            self.send_walking(None);
+        }
+    }
+
+    /// Tell the page the character started walking ("left" or "right") or stopped
+    fn send_walking(&self, direction: Option<&str>) {
+        crate::bridge::dispatch_event(
+            &self.webview,
+            "characterWalk",
+            &serde_json::json!({ "walking": direction.is_some(), "direction": direction }),
+        );
+    }
+}
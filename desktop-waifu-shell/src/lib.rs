@@ -0,0 +1,29 @@
+//! Running the assistant's shell commands, shared by the overlay and the Tauri app
+//!
+//! - [`policy`]: whether a command may run, needs confirmation or is refused
+//! - [`sandbox`]: confining commands in bubblewrap or firejail
+//! - [`session`]: shells that keep their state between commands
+//! - [`options`]: which shell runs a command, where, and with what environment
+//! - `pty`: commands attached to a pseudo-terminal, for interactive programs (Unix only)
+//!
+//! Where policy.toml and the scratch folder live is up to the app. Logging goes through
+//! `tracing`.
+
+pub mod options;
+pub mod policy;
+#[cfg(unix)]
+pub mod pty;
+pub mod sandbox;
+pub mod session;
+
+use std::path::PathBuf;
+
+/// `~/` and relative paths are taken from the home directory
+pub fn expand(path: &str) -> PathBuf {
+    let home = std::env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+    match path.strip_prefix("~/") {
+        Some(rest) => home.join(rest),
+        None if path == "~" => home,
+        None => home.join(path),
+    }
+}
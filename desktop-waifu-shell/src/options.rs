@@ -0,0 +1,71 @@
+//! Which shell runs a command, where, and with what extra environment
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Unset fields fall back to the user's login shell (`$SHELL`, else `sh`) and home
+/// directory. Commands run as `<shell> -c <cmd>`.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct ShellOptions {
+    pub shell: Option<String>,
+    /// `~/` expands to the home directory
+    pub cwd: Option<String>,
+    /// Added to the app's environment
+    pub env: HashMap<String, String>,
+}
+
+impl ShellOptions {
+    pub fn shell(&self) -> String {
+        self.shell
+            .clone()
+            .or_else(|| std::env::var("SHELL").ok())
+            .filter(|shell| !shell.is_empty())
+            .unwrap_or_else(|| "sh".into())
+    }
+
+    /// The working directory; `None` when it's the home directory and there is none
+    pub fn cwd(&self) -> Option<PathBuf> {
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        match self.cwd.as_deref().filter(|cwd| !cwd.is_empty()) {
+            Some("~") | None => home,
+            Some(cwd) => match (cwd.strip_prefix("~/"), home) {
+                (Some(rest), Some(home)) => Some(home.join(rest)),
+                _ => Some(PathBuf::from(cwd)),
+            },
+        }
+    }
+
+    /// `<shell> -c <cmd>` with the working directory and environment applied
+    pub fn command(&self, cmd: &str) -> Command {
+        let mut command = Command::new(self.shell());
+        command.arg("-c").arg(cmd).envs(&self.env);
+        if let Some(cwd) = self.cwd() {
+            command.current_dir(cwd);
+        }
+        command
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cwd_expands_home() {
+        let Some(home) = std::env::var_os("HOME").map(PathBuf::from) else {
+            return;
+        };
+        let options = |cwd: &str| ShellOptions {
+            cwd: Some(cwd.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(ShellOptions::default().cwd(), Some(home.clone()));
+        assert_eq!(options("").cwd(), Some(home.clone()));
+        assert_eq!(options("~").cwd(), Some(home.clone()));
+        assert_eq!(options("~/src").cwd(), Some(home.join("src")));
+        assert_eq!(options("/tmp").cwd(), Some(PathBuf::from("/tmp")));
+    }
+}
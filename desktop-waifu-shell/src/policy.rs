@@ -0,0 +1,443 @@
+//! Command policy (`policy.toml` in the app's data directory)
+//!
+//! Classifies a shell command before it is spawned: run it, run it only once the user has
+//! confirmed it, or refuse it outright. Rules are regexes matched anywhere in the command;
+//! when several match, the most restrictive action wins, so an `allow` rule can't unblock
+//! the built-in denials (`rm -rf /`, `dd` onto a device, fork bombs, ...).
+//!
+//! ```toml
+//! # Action for commands no rule matches
+//! default = "confirm"
+//!
+//! [[rules]]
+//! pattern = '^(ls|pwd|whoami|uptime)( [\w./-]+)*$'
+//! action = "allow"
+//!
+//! [[rules]]
+//! pattern = '\bsudo\b'
+//! action = "deny"
+//! reason = "no root from chat"
+//!
+//! # Run commands in bubblewrap (or firejail): read-only root, a writable scratch folder,
+//! # no network. Off by default
+//! [sandbox]
+//! enabled = true
+//! tool = "auto"            # "bwrap" or "firejail"; auto: whichever is installed
+//! network = false
+//! writable = ["~/projects"]  # folders commands may write to, besides the scratch folder
+//!
+//! # Categories adjust the sandbox for the commands they match; the first match wins
+//! [[sandbox.categories]]
+//! pattern = '^git (clone|fetch|pull|push)\b'
+//! network = true
+//!
+//! [[sandbox.categories]]
+//! pattern = '^(systemctl|loginctl) '
+//! sandbox = false          # run unsandboxed
+//! ```
+//!
+//! The file is read on every command, so edits apply without a restart. A file that can't
+//! be read or parsed stops all commands until it's fixed, rather than dropping its denials
+//! and sandbox.
+
+use crate::expand;
+use crate::sandbox::{Sandbox, SandboxTool};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Patterns that are always denied: (regex, reason)
+const BUILTIN_DENY: &[(&str, &str)] = &[
+    (
+        r"\brm\s+(\S+\s+)*-\S*[rR]\S*\s+(\S+\s+)*(/|/\*|~/?|\$HOME/?)(\s|$|[;&|])",
+        "recursive delete of / or home",
+    ),
+    (r"\bdd\b.*\bof=/dev/", "dd onto a device"),
+    (r"\bmkfs(\.\w+)?\b", "formats a filesystem"),
+    (r">\s*/dev/(sd|hd|vd|nvme|mmcblk)", "overwrites a block device"),
+    (r":\(\)\s*\{\s*:\s*\|\s*:\s*&\s*\}\s*;\s*:", "fork bomb"),
+    (r"\bchmod\s+(\S+\s+)*-\S*R\S*\s+(\S+\s+)*/(\s|$)", "recursive chmod of /"),
+];
+
+/// Ordered from least to most restrictive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Allow,
+    Confirm,
+    Deny,
+}
+
+#[derive(Debug, Deserialize)]
+struct RuleConfig {
+    pattern: String,
+    action: Action,
+    reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct PolicyConfig {
+    default: Action,
+    rules: Vec<RuleConfig>,
+    sandbox: SandboxConfig,
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            default: Action::Confirm,
+            rules: Vec::new(),
+            sandbox: SandboxConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct SandboxConfig {
+    enabled: bool,
+    tool: SandboxTool,
+    network: bool,
+    writable: Vec<String>,
+    categories: Vec<CategoryConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CategoryConfig {
+    pattern: String,
+    #[serde(default = "sandboxed")]
+    sandbox: bool,
+    network: Option<bool>,
+    #[serde(default)]
+    writable: Vec<String>,
+}
+
+fn sandboxed() -> bool {
+    true
+}
+
+struct Category {
+    regex: Regex,
+    config: CategoryConfig,
+}
+
+struct Rule {
+    regex: Regex,
+    action: Action,
+    reason: Option<String>,
+}
+
+/// How a command was classified, and by which rule
+#[derive(Debug, Clone, Serialize)]
+pub struct Verdict {
+    pub action: Action,
+    pub reason: Option<String>,
+}
+
+pub struct Policy {
+    default: Action,
+    rules: Vec<Rule>,
+    sandbox: SandboxConfig,
+    categories: Vec<Category>,
+    scratch: PathBuf,
+}
+
+impl Policy {
+    fn from_config(mut config: PolicyConfig, scratch: PathBuf) -> Self {
+        let builtin = BUILTIN_DENY.iter().map(|(pattern, reason)| Rule {
+            regex: Regex::new(pattern).expect("built-in policy pattern"),
+            action: Action::Deny,
+            reason: Some(reason.to_string()),
+        });
+        let configured = config.rules.into_iter().filter_map(|rule| match Regex::new(&rule.pattern) {
+            Ok(regex) => Some(Rule {
+                regex,
+                action: rule.action,
+                reason: rule.reason,
+            }),
+            Err(e) => {
+                tracing::warn!("Ignoring policy rule '{}': {}", rule.pattern, e);
+                None
+            }
+        });
+        let categories = std::mem::take(&mut config.sandbox.categories)
+            .into_iter()
+            .filter_map(|category| match Regex::new(&category.pattern) {
+                Ok(regex) => Some(Category { regex, config: category }),
+                Err(e) => {
+                    tracing::warn!("Ignoring sandbox category '{}': {}", category.pattern, e);
+                    None
+                }
+            })
+            .collect();
+        Self {
+            default: config.default,
+            rules: builtin.chain(configured).collect(),
+            sandbox: config.sandbox,
+            categories,
+            scratch,
+        }
+    }
+
+    /// The most restrictive matching rule, or the default action
+    pub fn classify(&self, cmd: &str) -> Verdict {
+        self.rules
+            .iter()
+            .filter(|rule| rule.regex.is_match(cmd))
+            .max_by_key(|rule| rule.action)
+            .map(|rule| Verdict {
+                action: rule.action,
+                reason: rule.reason.clone(),
+            })
+            .unwrap_or(Verdict {
+                action: self.default,
+                reason: None,
+            })
+    }
+
+    /// Ok if `cmd` may run now; `confirmed` says the user has approved this exact command
+    pub fn check(&self, cmd: &str, confirmed: bool) -> Result<(), String> {
+        let verdict = self.classify(cmd);
+        let reason = verdict.reason.map(|r| format!(": {}", r)).unwrap_or_default();
+        match verdict.action {
+            Action::Allow => Ok(()),
+            Action::Confirm if confirmed => Ok(()),
+            Action::Confirm => Err(format!("Command requires confirmation{}", reason)),
+            Action::Deny => Err(format!("Command blocked by policy{}", reason)),
+        }
+    }
+
+    /// The sandbox `cmd` runs in, adjusted by the first category matching it; None when
+    /// sandboxing is off or the category runs it unsandboxed
+    pub fn sandbox(&self, cmd: &str) -> Option<Sandbox> {
+        let mut sandbox = self.default_sandbox()?;
+        if let Some(category) = self.categories.iter().find(|category| category.regex.is_match(cmd)) {
+            if !category.config.sandbox {
+                return None;
+            }
+            sandbox.network = category.config.network.unwrap_or(sandbox.network);
+            sandbox
+                .writable
+                .extend(category.config.writable.iter().map(|dir| expand(dir)));
+        }
+        Some(sandbox)
+    }
+
+    /// The sandbox without any category applied, for shells that run many commands
+    pub fn default_sandbox(&self) -> Option<Sandbox> {
+        self.sandbox.enabled.then(|| Sandbox {
+            tool: self.sandbox.tool,
+            network: self.sandbox.network,
+            writable: self.sandbox.writable.iter().map(|dir| expand(dir)).collect(),
+            scratch: self.scratch.clone(),
+        })
+    }
+}
+
+/// The policy file at `path` on top of the built-in denials, with sandboxed commands
+/// writing to `scratch`. A missing file means defaults (which require confirmation for
+/// everything); one that can't be read or parsed is an error, so no command runs without
+/// the rules and sandbox it asks for.
+pub fn load(path: &Path, scratch: PathBuf) -> Result<Policy, String> {
+    let config = match std::fs::read_to_string(path) {
+        Ok(text) => toml::from_str(&text).map_err(|e| {
+            tracing::warn!("Invalid {}: {}", path.display(), e);
+            format!("Commands are disabled until {} is fixed: {}", path.display(), e)
+        })?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => PolicyConfig::default(),
+        Err(e) => return Err(format!("Commands are disabled, {} can't be read: {}", path.display(), e)),
+    };
+    Ok(Policy::from_config(config, scratch))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(toml: &str) -> Policy {
+        Policy::from_config(toml::from_str(toml).expect("test policy parses"), PathBuf::from("/tmp/scratch"))
+    }
+
+    fn action(policy: &Policy, cmd: &str) -> Action {
+        policy.classify(cmd).action
+    }
+
+    #[test]
+    fn denies_recursive_delete_of_root_or_home() {
+        let policy = policy("default = \"allow\"");
+        for cmd in [
+            "rm -rf /",
+            "rm -rf /*",
+            "rm -fr ~",
+            "rm -rf ~/",
+            "rm -rf $HOME",
+            "rm -r -f /",
+            "rm --no-preserve-root -rf / ",
+            "cd /tmp && rm -Rf /; echo done",
+        ] {
+            assert_eq!(action(&policy, cmd), Action::Deny, "{}", cmd);
+        }
+    }
+
+    #[test]
+    fn denies_dd_onto_a_device() {
+        let policy = policy("default = \"allow\"");
+        assert_eq!(action(&policy, "dd if=/dev/zero of=/dev/sda bs=1M"), Action::Deny);
+        assert_eq!(action(&policy, "sudo dd if=image.iso of=/dev/mmcblk0"), Action::Deny);
+    }
+
+    #[test]
+    fn denies_mkfs() {
+        let policy = policy("default = \"allow\"");
+        assert_eq!(action(&policy, "mkfs /dev/sdb1"), Action::Deny);
+        assert_eq!(action(&policy, "mkfs.ext4 /dev/nvme0n1p2"), Action::Deny);
+    }
+
+    #[test]
+    fn denies_overwriting_a_block_device() {
+        let policy = policy("default = \"allow\"");
+        assert_eq!(action(&policy, "echo hi > /dev/sda"), Action::Deny);
+        assert_eq!(action(&policy, "cat image >/dev/nvme0n1"), Action::Deny);
+    }
+
+    #[test]
+    fn denies_fork_bombs() {
+        let policy = policy("default = \"allow\"");
+        assert_eq!(action(&policy, ":(){ :|:& };:"), Action::Deny);
+        assert_eq!(action(&policy, ":() { : | : & } ; :"), Action::Deny);
+    }
+
+    #[test]
+    fn denies_recursive_chmod_of_root() {
+        let policy = policy("default = \"allow\"");
+        assert_eq!(action(&policy, "chmod -R 777 /"), Action::Deny);
+        assert_eq!(action(&policy, "chmod -vR a+w /"), Action::Deny);
+    }
+
+    #[test]
+    fn leaves_near_misses_to_the_default() {
+        let policy = policy("default = \"allow\"");
+        for cmd in [
+            "rm -rf ~/x",
+            "rm -rf ./build",
+            "rm -rf /tmp/build",
+            "rm -f /etc/foo.conf",
+            "rm -rf $HOME/.cache/thumbnails",
+            "dd if=/dev/sda of=disk.img",
+            "mkdir build",
+            "cat /dev/sda | head",
+            "echo hi > /dev/null",
+            "chmod -R 755 /srv/www",
+            "chmod 755 /",
+        ] {
+            assert_eq!(action(&policy, cmd), Action::Allow, "{}", cmd);
+        }
+    }
+
+    #[test]
+    fn allow_rules_cannot_unblock_builtin_denials() {
+        let policy = policy(
+            r#"
+            [[rules]]
+            pattern = '.*'
+            action = "allow"
+            "#,
+        );
+        let verdict = policy.classify("rm -rf /");
+        assert_eq!(verdict.action, Action::Deny);
+        assert_eq!(verdict.reason.as_deref(), Some("recursive delete of / or home"));
+        assert_eq!(action(&policy, "ls"), Action::Allow);
+    }
+
+    #[test]
+    fn most_restrictive_matching_rule_wins() {
+        let policy = policy(
+            r#"
+            default = "deny"
+
+            [[rules]]
+            pattern = '^git '
+            action = "allow"
+
+            [[rules]]
+            pattern = '^git push\b'
+            action = "confirm"
+            reason = "pushes"
+
+            [[rules]]
+            pattern = '--force\b'
+            action = "deny"
+            "#,
+        );
+        assert_eq!(action(&policy, "git status"), Action::Allow);
+        assert_eq!(action(&policy, "git push origin main"), Action::Confirm);
+        assert_eq!(action(&policy, "git push --force"), Action::Deny);
+        assert_eq!(action(&policy, "ls"), Action::Deny);
+    }
+
+    #[test]
+    fn confirmation_only_lifts_confirm() {
+        let policy = policy("default = \"confirm\"");
+        assert!(policy.check("ls", false).is_err());
+        assert!(policy.check("ls", true).is_ok());
+        assert!(policy.check("mkfs /dev/sda1", true).is_err());
+    }
+
+    #[test]
+    fn missing_file_means_defaults() {
+        let path = std::env::temp_dir().join(format!("policy-missing-{}.toml", std::process::id()));
+        let policy = load(&path, PathBuf::from("/tmp/scratch")).expect("defaults");
+        assert_eq!(action(&policy, "ls"), Action::Confirm);
+    }
+
+    #[test]
+    fn unparsable_file_is_an_error() {
+        let path = std::env::temp_dir().join(format!("policy-invalid-{}.toml", std::process::id()));
+        std::fs::write(&path, "[sandbox]\nenabled = true\n\n[[rules]]\npattern = '^ls$'\naction = \"alow\"\n").unwrap();
+        let loaded = load(&path, PathBuf::from("/tmp/scratch"));
+        let _ = std::fs::remove_file(&path);
+        assert!(loaded.is_err());
+    }
+
+    #[test]
+    fn invalid_rules_are_skipped() {
+        let policy = policy(
+            r#"
+            default = "confirm"
+
+            [[rules]]
+            pattern = '('
+            action = "allow"
+
+            [[rules]]
+            pattern = '^ls$'
+            action = "allow"
+            "#,
+        );
+        assert_eq!(action(&policy, "ls"), Action::Allow);
+        assert_eq!(action(&policy, "("), Action::Confirm);
+    }
+
+    #[test]
+    fn categories_adjust_the_sandbox() {
+        let policy = policy(
+            r#"
+            [sandbox]
+            enabled = true
+
+            [[sandbox.categories]]
+            pattern = '^git (clone|fetch|pull|push)\b'
+            network = true
+
+            [[sandbox.categories]]
+            pattern = '^systemctl '
+            sandbox = false
+            "#,
+        );
+        assert!(!policy.sandbox("curl example.com").expect("sandboxed").network);
+        assert!(policy.sandbox("git pull").expect("sandboxed").network);
+        assert!(policy.sandbox("systemctl --user restart foo").is_none());
+        assert!(self::policy("").sandbox("ls").is_none());
+    }
+}
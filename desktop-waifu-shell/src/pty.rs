@@ -0,0 +1,130 @@
+//! Commands attached to a pseudo-terminal (Unix only)
+//!
+//! `sudo`, `ssh` and anything else that checks `isatty` misbehave with piped stdio.
+//! `spawn` runs a command as the session leader of a new PTY; everything it prints
+//! (stdout and stderr interleaved, as on a terminal) is read from the PTY's master side,
+//! and writing to the master types into it: answers to prompts, passwords, Ctrl-C.
+
+use std::fs::File;
+use std::io::Read;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Child, Command, Stdio};
+use std::sync::Arc;
+
+/// Terminal size reported to programs that ask
+const COLUMNS: u16 = 120;
+const ROWS: u16 = 40;
+
+/// A command running on its own PTY
+pub struct Pty {
+    child: Child,
+    master: Arc<File>,
+}
+
+/// Start `command` (a `<shell> -c ...`) on a new PTY, in a new session and process group
+pub fn spawn(mut command: Command) -> std::io::Result<Pty> {
+    let (master, slave) = open_pty()?;
+
+    command
+        // Escape sequences would only clutter the transcript
+        .env("TERM", "dumb")
+        .stdin(Stdio::from(slave.try_clone()?))
+        .stdout(Stdio::from(slave.try_clone()?))
+        .stderr(Stdio::from(slave));
+    unsafe {
+        // New session with the PTY as its controlling terminal, so /dev/tty works and
+        // Ctrl-C written to the master reaches the command
+        command.pre_exec(|| {
+            if libc::setsid() < 0 || libc::ioctl(0, libc::TIOCSCTTY as _, 0) < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    let child = command.spawn()?;
+    // Our copies of the slave side are gone now, so reads on the master end with EIO once
+    // the command closes its terminal
+    Ok(Pty {
+        child,
+        master: Arc::new(File::from(master)),
+    })
+}
+
+impl Pty {
+    pub fn pid(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// The terminal's master side; what's written to it is typed into the command
+    pub fn input(&self) -> Arc<File> {
+        self.master.clone()
+    }
+
+    /// Pass everything the command prints to `on_output` until it and everything holding
+    /// its terminal are done, or `on_output` returns false. Blocks.
+    pub fn read(&self, mut on_output: impl FnMut(&[u8]) -> bool) {
+        let mut buf = [0u8; 8192];
+        loop {
+            match (&*self.master).read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    if !on_output(&buf[..n]) {
+                        break;
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                // EIO: the slave side closed
+                Err(_) => break,
+            }
+        }
+    }
+
+    /// Wait for the command to exit; signals are reported as 128 + signal, like a shell does
+    pub fn wait(mut self) -> std::io::Result<i32> {
+        let status = self.child.wait()?;
+        Ok(status.code().or_else(|| status.signal().map(|s| 128 + s)).unwrap_or(-1))
+    }
+}
+
+fn open_pty() -> std::io::Result<(OwnedFd, OwnedFd)> {
+    let mut master = -1;
+    let mut slave = -1;
+    let size = libc::winsize {
+        ws_row: ROWS,
+        ws_col: COLUMNS,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    let result = unsafe {
+        libc::openpty(&mut master, &mut slave, std::ptr::null_mut(), std::ptr::null(), &size)
+    };
+    if result < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let (master, slave) = unsafe { (OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) };
+    // Keep the master out of the command (and anything else the app spawns)
+    unsafe {
+        libc::fcntl(master.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC);
+    }
+    Ok((master, slave))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_see_a_terminal() {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("test -t 0 && test -t 1 && printf tty; exit 3");
+        let pty = spawn(command).expect("PTY starts");
+        let mut output = Vec::new();
+        pty.read(|bytes| {
+            output.extend_from_slice(bytes);
+            true
+        });
+        assert_eq!(String::from_utf8_lossy(&output), "tty");
+        assert_eq!(pty.wait().unwrap(), 3);
+    }
+}
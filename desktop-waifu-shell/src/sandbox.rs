@@ -0,0 +1,136 @@
+//! Sandboxed commands (`[sandbox]` in policy.toml)
+//!
+//! With the sandbox on, commands run inside bubblewrap, or firejail when that's what is
+//! installed: the whole filesystem is visible but read-only, `/tmp` is private, the only
+//! writable places are the scratch folder (`scratch` in the app's data directory) and the
+//! folders listed under `writable`, and there is no network unless it's granted. Policy
+//! categories loosen or tighten this per command (policy.rs). If neither tool is installed,
+//! sandboxed commands are refused rather than run unconfined, which on macOS and Windows
+//! means always.
+
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SandboxTool {
+    /// bwrap if installed, else firejail
+    #[default]
+    Auto,
+    Bwrap,
+    Firejail,
+}
+
+impl SandboxTool {
+    /// The concrete tool to use, if it's installed
+    fn resolve(self) -> Option<SandboxTool> {
+        match self {
+            SandboxTool::Auto => [SandboxTool::Bwrap, SandboxTool::Firejail]
+                .into_iter()
+                .find(|tool| installed(tool.program())),
+            tool => installed(tool.program()).then_some(tool),
+        }
+    }
+
+    fn program(self) -> &'static str {
+        match self {
+            SandboxTool::Auto | SandboxTool::Bwrap => "bwrap",
+            SandboxTool::Firejail => "firejail",
+        }
+    }
+}
+
+/// How one command is confined
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    pub tool: SandboxTool,
+    pub network: bool,
+    /// Writable besides the scratch folder; missing folders are skipped
+    pub writable: Vec<PathBuf>,
+    pub scratch: PathBuf,
+}
+
+impl Sandbox {
+    /// `command` run inside the sandbox, with its working directory and environment
+    pub fn wrap(&self, command: Command) -> Result<Command, String> {
+        let tool = self.tool.resolve().ok_or_else(|| {
+            format!(
+                "Commands are sandboxed, but {} is not installed",
+                match self.tool {
+                    SandboxTool::Auto => "neither bwrap nor firejail",
+                    tool => tool.program(),
+                }
+            )
+        })?;
+        std::fs::create_dir_all(&self.scratch).map_err(|e| format!("{}: {}", self.scratch.display(), e))?;
+        let writable: Vec<&Path> = std::iter::once(self.scratch.as_path())
+            .chain(self.writable.iter().map(PathBuf::as_path).filter(|dir| dir.is_dir()))
+            .collect();
+
+        let mut wrapped = Command::new(tool.program());
+        match tool {
+            SandboxTool::Firejail => {
+                wrapped.args(["--quiet", "--noprofile", "--read-only=/", "--private-tmp"]);
+                for dir in &writable {
+                    wrapped.arg(format!("--read-write={}", dir.display()));
+                }
+                if !self.network {
+                    wrapped.arg("--net=none");
+                }
+            }
+            _ => {
+                wrapped.args(["--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc", "--tmpfs", "/tmp"]);
+                for dir in &writable {
+                    wrapped.arg("--bind").arg(dir).arg(dir);
+                }
+                wrapped.args(["--unshare-pid", "--die-with-parent"]);
+                if !self.network {
+                    wrapped.arg("--unshare-net");
+                }
+            }
+        }
+        wrapped.arg("--").arg(command.get_program()).args(command.get_args());
+        if let Some(dir) = command.get_current_dir() {
+            wrapped.current_dir(dir);
+        }
+        for (key, value) in command.get_envs() {
+            match value {
+                Some(value) => wrapped.env(key, value),
+                None => wrapped.env_remove(key),
+            };
+        }
+        tracing::debug!(
+            "Sandboxing with {} ({} writable, network {})",
+            tool.program(),
+            writable.len(),
+            if self.network { "on" } else { "off" }
+        );
+        Ok(wrapped)
+    }
+}
+
+/// `command` as the policy wants `cmd` run: wrapped in its sandbox, or as it is
+pub fn apply(policy: &crate::policy::Policy, cmd: &str, command: Command) -> Result<Command, String> {
+    match policy.sandbox(cmd) {
+        Some(sandbox) => sandbox.wrap(command),
+        None => Ok(command),
+    }
+}
+
+/// Whether `program` is an executable on PATH
+fn installed(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| executable(&dir.join(program))))
+}
+
+#[cfg(unix)]
+fn executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path).is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn executable(path: &Path) -> bool {
+    path.is_file()
+}
@@ -0,0 +1,215 @@
+//! Persistent shell sessions
+//!
+//! Every command the assistant runs starts a fresh `sh -c`, so `cd`, exported variables and activated
+//! virtualenvs are gone by the assistant's next step. A session keeps one shell running and
+//! feeds it commands on stdin. After each command the shell prints a sentinel line (with the
+//! exit status on stdout) to both pipes, which marks where that command's output ends.
+//!
+//! With the sandbox on, the whole shell runs in it with the default settings; categories
+//! can't adjust a shell that's already running, so they don't apply to session commands.
+
+use crate::sandbox::Sandbox;
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::{Arc, LazyLock, Mutex};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command};
+
+static SESSIONS: LazyLock<Mutex<HashMap<String, Arc<Session>>>> = LazyLock::new(Default::default);
+
+struct Session {
+    /// The shell's process group, so destroying the session also ends what it started
+    #[cfg(unix)]
+    pgid: u32,
+    /// Held across a whole command, so commands in one session run one at a time
+    shell: tokio::sync::Mutex<Shell>,
+}
+
+struct Shell {
+    _child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    stderr: BufReader<ChildStderr>,
+    /// Unique per session; numbered per command to form the sentinel
+    token: String,
+    commands: u64,
+}
+
+pub struct SessionOutput {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub exit_code: i32,
+}
+
+/// Start a shell for session `id`, inside `sandbox` if given. Call from within the tokio
+/// runtime the session's commands will run on; the shell's pipes belong to its reactor.
+pub fn create(id: String, sandbox: Option<Sandbox>) -> Result<(), String> {
+    let mut sessions = SESSIONS.lock().unwrap();
+    if sessions.contains_key(&id) {
+        return Err(format!("A session with id '{}' already exists", id));
+    }
+
+    let sh = std::process::Command::new("sh");
+    let sh = match sandbox {
+        Some(sandbox) => sandbox.wrap(sh)?,
+        None => sh,
+    };
+    let mut command = Command::from(sh);
+    command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    #[cfg(unix)]
+    command.process_group(0);
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Failed to start shell: {}", e))?;
+    let pgid = child.id().ok_or("Shell exited at startup")?;
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let shell = Shell {
+        stdin: child.stdin.take().ok_or("Failed to capture stdin")?,
+        stdout: BufReader::new(child.stdout.take().ok_or("Failed to capture stdout")?),
+        stderr: BufReader::new(child.stderr.take().ok_or("Failed to capture stderr")?),
+        _child: child,
+        token: format!("__desktop_waifu_{:x}_{}", nanos, pgid),
+        commands: 0,
+    };
+
+    tracing::info!("Started shell session {} (pid {})", id, pgid);
+    sessions.insert(
+        id,
+        Arc::new(Session {
+            #[cfg(unix)]
+            pgid,
+            shell: tokio::sync::Mutex::new(shell),
+        }),
+    );
+    Ok(())
+}
+
+/// Run `cmd` in session `id` and collect its output. Waits for earlier commands in the same
+/// session. The command's stdin is /dev/null so it can't swallow the sentinels.
+pub async fn exec(id: &str, cmd: &str) -> Result<SessionOutput, String> {
+    // A syntax error would make the non-interactive shell exit, so check first
+    let check = Command::new("sh")
+        .arg("-n")
+        .arg("-c")
+        .arg(cmd)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| format!("Failed to check command: {}", e))?;
+    if !check.status.success() {
+        return Ok(SessionOutput {
+            stdout: Vec::new(),
+            stderr: check.stderr,
+            exit_code: check.status.code().unwrap_or(2),
+        });
+    }
+
+    let session = SESSIONS
+        .lock()
+        .unwrap()
+        .get(id)
+        .cloned()
+        .ok_or_else(|| format!("No session with id '{}'", id))?;
+    let mut shell = session.shell.lock().await;
+    shell.commands += 1;
+    let sentinel = format!("{}_{}", shell.token, shell.commands);
+
+    // The brace group keeps `cd` and assignments in the session's shell
+    let script = format!(
+        "{{\n{cmd}\n}} < /dev/null\nprintf '\\n%s %d\\n' {sentinel} \"$?\"\nprintf '\\n%s\\n' {sentinel} >&2\n"
+    );
+    let result = async {
+        shell
+            .stdin
+            .write_all(script.as_bytes())
+            .await
+            .map_err(|e| format!("Failed to send command: {}", e))?;
+        let Shell { stdout, stderr, .. } = &mut *shell;
+        let (stdout, stderr) = tokio::join!(
+            read_until_sentinel(stdout, &sentinel),
+            read_until_sentinel(stderr, &sentinel),
+        );
+        let ((stdout, status), (stderr, _)) = (stdout?, stderr?);
+        Ok(SessionOutput {
+            stdout,
+            stderr,
+            exit_code: status.unwrap_or(-1),
+        })
+    }
+    .await;
+
+    if result.is_err() {
+        // The shell is gone (`exit`, a fatal error, or destroyed meanwhile)
+        drop(shell);
+        remove(id, &session);
+    }
+    result
+}
+
+/// Kill session `id` and everything running in it. Returns false if there is no such session.
+pub fn destroy(id: &str) -> bool {
+    let Some(session) = SESSIONS.lock().unwrap().remove(id) else {
+        return false;
+    };
+    tracing::info!("Destroying shell session {}", id);
+    kill(&session);
+    true
+}
+
+#[cfg(unix)]
+fn kill(session: &Session) {
+    unsafe {
+        libc::kill(-(session.pgid as i32), libc::SIGKILL);
+    }
+}
+
+/// Without process groups only the shell itself is killed: now if no command is running,
+/// otherwise once that command's `exec` drops the session
+#[cfg(not(unix))]
+fn kill(session: &Session) {
+    if let Ok(mut shell) = session.shell.try_lock() {
+        let _ = shell._child.start_kill();
+    }
+}
+
+fn remove(id: &str, session: &Arc<Session>) {
+    let mut sessions = SESSIONS.lock().unwrap();
+    if sessions.get(id).is_some_and(|s| Arc::ptr_eq(s, session)) {
+        sessions.remove(id);
+    }
+}
+
+/// Output up to the sentinel line, and the exit status printed after it (if any)
+async fn read_until_sentinel<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    sentinel: &str,
+) -> Result<(Vec<u8>, Option<i32>), String> {
+    let mut output = Vec::new();
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let n = reader
+            .read_until(b'\n', &mut line)
+            .await
+            .map_err(|e| format!("Failed to read session output: {}", e))?;
+        if n == 0 {
+            return Err("The session's shell has exited".into());
+        }
+        let text = String::from_utf8_lossy(&line);
+        if let Some(rest) = text.trim_end_matches('\n').strip_prefix(sentinel) {
+            if rest.is_empty() || rest.starts_with(' ') {
+                // Drop the newline printed before the sentinel
+                output.pop();
+                return Ok((output, rest.trim().parse().ok()));
+            }
+        }
+        output.extend_from_slice(&line);
+    }
+}
@@ -35,6 +35,8 @@
             wayland
             wayland-protocols
             dbus
+            gst_all_1.gstreamer
+            gst_all_1.gst-plugins-base
             meson
             ninja
             gobject-introspection
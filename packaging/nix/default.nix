@@ -15,6 +15,7 @@
   wayland-protocols,
   wayland-scanner,
   dbus,
+  gst_all_1,
   meson,
   ninja,
   gobject-introspection,
@@ -136,6 +137,8 @@ rustPlatform.buildRustPackage {
     pango
     wayland
     dbus
+    gst_all_1.gstreamer
+    gst_all_1.gst-plugins-base
   ];
 
   # Skip cargo tests
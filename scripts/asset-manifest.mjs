@@ -0,0 +1,41 @@
+// Write dist/asset-manifest.json: every built file with its size and SHA-256, plus the
+// frontend version and the overlay version it goes with. The overlay checks dist against
+// it at startup and compares it with a release's manifest for frontend updates
+// (desktop-waifu-overlay/src/frontend.rs).
+
+import { createHash } from "node:crypto";
+import { readFileSync, readdirSync, writeFileSync } from "node:fs";
+import { join, relative, sep } from "node:path";
+
+const root = join(import.meta.dirname, "..");
+const dist = join(root, "dist");
+const MANIFEST = "asset-manifest.json";
+
+const version = JSON.parse(readFileSync(join(root, "package.json"), "utf8")).version;
+const cargo = readFileSync(join(root, "desktop-waifu-overlay", "Cargo.toml"), "utf8");
+const overlayVersion = cargo.match(/^version\s*=\s*"([^"]+)"/m)?.[1] ?? version;
+
+function* walk(dir) {
+  for (const entry of readdirSync(dir, { withFileTypes: true })) {
+    const path = join(dir, entry.name);
+    if (entry.isDirectory()) {
+      yield* walk(path);
+    } else if (entry.isFile()) {
+      yield path;
+    }
+  }
+}
+
+const files = {};
+for (const path of [...walk(dist)].sort()) {
+  const name = relative(dist, path).split(sep).join("/");
+  if (name === MANIFEST) continue;
+  const contents = readFileSync(path);
+  files[name] = {
+    size: contents.length,
+    sha256: createHash("sha256").update(contents).digest("hex"),
+  };
+}
+
+writeFileSync(join(dist, MANIFEST), JSON.stringify({ version, overlayVersion, files }, null, 2) + "\n");
+console.log(`Wrote ${MANIFEST} with ${Object.keys(files).length} files`);
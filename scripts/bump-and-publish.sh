@@ -88,6 +88,8 @@ VERSION_FILES=(
     "src-tauri/tauri.conf.json"
     "src-tauri/Cargo.toml"
     "desktop-waifu-overlay/Cargo.toml"
+    "desktop-waifu-shell/Cargo.toml"
+    "desktop-waifu-llm/Cargo.toml"
     "packaging/aur/PKGBUILD"
     "packaging/homebrew/desktop-waifu.rb"
     "packaging/nix/default.nix"
@@ -38,6 +38,14 @@ sed -i "s/^version = \"[^\"]*\"/version = \"$NEW_VERSION\"/" "$PROJECT_ROOT/src-
 echo "  - desktop-waifu-overlay/Cargo.toml"
 sed -i "s/^version = \"[^\"]*\"/version = \"$NEW_VERSION\"/" "$PROJECT_ROOT/desktop-waifu-overlay/Cargo.toml"
 
+# Update desktop-waifu-shell/Cargo.toml
+echo "  - desktop-waifu-shell/Cargo.toml"
+sed -i "s/^version = \"[^\"]*\"/version = \"$NEW_VERSION\"/" "$PROJECT_ROOT/desktop-waifu-shell/Cargo.toml"
+
+# Update desktop-waifu-llm/Cargo.toml
+echo "  - desktop-waifu-llm/Cargo.toml"
+sed -i "s/^version = \"[^\"]*\"/version = \"$NEW_VERSION\"/" "$PROJECT_ROOT/desktop-waifu-llm/Cargo.toml"
+
 # Update Cargo.lock files
 echo "  - desktop-waifu-overlay/Cargo.lock"
 (cd "$PROJECT_ROOT/desktop-waifu-overlay" && cargo update --quiet)
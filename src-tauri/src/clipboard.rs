@@ -0,0 +1,64 @@
+//! System clipboard access through wl-clipboard (`wl-copy`/`wl-paste`), or `xclip` when
+//! there is no Wayland display
+//!
+//! The webview's own clipboard API only works during a user gesture, which rules out the
+//! assistant quoting the clipboard or copying a snippet on its own.
+
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+fn use_wayland() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+/// Text on the clipboard, or `None` if it is empty or holds something else (an image, ...)
+pub async fn read_text() -> Result<Option<String>, String> {
+    let (program, args): (&str, &[&str]) = if use_wayland() {
+        ("wl-paste", &["--no-newline", "--type", "text/plain;charset=utf-8"])
+    } else {
+        ("xclip", &["-selection", "clipboard", "-out", "-target", "UTF8_STRING"])
+    };
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    // Both exit non-zero when the clipboard has no text
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()))
+}
+
+pub async fn write_text(text: &str) -> Result<(), String> {
+    let (program, args): (&str, &[&str]) = if use_wayland() {
+        ("wl-copy", &["--type", "text/plain;charset=utf-8"])
+    } else {
+        ("xclip", &["-selection", "clipboard", "-in"])
+    };
+    // Both fork a process that keeps serving the selection and holds on to any pipes, so
+    // only stdin is piped and the wait ends when the foreground process exits
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(text.as_bytes())
+            .await
+            .map_err(|e| format!("Failed to write to {}: {}", program, e))?;
+    }
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| format!("{} failed: {}", program, e))?;
+    if !status.success() {
+        return Err(format!("{} exited with {}", program, status));
+    }
+    Ok(())
+}
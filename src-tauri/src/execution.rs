@@ -0,0 +1,282 @@
+//! Running shell commands: the registry that lets them be cancelled by ID, and the
+//! timeout, output and CPU limits callers can put on them
+//!
+//! On Unix each command gets its own process group, so a cancel or timeout reaches
+//! everything it started. Elsewhere only the command itself is killed, and priority and CPU
+//! limits are not applied.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, ChildStdin, Command};
+
+/// Time a cancelled command gets to exit after SIGTERM before it is killed
+#[cfg(unix)]
+const CANCEL_GRACE: Duration = Duration::from_secs(3);
+/// How long output is still read after a command was killed. Something it started that
+/// left its process group (`setsid`, a daemon) can hold the pipes open indefinitely.
+const DRAIN_AFTER_KILL: Duration = Duration::from_secs(2);
+
+static RUNNING: LazyLock<Mutex<HashMap<String, Arc<Execution>>>> = LazyLock::new(Default::default);
+
+/// A spawned command, running in its own process group on Unix
+pub struct Execution {
+    #[cfg(unix)]
+    pgid: i32,
+    /// Where `cancel` asks `enforce_timeout` to kill the command, without process groups
+    #[cfg(not(unix))]
+    kill: tokio::sync::Notify,
+    cancelled: AtomicBool,
+    /// Set for commands started with their stdin kept open for `write_stdin`
+    stdin: tokio::sync::Mutex<Option<ChildStdin>>,
+}
+
+impl Execution {
+    pub fn was_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Removes the command from the registry when it finishes
+pub struct Registration {
+    id: String,
+    execution: Arc<Execution>,
+}
+
+impl Registration {
+    pub fn execution(&self) -> &Execution {
+        &self.execution
+    }
+
+    /// Let `write_stdin` feed the command until it exits
+    pub async fn attach_stdin(&self, stdin: ChildStdin) {
+        *self.execution.stdin.lock().await = Some(stdin);
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let mut running = RUNNING.lock().unwrap();
+        if running.get(&self.id).is_some_and(|e| Arc::ptr_eq(e, &self.execution)) {
+            running.remove(&self.id);
+        }
+    }
+}
+
+/// Track a command spawned with `process_group(0)`, i.e. whose pid is its process group id
+#[cfg_attr(not(unix), allow(unused_variables))]
+pub fn register(id: String, pid: u32) -> Result<Registration, String> {
+    let mut running = RUNNING.lock().unwrap();
+    if running.contains_key(&id) {
+        return Err(format!("A command with id '{}' is already running", id));
+    }
+    let execution = Arc::new(Execution {
+        #[cfg(unix)]
+        pgid: pid as i32,
+        #[cfg(not(unix))]
+        kill: tokio::sync::Notify::new(),
+        cancelled: AtomicBool::new(false),
+        stdin: Default::default(),
+    });
+    running.insert(id.clone(), execution.clone());
+    Ok(Registration { id, execution })
+}
+
+/// Send SIGTERM to the command's process group, then SIGKILL if it is still running after
+/// the grace period; without process groups the command is killed at once. Returns false
+/// if no command with this id is running.
+pub fn cancel(id: &str) -> bool {
+    let Some(execution) = RUNNING.lock().unwrap().get(id).cloned() else {
+        return false;
+    };
+    execution.cancelled.store(true, Ordering::Relaxed);
+    #[cfg(not(unix))]
+    execution.kill.notify_one();
+    #[cfg(unix)]
+    escalate(id, execution);
+    true
+}
+
+#[cfg(unix)]
+fn escalate(id: &str, execution: Arc<Execution>) {
+    signal(execution.pgid, libc::SIGTERM);
+
+    let id = id.to_string();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(CANCEL_GRACE).await;
+        let still_running = RUNNING
+            .lock()
+            .unwrap()
+            .get(&id)
+            .is_some_and(|e| Arc::ptr_eq(e, &execution));
+        if still_running {
+            println!("[Tauri] Command {} ignored SIGTERM, killing it", id);
+            signal(execution.pgid, libc::SIGKILL);
+        }
+    });
+}
+
+/// Write `data` to a running command's stdin; with `eof`, close it afterwards so the
+/// command sees end of input
+pub async fn write_stdin(id: &str, data: &[u8], eof: bool) -> Result<(), String> {
+    let execution = RUNNING
+        .lock()
+        .unwrap()
+        .get(id)
+        .cloned()
+        .ok_or_else(|| format!("No running command with id '{}'", id))?;
+    let mut stdin = execution.stdin.lock().await;
+    let pipe = stdin
+        .as_mut()
+        .ok_or_else(|| format!("Command '{}' does not accept input", id))?;
+    let written = match pipe.write_all(data).await {
+        Ok(()) => pipe.flush().await,
+        Err(e) => Err(e),
+    };
+    written.map_err(|e| format!("Failed to write to command: {}", e))?;
+    if eof {
+        *stdin = None;
+    }
+    Ok(())
+}
+
+/// Optional limits for one command; unset fields mean no limit
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionLimits {
+    /// Kill the command after this many seconds of wall time
+    pub timeout_secs: Option<u64>,
+    /// Keep at most this many bytes of stdout and of stderr each; the rest is discarded
+    pub max_output_bytes: Option<usize>,
+    /// Scheduling priority adjustment (0..=19, higher is nicer)
+    pub nice: Option<i32>,
+    /// CPU time limit in seconds; the kernel sends SIGXCPU, then SIGKILL
+    pub cpu_secs: Option<u64>,
+}
+
+impl ExecutionLimits {
+    /// Run the command in its own process group (so timeouts and cancel reach its
+    /// children) with the priority and CPU limit applied
+    #[cfg(unix)]
+    pub fn apply(&self, command: &mut Command) {
+        command.process_group(0);
+        let (nice, cpu_secs) = (self.nice, self.cpu_secs);
+        if nice.is_none() && cpu_secs.is_none() {
+            return;
+        }
+        unsafe {
+            command.pre_exec(move || {
+                if let Some(nice) = nice {
+                    // Only lowering priority is allowed without privileges
+                    libc::setpriority(libc::PRIO_PROCESS, 0, nice.clamp(0, 19));
+                }
+                if let Some(secs) = cpu_secs {
+                    let limit = libc::rlimit {
+                        rlim_cur: secs as libc::rlim_t,
+                        rlim_max: (secs + 1) as libc::rlim_t,
+                    };
+                    if libc::setrlimit(libc::RLIMIT_CPU, &limit) < 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+                Ok(())
+            });
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn apply(&self, _command: &mut Command) {}
+
+    /// Run `work` (reading the command's output) under the timeout. On timeout the process
+    /// group is killed and `work` gets `DRAIN_AFTER_KILL` to read what was already written;
+    /// whatever it collected by then is what the caller has. Without process groups, a
+    /// cancel of `execution` also arrives here to kill `child`. Returns whether the command
+    /// timed out.
+    pub async fn enforce_timeout(
+        &self,
+        child: &mut Child,
+        execution: Option<&Execution>,
+        work: impl Future<Output = ()>,
+    ) -> bool {
+        let timeout = async {
+            match self.timeout_secs {
+                Some(secs) => tokio::time::sleep(Duration::from_secs(secs)).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::pin!(work);
+        let timed_out = tokio::select! {
+            () = &mut work => return false,
+            _ = timeout => true,
+            _ = killed(execution) => false,
+        };
+        if timed_out {
+            println!("[Tauri] Command timed out after {}s, killing it", self.timeout_secs.unwrap_or_default());
+        }
+        kill(child);
+        if tokio::time::timeout(DRAIN_AFTER_KILL, work).await.is_err() {
+            println!("[Tauri] Output still open {}s after the kill, giving up on it", DRAIN_AFTER_KILL.as_secs());
+        }
+        timed_out
+    }
+}
+
+/// Read everything from `reader` into `kept`, keeping at most `cap` bytes; `truncated` is
+/// set if anything was dropped. Reading continues past the cap so the command never blocks
+/// on a full pipe. Both stay valid if reading is cut short.
+pub async fn read_capped<R: AsyncRead + Unpin>(
+    mut reader: R,
+    cap: Option<usize>,
+    kept: &mut Vec<u8>,
+    truncated: &mut bool,
+) {
+    let cap = cap.unwrap_or(usize::MAX);
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        let room = cap.saturating_sub(kept.len());
+        kept.extend_from_slice(&buf[..n.min(room)]);
+        *truncated |= n > room;
+    }
+}
+
+/// Resolves when `cancel` wants the command killed here rather than by signal
+#[cfg(unix)]
+async fn killed(_execution: Option<&Execution>) {
+    std::future::pending().await
+}
+
+#[cfg(not(unix))]
+async fn killed(execution: Option<&Execution>) {
+    match execution {
+        Some(execution) => execution.kill.notified().await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(unix)]
+fn kill(child: &Child) {
+    if let Some(pid) = child.id() {
+        signal(pid as i32, libc::SIGKILL);
+    }
+}
+
+#[cfg(not(unix))]
+fn kill(child: &mut Child) {
+    let _ = child.start_kill();
+}
+
+#[cfg(unix)]
+fn signal(pgid: i32, signal: i32) {
+    // Negative pid: the whole process group, so children of `sh -c` go too
+    unsafe {
+        libc::kill(-pgid, signal);
+    }
+}
@@ -1,9 +1,20 @@
+mod clipboard;
+mod execution;
+mod llm;
 mod overlay;
+mod privileged;
+mod pty;
+mod secrets;
+mod system;
 
+use desktop_waifu_shell::options::ShellOptions;
+use desktop_waifu_shell::{policy, sandbox, session};
+use execution::ExecutionLimits;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::process::Stdio;
-use tauri::Emitter;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use tauri::{Emitter, Manager};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
 use tokio::process::Command;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -11,102 +22,132 @@ pub struct CommandOutput {
     pub stdout: String,
     pub stderr: String,
     pub exit_code: i32,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SystemInfo {
-    pub os: String,
-    pub arch: String,
-    pub distro: Option<String>,
-    pub shell: Option<String>,
-    pub package_manager: Option<String>,
+    /// Killed for exceeding `timeoutSecs`
+    #[serde(default)]
+    pub timed_out: bool,
+    /// Output beyond `maxOutputBytes` was dropped
+    #[serde(default)]
+    pub truncated: bool,
 }
 
 /// Gets system information for context in LLM prompts
 #[tauri::command]
-async fn get_system_info() -> Result<SystemInfo, String> {
-    let os = std::env::consts::OS.to_string();
-    let arch = std::env::consts::ARCH.to_string();
-
-    let mut info = SystemInfo {
-        os: os.clone(),
-        arch,
-        distro: None,
-        shell: None,
-        package_manager: None,
-    };
-
-    // Get shell from environment
-    info.shell = std::env::var("SHELL").ok();
-
-    // Linux-specific info
-    if os == "linux" {
-        // Try to get distro from /etc/os-release
-        if let Ok(output) = Command::new("sh")
-            .arg("-c")
-            .arg("cat /etc/os-release 2>/dev/null | grep -E '^(NAME|ID)=' | head -2")
-            .output()
-            .await
-        {
-            let content = String::from_utf8_lossy(&output.stdout);
-            for line in content.lines() {
-                if line.starts_with("NAME=") {
-                    info.distro = Some(line.trim_start_matches("NAME=").trim_matches('"').to_string());
-                }
-            }
-        }
-
-        // Detect package manager
-        let pkg_managers = [
-            ("apt", "apt"),
-            ("dnf", "dnf"),
-            ("yum", "yum"),
-            ("pacman", "pacman"),
-            ("zypper", "zypper"),
-            ("apk", "apk"),
-            ("nix-env", "nix"),
-        ];
-
-        for (cmd, name) in pkg_managers {
-            if let Ok(output) = Command::new("which").arg(cmd).output().await {
-                if output.status.success() {
-                    info.package_manager = Some(name.to_string());
-                    break;
-                }
-            }
-        }
-    } else if os == "macos" {
-        info.distro = Some("macOS".to_string());
-        // Check for homebrew
-        if let Ok(output) = Command::new("which").arg("brew").output().await {
-            if output.status.success() {
-                info.package_manager = Some("homebrew".to_string());
-            }
-        }
-    }
+async fn get_system_info(cache: tauri::State<'_, system::SystemInfoCache>) -> Result<system::SystemInfo, String> {
+    Ok(cache.get())
+}
 
-    Ok(info)
+#[derive(Debug, Clone, Serialize)]
+struct CommandChunk {
+    id: String,
+    data: String,
 }
 
 /// Executes a shell command and returns the output.
+/// With `pty`, the command runs on a pseudo-terminal for interactive programs: output is
+/// also streamed as `command-output` events, `write_command_input` types into it, and
+/// stdout holds the whole terminal transcript. `id` names the command for both.
+/// `limits` bounds run time, output size and CPU use of non-PTY commands.
+/// `confirmed` says the user approved the command, for commands the policy makes ask.
+/// With `stdin` (and an `id`), the command's stdin stays open for `write_command_stdin`.
+/// `shell`, `cwd` and `env` override the login shell, home directory and environment.
 #[tauri::command]
-async fn execute_command(cmd: String) -> Result<CommandOutput, String> {
+#[allow(clippy::too_many_arguments)]
+async fn execute_command(
+    window: tauri::Window,
+    cmd: String,
+    id: Option<String>,
+    pty: Option<bool>,
+    limits: Option<ExecutionLimits>,
+    confirmed: Option<bool>,
+    stdin: Option<bool>,
+    shell: Option<String>,
+    cwd: Option<String>,
+    env: Option<HashMap<String, String>>,
+) -> Result<CommandOutput, String> {
     println!("[Tauri] execute_command called with: {}", cmd);
+    let policy = check_policy(&window, &cmd, confirmed)?;
+    let options = ShellOptions {
+        shell,
+        cwd,
+        env: env.unwrap_or_default(),
+    };
+    let shell_command = sandbox::apply(&policy, &cmd, options.command(&cmd))?;
 
-    let output = Command::new("sh")
-        .arg("-c")
-        .arg(&cmd)
-        .output()
+    if pty.unwrap_or(false) {
+        let id = id.ok_or("Interactive commands need an id")?;
+        return tauri::async_runtime::spawn_blocking(move || {
+            // Hold back a multi-byte character split across reads
+            let mut pending = Vec::new();
+            let (transcript, exit_code) = pty::run(&id, shell_command, |bytes| {
+                pending.extend_from_slice(bytes);
+                let complete = match std::str::from_utf8(&pending) {
+                    Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                    _ => pending.len(),
+                };
+                let chunk: Vec<u8> = pending.drain(..complete).collect();
+                let _ = window.emit("command-output", CommandChunk {
+                    id: id.clone(),
+                    data: String::from_utf8_lossy(&chunk).to_string(),
+                });
+            })?;
+            println!("[Tauri] Interactive command completed with exit code: {}", exit_code);
+            Ok(CommandOutput {
+                stdout: String::from_utf8_lossy(&transcript).to_string(),
+                stderr: String::new(),
+                exit_code,
+                timed_out: false,
+                truncated: false,
+            })
+        })
         .await
-        .map_err(|e| {
-            eprintln!("[Tauri] Command execution failed: {}", e);
-            format!("Failed to execute command: {}", e)
-        })?;
+        .map_err(|e| format!("Command task failed: {}", e))?;
+    }
+
+    let stdin = stdin.unwrap_or(false);
+    if stdin && id.is_none() {
+        return Err("Commands with stdin need an id".into());
+    }
+    let limits = limits.unwrap_or_default();
+    let mut command = Command::from(shell_command);
+    command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .stdin(if stdin { Stdio::piped() } else { Stdio::null() });
+    limits.apply(&mut command);
+    let mut child = command.spawn().map_err(|e| {
+        eprintln!("[Tauri] Command execution failed: {}", e);
+        format!("Failed to execute command: {}", e)
+    })?;
+
+    let pid = child.id().unwrap_or_default();
+    // With an id the command can be cancelled and, with stdin, written to
+    let registration = match id {
+        Some(id) => Some(register_with_stdin(id, pid, &mut child).await?),
+        None => None,
+    };
+    let stdout = child.stdout.take().ok_or("Failed to capture stdout")?;
+    let stderr = child.stderr.take().ok_or("Failed to capture stderr")?;
+    let (mut stdout_bytes, mut stdout_truncated) = (Vec::new(), false);
+    let (mut stderr_bytes, mut stderr_truncated) = (Vec::new(), false);
+    let read_both = async {
+        tokio::join!(
+            execution::read_capped(stdout, limits.max_output_bytes, &mut stdout_bytes, &mut stdout_truncated),
+            execution::read_capped(stderr, limits.max_output_bytes, &mut stderr_bytes, &mut stderr_truncated),
+        );
+    };
+    let execution = registration.as_ref().map(execution::Registration::execution);
+    let timed_out = limits.enforce_timeout(&mut child, execution, read_both).await;
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| format!("Failed to wait for command: {}", e))?;
 
     let result = CommandOutput {
-        stdout: String::from_utf8_lossy(&output.stdout).to_string(),
-        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
-        exit_code: output.status.code().unwrap_or(-1),
+        stdout: String::from_utf8_lossy(&stdout_bytes).to_string(),
+        stderr: String::from_utf8_lossy(&stderr_bytes).to_string(),
+        exit_code: status.code().unwrap_or(-1),
+        timed_out,
+        truncated: stdout_truncated || stderr_truncated,
     };
 
     println!("[Tauri] Command completed with exit code: {}", result.exit_code);
@@ -116,41 +157,58 @@ async fn execute_command(cmd: String) -> Result<CommandOutput, String> {
 }
 
 /// Executes a shell command and streams output line by line via Tauri events.
+/// `id` identifies the command for `cancel_command`; `limits` and `confirmed` as for
+/// `execute_command`. Lines past `maxOutputBytes` are neither kept nor emitted.
+/// With `stdin`, the command's stdin stays open for `write_command_stdin`.
+/// `shell`, `cwd` and `env` as for `execute_command`.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn execute_command_stream(
     window: tauri::Window,
+    id: String,
     cmd: String,
+    limits: Option<ExecutionLimits>,
+    confirmed: Option<bool>,
+    stdin: Option<bool>,
+    shell: Option<String>,
+    cwd: Option<String>,
+    env: Option<HashMap<String, String>>,
 ) -> Result<CommandOutput, String> {
-    let mut child = Command::new("sh")
-        .arg("-c")
-        .arg(&cmd)
+    let policy = check_policy(&window, &cmd, confirmed)?;
+    let options = ShellOptions {
+        shell,
+        cwd,
+        env: env.unwrap_or_default(),
+    };
+    let limits = limits.unwrap_or_default();
+    let mut command = Command::from(sandbox::apply(&policy, &cmd, options.command(&cmd))?);
+    command
         .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
+        .stderr(Stdio::piped());
+    if stdin.unwrap_or(false) {
+        command.stdin(Stdio::piped());
+    }
+    // Own process group, so cancelling reaches everything `sh -c` starts
+    limits.apply(&mut command);
+    let mut child = command
         .spawn()
         .map_err(|e| format!("Failed to spawn command: {}", e))?;
+    let pid = child.id().ok_or("Command exited before it could be tracked")?;
+    let registration = register_with_stdin(id.clone(), pid, &mut child).await?;
 
     let stdout = child.stdout.take().ok_or("Failed to capture stdout")?;
     let stderr = child.stderr.take().ok_or("Failed to capture stderr")?;
 
-    let mut stdout_reader = BufReader::new(stdout).lines();
-    let mut stderr_reader = BufReader::new(stderr).lines();
-
-    let mut full_stdout = String::new();
-    let mut full_stderr = String::new();
-
-    // Read stdout lines and emit events
-    while let Ok(Some(line)) = stdout_reader.next_line().await {
-        full_stdout.push_str(&line);
-        full_stdout.push('\n');
-        let _ = window.emit("command-stdout", &line);
-    }
-
-    // Read stderr lines and emit events
-    while let Ok(Some(line)) = stderr_reader.next_line().await {
-        full_stderr.push_str(&line);
-        full_stderr.push('\n');
-        let _ = window.emit("command-stderr", &line);
-    }
+    // Read both pipes at once so a command filling stderr can't stall on it
+    let (mut full_stdout, mut stdout_truncated) = (String::new(), false);
+    let (mut full_stderr, mut stderr_truncated) = (String::new(), false);
+    let read_both = async {
+        tokio::join!(
+            stream_lines(&window, stdout, "command-stdout", limits.max_output_bytes, &mut full_stdout, &mut stdout_truncated),
+            stream_lines(&window, stderr, "command-stderr", limits.max_output_bytes, &mut full_stderr, &mut stderr_truncated),
+        );
+    };
+    let timed_out = limits.enforce_timeout(&mut child, Some(registration.execution()), read_both).await;
 
     let status = child
         .wait()
@@ -158,15 +216,250 @@ async fn execute_command_stream(
         .map_err(|e| format!("Failed to wait for command: {}", e))?;
 
     let exit_code = status.code().unwrap_or(-1);
+    if registration.execution().was_cancelled() {
+        let _ = window.emit("command-cancelled", &id);
+    }
     let _ = window.emit("command-complete", exit_code);
 
     Ok(CommandOutput {
         stdout: full_stdout,
         stderr: full_stderr,
         exit_code,
+        timed_out,
+        truncated: stdout_truncated || stderr_truncated,
     })
 }
 
+/// Track a spawned command under `id`, handing its stdin (if piped) to the registry
+async fn register_with_stdin(
+    id: String,
+    pid: u32,
+    child: &mut tokio::process::Child,
+) -> Result<execution::Registration, String> {
+    let registration = execution::register(id, pid)?;
+    if let Some(stdin) = child.stdin.take() {
+        registration.attach_stdin(stdin).await;
+    }
+    Ok(registration)
+}
+
+/// The command policy from the app data directory
+fn load_policy(window: &tauri::Window) -> Result<policy::Policy, String> {
+    let data_dir = window
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("No app data directory: {}", e))?;
+    policy::load(&data_dir.join("policy.toml"), data_dir.join("scratch"))
+}
+
+/// Refuse `cmd` unless the command policy lets it run; returns the policy, which also
+/// decides how `cmd` is sandboxed
+fn check_policy(window: &tauri::Window, cmd: &str, confirmed: Option<bool>) -> Result<policy::Policy, String> {
+    let policy = load_policy(window)?;
+    policy
+        .check(cmd, confirmed.unwrap_or(false))
+        .inspect_err(|e| println!("[Tauri] {}: {}", e, cmd))?;
+    Ok(policy)
+}
+
+/// Emit each line of `reader` as `event` and collect them in `full`, up to `cap` bytes;
+/// `truncated` is set once lines are dropped. Keeps draining past the cap.
+async fn stream_lines<R: AsyncRead + Unpin>(
+    window: &tauri::Window,
+    reader: R,
+    event: &str,
+    cap: Option<usize>,
+    full: &mut String,
+    truncated: &mut bool,
+) {
+    let cap = cap.unwrap_or(usize::MAX);
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if *truncated || full.len() + line.len() + 1 > cap {
+            *truncated = true;
+            continue;
+        }
+        full.push_str(&line);
+        full.push('\n');
+        let _ = window.emit(event, &line);
+    }
+}
+
+/// Stops a command started with `execute_command_stream`: SIGTERM, then SIGKILL if it
+/// hasn't exited after a grace period. `command-cancelled` is emitted once it has.
+#[tauri::command]
+async fn cancel_command(id: String) -> Result<(), String> {
+    if execution::cancel(&id) {
+        Ok(())
+    } else {
+        Err(format!("No running command with id '{}'", id))
+    }
+}
+
+/// Starts a persistent shell session: commands run with `session_exec` share its working
+/// directory and environment. With the sandbox on, the whole shell runs in it.
+#[tauri::command]
+async fn session_create(window: tauri::Window, id: String) -> Result<(), String> {
+    session::create(id, load_policy(&window)?.default_sandbox())
+}
+
+/// Runs a command as administrator; the system asks for the password in its own dialog.
+/// Always needs `confirmed`, whatever the policy says.
+#[tauri::command]
+async fn execute_privileged_command(
+    window: tauri::Window,
+    cmd: String,
+    confirmed: Option<bool>,
+) -> Result<privileged::PrivilegedOutput, String> {
+    if confirmed != Some(true) {
+        return Err("Privileged commands require confirmation".into());
+    }
+    check_policy(&window, &cmd, confirmed)?;
+    println!("[Tauri] execute_privileged_command: {}", cmd);
+    privileged::run(&cmd).await
+}
+
+/// Runs a command in a session and returns its output; `confirmed` as for `execute_command`
+#[tauri::command]
+async fn session_exec(
+    window: tauri::Window,
+    id: String,
+    cmd: String,
+    confirmed: Option<bool>,
+) -> Result<CommandOutput, String> {
+    check_policy(&window, &cmd, confirmed)?;
+    println!("[Tauri] session_exec in {}: {}", id, cmd);
+    let output = session::exec(&id, &cmd).await?;
+    Ok(CommandOutput {
+        stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        exit_code: output.exit_code,
+        timed_out: false,
+        truncated: false,
+    })
+}
+
+/// Ends a session's shell and everything it started
+#[tauri::command]
+async fn session_destroy(id: String) -> Result<(), String> {
+    if session::destroy(&id) {
+        Ok(())
+    } else {
+        Err(format!("No session with id '{}'", id))
+    }
+}
+
+/// Types `data` into a command started with `execute_command` in PTY mode
+#[tauri::command]
+async fn write_command_input(id: String, data: String) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || pty::write_input(&id, data.as_bytes()))
+        .await
+        .map_err(|e| format!("Input task failed: {}", e))?
+}
+
+/// Feeds `data` to the stdin of a command started with `stdin: true`; `eof` closes it
+#[tauri::command]
+async fn write_command_stdin(id: String, data: String, eof: Option<bool>) -> Result<(), String> {
+    execution::write_stdin(&id, data.as_bytes(), eof.unwrap_or(false)).await
+}
+
+/// CPU load, memory, disks, network traffic, battery and temperature. The first call
+/// waits for a second CPU sample.
+#[tauri::command]
+async fn get_system_metrics() -> Result<system::Metrics, String> {
+    tauri::async_runtime::spawn_blocking(system::metrics)
+        .await
+        .map_err(|e| format!("Metrics task failed: {}", e))
+}
+
+/// Text on the system clipboard, or `None` if it holds no text
+#[tauri::command]
+async fn read_clipboard() -> Result<Option<String>, String> {
+    clipboard::read_text().await
+}
+
+/// Puts `text` on the system clipboard
+#[tauri::command]
+async fn write_clipboard(text: String) -> Result<(), String> {
+    clipboard::write_text(&text).await
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LlmToken {
+    request_id: String,
+    text: String,
+}
+
+/// Runs a chat completion with the key held for its provider, emitting the reply as
+/// `llm-token` events as it streams in. Returns the whole reply, or `None` if
+/// `llm_cancel` stopped it.
+#[tauri::command]
+async fn llm_chat(
+    window: tauri::Window,
+    request_id: String,
+    request: llm::ChatRequest,
+) -> Result<Option<String>, String> {
+    println!("[Tauri] llm_chat {} ({} messages)", request.provider.name(), request.messages.len());
+    llm::chat(&request_id, &request, |text| {
+        let _ = window.emit("llm-token", LlmToken {
+            request_id: request_id.clone(),
+            text: text.to_string(),
+        });
+    })
+    .await
+}
+
+/// Stops a request started with `llm_chat`
+#[tauri::command]
+async fn llm_cancel(request_id: String) -> Result<(), String> {
+    if llm::cancel(&request_id) {
+        Ok(())
+    } else {
+        Err(format!("No chat request with id '{}'", request_id))
+    }
+}
+
+/// Finds Ollama or llama.cpp's server on this machine and lists its models
+#[tauri::command]
+async fn llm_list_models() -> Result<llm::LocalServer, String> {
+    llm::local_models().await
+}
+
+/// Sets the API key `llm_chat` uses for `provider`; an empty key clears it
+#[tauri::command]
+async fn llm_set_key(provider: llm::Provider, key: String) -> Result<(), String> {
+    llm::set_key(provider, &key);
+    Ok(())
+}
+
+/// Saves a secret (an API key) in the platform keyring
+#[tauri::command]
+async fn store_secret(name: String, value: String) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || secrets::store(&name, &value))
+        .await
+        .map_err(|e| format!("Keyring task failed: {}", e))?
+        .map_err(|e| e.to_string())
+}
+
+/// A secret from the platform keyring, or `None` if it isn't set
+#[tauri::command]
+async fn get_secret(name: String) -> Result<Option<String>, String> {
+    tauri::async_runtime::spawn_blocking(move || secrets::get(&name))
+        .await
+        .map_err(|e| format!("Keyring task failed: {}", e))?
+        .map_err(|e| e.to_string())
+}
+
+/// Removes a secret from the platform keyring
+#[tauri::command]
+async fn delete_secret(name: String) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || secrets::delete(&name))
+        .await
+        .map_err(|e| format!("Keyring task failed: {}", e))?
+        .map_err(|e| e.to_string())
+}
+
 // Keep the original greet command for compatibility
 #[tauri::command]
 fn greet(name: &str) -> String {
@@ -192,8 +485,67 @@ pub fn run() {
         return;
     }
 
-    // Not on Wayland - show error and exit
-    eprintln!("Error: Desktop Waifu requires Wayland.");
-    eprintln!("Supported: Sway, Hyprland, GNOME (Wayland), KDE Plasma (Wayland)");
+    // On X11, Windows and macOS the Tauri window itself is the overlay
+    if overlay::is_window_overlay() {
+        println!("[Tauri] No Wayland session, starting the overlay window...");
+        run_window();
+        return;
+    }
+
+    eprintln!("Error: Desktop Waifu needs a Wayland or X11 session.");
+    eprintln!("Supported: Sway, Hyprland, GNOME, KDE Plasma, and X11 window managers such as i3");
     std::process::exit(1);
 }
+
+/// Run the Tauri app with its window turned into an overlay (x11, windows and macos modules)
+fn run_window() {
+    tauri::Builder::default()
+        .plugin(tauri_plugin_opener::init())
+        .manage(overlay::OverlayState::default())
+        .manage(system::SystemInfoCache::default())
+        .setup(|app| {
+            let window = app
+                .get_webview_window("main")
+                .ok_or("The main window is missing")?;
+            // Keep the overlay out of the Dock and the app switcher
+            #[cfg(target_os = "macos")]
+            app.set_activation_policy(tauri::ActivationPolicy::Accessory);
+            // Without the hints the app still works, as an ordinary always-on-top window
+            if let Err(e) = overlay::setup_overlay_window(&window, &app.state::<overlay::OverlayState>()) {
+                eprintln!("[Tauri] Overlay window hints unavailable: {}", e);
+            }
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            greet,
+            get_system_info,
+            get_system_metrics,
+            execute_command,
+            execute_command_stream,
+            execute_privileged_command,
+            cancel_command,
+            session_create,
+            session_exec,
+            session_destroy,
+            write_command_input,
+            write_command_stdin,
+            read_clipboard,
+            write_clipboard,
+            llm_chat,
+            llm_cancel,
+            llm_list_models,
+            llm_set_key,
+            store_secret,
+            get_secret,
+            delete_secret,
+            overlay::check_wayland,
+            overlay::set_click_through,
+            overlay::set_input_region,
+            overlay::clear_input_region,
+            overlay::move_window,
+            overlay::resize_window,
+            overlay::get_screen_size,
+        ])
+        .run(tauri::generate_context!())
+        .expect("error while running the Tauri app");
+}
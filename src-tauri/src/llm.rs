@@ -0,0 +1,79 @@
+//! Chat completions for the assistant, made here so API keys stay out of the webview
+//!
+//! `llm_chat` streams the reply from OpenAI, OpenRouter, Anthropic or Gemini as
+//! `llm-token` events; `llm_cancel` stops it. Requests, streaming and retries are
+//! desktop-waifu-llm's. Keys are read from the keyring (`<provider>-api-key`, see
+//! secrets.rs); `llm_set_key` puts one in use ahead of it. The `local` provider needs
+//! none: it talks to Ollama or llama.cpp's server on this machine, whichever is running,
+//! and `local_models` lists the models it has.
+
+pub use desktop_waifu_llm::{ChatRequest, LocalServer, Provider};
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+use tokio::sync::Notify;
+
+/// Keys set by the frontend, by provider
+static KEYS: LazyLock<Mutex<HashMap<Provider, String>>> = LazyLock::new(Default::default);
+/// Requests in progress, by request id
+static ACTIVE: LazyLock<Mutex<HashMap<String, Arc<Notify>>>> = LazyLock::new(Default::default);
+
+/// Use `key` for `provider` from now on; an empty key clears it
+pub fn set_key(provider: Provider, key: &str) {
+    let mut keys = KEYS.lock().unwrap();
+    if key.trim().is_empty() {
+        keys.remove(&provider);
+    } else {
+        keys.insert(provider, key.trim().to_string());
+    }
+}
+
+async fn api_key(provider: Provider) -> Option<String> {
+    let set = KEYS.lock().unwrap().get(&provider).cloned();
+    if set.is_some() {
+        return set;
+    }
+    let lookup = tokio::task::spawn_blocking(move || crate::secrets::get(provider.secret_name()));
+    match lookup.await {
+        Ok(Ok(key)) => key.filter(|key| !key.is_empty()),
+        Ok(Err(e)) => {
+            eprintln!("[Tauri] Couldn't read the {} key from the keyring: {}", provider.name(), e);
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+/// Run a chat completion, passing the reply to `on_token` as it streams in. Returns the
+/// whole reply, or `None` if `cancel` stopped the request. `id` names the request.
+pub async fn chat(id: &str, request: &ChatRequest, on_token: impl FnMut(&str)) -> Result<Option<String>, String> {
+    let cancelled = Arc::new(Notify::new());
+    ACTIVE.lock().unwrap().insert(id.to_string(), cancelled.clone());
+
+    let local_servers = desktop_waifu_llm::local_servers(None);
+    let send = desktop_waifu_llm::chat(request, api_key(request.provider), &local_servers, on_token);
+    let result = tokio::select! {
+        result = send => result.map(|reply| Some(reply.text)).map_err(|e| e.to_string()),
+        _ = cancelled.notified() => Ok(None),
+    };
+    ACTIVE.lock().unwrap().remove(id);
+    result
+}
+
+/// Stop a request started with `chat`
+pub fn cancel(id: &str) -> bool {
+    match ACTIVE.lock().unwrap().remove(id) {
+        Some(cancelled) => {
+            // Stores a permit, so a request still connecting sees it too
+            cancelled.notify_one();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Find the local model server and list its models
+pub async fn local_models() -> Result<LocalServer, String> {
+    desktop_waifu_llm::local_models(&desktop_waifu_llm::local_servers(None))
+        .await
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,79 @@
+//! Overlay window on macOS
+//!
+//! The Tauri window (transparent, undecorated, always on top) is the overlay, shown on
+//! every Space and over full-screen apps. AppKit has no input shapes: a window either takes
+//! every click or ignores them all (`setIgnoresMouseEvents`). So a polling thread follows
+//! the cursor and ignores mouse events while it's outside the regions the frontend reported
+//! (`set_input_region`), which hands clicks there to the windows below.
+
+use super::InputRect;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::WebviewWindow;
+
+/// How often the cursor is checked against the input region
+const POLL_INTERVAL: Duration = Duration::from_millis(30);
+
+/// Make the main window an overlay: on top, on every Space, and visible over full-screen apps
+pub fn set_overlay_mode(window: &WebviewWindow) -> Result<(), String> {
+    window.set_always_on_top(true).map_err(|e| e.to_string())?;
+    window.set_visible_on_all_workspaces(true).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Where the window takes clicks, in CSS pixels; `None` for the whole window
+#[derive(Clone, Default)]
+pub struct ClickThrough {
+    region: Arc<Mutex<Option<Vec<InputRect>>>>,
+}
+
+impl ClickThrough {
+    pub fn set_region(&self, rects: Vec<InputRect>) {
+        *self.region.lock().unwrap() = Some(rects);
+    }
+
+    pub fn clear_region(&self) {
+        *self.region.lock().unwrap() = None;
+    }
+
+    /// Follow the cursor for as long as the window exists
+    pub fn start(&self, window: WebviewWindow) {
+        let region = self.region.clone();
+        std::thread::spawn(move || {
+            let mut ignoring = false;
+            loop {
+                std::thread::sleep(POLL_INTERVAL);
+                let inside = match cursor_in_region(&window, &region) {
+                    Ok(inside) => inside,
+                    // The window is gone
+                    Err(_) if window.is_visible().is_err() => break,
+                    Err(_) => continue,
+                };
+                let ignore = !inside;
+                if ignore == ignoring {
+                    continue;
+                }
+                if window.set_ignore_cursor_events(ignore).is_err() {
+                    break;
+                }
+                ignoring = ignore;
+            }
+        });
+    }
+}
+
+/// Whether the cursor is over part of the window that takes clicks
+fn cursor_in_region(window: &WebviewWindow, region: &Mutex<Option<Vec<InputRect>>>) -> tauri::Result<bool> {
+    let Some(rects) = region.lock().unwrap().clone() else {
+        return Ok(true);
+    };
+    // Both are in screen device pixels; the frontend measures in CSS pixels
+    let cursor = window.cursor_position()?;
+    let origin = window.inner_position()?;
+    let scale = window.scale_factor()?;
+    let x = (cursor.x - origin.x as f64) / scale;
+    let y = (cursor.y - origin.y as f64) / scale;
+    Ok(rects
+        .iter()
+        .any(|r| x >= r.x && x < r.x + r.width && y >= r.y && y < r.y + r.height))
+}
@@ -1,4 +1,10 @@
+#[cfg(target_os = "macos")]
+pub mod macos;
 mod wayland;
+#[cfg(target_os = "windows")]
+pub mod windows;
+#[cfg(target_os = "linux")]
+pub mod x11;
 
 /// Check if the current session is running on Wayland
 pub fn is_wayland() -> bool {
@@ -23,3 +29,163 @@ pub fn launch_overlay_and_exit() -> Result<(), String> {
 pub fn is_overlay_available() -> bool {
     wayland::is_overlay_available()
 }
+
+/// Check if the current session is running on X11, where the Tauri window is the overlay
+pub fn is_x11() -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        x11::is_x11()
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        false
+    }
+}
+
+/// Whether the Tauri window is the overlay: on X11, Windows and macOS, which have no
+/// layer-shell overlay binary
+pub fn is_window_overlay() -> bool {
+    cfg!(any(target_os = "windows", target_os = "macos")) || is_x11()
+}
+
+/// The main window's platform side, once `setup_overlay_window` has set it up
+#[derive(Default)]
+pub struct OverlayState {
+    #[cfg(target_os = "linux")]
+    overlay: std::sync::Mutex<Option<x11::X11Overlay>>,
+    #[cfg(target_os = "macos")]
+    click_through: macos::ClickThrough,
+}
+
+/// Make the main window an overlay: on top of other windows and out of the taskbar. On X11
+/// and macOS it's also on every desktop and clickable only where the frontend says (see
+/// `set_input_region`); on Windows it's placed on its monitor.
+pub fn setup_overlay_window(window: &tauri::WebviewWindow, state: &OverlayState) -> Result<(), String> {
+    #[cfg(target_os = "linux")]
+    {
+        let overlay = x11::X11Overlay::attach(window)?;
+        overlay.set_sticky()?;
+        overlay.set_skip_taskbar()?;
+        *state.overlay.lock().unwrap() = Some(overlay);
+        Ok(())
+    }
+    #[cfg(target_os = "windows")]
+    {
+        let _ = state;
+        windows::setup(window)
+    }
+    #[cfg(target_os = "macos")]
+    {
+        macos::set_overlay_mode(window)?;
+        state.click_through.start(window.clone());
+        Ok(())
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+    {
+        let _ = (window, state);
+        Err("Window overlays are only supported on X11, Windows and macOS".to_string())
+    }
+}
+
+/// Rectangle of the window that takes input, in CSS pixels
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+pub struct InputRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Let clicks outside `rects` through to the windows below (X11 and macOS; on Windows a
+/// no-op, and the whole window takes input)
+#[tauri::command]
+pub fn set_input_region(window: tauri::WebviewWindow, state: tauri::State<OverlayState>, rects: Vec<InputRect>) -> Result<(), String> {
+    #[cfg(target_os = "linux")]
+    if let Some(overlay) = state.overlay.lock().unwrap().as_ref() {
+        // The frontend measures in CSS pixels, X in device pixels
+        let scale = window.scale_factor().map_err(|e| e.to_string())?;
+        let rects: Vec<x11::InputRect> = rects
+            .iter()
+            .map(|r| x11::InputRect {
+                x: (r.x * scale).floor() as i32,
+                y: (r.y * scale).floor() as i32,
+                width: (r.width * scale).ceil().max(0.0) as u32,
+                height: (r.height * scale).ceil().max(0.0) as u32,
+            })
+            .collect();
+        return overlay.set_input_region(&rects);
+    }
+    #[cfg(target_os = "macos")]
+    {
+        // Checked against the cursor in CSS pixels, see macos.rs
+        let _ = window;
+        state.click_through.set_region(rects);
+    }
+    #[cfg(not(target_os = "macos"))]
+    let _ = (window, state, rects);
+    Ok(())
+}
+
+/// Take input on the whole window again (X11 and macOS; elsewhere a no-op)
+#[tauri::command]
+pub fn clear_input_region(state: tauri::State<OverlayState>) -> Result<(), String> {
+    #[cfg(target_os = "linux")]
+    if let Some(overlay) = state.overlay.lock().unwrap().as_ref() {
+        return overlay.clear_input_region();
+    }
+    #[cfg(target_os = "macos")]
+    state.click_through.clear_region();
+    let _ = state;
+    Ok(())
+}
+
+/// Let every click through to the windows below (`true`), or take them again
+#[tauri::command]
+pub fn set_click_through(window: tauri::WebviewWindow, ignore: bool) -> Result<(), String> {
+    window.set_ignore_cursor_events(ignore).map_err(|e| e.to_string())
+}
+
+/// Move the window to `x`, `y` on the desktop, in CSS pixels
+#[tauri::command]
+pub fn move_window(window: tauri::WebviewWindow, x: f64, y: f64) -> Result<(), String> {
+    window
+        .set_position(tauri::LogicalPosition::new(x, y))
+        .map_err(|e| e.to_string())
+}
+
+/// Resize the window, in CSS pixels
+#[tauri::command]
+pub fn resize_window(window: tauri::WebviewWindow, width: f64, height: f64) -> Result<(), String> {
+    window
+        .set_size(tauri::LogicalSize::new(width, height))
+        .map_err(|e| e.to_string())
+}
+
+/// Size of the monitor the window is on
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScreenSize {
+    /// In CSS pixels
+    pub width: f64,
+    pub height: f64,
+    pub scale_factor: f64,
+}
+
+/// Size of the monitor the window is on (the primary one if it's off screen)
+#[tauri::command]
+pub fn get_screen_size(window: tauri::WebviewWindow) -> Result<ScreenSize, String> {
+    let monitor = match window.current_monitor().map_err(|e| e.to_string())? {
+        Some(monitor) => monitor,
+        None => window
+            .primary_monitor()
+            .map_err(|e| e.to_string())?
+            .ok_or("No monitor found")?,
+    };
+    let scale_factor = monitor.scale_factor();
+    let size = monitor.size().to_logical::<f64>(scale_factor);
+    Ok(ScreenSize {
+        width: size.width,
+        height: size.height,
+        scale_factor,
+    })
+}
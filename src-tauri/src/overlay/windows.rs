@@ -0,0 +1,53 @@
+//! Overlay window on Windows
+//!
+//! There's no layer shell here either, so the Tauri window (transparent, undecorated and
+//! always on top, see tauri.conf.json) is the overlay. It's kept out of the taskbar and
+//! placed in the bottom-right corner of the monitor it opens on, in that monitor's work
+//! area so the taskbar doesn't cover it. Tauri makes the process per-monitor DPI aware, so
+//! the size is converted to the monitor's scale factor when placing the window, and Windows
+//! rescales it when it's dragged to a monitor with another one.
+
+use tauri::{PhysicalPosition, PhysicalSize, WebviewWindow};
+
+/// Gap between the window and the edges of the work area, in logical pixels
+const MARGIN: f64 = 16.0;
+
+/// Make the main window an overlay: on top, out of the taskbar, in the corner of its monitor
+pub fn setup(window: &WebviewWindow) -> Result<(), String> {
+    window.set_always_on_top(true).map_err(|e| e.to_string())?;
+    window.set_skip_taskbar(true).map_err(|e| e.to_string())?;
+    place_on_monitor(window)
+}
+
+/// Move the window to the bottom-right corner of the monitor it's on (the primary one if
+/// it's off screen), sized for that monitor's scale factor
+pub fn place_on_monitor(window: &WebviewWindow) -> Result<(), String> {
+    let monitor = match window.current_monitor().map_err(|e| e.to_string())? {
+        Some(monitor) => monitor,
+        None => window
+            .primary_monitor()
+            .map_err(|e| e.to_string())?
+            .ok_or("No monitor found")?,
+    };
+
+    // The window's size is in device pixels for the scale it was created at, which isn't
+    // necessarily the monitor's
+    let logical = window
+        .outer_size()
+        .map_err(|e| e.to_string())?
+        .to_logical::<f64>(window.scale_factor().map_err(|e| e.to_string())?);
+    let scale = monitor.scale_factor();
+    let area = monitor.work_area();
+    let width = ((logical.width * scale).round() as u32).min(area.size.width);
+    let height = ((logical.height * scale).round() as u32).min(area.size.height);
+    let margin = (MARGIN * scale).round() as i32;
+
+    let x = (area.position.x + area.size.width as i32 - width as i32 - margin).max(area.position.x);
+    let y = (area.position.y + area.size.height as i32 - height as i32 - margin).max(area.position.y);
+    window
+        .set_size(PhysicalSize::new(width, height))
+        .map_err(|e| e.to_string())?;
+    window
+        .set_position(PhysicalPosition::new(x, y))
+        .map_err(|e| e.to_string())
+}
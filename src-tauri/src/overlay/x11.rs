@@ -0,0 +1,161 @@
+//! Overlay window hints on X11
+//!
+//! Wayland sessions get the GTK layer-shell overlay; on X11 the Tauri window itself becomes
+//! the overlay. It covers the screen, so clicks outside the character and the chat panel
+//! have to fall through to the windows below: the XShape extension's input shape limits
+//! where the window takes input (`set_input_region`), and an empty shape list hands every
+//! click through. EWMH hints keep it on every desktop and out of the taskbar and pager.
+
+use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+use x11rb::connection::Connection;
+use x11rb::protocol::shape::{self, ConnectionExt as _, SK, SO};
+use x11rb::protocol::xproto::{
+    AtomEnum, ClientMessageEvent, ClipOrdering, ConnectionExt as _, EventMask, PropMode, Rectangle, Window,
+};
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+
+/// `_NET_WM_STATE` client message actions
+const NET_WM_STATE_ADD: u32 = 1;
+/// Source indication for requests from normal applications
+const SOURCE_APPLICATION: u32 = 1;
+/// `_NET_WM_DESKTOP` value for "all desktops"
+const ALL_DESKTOPS: u32 = 0xFFFF_FFFF;
+
+x11rb::atom_manager! {
+    Atoms: AtomsCookie {
+        _NET_WM_STATE,
+        _NET_WM_STATE_STICKY,
+        _NET_WM_STATE_SKIP_TASKBAR,
+        _NET_WM_STATE_SKIP_PAGER,
+        _NET_WM_STATE_ABOVE,
+        _NET_WM_DESKTOP,
+    }
+}
+
+/// Check if the current session is running on X11 (and not under Wayland)
+pub fn is_x11() -> bool {
+    let session_x11 = std::env::var("XDG_SESSION_TYPE").map(|v| v == "x11").unwrap_or(false);
+    (session_x11 || std::env::var("DISPLAY").is_ok()) && !super::is_wayland()
+}
+
+/// A rectangle of the window that takes input, in window coordinates (device pixels)
+#[derive(Debug, Clone, Copy)]
+pub struct InputRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The Tauri window's X11 side, on a connection of our own
+pub struct X11Overlay {
+    conn: RustConnection,
+    root: Window,
+    window: Window,
+    atoms: Atoms,
+}
+
+impl X11Overlay {
+    /// Attach to `window`, which must be an X11 window, and check the server has XShape
+    pub fn attach(window: &impl HasWindowHandle) -> Result<Self, String> {
+        let handle = window.window_handle().map_err(|e| format!("No window handle: {}", e))?;
+        let window = match handle.as_raw() {
+            RawWindowHandle::Xlib(h) => h.window as Window,
+            RawWindowHandle::Xcb(h) => h.window.get(),
+            other => return Err(format!("Not an X11 window: {:?}", other)),
+        };
+
+        let (conn, screen) = x11rb::connect(None).map_err(|e| format!("Can't connect to the X server: {}", e))?;
+        if conn
+            .extension_information(shape::X11_EXTENSION_NAME)
+            .map_err(|e| e.to_string())?
+            .is_none()
+        {
+            return Err("The X server has no XShape extension".to_string());
+        }
+        let root = conn.setup().roots[screen].root;
+        let atoms = Atoms::new(&conn)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| e.to_string())?;
+        Ok(Self { conn, root, window, atoms })
+    }
+
+    /// Take input only inside `rects`; everywhere else clicks reach the windows below.
+    /// No rects makes the whole window click-through.
+    pub fn set_input_region(&self, rects: &[InputRect]) -> Result<(), String> {
+        let rectangles: Vec<Rectangle> = rects
+            .iter()
+            .map(|r| Rectangle {
+                x: clamp_i16(r.x),
+                y: clamp_i16(r.y),
+                width: r.width.min(u16::MAX.into()) as u16,
+                height: r.height.min(u16::MAX.into()) as u16,
+            })
+            .collect();
+        self.conn
+            .shape_rectangles(SO::SET, SK::INPUT, ClipOrdering::UNSORTED, self.window, 0, 0, &rectangles)
+            .map_err(|e| e.to_string())?;
+        self.flush()
+    }
+
+    /// Take input on the whole window again
+    pub fn clear_input_region(&self) -> Result<(), String> {
+        // A mask of None restores the default shape, the window's bounds
+        self.conn
+            .shape_mask(SO::SET, SK::INPUT, self.window, 0, 0, x11rb::NONE)
+            .map_err(|e| e.to_string())?;
+        self.flush()
+    }
+
+    /// Show the window on every desktop
+    pub fn set_sticky(&self) -> Result<(), String> {
+        self.add_states(self.atoms._NET_WM_STATE_STICKY, self.atoms._NET_WM_STATE_ABOVE)?;
+        // Window managers that ignore the state follow _NET_WM_DESKTOP instead
+        self.send_client_message(self.atoms._NET_WM_DESKTOP, [ALL_DESKTOPS, SOURCE_APPLICATION, 0, 0, 0])?;
+        self.conn
+            .change_property32(
+                PropMode::REPLACE,
+                self.window,
+                self.atoms._NET_WM_DESKTOP,
+                AtomEnum::CARDINAL,
+                &[ALL_DESKTOPS],
+            )
+            .map_err(|e| e.to_string())?;
+        self.flush()
+    }
+
+    /// Keep the window out of the taskbar and the pager
+    pub fn set_skip_taskbar(&self) -> Result<(), String> {
+        self.add_states(self.atoms._NET_WM_STATE_SKIP_TASKBAR, self.atoms._NET_WM_STATE_SKIP_PAGER)?;
+        self.flush()
+    }
+
+    /// Ask the window manager to add two `_NET_WM_STATE` atoms (the window is mapped, so
+    /// setting the property directly would be ignored)
+    fn add_states(&self, first: u32, second: u32) -> Result<(), String> {
+        self.send_client_message(self.atoms._NET_WM_STATE, [NET_WM_STATE_ADD, first, second, SOURCE_APPLICATION, 0])
+    }
+
+    fn send_client_message(&self, message_type: u32, data: [u32; 5]) -> Result<(), String> {
+        let event = ClientMessageEvent::new(32, self.window, message_type, data);
+        self.conn
+            .send_event(
+                false,
+                self.root,
+                EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+                event,
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<(), String> {
+        self.conn.flush().map_err(|e| e.to_string())
+    }
+}
+
+fn clamp_i16(value: i32) -> i16 {
+    value.clamp(i16::MIN.into(), i16::MAX.into()) as i16
+}
@@ -0,0 +1,95 @@
+//! Running commands as administrator without a password prompt in the chat
+//!
+//! On Linux through pkexec, whose polkit agent asks for the password in its own dialog
+//! (with the packaged helper and policy from packaging/polkit when they're installed); on
+//! macOS through `osascript`'s `with administrator privileges`, which shows the system's
+//! authorization dialog. Windows has no way to elevate one command and keep its output.
+
+use serde::Serialize;
+use tokio::process::Command;
+
+#[cfg(target_os = "linux")]
+const HELPER: &str = "/usr/lib/desktop-waifu/desktop-waifu-privileged";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// The command ran as root; see the exit code
+    Completed,
+    /// The user dismissed the authorization dialog
+    Cancelled,
+    /// Wrong password, or no polkit agent to ask
+    NotAuthorized,
+    /// pkexec isn't installed
+    Unavailable,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PrivilegedOutput {
+    pub command: String,
+    pub outcome: Outcome,
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: i32,
+}
+
+#[cfg(target_os = "linux")]
+fn elevated(cmd: &str) -> Result<Command, String> {
+    let mut command = Command::new("pkexec");
+    if std::path::Path::new(HELPER).exists() {
+        command.arg(HELPER);
+    }
+    command.args(["/bin/sh", "-c", cmd]);
+    Ok(command)
+}
+
+#[cfg(target_os = "macos")]
+fn elevated(cmd: &str) -> Result<Command, String> {
+    let escaped = cmd.replace('\\', "\\\\").replace('"', "\\\"");
+    let mut command = Command::new("osascript");
+    command
+        .arg("-e")
+        .arg(format!("do shell script \"{}\" with administrator privileges", escaped));
+    Ok(command)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn elevated(_cmd: &str) -> Result<Command, String> {
+    Err("Running commands as administrator isn't supported on this platform".to_string())
+}
+
+fn outcome(exit_code: i32, stderr: &str) -> Outcome {
+    match exit_code {
+        // pkexec: dialog dismissed, or not authorized / no agent
+        126 if cfg!(target_os = "linux") => Outcome::Cancelled,
+        127 if cfg!(target_os = "linux") => Outcome::NotAuthorized,
+        // osascript reports "User canceled. (-128)"
+        _ if cfg!(target_os = "macos") && stderr.contains("(-128)") => Outcome::Cancelled,
+        _ => Outcome::Completed,
+    }
+}
+
+/// Run `cmd` with `sh -c` as administrator once the user has authorized it
+pub async fn run(cmd: &str) -> Result<PrivilegedOutput, String> {
+    let output = match elevated(cmd)?.stdin(std::process::Stdio::null()).output().await {
+        Ok(output) => output,
+        Err(e) => {
+            return Ok(PrivilegedOutput {
+                command: cmd.to_string(),
+                outcome: Outcome::Unavailable,
+                stdout: String::new(),
+                stderr: format!("Failed to run privileged command: {}", e),
+                exit_code: -1,
+            });
+        }
+    };
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    let exit_code = output.status.code().unwrap_or(-1);
+    Ok(PrivilegedOutput {
+        command: cmd.to_string(),
+        outcome: outcome(exit_code, &stderr),
+        stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+        stderr,
+        exit_code,
+    })
+}
@@ -0,0 +1,69 @@
+//! Commands attached to a pseudo-terminal, for interactive programs
+//!
+//! `sudo`, `ssh` and anything that checks `isatty` misbehave under `sh -c` with piped
+//! stdio. `run` gives the command a PTY as its controlling terminal and reports what it
+//! prints as it goes; `write_input` types into it while it runs. The PTY itself is
+//! desktop-waifu-shell's and Unix-only; elsewhere interactive commands are refused.
+
+use std::process::Command;
+#[cfg(unix)]
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::Write,
+    sync::{Arc, LazyLock, Mutex},
+};
+
+#[cfg(unix)]
+use crate::execution;
+#[cfg(unix)]
+use desktop_waifu_shell::pty;
+
+/// Terminal masters of running PTY commands, by command id
+#[cfg(unix)]
+static INPUTS: LazyLock<Mutex<HashMap<String, Arc<File>>>> = LazyLock::new(Default::default);
+
+/// Run `command` on a new PTY until it exits, calling `on_output` with everything it prints.
+/// Blocks; returns the full transcript and the exit code. The command is registered under
+/// `id` for `write_input` and `execution::cancel`.
+#[cfg(unix)]
+pub fn run(id: &str, command: Command, mut on_output: impl FnMut(&[u8])) -> Result<(Vec<u8>, i32), String> {
+    // The PTY's session is also a process group, for cancel
+    let pty = pty::spawn(command).map_err(|e| format!("Failed to start command on a PTY: {}", e))?;
+
+    let _registration = execution::register(id.to_string(), pty.pid())?;
+    INPUTS.lock().unwrap().insert(id.to_string(), pty.input());
+
+    let mut transcript = Vec::new();
+    pty.read(|bytes| {
+        transcript.extend_from_slice(bytes);
+        on_output(bytes);
+        true
+    });
+    INPUTS.lock().unwrap().remove(id);
+
+    let exit_code = pty.wait().map_err(|e| format!("Failed to wait for command: {}", e))?;
+    Ok((transcript, exit_code))
+}
+
+#[cfg(not(unix))]
+pub fn run(_id: &str, _command: Command, _on_output: impl FnMut(&[u8])) -> Result<(Vec<u8>, i32), String> {
+    Err("Interactive commands aren't supported on this platform".to_string())
+}
+
+/// Type `data` into a running PTY command
+#[cfg(unix)]
+pub fn write_input(id: &str, data: &[u8]) -> Result<(), String> {
+    let master = INPUTS
+        .lock()
+        .unwrap()
+        .get(id)
+        .cloned()
+        .ok_or_else(|| format!("No interactive command with id '{}'", id))?;
+    (&*master).write_all(data).map_err(|e| format!("Failed to write to command: {}", e))
+}
+
+#[cfg(not(unix))]
+pub fn write_input(id: &str, _data: &[u8]) -> Result<(), String> {
+    Err(format!("No interactive command with id '{}'", id))
+}
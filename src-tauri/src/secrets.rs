@@ -0,0 +1,32 @@
+//! Secrets (API keys) in the platform keyring: the Secret Service on Linux, the Keychain on
+//! macOS and the Credential Manager on Windows, under the service name `desktop-waifu`
+//!
+//! The calls block (and may wait for the user to unlock the keyring): run them with
+//! `spawn_blocking`.
+
+const SERVICE: &str = "desktop-waifu";
+
+fn entry(name: &str) -> keyring::Result<keyring::Entry> {
+    keyring::Entry::new(SERVICE, name)
+}
+
+pub fn store(name: &str, value: &str) -> keyring::Result<()> {
+    entry(name)?.set_password(value)
+}
+
+/// The secret called `name`, or `None` if there is none
+pub fn get(name: &str) -> keyring::Result<Option<String>> {
+    match entry(name)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Remove the secret called `name`; removing one that doesn't exist is not an error
+pub fn delete(name: &str) -> keyring::Result<()> {
+    match entry(name)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
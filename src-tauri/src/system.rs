@@ -0,0 +1,238 @@
+//! What the assistant knows about the machine: OS and version, shell and package manager
+//! (`get_system_info`), and how it's doing: CPU load, memory, disks, network traffic,
+//! battery and temperature (`get_system_metrics`)
+//!
+//! Everything is probed in-process: `/etc/os-release` and PATH on Linux, sysinfo's
+//! registry and sysctl lookups for the Windows and macOS versions. Batteries are only read
+//! on Linux, from `/sys/class/power_supply`.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+use sysinfo::{Components, Disks, Networks, System};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemInfo {
+    pub os: String,
+    pub arch: String,
+    /// Distribution name on Linux, e.g. "Fedora Linux"; the OS version elsewhere
+    pub distro: Option<String>,
+    pub shell: Option<String>,
+    pub package_manager: Option<String>,
+}
+
+/// Managed state: the system is probed on the first `get_system_info` and the answer
+/// reused for every prompt after that
+#[derive(Default)]
+pub struct SystemInfoCache(OnceLock<SystemInfo>);
+
+impl SystemInfoCache {
+    pub fn get(&self) -> SystemInfo {
+        self.0.get_or_init(probe).clone()
+    }
+}
+
+/// Package managers to look for on PATH, as (program, name), in order of preference
+#[cfg(target_os = "linux")]
+const PACKAGE_MANAGERS: &[(&str, &str)] = &[
+    ("apt", "apt"),
+    ("dnf", "dnf"),
+    ("yum", "yum"),
+    ("pacman", "pacman"),
+    ("zypper", "zypper"),
+    ("apk", "apk"),
+    ("nix-env", "nix"),
+];
+#[cfg(target_os = "macos")]
+const PACKAGE_MANAGERS: &[(&str, &str)] = &[("brew", "homebrew"), ("port", "macports")];
+#[cfg(target_os = "windows")]
+const PACKAGE_MANAGERS: &[(&str, &str)] = &[("winget", "winget"), ("scoop", "scoop"), ("choco", "chocolatey")];
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+const PACKAGE_MANAGERS: &[(&str, &str)] = &[];
+
+fn probe() -> SystemInfo {
+    let os = std::env::consts::OS;
+    let distro = if os == "linux" {
+        os_release_name()
+    } else {
+        System::long_os_version()
+    };
+    let shell = std::env::var("SHELL")
+        .ok()
+        .or_else(|| cfg!(windows).then(|| std::env::var("COMSPEC").ok()).flatten());
+    let package_manager = PACKAGE_MANAGERS
+        .iter()
+        .find(|(program, _)| in_path(program))
+        .map(|(_, name)| name.to_string());
+
+    SystemInfo {
+        os: os.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        distro,
+        shell,
+        package_manager,
+    }
+}
+
+/// `NAME` from os-release(5)
+fn os_release_name() -> Option<String> {
+    let content = ["/etc/os-release", "/usr/lib/os-release"]
+        .iter()
+        .find_map(|path| std::fs::read_to_string(path).ok())?;
+    content.lines().find_map(|line| {
+        let value = line.strip_prefix("NAME=")?.trim();
+        let value = value.trim_matches(|c| c == '"' || c == '\'');
+        (!value.is_empty()).then(|| value.to_string())
+    })
+}
+
+/// Whether an executable `program` is in a PATH directory. Apps started from the macOS
+/// Finder get a minimal PATH, so Homebrew's prefixes are checked as well.
+fn in_path(program: &str) -> bool {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let extra = cfg!(target_os = "macos").then_some(["/opt/homebrew/bin", "/usr/local/bin"]);
+    std::env::split_paths(&path)
+        .chain(extra.into_iter().flatten().map(Into::into))
+        .any(|dir| is_executable(&dir.join(program)))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path).is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(windows)]
+fn is_executable(path: &Path) -> bool {
+    ["exe", "cmd", "bat"]
+        .iter()
+        .any(|ext| path.with_extension(ext).is_file())
+}
+
+/// Kept between calls: CPU load and network rates are measured since the previous sample
+struct Sampler {
+    system: System,
+    networks: Networks,
+    sampled: Instant,
+}
+
+static SAMPLER: Mutex<Option<Sampler>> = Mutex::new(None);
+
+/// The frontend's `SystemMetrics`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Metrics {
+    /// Average over all cores, 0-100
+    pub cpu_percent: f32,
+    pub memory_total_bytes: u64,
+    pub memory_used_bytes: u64,
+    pub swap_total_bytes: u64,
+    pub swap_used_bytes: u64,
+    pub disks: Vec<DiskUsage>,
+    /// Over all interfaces but loopback, since the previous sample
+    pub network_received_bytes_per_sec: u64,
+    pub network_transmitted_bytes_per_sec: u64,
+    pub battery: Option<Battery>,
+    /// Hottest sensor, in °C
+    pub temperature_celsius: Option<f32>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskUsage {
+    pub mount_point: String,
+    pub total_bytes: u64,
+    pub available_bytes: u64,
+    pub removable: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Battery {
+    pub percent: i64,
+    /// Running on battery power ("Charging", "Full" and "Not charging" are plugged in)
+    pub discharging: bool,
+}
+
+/// Current readings. Blocks for a moment on the first call, which needs two samples for
+/// CPU load and network rates; run it off the main thread.
+pub fn metrics() -> Metrics {
+    let mut sampler = SAMPLER.lock().unwrap_or_else(|e| e.into_inner());
+    let sampler = sampler.get_or_insert_with(|| {
+        let mut system = System::new();
+        system.refresh_cpu_usage();
+        let networks = Networks::new_with_refreshed_list();
+        let sampled = Instant::now();
+        std::thread::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
+        Sampler { system, networks, sampled }
+    });
+
+    sampler.system.refresh_cpu_usage();
+    sampler.system.refresh_memory();
+    sampler.networks.refresh(true);
+    let elapsed = sampler.sampled.elapsed().as_secs_f64().max(0.001);
+    sampler.sampled = Instant::now();
+
+    let (received, transmitted) = sampler
+        .networks
+        .iter()
+        .filter(|(name, _)| !matches!(name.as_str(), "lo" | "lo0"))
+        .fold((0, 0), |(rx, tx), (_, data)| (rx + data.received(), tx + data.transmitted()));
+
+    let disks = Disks::new_with_refreshed_list()
+        .iter()
+        .map(|disk| DiskUsage {
+            mount_point: disk.mount_point().to_string_lossy().into_owned(),
+            total_bytes: disk.total_space(),
+            available_bytes: disk.available_space(),
+            removable: disk.is_removable(),
+        })
+        .collect();
+
+    let temperature_celsius = Components::new_with_refreshed_list()
+        .iter()
+        .filter_map(|component| component.temperature())
+        .filter(|celsius| celsius.is_finite())
+        .reduce(f32::max);
+
+    Metrics {
+        cpu_percent: sampler.system.global_cpu_usage(),
+        memory_total_bytes: sampler.system.total_memory(),
+        memory_used_bytes: sampler.system.used_memory(),
+        swap_total_bytes: sampler.system.total_swap(),
+        swap_used_bytes: sampler.system.used_swap(),
+        disks,
+        network_received_bytes_per_sec: (received as f64 / elapsed) as u64,
+        network_transmitted_bytes_per_sec: (transmitted as f64 / elapsed) as u64,
+        battery: battery(),
+        temperature_celsius,
+    }
+}
+
+/// Charge of the first system battery (sysinfo doesn't read batteries)
+#[cfg(target_os = "linux")]
+fn battery() -> Option<Battery> {
+    let entries = std::fs::read_dir("/sys/class/power_supply").ok()?;
+    entries.flatten().find_map(|entry| {
+        let path = entry.path();
+        let kind = std::fs::read_to_string(path.join("type")).ok()?;
+        if kind.trim() != "Battery" {
+            return None;
+        }
+        // Peripherals (mice, headsets) report their batteries here too
+        if std::fs::read_to_string(path.join("scope")).is_ok_and(|scope| scope.trim() == "Device") {
+            return None;
+        }
+        let status = std::fs::read_to_string(path.join("status")).ok()?;
+        Some(Battery {
+            percent: std::fs::read_to_string(path.join("capacity")).ok()?.trim().parse().ok()?,
+            discharging: status.trim() == "Discharging",
+        })
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn battery() -> Option<Battery> {
+    None
+}
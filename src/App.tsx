@@ -3,8 +3,12 @@ import { CharacterCanvas } from './components/character';
 import { ChatPanel } from './components/chat';
 import { SettingsModal, TitleBar } from './components/ui';
 import { useAppStore } from './store';
-import { setHotkeyEnabled } from './lib/platform';
+import { setHotkeyEnabled, reportScale, reportChatSize, getOverlayConfig, setInputRegion as setWindowInputRegion, clearInputRegion, type CharacterSnap, type OverlayConfig, type OverlayLayout } from './lib/platform';
 import { debugLog } from './lib/debug';
+// Stretch reminders and remarks from the overlay's behavior scheduler
+import './lib/behavior';
+import { useSystemAppearance } from './hooks/useSystemAppearance';
+import { useNightLight } from './hooks/useNightLight';
 
 // Check if we're in overlay mode (desktop pet mode)
 // Window interface types are declared in src/lib/platform.ts
@@ -16,6 +20,7 @@ function requestKeyboardFocus() {
 }
 
 // Base window dimension constants (at scale 1.0)
+// Character and chat sizes are defaults; the overlay's config.toml can override them
 const BASE_WIDTH_COLLAPSED = 160;   // Character only
 const BASE_HEIGHT_COLLAPSED = 380;  // Character only
 const BASE_CANVAS_WIDTH = 240;      // Inner canvas width
@@ -34,16 +39,10 @@ function sendWindowControlMessage(message: { action: 'hide' | 'show' }) {
   window.webkit?.messageHandlers?.windowControl?.postMessage(message);
 }
 
-// Helper to set input region for click-through control
-function setInputRegion(mode: 'character' | 'full', characterBounds?: { x: number; y: number; width: number; height: number }) {
-  if (mode === 'character' && characterBounds) {
-    window.webkit?.messageHandlers?.setInputRegion?.postMessage({
-      mode: 'character',
-      ...characterBounds,
-    });
-  } else {
-    window.webkit?.messageHandlers?.setInputRegion?.postMessage({ mode: 'full' });
-  }
+// Helper to set input region for click-through control. In 'auto' mode the overlay keeps it
+// on the elements marked data-waifu-interactive as the layout changes; 'full' takes all input.
+function setInputRegion(mode: 'auto' | 'full') {
+  window.webkit?.messageHandlers?.setInputRegion?.postMessage({ mode });
 }
 
 // Double-click timing threshold in milliseconds
@@ -62,16 +61,44 @@ function OverlayMode() {
 
   // Character position (absolute screen coordinates from Rust)
   const [characterPos, setCharacterPos] = useState({ x: 0, y: 0 });
+  // Where Rust puts the chat panel for the dock mode (null until the first layoutChange)
+  const [chatRect, setChatRect] = useState<OverlayLayout['chat'] | null>(null);
+  // CSS transition while the character slides onto a screen edge after a drag
+  const [snapTransition, setSnapTransition] = useState<string | null>(null);
+
+  // Window sizes from the overlay's config.toml (the expanded window is chat + character)
+  const [baseSize, setBaseSize] = useState({
+    characterWidth: BASE_WIDTH_COLLAPSED,
+    characterHeight: BASE_HEIGHT_COLLAPSED,
+    chatWidth: BASE_CHAT_WIDTH,
+    chatHeight: BASE_CHAT_HEIGHT,
+  });
+  useEffect(() => {
+    const applyConfig = (config: OverlayConfig | null) => {
+      if (!config) return;
+      const { collapsedWidth, collapsedHeight, expandedWidth, expandedHeight } = config.window;
+      setBaseSize({
+        characterWidth: collapsedWidth,
+        characterHeight: collapsedHeight,
+        chatWidth: Math.max(0, expandedWidth - collapsedWidth),
+        chatHeight: expandedHeight,
+      });
+    };
+    getOverlayConfig().then(applyConfig).catch((e) => debugLog(`[CONFIG] ${e}`));
+    const handleConfigChanged = (e: Event) => applyConfig((e as CustomEvent<OverlayConfig>).detail);
+    window.addEventListener('configChanged', handleConfigChanged);
+    return () => window.removeEventListener('configChanged', handleConfigChanged);
+  }, []);
 
   // Scaled character dimensions
-  const scaledCharacterWidth = Math.round(BASE_WIDTH_COLLAPSED * characterScale);
-  const scaledCharacterHeight = Math.round(BASE_HEIGHT_COLLAPSED * characterScale);
+  const scaledCharacterWidth = Math.round(baseSize.characterWidth * characterScale);
+  const scaledCharacterHeight = Math.round(baseSize.characterHeight * characterScale);
   const scaledCanvasWidth = Math.round(BASE_CANVAS_WIDTH * characterScale);
   const scaledCanvasHeight = Math.round(BASE_CANVAS_HEIGHT * characterScale);
 
   // Scaled chat dimensions
-  const scaledChatWidth = Math.round(BASE_CHAT_WIDTH * chatScale);
-  const scaledChatHeight = Math.round(BASE_CHAT_HEIGHT * chatScale);
+  const scaledChatWidth = Math.round(baseSize.chatWidth * chatScale);
+  const scaledChatHeight = Math.round(baseSize.chatHeight * chatScale);
 
   // Drag state - track start position, not incremental deltas
   const isDragging = useRef(false);
@@ -157,94 +184,81 @@ function OverlayMode() {
     setHotkeyEnabled(hotkeyEnabled);
   }, [hotkeyEnabled]);
 
-  // Handle initial state from Rust (position + quadrant + screen dimensions)
+  // Layout from Rust: sent in answer to getQuadrant, while dragging, at the end of a drag
+  // and whenever the dock mode or the chat size changes
   useEffect(() => {
-    const handleInitialState = (e: Event) => {
-      const detail = (e as CustomEvent<{
-        x: number;
-        y: number;
-        isRightHalf: boolean;
-        isBottomHalf: boolean;
-        screenWidth: number;
-        screenHeight: number;
-      }>).detail;
-
-      setCharacterPos({ x: detail.x, y: detail.y });
-      setQuadrant(detail.isRightHalf, detail.isBottomHalf);
+    const handleLayoutChange = (e: Event) => {
+      const layout = (e as CustomEvent<OverlayLayout>).detail;
+      setCharacterPos({ x: layout.character.x, y: layout.character.y });
+      setChatRect(layout.chat);
+      const { isRightHalf, isBottomHalf } = useAppStore.getState().ui.quadrant;
+      if (isRightHalf !== layout.isRightHalf || isBottomHalf !== layout.isBottomHalf) {
+        setQuadrant(layout.isRightHalf, layout.isBottomHalf);
+      }
     };
 
-    window.addEventListener('initialState', handleInitialState);
-    return () => window.removeEventListener('initialState', handleInitialState);
+    window.addEventListener('layoutChange', handleLayoutChange);
+    return () => window.removeEventListener('layoutChange', handleLayoutChange);
   }, [setQuadrant]);
 
-  // Handle character position updates during drag
+  // A drag that ended near an edge snaps onto it; the layoutChange that follows moves the
+  // character there, eased over the snap's duration
   useEffect(() => {
-    const handleCharacterMove = (e: Event) => {
-      const detail = (e as CustomEvent<{ x: number; y: number }>).detail;
-      setCharacterPos({ x: detail.x, y: detail.y });
+    let timer: ReturnType<typeof setTimeout> | undefined;
+    const handleSnap = (e: Event) => {
+      const { durationMs } = (e as CustomEvent<CharacterSnap>).detail;
+      setSnapTransition(`left ${durationMs}ms ease-out, top ${durationMs}ms ease-out`);
+      clearTimeout(timer);
+      timer = setTimeout(() => setSnapTransition(null), durationMs);
     };
 
-    window.addEventListener('characterMove', handleCharacterMove);
-    return () => window.removeEventListener('characterMove', handleCharacterMove);
+    window.addEventListener('characterSnap', handleSnap);
+    return () => {
+      window.removeEventListener('characterSnap', handleSnap);
+      clearTimeout(timer);
+    };
   }, []);
 
-  // Handle quadrant changes from Rust (sent at end of drag)
+  // Rust places the chat, so it needs to know its size at the chat scale
   useEffect(() => {
-    const handleQuadrantChange = (e: Event) => {
-      const detail = (e as CustomEvent<{
-        isRightHalf: boolean;
-        isBottomHalf: boolean;
-      }>).detail;
+    reportChatSize(scaledChatWidth, scaledChatHeight);
+  }, [scaledChatWidth, scaledChatHeight]);
 
-      setQuadrant(detail.isRightHalf, detail.isBottomHalf);
+  // Keep the overlay's saved state in step with the scale setting
+  useEffect(() => {
+    reportScale(characterScale);
+  }, [characterScale]);
+
+  // Scale set through IPC, or restored from the overlay's saved state. Like the settings
+  // slider, a new scale reloads the page so the canvas is laid out again.
+  useEffect(() => {
+    const handleSetScale = (e: Event) => {
+      const { scale } = (e as CustomEvent<{ scale: number }>).detail;
+      const clamped = Math.min(2, Math.max(0.5, scale));
+      const current = useAppStore.getState().settings.characterScale;
+      if (Math.abs(clamped - current) < 0.01) return;
+      useAppStore.getState().updateSettings({ characterScale: clamped });
+      window.location.reload();
     };
 
-    window.addEventListener('quadrantChange', handleQuadrantChange);
-    return () => window.removeEventListener('quadrantChange', handleQuadrantChange);
-  }, [setQuadrant]);
+    window.addEventListener('setScale', handleSetScale);
+    return () => window.removeEventListener('setScale', handleSetScale);
+  }, []);
 
-  // Request initial state from Rust - only on mount
+  // Request initial state from Rust on mount, and again whenever monitors change
+  // (screen size and quadrant depend on the monitor hosting the character)
   useEffect(() => {
-    window.webkit?.messageHandlers?.getQuadrant?.postMessage({});
+    const requestState = () => window.webkit?.messageHandlers?.getQuadrant?.postMessage({});
+    requestState();
+    window.addEventListener('monitorsChanged', requestState);
+    return () => window.removeEventListener('monitorsChanged', requestState);
   }, []);
 
-  // Update input region for click-through when chat opens/closes, settings modal, or character moves
+  // Only the character and the chat panel take clicks (they're marked data-waifu-interactive),
+  // unless the settings modal is open
   useEffect(() => {
-    if (showSettings) {
-      // Settings modal is open: allow clicks everywhere
-      setInputRegion('full');
-    } else if (chatPanelOpen) {
-      // Chat is open: set input region to character + chat area
-      // Calculate chat bounds based on character position and quadrant
-      const chatX = quadrant.isRightHalf
-        ? characterPos.x - scaledChatWidth  // Chat to the left
-        : characterPos.x + scaledCharacterWidth;  // Chat to the right
-      const chatY = quadrant.isBottomHalf
-        ? characterPos.y + scaledCharacterHeight - scaledChatHeight  // Chat aligned to bottom
-        : characterPos.y;  // Chat aligned to top
-
-      // Combined bounds (character + chat)
-      const minX = Math.min(characterPos.x, chatX);
-      const minY = Math.min(characterPos.y, chatY);
-      const maxX = Math.max(characterPos.x + scaledCharacterWidth, chatX + scaledChatWidth);
-      const maxY = Math.max(characterPos.y + scaledCharacterHeight, chatY + scaledChatHeight);
-
-      setInputRegion('character', {
-        x: minX,
-        y: minY,
-        width: maxX - minX,
-        height: maxY - minY,
-      });
-    } else {
-      // Chat is closed: only character area should receive input
-      setInputRegion('character', {
-        x: characterPos.x,
-        y: characterPos.y,
-        width: scaledCharacterWidth,
-        height: scaledCharacterHeight,
-      });
-    }
-  }, [showSettings, chatPanelOpen, characterPos, scaledCharacterWidth, scaledCharacterHeight, scaledChatWidth, scaledChatHeight, quadrant]);
+    setInputRegion(showSettings ? 'full' : 'auto');
+  }, [showSettings]);
 
   // Trigger hide sequence: set hiding state, wait for animation, then tell Rust to hide
   const triggerHide = useCallback(() => {
@@ -375,14 +389,15 @@ function OverlayMode() {
   // Character hide animation direction based on which side of screen
   const characterHideTransform = quadrant.isRightHalf ? 'translateX(100%)' : 'translateX(-100%)';
 
-  // Calculate chat position relative to character's screen position
-  // Chat goes to the opposite side of the screen from the character
-  const chatLeft = quadrant.isRightHalf
-    ? characterPos.x - scaledChatWidth  // Chat to the left of character
-    : characterPos.x + scaledCharacterWidth;  // Chat to the right of character
-  const chatTop = quadrant.isBottomHalf
-    ? characterPos.y + scaledCharacterHeight - scaledChatHeight  // Align chat bottom to character bottom
-    : characterPos.y;  // Align chat top to character top
+  // Chat placement comes from Rust for the dock mode (see layout.rs in the overlay).
+  // Before the first layout it goes beside the character, on the side facing the middle
+  // of the screen.
+  const chat = chatRect ?? {
+    x: quadrant.isRightHalf ? characterPos.x - scaledChatWidth : characterPos.x + scaledCharacterWidth,
+    y: quadrant.isBottomHalf ? characterPos.y + scaledCharacterHeight - scaledChatHeight : characterPos.y,
+    width: scaledChatWidth,
+    height: scaledChatHeight,
+  };
 
   return (
     <div
@@ -392,13 +407,15 @@ function OverlayMode() {
       {/* Chat container - positioned relative to character's screen position */}
       {chatContainerVisible && (
         <div
-          className="absolute overflow-hidden bg-[#1a1a2e] transition-opacity duration-300 ease-out"
+          data-waifu-interactive
+          className="absolute overflow-hidden bg-[#1a1a2e] transition-opacity duration-300 ease-out night-dimmable"
           style={{
-            width: scaledChatWidth,
-            height: scaledChatHeight,
-            left: chatLeft,
-            top: chatTop,
+            width: chat.width,
+            height: chat.height,
+            left: chat.x,
+            top: chat.y,
             opacity: chatOpacity,
+            transition: snapTransition ? `opacity 300ms ease-out, ${snapTransition}` : undefined,
           }}
         >
           <ChatPanel onClose={() => setChatPanelOpen(false)} />
@@ -408,6 +425,7 @@ function OverlayMode() {
       {/* Character viewport - positioned via absolute screen coordinates */}
       <div
         ref={dragElementRef}
+        data-waifu-interactive
         className="absolute cursor-grab active:cursor-grabbing overflow-hidden"
         style={{
           width: scaledCharacterWidth,
@@ -417,7 +435,7 @@ function OverlayMode() {
           // Transform and opacity for hide animation
           transform: isHiding ? characterHideTransform : undefined,
           opacity: isHiding ? 0 : 1,
-          transition: isHiding ? 'transform 700ms ease-in, opacity 700ms ease-in' : undefined,
+          transition: isHiding ? 'transform 700ms ease-in, opacity 700ms ease-in' : snapTransition ?? undefined,
         }}
         onPointerDown={handlePointerDown}
         onPointerMove={handlePointerMove}
@@ -444,14 +462,51 @@ function OverlayMode() {
 }
 
 function App() {
+  // Follow the desktop's dark/light mode and accent color
+  useSystemAppearance();
+  // Dim the chat UI in the evening (see index.css)
+  useNightLight();
+
   // Overlay mode: character with sliding chat panel
   if (isOverlayMode) {
     return <OverlayMode />;
   }
 
-  // Normal mode: full app with chat
+  return <NormalMode />;
+}
+
+// Normal mode: full app with chat. On X11 the window is the overlay, so clicks on the
+// transparent margin go through to the windows below (unless the settings are open).
+function NormalMode() {
+  const showSettings = useAppStore((state) => state.settings.showSettings);
+  const containerRef = useRef<HTMLDivElement>(null);
+
+  useEffect(() => {
+    const container = containerRef.current;
+    if (!container) return;
+    if (showSettings) {
+      clearInputRegion();
+      return;
+    }
+    const updateRegion = () => {
+      const style = getComputedStyle(container);
+      const left = parseFloat(style.paddingLeft);
+      const top = parseFloat(style.paddingTop);
+      setWindowInputRegion(
+        left,
+        top,
+        container.clientWidth - left - parseFloat(style.paddingRight),
+        container.clientHeight - top - parseFloat(style.paddingBottom),
+      );
+    };
+    updateRegion();
+    const observer = new ResizeObserver(updateRegion);
+    observer.observe(container);
+    return () => observer.disconnect();
+  }, [showSettings]);
+
   return (
-    <div className="w-screen h-screen p-[5%]">
+    <div ref={containerRef} className="w-screen h-screen p-[5%]">
       <TitleBar />
 
       <div className="w-full h-full flex flex-row">
@@ -461,7 +516,7 @@ function App() {
         </div>
 
         {/* Chat - 50% width */}
-        <div className="w-1/2 h-full bg-[#1a1a2e]">
+        <div className="w-1/2 h-full bg-[#1a1a2e] night-dimmable">
           <ChatPanel />
         </div>
       </div>
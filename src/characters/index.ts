@@ -1,4 +1,5 @@
 import type { CharacterConfig } from '../types';
+import type { UserModel } from '../lib/platform';
 
 export interface Character {
   config: CharacterConfig;
@@ -45,3 +46,29 @@ export const getCharacter = (id: string): Character | undefined => {
 };
 
 export const defaultCharacterId = 'emily';
+
+// Models imported into the overlay's models folder are characters with ids like "user:mymodel"
+export const USER_CHARACTER_PREFIX = 'user:';
+
+export function userCharacterId(model: UserModel): string {
+  return `${USER_CHARACTER_PREFIX}${model.id}`;
+}
+
+export function createUserCharacter(model: UserModel): Character {
+  return createCharacter(userCharacterId(model), model.name, {
+    path: model.url,
+    // VRM 1.0 models face the camera; 0.x ones need turning around like the built-ins
+    rotation: model.vrmVersion === 1 ? [0, 0, 0] : [0, Math.PI, 0],
+  });
+}
+
+/**
+ * A built-in character, or an imported model from `userModels`
+ */
+export function findCharacter(id: string, userModels: UserModel[]): Character | undefined {
+  if (id.startsWith(USER_CHARACTER_PREFIX)) {
+    const model = userModels.find((m) => userCharacterId(m) === id);
+    return model ? createUserCharacter(model) : undefined;
+  }
+  return characters[id];
+}
@@ -1,10 +1,18 @@
-import { Suspense } from 'react';
+import { Suspense, useEffect, useMemo } from 'react';
+import { Color } from 'three';
 import { Canvas } from '@react-three/fiber';
 import { OrbitControls } from '@react-three/drei';
 import { EffectComposer, Outline } from '@react-three/postprocessing';
 import { CharacterModel } from './CharacterModel';
-import { characters } from '../../characters';
+import { characters, createUserCharacter, defaultCharacterId, findCharacter } from '../../characters';
 import { useAppStore } from '../../store';
+import { useNightLight } from '../../hooks/useNightLight';
+import { isOverlayMode, reportCharacter } from '../../lib/platform';
+
+// Light color at the warmest night light temperature we tint towards
+const WARM_LIGHT = new Color('#ffb46b');
+const WARMEST_TEMPERATURE = 2500;
+const NEUTRAL_TEMPERATURE = 6500;
 
 function LoadingFallback() {
   return (
@@ -21,9 +29,31 @@ interface CharacterCanvasProps {
 
 export function CharacterCanvas({ disableControls = false }: CharacterCanvasProps) {
   const selectedCharacter = useAppStore((state) => state.settings.selectedCharacter);
-  const character = characters[selectedCharacter];
+  const userModels = useAppStore((state) => state.character.userModels);
+  // An imported model can't be found until the overlay has listed them; after that, one
+  // that's gone falls back to the default character
+  const character = findCharacter(selectedCharacter, userModels ?? [])
+    ?? (userModels === null ? undefined : characters[defaultCharacterId]);
+  const nightLight = useNightLight();
+
+  useEffect(() => {
+    const choices = [...Object.values(characters), ...(userModels ?? []).map(createUserCharacter)]
+      .map(({ config }) => ({ id: config.id, name: config.name }));
+    reportCharacter(selectedCharacter, choices);
+  }, [selectedCharacter, userModels]);
+
+  // Night light dims the scene by the configured amount and warms the light color
+  const brightness = 1 - nightLight.dim;
+  const lightColor = useMemo(() => {
+    const warmth = nightLight.active
+      ? Math.min(Math.max((NEUTRAL_TEMPERATURE - nightLight.temperature) / (NEUTRAL_TEMPERATURE - WARMEST_TEMPERATURE), 0), 1)
+      : 0;
+    return new Color('#ffffff').lerp(WARM_LIGHT, warmth);
+  }, [nightLight.active, nightLight.temperature]);
 
   if (!character) {
+    // Still waiting for the list of imported models
+    if (isOverlayMode) return null;
     return <div className="flex-1 flex items-center justify-center text-white">Character not found</div>;
   }
 
@@ -56,10 +86,11 @@ export function CharacterCanvas({ disableControls = false }: CharacterCanvasProp
 
 
         {/* Lighting for Toon Shading */}
-        <ambientLight intensity={0.8} />
+        <ambientLight intensity={0.8 * brightness} color={lightColor} />
         <directionalLight
           position={[5, 10, 5]}
-          intensity={2.5}
+          intensity={2.5 * brightness}
+          color={lightColor}
           castShadow
           shadow-mapSize={[2048, 2048]}
         />
@@ -74,7 +105,7 @@ export function CharacterCanvas({ disableControls = false }: CharacterCanvasProp
               edgeStrength={100}
               width={1000}
             />
-            <CharacterModel key={selectedCharacter} config={character.config} />
+            <CharacterModel key={character.config.id} config={character.config} />
           </EffectComposer>
         </Suspense>
 
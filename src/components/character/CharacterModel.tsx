@@ -2,12 +2,46 @@ import { useEffect, useRef, useState, useMemo, useCallback } from 'react';
 import { useFrame, useLoader } from '@react-three/fiber';
 import { GLTFLoader, type GLTFParser } from 'three/addons/loaders/GLTFLoader.js';
 import { VRMLoaderPlugin, VRMUtils, VRM } from '@pixiv/three-vrm';
-import { VRMAnimationLoaderPlugin, createVRMAnimationClip } from '@pixiv/three-vrm-animation';
+import { VRMAnimationLoaderPlugin, createVRMAnimationClip, type VRMAnimation } from '@pixiv/three-vrm-animation';
 import * as THREE from 'three';
 import type { CharacterConfig } from '../../types';
 import { useAppStore } from '../../store';
+import { VISEMES, mouthShape } from '../../lib/speech';
+import { reportOverlayEvent } from '../../lib/platform';
+
+type AnimationState = 'idle' | 'listening' | 'thinking' | 'talking' | 'running';
+
+// A clip the overlay asked for (PlayAnimation); see animations.rs
+interface PlayAnimationDetail {
+  animation: string;
+  url: string;
+  fade: number;    // Seconds to cross-fade in, and back out when done
+  repeat: number;  // 0 loops until something replaces it
+  speed: number;
+}
 
-type AnimationState = 'idle' | 'listening' | 'thinking' | 'running';
+// VRM preset expressions `setExpression` can show; "neutral" is none of them
+const EMOTIONS = ['happy', 'angry', 'sad', 'relaxed', 'surprised'] as const;
+
+const clipLoader = new GLTFLoader();
+clipLoader.register((parser: GLTFParser) => new VRMAnimationLoaderPlugin(parser));
+// Requested clips by URL, loaded once whichever character plays them
+const loadedClips = new Map<string, Promise<VRMAnimation>>();
+
+function loadClip(url: string): Promise<VRMAnimation> {
+  let clip = loadedClips.get(url);
+  if (!clip) {
+    clip = clipLoader.loadAsync(url).then((animGltf) => {
+      const vrmAnimation = animGltf.userData.vrmAnimations?.[0] as VRMAnimation | undefined;
+      if (!vrmAnimation) throw new Error(`${url} has no VRM animation`);
+      return vrmAnimation;
+    });
+    // Try again next time
+    clip.catch(() => loadedClips.delete(url));
+    loadedClips.set(url, clip);
+  }
+  return clip;
+}
 
 interface CharacterModelProps {
   config: CharacterConfig;
@@ -24,13 +58,18 @@ export function CharacterModel({ config }: CharacterModelProps) {
   const isThinking = useAppStore((state) => state.chat.isThinking);
   const isHiding = useAppStore((state) => state.character.isHiding);
   const isRightHalf = useAppStore((state) => state.ui.quadrant.isRightHalf);
+  const walking = useAppStore((state) => state.character.walking);
+  const isTalking = useAppStore((state) => state.character.isTalking);
+  const requestedState = useAppStore((state) => state.character.requestedState);
+  const currentExpression = useAppStore((state) => state.character.currentExpression);
 
   const animationState: AnimationState = useMemo(() => {
-    if (isHiding) return 'running';
-    if (isThinking) return 'thinking';
-    if (isUserTyping) return 'listening';
+    if (isHiding || walking) return 'running';
+    if (isThinking || requestedState === 'thinking') return 'thinking';
+    if (requestedState === 'talking') return 'talking';
+    if (isUserTyping || requestedState === 'listening') return 'listening';
     return 'idle';
-  }, [isHiding, isThinking, isUserTyping]);
+  }, [isHiding, walking, isThinking, isUserTyping, requestedState]);
 
   // Load VRM model
   const gltf = useLoader(GLTFLoader, config.model.path, (loader) => {
@@ -53,10 +92,17 @@ export function CharacterModel({ config }: CharacterModelProps) {
   const standingPoseAnimGltf = useLoader(GLTFLoader, '/animations/Female Standing Pose.vrma', (loader) => {
     loader.register((parser: GLTFParser) => new VRMAnimationLoaderPlugin(parser));
   });
+  const talkingAnimGltf = useLoader(GLTFLoader, '/animations/talking.vrma', (loader) => {
+    loader.register((parser: GLTFParser) => new VRMAnimationLoaderPlugin(parser));
+  });
 
   // Store animation actions
   const actionsRef = useRef<{ [key: string]: THREE.AnimationAction }>({});
   const activeActionRef = useRef<THREE.AnimationAction | null>(null);
+  // Animation for the current state, which a requested clip fades back to
+  const stateAnimationRef = useRef('idle');
+  // Requested clip while it plays
+  const clipRef = useRef<{ name: string; action: THREE.AnimationAction; fade: number } | null>(null);
 
   // Idle cycling
   const idleVariants = useMemo(() => ['idle'], []);
@@ -69,7 +115,7 @@ export function CharacterModel({ config }: CharacterModelProps) {
   const targetRotationYRef = useRef<number>(Math.PI);
 
   // Helper function to transition to a new animation
-  const transitionToAnimation = useCallback((animName: string) => {
+  const transitionToAnimation = useCallback((animName: string, fade = 0.3) => {
     if (!mixerRef.current || !actionsRef.current[animName]) return;
 
     const newAction = actionsRef.current[animName];
@@ -77,13 +123,21 @@ export function CharacterModel({ config }: CharacterModelProps) {
 
     if (newAction !== oldAction) {
       if (oldAction) {
-        oldAction.fadeOut(0.3);
+        oldAction.fadeOut(fade);
       }
-      newAction.reset().setEffectiveTimeScale(1).setEffectiveWeight(1).fadeIn(0.3).play();
+      newAction.reset().setEffectiveTimeScale(1).setEffectiveWeight(1).fadeIn(fade).play();
       activeActionRef.current = newAction;
     }
   }, []);
 
+  // The requested clip is done (finished, replaced or interrupted): tell the overlay
+  const endClip = useCallback(() => {
+    const clip = clipRef.current;
+    if (!clip) return;
+    clipRef.current = null;
+    reportOverlayEvent({ type: 'AnimationComplete', data: clip.name });
+  }, []);
+
   // Idle cycling timer
   useEffect(() => {
     if (!modelLoaded || animationState !== 'idle') return;
@@ -105,7 +159,7 @@ export function CharacterModel({ config }: CharacterModelProps) {
   }, [modelLoaded, animationState, idleVariants, idleTrigger]);
 
   useEffect(() => {
-    if (!gltf || !groupRef.current || !idleAnimGltf || !thinkingAnimGltf || !runningAnimGltf || !armStretchAnimGltf || !standingPoseAnimGltf) return;
+    if (!gltf || !groupRef.current || !idleAnimGltf || !thinkingAnimGltf || !runningAnimGltf || !armStretchAnimGltf || !standingPoseAnimGltf || !talkingAnimGltf) return;
 
     // --- VRM Setup ---
     VRMUtils.removeUnnecessaryJoints(gltf.scene); // Clean up bones
@@ -145,6 +199,7 @@ export function CharacterModel({ config }: CharacterModelProps) {
       idle_stretch: armStretchAnimGltf,
       idle_pose: standingPoseAnimGltf,
       thinking: thinkingAnimGltf,
+      talking: talkingAnimGltf,
       running: runningAnimGltf,
     };
 
@@ -162,17 +217,29 @@ export function CharacterModel({ config }: CharacterModelProps) {
       activeActionRef.current.play();
     }
 
+    // A requested clip ran its repetitions: back to the state's animation
+    const mixer = mixerRef.current;
+    const onFinished = ({ action }: { action: THREE.AnimationAction }) => {
+      const clip = clipRef.current;
+      if (!clip || action !== clip.action) return;
+      endClip();
+      transitionToAnimation(stateAnimationRef.current, clip.fade);
+    };
+    mixer.addEventListener('finished', onFinished);
+
     setModelLoaded(true);
     setCharacterLoaded(true);
 
     const modelPath = config.model.path;
     return () => {
+      mixer.removeEventListener('finished', onFinished);
+      endClip();
       mixerRef.current?.stopAllAction();
       VRMUtils.deepDispose(vrm.scene); // Dispose resources
       // Clear the useLoader cache to prevent stale rotation/state on next load
       useLoader.clear(GLTFLoader, modelPath);
     };
-  }, [gltf, idleAnimGltf, thinkingAnimGltf, runningAnimGltf, armStretchAnimGltf, standingPoseAnimGltf, config, setCharacterLoaded]);
+  }, [gltf, idleAnimGltf, thinkingAnimGltf, runningAnimGltf, armStretchAnimGltf, standingPoseAnimGltf, talkingAnimGltf, config, setCharacterLoaded, endClip, transitionToAnimation]);
 
   // Handle animation state changes
   useEffect(() => {
@@ -187,7 +254,7 @@ export function CharacterModel({ config }: CharacterModelProps) {
       // Listening uses the current idle animation
       animName = currentIdleRef.current;
     } else {
-      // 'thinking' or 'running' use their specific animations
+      // 'thinking', 'talking' or 'running' use their specific animations
       animName = animationState;
     }
 
@@ -196,38 +263,104 @@ export function CharacterModel({ config }: CharacterModelProps) {
       animName = 'idle';
       if (!actionsRef.current[animName]) return;
     }
+    stateAnimationRef.current = animName;
 
+    // A requested clip plays on through idle and listening; other states interrupt it
+    if (clipRef.current) {
+      if (animationState === 'idle' || animationState === 'listening') return;
+      endClip();
+    }
     transitionToAnimation(animName);
-  }, [animationState, modelLoaded, idleTrigger, transitionToAnimation]);
+  }, [animationState, modelLoaded, idleTrigger, transitionToAnimation, endClip]);
+
+  // Clips requested through the overlay (IPC, scripts, the tray)
+  useEffect(() => {
+    if (!modelLoaded) return;
+
+    const handlePlayAnimation = async (e: Event) => {
+      const { animation, url, fade, repeat, speed } = (e as CustomEvent<PlayAnimationDetail>).detail;
+      let vrmAnimation: VRMAnimation;
+      try {
+        vrmAnimation = await loadClip(url);
+      } catch (error) {
+        console.error(`Failed to load animation ${animation}:`, error);
+        reportOverlayEvent({ type: 'Error', data: `Couldn't load animation '${animation}'` });
+        return;
+      }
+      const vrm = vrmRef.current;
+      const mixer = mixerRef.current;
+      if (!vrm || !mixer) return;
+
+      const key = `clip:${url}`;
+      actionsRef.current[key] ??= mixer.clipAction(createVRMAnimationClip(vrmAnimation, vrm));
+      const action = actionsRef.current[key];
+
+      endClip();
+      const oldAction = activeActionRef.current;
+      if (oldAction && oldAction !== action) {
+        oldAction.fadeOut(fade);
+      }
+      action.reset();
+      action.setLoop(THREE.LoopRepeat, repeat === 0 ? Infinity : repeat);
+      action.clampWhenFinished = true;
+      action.setEffectiveTimeScale(speed).setEffectiveWeight(1).fadeIn(fade).play();
+      activeActionRef.current = action;
+      clipRef.current = { name: animation, action, fade };
+    };
+
+    window.addEventListener('playAnimation', handlePlayAnimation);
+    return () => window.removeEventListener('playAnimation', handlePlayAnimation);
+  }, [modelLoaded, endClip]);
 
   // Handle model rotation target when running
   useEffect(() => {
     if (animationState === 'running') {
-      // Rotate to face the edge of the screen the character is running towards
-      // Direction depends on the character's base rotation
+      // Rotate to face the edge of the screen the character is running towards, or the
+      // way it's walking. Direction depends on the character's base rotation
+      const runningRight = walking ? walking === 'right' : isRightHalf;
       const baseRotation = originalRotationYRef.current;
       if (baseRotation === 0) {
         // jessica, sam, victoria - base rotation 0 (facing camera from opposite direction)
         // right half → run right → face right: +90° from 0
         // left half → run left → face left: -90° from 0
-        targetRotationYRef.current = isRightHalf ? Math.PI / 2 : -Math.PI / 2;
+        targetRotationYRef.current = runningRight ? Math.PI / 2 : -Math.PI / 2;
       } else {
         // emily, grace, rose - base rotation Math.PI
         // right half → run right → face right: +90° from Math.PI = 270°
         // left half → run left → face left: -90° from Math.PI = 90°
-        targetRotationYRef.current = isRightHalf ? Math.PI * 1.5 : Math.PI / 2;
+        targetRotationYRef.current = runningRight ? Math.PI * 1.5 : Math.PI / 2;
       }
     } else {
       // Restore original rotation (facing camera)
       targetRotationYRef.current = originalRotationYRef.current;
     }
-  }, [animationState, isRightHalf]);
+  }, [animationState, isRightHalf, walking]);
 
   useFrame((_, delta) => {
     if (mixerRef.current) {
       mixerRef.current.update(delta); // Update animation mixer
     }
     if (vrmRef.current) {
+      // Lip-sync: ease each mouth shape towards the current viseme
+      const expressions = vrmRef.current.expressionManager;
+      if (expressions) {
+        const mouth = mouthShape(performance.now(), isTalking || requestedState === 'talking');
+        const easing = Math.min(1, delta * 20);
+        for (const viseme of VISEMES) {
+          const target = mouth?.viseme === viseme ? mouth.weight : 0;
+          const current = expressions.getValue(viseme) ?? 0;
+          expressions.setValue(viseme, THREE.MathUtils.lerp(current, target, easing));
+        }
+
+        // Blend towards the requested expression more slowly than the mouth moves
+        const expressionEasing = Math.min(1, delta * 5);
+        for (const emotion of EMOTIONS) {
+          const target = emotion === currentExpression ? 1 : 0;
+          const current = expressions.getValue(emotion) ?? 0;
+          expressions.setValue(emotion, THREE.MathUtils.lerp(current, target, expressionEasing));
+        }
+      }
+
       vrmRef.current.update(delta); // Update VRM (expressions, look-at, etc.)
 
       // Smoothly interpolate rotation towards target
@@ -3,20 +3,28 @@ import { MessageList } from './MessageList';
 import { InputArea } from './InputArea';
 import { CommandApproval } from './CommandApproval';
 import { useAppStore } from '../../store';
-import { getProvider } from '../../lib/llm';
+import { getProvider, needsApiKey } from '../../lib/llm';
 import { buildSystemPrompt } from '../../lib/personalities';
-import { executeCommand as platformExecuteCommand, getSystemInfo, saveFile, showDesktopNotification, isWindowCurrentlyFocused } from '../../lib/platform';
+import { executeCommand as platformExecuteCommand, executePrivilegedCommand, getSystemInfo, saveFile, showDesktopNotification, playSound, isWindowCurrentlyFocused, announce, getLocaleInfo, getActiveWindow, fireScriptHook, getGitContextSummary } from '../../lib/platform';
 import { exportToJSON, exportToMarkdown } from '../../lib/export';
 import { debugLog } from '../../lib/debug';
+import { speakReply } from '../../lib/speech';
 import { isSlashCommand, executeSlashCommand } from '../../lib/commands';
-import { characters } from '../../characters';
+import { characters, userCharacterId } from '../../characters';
 import AnsiToHtml from 'ansi-to-html';
-import type { LLMMessage, SystemInfo, ImageAttachment, LLMContentPart } from '../../types';
+import type { LLMMessage, CommandOutput, SystemInfo, LocaleInfo, DesktopContext, ImageAttachment, LLMContentPart } from '../../types';
 
 interface ChatPanelProps {
   onClose?: () => void; // Optional close handler for overlay mode
 }
 
+// The window the user is in as the message goes out; falls back to the last
+// desktopChanged event if the compositor doesn't answer
+async function currentDesktop(): Promise<DesktopContext | null> {
+  const desktop = await getActiveWindow().catch(() => undefined);
+  return desktop === undefined ? useAppStore.getState().ui.desktop : desktop;
+}
+
 // Debug: Log when this module loads
 debugLog('[CHATPANEL] Module loaded');
 
@@ -36,7 +44,16 @@ export function ChatPanel({ onClose }: ChatPanelProps) {
   const truncateMessagesAfter = useAppStore((state) => state.truncateMessagesAfter);
 
   // System info for command execution context
-  const [systemInfo, setSystemInfo] = useState<SystemInfo | null>(null);
+  const [detectedSystemInfo, setSystemInfo] = useState<SystemInfo | null>(null);
+  // Commands run in the shell from settings when one is set, so describe that one
+  const systemInfo = useMemo(
+    () => (detectedSystemInfo && settings.commandShell
+      ? { ...detectedSystemInfo, shell: settings.commandShell }
+      : detectedSystemInfo),
+    [detectedSystemInfo, settings.commandShell]
+  );
+  // Timezone and date/time conventions for the assistant's time references
+  const [localeInfo, setLocaleInfo] = useState<LocaleInfo | null>(null);
 
   // Export menu state
   const [showExportMenu, setShowExportMenu] = useState(false);
@@ -61,6 +78,9 @@ export function ChatPanel({ onClose }: ChatPanelProps) {
     getSystemInfo()
       .then(setSystemInfo)
       .catch((err) => console.error('Failed to get system info:', err));
+    getLocaleInfo()
+      .then(setLocaleInfo)
+      .catch((err) => console.error('Failed to get locale info:', err));
   }, []);
 
   // Close export menu when clicking outside
@@ -118,7 +138,30 @@ export function ChatPanel({ onClose }: ChatPanelProps) {
       const runCommand = async () => {
         try {
           debugLog(`[EXEC EFFECT] Running command: ${execution.generatedCommand}`);
-          const output = await platformExecuteCommand(execution.generatedCommand!);
+          // Only reached after approveCommand, so the user has confirmed it
+          const { commandShell, commandCwd } = useAppStore.getState().settings;
+          let output: CommandOutput;
+          if (execution.privileged) {
+            const privileged = await executePrivilegedCommand(execution.generatedCommand!, { confirmed: true });
+            if (privileged.outcome !== 'completed') {
+              const reason = {
+                cancelled: 'Authorization was cancelled, so the command did not run.',
+                not_authorized: 'Not authorized to run the command as administrator (is a polkit agent running?).',
+                timed_out: 'The command timed out.',
+                unavailable: 'Running commands as administrator needs pkexec (polkit), which is not installed.',
+              }[privileged.outcome];
+              addMessage({ role: 'assistant', content: `**${reason}**` });
+              clearExecution();
+              return;
+            }
+            output = privileged;
+          } else {
+            output = await platformExecuteCommand(execution.generatedCommand!, {
+              confirmed: true,
+              shell: commandShell || undefined,
+              cwd: commandCwd || undefined,
+            });
+          }
 
           // Convert ANSI codes to HTML for terminal-style colored output
           const stdoutHtml = ansiConverter.toHtml(output.stdout.trim());
@@ -166,19 +209,27 @@ export function ChatPanel({ onClose }: ChatPanelProps) {
       };
       runCommand();
     }
-  }, [execution.status, execution.generatedCommand, execution.approved, setExecutionStatus, clearExecution, addMessage, ansiConverter]);
+  }, [execution.status, execution.generatedCommand, execution.approved, execution.privileged, setExecutionStatus, clearExecution, addMessage, ansiConverter]);
 
   // Parse EXECUTE tag from LLM response
-  const parseExecuteTag = useCallback((response: string): { command: string; cleanResponse: string } | null => {
-    const executeMatch = response.match(/\[EXECUTE:\s*(.+?)\]/);
+  // [EXECUTE_PRIVILEGED: ...] runs as administrator; so does an [EXECUTE: sudo ...], minus
+  // the sudo, so the password goes to the system's dialog instead of a terminal prompt
+  const parseExecuteTag = useCallback((response: string): { command: string; cleanResponse: string; privileged: boolean } | null => {
+    const executeMatch = response.match(/\[EXECUTE(_PRIVILEGED)?:\s*(.+?)\]/);
     if (executeMatch) {
-      const command = executeMatch[1].trim();
+      let command = executeMatch[2].trim();
+      let privileged = executeMatch[1] !== undefined;
+      const sudo = command.match(/^sudo\s+(?:-\S+\s+)*(.+)$/);
+      if (sudo) {
+        command = sudo[1].trim();
+        privileged = true;
+      }
       // If command is empty, treat as parse failure
       if (!command) {
         return null;
       }
-      const cleanResponse = response.replace(/\[EXECUTE:\s*.+?\]/, '').trim();
-      return { command, cleanResponse };
+      const cleanResponse = response.replace(/\[EXECUTE(_PRIVILEGED)?:\s*.+?\]/, '').trim();
+      return { command, cleanResponse, privileged };
     }
     return null;
   }, []);
@@ -205,11 +256,12 @@ export function ChatPanel({ onClose }: ChatPanelProps) {
   }, []);
 
   const handleEditAndRetry = useCallback(async (messageId: string, newContent: string) => {
-    if (!settings.apiKey) return;
+    if (needsApiKey(settings.llmProvider) && !settings.apiKey) return;
 
     // Update the message content and truncate subsequent messages
     updateMessage(messageId, newContent);
     truncateMessagesAfter(messageId);
+    fireScriptHook('on_message', 'user', newContent);
 
     // Start thinking animation while waiting for LLM
     setThinking(true);
@@ -218,12 +270,16 @@ export function ChatPanel({ onClose }: ChatPanelProps) {
       const provider = getProvider(settings.llmProvider);
 
       // Build system prompt from personality settings with system info
+      const [desktop, gitSummary] = await Promise.all([
+        currentDesktop(),
+        getGitContextSummary().catch(() => ''),
+      ]);
       const systemPrompt = buildSystemPrompt({
         selectedPersonality: settings.selectedPersonality,
         detailLevel: settings.detailLevel,
         assistantSubject: settings.assistantSubject,
         customSubject: settings.customSubject,
-      }, systemInfo);
+      }, systemInfo, localeInfo, desktop, gitSummary);
 
       // Get fresh messages from store (after truncation)
       const currentMessages = useAppStore.getState().chat.messages;
@@ -256,10 +312,11 @@ export function ChatPanel({ onClose }: ChatPanelProps) {
         if (executeResult.cleanResponse) {
           addMessage({ role: 'assistant', content: executeResult.cleanResponse });
         }
-        setGeneratedCommand(newContent, executeResult.command);
+        setGeneratedCommand(newContent, executeResult.command, executeResult.privileged);
       } else {
         addMessage({ role: 'assistant', content: response });
       }
+      fireScriptHook('on_message', 'assistant', executeResult ? executeResult.cleanResponse : response);
 
       setExpression('neutral');
 
@@ -271,9 +328,10 @@ export function ChatPanel({ onClose }: ChatPanelProps) {
         role: 'assistant',
         content: `Ah, something went wrong! ${error instanceof Error ? error.message : 'Unknown error'}`,
       });
+      playSound('error');
       setExpression('sad');
     }
-  }, [settings, systemInfo, parseExecuteTag, setGeneratedCommand, addMessage, setThinking, setExpression, updateMessage, truncateMessagesAfter]);
+  }, [settings, systemInfo, localeInfo, parseExecuteTag, setGeneratedCommand, addMessage, setThinking, setExpression, updateMessage, truncateMessagesAfter]);
 
   const handleSend = useCallback(async (content: string, images?: ImageAttachment[]) => {
     // Handle slash commands before anything else
@@ -283,7 +341,10 @@ export function ChatPanel({ onClose }: ChatPanelProps) {
         toggleSettings,
         updateSettings: (s) => useAppStore.getState().updateSettings(s),
         addMessage: (msg) => addMessage(msg),
-        availableCharacters: Object.keys(characters),
+        availableCharacters: [
+          ...Object.keys(characters),
+          ...(useAppStore.getState().character.userModels ?? []).map(userCharacterId),
+        ],
         currentCharacter: settings.selectedCharacter,
       });
 
@@ -297,13 +358,14 @@ export function ChatPanel({ onClose }: ChatPanelProps) {
       }
     }
 
-    if (!settings.apiKey) {
+    if (needsApiKey(settings.llmProvider) && !settings.apiKey) {
       // This shouldn't happen since input is disabled without API key
       return;
     }
 
     // Add user message with images
     addMessage({ role: 'user', content, images });
+    fireScriptHook('on_message', 'user', content);
 
     // Start thinking animation while waiting for LLM
     setThinking(true);
@@ -312,12 +374,16 @@ export function ChatPanel({ onClose }: ChatPanelProps) {
       const provider = getProvider(settings.llmProvider);
 
       // Build system prompt from personality settings with system info
+      const [desktop, gitSummary] = await Promise.all([
+        currentDesktop(),
+        getGitContextSummary().catch(() => ''),
+      ]);
       const systemPrompt = buildSystemPrompt({
         selectedPersonality: settings.selectedPersonality,
         detailLevel: settings.detailLevel,
         assistantSubject: settings.assistantSubject,
         customSubject: settings.customSubject,
-      }, systemInfo);
+      }, systemInfo, localeInfo, desktop, gitSummary);
 
       // Build messages array with system prompt
       // Include images from previous messages and the current message
@@ -416,6 +482,7 @@ export function ChatPanel({ onClose }: ChatPanelProps) {
             const preview = fullResponse.substring(0, 100);
             debugLog(`[NOTIFICATION] Sending notification: "${preview}"`);
             showDesktopNotification('Desktop Waifu', preview + (preview.length >= 100 ? '...' : ''));
+            playSound('message');
           }
         }
 
@@ -434,6 +501,8 @@ export function ChatPanel({ onClose }: ChatPanelProps) {
         document.removeEventListener('visibilitychange', handleVisibilityChange);
 
         response = fullResponse;
+        announce(executeResult ? executeResult.cleanResponse : fullResponse);
+        speakReply(executeResult ? executeResult.cleanResponse : fullResponse);
 
         debugLog(`[LLM] Streaming complete, length=${response.length}`);
         debugLog(`[LLM] Response preview: ${response.substring(0, 200)}`);
@@ -446,7 +515,7 @@ export function ChatPanel({ onClose }: ChatPanelProps) {
           // Update the message to show clean response (without EXECUTE tag)
           updateMessageContent(messageId, executeResult.cleanResponse);
           // Trigger command approval flow
-          setGeneratedCommand(content, executeResult.command);
+          setGeneratedCommand(content, executeResult.command, executeResult.privileged);
           debugLog(`[LLM] setGeneratedCommand called, current status=${useAppStore.getState().execution.status}`);
         }
       } else {
@@ -470,11 +539,12 @@ export function ChatPanel({ onClose }: ChatPanelProps) {
             addMessage({ role: 'assistant', content: executeResult.cleanResponse });
           }
           // Trigger command approval flow - CommandApproval component shows the command
-          setGeneratedCommand(content, executeResult.command);
+          setGeneratedCommand(content, executeResult.command, executeResult.privileged);
           debugLog(`[LLM] setGeneratedCommand called, current status=${useAppStore.getState().execution.status}`);
         } else {
           debugLog(`[LLM] No EXECUTE tag, adding as regular message`);
           addMessage({ role: 'assistant', content: response });
+          speakReply(response);
           // Show notification based on user preference
           const state = useAppStore.getState();
           const pref = state.settings.notificationPreference;
@@ -488,9 +558,12 @@ export function ChatPanel({ onClose }: ChatPanelProps) {
             const preview = response.substring(0, 100);
             debugLog(`[NOTIFICATION] Sending notification: "${preview}"`);
             showDesktopNotification('Desktop Waifu', preview + (preview.length >= 100 ? '...' : ''));
+            playSound('message');
           }
         }
       }
+      // Behavior scripts' on_message(role, text) (see scripting.rs)
+      fireScriptHook('on_message', 'assistant', parseExecuteTag(response)?.cleanResponse ?? response);
 
       setExpression('neutral');
 
@@ -502,9 +575,10 @@ export function ChatPanel({ onClose }: ChatPanelProps) {
         role: 'assistant',
         content: `Ah, something went wrong! ${error instanceof Error ? error.message : 'Unknown error'}`,
       });
+      playSound('error');
       setExpression('sad');
     }
-}, [settings, messages, addMessage, addStreamingMessage, updateMessageContent, setThinking, setExpression, systemInfo, parseExecuteTag, setGeneratedCommand, buildLLMContent]);
+}, [settings, messages, addMessage, addStreamingMessage, updateMessageContent, setThinking, setExpression, systemInfo, localeInfo, parseExecuteTag, setGeneratedCommand, buildLLMContent]);
 
   return (
     <div className="w-full h-full flex flex-col bg-slate-900/90 border border-slate-600">
@@ -592,7 +666,7 @@ export function ChatPanel({ onClose }: ChatPanelProps) {
       <CommandApproval />
 
       {/* Input */}
-      <InputArea onSend={handleSend} disabled={isThinking || !settings.apiKey} />
+      <InputArea onSend={handleSend} disabled={isThinking || (needsApiKey(settings.llmProvider) && !settings.apiKey)} />
     </div>
   );
 }
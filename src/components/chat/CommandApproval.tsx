@@ -121,6 +121,11 @@ export function CommandApproval() {
       <div className="bg-slate-800 px-4 py-2 border-b border-slate-600">
         <div className="flex items-center gap-2">
           <span className="text-yellow-400 text-sm font-bold">Command Generated</span>
+          {execution.privileged && (
+            <span className="text-red-300 text-xs font-medium" title="Your system will ask for your password in its own dialog">
+              Runs as administrator
+            </span>
+          )}
           {execution.task && (
             <span className="text-slate-400 text-xs truncate">
               Task: {execution.task}
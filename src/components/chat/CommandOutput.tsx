@@ -1,5 +1,6 @@
 import { useState } from 'react';
 import { useAppStore } from '../../store';
+import { writeClipboard } from '../../lib/platform';
 
 export function CommandOutput() {
   const execution = useAppStore((state) => state.execution);
@@ -14,7 +15,7 @@ export function CommandOutput() {
     const text = execution.output
       ? `${execution.output.stdout}${execution.output.stderr}`
       : '';
-    await navigator.clipboard.writeText(text);
+    await writeClipboard(text);
     setCopied(true);
     setTimeout(() => setCopied(false), 1000);
   };
@@ -1,7 +1,16 @@
 import { useState, useRef, useEffect, useCallback, useMemo } from 'react';
 import { useAppStore } from '../../store';
 import { readClipboardImage, fileToImageAttachment, revokeImagePreview, SUPPORTED_MIME_TYPES } from '../../lib/image';
-import { isOverlayMode, openFileDialog, type FileDialogResult } from '../../lib/platform';
+import {
+  isOverlayMode,
+  openFileDialog,
+  readClipboard,
+  captureScreen,
+  isSpeechInputAvailable,
+  startListening,
+  stopListening,
+  type FileDialogResult,
+} from '../../lib/platform';
 import type { ImageAttachment } from '../../types';
 import { CommandSuggestions, getFilteredCommands } from './CommandSuggestions';
 
@@ -21,6 +30,13 @@ export function InputArea({ onSend, disabled }: InputAreaProps) {
 
   const setUserTyping = useAppStore((state) => state.setUserTyping);
   const executionStatus = useAppStore((state) => state.execution.status);
+  const pendingScreenshot = useAppStore((state) => state.chat.pendingScreenshot);
+  const pendingDrop = useAppStore((state) => state.chat.pendingDrop);
+  // OCR text from a screenshot-and-ask capture, sent along with the next message
+  const [screenshotText, setScreenshotText] = useState<string | null>(null);
+  // Speech input: recording state and the transcript of the utterance in progress
+  const [listening, setListening] = useState(false);
+  const [partialTranscript, setPartialTranscript] = useState('');
 
   // Determine if we should show command suggestions
   const showSuggestions = useMemo(() => {
@@ -81,14 +97,19 @@ export function InputArea({ onSend, disabled }: InputAreaProps) {
         clearTimeout(typingTimeoutRef.current);
       }
 
-      onSend(trimmed || 'What is in this image?', pendingImages.length > 0 ? pendingImages : undefined);
+      let message = trimmed || 'What is in this image?';
+      if (screenshotText) {
+        message += `\n\nText in the screenshot:\n${screenshotText}`;
+      }
+      onSend(message, pendingImages.length > 0 ? pendingImages : undefined);
       setInput('');
       setPendingImages([]);
+      setScreenshotText(null);
       if (textareaRef.current) {
         textareaRef.current.style.height = 'auto';
       }
     }
-  }, [input, pendingImages, disabled, onSend, setUserTyping]);
+  }, [input, pendingImages, screenshotText, disabled, onSend, setUserTyping]);
 
   // Handle clipboard paste for images
   const handlePaste = useCallback(async (e: React.ClipboardEvent) => {
@@ -164,6 +185,39 @@ export function InputArea({ onSend, disabled }: InputAreaProps) {
     }
   }, [fileDialogResultToAttachment]);
 
+  // Attach a screenshot of the whole screen, or of a region picked with Shift held
+  const handleCaptureScreen = useCallback(async (e: React.MouseEvent) => {
+    try {
+      const capture = await captureScreen(e.shiftKey);
+      if (!capture) return;
+      const image: ImageAttachment = {
+        id: crypto.randomUUID(),
+        mimeType: capture.mimeType as ImageAttachment['mimeType'],
+        data: capture.data,
+        previewUrl: `data:${capture.mimeType};base64,${capture.data}`,
+      };
+      setPendingImages((prev) => [...prev, image]);
+      textareaRef.current?.focus();
+    } catch (error) {
+      console.error('Failed to capture screen:', error);
+    }
+  }, []);
+
+  // Insert the clipboard text as a Markdown quote at the start of the message
+  const handleQuoteClipboard = useCallback(async () => {
+    let text: string | null;
+    try {
+      text = await readClipboard();
+    } catch (error) {
+      console.error('Failed to read clipboard:', error);
+      return;
+    }
+    if (!text?.trim()) return;
+    const quote = text.trimEnd().split('\n').map((line) => `> ${line}`).join('\n');
+    setInput((prev) => (prev.trim() ? `${quote}\n\n${prev}` : `${quote}\n\n`));
+    textareaRef.current?.focus();
+  }, []);
+
   // Cleanup preview URLs on unmount
   useEffect(() => {
     return () => {
@@ -230,6 +284,60 @@ export function InputArea({ onSend, disabled }: InputAreaProps) {
     };
   }, []);
 
+  // Pick up a screenshot from the overlay's screenshot-and-ask action
+  useEffect(() => {
+    if (!pendingScreenshot) return;
+    setPendingImages((prev) => [...prev, pendingScreenshot.image]);
+    if (pendingScreenshot.ocrText) {
+      setScreenshotText(pendingScreenshot.ocrText);
+    }
+    useAppStore.setState((state) => ({ chat: { ...state.chat, pendingScreenshot: null } }));
+    textareaRef.current?.focus();
+  }, [pendingScreenshot]);
+
+  // Pick up files dropped on the character
+  useEffect(() => {
+    if (!pendingDrop) return;
+    if (pendingDrop.images.length > 0) {
+      setPendingImages((prev) => [...prev, ...pendingDrop.images]);
+    }
+    if (pendingDrop.text) {
+      setInput((prev) => pendingDrop.text + prev);
+    }
+    useAppStore.setState((state) => ({ chat: { ...state.chat, pendingDrop: null } }));
+    textareaRef.current?.focus();
+  }, [pendingDrop]);
+
+  // Finished utterances are appended to the input; the one in progress is shown above it
+  useEffect(() => {
+    const handleTranscript = (e: Event) => {
+      const { text, final } = (e as CustomEvent<{ text: string; final: boolean }>).detail;
+      if (final) {
+        setPartialTranscript('');
+        setInput((prev) => (prev.trim() ? `${prev.trimEnd()} ${text}` : text));
+      } else {
+        setPartialTranscript(text);
+      }
+    };
+    const handleListeningChanged = (e: Event) => {
+      const { listening, error } = (e as CustomEvent<{ listening: boolean; error?: string | null }>).detail;
+      setListening(listening);
+      if (!listening) {
+        setPartialTranscript('');
+      }
+      if (error) {
+        console.error('Speech input failed:', error);
+      }
+    };
+
+    window.addEventListener('transcript', handleTranscript);
+    window.addEventListener('listeningChanged', handleListeningChanged);
+    return () => {
+      window.removeEventListener('transcript', handleTranscript);
+      window.removeEventListener('listeningChanged', handleListeningChanged);
+    };
+  }, []);
+
   // Refocus input when command approval flow completes
   useEffect(() => {
     const prevStatus = prevExecutionStatusRef.current;
@@ -287,6 +395,10 @@ export function InputArea({ onSend, disabled }: InputAreaProps) {
         </div>
       )}
 
+      {partialTranscript && (
+        <div className="mb-2 text-sm text-slate-400 italic truncate">{partialTranscript}</div>
+      )}
+
       <div className="flex items-end gap-2">
         {/* Hidden file input */}
         <input
@@ -310,6 +422,44 @@ export function InputArea({ onSend, disabled }: InputAreaProps) {
           </svg>
         </button>
 
+        {isOverlayMode && (
+          <button
+            onClick={handleCaptureScreen}
+            disabled={disabled}
+            className="bg-slate-700 text-white border border-slate-600 px-3 py-3 hover:bg-slate-600 transition-colors cursor-pointer disabled:opacity-50 disabled:cursor-not-allowed"
+            title="Look at my screen (Shift+click to pick a region)"
+          >
+            <svg xmlns="http://www.w3.org/2000/svg" className="h-5 w-5" viewBox="0 0 20 20" fill="currentColor">
+              <path fillRule="evenodd" d="M3 5a2 2 0 012-2h10a2 2 0 012 2v8a2 2 0 01-2 2h-2.22l.123.489.804.804A1 1 0 0113 18H7a1 1 0 01-.707-1.707l.804-.804L7.22 15H5a2 2 0 01-2-2V5zm5.771 7H5V5h10v7H8.771z" clipRule="evenodd" />
+            </svg>
+          </button>
+        )}
+
+        {isSpeechInputAvailable() && (
+          <button
+            onClick={listening ? stopListening : startListening}
+            disabled={disabled}
+            className={`${listening ? 'bg-pink-500 border-pink-500 hover:bg-pink-600' : 'bg-slate-700 border-slate-600 hover:bg-slate-600'} text-white border px-3 py-3 transition-colors cursor-pointer disabled:opacity-50 disabled:cursor-not-allowed`}
+            title={listening ? 'Stop listening' : 'Speak'}
+          >
+            <svg xmlns="http://www.w3.org/2000/svg" className="h-5 w-5" viewBox="0 0 20 20" fill="currentColor">
+              <path fillRule="evenodd" d="M7 4a3 3 0 016 0v4a3 3 0 11-6 0V4zm4 10.93A7.001 7.001 0 0017 8a1 1 0 10-2 0A5 5 0 015 8a1 1 0 00-2 0 7.001 7.001 0 006 6.93V17H6a1 1 0 100 2h8a1 1 0 100-2h-3v-2.07z" clipRule="evenodd" />
+            </svg>
+          </button>
+        )}
+
+        <button
+          onClick={handleQuoteClipboard}
+          disabled={disabled}
+          className="bg-slate-700 text-white border border-slate-600 px-3 py-3 hover:bg-slate-600 transition-colors cursor-pointer disabled:opacity-50 disabled:cursor-not-allowed"
+          title="Quote clipboard"
+        >
+          <svg xmlns="http://www.w3.org/2000/svg" className="h-5 w-5" viewBox="0 0 20 20" fill="currentColor">
+            <path d="M8 2a1 1 0 000 2h2a1 1 0 100-2H8z" />
+            <path d="M3 5a2 2 0 012-2 3 3 0 003 3h2a3 3 0 003-3 2 2 0 012 2v6h-4.586l1.293-1.293a1 1 0 00-1.414-1.414l-3 3a1 1 0 000 1.414l3 3a1 1 0 001.414-1.414L10.414 13H15v3a2 2 0 01-2 2H5a2 2 0 01-2-2V5zM15 11h2a1 1 0 110 2h-2v-2z" />
+          </svg>
+        </button>
+
         <div className="flex-1 relative">
           {showSuggestions && (
             <CommandSuggestions
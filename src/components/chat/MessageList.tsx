@@ -5,8 +5,10 @@ import { Prism as SyntaxHighlighter } from 'react-syntax-highlighter';
 import { oneDark } from 'react-syntax-highlighter/dist/esm/styles/prism';
 import type { ChatMessage, LLMProviderType } from '../../types';
 import { useAppStore } from '../../store';
-import { defaultModels } from '../../lib/llm';
+import { defaultModels, needsApiKey, providerNames } from '../../lib/llm';
+import { useModels } from '../../hooks/useModels';
 import { debugLog } from '../../lib/debug';
+import { writeClipboard } from '../../lib/platform';
 
 interface MessageListProps {
   messages: ChatMessage[];
@@ -24,11 +26,12 @@ function ApiKeySetup() {
   const settings = useAppStore((state) => state.settings);
   const updateSettings = useAppStore((state) => state.updateSettings);
   const [apiKey, setApiKey] = useState('');
+  const models = useModels(settings.llmProvider);
 
   const handleProviderChange = (provider: LLMProviderType) => {
     updateSettings({
       llmProvider: provider,
-      llmModel: defaultModels[provider][0],
+      llmModel: defaultModels[provider][0] ?? '',
     });
   };
 
@@ -73,6 +76,8 @@ function ApiKeySetup() {
               <option value="openai">OpenAI</option>
               <option value="anthropic">Anthropic</option>
               <option value="gemini">Google Gemini</option>
+              <option value="openrouter">OpenRouter</option>
+              <option value="local">Local (Ollama / llama.cpp)</option>
             </select>
           </div>
 
@@ -92,7 +97,7 @@ function ApiKeySetup() {
               }}
               className="w-full bg-gray-100 text-black border-2 border-black px-3 py-2 text-sm focus:outline-none focus:ring-2 focus:ring-pink-400"
             >
-              {defaultModels[settings.llmProvider].map((model) => (
+              {models.map((model) => (
                 <option key={model} value={model}>
                   {model}
                 </option>
@@ -115,7 +120,7 @@ function ApiKeySetup() {
                 debugLog('[ApiKeySetup] API Key input onMouseDown');
                 e.stopPropagation();
               }}
-              placeholder={`Enter your ${settings.llmProvider === 'openai' ? 'OpenAI' : settings.llmProvider === 'anthropic' ? 'Anthropic' : 'Gemini'} API key`}
+              placeholder={`Enter your ${providerNames[settings.llmProvider]} API key`}
               className="w-full bg-gray-100 text-black border-2 border-black px-3 py-2 text-sm focus:outline-none focus:ring-2 focus:ring-pink-400 placeholder-gray-400"
               onKeyDown={(e) => e.key === 'Enter' && handleSaveKey()}
             />
@@ -142,6 +147,7 @@ export function MessageList({ messages, isTyping, onEditAndRetry }: MessageListP
   const scrollRef = useRef<HTMLDivElement>(null);
   const userScrolledUp = useRef<boolean>(false);
   const apiKey = useAppStore((state) => state.settings.apiKey);
+  const llmProvider = useAppStore((state) => state.settings.llmProvider);
   const fontSize = useAppStore((state) => state.settings.fontSize);
   const [copiedId, setCopiedId] = useState<string | null>(null);
   const [expandedImage, setExpandedImage] = useState<string | null>(null);
@@ -172,7 +178,7 @@ export function MessageList({ messages, isTyping, onEditAndRetry }: MessageListP
   }, [messages.length]);
 
   const copyToClipboard = async (text: string, messageId: string) => {
-    await navigator.clipboard.writeText(text);
+    await writeClipboard(text);
     setCopiedId(messageId);
     setTimeout(() => setCopiedId(null), 1000);
   };
@@ -194,8 +200,8 @@ export function MessageList({ messages, isTyping, onEditAndRetry }: MessageListP
     cancelEditing();
   };
 
-  // Show API key setup if no key is set
-  const showSetup = !apiKey && messages.length === 0;
+  // Show API key setup if no key is set (local models need none)
+  const showSetup = !apiKey && needsApiKey(llmProvider) && messages.length === 0;
 
   return (
     <div
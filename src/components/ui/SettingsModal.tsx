@@ -1,21 +1,27 @@
 import { useEffect, useState } from 'react';
 import { useAppStore } from '../../store';
-import { defaultModels } from '../../lib/llm';
+import { defaultModels, needsApiKey } from '../../lib/llm';
+import { useModels } from '../../hooks/useModels';
 import { personalities } from '../../lib/personalities';
-import { characters } from '../../characters';
-import { executeCommand, setHotkeyEnabled, isOverlayMode } from '../../lib/platform';
+import { characters, userCharacterId } from '../../characters';
+import { executeCommand, getGlobalShortcut, setHotkeyEnabled, isOverlayMode, stopSpeaking } from '../../lib/platform';
 import type { LLMProviderType, PersonalityId, DetailLevel, NotificationPreference } from '../../types';
 
 // Script path varies between dev and production
 const HOTKEY_SCRIPT = import.meta.env.PROD
   ? '/usr/share/desktop-waifu/scripts/setup-hotkey.sh'
   : './scripts/setup-hotkey.sh';
+// Run from the app's own directory (for the dev path) rather than the user's home
+const HOTKEY_SCRIPT_OPTIONS = { confirmed: true, cwd: '.' };
 
 export function SettingsModal() {
   const settings = useAppStore((state) => state.settings);
   const updateSettings = useAppStore((state) => state.updateSettings);
   const toggleSettings = useAppStore((state) => state.toggleSettings);
+  const userModels = useAppStore((state) => state.character.userModels) ?? [];
+  const selectedUserModel = userModels.find((model) => userCharacterId(model) === settings.selectedCharacter);
   const setScaleSliderDragging = useAppStore((state) => state.setScaleSliderDragging);
+  const models = useModels(settings.llmProvider);
 
   // Hotkey setup state
   const [hotkeyLoading, setHotkeyLoading] = useState(false);
@@ -36,8 +42,20 @@ export function SettingsModal() {
       // Enabling: run setup script to configure compositor
       setHotkeyLoading(true);
       try {
+        // The desktop portal already delivers the shortcut, no compositor config needed
+        const shortcut = await getGlobalShortcut().catch(() => null);
+        if (shortcut?.bound) {
+          setHotkeyEnabled(true);
+          updateSettings({ hotkeyEnabled: true });
+          setHotkeyStatus({
+            type: 'success',
+            message: `Using the system shortcut${shortcut.trigger ? ` (${shortcut.trigger})` : ''}`,
+          });
+          return;
+        }
+
         // First check if binding already exists
-        const checkResult = await executeCommand(`${HOTKEY_SCRIPT} --check --json`);
+        const checkResult = await executeCommand(`${HOTKEY_SCRIPT} --check --json`, HOTKEY_SCRIPT_OPTIONS);
         let status: { status: string; message: string; compositor: string } | null = null;
 
         try {
@@ -59,7 +77,7 @@ export function SettingsModal() {
           });
         } else {
           // Not configured, run setup
-          const setupResult = await executeCommand(`${HOTKEY_SCRIPT} --json`);
+          const setupResult = await executeCommand(`${HOTKEY_SCRIPT} --json`, HOTKEY_SCRIPT_OPTIONS);
           let setupStatus: { status: string; message: string } | null = null;
 
           try {
@@ -114,7 +132,7 @@ export function SettingsModal() {
   const handleProviderChange = (provider: LLMProviderType) => {
     updateSettings({
       llmProvider: provider,
-      llmModel: defaultModels[provider][0],
+      llmModel: defaultModels[provider][0] ?? '',
     });
   };
 
@@ -145,6 +163,8 @@ export function SettingsModal() {
               <option value="openai">OpenAI</option>
               <option value="anthropic">Anthropic</option>
               <option value="gemini">Google Gemini</option>
+              <option value="openrouter">OpenRouter</option>
+              <option value="local">Local (Ollama / llama.cpp)</option>
             </select>
           </div>
 
@@ -156,28 +176,31 @@ export function SettingsModal() {
               onChange={(e) => updateSettings({ llmModel: e.target.value })}
               className="w-full bg-gray-700 text-black rounded-lg px-3 py-2 text-sm focus:outline-none focus:ring-2 focus:ring-teal-400"
             >
-              {defaultModels[settings.llmProvider].map((model) => (
+              {models.map((model) => (
                 <option key={model} value={model}>
                   {model}
                 </option>
               ))}
+              {models.length === 0 && <option value="">No local model server found</option>}
             </select>
           </div>
 
-          {/* API Key */}
-          <div>
-            <label className="block text-sm text-gray-300 mb-2">API Key</label>
-            <input
-              type="password"
-              value={settings.apiKey}
-              onChange={(e) => updateSettings({ apiKey: e.target.value })}
-              placeholder="Enter your API key"
-              className="w-full bg-gray-700 text-black rounded-lg px-3 py-2 text-sm focus:outline-none focus:ring-2 focus:ring-teal-400 placeholder-gray-400"
-            />
-            <p className="text-xs text-gray-500 mt-1">
-              Your API key is stored locally and never sent to our servers.
-            </p>
-          </div>
+          {/* API Key (local models need none) */}
+          {needsApiKey(settings.llmProvider) && (
+            <div>
+              <label className="block text-sm text-gray-300 mb-2">API Key</label>
+              <input
+                type="password"
+                value={settings.apiKey}
+                onChange={(e) => updateSettings({ apiKey: e.target.value })}
+                placeholder="Enter your API key"
+                className="w-full bg-gray-700 text-black rounded-lg px-3 py-2 text-sm focus:outline-none focus:ring-2 focus:ring-teal-400 placeholder-gray-400"
+              />
+              <p className="text-xs text-gray-500 mt-1">
+                Your API key is stored locally and never sent to our servers.
+              </p>
+            </div>
+          )}
 
           {/* Personality Section Divider */}
           <div className="pt-2">
@@ -279,7 +302,36 @@ export function SettingsModal() {
                   {c.config.name}
                 </option>
               ))}
+              {userModels.length > 0 && (
+                <optgroup label="Imported">
+                  {userModels.map((model) => (
+                    <option key={model.id} value={userCharacterId(model)}>
+                      {model.name}
+                    </option>
+                  ))}
+                </optgroup>
+              )}
             </select>
+            {selectedUserModel && (
+              <div className="mt-2 flex items-center gap-3 text-xs text-gray-400">
+                {selectedUserModel.thumbnailUrl && (
+                  <img
+                    src={selectedUserModel.thumbnailUrl}
+                    alt=""
+                    className="w-12 h-12 rounded object-cover flex-shrink-0"
+                  />
+                )}
+                <div className="min-w-0">
+                  {selectedUserModel.authors.length > 0 && (
+                    <div className="truncate">By {selectedUserModel.authors.join(', ')}</div>
+                  )}
+                  {selectedUserModel.license && (
+                    <div className="truncate">License: {selectedUserModel.license}</div>
+                  )}
+                  <div>VRM {selectedUserModel.vrmVersion === 1 ? '1.0' : '0.x'}, {selectedUserModel.expressions.length} expressions</div>
+                </div>
+              </div>
+            )}
           </div>
 
           {/* Character Scale */}
@@ -402,6 +454,43 @@ export function SettingsModal() {
             </p>
           </div>
 
+          {/* Commands Section Divider */}
+          <div className="pt-2">
+            <div className="text-xs font-medium text-gray-500 uppercase tracking-wide border-b border-gray-700 pb-2">
+              Commands
+            </div>
+          </div>
+
+          {/* Command Shell */}
+          <div>
+            <label className="block text-sm text-gray-300 mb-2">Shell</label>
+            <input
+              type="text"
+              value={settings.commandShell}
+              onChange={(e) => updateSettings({ commandShell: e.target.value })}
+              placeholder="Login shell ($SHELL)"
+              className="w-full bg-gray-700 text-white rounded-lg px-3 py-2 text-sm focus:outline-none focus:ring-2 focus:ring-teal-400 placeholder-gray-400"
+            />
+            <p className="text-xs text-gray-500 mt-1">
+              Shell that runs approved commands, e.g. /usr/bin/fish.
+            </p>
+          </div>
+
+          {/* Command Working Directory */}
+          <div>
+            <label className="block text-sm text-gray-300 mb-2">Working Directory</label>
+            <input
+              type="text"
+              value={settings.commandCwd}
+              onChange={(e) => updateSettings({ commandCwd: e.target.value })}
+              placeholder="~"
+              className="w-full bg-gray-700 text-white rounded-lg px-3 py-2 text-sm focus:outline-none focus:ring-2 focus:ring-teal-400 placeholder-gray-400"
+            />
+            <p className="text-xs text-gray-500 mt-1">
+              Directory commands start in. Defaults to your home directory.
+            </p>
+          </div>
+
           {/* Notifications Section Divider */}
           <div className="pt-2">
             <div className="text-xs font-medium text-gray-500 uppercase tracking-wide border-b border-gray-700 pb-2">
@@ -427,6 +516,55 @@ export function SettingsModal() {
               {settings.notificationPreference === 'off' && 'Never show desktop notifications.'}
             </p>
           </div>
+
+          {isOverlayMode && (
+            <>
+              {/* Voice Section Divider */}
+              <div className="pt-2">
+                <div className="text-xs font-medium text-gray-500 uppercase tracking-wide border-b border-gray-700 pb-2">
+                  Voice
+                </div>
+              </div>
+
+              {/* Spoken Replies */}
+              <div className="flex items-center justify-between">
+                <div>
+                  <label className="text-sm text-gray-300">Speak Replies</label>
+                  <p className="text-xs text-gray-500">Read replies aloud, with lip-sync. Muted during calls.</p>
+                </div>
+                <button
+                  onClick={() => {
+                    if (settings.speakReplies) stopSpeaking();
+                    updateSettings({ speakReplies: !settings.speakReplies });
+                  }}
+                  className={`relative w-12 h-6 rounded-full transition-colors ${
+                    settings.speakReplies ? 'bg-teal-400' : 'bg-gray-600'
+                  }`}
+                >
+                  <span
+                    className={`absolute top-1 left-1 w-4 h-4 rounded-full bg-white transition-transform ${
+                      settings.speakReplies ? 'translate-x-6' : 'translate-x-0'
+                    }`}
+                  />
+                </button>
+              </div>
+
+              {/* Voice */}
+              <div>
+                <label className="block text-sm text-gray-300 mb-2">Voice</label>
+                <input
+                  type="text"
+                  value={settings.ttsVoice}
+                  onChange={(e) => updateSettings({ ttsVoice: e.target.value })}
+                  placeholder="Default from config.toml"
+                  className="w-full bg-gray-700 text-white rounded-lg px-3 py-2 text-sm focus:outline-none focus:ring-2 focus:ring-teal-400 placeholder-gray-400"
+                />
+                <p className="text-xs text-gray-500 mt-1">
+                  A Piper voice, an espeak-ng voice like "en-us", or a cloud voice like "alloy", depending on the engine.
+                </p>
+              </div>
+            </>
+          )}
         </div>
 
         <div className="mt-6 pt-4 border-t border-gray-700">
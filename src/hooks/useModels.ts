@@ -0,0 +1,31 @@
+import { useState, useEffect } from 'react';
+import { defaultModels } from '../lib/llm';
+import { listLocalModels } from '../lib/platform';
+import { debugLog } from '../lib/debug';
+import type { LLMProviderType } from '../types';
+
+/**
+ * Hook for the models to offer for a provider: the usual ones for cloud providers, and
+ * whatever the local server (Ollama, llama.cpp) has for `local`, fetched when it's picked.
+ */
+export function useModels(provider: LLMProviderType): string[] {
+  const [localModels, setLocalModels] = useState<string[]>([]);
+
+  useEffect(() => {
+    if (provider !== 'local') return;
+    let cancelled = false;
+    listLocalModels()
+      .then(({ models }) => {
+        if (!cancelled) setLocalModels(models);
+      })
+      .catch((error) => {
+        debugLog(`[MODELS] No local model server: ${error}`);
+        if (!cancelled) setLocalModels([]);
+      });
+    return () => {
+      cancelled = true;
+    };
+  }, [provider]);
+
+  return provider === 'local' ? localModels : defaultModels[provider];
+}
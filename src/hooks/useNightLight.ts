@@ -0,0 +1,53 @@
+import { useState, useEffect } from 'react';
+import { getNightLight, type NightLight } from '../lib/platform';
+
+const DEFAULT_NIGHT_LIGHT: NightLight = {
+  active: false,
+  temperature: 6500,
+  dim: 0,
+  source: 'schedule',
+};
+
+/**
+ * Apply night light to the document root so CSS can dim the UI:
+ * `data-night-light` and the `--night-light-dim` custom property.
+ */
+function applyToDocument(nightLight: NightLight) {
+  const root = document.documentElement;
+  root.dataset.nightLight = String(nightLight.active);
+  root.style.setProperty('--night-light-dim', String(nightLight.dim));
+}
+
+/**
+ * Hook to follow the desktop's night light (or the overlay's evening hours / tray override).
+ * Fetches the current state on mount and updates on `nightLightChanged` events from Rust.
+ */
+export function useNightLight(): NightLight {
+  const [nightLight, setNightLight] = useState<NightLight>(DEFAULT_NIGHT_LIGHT);
+
+  useEffect(() => {
+    let cancelled = false;
+    getNightLight()
+      .then((initial) => {
+        if (!cancelled) setNightLight(initial);
+      })
+      .catch(() => {
+        // No response: keep night light off
+      });
+
+    const handleChange = (e: Event) => {
+      setNightLight((e as CustomEvent<NightLight>).detail);
+    };
+    window.addEventListener('nightLightChanged', handleChange);
+    return () => {
+      cancelled = true;
+      window.removeEventListener('nightLightChanged', handleChange);
+    };
+  }, []);
+
+  useEffect(() => {
+    applyToDocument(nightLight);
+  }, [nightLight]);
+
+  return nightLight;
+}
@@ -0,0 +1,59 @@
+import { useState, useEffect } from 'react';
+import { getAppearance, type SystemAppearance } from '../lib/platform';
+
+const DEFAULT_APPEARANCE: SystemAppearance = {
+  colorScheme: 'no-preference',
+  accentColor: null,
+  highContrast: false,
+};
+
+/**
+ * Apply appearance to the document root so CSS can follow it:
+ * `data-color-scheme`, `data-high-contrast`, the `color-scheme` property
+ * and the `--system-accent-color` custom property.
+ */
+function applyToDocument(appearance: SystemAppearance) {
+  const root = document.documentElement;
+  root.dataset.colorScheme = appearance.colorScheme;
+  root.dataset.highContrast = String(appearance.highContrast);
+  root.style.colorScheme = appearance.colorScheme === 'no-preference' ? '' : appearance.colorScheme;
+  if (appearance.accentColor) {
+    root.style.setProperty('--system-accent-color', appearance.accentColor);
+  } else {
+    root.style.removeProperty('--system-accent-color');
+  }
+}
+
+/**
+ * Hook to follow the desktop's dark/light mode, accent color and contrast setting.
+ * Fetches the current appearance on mount and updates on `appearanceChanged` events from Rust.
+ */
+export function useSystemAppearance(): SystemAppearance {
+  const [appearance, setAppearance] = useState<SystemAppearance>(DEFAULT_APPEARANCE);
+
+  useEffect(() => {
+    let cancelled = false;
+    getAppearance()
+      .then((initial) => {
+        if (!cancelled) setAppearance(initial);
+      })
+      .catch(() => {
+        // No portal or no response: keep the defaults
+      });
+
+    const handleChange = (e: Event) => {
+      setAppearance((e as CustomEvent<SystemAppearance>).detail);
+    };
+    window.addEventListener('appearanceChanged', handleChange);
+    return () => {
+      cancelled = true;
+      window.removeEventListener('appearanceChanged', handleChange);
+    };
+  }, []);
+
+  useEffect(() => {
+    applyToDocument(appearance);
+  }, [appearance]);
+
+  return appearance;
+}
@@ -0,0 +1,102 @@
+/**
+ * Things the character says on its own
+ *
+ * The overlay's behavior scheduler (behavior.rs) and reminders (reminders.rs) decide when,
+ * so the timing holds while the window is hidden. Here the character finds the words:
+ * stretch reminders, the user's own reminders and unprompted remarks become chat messages
+ * in the chosen personality, read aloud and notified the way replies are. Lines from
+ * behavior scripts and the REST API are said word for word.
+ */
+
+import { useAppStore } from '../store';
+import { getProvider, needsApiKey } from './llm';
+import { buildSystemPrompt } from './personalities';
+import { isWindowCurrentlyFocused, showDesktopNotification } from './platform';
+import { speakReply } from './speech';
+import { debugLog } from './debug';
+
+/**
+ * Have the character say something prompted by `instruction`. Without a usable model
+ * (no API key, request failed) the character says `fallback` instead, or nothing if there
+ * is none. `notify: false` skips the desktop notification, for when the overlay has
+ * already shown one.
+ */
+export async function sayInCharacter(instruction: string, fallback: string | null, notify = true): Promise<void> {
+  const { settings, chat, ui } = useAppStore.getState();
+  // Don't talk over a reply or a call
+  if (chat.isThinking || ui.quietMode) return;
+
+  let text = fallback;
+  if (!needsApiKey(settings.llmProvider) || settings.apiKey) {
+    try {
+      const systemPrompt = buildSystemPrompt({
+        selectedPersonality: settings.selectedPersonality,
+        detailLevel: settings.detailLevel,
+        assistantSubject: settings.assistantSubject,
+        customSubject: settings.customSubject,
+      });
+      const reply = await getProvider(settings.llmProvider).chat(
+        [
+          { role: 'system', content: systemPrompt },
+          { role: 'user', content: `(Not from the user: ${instruction} Reply with only what you'd say.)` },
+        ],
+        { apiKey: settings.apiKey, model: settings.llmModel, maxTokens: 200, temperature: 0.9 },
+      );
+      text = reply.trim() || fallback;
+    } catch (error) {
+      debugLog(`[BEHAVIOR] Couldn't ask the model: ${error}`);
+    }
+  }
+  if (!text) return;
+
+  const state = useAppStore.getState();
+  state.addMessage({ role: 'assistant', content: text });
+  speakReply(text);
+  const pref = state.settings.notificationPreference;
+  if (notify && ((pref === 'chat_closed' && !state.ui.chatPanelOpen) || (pref === 'unfocused' && !isWindowCurrentlyFocused()))) {
+    showDesktopNotification('Desktop Waifu', text.substring(0, 100));
+  }
+}
+
+window.addEventListener('stretchReminder', (e) => {
+  const { minutes } = (e as CustomEvent<{ minutes: number }>).detail;
+  debugLog(`[BEHAVIOR] Stretch reminder after ${minutes} minutes`);
+  sayInCharacter(
+    `The user has been at the computer for ${minutes} minutes without a break. Remind them to stand up and stretch, in one or two short sentences.`,
+    `You've been at it for ${minutes} minutes. Time to stand up and stretch!`,
+  );
+});
+
+window.addEventListener('reminder', (e) => {
+  const { text, late } = (e as CustomEvent<{ id: number; text: string; late: boolean }>).detail;
+  debugLog(`[BEHAVIOR] Reminder due: ${text}`);
+  const { chat, ui, addMessage } = useAppStore.getState();
+  // The user asked for this one, so it isn't dropped like a remark; the overlay already
+  // showed a notification
+  if (chat.isThinking || ui.quietMode) {
+    addMessage({ role: 'assistant', content: `Reminder: ${text}` });
+    return;
+  }
+  sayInCharacter(
+    `The user asked you to remind them: "${text}". ${late ? "It came due while you weren't running, so you're a little late. " : ''}Remind them now, in one or two short sentences.`,
+    `Reminder: ${text}`,
+    false,
+  );
+});
+
+window.addEventListener('idleRemark', () => {
+  debugLog('[BEHAVIOR] Time for a remark');
+  sayInCharacter(
+    "Say something to the user unprompted, as if it just crossed your mind: a remark, a question or a bit of encouragement. One or two sentences, and don't suggest commands.",
+    null,
+  );
+});
+
+// Words chosen elsewhere (behavior scripts' say(), the REST API) are said as they are
+window.addEventListener('characterSay', (e) => {
+  const { text } = (e as CustomEvent<{ text: string }>).detail;
+  if (!text?.trim()) return;
+  debugLog(`[BEHAVIOR] Saying: ${text}`);
+  useAppStore.getState().addMessage({ role: 'assistant', content: text });
+  speakReply(text);
+});
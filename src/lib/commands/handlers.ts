@@ -1,3 +1,19 @@
+import {
+  addReminder,
+  allowDir,
+  cancelReminder,
+  disallowDir,
+  forgetMemory,
+  isOverlayMode,
+  listAllowedDirs,
+  listGitRepos,
+  listMemories,
+  listReminders,
+  recallMemories,
+  registerGitRepo,
+  remember,
+  unregisterGitRepo,
+} from '../platform';
 import type { CommandDefinition, CommandHandler, CommandResult } from './types';
 
 // Handler for /clear
@@ -56,6 +72,221 @@ const characterHandler: CommandHandler = (args, _rawArgs, context): CommandResul
   };
 };
 
+// Memory lives in the overlay; the commands reply once it answers
+const memoryUnavailable: CommandResult = {
+  handled: true,
+  error: 'Memory is only available in the desktop overlay.',
+};
+
+// Handler for /remember
+const rememberHandler: CommandHandler = (_args, rawArgs, context): CommandResult => {
+  if (!isOverlayMode) return memoryUnavailable;
+  const fact = rawArgs.trim();
+  if (!fact) {
+    return { handled: true, error: 'Usage: `/remember <something about you>`' };
+  }
+  remember(fact)
+    .then((memory) => context.addMessage({ role: 'assistant', content: `Got it, I'll remember that! (#${memory.id})` }))
+    .catch((error) => context.addMessage({ role: 'assistant', content: `**Error:** ${error.message}` }));
+  return { handled: true };
+};
+
+// Handler for /memories
+const memoriesHandler: CommandHandler = (_args, rawArgs, context): CommandResult => {
+  if (!isOverlayMode) return memoryUnavailable;
+  const query = rawArgs.trim();
+  (query ? recallMemories(query) : listMemories())
+    .then((memories) => {
+      const content = memories.length === 0
+        ? "I don't remember anything about you yet. Teach me with `/remember`!"
+        : `**${query ? 'Memories about that' : 'What I remember'}:**\n\n` +
+          memories.map((m) => `- ${m.text} (#${m.id})`).join('\n') +
+          '\n\nForget one with `/forget <number>`.';
+      context.addMessage({ role: 'assistant', content });
+    })
+    .catch((error) => context.addMessage({ role: 'assistant', content: `**Error:** ${error.message}` }));
+  return { handled: true };
+};
+
+// Handler for /forget
+const forgetHandler: CommandHandler = (args, _rawArgs, context): CommandResult => {
+  if (!isOverlayMode) return memoryUnavailable;
+  const id = args[0] ? Number(args[0].replace(/^#/, '')) : NaN;
+  if (!Number.isInteger(id)) {
+    return { handled: true, error: 'Usage: `/forget <number>` (see `/memories`)' };
+  }
+  forgetMemory(id)
+    .then((found) => context.addMessage({
+      role: 'assistant',
+      content: found ? `Forgotten #${id}.` : `There's no memory #${id}.`,
+    }))
+    .catch((error) => context.addMessage({ role: 'assistant', content: `**Error:** ${error.message}` }));
+  return { handled: true };
+};
+
+// Folder access is kept by the overlay, in config.toml
+const filesUnavailable: CommandResult = {
+  handled: true,
+  error: 'File access is only available in the desktop overlay.',
+};
+
+// Handler for /allow; typing it is the user's approval, so the chat doesn't ask again (the
+// overlay's confirmation dialog does, when it's on)
+const allowHandler: CommandHandler = (_args, rawArgs, context): CommandResult => {
+  if (!isOverlayMode) return filesUnavailable;
+  const dir = rawArgs.trim();
+  if (!dir) {
+    listAllowedDirs()
+      .then((dirs) => context.addMessage({
+        role: 'assistant',
+        content: dirs.length === 0
+          ? "I can't look at any of your folders yet. Allow one with `/allow <folder>`, e.g. `/allow ~/projects`."
+          : `**Folders I can read:**\n\n${dirs.map((d) => `- \`${d}\``).join('\n')}\n\nTake one back with \`/disallow <folder>\`.`,
+      }))
+      .catch((error) => context.addMessage({ role: 'assistant', content: `**Error:** ${error.message}` }));
+    return { handled: true };
+  }
+  allowDir(dir)
+    .then((saved) => context.addMessage({ role: 'assistant', content: `Okay, I can read files in \`${saved}\` now.` }))
+    .catch((error) => context.addMessage({ role: 'assistant', content: `**Error:** ${error.message}` }));
+  return { handled: true };
+};
+
+// Handler for /disallow
+const disallowHandler: CommandHandler = (_args, rawArgs, context): CommandResult => {
+  if (!isOverlayMode) return filesUnavailable;
+  const dir = rawArgs.trim();
+  if (!dir) {
+    return { handled: true, error: 'Usage: `/disallow <folder>` (see `/allow`)' };
+  }
+  disallowDir(dir)
+    .then((found) => context.addMessage({
+      role: 'assistant',
+      content: found ? `I won't look in \`${dir}\` anymore.` : `\`${dir}\` wasn't allowed.`,
+    }))
+    .catch((error) => context.addMessage({ role: 'assistant', content: `**Error:** ${error.message}` }));
+  return { handled: true };
+};
+
+// Reminders are kept and fired by the overlay, so they work while the chat is closed
+const remindersUnavailable: CommandResult = {
+  handled: true,
+  error: 'Reminders are only available in the desktop overlay.',
+};
+
+const timeOf = (due: number) => new Date(due).toLocaleTimeString([], { hour: '2-digit', minute: '2-digit' });
+
+// Handler for /remind
+const remindHandler: CommandHandler = (args, rawArgs, context): CommandResult => {
+  if (!isOverlayMode) return remindersUnavailable;
+  const text = rawArgs.trim().slice(args[0]?.length ?? 0).trim();
+  if (!args[0] || !text) {
+    return { handled: true, error: 'Usage: `/remind <delay> <text>`, e.g. `/remind 25m stand up`' };
+  }
+  addReminder(text, args[0])
+    .then((reminder) => context.addMessage({
+      role: 'assistant',
+      content: `Okay, I'll remind you at ${timeOf(reminder.due)}! (#${reminder.id})`,
+    }))
+    .catch((error) => context.addMessage({ role: 'assistant', content: `**Error:** ${error.message}` }));
+  return { handled: true };
+};
+
+// Handler for /reminders
+const remindersHandler: CommandHandler = (args, _rawArgs, context): CommandResult => {
+  if (!isOverlayMode) return remindersUnavailable;
+  if (args[0] === 'cancel') {
+    const id = args[1] ? Number(args[1].replace(/^#/, '')) : NaN;
+    if (!Number.isInteger(id)) {
+      return { handled: true, error: 'Usage: `/reminders cancel <number>`' };
+    }
+    cancelReminder(id)
+      .then((found) => context.addMessage({
+        role: 'assistant',
+        content: found ? `Cancelled reminder #${id}.` : `There's no reminder #${id}.`,
+      }))
+      .catch((error) => context.addMessage({ role: 'assistant', content: `**Error:** ${error.message}` }));
+    return { handled: true };
+  }
+  listReminders()
+    .then((reminders) => context.addMessage({
+      role: 'assistant',
+      content: reminders.length === 0
+        ? 'No reminders set. Add one with `/remind <delay> <text>`.'
+        : `**Reminders:**\n\n${reminders.map((r) => `- ${timeOf(r.due)}: ${r.text} (#${r.id})`).join('\n')}` +
+          '\n\nCancel one with `/reminders cancel <number>`.',
+    }))
+    .catch((error) => context.addMessage({ role: 'assistant', content: `**Error:** ${error.message}` }));
+  return { handled: true };
+};
+
+// Handler for /pomodoro: a focus timer followed by a break timer
+const pomodoroHandler: CommandHandler = (args, _rawArgs, context): CommandResult => {
+  if (!isOverlayMode) return remindersUnavailable;
+  const [work, rest] = [args[0] ?? '25', args[1] ?? '5'].map(Number);
+  if (![work, rest].every((n) => Number.isInteger(n) && n > 0)) {
+    return { handled: true, error: 'Usage: `/pomodoro [focus minutes] [break minutes]`' };
+  }
+  addReminder(`Pomodoro done! Take a ${rest} minute break.`, `${work}m`)
+    .then(() => addReminder("Break's over, back to focusing!", `${work + rest}m`))
+    .then(() => context.addMessage({
+      role: 'assistant',
+      content: `Pomodoro started: ${work} minutes of focus, then a ${rest} minute break. You've got this!`,
+    }))
+    .catch((error) => context.addMessage({ role: 'assistant', content: `**Error:** ${error.message}` }));
+  return { handled: true };
+};
+
+// The git watcher runs in the overlay; what it sees goes into the assistant's prompt
+const gitUnavailable: CommandResult = {
+  handled: true,
+  error: 'Watching repositories is only available in the desktop overlay.',
+};
+
+const gitUsage = 'Usage: `/git [watch <folder> | unwatch <folder>]`';
+
+// Handler for /git
+const gitHandler: CommandHandler = (args, rawArgs, context): CommandResult => {
+  if (!isOverlayMode) return gitUnavailable;
+  const path = rawArgs.trim().slice(args[0]?.length ?? 0).trim();
+  const fail = (error: Error) => context.addMessage({ role: 'assistant', content: `**Error:** ${error.message}` });
+  switch (args[0]) {
+    case undefined:
+      listGitRepos()
+        .then((repos) => context.addMessage({
+          role: 'assistant',
+          content: repos.length === 0
+            ? "I'm not watching any repositories. Point me at one with `/git watch <folder>`, e.g. `/git watch ~/projects/app`."
+            : `**Repositories I'm watching:**\n\n${repos.map((r) => `- **${r.name}** (\`${r.path}\`)`).join('\n')}` +
+              '\n\nStop with `/git unwatch <folder>`.',
+        }))
+        .catch(fail);
+      return { handled: true };
+    case 'watch':
+      if (!path) return { handled: true, error: gitUsage };
+      registerGitRepo(path)
+        .then((result) => context.addMessage({
+          role: 'assistant',
+          content: 'error' in result
+            ? `**Error:** ${result.error}`
+            : `Okay, I'm keeping an eye on **${result.name}** (${result.branch ?? 'detached HEAD'}).`,
+        }))
+        .catch(fail);
+      return { handled: true };
+    case 'unwatch':
+      if (!path) return { handled: true, error: gitUsage };
+      unregisterGitRepo(path)
+        .then((found) => context.addMessage({
+          role: 'assistant',
+          content: found ? `I've stopped watching \`${path}\`.` : `I wasn't watching \`${path}\`.`,
+        }))
+        .catch(fail);
+      return { handled: true };
+    default:
+      return { handled: true, error: gitUsage };
+  }
+};
+
 // Handler for /help
 const helpHandler: CommandHandler = (_args, _rawArgs, _context): CommandResult => {
   const helpText = commandRegistry
@@ -88,6 +319,60 @@ export const commandRegistry: CommandDefinition[] = [
     usage: '/character [name]',
     handler: characterHandler,
   },
+  {
+    name: 'remember',
+    description: 'Teach the assistant something about you',
+    usage: '/remember <fact>',
+    handler: rememberHandler,
+  },
+  {
+    name: 'memories',
+    description: 'List what the assistant remembers, or search it',
+    usage: '/memories [query]',
+    handler: memoriesHandler,
+  },
+  {
+    name: 'forget',
+    description: 'Forget a memory by number',
+    usage: '/forget <number>',
+    handler: forgetHandler,
+  },
+  {
+    name: 'allow',
+    description: 'Let the assistant read files in a folder, or list the folders it can read',
+    usage: '/allow [folder]',
+    handler: allowHandler,
+  },
+  {
+    name: 'disallow',
+    description: 'Take back access to a folder',
+    usage: '/disallow <folder>',
+    handler: disallowHandler,
+  },
+  {
+    name: 'remind',
+    description: 'Get reminded of something later, even with the chat closed',
+    usage: '/remind <delay> <text>',
+    handler: remindHandler,
+  },
+  {
+    name: 'reminders',
+    description: 'List pending reminders, or cancel one',
+    usage: '/reminders [cancel <number>]',
+    handler: remindersHandler,
+  },
+  {
+    name: 'pomodoro',
+    description: 'Start a focus timer followed by a break',
+    usage: '/pomodoro [focus minutes] [break minutes]',
+    handler: pomodoroHandler,
+  },
+  {
+    name: 'git',
+    description: 'Let the assistant keep an eye on a git repository, or list the ones it watches',
+    usage: '/git [watch <folder> | unwatch <folder>]',
+    handler: gitHandler,
+  },
   {
     name: 'help',
     description: 'Show available commands',
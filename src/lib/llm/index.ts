@@ -2,22 +2,51 @@ import type { LLMProvider } from './providers/base';
 import { OpenAIProvider } from './providers/openai';
 import { AnthropicProvider } from './providers/anthropic';
 import { GeminiProvider } from './providers/gemini';
+import { ProxyProvider } from './providers/proxy';
+import { isOverlayMode, OLLAMA_URL } from '../platform';
 import type { LLMProviderType } from '../../types';
 
 const providers: Record<LLMProviderType, LLMProvider> = {
   openai: new OpenAIProvider(),
   anthropic: new AnthropicProvider(),
   gemini: new GeminiProvider(),
+  openrouter: new OpenAIProvider('openrouter', 'https://openrouter.ai/api/v1'),
+  local: new OpenAIProvider('local', OLLAMA_URL),
+};
+
+// In overlay mode the overlay makes the requests (and holds the keys)
+const proxyProviders: Record<LLMProviderType, LLMProvider> = {
+  openai: new ProxyProvider('openai'),
+  anthropic: new ProxyProvider('anthropic'),
+  gemini: new ProxyProvider('gemini'),
+  openrouter: new ProxyProvider('openrouter'),
+  local: new ProxyProvider('local'),
 };
 
 export const getProvider = (type: LLMProviderType): LLMProvider => {
-  return providers[type];
+  return isOverlayMode ? proxyProviders[type] : providers[type];
 };
 
 export const defaultModels: Record<LLMProviderType, string[]> = {
   openai: ['gpt-4o-mini', 'gpt-4o', 'gpt-4-turbo'],
   anthropic: ['claude-sonnet-4-5-20250514', 'claude-opus-4-5-20251101', 'claude-3-haiku-20240307'],
   gemini: ['gemini-2.5-flash', 'gemini-2.0-flash', 'gemini-2.5-pro'],
+  openrouter: ['openai/gpt-4o-mini', 'anthropic/claude-sonnet-4.5', 'google/gemini-2.5-flash', 'meta-llama/llama-3.3-70b-instruct'],
+  // Whatever the local server has; see useModels
+  local: [],
 };
 
+export const providerNames: Record<LLMProviderType, string> = {
+  openai: 'OpenAI',
+  anthropic: 'Anthropic',
+  gemini: 'Gemini',
+  openrouter: 'OpenRouter',
+  local: 'Local (Ollama / llama.cpp)',
+};
+
+/**
+ * Whether the provider needs an API key; local models don't
+ */
+export const needsApiKey = (type: LLMProviderType): boolean => type !== 'local';
+
 export { type LLMProvider } from './providers/base';
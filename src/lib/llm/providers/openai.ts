@@ -6,8 +6,11 @@ import type {
 import type { LLMProvider } from './base';
 import type { LLMMessage, LLMConfig, LLMContentPart } from '../../../types';
 
+/**
+ * OpenAI's chat completions API, or another service that speaks it (OpenRouter)
+ */
 export class OpenAIProvider implements LLMProvider {
-  readonly name = 'openai';
+  constructor(readonly name: string = 'openai', private readonly baseURL?: string) {}
 
   /**
    * Format message content for OpenAI API
@@ -53,6 +56,7 @@ export class OpenAIProvider implements LLMProvider {
   async chat(messages: LLMMessage[], config: LLMConfig): Promise<string> {
     const client = new OpenAI({
       apiKey: config.apiKey,
+      baseURL: this.baseURL,
       dangerouslyAllowBrowser: true,
     });
 
@@ -69,6 +73,7 @@ export class OpenAIProvider implements LLMProvider {
   async *streamChat(messages: LLMMessage[], config: LLMConfig): AsyncIterable<string> {
     const client = new OpenAI({
       apiKey: config.apiKey,
+      baseURL: this.baseURL,
       dangerouslyAllowBrowser: true,
     });
 
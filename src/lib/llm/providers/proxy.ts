@@ -0,0 +1,112 @@
+import type { LLMProvider } from './base';
+import type { LLMMessage, LLMConfig, LLMProviderType } from '../../../types';
+import { llmChat, listTools, callTool } from '../../platform';
+import type { LLMChatMessage, LLMChatRequest, LLMToolCall, ToolDefinition } from '../../platform';
+
+/** Requests in one reply that may call tools; the last gets no tools, so it has to answer */
+const MAX_TOOL_ROUNDS = 5;
+
+/**
+ * Provider that has the overlay make the request: the key stays on the native side (see
+ * setLlmKey), so `config.apiKey` is ignored here.
+ *
+ * In overlay mode the model is offered the overlay's tools. When a reply calls some, they
+ * run (after asking the user, for tools that require it), their results go back in a
+ * follow-up request, and so on until the model answers in text.
+ */
+export class ProxyProvider implements LLMProvider {
+  constructor(readonly name: LLMProviderType) {}
+
+  async chat(messages: LLMMessage[], config: LLMConfig): Promise<string> {
+    let text = '';
+    for await (const token of this.streamChat(messages, config)) {
+      text += token;
+    }
+    return text;
+  }
+
+  async *streamChat(messages: LLMMessage[], config: LLMConfig): AsyncIterable<string> {
+    const tools = await listTools().catch(() => [] as ToolDefinition[]);
+    const conversation: LLMChatMessage[] = [...messages];
+
+    for (let round = 1; ; round++) {
+      const offered = round < MAX_TOOL_ROUNDS ? tools : [];
+      const reply = yield* this.streamRequest({
+        provider: this.name,
+        model: config.model,
+        messages: conversation,
+        maxTokens: config.maxTokens ?? 500,
+        temperature: config.temperature ?? 0.8,
+        tools: offered.length > 0 ? offered : undefined,
+      });
+      if (!reply || reply.toolCalls.length === 0) return;
+
+      conversation.push({ role: 'assistant', content: reply.text, toolCalls: reply.toolCalls });
+      for (const call of reply.toolCalls) {
+        const definition = tools.find((tool) => tool.name === call.name);
+        conversation.push({
+          role: 'tool',
+          toolCallId: call.id,
+          name: call.name,
+          content: JSON.stringify(await runTool(call, definition)),
+        });
+      }
+      // Keep the text of each round apart
+      if (reply.text) yield '\n\n';
+    }
+  }
+
+  /** One request, yielding its tokens; returns the finished reply (null if cancelled) */
+  private async *streamRequest(request: LLMChatRequest) {
+    const pending: string[] = [];
+    let wake: (() => void) | null = null;
+    let finished = false;
+
+    const chat = llmChat(request, (text) => {
+      pending.push(text);
+      wake?.();
+    });
+    const done = chat.result.finally(() => {
+      finished = true;
+      wake?.();
+    });
+    // Surfaced by `await done` below; don't report it as unhandled meanwhile
+    done.catch(() => {});
+
+    try {
+      while (true) {
+        while (pending.length > 0) {
+          yield pending.shift()!;
+        }
+        if (finished) break;
+        await new Promise<void>((resolve) => { wake = resolve; });
+        wake = null;
+      }
+      return await done;
+    } finally {
+      // The caller stopped reading early
+      if (!finished) chat.cancel();
+    }
+  }
+}
+
+/** Run a tool the model called; failures and refusals go back to the model as its result */
+async function runTool(call: LLMToolCall, definition: ToolDefinition | undefined): Promise<unknown> {
+  if (!definition) {
+    return { error: `There is no tool named '${call.name}'` };
+  }
+  let confirmed = false;
+  if (definition.requiresConfirmation) {
+    const args = typeof call.arguments === 'string' ? call.arguments : JSON.stringify(call.arguments, null, 2);
+    confirmed = window.confirm(`Allow ${call.name}?\n\n${args}`);
+    if (!confirmed) {
+      return { error: 'The user declined this tool call' };
+    }
+  }
+  try {
+    const output = await callTool(call.name, call.arguments, confirmed);
+    return output.error ? { error: output.error } : output.result ?? null;
+  } catch (e) {
+    return { error: e instanceof Error ? e.message : String(e) };
+  }
+}
@@ -1,4 +1,4 @@
-import type { SystemInfo } from '../../types';
+import type { SystemInfo, LocaleInfo, DesktopContext } from '../../types';
 
 export const basePrompt = `You are a helpful AI companion in a desktop application. Your primary goal is to provide accurate, thorough, and genuinely useful responses to the user.
 
@@ -25,6 +25,38 @@ RESPONSE QUALITY:
 - Provide actionable information the user can actually use
 - Don't pad responses with filler - be substantive, not verbose`;
 
+const WEEKDAYS = ['Monday', 'Tuesday', 'Wednesday', 'Thursday', 'Friday', 'Saturday', 'Sunday'];
+
+export function getLocalePrompt(localeInfo: LocaleInfo): string {
+  const offsetHours = localeInfo.utcOffsetMinutes / 60;
+  const offset = `UTC${offsetHours >= 0 ? '+' : ''}${offsetHours}`;
+  return `
+USER'S LOCALE:
+- Locale: ${localeInfo.locale}
+- Timezone: ${localeInfo.timezone ? `${localeInfo.timezone} (${offset})` : offset}
+- Clock: ${localeInfo.hourCycle === 'h12' ? '12-hour (AM/PM)' : '24-hour'}
+- Week starts on: ${WEEKDAYS[localeInfo.firstDayOfWeek - 1] ?? 'Monday'}
+When you mention times or dates, use the user's timezone and these conventions.`;
+}
+
+export function getDesktopPrompt(desktop: DesktopContext): string | null {
+  if (!desktop.focusedApp) return null;
+  const title = desktop.focusedTitle ? ` ("${desktop.focusedTitle}")` : '';
+  const workspace = desktop.workspace ? ` on workspace ${desktop.workspace}` : '';
+  return `
+USER'S DESKTOP:
+The user is in ${desktop.focusedApp}${title}${workspace}. Use this as context when it's relevant; don't mention it otherwise.`;
+}
+
+export function getGitPrompt(summary: string): string | null {
+  if (!summary.trim()) return null;
+  return `
+USER'S REPOSITORIES:
+The user asked you to keep an eye on these git repositories:
+${summary.trim()}
+Bring them up when it helps, e.g. work that's been uncommitted for a while or a failing build; don't list them otherwise.`;
+}
+
 export function getCommandExecutionPrompt(systemInfo: SystemInfo | null): string {
   const systemContext = systemInfo
     ? `
@@ -61,10 +93,13 @@ CRITICAL RULES - YOU MUST FOLLOW THESE:
 
 7. Never ask "should I run this?" - the approval UI handles user consent automatically.
 
+8. For commands that need root (installing packages, editing system files, restarting services), use [EXECUTE_PRIVILEGED: command] WITHOUT sudo. The user's system asks for their password in its own dialog. NEVER ask the user to type their password into the chat.
+
 Examples:
 - "run fastfetch" → [EXECUTE: fastfetch]
 - "list my files" → [EXECUTE: ls ~]
 - "what's my disk usage" → [EXECUTE: df -h]
+- "install htop" → [EXECUTE_PRIVILEGED: apt install -y htop] (with the user's package manager)
 - "show me how to list files" → Show the command in a code block (teaching, not doing)
 ${systemContext}`;
 }
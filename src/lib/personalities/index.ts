@@ -1,4 +1,4 @@
-import { basePrompt, getCommandExecutionPrompt } from './base-prompt';
+import { basePrompt, getCommandExecutionPrompt, getDesktopPrompt, getGitPrompt, getLocalePrompt } from './base-prompt';
 import { getDetailPrompt } from './detail-prompts';
 import { naiveGirlfriend } from './definitions/naive-girlfriend';
 import { smartGirlfriend } from './definitions/smart-girlfriend';
@@ -8,7 +8,7 @@ import { lifeCoach } from './definitions/life-coach';
 import { creativePartner } from './definitions/creative-partner';
 import { assistant } from './definitions/assistant';
 import type { Personality, PersonalityId, PersonalitySettings } from './types';
-import type { SystemInfo } from '../../types';
+import type { SystemInfo, LocaleInfo, DesktopContext } from '../../types';
 
 export const personalities: Record<PersonalityId, Personality> = {
   'naive-girlfriend': naiveGirlfriend,
@@ -20,7 +20,13 @@ export const personalities: Record<PersonalityId, Personality> = {
   assistant: assistant,
 };
 
-export function buildSystemPrompt(settings: PersonalitySettings, systemInfo: SystemInfo | null = null): string {
+export function buildSystemPrompt(
+  settings: PersonalitySettings,
+  systemInfo: SystemInfo | null = null,
+  localeInfo: LocaleInfo | null = null,
+  desktop: DesktopContext | null = null,
+  gitSummary = '',
+): string {
   const personality = personalities[settings.selectedPersonality];
 
   let prompt = basePrompt;
@@ -49,6 +55,23 @@ export function buildSystemPrompt(settings: PersonalitySettings, systemInfo: Sys
     }
   }
 
+  // Add the user's timezone and date/time conventions
+  if (localeInfo) {
+    prompt += '\n\n' + getLocalePrompt(localeInfo);
+  }
+
+  // Add the app the user is in, where the compositor tells us
+  const desktopPrompt = desktop && getDesktopPrompt(desktop);
+  if (desktopPrompt) {
+    prompt += '\n\n' + desktopPrompt;
+  }
+
+  // Add the state of the repositories the git watcher follows
+  const gitPrompt = getGitPrompt(gitSummary);
+  if (gitPrompt) {
+    prompt += '\n\n' + gitPrompt;
+  }
+
   // Add command execution capabilities with system context
   prompt += '\n\n' + getCommandExecutionPrompt(systemInfo);
 
@@ -4,7 +4,8 @@
  */
 
 import { invoke } from '@tauri-apps/api/core';
-import type { SystemInfo, CommandOutput } from '../types';
+import { listen } from '@tauri-apps/api/event';
+import type { SystemInfo, SystemMetrics, LocaleInfo, DesktopContext, CommandOutput, PrivilegedCommandOutput, ExecutionLimits, LLMMessage, LLMProviderType } from '../types';
 
 // Check if we're in overlay mode (WebKitGTK)
 export const isOverlayMode = new URLSearchParams(window.location.search).get('overlay') === 'true';
@@ -20,25 +21,121 @@ declare global {
         windowControl?: { postMessage: (msg: { action: 'hide' | 'show' }) => void };
         resizeWindow?: { postMessage: (msg: { action: 'resize'; width: number; height: number }) => void };
         keyboardFocus?: { postMessage: (msg: object) => void };
+        // Screen reader announcements (platform.ts)
+        announce?: { postMessage: (msg: { message: string; priority: AnnouncementPriority }) => void };
         // Command execution handlers (platform.ts)
-        executeCommand?: { postMessage: (msg: { cmd: string; callbackId: string }) => void };
+        executeCommand?: {
+          postMessage: (msg: {
+            cmd: string;
+            callbackId: string;
+            pty?: boolean;
+            stdin?: boolean;
+            confirmed?: boolean;
+            shell?: string;
+            cwd?: string;
+            env?: Record<string, string>;
+          }) => void;
+        };
+        executePrivilegedCommand?: { postMessage: (msg: { cmd: string; confirmed: boolean; callbackId: string }) => void };
+        commandInput?: { postMessage: (msg: { id: string; data: string }) => void };
+        commandStdin?: { postMessage: (msg: { id: string; data: string; eof: boolean }) => void };
+        sessionCreate?: { postMessage: (msg: { id: string; callbackId: string }) => void };
+        sessionExec?: { postMessage: (msg: { id: string; cmd: string; confirmed?: boolean; callbackId: string }) => void };
+        sessionDestroy?: { postMessage: (msg: { id: string; callbackId: string }) => void };
         getSystemInfo?: { postMessage: (msg: { callbackId: string }) => void };
+        getSystemMetrics?: { postMessage: (msg: { callbackId: string }) => void };
+        getLocaleInfo?: { postMessage: (msg: { callbackId: string }) => void };
+        // Focused app from the compositor, Hyprland and Sway only (ChatPanel.tsx)
+        getActiveWindow?: { postMessage: (msg: { callbackId: string }) => void };
         // Quadrant detection handler (App.tsx)
         getQuadrant?: { postMessage: (msg: object) => void };
+        getMonitors?: { postMessage: (msg: { callbackId: string }) => void };
+        // Overlay config.toml (App.tsx)
+        getOverlayConfig?: { postMessage: (msg: { callbackId: string }) => void };
+        // Renderer the overlay picked at startup (getRenderInfo below)
+        getRenderInfo?: { postMessage: (msg: { callbackId: string }) => void };
+        // System color scheme / accent color (useSystemAppearance.ts)
+        getAppearance?: { postMessage: (msg: { callbackId: string }) => void };
+        // Currently shown character, reported for --status (CharacterCanvas.tsx)
+        characterChanged?: { postMessage: (msg: { character: string; characters: { id: string; name: string }[] }) => void };
+        // Character scale, saved with the overlay state (App.tsx)
+        scaleChanged?: { postMessage: (msg: { scale: number }) => void };
+        // Chat panel size at the chat scale, for placing it (App.tsx)
+        chatSize?: { postMessage: (msg: { width: number; height: number }) => void };
+        trayState?: { postMessage: (msg: { thinking?: boolean; unread?: boolean }) => void };
+        // Desktop night light state (useNightLight.ts)
+        getNightLight?: { postMessage: (msg: { callbackId: string }) => void };
+        // Media players over MPRIS (platform.ts)
+        getNowPlaying?: { postMessage: (msg: { callbackId: string }) => void };
+        mediaPlayPause?: { postMessage: (msg: { callbackId: string }) => void };
+        mediaNext?: { postMessage: (msg: { callbackId: string }) => void };
+        mediaPrevious?: { postMessage: (msg: { callbackId: string }) => void };
         // Input region handler for click-through control (App.tsx)
-        setInputRegion?: { postMessage: (msg: { mode: 'character' | 'full'; x?: number; y?: number; width?: number; height?: number }) => void };
+        setInputRegion?: { postMessage: (msg: { mode: 'character' | 'auto' | 'full'; rects?: InputRect[] }) => void };
         // Apply anchoring handler - frontend calls this AFTER CSS updates to prevent flicker (App.tsx)
         applyAnchoring?: { postMessage: (msg: { isRightHalf: boolean; isBottomHalf: boolean; horizontalMargin: number; verticalMargin: number }) => void };
         // Debug logging handler (debug.ts)
         debug?: { postMessage: (msg: { message: string }) => void };
         // Desktop notification handler (platform.ts)
         showNotification?: { postMessage: (msg: { title: string; body: string }) => void };
+        // Native sound playback handler (platform.ts)
+        playSound?: { postMessage: (msg: { name: string; volume: number }) => void };
         // Native file dialog handler (overlay mode only)
         openFileDialog?: { postMessage: (msg: { callbackId: string }) => void };
         // Hotkey enable/disable handler (SettingsModal.tsx)
         setHotkeyEnabled?: { postMessage: (msg: { enabled: boolean }) => void };
+        // Toggle shortcut bound through the desktop portal (SettingsModal.tsx)
+        getGlobalShortcut?: { postMessage: (msg: { callbackId: string }) => void };
         // File save handler (export.ts)
         saveFile?: { postMessage: (msg: { path: string; content: string; callbackId: string }) => void };
+        getAuditLog?: { postMessage: (msg: { offset: number; limit: number; callbackId: string }) => void };
+        // Frontend updates (platform.ts)
+        checkFrontendUpdate?: { postMessage: (msg: { callbackId: string }) => void };
+        applyFrontendUpdate?: { postMessage: (msg: { callbackId: string }) => void };
+        // File reading within allowed folders (platform.ts)
+        readFile?: { postMessage: (msg: { path: string; callbackId: string }) => void };
+        listDir?: { postMessage: (msg: { path: string; callbackId: string }) => void };
+        statPath?: { postMessage: (msg: { path: string; callbackId: string }) => void };
+        watchPath?: { postMessage: (msg: { path: string; callbackId: string }) => void };
+        unwatchPath?: { postMessage: (msg: { watchId: number; callbackId: string }) => void };
+        // Folder access grants (lib/commands/handlers.ts)
+        fileAccess?: { postMessage: (msg: { action: 'list' | 'allow' | 'disallow'; dir?: string; callbackId: string }) => void };
+        // System clipboard (platform.ts)
+        readClipboard?: { postMessage: (msg: { callbackId: string }) => void };
+        writeClipboard?: { postMessage: (msg: { text: string; callbackId: string }) => void };
+        // Screen capture through the screenshot portal (InputArea.tsx)
+        captureScreen?: { postMessage: (msg: { region: boolean; callbackId: string }) => void };
+        // Text-to-speech with lip-sync (speech.ts)
+        speak?: { postMessage: (msg: { text: string; voice?: string; callbackId?: string }) => void };
+        stopSpeaking?: { postMessage: (msg: Record<string, never>) => void };
+        // Speech input, in overlay builds with the `stt` feature (InputArea.tsx)
+        startListening?: { postMessage: (msg: Record<string, never>) => void };
+        stopListening?: { postMessage: (msg: Record<string, never>) => void };
+        // Chat completions made by the overlay (providers/proxy.ts)
+        llmChat?: { postMessage: (msg: { requestId: string; callbackId: string } & LLMChatRequest) => void };
+        llmCancel?: { postMessage: (msg: { requestId: string }) => void };
+        llmSetKey?: { postMessage: (msg: { provider: LLMProviderType; key: string }) => void };
+        llmListModels?: { postMessage: (msg: { callbackId: string }) => void };
+        // Desktop keyring for API keys (store/index.ts)
+        storeSecret?: { postMessage: (msg: { name: string; value: string; callbackId: string }) => void };
+        getSecret?: { postMessage: (msg: { name: string; callbackId: string }) => void };
+        deleteSecret?: { postMessage: (msg: { name: string; callbackId: string }) => void };
+        // Assistant tool registry (platform.ts)
+        tools?: { postMessage: (msg: { action: 'list' | 'call'; name?: string; args?: unknown; confirmed?: boolean; callbackId: string }) => void };
+        // Behavior script hooks (platform.ts)
+        scriptHook?: { postMessage: (msg: { hook: 'on_idle' | 'on_message'; args: unknown[] }) => void };
+        // Interaction events forwarded to companions/subscribers (platform.ts)
+        overlayEvent?: { postMessage: (msg: OverlayEventMessage) => void };
+        // Git repository watcher (platform.ts)
+        gitWatcher?: { postMessage: (msg: { action: 'list' | 'register' | 'unregister' | 'context' | 'commitContext'; path?: string; callbackId: string }) => void };
+        // Conversation history in SQLite (lib/history.ts)
+        history?: { postMessage: (msg: { action: HistoryAction; callbackId: string } & Record<string, unknown>) => void };
+        // Character models added by the user (store/index.ts)
+        models?: { postMessage: (msg: { action: 'list' | 'import' | 'setActive'; path?: string; id?: string; callbackId: string }) => void };
+        // Long-term memory (lib/commands/handlers.ts)
+        memory?: { postMessage: (msg: { action: 'remember' | 'recall' | 'forget' | 'list'; text?: string; k?: number; id?: number; callbackId: string }) => void };
+        // Reminders and timers (lib/commands/handlers.ts)
+        reminders?: { postMessage: (msg: { action: 'list' | 'add' | 'cancel'; text?: string; in?: string; id?: number; callbackId: string }) => void };
       };
     };
   }
@@ -55,19 +152,121 @@ function generateCallbackId(): string {
   return `cb_${Date.now()}_${callbackCounter++}`;
 }
 
+/**
+ * Post a message to an overlay handler and resolve with the value passed to its callback.
+ * Rejects if the overlay doesn't answer within `timeoutMs`.
+ */
+function requestOverlay<T>(
+  handler: string,
+  msg: Record<string, unknown>,
+  timeoutMs: number,
+): Promise<T> {
+  return new Promise((resolve, reject) => {
+    const callbackId = generateCallbackId();
+
+    window.__commandCallbacks![callbackId] = (result: unknown) => {
+      delete window.__commandCallbacks![callbackId];
+      resolve(result as T);
+    };
+
+    setTimeout(() => {
+      if (window.__commandCallbacks![callbackId]) {
+        delete window.__commandCallbacks![callbackId];
+        reject(new Error(`${handler} request timed out`));
+      }
+    }, timeoutMs);
+
+    const handlers = window.webkit?.messageHandlers as Record<string, { postMessage: (msg: unknown) => void } | undefined> | undefined;
+    const target = handlers?.[handler];
+    target?.postMessage({ ...msg, callbackId });
+  });
+}
+
+/**
+ * Command result as sent by the overlay; output above its inline limit is streamed.
+ */
+interface OverlayCommandOutput {
+  stdout?: string;
+  stderr?: string;
+  stdoutUrl?: string;
+  stderrUrl?: string;
+  exit_code: number;
+  error?: string; // Refused by the command policy
+}
+
+export interface ExecuteOptions {
+  limits?: ExecutionLimits; // Native mode only
+  confirmed?: boolean; // The user approved this command (needed unless the policy auto-allows it)
+  shell?: string; // Defaults to the login shell ($SHELL)
+  cwd?: string; // Defaults to the home directory; "~/" is expanded
+  env?: Record<string, string>; // Added to the environment
+}
+
+/**
+ * Fetch a streamed `waifu-transfer://` payload as text.
+ */
+async function fetchTransferText(url: string): Promise<string> {
+  const response = await fetch(url);
+  return response.text();
+}
+
+/**
+ * Fetch a streamed `waifu-transfer://` payload, reporting bytes received as it arrives.
+ */
+async function fetchTransferBlob(
+  url: string,
+  totalBytes: number,
+  onChunk?: (bytesRead: number, totalBytes: number) => void
+): Promise<Blob> {
+  const response = await fetch(url);
+  if (!response.body || !onChunk) {
+    return response.blob();
+  }
+
+  const reader = response.body.getReader();
+  const chunks: Uint8Array[] = [];
+  let bytesRead = 0;
+  for (;;) {
+    const { done, value } = await reader.read();
+    if (done) break;
+    chunks.push(value);
+    bytesRead += value.length;
+    onChunk(bytesRead, totalBytes);
+  }
+  return new Blob(chunks as BlobPart[], { type: response.headers.get('Content-Type') ?? undefined });
+}
+
+/**
+ * Base64-encode a blob without building a giant intermediate string by hand.
+ */
+function blobToBase64(blob: Blob): Promise<string> {
+  return new Promise((resolve, reject) => {
+    const reader = new FileReader();
+    reader.onload = () => {
+      const dataUrl = reader.result as string;
+      resolve(dataUrl.slice(dataUrl.indexOf(',') + 1));
+    };
+    reader.onerror = () => reject(reader.error);
+    reader.readAsDataURL(blob);
+  });
+}
+
 /**
  * Execute a shell command and return the output.
  * Uses Tauri invoke in native mode, WebKit message handlers in overlay mode.
+ * Rejects if the command policy denies the command, or wants confirmation it didn't get.
  */
-export async function executeCommand(cmd: string): Promise<CommandOutput> {
+export async function executeCommand(
+  cmd: string,
+  { limits, confirmed, shell, cwd, env }: ExecuteOptions = {}
+): Promise<CommandOutput> {
   if (isOverlayMode) {
-    return new Promise((resolve, reject) => {
+    const result = await new Promise<OverlayCommandOutput>((resolve, reject) => {
       const callbackId = generateCallbackId();
 
       window.__commandCallbacks![callbackId] = (result: unknown) => {
         delete window.__commandCallbacks![callbackId];
-        const output = result as CommandOutput;
-        resolve(output);
+        resolve(result as OverlayCommandOutput);
       };
 
       // Set timeout for command execution (30 seconds)
@@ -78,11 +277,208 @@ export async function executeCommand(cmd: string): Promise<CommandOutput> {
         }
       }, 30000);
 
-      window.webkit?.messageHandlers?.executeCommand?.postMessage({ cmd, callbackId });
+      window.webkit?.messageHandlers?.executeCommand?.postMessage({ cmd, callbackId, confirmed, shell, cwd, env });
     });
+    if (result.error) throw new Error(result.error);
+
+    // Large output arrives as transfer URLs rather than inline strings
+    return {
+      stdout: result.stdoutUrl ? await fetchTransferText(result.stdoutUrl) : result.stdout ?? '',
+      stderr: result.stderrUrl ? await fetchTransferText(result.stderrUrl) : result.stderr ?? '',
+      exit_code: result.exit_code,
+    };
   } else {
-    return invoke<CommandOutput>('execute_command', { cmd });
+    return invoke<CommandOutput>('execute_command', { cmd, limits, confirmed, shell, cwd, env });
+  }
+}
+
+/**
+ * Run a command as administrator (pkexec on Linux, the authorization dialog on macOS).
+ * The system asks for the password in its own dialog, never in the chat. Only for
+ * commands the user has approved: `confirmed` is required, whatever the command policy says.
+ */
+export async function executePrivilegedCommand(cmd: string, { confirmed }: { confirmed: boolean }): Promise<PrivilegedCommandOutput> {
+  if (isOverlayMode) {
+    // As long as the overlay waits: the user may take a while with the password dialog
+    const result = await requestOverlay<OverlayCommandOutput & { command: string; outcome: PrivilegedCommandOutput['outcome'] }>(
+      'executePrivilegedCommand',
+      { cmd, confirmed },
+      30 * 60 * 1000,
+    );
+    if (result.error) throw new Error(result.error);
+    return {
+      command: result.command,
+      outcome: result.outcome,
+      stdout: result.stdoutUrl ? await fetchTransferText(result.stdoutUrl) : result.stdout ?? '',
+      stderr: result.stderrUrl ? await fetchTransferText(result.stderrUrl) : result.stderr ?? '',
+      exit_code: result.exit_code,
+    };
+  }
+  return invoke<PrivilegedCommandOutput>('execute_privileged_command', { cmd, confirmed });
+}
+
+/**
+ * A command whose stdin stays open (see executeCommandWithStdin).
+ */
+export interface PipedCommand {
+  /** Write to the command's stdin, e.g. "y\n" for a prompt */
+  write: (data: string) => Promise<void>;
+  /** Close stdin so the command sees end of input */
+  close: () => Promise<void>;
+  result: Promise<CommandOutput>;
+}
+
+/**
+ * Execute a shell command with its stdin kept open, for answering prompts of programs that
+ * read plain stdin (apt's y/n, read). Stdin is closed when the command exits or on close().
+ * There is no timeout beyond `limits`.
+ */
+export function executeCommandWithStdin(
+  cmd: string,
+  { limits, confirmed, shell, cwd, env }: ExecuteOptions = {}
+): PipedCommand {
+  const id = generateCallbackId();
+
+  if (isOverlayMode) {
+    const send = async (data: string, eof: boolean) => {
+      window.webkit?.messageHandlers?.commandStdin?.postMessage({ id, data, eof });
+    };
+    const result = new Promise<OverlayCommandOutput>((resolve) => {
+      window.__commandCallbacks![id] = (output: unknown) => {
+        delete window.__commandCallbacks![id];
+        resolve(output as OverlayCommandOutput);
+      };
+      window.webkit?.messageHandlers?.executeCommand?.postMessage({ cmd, callbackId: id, stdin: true, confirmed, shell, cwd, env });
+    }).then(async (output) => {
+      if (output.error) throw new Error(output.error);
+      return {
+        stdout: output.stdoutUrl ? await fetchTransferText(output.stdoutUrl) : output.stdout ?? '',
+        stderr: output.stderrUrl ? await fetchTransferText(output.stderrUrl) : output.stderr ?? '',
+        exit_code: output.exit_code,
+      };
+    });
+    return { write: (data) => send(data, false), close: () => send('', true), result };
   }
+
+  return {
+    write: (data) => invoke<void>('write_command_stdin', { id, data }),
+    close: () => invoke<void>('write_command_stdin', { id, data: '', eof: true }),
+    result: invoke<CommandOutput>('execute_command', { cmd, id, stdin: true, limits, confirmed, shell, cwd, env }),
+  };
+}
+
+/**
+ * A command running on a pseudo-terminal (see executeInteractiveCommand).
+ */
+export interface InteractiveCommand {
+  /** Type into the command's terminal, e.g. an answer followed by "\n" */
+  write: (data: string) => Promise<void>;
+  /** Resolves when the command exits; stdout holds the whole terminal transcript */
+  result: Promise<CommandOutput>;
+}
+
+/**
+ * Run a command on a pseudo-terminal so interactive programs (sudo, ssh, prompts) work.
+ * Output is passed to `onOutput` as it arrives. There is no timeout: the command runs
+ * until it exits.
+ */
+export function executeInteractiveCommand(
+  cmd: string,
+  onOutput: (data: string) => void,
+  { confirmed, shell, cwd, env }: Omit<ExecuteOptions, 'limits'> = {}
+): InteractiveCommand {
+  const id = generateCallbackId();
+
+  if (isOverlayMode) {
+    const handleOutput = (e: Event) => {
+      const detail = (e as CustomEvent<{ id: string; data: string }>).detail;
+      if (detail.id === id) onOutput(detail.data);
+    };
+    window.addEventListener('commandOutput', handleOutput);
+
+    const result = new Promise<OverlayCommandOutput>((resolve) => {
+      window.__commandCallbacks![id] = (output: unknown) => {
+        delete window.__commandCallbacks![id];
+        window.removeEventListener('commandOutput', handleOutput);
+        resolve(output as OverlayCommandOutput);
+      };
+      window.webkit?.messageHandlers?.executeCommand?.postMessage({ cmd, callbackId: id, pty: true, confirmed, shell, cwd, env });
+    }).then(async (output) => {
+      if (output.error) throw new Error(output.error);
+      return {
+        stdout: output.stdoutUrl ? await fetchTransferText(output.stdoutUrl) : output.stdout ?? '',
+        stderr: output.stderr ?? '',
+        exit_code: output.exit_code,
+      };
+    });
+
+    return {
+      write: async (data) => {
+        window.webkit?.messageHandlers?.commandInput?.postMessage({ id, data });
+      },
+      result,
+    };
+  }
+
+  const unlisten = listen<{ id: string; data: string }>('command-output', (event) => {
+    if (event.payload.id === id) onOutput(event.payload.data);
+  });
+  const result = invoke<CommandOutput>('execute_command', { cmd, id, pty: true, confirmed, shell, cwd, env }).finally(() => {
+    unlisten.then((stop) => stop());
+  });
+  return {
+    write: (data) => invoke<void>('write_command_input', { id, data }),
+    result,
+  };
+}
+
+/**
+ * A long-lived shell: commands run through it share the working directory, exported
+ * variables and activated virtualenvs, unlike executeCommand which starts a fresh shell.
+ */
+export interface ShellSession {
+  /** Run a command in the session; rejects if the policy refuses it or the shell has exited */
+  exec: (cmd: string, options?: Pick<ExecuteOptions, 'confirmed'>) => Promise<CommandOutput>;
+  /** End the shell and everything it started */
+  destroy: () => Promise<void>;
+}
+
+export async function createShellSession(): Promise<ShellSession> {
+  const id = generateCallbackId();
+
+  if (isOverlayMode) {
+    const created = await requestOverlay<{ error?: string }>('sessionCreate', { id }, 5000);
+    if (created.error) throw new Error(created.error);
+    return {
+      exec: async (cmd, { confirmed } = {}) => {
+        // No timeout: commands run until they finish or the session is destroyed
+        const output = await new Promise<OverlayCommandOutput>((resolve) => {
+          const callbackId = generateCallbackId();
+          window.__commandCallbacks![callbackId] = (result: unknown) => {
+            delete window.__commandCallbacks![callbackId];
+            resolve(result as OverlayCommandOutput);
+          };
+          window.webkit?.messageHandlers?.sessionExec?.postMessage({ id, cmd, confirmed, callbackId });
+        });
+        if (output.error) throw new Error(output.error);
+        return {
+          stdout: output.stdoutUrl ? await fetchTransferText(output.stdoutUrl) : output.stdout ?? '',
+          stderr: output.stderrUrl ? await fetchTransferText(output.stderrUrl) : output.stderr ?? '',
+          exit_code: output.exit_code,
+        };
+      },
+      destroy: async () => {
+        const destroyed = await requestOverlay<{ error?: string }>('sessionDestroy', { id }, 5000);
+        if (destroyed.error) throw new Error(destroyed.error);
+      },
+    };
+  }
+
+  await invoke<void>('session_create', { id });
+  return {
+    exec: (cmd, { confirmed } = {}) => invoke<CommandOutput>('session_exec', { id, cmd, confirmed }),
+    destroy: () => invoke<void>('session_destroy', { id }),
+  };
 }
 
 /**
@@ -115,9 +511,54 @@ export async function getSystemInfo(): Promise<SystemInfo> {
   }
 }
 
+/**
+ * CPU load, memory, disk space, network traffic, battery and temperature.
+ * The first call takes a moment longer: CPU load needs two samples.
+ */
+export async function getSystemMetrics(): Promise<SystemMetrics> {
+  if (isOverlayMode) {
+    return requestOverlay<SystemMetrics>('getSystemMetrics', {}, 5000);
+  }
+  return invoke<SystemMetrics>('get_system_metrics');
+}
+
+/**
+ * Get the user's locale, timezone, clock format and first day of week.
+ * In overlay mode these come from the system; elsewhere from the browser's Intl settings.
+ */
+export async function getLocaleInfo(): Promise<LocaleInfo> {
+  if (isOverlayMode) {
+    return requestOverlay<LocaleInfo>('getLocaleInfo', {}, 5000);
+  }
+  const options = Intl.DateTimeFormat().resolvedOptions();
+  const hour12 = new Intl.DateTimeFormat(options.locale, { hour: 'numeric' }).resolvedOptions().hour12;
+  // weekInfo isn't in every engine's typings yet
+  const weekInfo = (new Intl.Locale(options.locale) as { weekInfo?: { firstDay: number } }).weekInfo;
+  return {
+    locale: options.locale,
+    uiLocale: navigator.language,
+    timezone: options.timeZone ?? null,
+    utcOffsetMinutes: -new Date().getTimezoneOffset(),
+    hourCycle: hour12 ? 'h12' : 'h23',
+    firstDayOfWeek: weekInfo?.firstDay ?? 1,
+  };
+}
+
+/**
+ * Ask the compositor which window the user is in right now, limited to what config.toml
+ * shares. Null outside overlay mode and on compositors the overlay can't ask.
+ */
+export async function getActiveWindow(): Promise<DesktopContext | null> {
+  if (!isOverlayMode) {
+    return null;
+  }
+  return requestOverlay<DesktopContext | null>('getActiveWindow', {}, 2000);
+}
+
 /**
  * Check if the current session is running on Wayland.
- * In overlay mode, this always returns true (overlay only runs on Wayland).
+ * In overlay mode, this always returns true (overlay only runs on Wayland); on X11 the
+ * Tauri window stands in for it.
  */
 export async function checkWayland(): Promise<boolean> {
   if (isOverlayMode) {
@@ -126,23 +567,45 @@ export async function checkWayland(): Promise<boolean> {
   return invoke<boolean>('check_wayland');
 }
 
+/** An area of the window that takes clicks, in CSS pixels */
+export interface InputRect {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
 /**
- * Set the input region for click-through control (overlay mode only).
- * This defines the area where the overlay captures mouse input.
+ * Set the input region for click-through control.
+ * This defines the areas where the overlay (or, on X11, the Tauri window) captures mouse
+ * input, e.g. the character and the chat panel; clicks elsewhere reach the windows below.
+ * No areas make the whole window click-through.
  */
-export async function setInputRegion(x: number, y: number, width: number, height: number): Promise<void> {
+export async function setInputRegions(rects: InputRect[]): Promise<void> {
   if (isOverlayMode) {
-    window.webkit?.messageHandlers?.setInputRegion?.postMessage({ mode: 'character', x, y, width, height });
+    window.webkit?.messageHandlers?.setInputRegion?.postMessage({ mode: 'character', rects });
+  } else {
+    // A no-op outside X11 sessions
+    await invoke<void>('set_input_region', { rects }).catch(() => {});
   }
 }
 
 /**
- * Clear the input region to capture all input (overlay mode only).
- * This makes the entire overlay window interactive.
+ * Set the input region to a single rectangle (see setInputRegions).
+ */
+export async function setInputRegion(x: number, y: number, width: number, height: number): Promise<void> {
+  await setInputRegions([{ x, y, width, height }]);
+}
+
+/**
+ * Clear the input region to capture all input.
+ * This makes the entire window interactive.
  */
 export async function clearInputRegion(): Promise<void> {
   if (isOverlayMode) {
     window.webkit?.messageHandlers?.setInputRegion?.postMessage({ mode: 'full' });
+  } else {
+    await invoke<void>('clear_input_region').catch(() => {});
   }
 }
 
@@ -160,6 +623,24 @@ export function showDesktopNotification(title: string, body: string): void {
   }
 }
 
+/** Chimes bundled in public/sounds and with the overlay */
+export type Chime = 'message' | 'notification' | 'error' | 'reminder';
+
+/**
+ * Play a chime, or in overlay mode also a sound from ~/.local/share/desktop-waifu/sounds
+ * or a path. The overlay plays it natively, since WebKit audio on a hidden layer surface is
+ * muted, and skips it while notifications are muted or in meeting mode.
+ */
+export function playSound(name: Chime | string, volume = 1): void {
+  if (isOverlayMode) {
+    window.webkit?.messageHandlers?.playSound?.postMessage({ name, volume });
+    return;
+  }
+  const audio = new Audio(`/sounds/${name}.wav`);
+  audio.volume = Math.min(Math.max(volume, 0), 1);
+  audio.play().catch((e) => console.warn('Could not play sound:', name, e));
+}
+
 /**
  * Check if the window is currently focused (overlay mode only).
  * Reads from a global variable set by Rust when window focus changes.
@@ -187,22 +668,49 @@ export interface FileDialogResult {
   filename: string;  // Original filename
 }
 
+// Files at least this large report read progress
+const PROGRESS_THRESHOLD_BYTES = 2 * 1024 * 1024;
+
+/**
+ * Read progress for a large file selected in the native file dialog.
+ */
+export interface FileDialogProgress {
+  filename: string;
+  index: number;       // Position of the file in the selection
+  count: number;       // Number of files selected
+  bytesRead: number;
+  totalBytes: number;
+}
+
+/**
+ * A file selected in the native dialog, before its contents are fetched.
+ */
+interface SelectedFile {
+  url: string;       // waifu-transfer:// URL streaming the file
+  mimeType: string;
+  filename: string;
+  size: number;
+}
+
 /**
  * Open a native file dialog for selecting images (overlay mode only).
  * Returns null if not in overlay mode or if dialog was cancelled.
  * Uses GTK4's FileDialog API which properly integrates with Wayland.
+ * Files are streamed from the overlay; `onProgress` reports how far along large ones are.
  */
-export async function openFileDialog(): Promise<FileDialogResult[] | null> {
+export async function openFileDialog(
+  onProgress?: (progress: FileDialogProgress) => void
+): Promise<FileDialogResult[] | null> {
   if (!isOverlayMode) {
     return null;
   }
 
-  return new Promise((resolve) => {
+  const selected = await new Promise<SelectedFile[] | null>((resolve) => {
     const callbackId = generateCallbackId();
 
     window.__commandCallbacks![callbackId] = (result: unknown) => {
       delete window.__commandCallbacks![callbackId];
-      resolve(result as FileDialogResult[] | null);
+      resolve(result as SelectedFile[] | null);
     };
 
     // Set timeout (30 seconds - file selection can take a while)
@@ -215,6 +723,30 @@ export async function openFileDialog(): Promise<FileDialogResult[] | null> {
 
     window.webkit?.messageHandlers?.openFileDialog?.postMessage({ callbackId });
   });
+
+  if (!selected) {
+    return null;
+  }
+
+  const results: FileDialogResult[] = [];
+  for (const [index, file] of selected.entries()) {
+    const reportProgress = file.size >= PROGRESS_THRESHOLD_BYTES && onProgress
+      ? (bytesRead: number, totalBytes: number) => onProgress({
+          filename: file.filename,
+          index,
+          count: selected.length,
+          bytesRead,
+          totalBytes,
+        })
+      : undefined;
+    try {
+      const blob = await fetchTransferBlob(file.url, file.size, reportProgress);
+      results.push({ data: await blobToBase64(blob), mimeType: file.mimeType, filename: file.filename });
+    } catch (e) {
+      console.error(`Failed to read ${file.filename}:`, e);
+    }
+  }
+  return results;
 }
 
 /**
@@ -263,3 +795,1231 @@ export async function saveFile(path: string, content: string): Promise<SaveFileR
     return { success: false, error: 'Not implemented' };
   }
 }
+
+/**
+ * One thing the overlay did on the user's behalf (see getAuditLog).
+ */
+export interface AuditEntry {
+  time: number; // Unix time in milliseconds
+  kind: 'command' | 'privilegedCommand' | 'sessionCommand' | 'fileWrite' | 'notification' | 'toolCall';
+  subject: string; // Command, path, notification title or tool call
+  outcome: 'ok' | 'failed' | 'refused';
+  detail?: string; // Exit code, error or reason it was refused
+}
+
+/**
+ * Commands run, files written, notifications shown and tools called, newest first,
+ * skipping the `offset` newest (overlay mode only; empty elsewhere).
+ */
+export async function getAuditLog(offset = 0, limit = 100): Promise<AuditEntry[]> {
+  if (!isOverlayMode) {
+    return [];
+  }
+  return requestOverlay<AuditEntry[]>('getAuditLog', { offset, limit }, 10000);
+}
+
+/**
+ * What a check for a newer frontend found (see checkFrontendUpdate).
+ */
+export interface FrontendUpdate {
+  current: string; // Version being served; empty for builds without a manifest
+  available: string | null; // Newer version for this overlay, if any
+  files: number; // Files to download for it
+  bytes: number;
+}
+
+/**
+ * Compare the frontend being served with the release at `[frontend] update_url`
+ * (overlay mode only; null elsewhere).
+ */
+export async function checkFrontendUpdate(): Promise<FrontendUpdate | null> {
+  if (!isOverlayMode) {
+    return null;
+  }
+  const result = await requestOverlay<FrontendUpdate & { error?: string }>('checkFrontendUpdate', {}, 30000);
+  if (result.error) {
+    throw new Error(result.error);
+  }
+  return result;
+}
+
+/**
+ * Download and install the newer frontend. On success the page reloads from the new
+ * files shortly after this resolves with the installed version.
+ */
+export async function applyFrontendUpdate(): Promise<string> {
+  if (!isOverlayMode) {
+    throw new Error('Frontend updates need the overlay');
+  }
+  const result = await requestOverlay<{ version?: string; error?: string }>('applyFrontendUpdate', {}, 600000);
+  if (result.error) {
+    throw new Error(result.error);
+  }
+  return result.version ?? '';
+}
+
+/**
+ * Text on the system clipboard, or null if it holds none.
+ * Goes through the overlay (GDK) or Tauri, since the webview's clipboard API only works
+ * inside a user gesture; falls back to it when neither is available.
+ */
+export async function readClipboard(): Promise<string | null> {
+  if (isOverlayMode) {
+    const result = await requestOverlay<{ text?: string | null; error?: string }>('readClipboard', {}, 5000);
+    if (result.error) {
+      throw new Error(result.error);
+    }
+    return result.text ?? null;
+  }
+  try {
+    return await invoke<string | null>('read_clipboard');
+  } catch {
+    return navigator.clipboard.readText();
+  }
+}
+
+/**
+ * Put text on the system clipboard (see readClipboard).
+ */
+export async function writeClipboard(text: string): Promise<void> {
+  if (isOverlayMode) {
+    const result = await requestOverlay<{ success?: boolean; error?: string }>('writeClipboard', { text }, 5000);
+    if (result.error) {
+      throw new Error(result.error);
+    }
+    return;
+  }
+  try {
+    await invoke<void>('write_clipboard', { text });
+  } catch {
+    await navigator.clipboard.writeText(text);
+  }
+}
+
+/**
+ * A screenshot as returned by captureScreen.
+ */
+export interface ScreenCapture {
+  mimeType: string;
+  data: string;  // Base64-encoded image
+}
+
+/**
+ * Capture the screen through the desktop's screenshot portal (overlay mode only).
+ * With `region` the user picks an area in the compositor's own UI.
+ * Resolves with null if the user cancels or outside overlay mode.
+ */
+export async function captureScreen(region = false): Promise<ScreenCapture | null> {
+  if (!isOverlayMode) {
+    return null;
+  }
+  // Long timeout: the portal may ask for permission, and picking a region takes a while
+  const result = await requestOverlay<Partial<ScreenCapture> & { cancelled?: boolean; error?: string }>(
+    'captureScreen',
+    { region },
+    120000,
+  );
+  if (result.error) {
+    throw new Error(result.error);
+  }
+  if (result.cancelled || !result.mimeType || !result.data) {
+    return null;
+  }
+  return { mimeType: result.mimeType, data: result.data };
+}
+
+/**
+ * How a speak request ended.
+ */
+export interface SpeechResult {
+  done?: boolean;
+  interrupted?: boolean;  // Stopped, or replaced by a newer speak
+  error?: string;
+}
+
+/**
+ * Speak text with the overlay's TTS engine (overlay mode only), cutting off any speech in
+ * progress. `voice` overrides the one in config.toml. Resolves once speech ends; the
+ * character's mouth follows along through `setTalking` and `speechVisemes` events.
+ */
+export async function speak(text: string, voice?: string): Promise<SpeechResult> {
+  if (!isOverlayMode) {
+    return { error: 'Text-to-speech is only available in overlay mode' };
+  }
+  // No timeout: a long reply takes minutes to read out
+  return new Promise((resolve) => {
+    const callbackId = generateCallbackId();
+    window.__commandCallbacks![callbackId] = (result: unknown) => {
+      delete window.__commandCallbacks![callbackId];
+      resolve(result as SpeechResult);
+    };
+    window.webkit?.messageHandlers?.speak?.postMessage({ text, voice, callbackId });
+  });
+}
+
+/**
+ * Stop speaking (overlay mode only).
+ */
+export function stopSpeaking(): void {
+  if (isOverlayMode) {
+    window.webkit?.messageHandlers?.stopSpeaking?.postMessage({});
+  }
+}
+
+/**
+ * Whether the overlay was built with speech input (the `stt` feature).
+ */
+export function isSpeechInputAvailable(): boolean {
+  return isOverlayMode && !!window.webkit?.messageHandlers?.startListening;
+}
+
+/**
+ * Start transcribing the microphone (see isSpeechInputAvailable). Transcripts arrive as
+ * `transcript` window events ({ text, final }); `listeningChanged` ({ listening, error })
+ * reports when recording starts and stops.
+ */
+export function startListening(): void {
+  window.webkit?.messageHandlers?.startListening?.postMessage({});
+}
+
+/**
+ * Stop recording; what was said so far still arrives as a final transcript.
+ */
+export function stopListening(): void {
+  window.webkit?.messageHandlers?.stopListening?.postMessage({});
+}
+
+/**
+ * A tool call in an LLM reply; `arguments` is a string if the model's weren't valid JSON.
+ */
+export interface LLMToolCall {
+  id: string;
+  name: string;
+  arguments: unknown;
+}
+
+/**
+ * A message in a native chat request: the usual ones, assistant turns that called tools,
+ * and the results of those calls.
+ */
+export type LLMChatMessage =
+  | (LLMMessage & { toolCalls?: LLMToolCall[] })
+  | { role: 'tool'; content: string; toolCallId: string; name: string };
+
+/**
+ * A chat completion for the overlay (or Tauri) to make; the API key is the one last
+ * passed to setLlmKey for the provider.
+ */
+export interface LLMChatRequest {
+  provider: LLMProviderType;
+  model: string;
+  messages: LLMChatMessage[];
+  maxTokens?: number;
+  temperature?: number;
+  tools?: ToolDefinition[];           // offered to the model (overlay only)
+}
+
+/**
+ * A finished reply: its text and the tools it asks to call.
+ */
+export interface LLMReply {
+  text: string;
+  toolCalls: LLMToolCall[];
+}
+
+/**
+ * A chat completion in progress (see llmChat).
+ */
+export interface LLMChat {
+  /** Resolves with the whole reply, or null if cancelled; rejects if the request failed */
+  result: Promise<LLMReply | null>;
+  cancel: () => void;
+}
+
+/**
+ * Run a chat completion natively, passing the reply to `onToken` as it streams in.
+ * Failed requests are retried there before `result` rejects.
+ */
+export function llmChat(request: LLMChatRequest, onToken: (text: string) => void): LLMChat {
+  const requestId = generateCallbackId();
+
+  if (isOverlayMode) {
+    const handleToken = (e: Event) => {
+      const detail = (e as CustomEvent<{ requestId: string; text: string }>).detail;
+      if (detail.requestId === requestId) onToken(detail.text);
+    };
+    window.addEventListener('llmToken', handleToken);
+
+    // No timeout: long replies stream for minutes, and the overlay always answers
+    type Output = Partial<LLMReply> & { cancelled?: boolean; error?: string };
+    const result = new Promise<Output>((resolve) => {
+      const callbackId = generateCallbackId();
+      window.__commandCallbacks![callbackId] = (output: unknown) => {
+        delete window.__commandCallbacks![callbackId];
+        window.removeEventListener('llmToken', handleToken);
+        resolve(output as Output);
+      };
+      window.webkit?.messageHandlers?.llmChat?.postMessage({ ...request, requestId, callbackId });
+    }).then((output) => {
+      if (output.error) throw new Error(output.error);
+      return output.cancelled ? null : { text: output.text ?? '', toolCalls: output.toolCalls ?? [] };
+    });
+
+    return {
+      result,
+      cancel: () => window.webkit?.messageHandlers?.llmCancel?.postMessage({ requestId }),
+    };
+  }
+
+  const unlisten = listen<{ requestId: string; text: string }>('llm-token', (event) => {
+    if (event.payload.requestId === requestId) onToken(event.payload.text);
+  });
+  // Tauri doesn't do tool calls; its reply is just the text
+  const result = invoke<string | null>('llm_chat', { requestId, request })
+    .then((text) => (text === null ? null : { text, toolCalls: [] }))
+    .finally(() => {
+      unlisten.then((stop) => stop());
+    });
+  return {
+    result,
+    cancel: () => {
+      invoke<void>('llm_cancel', { requestId }).catch(() => {});
+    },
+  };
+}
+
+/**
+ * Hand the API key for `provider` to the overlay (or Tauri), which makes the requests.
+ * An empty key clears it.
+ */
+export function setLlmKey(provider: LLMProviderType, key: string): void {
+  if (isOverlayMode) {
+    window.webkit?.messageHandlers?.llmSetKey?.postMessage({ provider, key });
+    return;
+  }
+  invoke<void>('llm_set_key', { provider, key }).catch(() => {
+    // Not running under Tauri: the browser SDKs take the key with each request
+  });
+}
+
+/**
+ * Save a secret (an API key) in the desktop keyring.
+ * Rejects if there is no keyring (no Secret Service running) or outside overlay/Tauri.
+ */
+export async function storeSecret(name: string, value: string): Promise<void> {
+  if (isOverlayMode) {
+    // Long timeout: the keyring may ask the user to unlock it
+    const result = await requestOverlay<{ error?: string }>('storeSecret', { name, value }, 60000);
+    if (result.error) {
+      throw new Error(result.error);
+    }
+    return;
+  }
+  await invoke<void>('store_secret', { name, value });
+}
+
+/**
+ * A secret from the desktop keyring, or null if it isn't set (see storeSecret).
+ */
+export async function getSecret(name: string): Promise<string | null> {
+  if (isOverlayMode) {
+    const result = await requestOverlay<{ value?: string | null; error?: string }>('getSecret', { name }, 60000);
+    if (result.error) {
+      throw new Error(result.error);
+    }
+    return result.value ?? null;
+  }
+  return invoke<string | null>('get_secret', { name });
+}
+
+/**
+ * Remove a secret from the desktop keyring (see storeSecret).
+ */
+export async function deleteSecret(name: string): Promise<void> {
+  if (isOverlayMode) {
+    const result = await requestOverlay<{ error?: string }>('deleteSecret', { name }, 60000);
+    if (result.error) {
+      throw new Error(result.error);
+    }
+    return;
+  }
+  await invoke<void>('delete_secret', { name });
+}
+
+/**
+ * A model server running on this machine and the models it has (see listLocalModels).
+ */
+export interface LocalModels {
+  url: string;
+  models: string[];
+}
+
+// Ollama's OpenAI-compatible API, for the browser fallback
+export const OLLAMA_URL = 'http://127.0.0.1:11434/v1';
+
+/**
+ * Find the local model server (Ollama or llama.cpp's server) and list its models.
+ * Rejects if none is running.
+ */
+export async function listLocalModels(): Promise<LocalModels> {
+  if (isOverlayMode) {
+    const result = await requestOverlay<Partial<LocalModels> & { error?: string }>('llmListModels', {}, 10000);
+    if (result.error) {
+      throw new Error(result.error);
+    }
+    return { url: result.url ?? '', models: result.models ?? [] };
+  }
+  try {
+    return await invoke<LocalModels>('llm_list_models');
+  } catch {
+    // Not running under Tauri: ask Ollama directly (it allows localhost origins)
+    const response = await fetch(`${OLLAMA_URL}/models`);
+    const body = (await response.json()) as { data?: { id: string }[] };
+    return { url: OLLAMA_URL, models: (body.data ?? []).map((model) => model.id) };
+  }
+}
+
+/**
+ * A saved conversation (see listConversations). Times are milliseconds since the epoch.
+ */
+export interface Conversation {
+  id: number;
+  title: string;
+  createdAt: number;
+  updatedAt: number;
+  messageCount: number;
+}
+
+/**
+ * A message as saved in the history database, with images as base64 (no prefix).
+ */
+export interface StoredMessage {
+  id: string;
+  role: 'user' | 'assistant';
+  content: string;
+  timestamp: number;
+  attachments: StoredAttachment[];
+}
+
+export interface StoredAttachment {
+  id: string;
+  mimeType: string;
+  data: string;
+}
+
+/**
+ * A message matching a history search; `snippet` wraps matches in <mark>.
+ */
+export interface HistorySearchHit {
+  conversationId: number;
+  conversationTitle: string;
+  messageId: string;
+  role: 'user' | 'assistant';
+  snippet: string;
+  timestamp: number;
+}
+
+type HistoryAction =
+  | 'listConversations' | 'createConversation' | 'renameConversation' | 'deleteConversation'
+  | 'getMessages' | 'saveMessage' | 'updateMessage' | 'deleteMessages'
+  | 'addAttachment' | 'deleteAttachment' | 'search';
+
+async function historyRequest<T>(action: HistoryAction, msg: Record<string, unknown> = {}): Promise<T> {
+  if (!isOverlayMode) {
+    throw new Error('Conversation history is only available in the overlay');
+  }
+  const result = await requestOverlay<T | { error: string }>('history', { action, ...msg }, 10000);
+  if (result && typeof result === 'object' && 'error' in result) {
+    throw new Error(result.error);
+  }
+  return result as T;
+}
+
+/**
+ * Saved conversations, most recently active first (overlay mode only).
+ */
+export function listConversations(): Promise<Conversation[]> {
+  return historyRequest('listConversations');
+}
+
+export function createConversation(title: string): Promise<Conversation> {
+  return historyRequest('createConversation', { title });
+}
+
+export async function renameConversation(id: number, title: string): Promise<boolean> {
+  const result = await historyRequest<{ found: boolean }>('renameConversation', { id, title });
+  return result.found;
+}
+
+/**
+ * Delete a conversation with all its messages and attachments.
+ */
+export async function deleteConversation(id: number): Promise<boolean> {
+  const result = await historyRequest<{ found: boolean }>('deleteConversation', { id });
+  return result.found;
+}
+
+export function getConversationMessages(id: number): Promise<StoredMessage[]> {
+  return historyRequest('getMessages', { id });
+}
+
+/**
+ * Save a message to a conversation. Saving a message id again replaces its content.
+ */
+export async function saveMessage(conversationId: number, message: StoredMessage): Promise<void> {
+  await historyRequest('saveMessage', { id: conversationId, message });
+}
+
+export async function updateStoredMessage(messageId: string, content: string): Promise<boolean> {
+  const result = await historyRequest<{ found: boolean }>('updateMessage', { messageId, content });
+  return result.found;
+}
+
+export async function deleteStoredMessages(messageIds: string[]): Promise<number> {
+  const result = await historyRequest<{ deleted: number }>('deleteMessages', { messageIds });
+  return result.deleted;
+}
+
+export async function addAttachment(messageId: string, attachment: StoredAttachment): Promise<void> {
+  await historyRequest('addAttachment', { messageId, attachment });
+}
+
+export async function deleteAttachment(attachmentId: string): Promise<boolean> {
+  const result = await historyRequest<{ found: boolean }>('deleteAttachment', { attachmentId });
+  return result.found;
+}
+
+/**
+ * Full-text search over all saved messages, best matches first.
+ */
+export function searchHistory(query: string, limit?: number): Promise<HistorySearchHit[]> {
+  return historyRequest('search', { query, limit });
+}
+
+/**
+ * A fact about the user kept across conversations. `relevance` (0 to 1) is set on
+ * recallMemories results.
+ */
+export interface Memory {
+  id: number;
+  text: string;
+  createdAt: number;
+  relevance?: number;
+}
+
+async function memoryRequest<T>(msg: Record<string, unknown>): Promise<T> {
+  if (!isOverlayMode) {
+    throw new Error('Memory is only available in the overlay');
+  }
+  // Embedding may wait on a local model server or the OpenAI API
+  const result = await requestOverlay<T | { error: string }>('memory', msg, 30000);
+  if (result && typeof result === 'object' && 'error' in result) {
+    throw new Error(result.error);
+  }
+  return result as T;
+}
+
+/**
+ * Remember a fact about the user (overlay mode only). Returns the existing memory if the
+ * fact is already known. Relevant memories are added to chat requests by the overlay.
+ */
+export function remember(text: string): Promise<Memory> {
+  return memoryRequest({ action: 'remember', text });
+}
+
+/**
+ * The k memories most relevant to a query, best first.
+ */
+export function recallMemories(query: string, k = 5): Promise<Memory[]> {
+  return memoryRequest({ action: 'recall', text: query, k });
+}
+
+export async function forgetMemory(id: number): Promise<boolean> {
+  const result = await memoryRequest<{ found: boolean }>({ action: 'forget', id });
+  return result.found;
+}
+
+export function listMemories(): Promise<Memory[]> {
+  return memoryRequest({ action: 'list' });
+}
+
+/**
+ * Status of a git repository watched by the overlay.
+ */
+export interface RepoStatus {
+  path: string;
+  name: string;
+  branch: string | null;
+  upstream: string | null;
+  ahead: number;
+  behind: number;
+  changedFiles: number;
+  untrackedFiles: number;
+  dirtySince: number | null;   // Unix seconds of the oldest uncommitted change
+  lastCommitAt: number | null; // Unix seconds of the HEAD commit
+  ci: { workflow: string; status: string; conclusion: string | null; url: string } | null;
+}
+
+/**
+ * Diff and history used to draft a commit message.
+ */
+export interface CommitContext {
+  path: string;
+  staged: boolean;
+  stat: string;
+  diff: string;
+  truncated: boolean;
+  recentMessages: string[];
+}
+
+/**
+ * Register a repository with the git watcher (overlay mode only).
+ * Status updates afterwards arrive as `gitStatusChange` window events.
+ */
+export async function registerGitRepo(path: string): Promise<RepoStatus | { error: string }> {
+  if (!isOverlayMode) {
+    return { error: 'Git watcher is only available in overlay mode' };
+  }
+  return requestOverlay('gitWatcher', { action: 'register', path }, 30000);
+}
+
+/**
+ * Stop watching a repository (overlay mode only).
+ */
+export async function unregisterGitRepo(path: string): Promise<boolean> {
+  if (!isOverlayMode) {
+    return false;
+  }
+  const result = await requestOverlay<{ removed: boolean }>('gitWatcher', { action: 'unregister', path }, 5000);
+  return result.removed;
+}
+
+/**
+ * Get the last known status of all watched repositories (overlay mode only).
+ */
+export async function listGitRepos(): Promise<RepoStatus[]> {
+  if (!isOverlayMode) {
+    return [];
+  }
+  return requestOverlay('gitWatcher', { action: 'list' }, 5000);
+}
+
+/**
+ * Get a one-line-per-repo summary for the assistant's context (overlay mode only).
+ */
+export async function getGitContextSummary(): Promise<string> {
+  if (!isOverlayMode) {
+    return '';
+  }
+  const result = await requestOverlay<{ summary: string }>('gitWatcher', { action: 'context' }, 5000);
+  return result.summary;
+}
+
+/**
+ * Get the staged (or working tree) diff plus recent commit subjects for commit message drafting.
+ */
+export async function getCommitContext(path: string): Promise<CommitContext | { error: string }> {
+  if (!isOverlayMode) {
+    return { error: 'Git watcher is only available in overlay mode' };
+  }
+  return requestOverlay('gitWatcher', { action: 'commitContext', path }, 30000);
+}
+
+/**
+ * Tool exposed by the overlay's tool registry (built-in or plugin-provided).
+ */
+export interface ToolDefinition {
+  name: string;
+  description: string;
+  parameters: Record<string, unknown>; // JSON schema for the arguments
+  source: string;                      // e.g. "builtin", "plugin:weather", "manifest:net.toml"
+  requiresConfirmation: boolean;       // call must pass confirmed=true after asking the user
+}
+
+/**
+ * List tools registered in the overlay (overlay mode only).
+ */
+export async function listTools(): Promise<ToolDefinition[]> {
+  if (!isOverlayMode) {
+    return [];
+  }
+  return requestOverlay('tools', { action: 'list' }, 5000);
+}
+
+/**
+ * Call a registered tool by name with JSON arguments (overlay mode only).
+ */
+export async function callTool(
+  name: string,
+  args: unknown,
+  confirmed = false,
+): Promise<{ result?: unknown; error?: string; confirmationRequired?: boolean }> {
+  if (!isOverlayMode) {
+    return { error: 'Tools are only available in overlay mode' };
+  }
+  // Longer than the slowest built-in tool (run_shell's two minutes)
+  return requestOverlay('tools', { action: 'call', name, args, confirmed }, 150000);
+}
+
+/**
+ * Send a message to a plugin-provided handler ("<plugin>.<handler>") and wait for its reply.
+ */
+export async function callPluginHandler(plugin: string, handler: string, payload: Record<string, unknown> = {}): Promise<{ result?: unknown; error?: string }> {
+  if (!isOverlayMode) {
+    return { error: 'Plugins are only available in overlay mode' };
+  }
+  return requestOverlay(`${plugin}.${handler}`, payload, 60000);
+}
+
+export type AnnouncementPriority = 'low' | 'medium' | 'high';
+
+/**
+ * Have the screen reader speak a message (overlay mode only).
+ * Used for new character messages and state changes the user can't see.
+ */
+export function announce(message: string, priority: AnnouncementPriority = 'medium'): void {
+  if (isOverlayMode && message.trim()) {
+    window.webkit?.messageHandlers?.announce?.postMessage({ message, priority });
+  }
+}
+
+/**
+ * A VRM model in the overlay's models folder (`~/.local/share/desktop-waifu/models`).
+ */
+export interface UserModel {
+  id: string;              // File name without .vrm
+  name: string;            // From the model's metadata
+  vrmVersion: 0 | 1;       // 0.x models face away from the camera, 1.0 towards it
+  authors: string[];
+  license: string | null;  // e.g. "CC BY" or "VRM Public License 1.0"
+  licenseUrl: string | null;
+  expressions: string[];   // What setExpression can show, named as three-vrm names them
+  size: number;
+  url: string;             // waifu-model:// URL to load it from
+  thumbnailUrl: string | null;
+}
+
+async function modelsRequest<T>(msg: Record<string, unknown>): Promise<T> {
+  if (!isOverlayMode) {
+    throw new Error('Imported models are only available in the overlay');
+  }
+  // Importing copies the file, which can be a few hundred MB
+  const result = await requestOverlay<T | { error: string }>('models', msg, 60000);
+  if (result && typeof result === 'object' && 'error' in result) {
+    throw new Error(result.error);
+  }
+  return result as T;
+}
+
+/**
+ * Models in the overlay's models folder (overlay mode only; empty elsewhere).
+ */
+export async function listModels(): Promise<UserModel[]> {
+  if (!isOverlayMode) {
+    return [];
+  }
+  return modelsRequest({ action: 'list' });
+}
+
+/**
+ * Check a .vrm file and copy it into the models folder. The overlay also sends a
+ * `modelImported` event, as it does for imports through IPC.
+ */
+export function importModel(path: string): Promise<UserModel> {
+  return modelsRequest({ action: 'import', path });
+}
+
+/**
+ * Switch the character to an imported model, through the overlay's `setActiveModel` event.
+ */
+export function setActiveModel(id: string): Promise<UserModel> {
+  return modelsRequest({ action: 'setActive', id });
+}
+
+/**
+ * A pending reminder, kept by the overlay across restarts. When it comes due the overlay
+ * shows a notification and sends a `reminder` event for the character to announce.
+ */
+export interface Reminder {
+  id: number;
+  text: string;
+  due: number;             // Milliseconds since the epoch
+  createdAt: number;
+}
+
+async function remindersRequest<T>(msg: Record<string, unknown>): Promise<T> {
+  if (!isOverlayMode) {
+    throw new Error('Reminders are only available in the overlay');
+  }
+  const result = await requestOverlay<T | { error: string }>('reminders', msg, 5000);
+  if (result && typeof result === 'object' && 'error' in result) {
+    throw new Error(result.error);
+  }
+  return result as T;
+}
+
+/**
+ * Remind the user of `text` after `delay`, written like `25m`, `1h30m` or `90s`.
+ */
+export function addReminder(text: string, delay: string): Promise<Reminder> {
+  return remindersRequest({ action: 'add', text, in: delay });
+}
+
+/**
+ * Pending reminders, soonest first.
+ */
+export function listReminders(): Promise<Reminder[]> {
+  return remindersRequest({ action: 'list' });
+}
+
+export async function cancelReminder(id: number): Promise<boolean> {
+  const result = await remindersRequest<{ found: boolean }>({ action: 'cancel', id });
+  return result.found;
+}
+
+/**
+ * Tell the overlay which character is shown, for `--status`, and which ones can be picked,
+ * for the tray's Character menu (overlay mode only).
+ */
+export function reportCharacter(character: string, characters: { id: string; name: string }[]): void {
+  if (isOverlayMode) {
+    window.webkit?.messageHandlers?.characterChanged?.postMessage({ character, characters });
+  }
+}
+
+/**
+ * Tell the overlay the character scale, which it restores on the next start (overlay mode only).
+ */
+export function reportScale(scale: number): void {
+  if (isOverlayMode) {
+    window.webkit?.messageHandlers?.scaleChanged?.postMessage({ scale });
+  }
+}
+
+/**
+ * Tell the overlay how big the chat panel is, so it can place it (overlay mode only).
+ */
+export function reportChatSize(width: number, height: number): void {
+  if (isOverlayMode) {
+    window.webkit?.messageHandlers?.chatSize?.postMessage({ width, height });
+  }
+}
+
+/**
+ * A drag ended near a screen edge and the character snaps onto it, at (`x`, `y`) over
+ * `durationMs`; from `characterSnap` events, followed by a `layoutChange` with the new spot.
+ */
+export interface CharacterSnap {
+  x: number;
+  y: number;
+  durationMs: number;
+}
+
+/**
+ * The character started or stopped walking around on its own, from `characterWalk` events.
+ * `direction` is the way it's heading while it walks.
+ */
+export interface CharacterWalk {
+  walking: boolean;
+  direction: 'left' | 'right' | null;
+}
+
+/**
+ * Where the overlay puts the character and the chat panel, from `layoutChange` events.
+ * Screen coordinates in CSS pixels.
+ */
+export interface OverlayLayout {
+  dock: 'follow-character' | 'left-panel' | 'right-panel' | 'floating';
+  character: { x: number; y: number; width: number; height: number };
+  chat: { x: number; y: number; width: number; height: number };
+  chatSide: 'left' | 'right';
+  isRightHalf: boolean;
+  isBottomHalf: boolean;
+  screenWidth: number;
+  screenHeight: number;
+}
+
+/**
+ * Update the tray icon: a spinner while the character is thinking, a speech bubble while a
+ * reply is waiting unread (overlay mode only).
+ */
+export function reportTrayState(state: { thinking?: boolean; unread?: boolean }): void {
+  if (isOverlayMode) {
+    window.webkit?.messageHandlers?.trayState?.postMessage(state);
+  }
+}
+
+/**
+ * Run a hook in the user's behavior scripts (overlay mode only).
+ * ChatPanel fires `on_message(role, text)` after each user message and reply; the overlay
+ * fires `on_idle(seconds)` itself while the user is away.
+ */
+export function fireScriptHook(hook: 'on_idle' | 'on_message', ...args: unknown[]): void {
+  if (isOverlayMode) {
+    window.webkit?.messageHandlers?.scriptHook?.postMessage({ hook, args });
+  }
+}
+
+/**
+ * Events the overlay broadcasts to external integrations (mirrors ipc::OverlayEvent).
+ */
+export type OverlayEventMessage =
+  | { type: 'Clicked' }
+  | { type: 'AnimationComplete'; data: string }
+  | { type: 'Ready' }
+  | { type: 'VisibilityChanged'; data: boolean }
+  | { type: 'Error'; data: string };
+
+/**
+ * Report a character interaction to the overlay so companions can react (overlay mode only).
+ */
+export function reportOverlayEvent(event: OverlayEventMessage): void {
+  if (isOverlayMode) {
+    window.webkit?.messageHandlers?.overlayEvent?.postMessage(event);
+  }
+}
+
+/**
+ * A connected monitor as reported by the overlay.
+ */
+export interface MonitorInfo {
+  name: string | null;         // Connector name, e.g. "DP-1"
+  description: string | null;
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+  scaleFactor: number;
+  refreshRate: number;         // Millihertz
+  hostsCharacter: boolean;     // The character is on this monitor
+}
+
+/**
+ * List connected monitors (overlay mode only).
+ * Listen for the `monitorsChanged` window event to hear about hotplug and geometry changes.
+ */
+export async function getMonitors(): Promise<MonitorInfo[]> {
+  if (!isOverlayMode) {
+    return [];
+  }
+  return requestOverlay<MonitorInfo[]>('getMonitors', {}, 5000);
+}
+
+/**
+ * The overlay's config.toml (`~/.config/desktop-waifu/config.toml`).
+ */
+export interface OverlayConfig {
+  debugLogging: boolean;
+  devServerPort: number;
+  staticServerPort: number;
+  window: {
+    collapsedWidth: number;    // Character only
+    collapsedHeight: number;
+    expandedWidth: number;     // Chat + character
+    expandedHeight: number;
+    scale: number;             // Character scale until one is set
+    layer: 'overlay' | 'top';
+    keyboardMode: 'on_demand' | 'none';
+    chatDock: OverlayLayout['dock'];
+    respectPanels: boolean;
+  };
+  position: {
+    corner: 'top-left' | 'top-right' | 'bottom-left' | 'bottom-right';
+    margin: number;
+    snapDistance: number;     // 0: no snapping to screen edges
+  };
+  tts: {
+    engine: 'auto' | 'piper' | 'espeak' | 'cloud';
+    voice: string | null;
+    rate: number;
+    cloud: { url: string; model: string; apiKeyEnv: string };
+  };
+  llm: {
+    localUrl: string | null;  // Local model server; null: detect Ollama or llama.cpp
+  };
+  memory: {
+    enabled: boolean;         // Relevant memories are added to chat requests
+    embeddings: 'auto' | 'local' | 'openai' | 'none';
+    model: string | null;
+    recallCount: number;
+  };
+  files: {
+    allowedDirs: string[];    // Folders readFile and friends may reach, e.g. "~/projects"
+  };
+  expressions: {
+    enabled: boolean;         // The overlay sets faces for thinking, commands and their results
+    thinking: string;
+    working: string;          // A command has run for `workingAfter` seconds
+    workingAfter: number;
+    success: string;
+    error: string;
+    flash: number;            // Seconds success and error show for
+    hold: number;             // Seconds SetExpression holds a face by default; 0: until the next one
+  };
+  walk: {
+    enabled: boolean;         // Wander about now and then (also in the tray)
+    speed: number;            // Pixels per second
+    interval: number;         // Seconds between walks, on average
+    perchOnWindows: boolean;
+  };
+  render: {
+    mode: RenderMode;         // Read at startup only
+  };
+  webkit: {                   // Read at startup only
+    hardwareAcceleration: 'auto' | 'always' | 'never';
+    cacheModel: 'web-browser' | 'document-browser' | 'document-viewer';
+    memoryLimit: number | null;  // MB per web process
+    conservativeThreshold: number;
+    strictThreshold: number;
+    killThreshold: number;
+  };
+}
+
+/**
+ * Read the overlay's config (overlay mode only; null elsewhere).
+ * Listen for the `configChanged` window event to hear about edits.
+ */
+export async function getOverlayConfig(): Promise<OverlayConfig | null> {
+  if (!isOverlayMode) {
+    return null;
+  }
+  return requestOverlay<OverlayConfig>('getOverlayConfig', {}, 5000);
+}
+
+export type RenderMode = 'auto' | 'gpu' | 'no-dmabuf' | 'software';
+
+/**
+ * How the character is drawn: the renderer the overlay picked at startup and why, and
+ * what WebGL says it runs on (e.g. "llvmpipe" in software mode).
+ */
+export interface RenderInfo {
+  mode: RenderMode;                // [render] mode
+  renderer: Exclude<RenderMode, 'auto'>;
+  reason: string | null;           // Why auto fell back, if it did
+  egl: boolean;
+  renderNode: string | null;       // e.g. /dev/dri/renderD128
+  driver: string | null;           // e.g. "amdgpu" or "nvidia"
+  hardwareAcceleration: boolean;
+  environment: Record<string, string>;
+  webgl: string | null;            // WebGL renderer string; null when WebGL doesn't work
+}
+
+/** The renderer WebGL reports, or null when it can't create a context */
+function webglRenderer(): string | null {
+  const gl = document.createElement('canvas').getContext('webgl2') ?? document.createElement('canvas').getContext('webgl');
+  if (!gl) {
+    return null;
+  }
+  const debugInfo = gl.getExtension('WEBGL_debug_renderer_info');
+  const renderer = gl.getParameter(debugInfo ? debugInfo.UNMASKED_RENDERER_WEBGL : gl.RENDERER) as string;
+  gl.getExtension('WEBGL_lose_context')?.loseContext();
+  return renderer;
+}
+
+/**
+ * Rendering status (overlay mode only; null elsewhere), for telling why the character
+ * doesn't show.
+ */
+export async function getRenderInfo(): Promise<RenderInfo | null> {
+  if (!isOverlayMode) {
+    return null;
+  }
+  const info = await requestOverlay<Omit<RenderInfo, 'webgl'> | null>('getRenderInfo', {}, 5000);
+  return info && { ...info, webgl: webglRenderer() };
+}
+
+/**
+ * Desktop appearance preferences from the XDG settings portal.
+ */
+export interface SystemAppearance {
+  colorScheme: 'no-preference' | 'dark' | 'light';
+  accentColor: string | null;  // CSS hex color, e.g. "#3584e4"
+  highContrast: boolean;
+}
+
+/**
+ * Current desktop appearance (overlay mode only; elsewhere falls back to prefers-color-scheme).
+ * Listen for the `appearanceChanged` window event to follow changes.
+ */
+export async function getAppearance(): Promise<SystemAppearance> {
+  if (!isOverlayMode) {
+    const dark = window.matchMedia?.('(prefers-color-scheme: dark)').matches;
+    return { colorScheme: dark ? 'dark' : 'light', accentColor: null, highContrast: false };
+  }
+  return requestOverlay<SystemAppearance>('getAppearance', {}, 5000);
+}
+
+/**
+ * A file or folder as statPath and listDir describe it.
+ */
+export interface PathInfo {
+  name: string;
+  path: string;              // Resolved: absolute, symlinks followed
+  kind: 'file' | 'directory' | 'symlink' | 'other';
+  size: number;
+  modified: number | null;   // Milliseconds since the epoch
+  readonly: boolean;
+}
+
+export interface FileContent {
+  path: string;
+  content: string;           // Base64 when encoding is "base64" (not a UTF-8 file)
+  encoding: 'utf-8' | 'base64';
+  size: number;
+}
+
+/**
+ * A change to a watched path, sent as the `pathChanged` window event.
+ */
+export interface PathChange {
+  watchId: number;
+  path: string;
+  change: 'changed' | 'created' | 'deleted' | 'renamed' | 'attributes';
+}
+
+async function fileRequest<T>(handler: string, msg: Record<string, unknown>): Promise<T> {
+  if (!isOverlayMode) {
+    throw new Error('File access is only available in the overlay');
+  }
+  const result = await requestOverlay<T | { error: string }>(handler, msg, 15000);
+  if (result && typeof result === 'object' && 'error' in result) {
+    throw new Error(result.error);
+  }
+  return result as T;
+}
+
+/**
+ * Read a file in an allowed folder (overlay mode only; up to 4 MB).
+ * `~/` and relative paths are taken from the home directory.
+ */
+export function readFile(path: string): Promise<FileContent> {
+  return fileRequest('readFile', { path });
+}
+
+/**
+ * A folder's entries, folders first (overlay mode only).
+ */
+export function listDir(path: string): Promise<PathInfo[]> {
+  return fileRequest('listDir', { path });
+}
+
+export function statPath(path: string): Promise<PathInfo> {
+  return fileRequest('statPath', { path });
+}
+
+/**
+ * Call `onChange` whenever the file, or an entry of the folder, changes. Resolves with a
+ * function that stops watching.
+ */
+export async function watchPath(path: string, onChange: (change: PathChange) => void): Promise<() => void> {
+  const { watchId } = await fileRequest<{ watchId: number }>('watchPath', { path });
+  const handleChange = (e: Event) => {
+    const change = (e as CustomEvent<PathChange>).detail;
+    if (change.watchId === watchId) onChange(change);
+  };
+  window.addEventListener('pathChanged', handleChange);
+  return () => {
+    window.removeEventListener('pathChanged', handleChange);
+    fileRequest('unwatchPath', { watchId }).catch(() => {});
+  };
+}
+
+/**
+ * Folders file access is allowed to, as saved in config.toml.
+ */
+export async function listAllowedDirs(): Promise<string[]> {
+  const result = await fileRequest<{ dirs: string[] }>('fileAccess', { action: 'list' });
+  return result.dirs;
+}
+
+/**
+ * Allow file access to a folder and everything in it; ask the user first. Resolves with
+ * the folder as saved (e.g. "~/projects").
+ */
+export async function allowDir(dir: string): Promise<string> {
+  const result = await fileRequest<{ dir: string }>('fileAccess', { action: 'allow', dir });
+  return result.dir;
+}
+
+export async function disallowDir(dir: string): Promise<boolean> {
+  const result = await fileRequest<{ found: boolean }>('fileAccess', { action: 'disallow', dir });
+  return result.found;
+}
+
+/**
+ * Desktop night light state, with the dim amount from the overlay's nightlight.toml curve.
+ */
+export interface NightLight {
+  active: boolean;
+  temperature: number;  // Color temperature in Kelvin
+  dim: number;          // 0 (normal) to 1 (dark)
+  source: 'gnome' | 'kde' | 'schedule' | 'manual';
+}
+
+/**
+ * Current night light state (overlay mode only; elsewhere night light is always off).
+ * Listen for the `nightLightChanged` window event to follow changes.
+ */
+export async function getNightLight(): Promise<NightLight> {
+  if (!isOverlayMode) {
+    return { active: false, temperature: 6500, dim: 0, source: 'schedule' };
+  }
+  return requestOverlay<NightLight>('getNightLight', {}, 5000);
+}
+
+/**
+ * What the user's media player (Spotify, mpv, a browser, ...) is playing, over MPRIS.
+ */
+export interface NowPlaying {
+  player: string;             // e.g. "spotify" or "mpv"
+  status: 'playing' | 'paused' | 'stopped';
+  title: string | null;
+  artists: string[];
+  album: string | null;
+  artUrl: string | null;
+  lengthMs: number | null;
+}
+
+/**
+ * The preferred player's track, null without one (overlay mode only). Listen for the
+ * `nowPlayingChanged` window event to follow changes.
+ */
+export async function getNowPlaying(): Promise<NowPlaying | null> {
+  if (!isOverlayMode) {
+    return null;
+  }
+  return requestOverlay<NowPlaying | null>('getNowPlaying', {}, 5000);
+}
+
+/**
+ * Pause or resume the preferred player, or skip a track (overlay mode only).
+ */
+export async function mediaControl(action: 'play_pause' | 'next' | 'previous'): Promise<void> {
+  if (!isOverlayMode) {
+    throw new Error('Media control is only available in overlay mode');
+  }
+  const handler = { play_pause: 'mediaPlayPause', next: 'mediaNext', previous: 'mediaPrevious' }[action];
+  const result = await requestOverlay<{ success: boolean; error?: string }>(handler, {}, 5000);
+  if (!result.success) {
+    throw new Error(result.error ?? 'Media control failed');
+  }
+}
+
+/**
+ * Toggle shortcut the overlay registered through the desktop's GlobalShortcuts portal.
+ */
+export interface GlobalShortcut {
+  bound: boolean;
+  trigger: string | null;  // As the desktop describes it, e.g. "Super+W"
+}
+
+/**
+ * Whether the portal shortcut is in place, in which case no compositor keybinding is
+ * needed (overlay mode only).
+ */
+export async function getGlobalShortcut(): Promise<GlobalShortcut> {
+  if (!isOverlayMode) {
+    return { bound: false, trigger: null };
+  }
+  return requestOverlay<GlobalShortcut>('getGlobalShortcut', {}, 5000);
+}
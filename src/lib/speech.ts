@@ -0,0 +1,83 @@
+/**
+ * Spoken replies and lip-sync
+ *
+ * The overlay synthesizes and plays speech; before each sentence plays it sends the mouth
+ * shapes for it as a `speechVisemes` track, which CharacterModel reads through mouthShape().
+ */
+
+import { useAppStore } from '../store';
+import { isOverlayMode, speak } from './platform';
+import { debugLog } from './debug';
+
+export const VISEMES = ['aa', 'ih', 'ou', 'ee', 'oh'] as const;
+export type Viseme = (typeof VISEMES)[number];
+
+interface VisemeTrack {
+  interval: number;  // Seconds per frame
+  frames: [Viseme, number][];
+}
+
+let track: (VisemeTrack & { startedAt: number }) | null = null;
+
+window.addEventListener('speechVisemes', (e) => {
+  const detail = (e as CustomEvent<VisemeTrack>).detail;
+  track = { ...detail, startedAt: performance.now() };
+});
+
+window.addEventListener('setTalking', (e) => {
+  const { talking } = (e as CustomEvent<{ talking: boolean }>).detail;
+  if (!talking) {
+    track = null;
+  }
+  useAppStore.getState().setTalking(talking);
+});
+
+/**
+ * Mouth shape at `now` (performance.now() time). Without a track, e.g. when talking was
+ * switched on over IPC, the mouth just flaps.
+ */
+export function mouthShape(now: number, isTalking: boolean): { viseme: Viseme; weight: number } | null {
+  if (track) {
+    const frame = track.frames[Math.floor((now - track.startedAt) / 1000 / track.interval)];
+    if (frame) {
+      return { viseme: frame[0], weight: frame[1] };
+    }
+  }
+  if (isTalking && !track) {
+    return { viseme: 'aa', weight: 0.3 + 0.3 * Math.sin(now / 60) };
+  }
+  return null;
+}
+
+/**
+ * Markdown reply as it should be read out: code blocks, links and markup left out.
+ */
+function speakableText(markdown: string): string {
+  return markdown
+    .replace(/```[\s\S]*?```/g, ' ')
+    .replace(/`([^`]*)`/g, '$1')
+    .replace(/!\[[^\]]*\]\([^)]*\)/g, ' ')
+    .replace(/\[([^\]]*)\]\([^)]*\)/g, '$1')
+    .replace(/^\s{0,3}(#{1,6}|>|[-*+]|\d+\.)\s+/gm, '')
+    .replace(/[*_~]+/g, '')
+    .trim();
+}
+
+/**
+ * Read an assistant reply aloud if spoken replies are on and nobody is on a call.
+ */
+export function speakReply(markdown: string): void {
+  const { settings, ui } = useAppStore.getState();
+  if (!isOverlayMode || !settings.speakReplies || ui.quietMode) {
+    return;
+  }
+  const text = speakableText(markdown);
+  if (!text) {
+    return;
+  }
+  speak(text, settings.ttsVoice || undefined).then((result) => {
+    if (result.error) {
+      debugLog(`[SPEECH] ${result.error}`);
+    }
+  });
+}
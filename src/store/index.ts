@@ -1,6 +1,26 @@
 import { create } from 'zustand';
 import { persist } from 'zustand/middleware';
 import { debugLog } from '../lib/debug';
+import {
+  announce,
+  createConversation,
+  deleteSecret,
+  deleteStoredMessages,
+  getConversationMessages,
+  getSecret,
+  isOverlayMode,
+  listConversations,
+  listModels,
+  reportTrayState,
+  saveMessage,
+  setLlmKey,
+  storeSecret,
+  type CharacterWalk,
+  type StoredMessage,
+  type UserModel,
+} from '../lib/platform';
+import { findCharacter, userCharacterId } from '../characters';
+import { fileToImageAttachment } from '../lib/image';
 import type {
   ChatMessage,
   LLMProviderType,
@@ -9,7 +29,9 @@ import type {
   ExecutionStatus,
   CommandOutput,
   CodeExecutionState,
+  DesktopContext,
   NotificationPreference,
+  ImageAttachment,
 } from '../types';
 
 interface CharacterState {
@@ -18,12 +40,21 @@ interface CharacterState {
   currentExpression: string;
   isTalking: boolean;
   isHiding: boolean;  // Character is running off screen to hide
+  walking: 'left' | 'right' | null;  // Walking around on its own, heading that way
+  // State set over IPC (SetAnimationState), held until set back to idle; null follows the chat
+  requestedState: 'thinking' | 'talking' | 'listening' | null;
+  // Models in the overlay's models folder, selectable as "user:<id>"; null until listed
+  userModels: UserModel[] | null;
 }
 
 interface ChatState {
   messages: ChatMessage[];
   isThinking: boolean;  // Waiting for LLM response
   isUserTyping: boolean; // User is typing in input
+  // Screenshot from the overlay's screenshot-and-ask action, waiting for InputArea to pick it up
+  pendingScreenshot: { image: ImageAttachment; ocrText: string | null } | null;
+  // Files dropped on the character: images to attach and text files quoted as Markdown
+  pendingDrop: { images: ImageAttachment[]; text: string } | null;
 }
 
 interface SettingsState {
@@ -47,11 +78,20 @@ interface SettingsState {
   notificationPreference: NotificationPreference;
   // Export settings
   exportPath: string;
+  // Command execution: empty means the login shell ($SHELL) and home directory
+  commandShell: string;
+  commandCwd: string;
+  // Spoken replies (overlay mode): empty voice means the one in config.toml
+  speakReplies: boolean;
+  ttsVoice: string;
 }
 
 interface UIState {
   chatPanelOpen: boolean;
   isScaleSliderDragging: boolean;
+  quietMode: boolean;  // User is on a call: hold back sound and proactive messages
+  activeProfile: string | null;  // Profile from profiles.toml, switched by the overlay
+  desktop: DesktopContext | null;  // Focused app and workspace (Hyprland, Sway)
   quadrant: {
     isRightHalf: boolean;
     isBottomHalf: boolean;
@@ -95,7 +135,7 @@ interface AppState {
   // Code Execution
   execution: ExecutionState;
   setExecutionStatus: (status: ExecutionStatus) => void;
-  setGeneratedCommand: (task: string, command: string) => void;
+  setGeneratedCommand: (task: string, command: string, privileged?: boolean) => void;
   approveCommand: () => void; // CRITICAL: Only way to approve command execution
   setExecutionOutput: (output: CommandOutput) => void;
   setExecutionError: (error: string) => void;
@@ -103,6 +143,10 @@ interface AppState {
   updateGeneratedCommand: (command: string) => void;
 }
 
+// Set once API keys are known to be in the desktop keyring; until then (or without a
+// keyring) the key is persisted with the other settings
+let apiKeysInKeyring = false;
+
 export const useAppStore = create<AppState>()(
   persist(
     (set) => ({
@@ -113,6 +157,9 @@ export const useAppStore = create<AppState>()(
         currentExpression: 'neutral',
         isTalking: false,
         isHiding: false,
+        walking: null,
+        requestedState: null,
+        userModels: null,
       },
       setCharacterLoaded: (loaded) =>
         set((state) => ({
@@ -140,6 +187,8 @@ export const useAppStore = create<AppState>()(
         messages: [],
         isThinking: false,
         isUserTyping: false,
+        pendingScreenshot: null,
+        pendingDrop: null,
       },
       addMessage: (message) =>
         set((state) => ({
@@ -239,6 +288,11 @@ export const useAppStore = create<AppState>()(
         notificationPreference: 'chat_closed',
         // Export settings
         exportPath: '~/Documents/DesktopWaifu',
+        // Command execution defaults
+        commandShell: '',
+        commandCwd: '',
+        speakReplies: false,
+        ttsVoice: '',
       },
       updateSettings: (newSettings) =>
         set((state) => ({
@@ -253,6 +307,9 @@ export const useAppStore = create<AppState>()(
       ui: {
         chatPanelOpen: false,
         isScaleSliderDragging: false,
+        quietMode: false,
+        activeProfile: null,
+        desktop: null,
         quadrant: {
           isRightHalf: true,  // Default: bottom-right corner
           isBottomHalf: true,
@@ -284,13 +341,14 @@ export const useAppStore = create<AppState>()(
         output: null,
         error: null,
         approved: false,
+        privileged: false,
       },
       setExecutionStatus: (status) =>
         set((state) => ({
           execution: { ...state.execution, status },
         })),
-      setGeneratedCommand: (task, command) => {
-        debugLog(`[EXEC] setGeneratedCommand: task="${task}", command="${command}"`);
+      setGeneratedCommand: (task, command, privileged = false) => {
+        debugLog(`[EXEC] setGeneratedCommand: task="${task}", command="${command}", privileged=${privileged}`);
         set(() => ({
           execution: {
             status: 'pending_approval',
@@ -299,6 +357,7 @@ export const useAppStore = create<AppState>()(
             output: null,
             error: null,
             approved: false, // CRITICAL: Reset approval when new command is generated
+            privileged,
           },
         }));
         debugLog(`[EXEC] After setGeneratedCommand: approved=${useAppStore.getState().execution.approved}`);
@@ -343,6 +402,7 @@ export const useAppStore = create<AppState>()(
             output: null,
             error: null,
             approved: false, // CRITICAL: Always reset approval
+            privileged: false,
           },
         }));
         debugLog(`[EXEC] After clearExecution: approved=${useAppStore.getState().execution.approved}`);
@@ -358,7 +418,7 @@ export const useAppStore = create<AppState>()(
         settings: {
           llmProvider: state.settings.llmProvider,
           llmModel: state.settings.llmModel,
-          apiKey: state.settings.apiKey,
+          ...(apiKeysInKeyring ? {} : { apiKey: state.settings.apiKey }),
           alwaysOnTop: state.settings.alwaysOnTop,
           hotkeyEnabled: state.settings.hotkeyEnabled,
           characterScale: state.settings.characterScale,
@@ -372,6 +432,10 @@ export const useAppStore = create<AppState>()(
           showSettings: state.settings.showSettings,
           notificationPreference: state.settings.notificationPreference,
           exportPath: state.settings.exportPath,
+          commandShell: state.settings.commandShell,
+          commandCwd: state.settings.commandCwd,
+          speakReplies: state.settings.speakReplies,
+          ttsVoice: state.settings.ttsVoice,
         },
         ui: {
           chatPanelOpen: state.ui.chatPanelOpen,
@@ -391,3 +455,399 @@ export const useAppStore = create<AppState>()(
 );
 
 debugLog('[STORE] Store created successfully');
+
+// The overlay announces shutdown before quitting; write the store out one last time so
+// nothing set in the final moments is lost. setState runs the persist middleware.
+window.addEventListener('appShutdown', () => {
+  debugLog('[STORE] App shutting down, flushing persisted state');
+  useAppStore.setState({});
+});
+
+// The overlay detects calls (mic/camera in use, screen shared) and asks us to go quiet
+window.addEventListener('presenceChanged', (e) => {
+  const { quiet } = (e as CustomEvent<{ quiet: boolean }>).detail;
+  debugLog(`[STORE] Meeting mode ${quiet ? 'on' : 'off'}`);
+  useAppStore.setState((state) => ({ ui: { ...state.ui, quietMode: quiet } }));
+});
+
+// The overlay follows the focused app and workspace on Hyprland and Sway
+window.addEventListener('desktopChanged', (e) => {
+  const desktop = (e as CustomEvent<DesktopContext>).detail;
+  useAppStore.setState((state) => ({ ui: { ...state.ui, desktop } }));
+});
+
+// SetAnimationState over IPC (e.g. a voice assistant showing that it's listening)
+window.addEventListener('setAnimationState', (e) => {
+  const { state } = (e as CustomEvent<{ state: 'idle' | 'thinking' | 'talking' | 'listening' }>).detail;
+  debugLog(`[STORE] Animation state set to ${state}`);
+  useAppStore.setState((current) => ({
+    character: { ...current.character, requestedState: state === 'idle' ? null : state },
+  }));
+});
+
+// The overlay walks the character around when walking is on (walk.rs)
+window.addEventListener('characterWalk', (e) => {
+  const { walking, direction } = (e as CustomEvent<CharacterWalk>).detail;
+  useAppStore.setState((current) => ({
+    character: { ...current.character, walking: walking ? direction : null },
+  }));
+});
+
+// SetExpression over IPC, from the tray or a behavior script
+window.addEventListener('setExpression', (e) => {
+  const { expression } = (e as CustomEvent<{ expression: string }>).detail;
+  debugLog(`[STORE] Expression set to ${expression}`);
+  useAppStore.getState().setExpression(expression);
+});
+
+// The tray's Character menu, with the ids CharacterCanvas reported
+window.addEventListener('setCharacter', (e) => {
+  const { id } = (e as CustomEvent<{ id: string }>).detail;
+  const { character, updateSettings } = useAppStore.getState();
+  if (!findCharacter(id, character.userModels ?? [])) {
+    debugLog(`[STORE] Ignoring unknown character ${id}`);
+    return;
+  }
+  debugLog(`[STORE] Switching to character ${id}`);
+  updateSettings({ selectedCharacter: id });
+});
+
+// The tray's Open settings item; the overlay has already shown the window
+window.addEventListener('openSettings', () => {
+  useAppStore.getState().updateSettings({ showSettings: true });
+});
+
+// Shortcuts the overlay catches itself (desktop-waifu-overlay/src/keys.rs). Escape also
+// reaches the page, and when something here used it (clearing the input, declining a
+// command) it doesn't close the chat as well. Either may arrive first, hence the delays.
+let lastUsedEscape = 0;
+window.addEventListener('keydown', (e) => {
+  if (e.key !== 'Escape') return;
+  setTimeout(() => {
+    if (e.defaultPrevented) lastUsedEscape = performance.now();
+  });
+});
+window.addEventListener('overlayShortcut', (e) => {
+  const { action } = (e as CustomEvent<{ action: string }>).detail;
+  if (action === 'closeChat') {
+    setTimeout(() => {
+      if (performance.now() - lastUsedEscape < 250) return;
+      const { settings, ui, updateSettings, setChatPanelOpen } = useAppStore.getState();
+      if (settings.showSettings) {
+        updateSettings({ showSettings: false });
+      } else if (ui.chatPanelOpen) {
+        setChatPanelOpen(false);
+      }
+    }, 50);
+  } else if (action === 'focusInput') {
+    const { ui, setChatPanelOpen } = useAppStore.getState();
+    if (ui.chatPanelOpen) {
+      document.querySelector<HTMLTextAreaElement>('textarea')?.focus();
+    } else {
+      // Opening the chat focuses its input (App.tsx)
+      setChatPanelOpen(true);
+    }
+  }
+});
+
+// Screenshot-and-ask: the overlay captured a region; attach it and open the chat
+window.addEventListener('screenshotAsk', async (e) => {
+  const { url, mimeType, ocrText } = (e as CustomEvent<{ url: string; mimeType: string; ocrText: string | null }>).detail;
+  try {
+    const blob = await (await fetch(url)).blob();
+    const image = await fileToImageAttachment(new File([blob], 'screenshot', { type: mimeType }));
+    debugLog(`[STORE] Screenshot attached (${blob.size} bytes, OCR: ${ocrText ? 'yes' : 'no'})`);
+    useAppStore.setState((state) => ({
+      chat: { ...state.chat, pendingScreenshot: { image, ocrText } },
+      ui: { ...state.ui, chatPanelOpen: true },
+    }));
+  } catch (error) {
+    console.error('Failed to load screenshot:', error);
+  }
+});
+
+// Files dropped on the character: the overlay checked and read them; open the chat with
+// images attached and text files quoted into the input
+interface DroppedFile {
+  name: string;
+  mimeType: string;
+  size: number;
+  data: string;  // base64
+}
+
+window.addEventListener('fileDropped', (e) => {
+  const { files, rejected } = (e as CustomEvent<{ files: DroppedFile[]; rejected: { name: string; reason: string }[] }>).detail;
+  debugLog(`[STORE] ${files.length} file(s) dropped, ${rejected.length} rejected`);
+  const images: ImageAttachment[] = files
+    .filter((file) => file.mimeType.startsWith('image/'))
+    .map((file) => ({
+      id: crypto.randomUUID(),
+      data: file.data,
+      mimeType: file.mimeType as ImageAttachment['mimeType'],
+      previewUrl: `data:${file.mimeType};base64,${file.data}`,
+    }));
+  const text = files
+    .filter((file) => file.mimeType === 'text/plain')
+    .map((file) => {
+      const content = new TextDecoder().decode(Uint8Array.from(atob(file.data), (c) => c.charCodeAt(0)));
+      return `\`${file.name}\`:\n\n\`\`\`\n${content.trimEnd()}\n\`\`\`\n\n`;
+    })
+    .join('');
+
+  const { addMessage } = useAppStore.getState();
+  if (rejected.length > 0) {
+    addMessage({
+      role: 'assistant',
+      content: rejected.map((file) => `I can't take \`${file.name}\`: ${file.reason}.`).join('\n'),
+    });
+  }
+  if (images.length === 0 && !text) return;
+  useAppStore.setState((state) => ({
+    chat: { ...state.chat, pendingDrop: { images, text } },
+    ui: { ...state.ui, chatPanelOpen: true },
+  }));
+});
+
+// Imported models: list them on startup, add ones imported later (through IPC or
+// `--load-model`), and switch to one when the overlay says so
+if (isOverlayMode) {
+  const addUserModel = (model: UserModel) => {
+    useAppStore.setState((state) => ({
+      character: {
+        ...state.character,
+        userModels: [...(state.character.userModels ?? []).filter((m) => m.id !== model.id), model]
+          .sort((a, b) => a.name.localeCompare(b.name)),
+      },
+    }));
+  };
+
+  listModels()
+    .then((userModels) => {
+      debugLog(`[STORE] ${userModels.length} imported model(s)`);
+      useAppStore.setState((state) => ({ character: { ...state.character, userModels } }));
+    })
+    .catch((error) => {
+      debugLog(`[STORE] Failed to list models: ${error}`);
+      useAppStore.setState((state) => ({ character: { ...state.character, userModels: [] } }));
+    });
+
+  window.addEventListener('modelImported', (e) => {
+    const model = (e as CustomEvent<UserModel>).detail;
+    debugLog(`[STORE] Model imported: ${model.id}`);
+    addUserModel(model);
+  });
+
+  window.addEventListener('setActiveModel', (e) => {
+    const model = (e as CustomEvent<UserModel>).detail;
+    debugLog(`[STORE] Switching to model ${model.id}`);
+    addUserModel(model);
+    useAppStore.getState().updateSettings({ selectedCharacter: userCharacterId(model) });
+  });
+}
+
+// Profile switches (tray, --profile, schedule) carry the settings the profile overrides
+interface ProfileDetail {
+  name: string | null;
+  personality?: PersonalityId | null;
+  llmProvider?: LLMProviderType | null;
+  llmModel?: string | null;
+  notifications?: NotificationPreference | null;
+}
+
+window.addEventListener('profileChanged', (e) => {
+  const profile = (e as CustomEvent<ProfileDetail>).detail;
+  debugLog(`[STORE] Profile changed to ${profile.name ?? 'none'}`);
+  const overrides: Partial<SettingsState> = {};
+  if (profile.personality) overrides.selectedPersonality = profile.personality;
+  if (profile.llmProvider) overrides.llmProvider = profile.llmProvider;
+  if (profile.llmModel) overrides.llmModel = profile.llmModel;
+  if (profile.notifications) overrides.notificationPreference = profile.notifications;
+
+  const state = useAppStore.getState();
+  state.updateSettings(overrides);
+  useAppStore.setState({ ui: { ...state.ui, activeProfile: profile.name } });
+});
+
+// API keys: the overlay makes LLM requests itself, so hand it the key now and whenever it
+// changes. Each provider's key is kept in the desktop keyring as `<provider>-api-key`;
+// settings.apiKey holds the selected provider's, in memory only. Without a keyring the one
+// key stays in localStorage as before.
+if (isOverlayMode) {
+  const secretName = (provider: LLMProviderType) => `${provider}-api-key`;
+  // What the keyring holds, to tell keys just loaded from it from ones the user typed
+  const savedKeys: Partial<Record<LLMProviderType, string>> = {};
+  let saveTimer: ReturnType<typeof setTimeout> | undefined;
+
+  const loadApiKey = async (provider: LLMProviderType) => {
+    const key = (await getSecret(secretName(provider))) ?? '';
+    savedKeys[provider] = key;
+    if (useAppStore.getState().settings.llmProvider === provider) {
+      useAppStore.getState().updateSettings({ apiKey: key });
+    }
+  };
+
+  const { llmProvider, apiKey } = useAppStore.getState().settings;
+  setLlmKey(llmProvider, apiKey);
+  // A key still in localStorage is from before the keyring (or from a run without one): move it
+  const migrated = apiKey
+    ? storeSecret(secretName(llmProvider), apiKey).then(() => { savedKeys[llmProvider] = apiKey; })
+    : loadApiKey(llmProvider);
+  migrated
+    .then(() => {
+      apiKeysInKeyring = true;
+      // Persist again, now without the key
+      useAppStore.setState({});
+      debugLog(`[STORE] API keys are in the keyring${apiKey ? ' (moved from localStorage)' : ''}`);
+    })
+    .catch((error) => {
+      debugLog(`[STORE] No keyring, API key stays in localStorage: ${error}`);
+    });
+
+  useAppStore.subscribe((state, prev) => {
+    const { llmProvider, apiKey } = state.settings;
+    if (llmProvider !== prev.settings.llmProvider && apiKeysInKeyring) {
+      loadApiKey(llmProvider).catch((error) => debugLog(`[STORE] Failed to read API key: ${error}`));
+      return;
+    }
+    if (llmProvider === prev.settings.llmProvider && apiKey === prev.settings.apiKey) {
+      return;
+    }
+    setLlmKey(llmProvider, apiKey);
+    if (apiKeysInKeyring && apiKey !== savedKeys[llmProvider]) {
+      // The key is typed a character at a time; save it once the user stops
+      clearTimeout(saveTimer);
+      saveTimer = setTimeout(() => {
+        savedKeys[llmProvider] = apiKey;
+        const name = secretName(llmProvider);
+        (apiKey ? storeSecret(name, apiKey) : deleteSecret(name)).catch((error) => {
+          debugLog(`[STORE] Failed to save API key: ${error}`);
+        });
+      }, 1000);
+    }
+  });
+}
+
+// Chat history: the overlay keeps conversations in SQLite, outside WebKit's storage. Restore
+// the latest one on startup, then save messages as they settle. Clearing the chat starts a
+// new conversation; the old one stays in the database (and in search).
+if (isOverlayMode) {
+  let conversationId: number | null = null;
+  // Content of each message as last saved, to skip unchanged ones and spot removed ones
+  const savedContent = new Map<string, string>();
+  let syncTimer: ReturnType<typeof setTimeout> | undefined;
+  let syncing: Promise<void> = Promise.resolve();
+
+  const toStored = (message: ChatMessage, withImages: boolean): StoredMessage => ({
+    id: message.id,
+    role: message.role,
+    content: message.content,
+    timestamp: message.timestamp,
+    attachments: withImages
+      ? (message.images ?? []).map(({ id, mimeType, data }) => ({ id, mimeType, data }))
+      : [],
+  });
+
+  const syncHistory = async () => {
+    const messages = useAppStore.getState().chat.messages;
+    // Edit-and-retry drops the messages after the edited one
+    const removed = [...savedContent.keys()].filter((id) => !messages.some((m) => m.id === id));
+    if (removed.length > 0) {
+      await deleteStoredMessages(removed);
+      removed.forEach((id) => savedContent.delete(id));
+    }
+    // Streaming replies start out empty; they're saved once text arrives
+    const changed = messages.filter((m) => m.content && savedContent.get(m.id) !== m.content);
+    if (changed.length === 0) return;
+    if (conversationId === null) {
+      const firstLine = messages.find((m) => m.role === 'user')?.content.split('\n')[0] ?? '';
+      conversationId = (await createConversation(firstLine.slice(0, 80) || 'New chat')).id;
+    }
+    const id = conversationId;
+    for (const message of changed) {
+      // Images don't change, so they only go with the first save
+      await saveMessage(id, toStored(message, !savedContent.has(message.id)));
+      savedContent.set(message.id, message.content);
+    }
+  };
+
+  listConversations()
+    .then(async ([latest]) => {
+      if (!latest || useAppStore.getState().chat.messages.length > 0) return;
+      const stored = await getConversationMessages(latest.id);
+      conversationId = latest.id;
+      stored.forEach((m) => savedContent.set(m.id, m.content));
+      const messages: ChatMessage[] = stored.map((m) => ({
+        id: m.id,
+        role: m.role,
+        content: m.content,
+        timestamp: m.timestamp,
+        images: m.attachments.length > 0
+          ? m.attachments.map((a) => ({
+              id: a.id,
+              data: a.data,
+              mimeType: a.mimeType as ImageAttachment['mimeType'],
+              previewUrl: `data:${a.mimeType};base64,${a.data}`,
+            }))
+          : undefined,
+      }));
+      useAppStore.setState((state) => ({ chat: { ...state.chat, messages } }));
+      debugLog(`[STORE] Restored conversation ${latest.id} (${messages.length} messages)`);
+    })
+    .catch((error) => debugLog(`[STORE] Failed to restore chat history: ${error}`));
+
+  useAppStore.subscribe((state, prev) => {
+    const messages = state.chat.messages;
+    if (messages === prev.chat.messages) return;
+    if (messages.length === 0) {
+      clearTimeout(syncTimer);
+      conversationId = null;
+      savedContent.clear();
+      return;
+    }
+    // Streaming updates arrive many times a second; save once they settle
+    clearTimeout(syncTimer);
+    syncTimer = setTimeout(() => {
+      syncing = syncing
+        .then(syncHistory)
+        .catch((error) => debugLog(`[STORE] Failed to save chat history: ${error}`));
+    }, 1000);
+  });
+}
+
+// Speak new character messages and state changes to screen reader users (overlay mode).
+useAppStore.subscribe((state, prev) => {
+  if (state.chat.isThinking && !prev.chat.isThinking) {
+    announce('Thinking…', 'low');
+  }
+
+  const messages = state.chat.messages;
+  if (messages.length > prev.chat.messages.length) {
+    const latest = messages[messages.length - 1];
+    // Streaming messages start out empty; ChatPanel announces them once complete
+    if (latest.role === 'assistant' && latest.content) {
+      announce(latest.content);
+    }
+  }
+
+  if (state.execution.status === 'pending_approval' && prev.execution.status !== 'pending_approval') {
+    const elevation = state.execution.privileged ? ' as administrator' : '';
+    announce(`Command needs your approval${elevation}: ${state.execution.generatedCommand ?? ''}`, 'high');
+  }
+});
+
+// Reflect thinking and unread replies in the tray icon (overlay mode). A reply is unread
+// if it arrives while the chat panel is closed, until the panel opens.
+let unread = false;
+useAppStore.subscribe((state, prev) => {
+  if (state.chat.isThinking !== prev.chat.isThinking) {
+    reportTrayState({ thinking: state.chat.isThinking });
+  }
+
+  const messages = state.chat.messages;
+  const replied = messages.length > prev.chat.messages.length && messages[messages.length - 1].role === 'assistant';
+  const nowUnread = state.ui.chatPanelOpen ? false : unread || replied;
+  if (nowUnread !== unread) {
+    unread = nowUnread;
+    reportTrayState({ unread });
+  }
+});
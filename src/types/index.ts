@@ -32,7 +32,7 @@ export interface LLMConfig {
   temperature?: number;
 }
 
-export type LLMProviderType = 'openai' | 'anthropic' | 'gemini';
+export type LLMProviderType = 'openai' | 'anthropic' | 'gemini' | 'openrouter' | 'local';
 
 // Character Types
 export interface CharacterExpression {
@@ -133,6 +133,22 @@ export interface CommandOutput {
   stdout: string;
   stderr: string;
   exit_code: number;
+  timed_out?: boolean; // Killed after ExecutionLimits.timeoutSecs
+  truncated?: boolean; // Output beyond ExecutionLimits.maxOutputBytes was dropped
+}
+
+// Result of executePrivilegedCommand
+export interface PrivilegedCommandOutput extends CommandOutput {
+  command: string;
+  outcome: 'completed' | 'cancelled' | 'not_authorized' | 'timed_out' | 'unavailable';
+}
+
+// Optional bounds on a command (native mode); unset fields mean no limit
+export interface ExecutionLimits {
+  timeoutSecs?: number;
+  maxOutputBytes?: number;
+  nice?: number; // 0-19
+  cpuSecs?: number;
 }
 
 export interface CodeExecutionState {
@@ -142,6 +158,7 @@ export interface CodeExecutionState {
   output: CommandOutput | null;
   error: string | null;
   approved: boolean; // CRITICAL: Commands can ONLY execute when explicitly approved by user
+  privileged: boolean; // Runs as administrator; the system asks for the password itself
 }
 
 // System Info Types
@@ -153,5 +170,42 @@ export interface SystemInfo {
   package_manager: string | null;
 }
 
+// Readings from getSystemMetrics; rates are averaged since the previous call
+export interface SystemMetrics {
+  cpuPercent: number;              // All cores, 0-100
+  memoryTotalBytes: number;
+  memoryUsedBytes: number;
+  swapTotalBytes: number;
+  swapUsedBytes: number;
+  disks: {
+    mountPoint: string;
+    totalBytes: number;
+    availableBytes: number;
+    removable: boolean;
+  }[];
+  networkReceivedBytesPerSec: number;     // All interfaces but loopback
+  networkTransmittedBytesPerSec: number;
+  battery: { percent: number; discharging: boolean } | null;   // Linux only
+  temperatureCelsius: number | null;      // Hottest sensor
+}
+
+export interface LocaleInfo {
+  locale: string;              // Locale for dates and times, e.g. "en-GB"
+  uiLocale: string;            // Language of the overlay's own messages
+  timezone: string | null;     // IANA name, e.g. "Europe/Berlin"
+  utcOffsetMinutes: number;
+  hourCycle: 'h12' | 'h23';
+  firstDayOfWeek: number;      // ISO 8601: 1 = Monday ... 7 = Sunday
+}
+
+// Focused app and fullscreen windows, from the overlay's compositor integration (Hyprland, Sway)
+export interface DesktopContext {
+  compositor: 'hyprland' | 'sway';
+  workspace: string | null;
+  focusedApp: string | null;       // App ID or window class; null unless shared (config.toml)
+  focusedTitle: string | null;     // Only with share_window_title
+  fullscreenOutputs: string[];     // Connector names, e.g. "DP-1"
+}
+
 // Notification Types
 export type NotificationPreference = 'off' | 'unfocused' | 'chat_closed';